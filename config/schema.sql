@@ -12,7 +12,99 @@ CREATE TABLE IF NOT EXISTS users (
     approved_at TIMESTAMP,
     first_name TEXT,
     last_name TEXT,
-    reset_requested_at TIMESTAMP
+    reset_requested_at TIMESTAMP,
+    -- IANA zone name (e.g. `Australia/Sydney`) the SPA formats timestamps in.
+    -- NULL means use the browser's zone. Stored timestamps stay naive UTC.
+    timezone TEXT,
+    -- 'active' or 'lapsed', kept in sync by the gym's billing system (see
+    -- db::memberships). NULL means membership isn't tracked for this user.
+    membership_status TEXT,
+    membership_updated_at TIMESTAMP,
+    -- Profile picture, an image in `attachments`. NULL shows initials.
+    avatar_attachment_id INTEGER REFERENCES attachments (id) ON DELETE SET NULL
+);
+
+-- Free-form UI settings (sort order, theme, collapsed sections) as a JSON
+-- object. The server only checks it is an object under the size limit.
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    preferences TEXT NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Which emails a user wants. Unlike user_preferences the server acts on
+-- these, so they are typed columns. No row means the defaults.
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    email_on_assignment BOOLEAN NOT NULL DEFAULT 1,
+    email_on_coach_note BOOLEAN NOT NULL DEFAULT 1,
+    -- 'off', 'daily' or 'weekly'
+    digest_frequency TEXT NOT NULL DEFAULT 'off',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- In-app notifications (see db::notifications), shown whatever the user's
+-- email preferences. `kind` is 'assignment' or 'coach_note'; read_at is NULL
+-- until they open it.
+CREATE TABLE IF NOT EXISTS notifications (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    student_technique_id INTEGER REFERENCES student_techniques (id) ON DELETE CASCADE,
+    actor_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    read_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, read_at);
+
+-- Which students each coach looks after (see db::coach_students). A coach
+-- only sees the students with rows here; admins see everyone.
+CREATE TABLE IF NOT EXISTS coach_students (
+    coach_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    assigned_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    assigned_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (coach_id, student_id)
+);
+CREATE INDEX IF NOT EXISTS idx_coach_students_student ON coach_students(student_id);
+
+-- Class sessions and who turned up to them (see db::attendance).
+CREATE TABLE IF NOT EXISTS classes (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL DEFAULT '',
+    starts_at TIMESTAMP NOT NULL,
+    duration_minutes INTEGER NOT NULL DEFAULT 60,
+    coach_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_classes_starts_at ON classes(starts_at);
+
+CREATE TABLE IF NOT EXISTS attendance (
+    class_id INTEGER NOT NULL REFERENCES classes (id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    marked_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    marked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (class_id, student_id)
+);
+CREATE INDEX IF NOT EXISTS idx_attendance_student ON attendance(student_id);
+
+-- The gym's weekly timetable (see db::schedule). Times are wall-clock in
+-- `timezone`, so an 18:00 class stays at 18:00 across daylight saving.
+CREATE TABLE IF NOT EXISTS class_schedule (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    -- ISO weekday: 1 is Monday, 7 is Sunday.
+    weekday INTEGER NOT NULL,
+    start_time TIME NOT NULL,
+    duration_minutes INTEGER NOT NULL DEFAULT 60,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    coach_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    starts_on DATE NOT NULL,
+    -- Last day the class runs; NULL while it runs indefinitely.
+    ends_on DATE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
 CREATE TABLE IF NOT EXISTS techniques (
@@ -21,6 +113,16 @@ CREATE TABLE IF NOT EXISTS techniques (
     description TEXT,
     coach_id INTEGER,
     coach_name TEXT,
+    -- NULL for techniques created before this was recorded, and for ones
+    -- brought in from an archive.
+    created_at TIMESTAMP,
+    -- Soft delete (see db::delete_technique). A deleted technique leaves the
+    -- library, pickers and search but keeps its assignments and history, and
+    -- can be restored.
+    deleted_at TIMESTAMP,
+    -- Set on a variant (e.g. armbar from mount) to the base technique it
+    -- groups under. Variants are one level deep: a base is never a variant.
+    parent_technique_id INTEGER REFERENCES techniques (id) ON DELETE SET NULL,
     FOREIGN KEY (coach_id) REFERENCES users (id)
 );
 
@@ -40,13 +142,101 @@ CREATE TABLE IF NOT EXISTS student_techniques (
     last_student_update_at TIMESTAMP,
     last_student_update_by_id INTEGER,
     collection_id INTEGER,
+    -- Set when the student asks a coach to review the technique; cleared
+    -- when a coach next changes its status.
+    review_requested_at TIMESTAMP,
+    -- The coach who assigned it; NULL for rows older than the column.
+    assigned_by_id INTEGER,
+    -- Coach notes for coaches only: hidden from the student.
+    coach_notes_private BOOLEAN NOT NULL DEFAULT FALSE,
     FOREIGN KEY (technique_id) REFERENCES techniques (id),
     FOREIGN KEY (student_id) REFERENCES users (id),
     FOREIGN KEY (last_coach_update_by_id) REFERENCES users (id),
     FOREIGN KEY (last_student_update_by_id) REFERENCES users (id),
-    FOREIGN KEY (collection_id) REFERENCES collections (id)
+    FOREIGN KEY (collection_id) REFERENCES collections (id),
+    FOREIGN KEY (assigned_by_id) REFERENCES users (id)
+);
+
+-- The grading scale student_techniques.status is drawn from, lowest
+-- position first (see db::statuses). Seeded with red, amber and green on
+-- boot while empty. 'red' is where new assignments start, so it can be
+-- recoloured or moved but not deleted.
+CREATE TABLE IF NOT EXISTS statuses (
+    name TEXT PRIMARY KEY,
+    color TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0
 );
 
+-- Allowed status changes on student_techniques. With no rows any change is
+-- allowed; once a gym adds rows, a change must match one and the acting
+-- user's role must be at least min_role ('coach' or 'admin').
+CREATE TABLE IF NOT EXISTS status_transitions (
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    min_role TEXT NOT NULL DEFAULT 'coach',
+    PRIMARY KEY (from_status, to_status)
+);
+
+-- Earlier versions of student_techniques.student_notes and coach_notes (see
+-- db::note_revisions). Each row is the text an update replaced, so an
+-- accidental overwrite can be undone. field is 'student_notes' or
+-- 'coach_notes'.
+CREATE TABLE IF NOT EXISTS note_revisions (
+    id INTEGER PRIMARY KEY,
+    student_technique_id INTEGER NOT NULL REFERENCES student_techniques(id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    content TEXT NOT NULL,
+    replaced_at TIMESTAMP NOT NULL,
+    replaced_by_id INTEGER REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_note_revisions_st ON note_revisions(student_technique_id);
+
+-- What each save of a student_techniques row changed (see
+-- db::student_technique_revisions): one row per changed field, with its
+-- value before and after. note_revisions keeps replaced note text so it can
+-- be restored; this is the who-changed-what trail. field is 'status',
+-- 'student_notes' or 'coach_notes'.
+CREATE TABLE IF NOT EXISTS student_technique_revisions (
+    id INTEGER PRIMARY KEY,
+    student_technique_id INTEGER NOT NULL REFERENCES student_techniques(id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    old_value TEXT NOT NULL,
+    new_value TEXT NOT NULL,
+    changed_at TIMESTAMP NOT NULL,
+    changed_by_id INTEGER REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_st_revisions ON student_technique_revisions(student_technique_id);
+
+-- Reusable coach_notes text (see db::note_templates). owner_id is the coach
+-- who keeps it for themselves, or NULL for a template the whole gym shares.
+CREATE TABLE IF NOT EXISTS note_templates (
+    id INTEGER PRIMARY KEY,
+    owner_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_note_templates_owner ON note_templates(owner_id);
+
+-- A student's private reflections (see db::journal). Only user_id ever reads
+-- them; coaches and admins get no route to another user's entries.
+-- technique_id is optional, for entries about one technique rather than a
+-- day's training.
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    technique_id INTEGER REFERENCES techniques(id) ON DELETE SET NULL,
+    entry_date DATE NOT NULL,
+    body TEXT NOT NULL,
+    -- Private to the author unless they share it, when coaches with
+    -- view_student_journals can read it too.
+    shared BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_journal_entries_user ON journal_entries(user_id, entry_date);
+
 CREATE TABLE IF NOT EXISTS student_technique_views (
     student_technique_id INTEGER NOT NULL REFERENCES student_techniques(id) ON DELETE CASCADE,
     user_id              INTEGER NOT NULL REFERENCES users(id)              ON DELETE CASCADE,
@@ -55,12 +245,28 @@ CREATE TABLE IF NOT EXISTS student_technique_views (
 );
 CREATE INDEX IF NOT EXISTS idx_stv_user ON student_technique_views(user_id);
 
+-- Badges a user has earned (see `db::badges`). subject_id is the collection
+-- for curriculum_complete and 0 for badges earned once. Rows are never
+-- removed: a badge stays earned if the work behind it is later undone.
+CREATE TABLE IF NOT EXISTS user_badges (
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    badge TEXT NOT NULL,
+    subject_id INTEGER NOT NULL DEFAULT 0,
+    awarded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, badge, subject_id)
+);
+
+-- Curricula: an ordered list of techniques (collection_techniques.position)
+-- that can be assigned to a student in one go.
 CREATE TABLE IF NOT EXISTS collections (
     id INTEGER PRIMARY KEY,
     name TEXT NOT NULL,
     description TEXT,
     coach_id INTEGER REFERENCES users (id),
-    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    -- The belt the curriculum is for, by rank name like student_ranks.rank,
+    -- so it survives the ladder being redefined. NULL when not tied to one.
+    belt_level TEXT
 );
 
 CREATE TABLE IF NOT EXISTS collection_techniques (
@@ -70,6 +276,34 @@ CREATE TABLE IF NOT EXISTS collection_techniques (
     PRIMARY KEY (collection_id, technique_id)
 );
 
+-- A coach grading a student against a curriculum (see db::gradings). The
+-- curriculum's techniques are copied into grading_results when it opens, so
+-- later edits to the collection don't change a grading in progress or a
+-- closed one. outcome is 'passed', 'failed' or 'deferred' once closed.
+CREATE TABLE IF NOT EXISTS gradings (
+    id INTEGER PRIMARY KEY,
+    student_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    collection_id INTEGER REFERENCES collections (id) ON DELETE SET NULL,
+    collection_name TEXT NOT NULL,
+    coach_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    opened_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP,
+    outcome TEXT,
+    notes TEXT NOT NULL DEFAULT ''
+);
+CREATE INDEX IF NOT EXISTS idx_gradings_student ON gradings(student_id);
+
+-- passed is NULL until the technique has been graded.
+CREATE TABLE IF NOT EXISTS grading_results (
+    grading_id INTEGER NOT NULL REFERENCES gradings (id) ON DELETE CASCADE,
+    technique_id INTEGER NOT NULL REFERENCES techniques (id) ON DELETE CASCADE,
+    technique_name TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    passed BOOLEAN,
+    note TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (grading_id, technique_id)
+);
+
 CREATE TABLE IF NOT EXISTS invite_tokens (
     id INTEGER PRIMARY KEY,
     user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
@@ -79,6 +313,18 @@ CREATE TABLE IF NOT EXISTS invite_tokens (
     used_at TIMESTAMP
 );
 
+-- Audit trail of change-password attempts (see db::password_attempts).
+-- Failures since the last success throttle further attempts.
+CREATE TABLE IF NOT EXISTS password_change_attempts (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attempted_at TIMESTAMP NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    client_ip TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_password_change_attempts_user
+    ON password_change_attempts(user_id, attempted_at);
+
 CREATE TABLE IF NOT EXISTS user_sessions (
     id INTEGER PRIMARY KEY,
     user_id INTEGER NOT NULL,
@@ -88,10 +334,111 @@ CREATE TABLE IF NOT EXISTS user_sessions (
     FOREIGN KEY (user_id) REFERENCES users (id)
 );
 
+-- Personal access tokens, for scripts and the CLI (see auth::api_tokens).
+-- Only the SHA-256 of a token is kept; the token is shown once, when made.
+-- `scopes` is space-separated: 'read' allows GET and HEAD, 'write' the rest.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    -- The token's first characters, so its owner can tell tokens apart.
+    token_prefix TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- NULL never expires.
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens(user_id);
+
+-- Accounts at an OpenID Connect provider that sign in as a local user (see
+-- crate::oidc). `subject` is the provider's stable id for the account;
+-- `email` is what the provider last reported, for reference only.
+CREATE TABLE IF NOT EXISTS user_identities (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    email TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login_at TIMESTAMP,
+    UNIQUE (issuer, subject)
+);
+CREATE INDEX IF NOT EXISTS idx_user_identities_user ON user_identities(user_id);
+
+-- Responses to POSTs sent with an Idempotency-Key header (see
+-- idempotency::IdempotencyFairing), so a retry gets the first answer back
+-- instead of doing the work twice. `scope` is 'user:<id>'; signed-out
+-- requests aren't kept. request_hash is a SHA-256 of the request's content
+-- type and body. status is NULL while the first request is still running.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    request_hash TEXT NOT NULL DEFAULT '',
+    status INTEGER,
+    content_type TEXT,
+    body TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (scope, key)
+);
+
 CREATE TABLE IF NOT EXISTS tags (
     id INTEGER PRIMARY KEY,
-    name TEXT NOT NULL UNIQUE
+    name TEXT NOT NULL UNIQUE,
+    -- Archived tags stay on the techniques that carry them but are left out
+    -- of pickers (see db::set_tag_archived).
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Optional grouping such as 'Position' or 'Submission', and a #rrggbb
+    -- colour for the tag's chip. Both NULL when unset.
+    category TEXT,
+    color TEXT,
+    -- Nests tags, e.g. Closed Guard under Guard. Filtering by a tag takes in
+    -- its descendants; deleting a parent makes its children top-level.
+    parent_id INTEGER REFERENCES tags (id) ON DELETE SET NULL
+);
+-- Names keep the case they were typed in but are unique regardless of it
+-- (see validation::normalize_tag_name).
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tags_name_nocase ON tags(name COLLATE NOCASE);
+
+-- Other names a technique goes by: a gym's own term, the Japanese or
+-- Portuguese name (see db::technique_aliases). language is an optional code
+-- such as 'pt' or 'ja'.
+CREATE TABLE IF NOT EXISTS technique_aliases (
+    id INTEGER PRIMARY KEY,
+    technique_id INTEGER NOT NULL REFERENCES techniques (id) ON DELETE CASCADE,
+    alias TEXT NOT NULL,
+    language TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_technique_aliases_unique
+    ON technique_aliases(technique_id, alias COLLATE NOCASE);
+
+-- Techniques a student should reach green on before being assigned another
+-- (see db::prerequisites). Cycles are refused when a link is added.
+CREATE TABLE IF NOT EXISTS technique_prerequisites (
+    technique_id INTEGER NOT NULL REFERENCES techniques (id) ON DELETE CASCADE,
+    prerequisite_id INTEGER NOT NULL REFERENCES techniques (id) ON DELETE CASCADE,
+    PRIMARY KEY (technique_id, prerequisite_id)
+);
+CREATE INDEX IF NOT EXISTS idx_technique_prerequisites_prerequisite
+    ON technique_prerequisites(prerequisite_id);
+
+-- Demonstrations linked to a library technique (see db::technique_media):
+-- an outside URL or an uploaded attachment, exactly one of the two. kind is
+-- 'video' or 'image'.
+CREATE TABLE IF NOT EXISTS technique_media (
+    id INTEGER PRIMARY KEY,
+    technique_id INTEGER NOT NULL REFERENCES techniques (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    title TEXT NOT NULL DEFAULT '',
+    url TEXT,
+    attachment_id INTEGER REFERENCES attachments (id) ON DELETE CASCADE,
+    added_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
+CREATE INDEX IF NOT EXISTS idx_technique_media_technique ON technique_media(technique_id);
 
 CREATE TABLE IF NOT EXISTS technique_tags (
     technique_id INTEGER NOT NULL,
@@ -101,6 +448,43 @@ CREATE TABLE IF NOT EXISTS technique_tags (
     FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
 );
 
+-- Full-text index for technique search (see db::technique_search). rowid is
+-- the technique id; tags and aliases hold the names space-separated. Kept in
+-- sync by the db functions that write techniques, tags and aliases.
+CREATE VIRTUAL TABLE IF NOT EXISTS technique_search USING fts5(
+    name,
+    description,
+    tags,
+    aliases,
+    tokenize = 'porter unicode61'
+);
+
+-- The gym's belt ladder (see db::ranks), lowest position first. Each
+-- requirement asks for min_count techniques tagged tag_id at status or
+-- better.
+CREATE TABLE IF NOT EXISTS ranks (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    position INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS rank_requirements (
+    rank_id INTEGER NOT NULL REFERENCES ranks (id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    min_count INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'green',
+    PRIMARY KEY (rank_id, tag_id)
+);
+
+-- The rank a student currently holds, by name so it survives the ladder
+-- being redefined.
+CREATE TABLE IF NOT EXISTS student_ranks (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    rank TEXT NOT NULL,
+    awarded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    awarded_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL
+);
+
 CREATE TABLE IF NOT EXISTS attempts (
     id INTEGER PRIMARY KEY,
     student_technique_id INTEGER NOT NULL REFERENCES student_techniques (id) ON DELETE CASCADE,
@@ -203,6 +587,67 @@ CREATE TABLE IF NOT EXISTS video_privacy_acks (
     acked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Files kept in the attachment store (see `crate::attachments`), one row per
+-- stored object. The object is written before the row, so a failed upload
+-- can leave an object with no row; the attachment_cleanup job removes those.
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY,
+    storage_key TEXT NOT NULL UNIQUE,
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    bytes INTEGER NOT NULL,
+    uploaded_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Runtime toggles, keyed by `flags::Flag::key`. A missing row means the
+-- flag's compiled-in default applies.
+CREATE TABLE IF NOT EXISTS feature_flags (
+    key TEXT PRIMARY KEY,
+    enabled INTEGER NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_by_id INTEGER REFERENCES users (id) ON DELETE SET NULL
+);
+
+-- Long-running work started by a request (see
+-- scheduler::spawn_background_job). The request returns the id at once and
+-- the SPA polls GET /api/jobs/<id>. status is queued, running, succeeded or
+-- failed; result is the work's JSON output once it succeeds.
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    requested_by_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    status TEXT NOT NULL DEFAULT 'queued',
+    result TEXT,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+-- Last run of each background job, keyed by `scheduler::Job::name`.
+-- running_since is set for the length of a run and doubles as the lock that
+-- stops two runs of one job overlapping; a run that never finished (crash)
+-- stops holding it after `scheduler::STALE_RUN`.
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    name TEXT PRIMARY KEY,
+    running_since TIMESTAMP,
+    last_started_at TIMESTAMP,
+    last_finished_at TIMESTAMP,
+    last_status TEXT,
+    last_message TEXT
+);
+
+-- One row per run of the migrate binary that changed the schema: the
+-- SHA-256 of schema.sql it applied and the steps it took, one per line.
+-- Written only by the migrate binary; shown in GET /api/admin/system.
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    id INTEGER PRIMARY KEY,
+    applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    schema_sha256 TEXT NOT NULL,
+    steps TEXT NOT NULL
+);
+
 -- Litestream-owned bookkeeping tables. Declared here only so the migration
 -- engine recognises them as expected and doesn't try to drop them. Litestream
 -- creates and maintains the rows; the app never reads or writes them.
@@ -12,6 +12,9 @@
 //!
 //! The SQLite file is created on the fly if missing, so `just clean &&
 //! just migrate` works out of the box.
+//!
+//! A run that changes anything is recorded in `schema_migrations` when the
+//! schema declares that table.
 
 use std::path::Path;
 use std::process::ExitCode;
@@ -21,7 +24,8 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use migration_engine::migrations::{
     ChangesNeeded, MigrationReporter, NoopReporter, TerminalReporter, get_schema_changes,
-    migrate_database_declaratively_with_reporter, read_schema_file_to_string,
+    migrate_database_declaratively_with_reporter, planned_step_descriptions,
+    read_schema_file_to_string, schema_sha256,
 };
 use sqlx::SqlitePool;
 use sqlx::sqlite::SqliteConnectOptions;
@@ -141,10 +145,42 @@ async fn run() -> Result<()> {
         Arc::new(TerminalReporter::new())
     };
 
-    migrate_database_declaratively_with_reporter(pool.clone(), &schema, allow_destructive, reporter)
-        .await
-        .map_err(|e| anyhow::anyhow!("Migration failed: {:?}", e))?;
+    let steps = planned_step_descriptions(&changes);
+    let changed = migrate_database_declaratively_with_reporter(
+        pool.clone(),
+        &schema,
+        allow_destructive,
+        reporter,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Migration failed: {:?}", e))?;
+
+    if changed {
+        record_migration(&pool, &schema, &steps)
+            .await
+            .context("Migration applied but could not be recorded in schema_migrations")?;
+    }
+
+    Ok(())
+}
+
+/// Appends a `schema_migrations` row for an applied run. The engine is not
+/// tied to this project's schema, so a schema without the table skips this.
+async fn record_migration(pool: &SqlitePool, schema: &str, steps: &[String]) -> Result<()> {
+    let declared: Option<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if declared.is_none() {
+        return Ok(());
+    }
 
+    sqlx::query("INSERT INTO schema_migrations (schema_sha256, steps) VALUES (?, ?)")
+        .bind(schema_sha256(schema))
+        .bind(steps.join("\n"))
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
@@ -1,4 +1,5 @@
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use sqlx::{Connection, Pool, Row, Sqlite, SqlitePool};
 use std::{
     collections::{HashMap, HashSet},
@@ -325,6 +326,26 @@ impl DeclarativeMigrator {
         self.reporter
             .step_started(&modified_table_description(table_name));
 
+        // A virtual table's contents are derived (an FTS index), so it is
+        // rebuilt empty rather than copied; the app refills it.
+        if is_virtual_table(&target_table.sql) {
+            let drop_sql = format!("DROP TABLE {}", table_name);
+            self.execute_schema_change_silent(
+                &format!("Drop old virtual table {}", table_name),
+                &drop_sql,
+                &mut **tx,
+            )
+            .await?;
+            self.execute_schema_change_silent(
+                &format!("Recreate virtual table {}", table_name),
+                &target_table.sql,
+                &mut **tx,
+            )
+            .await?;
+            self.reporter.step_finished();
+            return Ok(());
+        }
+
         // Create temporary table with new schema
         let temp_name = format!("{}_migration_new", table_name);
         let temp_sql = target_table.sql.replace(
@@ -499,11 +520,18 @@ impl DeclarativeMigrator {
             "SELECT name, sql FROM sqlite_master WHERE type = 'table' AND name != 'sqlite_sequence'"
         ).fetch_all(executor).await?;
 
+        let rows: Vec<(String, String)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+        let virtual_tables: Vec<&str> = rows
+            .iter()
+            .filter(|(_, sql)| is_virtual_table(sql))
+            .map(|(name, _)| name.as_str())
+            .collect();
+
         let mut tables = HashMap::new();
-        for row in rows {
-            let name: String = row.get(0);
-            let sql: String = row.get(1);
-            tables.insert(name.clone(), TableInfo { sql });
+        for (name, sql) in &rows {
+            if !is_shadow_table(name, &virtual_tables) {
+                tables.insert(name.clone(), TableInfo { sql: sql.clone() });
+            }
         }
         Ok(tables)
     }
@@ -667,6 +695,25 @@ impl DeclarativeMigrator {
     }
 }
 
+/// Tables FTS5 keeps a virtual table's index in, named `<table>_<suffix>`.
+const SHADOW_TABLE_SUFFIXES: &[&str] = &["data", "idx", "content", "docsize", "config"];
+
+fn is_virtual_table(sql: &str) -> bool {
+    normalize_sql(sql)
+        .to_ascii_uppercase()
+        .starts_with("CREATE VIRTUAL TABLE")
+}
+
+/// Shadow tables come and go with their virtual table, so the migrator
+/// neither creates nor drops them itself.
+fn is_shadow_table(name: &str, virtual_tables: &[&str]) -> bool {
+    virtual_tables.iter().any(|table| {
+        name.strip_prefix(table)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .is_some_and(|suffix| SHADOW_TABLE_SUFFIXES.contains(&suffix))
+    })
+}
+
 #[instrument(skip_all)]
 pub fn normalize_sql(sql: &str) -> String {
     // Remove comments
@@ -818,6 +865,12 @@ impl ChangesNeeded {
     }
 }
 
+/// Hex SHA-256 of a schema file's text. Recorded with each applied migration
+/// so a database can be matched to the schema that last changed it.
+pub fn schema_sha256(schema: &str) -> String {
+    format!("{:x}", Sha256::digest(schema.as_bytes()))
+}
+
 pub fn read_schema_file_to_string(path: &Path) -> Result<String, MigrationError> {
     let schema = fs::read_to_string(path)?;
     Ok(schema)
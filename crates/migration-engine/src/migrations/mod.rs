@@ -1,4 +1,6 @@
 pub mod main;
+#[cfg(any(test, feature = "test-support"))]
+pub mod proptest_support;
 pub mod reporter;
 pub mod terminal_reporter;
 pub mod test;
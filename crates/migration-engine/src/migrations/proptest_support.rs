@@ -0,0 +1,502 @@
+//! Property-testing harness for the declarative migrator. `arb_schema`
+//! generates random valid schemas, `arb_edits` random edit sequences to apply
+//! to them, and `check_migration_invariants` migrates a populated database
+//! from one to the other and checks that:
+//!
+//! - the migrated schema matches a pristine database built from the target,
+//! - a second run finds nothing left to change, and
+//! - every row keeps its values in the columns both schemas share.
+//!
+//! Edits only produce changes the migrator is expected to handle with data in
+//! place: added `NOT NULL` columns always carry a default, column types never
+//! change, and indices are non-unique. Foreign keys are not generated.
+//!
+//! Built for this crate's tests and, behind the `test-support` feature, for
+//! other crates that want to drive the migrator with their own properties.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::Index;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+
+use crate::migrations::{get_schema_changes, migrate_database_declaratively, normalize_sql};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Integer,
+    Text,
+    Real,
+}
+
+impl ColumnType {
+    fn sql(self) -> &'static str {
+        match self {
+            ColumnType::Integer => "INTEGER",
+            ColumnType::Text => "TEXT",
+            ColumnType::Real => "REAL",
+        }
+    }
+
+    /// A literal of this type derived from `n`, used for defaults and rows.
+    fn literal(self, n: u64) -> String {
+        match self {
+            ColumnType::Integer => n.to_string(),
+            ColumnType::Text => format!("'v{}'", n),
+            ColumnType::Real => format!("{}.5", n),
+        }
+    }
+}
+
+/// A column before it has a name. `not_null` columns always have a default,
+/// so they can be added to tables that already hold rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSpec {
+    pub ty: ColumnType,
+    pub not_null: bool,
+    pub default: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnModel {
+    pub name: String,
+    pub spec: ColumnSpec,
+}
+
+impl ColumnModel {
+    fn sql(&self) -> String {
+        let mut sql = format!("{} {}", self.name, self.spec.ty.sql());
+        if self.spec.not_null {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(default) = self.spec.default {
+            sql.push_str(&format!(" DEFAULT {}", self.spec.ty.literal(default.into())));
+        }
+        sql
+    }
+}
+
+/// Every table has an `id INTEGER PRIMARY KEY` ahead of `columns`; rows are
+/// matched on it when checking data survived.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableModel {
+    pub name: String,
+    pub columns: Vec<ColumnModel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexModel {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ColumnChange {
+    SetDefault(u8),
+    /// Ignored for `NOT NULL` columns, which must keep a default.
+    DropDefault,
+    DropNotNull,
+}
+
+/// One edit to a `SchemaModel`. Targets are `Index`es rather than names so
+/// any generated sequence applies to any schema; an edit whose target does
+/// not exist (a column edit on a schema with no tables, say) is a no-op.
+#[derive(Debug, Clone)]
+pub enum SchemaEdit {
+    AddTable(Vec<ColumnSpec>),
+    DropTable(Index),
+    AddColumn(Index, ColumnSpec),
+    DropColumn(Index, Index),
+    ModifyColumn(Index, Index, ColumnChange),
+    AddIndex(Index, Vec<Index>),
+    DropIndex(Index),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaModel {
+    pub tables: Vec<TableModel>,
+    pub indices: Vec<IndexModel>,
+    /// Suffix for the next generated name. Names are never reused, so a
+    /// dropped table and a later added one cannot be confused.
+    next_name: usize,
+}
+
+impl SchemaModel {
+    pub fn to_sql(&self) -> String {
+        let mut sql = String::new();
+        for table in &self.tables {
+            let mut columns = vec!["id INTEGER PRIMARY KEY".to_string()];
+            columns.extend(table.columns.iter().map(ColumnModel::sql));
+            sql.push_str(&format!(
+                "CREATE TABLE {} (\n    {}\n);\n",
+                table.name,
+                columns.join(",\n    ")
+            ));
+        }
+        for index in &self.indices {
+            sql.push_str(&format!(
+                "CREATE INDEX {} ON {} ({});\n",
+                index.name,
+                index.table,
+                index.columns.join(", ")
+            ));
+        }
+        sql
+    }
+
+    pub fn with_edits(&self, edits: &[SchemaEdit]) -> Self {
+        let mut schema = self.clone();
+        for edit in edits {
+            schema.apply(edit);
+        }
+        schema
+    }
+
+    pub fn apply(&mut self, edit: &SchemaEdit) {
+        match edit {
+            SchemaEdit::AddTable(specs) => {
+                let name = self.fresh_name("t");
+                let columns = specs.iter().map(|spec| self.fresh_column(spec)).collect();
+                self.tables.push(TableModel { name, columns });
+            }
+            SchemaEdit::DropTable(table) => {
+                if self.tables.is_empty() {
+                    return;
+                }
+                let removed = self.tables.remove(table.index(self.tables.len()));
+                self.indices.retain(|index| index.table != removed.name);
+            }
+            SchemaEdit::AddColumn(table, spec) => {
+                let column = self.fresh_column(spec);
+                if let Some(table) = self.pick_table(table) {
+                    table.columns.push(column);
+                }
+            }
+            SchemaEdit::DropColumn(table, column) => {
+                let Some(table) = self.pick_table(table) else {
+                    return;
+                };
+                if table.columns.is_empty() {
+                    return;
+                }
+                let removed = table.columns.remove(column.index(table.columns.len()));
+                let table_name = table.name.clone();
+                self.indices.retain(|index| {
+                    index.table != table_name || !index.columns.contains(&removed.name)
+                });
+            }
+            SchemaEdit::ModifyColumn(table, column, change) => {
+                let Some(table) = self.pick_table(table) else {
+                    return;
+                };
+                if table.columns.is_empty() {
+                    return;
+                }
+                let len = table.columns.len();
+                let spec = &mut table.columns[column.index(len)].spec;
+                match change {
+                    ColumnChange::SetDefault(default) => spec.default = Some(*default),
+                    ColumnChange::DropDefault if !spec.not_null => spec.default = None,
+                    ColumnChange::DropDefault => {}
+                    ColumnChange::DropNotNull => spec.not_null = false,
+                }
+            }
+            SchemaEdit::AddIndex(table, picks) => {
+                let name = self.fresh_name("idx");
+                let Some(table) = self.pick_table(table) else {
+                    return;
+                };
+                let available: Vec<String> = std::iter::once("id".to_string())
+                    .chain(table.columns.iter().map(|c| c.name.clone()))
+                    .collect();
+                let mut columns: Vec<String> = Vec::new();
+                for pick in picks {
+                    let column = &available[pick.index(available.len())];
+                    if !columns.contains(column) {
+                        columns.push(column.clone());
+                    }
+                }
+                let table = table.name.clone();
+                self.indices.push(IndexModel { name, table, columns });
+            }
+            SchemaEdit::DropIndex(index) => {
+                if !self.indices.is_empty() {
+                    self.indices.remove(index.index(self.indices.len()));
+                }
+            }
+        }
+    }
+
+    fn pick_table(&mut self, table: &Index) -> Option<&mut TableModel> {
+        if self.tables.is_empty() {
+            return None;
+        }
+        let len = self.tables.len();
+        self.tables.get_mut(table.index(len))
+    }
+
+    fn fresh_name(&mut self, prefix: &str) -> String {
+        self.next_name += 1;
+        format!("{}{}", prefix, self.next_name)
+    }
+
+    fn fresh_column(&mut self, spec: &ColumnSpec) -> ColumnModel {
+        ColumnModel {
+            name: self.fresh_name("c"),
+            spec: spec.clone(),
+        }
+    }
+}
+
+pub fn arb_column_spec() -> impl Strategy<Value = ColumnSpec> {
+    let ty = prop_oneof![
+        Just(ColumnType::Integer),
+        Just(ColumnType::Text),
+        Just(ColumnType::Real),
+    ];
+    (ty, any::<bool>(), any::<Option<u8>>()).prop_map(|(ty, not_null, default)| ColumnSpec {
+        ty,
+        not_null,
+        default: if not_null { Some(default.unwrap_or(0)) } else { default },
+    })
+}
+
+pub fn arb_column_change() -> impl Strategy<Value = ColumnChange> {
+    prop_oneof![
+        any::<u8>().prop_map(ColumnChange::SetDefault),
+        Just(ColumnChange::DropDefault),
+        Just(ColumnChange::DropNotNull),
+    ]
+}
+
+pub fn arb_edit() -> impl Strategy<Value = SchemaEdit> {
+    prop_oneof![
+        vec(arb_column_spec(), 0..4).prop_map(SchemaEdit::AddTable),
+        any::<Index>().prop_map(SchemaEdit::DropTable),
+        (any::<Index>(), arb_column_spec()).prop_map(|(t, spec)| SchemaEdit::AddColumn(t, spec)),
+        (any::<Index>(), any::<Index>()).prop_map(|(t, c)| SchemaEdit::DropColumn(t, c)),
+        (any::<Index>(), any::<Index>(), arb_column_change())
+            .prop_map(|(t, c, change)| SchemaEdit::ModifyColumn(t, c, change)),
+        (any::<Index>(), vec(any::<Index>(), 1..3)).prop_map(|(t, c)| SchemaEdit::AddIndex(t, c)),
+        any::<Index>().prop_map(SchemaEdit::DropIndex),
+    ]
+}
+
+pub fn arb_edits(max_len: usize) -> impl Strategy<Value = Vec<SchemaEdit>> {
+    vec(arb_edit(), 0..=max_len)
+}
+
+/// One to three tables, each with up to three columns besides `id`, and up
+/// to three indices over them.
+pub fn arb_schema() -> impl Strategy<Value = SchemaModel> {
+    let tables = vec(vec(arb_column_spec(), 0..4), 1..4);
+    let indices = vec((any::<Index>(), vec(any::<Index>(), 1..3)), 0..4);
+    (tables, indices).prop_map(|(tables, indices)| {
+        let tables = tables.into_iter().map(SchemaEdit::AddTable);
+        let indices = indices.into_iter().map(|(t, c)| SchemaEdit::AddIndex(t, c));
+        let edits: Vec<SchemaEdit> = tables.chain(indices).collect();
+        SchemaModel::default().with_edits(&edits)
+    })
+}
+
+/// Each table's rows as `quote()`d values per column, keyed by `id`.
+type TableData = BTreeMap<i64, BTreeMap<String, String>>;
+
+/// Migrates a database built from `start`, with `rows_per_table` rows in each
+/// table, to `target` (deletions allowed) and checks the invariants listed in
+/// the module docs. The error describes the first one that failed.
+pub async fn check_migration_invariants(
+    start: &SchemaModel,
+    target: &SchemaModel,
+    rows_per_table: usize,
+) -> Result<(), String> {
+    let db = ScratchDb::new();
+    let pool = db.connect().await?;
+    sqlx::raw_sql(&start.to_sql())
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("start schema did not load: {}", e))?;
+    seed_rows(&pool, start, rows_per_table).await?;
+    let before = read_data(&pool, start).await?;
+
+    let target_sql = target.to_sql();
+    migrate_database_declaratively(pool.clone(), &target_sql, true)
+        .await
+        .map_err(|e| format!("migration failed: {}", e))?;
+
+    let pristine = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::raw_sql(&target_sql)
+        .execute(&pristine)
+        .await
+        .map_err(|e| format!("target schema did not load: {}", e))?;
+
+    let migrated_schema = read_schema(&pool).await?;
+    let pristine_schema = read_schema(&pristine).await?;
+    if migrated_schema != pristine_schema {
+        return Err(format!(
+            "migrated schema differs from pristine\nmigrated: {:#?}\npristine: {:#?}",
+            migrated_schema, pristine_schema
+        ));
+    }
+
+    let rerun = get_schema_changes(pool.clone(), &target_sql)
+        .await
+        .map_err(|e| format!("re-analysis failed: {}", e))?;
+    if rerun.has_any_changes() {
+        return Err(format!("second run still finds changes: {:?}", rerun));
+    }
+
+    let after = read_data(&pool, target).await?;
+    for (table, rows_before) in &before {
+        let Some(rows_after) = after.get(table) else {
+            continue;
+        };
+        if rows_before.len() != rows_after.len() {
+            return Err(format!(
+                "{} had {} rows, now has {}",
+                table,
+                rows_before.len(),
+                rows_after.len()
+            ));
+        }
+        for (id, values_before) in rows_before {
+            let values_after = rows_after
+                .get(id)
+                .ok_or_else(|| format!("{} lost row {}", table, id))?;
+            for (column, value) in values_before {
+                if let Some(new_value) = values_after.get(column)
+                    && new_value != value
+                {
+                    return Err(format!(
+                        "{}.{} in row {} changed from {} to {}",
+                        table, column, id, value, new_value
+                    ));
+                }
+            }
+        }
+    }
+
+    pool.close().await;
+    Ok(())
+}
+
+async fn seed_rows(pool: &SqlitePool, schema: &SchemaModel, rows: usize) -> Result<(), String> {
+    for table in &schema.tables {
+        for row in 1..=rows as u64 {
+            let mut names = vec!["id".to_string()];
+            let mut values = vec![row.to_string()];
+            for (i, column) in table.columns.iter().enumerate() {
+                names.push(column.name.clone());
+                // Leave some nullable cells empty so NULLs get copied too.
+                if !column.spec.not_null && (row + i as u64) % 3 == 0 {
+                    values.push("NULL".to_string());
+                } else {
+                    values.push(column.spec.ty.literal(row * 10 + i as u64));
+                }
+            }
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table.name,
+                names.join(", "),
+                values.join(", ")
+            );
+            sqlx::query(&sql)
+                .execute(pool)
+                .await
+                .map_err(|e| format!("seeding {} failed: {}", table.name, e))?;
+        }
+    }
+    Ok(())
+}
+
+async fn read_data(
+    pool: &SqlitePool,
+    schema: &SchemaModel,
+) -> Result<BTreeMap<String, TableData>, String> {
+    let mut data = BTreeMap::new();
+    for table in &schema.tables {
+        let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        let selects: Vec<String> = names.iter().map(|n| format!("quote({})", n)).collect();
+        let sql = format!(
+            "SELECT id{} FROM {}",
+            selects.iter().map(|s| format!(", {}", s)).collect::<String>(),
+            table.name
+        );
+        let rows = sqlx::query(&sql)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("reading {} failed: {}", table.name, e))?;
+
+        let mut table_data = TableData::new();
+        for row in rows {
+            let id: i64 = row.get(0);
+            let values = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.to_string(), row.get::<String, _>(i + 1)))
+                .collect();
+            table_data.insert(id, values);
+        }
+        data.insert(table.name.clone(), table_data);
+    }
+    Ok(data)
+}
+
+/// `(type, name, normalized sql)` for every table and explicit index, the
+/// same comparison the migrator makes when deciding what changed.
+async fn read_schema(pool: &SqlitePool) -> Result<BTreeSet<(String, String, String)>, String> {
+    let rows = sqlx::query(
+        "SELECT type, name, sql FROM sqlite_master
+         WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), normalize_sql(&row.get::<String, _>(2))))
+        .collect())
+}
+
+/// A throwaway on-disk database. In-memory pools hand each connection its
+/// own empty database, and the migrator uses more than one connection.
+struct ScratchDb {
+    path: PathBuf,
+}
+
+impl ScratchDb {
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "migrator-proptest-{}-{}.db",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self { path }
+    }
+
+    async fn connect(&self) -> Result<SqlitePool, String> {
+        let options = SqliteConnectOptions::new()
+            .filename(&self.path)
+            .create_if_missing(true);
+        SqlitePool::connect_with(options)
+            .await
+            .map_err(|e| format!("cannot open {}: {}", self.path.display(), e))
+    }
+}
+
+impl Drop for ScratchDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -609,4 +609,64 @@ mod tests {
             "No FK violations should remain after migration"
         );
     }
+
+    #[tokio::test]
+    async fn test_virtual_table_is_rebuilt_and_its_shadow_tables_ignored() {
+        let pool = create_test_db().await;
+        let schema = r#"
+            CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT);
+            CREATE VIRTUAL TABLE docs_search USING fts5(body);
+        "#;
+
+        assert!(migrate_database_declaratively(pool.clone(), schema, false).await.unwrap());
+        assert!(
+            !migrate_database_declaratively(pool.clone(), schema, false).await.unwrap(),
+            "FTS5 shadow tables should not read as unexpected tables"
+        );
+
+        sqlx::query("INSERT INTO docs_search (body) VALUES ('half guard sweep')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let changed = r#"
+            CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT);
+            CREATE VIRTUAL TABLE docs_search USING fts5(title, body);
+        "#;
+        assert!(migrate_database_declaratively(pool.clone(), changed, false).await.unwrap());
+        let rows = sqlx::query("SELECT title FROM docs_search")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert!(rows.is_empty(), "the index is rebuilt empty");
+        assert!(!migrate_database_declaratively(pool.clone(), changed, false).await.unwrap());
+    }
+
+    mod properties {
+        use proptest::prelude::*;
+
+        use crate::migrations::proptest_support::{
+            arb_edits, arb_schema, check_migration_invariants,
+        };
+
+        proptest! {
+            // Each case builds and migrates two databases, so keep the count
+            // modest; raise PROPTEST_CASES locally when changing the migrator.
+            #![proptest_config(ProptestConfig::with_cases(32))]
+
+            #[test]
+            fn migrating_random_edits_matches_pristine_and_keeps_data(
+                start in arb_schema(),
+                edits in arb_edits(6),
+            ) {
+                let target = start.with_edits(&edits);
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                runtime
+                    .block_on(check_migration_invariants(&start, &target, 3))
+                    .map_err(TestCaseError::fail)?;
+            }
+        }
+    }
 }
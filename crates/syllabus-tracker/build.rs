@@ -0,0 +1,45 @@
+//! Compiles build info into the binary for `GET /api/version`: the git
+//! commit as `GIT_SHA` and the build time as `BUILD_UNIX_TIME`.
+//!
+//! Docker builds have no `.git` (see `.dockerignore`), so CI passes the
+//! commit in as a `GIT_SHA` build arg; local builds ask git.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // A new commit or checkout moves HEAD or the branch it points at.
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/refs/heads");
+
+    let commit = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.trim().is_empty())
+        .or_else(git_head);
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_SHA={}", commit.trim());
+    }
+
+    // Reproducible builds pin the timestamp.
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=BUILD_UNIX_TIME={}", built_at);
+}
+
+fn git_head() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8(output.stdout).ok()
+}
@@ -0,0 +1,134 @@
+//! Live student activity for coach dashboards. Handlers publish to the
+//! `ActivityFeed` in managed state as students update their techniques, and
+//! `GET /api/coach/live` relays it over a WebSocket to each coach, limited
+//! to the students they can see.
+//!
+//! Events are hints to refetch rather than a record: a coach whose socket
+//! falls behind skips what it missed. A handler holding a `Tx` publishes
+//! with `publish_on_commit`, so coaches never refetch before the writes are
+//! visible or hear about a request that was rolled back.
+
+use chrono::{DateTime, Utc};
+use rocket::State;
+use rocket::futures::{SinkExt, StreamExt};
+use rocket::http::Status;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::{self, Receiver, Sender, error::RecvError};
+use rocket_ws::{Channel, Message, WebSocket};
+use sqlx::{Pool, Sqlite};
+use tracing::{info, warn};
+
+use crate::api::{ApiResult, visible_students};
+use crate::auth::{Permission, User};
+use crate::ids::{StudentTechniqueId, UserId};
+use crate::transaction::Tx;
+
+/// How many events a slow socket can fall behind by before it skips some.
+const FEED_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    /// The student changed their notes or status.
+    TechniqueUpdated,
+    ReviewRequested,
+    AttemptLogged,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub kind: ActivityKind,
+    pub student_id: UserId,
+    pub student_technique_id: StudentTechniqueId,
+    pub at: DateTime<Utc>,
+}
+
+pub struct ActivityFeed(Sender<ActivityEvent>);
+
+impl Default for ActivityFeed {
+    fn default() -> Self {
+        Self(broadcast::channel(FEED_CAPACITY).0)
+    }
+}
+
+impl ActivityFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends to every open socket. With none open the event is dropped.
+    pub fn publish(
+        &self,
+        kind: ActivityKind,
+        student_id: UserId,
+        student_technique_id: StudentTechniqueId,
+    ) {
+        let event = ActivityEvent { kind, student_id, student_technique_id, at: Utc::now() };
+        let _ = self.0.send(event);
+    }
+
+    /// `publish` once `tx` commits.
+    pub fn publish_on_commit(
+        &self,
+        tx: &Tx,
+        kind: ActivityKind,
+        student_id: UserId,
+        student_technique_id: StudentTechniqueId,
+    ) {
+        let sender = self.0.clone();
+        tx.after_commit(move || {
+            let feed = ActivityFeed(sender);
+            feed.publish(kind, student_id, student_technique_id);
+        });
+    }
+
+    pub fn subscribe(&self) -> Receiver<ActivityEvent> {
+        self.0.subscribe()
+    }
+}
+
+/// Each event as a JSON text message. Which students the coach sees is
+/// settled when the socket opens; a change to their students applies from
+/// the next connection. A plain request without the upgrade gets a 426.
+#[get("/coach/live")]
+pub async fn api_coach_live(
+    user: User,
+    ws: Option<WebSocket>,
+    feed: &State<ActivityFeed>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Channel<'static>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let ws = ws.ok_or(Status::UpgradeRequired)?;
+    let scope = visible_students(db.inner(), &user).await?;
+    let mut events = feed.subscribe();
+    info!(coach_id = %user.id, "Live feed opened");
+
+    Ok(ws.channel(move |mut stream| {
+        Box::pin(async move {
+            loop {
+                select! {
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if scope.as_ref().is_some_and(|ids| !ids.contains(&event.student_id)) {
+                                continue;
+                            }
+                            let Ok(json) = serde_json::to_string(&event) else {
+                                continue;
+                            };
+                            stream.send(Message::text(json)).await?;
+                        }
+                        Err(RecvError::Lagged(skipped)) => warn!(skipped, "Live feed fell behind"),
+                        Err(RecvError::Closed) => break,
+                    },
+                    // Nothing is read from coaches; this only notices them leave.
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                        Some(Ok(_)) => {}
+                    },
+                }
+            }
+            Ok(())
+        })
+    }))
+}
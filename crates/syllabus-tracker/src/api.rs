@@ -1,47 +1,133 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::HashSet;
 
 use rocket::FromForm;
 use rocket::Request;
 use rocket::State;
 use rocket::http::CookieJar;
+use rocket::http::Header;
 use rocket::http::Status;
+use rocket::http::uri::Origin;
 use rocket::response::Redirect;
 use rocket::response::Responder;
 use rocket::response::status::Custom;
-use rocket::serde::{Deserialize, Serialize, json::Json};
-use sqlx::{Pool, Sqlite};
-use tracing::warn;
+use rocket::response::stream::ByteStream;
+use rocket::serde::json::{Error as JsonError, Json};
+use rocket::serde::{Deserialize, Serialize};
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection, SqliteExecutor};
+use tracing::{error, info, warn};
 use validator::Validate;
-use validator::ValidationErrors;
-
-use crate::auth::UserSession;
-use crate::auth::{Permission, User};
+use validator::{ValidateArgs, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::activity::{ActivityFeed, ActivityKind};
+use crate::attachments::avatar_url;
+use crate::attachments::storage::content_disposition;
+use crate::auth::{UserSession, generate_api_token};
+use crate::auth::{BillingWebhook, Permission, Role, SessionUser, TokenScope, User};
+use crate::config::{LiveConfig, ReloadReport};
 use crate::db::{
-    add_tag_to_technique, add_techniques_to_collection, add_techniques_to_student, approve_user,
-    assign_collection_to_student, attempt_buckets_for_student, attempt_summary_for_student,
+    add_prerequisite, add_tag_to_technique, add_technique_alias, add_techniques_to_collection,
+    add_techniques_to_student, approve_user,
+    assign_collection_to_student, assign_group_to_student, attempt_buckets_for_student,
+    attempt_summary_for_student,
+    award_badges, bulk_update_status, begin_password_attempt, get_matching_statuses,
+    mark_password_attempt_succeeded,
     attempt_weekly_buckets_for_technique, authenticate_user, claim_invite, count_techniques,
-    create_and_assign_technique, create_attempt, create_collection, create_invite_token,
+    create_and_assign_technique, create_attempt, create_techniques, create_collection,
+    create_invite_token,
+    create_note_template, delete_note_template, update_note_template, create_journal_entry,
+    create_api_token, get_api_tokens, revoke_api_token,
+    delete_journal_entry, get_journal_entries, get_journal_entry, update_journal_entry,
     create_self_registered_user, create_tag, create_technique_in_collection, create_user,
     create_user_session, create_user_stub, delete_attempt, delete_collection, delete_tag,
-    find_user_by_username, find_valid_invite_token, get_all_collections, get_all_tags,
-    get_all_users, get_collection, get_student_technique, get_student_techniques,
-    get_students_by_recent_updates, get_students_with_collection, get_tags_for_technique,
-    get_unassigned_techniques, get_user, invalidate_session, list_attempts,
-    list_recent_attempts_for_student, mark_student_technique_seen, remove_tag_from_technique,
-    remove_technique_from_collection, request_password_reset, reset_user_claim, set_user_archived,
+    get_attempt_student_id,
+    delete_technique, restore_technique, add_technique_media, get_technique_media,
+    remove_technique_media, MediaSource, get_coach_student_ids, get_coach_student_scope,
+    replace_coach_students, create_class, get_class, list_classes, set_class_attendance,
+    get_student_attendance, AttendanceRecord, ClassSession, create_schedule_slot,
+    delete_schedule_slot, get_schedule, get_schedule_slot, update_schedule_slot, ScheduleSlot,
+    ScheduleSlotInput, close_grading, get_grading, list_gradings, open_grading,
+    record_grading_result, Grading, GradingOutcome, GradingSummary,
+    find_similar_techniques, find_user_by_username, find_valid_invite_token, get_all_collections,
+    get_all_tags, get_all_users, get_collection, get_student_technique, get_student_technique_in,
+    get_student_techniques,
+    get_status_transitions, get_statuses, get_students_by_recent_updates,
+    get_students_with_collection,
+    get_background_job, get_coach_report, get_curriculum_progress, get_note_revision,
+    get_note_revisions, get_student_technique_revisions,
+    get_note_template, get_note_templates, get_notification_preferences, get_notifications,
+    count_unread_notifications, mark_notification_read,
+    get_prerequisites, get_public_syllabus, get_rank_eligibility, get_ranks,
+    get_student_progress, get_unmet_prerequisites,
+    get_student_techniques_for_export, get_tag_progress,
+    get_tags_for_technique, get_tag_by_name, get_tag_subtree_ids, get_tag_tree,
+    get_technique_aliases, get_unassigned_techniques, get_user, get_user_badges,
+    get_user_preferences, import_memberships, import_spreadsheet,
+    invalidate_session, list_attempts, list_recent_attempts_for_student,
+    mark_student_technique_seen, parse_membership_csv, parse_spreadsheet, remove_tag_from_technique,
+    remove_prerequisite, remove_technique_alias, remove_technique_from_collection,
+    remove_techniques_from_student,
+    rename_tag, reorder_collection_techniques, replace_ranks,
+    replace_status_transitions, set_student_rank, set_tag_archived, set_tag_parent,
+    set_technique_parent, set_coach_notes_private,
+    count_status_uses,
+    create_status, delete_status, reorder_statuses, update_status_color, STARTING_STATUS,
+    request_password_reset, request_review, reset_user_claim, set_feature_flag, set_user_archived,
+    set_membership_status, set_notification_preferences, set_user_preferences,
     set_user_graduated, update_attempt_note, update_attempt_timestamp, update_collection,
-    update_student_notes, update_student_technique, update_technique, update_user_display_name,
-    update_user_password, update_user_role, update_username, AttemptSuggestion, Collection,
+    update_student_notes, update_student_technique, update_tag_style, update_technique,
+    update_user_display_name, update_user_password, update_user_role, update_user_timezone,
+    update_username,
+    ApiToken, AttemptSuggestion, BackgroundJob, Collection, GroupAssignment, JournalEntry,
+    JournalEntryInput,
+    MemberRef,
+    MembershipImportReport, MembershipOutcome, MembershipStatus, NoteField, NoteRevision,
+    NewTechnique, NoteTemplate, Notification, NotificationPreferences, Prerequisite, Rank,
+    RankDefinition, RankEligibility,
+    SheetColumns,
+    SheetImportReport, StatusFilter, StatusLevel, StatusTransition, StudentTechniqueExportRow,
+    StudentTechniqueField, StudentTechniqueFilter, StudentTechniqueRevision, TagNode,
+    TechniqueGroup, UserBadge,
+    UserListFilter, UserSort,
 };
 use crate::error::AppError;
+use crate::etag::ETagged;
+use crate::flags::{Flag, Flags};
+use crate::i18n::Locale;
+use crate::ical::schedule_calendar;
+use crate::ids::{StudentTechniqueId, TagId, TechniqueId, UserId};
+use crate::models::CoachActivity;
+use crate::models::GroupProgress;
+use crate::models::PublicSyllabus;
+use crate::models::StudentProgress;
+use crate::models::StudentTechnique;
 use crate::models::Tag;
 use crate::models::Technique;
-use crate::validation::ToValidationResponse;
-use crate::validation::ValidationResponse;
+use crate::models::TechniqueAlias;
+use crate::models::{MediaKind, TechniqueMedia};
+use crate::models::naive_to_rfc3339;
+use crate::models::to_rfc3339_utc;
+use crate::scheduler::spawn_background_job;
+use crate::transaction::Tx;
+use crate::validation::{ToValidationResponse, validation_errors_response};
+use crate::validation::{ValidationConfig, ValidationResponse};
+use crate::validation::{
+    deserialize_nullable, deserialize_nullable_plain_text, deserialize_optional_plain_text,
+    deserialize_optional_username, deserialize_plain_text,
+    deserialize_tag_name, deserialize_username, normalize_tag_name,
+    validate_class_duration, validate_color, validate_description, validate_display_name,
+    validate_media_url,
+    validate_note, validate_password,
+    validate_person_name, validate_tag_name, validate_technique_name, validate_technique_notes,
+    validate_preferences, validate_timezone, validate_username,
+};
 
 #[derive(Debug)]
 pub enum ApiError {
     Validation(ValidationErrors),
+    /// Field errors where the input is well-formed but clashes with existing
+    /// data. Same body as `Validation`, sent as 409.
+    Conflict(ValidationErrors),
     AppError(AppError),
     Status(Status),
 }
@@ -52,6 +138,31 @@ impl From<ValidationErrors> for ApiError {
     }
 }
 
+/// Body parse failures become a 422 carrying serde's message under the
+/// `request` key, so typed fields (e.g. an unknown `role`) explain themselves
+/// instead of falling through to the generic catcher. Pair with a
+/// `Result<Json<T>, JsonError<'_>>` data guard. A body cut off at the `json`
+/// data limit surfaces from Rocket as an unexpected EOF and maps to 413.
+impl From<JsonError<'_>> for ApiError {
+    fn from(error: JsonError<'_>) -> Self {
+        match error {
+            JsonError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                ApiError::Status(Status::PayloadTooLarge)
+            }
+            JsonError::Io(_) => ApiError::Status(Status::BadRequest),
+            JsonError::Parse(_, err) => {
+                let detail = err.to_string();
+                let mut error = ValidationError::new("request.invalid_body")
+                    .with_message(detail.clone().into());
+                error.add_param("detail".into(), &detail);
+                let mut errors = ValidationErrors::new();
+                errors.add("request", error);
+                ApiError::Validation(errors)
+            }
+        }
+    }
+}
+
 impl From<AppError> for ApiError {
     fn from(error: AppError) -> Self {
         ApiError::AppError(error)
@@ -68,53 +179,46 @@ impl From<ApiError> for Status {
     fn from(error: ApiError) -> Self {
         match error {
             ApiError::Validation(_) => Status::UnprocessableEntity,
+            ApiError::Conflict(_) => Status::Conflict,
             ApiError::AppError(ref app_error) => app_error.status_code(),
             ApiError::Status(status) => status,
         }
     }
 }
 
-impl From<ApiError> for Custom<Json<ValidationResponse>> {
-    fn from(error: ApiError) -> Self {
-        match error {
-            ApiError::Validation(errors) => {
-                let mut error_map = HashMap::new();
-                for (field, field_errors) in errors.field_errors() {
-                    let error_messages: Vec<String> = field_errors
-                        .iter()
-                        .map(|error| {
-                            error
-                                .message
-                                .clone()
-                                .unwrap_or_else(|| "Invalid value".into())
-                                .to_string()
-                        })
-                        .collect();
-                    error_map.insert(field.to_string(), error_messages);
-                }
-                Custom(
-                    Status::UnprocessableEntity,
-                    Json(ValidationResponse::new(error_map)),
-                )
+impl ApiError {
+    /// 422/4xx/5xx body with messages in `locale`.
+    pub fn into_validation_response(self, locale: Locale) -> Custom<Json<ValidationResponse>> {
+        match self {
+            ApiError::Validation(errors) => validation_errors_response(&errors, locale),
+            ApiError::Conflict(errors) => {
+                let Custom(_, body) = validation_errors_response(&errors, locale);
+                Custom(Status::Conflict, body)
             }
-            ApiError::AppError(app_error) => app_error.to_validation_response(),
-            ApiError::Status(status) => status.to_validation_response(),
+            ApiError::AppError(app_error) => app_error.to_localized_validation_response(locale),
+            ApiError::Status(status) => status.to_localized_validation_response(locale),
         }
     }
 }
 
+impl From<ApiError> for Custom<Json<ValidationResponse>> {
+    fn from(error: ApiError) -> Self {
+        error.into_validation_response(Locale::default())
+    }
+}
+
 pub type ApiResult<T> = Result<T, ApiError>;
 
 impl<'r> Responder<'r, 'static> for ApiError {
     fn respond_to(self, req: &'r Request<'_>) -> rocket::response::Result<'static> {
-        let custom_response: Custom<Json<ValidationResponse>> = self.into();
-        custom_response.respond_to(req)
+        self.into_validation_response(Locale::from_request(req))
+            .respond_to(req)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserData {
-    pub id: i64,
+    pub id: UserId,
     pub username: String,
     pub display_name: String,
     pub role: String,
@@ -133,9 +237,14 @@ pub struct UserData {
     pub amber_count: Option<i64>,
     pub green_count: Option<i64>,
     pub has_unseen_activity: Option<bool>,
+    pub review_requests: Option<i64>,
     pub last_student_initiative_at: Option<String>,
     pub last_watch_at: Option<String>,
     pub last_watch_video_title: Option<String>,
+    pub timezone: Option<String>,
+    pub membership_status: Option<MembershipStatus>,
+    /// Loads the profile picture; `None` when the user hasn't set one.
+    pub avatar_url: Option<String>,
 }
 
 impl From<User> for UserData {
@@ -143,7 +252,7 @@ impl From<User> for UserData {
         Self {
             id: user.id,
             username: user.username.clone(),
-            display_name: user.display_name.clone(),
+            display_name: user.effective_display_name().to_string(),
             role: user.role.to_string(),
             last_update: user.last_update.clone(),
             archived: user.archived,
@@ -160,9 +269,13 @@ impl From<User> for UserData {
             amber_count: user.amber_count,
             green_count: user.green_count,
             has_unseen_activity: user.has_unseen_activity,
+            review_requests: user.review_requests,
             last_student_initiative_at: user.last_student_initiative_at.clone(),
             last_watch_at: user.last_watch_at.clone(),
             last_watch_video_title: user.last_watch_video_title.clone(),
+            timezone: user.timezone.clone(),
+            membership_status: user.membership_status,
+            avatar_url: user.avatar_attachment_id.map(|id| avatar_url(user.id, id)),
         }
     }
 }
@@ -175,28 +288,34 @@ pub struct LoginResponse {
     pub redirect_url: Option<String>,
 }
 
-#[derive(Deserialize, Validate)]
+/// Also serialized by the `loadtest` bin, hence `Serialize` and the public
+/// fields.
+#[derive(Serialize, Deserialize, Validate)]
 pub struct LoginRequest {
-    #[validate(length(min = 1, message = "Username cannot be empty"))]
-    username: String,
-    #[validate(length(min = 1, message = "Password cannot be empty"))]
-    password: String,
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(length(min = 1, code = "username.required", message = "Username cannot be empty"))]
+    pub username: String,
+    #[validate(length(min = 1, code = "password.required", message = "Password cannot be empty"))]
+    pub password: String,
 }
 
-/// Establishes the session cookies for a user. Shared by login and invite-claim.
-async fn establish_session(
+/// Establishes the session cookies for a user. Shared by login, invite-claim
+/// and OIDC sign-in.
+pub(crate) async fn establish_session(
     cookies: &rocket::http::CookieJar<'_>,
     db: &State<Pool<Sqlite>>,
+    config: &LiveConfig,
     user: &User,
 ) -> Result<(), AppError> {
     use chrono::Utc;
     use rocket::http::{Cookie, SameSite};
 
     let token = UserSession::generate_token();
-    let lifetime = chrono::Duration::days(UserSession::LIFETIME_DAYS);
-    let cookie_max_age = rocket::time::Duration::days(UserSession::LIFETIME_DAYS);
+    let ttl_days = config.get().session_ttl_days;
+    let lifetime = chrono::Duration::days(ttl_days);
+    let cookie_max_age = rocket::time::Duration::days(ttl_days);
     let expires_at = Utc::now() + lifetime;
-    create_user_session(db, user.id, &token, expires_at.naive_utc()).await?;
+    create_user_session(db.inner(), user.id, &token, expires_at.naive_utc()).await?;
 
     cookies.add_private(
         Cookie::build(("session_token", token))
@@ -235,17 +354,18 @@ async fn establish_session(
 pub async fn api_login(
     login: Json<LoginRequest>,
     cookies: &rocket::http::CookieJar<'_>,
+    config: &State<LiveConfig>,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<LoginResponse>> {
     login.validate()?;
 
-    match authenticate_user(db, &login.username, &login.password).await? {
+    match authenticate_user(db.inner(), &login.username, &login.password).await? {
         Some(user) => {
-            establish_session(cookies, db, &user).await?;
+            establish_session(cookies, db, config, &user).await?;
 
             let redirect_url = match user.role.as_str() {
-                "student" => format!("/ui/student/{}", user.id),
-                _ => "/ui/dashboard".to_string(),
+                "student" => format!("/student/{}", user.id),
+                _ => "/dashboard".to_string(),
             };
 
             Ok(Json(LoginResponse {
@@ -287,15 +407,28 @@ pub fn compute_has_unseen_activity(
     }
 }
 
+/// Read receipt for coach feedback: has the student opened the row since a
+/// coach last changed it? `None` when no coach has changed it.
+pub fn compute_coach_update_read(
+    last_coach_update_at: Option<chrono::DateTime<chrono::Utc>>,
+    student_seen_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Option<bool> {
+    let update = last_coach_update_at?;
+    Some(student_seen_at.is_some_and(|seen| seen >= update))
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TechniqueResponse {
-    pub id: i64,
-    pub technique_id: i64,
+    pub id: StudentTechniqueId,
+    pub technique_id: TechniqueId,
     pub technique_name: String,
     pub technique_description: String,
     pub status: String,
     pub student_notes: String,
     pub coach_notes: String,
+    /// When set, only viewers who can edit every technique see
+    /// `coach_notes`; for anyone else it comes back empty.
+    pub coach_notes_private: bool,
     pub created_at: String,
     pub updated_at: String,
     pub last_coach_update_at: Option<String>,
@@ -303,16 +436,64 @@ pub struct TechniqueResponse {
     pub last_student_update_at: Option<String>,
     pub last_student_update_by_name: Option<String>,
     pub has_unseen_activity: bool,
+    pub student_last_viewed_at: Option<String>,
+    pub coach_update_read: Option<bool>,
     pub collection_id: Option<i64>,
     pub collection_name: Option<String>,
+    pub review_requested_at: Option<String>,
     pub tags: Vec<TagResponse>,
+    pub media: Vec<TechniqueMedia>,
     pub attempt_count: i64,
     pub last_attempt_at: Option<String>,
 }
 
+/// Whether `viewer` is kept from `technique`'s coach notes.
+fn hides_coach_notes(technique: &StudentTechnique, viewer: &User) -> bool {
+    technique.coach_notes_private && !viewer.has_permission(Permission::EditAllTechniques)
+}
+
+fn technique_response(mut t: StudentTechnique, viewer: &User) -> TechniqueResponse {
+    if hides_coach_notes(&t, viewer) {
+        t.coach_notes.clear();
+    }
+    let viewer_is_owner = viewer.id == t.student_id;
+    let has_unseen_activity = compute_has_unseen_activity(
+        viewer_is_owner,
+        t.last_coach_update_at,
+        t.last_student_update_at,
+        t.viewer_seen_at,
+    );
+    TechniqueResponse {
+        id: t.id,
+        technique_id: t.technique_id,
+        technique_name: t.technique_name,
+        technique_description: t.technique_description,
+        status: t.status,
+        student_notes: t.student_notes,
+        coach_notes: t.coach_notes,
+        coach_notes_private: t.coach_notes_private,
+        created_at: to_rfc3339_utc(t.created_at),
+        updated_at: to_rfc3339_utc(t.updated_at),
+        last_coach_update_at: t.last_coach_update_at.map(to_rfc3339_utc),
+        last_coach_update_by_name: t.last_coach_update_by_name,
+        last_student_update_at: t.last_student_update_at.map(to_rfc3339_utc),
+        last_student_update_by_name: t.last_student_update_by_name,
+        has_unseen_activity,
+        student_last_viewed_at: t.student_seen_at.map(to_rfc3339_utc),
+        coach_update_read: compute_coach_update_read(t.last_coach_update_at, t.student_seen_at),
+        collection_id: t.collection_id,
+        collection_name: t.collection_name,
+        review_requested_at: t.review_requested_at.map(to_rfc3339_utc),
+        tags: t.tags.into_iter().map(TagResponse::from).collect(),
+        media: t.media,
+        attempt_count: t.attempt_count,
+        last_attempt_at: t.last_attempt_at.map(to_rfc3339_utc),
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct StudentResponse {
-    pub id: i64,
+    pub id: UserId,
     pub username: String,
     pub display_name: String,
     pub archived: bool,
@@ -322,70 +503,100 @@ pub struct StudentResponse {
 #[derive(Serialize, Deserialize)]
 pub struct StudentTechniquesResponse {
     pub student: StudentResponse,
-    pub techniques: Vec<TechniqueResponse>,
+    pub techniques: Paginated<TechniqueResponse>,
     pub can_edit_all_techniques: bool,
     pub can_assign_techniques: bool,
     pub can_create_techniques: bool,
     pub can_manage_tags: bool,
 }
 
-#[get("/student/<id>/techniques")]
+/// The students `user` may see besides themselves: `None` for all of them.
+/// A coach sees only the students assigned to them (see `db::coach_students`),
+/// none if they have none, unless they can `ViewAllGymStudents`.
+pub async fn visible_students(
+    executor: impl SqliteExecutor<'_>,
+    user: &User,
+) -> Result<Option<HashSet<UserId>>, AppError> {
+    if !user.has_permission(Permission::ViewAllStudents) {
+        return Ok(Some(HashSet::new()));
+    }
+    if user.has_permission(Permission::ViewAllGymStudents) {
+        return Ok(None);
+    }
+    Ok(Some(get_coach_student_scope(executor, user.id).await?))
+}
+
+/// Whether `student_id` is `user` or one of their visible students. Writes
+/// check it too, answering 404 so a student outside a coach's scope looks
+/// missing rather than protected.
+async fn can_view_student(
+    executor: impl SqliteExecutor<'_>,
+    user: &User,
+    student_id: UserId,
+) -> Result<bool, AppError> {
+    if user.id == student_id {
+        return Ok(true);
+    }
+    let visible = visible_students(executor, user).await?;
+    Ok(visible.is_none_or(|ids| ids.contains(&student_id)))
+}
+
+#[derive(FromForm)]
+pub struct StudentTechniquesQuery {
+    /// A tag id; techniques under its nested tags match too.
+    tag: Option<i64>,
+    status: Option<String>,
+    /// RFC 3339, e.g. an `updated_at` from an earlier response.
+    updated_since: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[get("/student/<id>/techniques?<params..>")]
 pub async fn api_get_student_techniques(
-    id: i64,
+    id: UserId,
+    params: StudentTechniquesQuery,
     user: User,
+    uri: &Origin<'_>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<StudentTechniquesResponse>> {
-    if user.id != id && !user.has_permission(Permission::ViewAllStudents) {
+) -> ApiResult<ETagged<StudentTechniquesResponse>> {
+    if !can_view_student(db.inner(), &user, id).await? {
         return Err(Status::Forbidden.into());
     }
 
-    let student = get_user(db, id).await?;
+    let updated_since = match params.updated_since.as_deref() {
+        Some(raw) => Some(
+            chrono::DateTime::parse_from_rfc3339(raw)
+                .map_err(|e| {
+                    warn!(raw_value = raw, error = %e, "rejected updated_since: not RFC 3339");
+                    ApiError::from(Status::BadRequest)
+                })?
+                .with_timezone(&chrono::Utc),
+        ),
+        None => None,
+    };
+    let filter = StudentTechniqueFilter {
+        tag_id: params.tag.map(TagId),
+        status: params.status,
+        updated_since,
+    };
+
+    let student = get_user(db.inner(), id).await?;
 
-    let techniques = get_student_techniques(db, id, user.id).await?;
+    let techniques = get_student_techniques(db.inner(), id, user.id, &filter).await?;
 
-    let viewer_is_owner = user.id == id;
-    let technique_responses: Vec<TechniqueResponse> = techniques
-        .into_iter()
-        .map(|t| {
-            let has_unseen_activity = compute_has_unseen_activity(
-                viewer_is_owner,
-                t.last_coach_update_at,
-                t.last_student_update_at,
-                t.viewer_seen_at,
-            );
-            TechniqueResponse {
-                id: t.id,
-                technique_id: t.technique_id,
-                technique_name: t.technique_name,
-                technique_description: t.technique_description,
-                status: t.status,
-                student_notes: t.student_notes,
-                coach_notes: t.coach_notes,
-                created_at: t.created_at.to_rfc3339(),
-                updated_at: t.updated_at.to_rfc3339(),
-                last_coach_update_at: t.last_coach_update_at.map(|d| d.to_rfc3339()),
-                last_coach_update_by_name: t.last_coach_update_by_name,
-                last_student_update_at: t.last_student_update_at.map(|d| d.to_rfc3339()),
-                last_student_update_by_name: t.last_student_update_by_name,
-                has_unseen_activity,
-                collection_id: t.collection_id,
-                collection_name: t.collection_name,
-                tags: t.tags.into_iter().map(TagResponse::from).collect(),
-                attempt_count: t.attempt_count,
-                last_attempt_at: t.last_attempt_at.map(|d| d.to_rfc3339()),
-            }
-        })
-        .collect();
+    let technique_responses: Vec<TechniqueResponse> =
+        techniques.into_iter().map(|t| technique_response(t, &user)).collect();
 
-    Ok(Json(StudentTechniquesResponse {
+    Ok(ETagged(StudentTechniquesResponse {
         student: StudentResponse {
             id: student.id,
+            display_name: student.effective_display_name().to_string(),
             username: student.username,
-            display_name: student.display_name,
             archived: student.archived,
             graduated_at: student.graduated_at,
         },
-        techniques: technique_responses,
+        techniques: Paginated::from_all(technique_responses, params.page, params.per_page, uri),
         can_edit_all_techniques: user.has_permission(Permission::EditAllTechniques),
         can_assign_techniques: user.has_permission(Permission::AssignTechniques),
         can_create_techniques: user.has_permission(Permission::CreateTechniques),
@@ -393,30 +604,144 @@ pub async fn api_get_student_techniques(
     }))
 }
 
-#[derive(Deserialize, Validate, Clone)]
+/// Also serialized by the `loadtest` bin; unset fields are left out so the
+/// handler keeps the stored values.
+#[derive(Serialize, Deserialize, Validate, Clone, Default)]
+#[validate(context = ValidationConfig)]
 pub struct TechniqueUpdateRequest {
-    status: Option<String>,
-    student_notes: Option<String>,
-    coach_notes: Option<String>,
-    #[validate(length(
-        min = 1,
-        max = 100,
-        message = "Technique name must be between 1 and 100 characters"
-    ))]
-    technique_name: Option<String>,
-    technique_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_technique_notes", use_context))]
+    pub student_notes: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_technique_notes", use_context))]
+    pub coach_notes: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    pub technique_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "validate_description", use_context))]
+    pub technique_description: Option<String>,
+    /// Sets coach_notes to this note template's text. Can't be combined with
+    /// `coach_notes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coach_notes_template_id: Option<i64>,
+    /// Hides the coach notes from the student, or shows them again. Not
+    /// reported in `changed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coach_notes_private: Option<bool>,
+}
+
+/// `Err` unless `status` is on the gym's grading scale.
+fn check_known_status(scale: &[StatusLevel], status: &str) -> Result<(), ValidationError> {
+    if scale.iter().any(|level| level.name == status) {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("status.unknown")
+        .with_message(format!("{} is not a status", status).into());
+    error.add_param("status".into(), &status);
+    Err(error)
+}
+
+/// 422 on `status` unless it is on the gym's grading scale.
+async fn require_known_status(db: &Pool<Sqlite>, status: &str) -> ApiResult<()> {
+    let scale = get_statuses(db).await?;
+    check_known_status(&scale, status).map_err(|error| {
+        let mut errors = ValidationErrors::new();
+        errors.add("status", error);
+        ApiError::Validation(errors)
+    })
+}
+
+/// Checks a status change against the gym's `status_transitions` rules. No
+/// rules means no restriction beyond the caller's existing permissions.
+async fn check_status_transition(
+    db: &Pool<Sqlite>,
+    user: &User,
+    from: &str,
+    to: &str,
+) -> ApiResult<()> {
+    let transitions = get_status_transitions(db).await?;
+    if transitions.is_empty() {
+        return Ok(());
+    }
+
+    let rule = transitions
+        .iter()
+        .find(|t| t.from_status == from && t.to_status == to);
+    let mut error = match rule {
+        Some(rule) if user.role >= rule.min_role => return Ok(()),
+        Some(rule) => {
+            let mut error = ValidationError::new("status.transition_requires_role").with_message(
+                format!("Only a {} can change status from {} to {}", rule.min_role, from, to)
+                    .into(),
+            );
+            error.add_param("min_role".into(), &rule.min_role.as_str());
+            error
+        }
+        None => ValidationError::new("status.transition_not_allowed")
+            .with_message(format!("Status cannot change from {} to {}", from, to).into()),
+    };
+    error.add_param("from".into(), &from);
+    error.add_param("to".into(), &to);
+    let mut errors = ValidationErrors::new();
+    errors.add("status", error);
+    Err(ApiError::Validation(errors))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StudentTechniqueUpdateResponse {
+    pub technique: TechniqueResponse,
+    /// What this save changed; empty when it matched what was stored.
+    pub changed: Vec<StudentTechniqueField>,
 }
 
 #[put("/student_technique/<id>", data = "<technique>")]
 pub async fn api_update_student_technique(
-    id: i64,
+    id: StudentTechniqueId,
     technique: Json<TechniqueUpdateRequest>,
     user: User,
+    tx: Tx,
+    limits: &State<ValidationConfig>,
+    feed: &State<ActivityFeed>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    technique.validate()?;
+) -> ApiResult<Json<StudentTechniqueUpdateResponse>> {
+    // One transaction (see `crate::transaction`), so a failed rename doesn't
+    // leave the status and notes changed without it.
+    let mut conn = tx.conn().await?;
+    let applied = apply_technique_update(&mut conn, db, &user, id, &technique, limits).await?;
+    if applied.announce {
+        feed.publish_on_commit(&tx, ActivityKind::TechniqueUpdated, user.id, id);
+    }
+    Ok(Json(applied.response))
+}
+
+/// One saved update, and whether coaches should hear about it once it
+/// commits.
+struct AppliedUpdate {
+    response: StudentTechniqueUpdateResponse,
+    announce: bool,
+}
 
-    let student_technique = get_student_technique(db, id, user.id).await?;
+/// Validates, permission-checks and saves one update through `conn`, for
+/// both the single and the batch endpoint. The technique is read through
+/// `conn` too, so it reflects earlier writes in the same transaction.
+async fn apply_technique_update(
+    conn: &mut SqliteConnection,
+    db: &Pool<Sqlite>,
+    user: &User,
+    id: StudentTechniqueId,
+    technique: &TechniqueUpdateRequest,
+    limits: &ValidationConfig,
+) -> ApiResult<AppliedUpdate> {
+    technique.validate_with_args(limits)?;
+
+    let student_technique = get_student_technique_in(conn, id, user.id).await?;
 
     let is_own_technique = user.id == student_technique.student_id;
     let can_edit_all = user.has_permission(Permission::EditAllTechniques);
@@ -424,25 +749,54 @@ pub async fn api_update_student_technique(
     if !is_own_technique && !can_edit_all {
         return Err(Status::Forbidden.into());
     }
+    if !can_view_student(&mut *conn, user, student_technique.student_id).await? {
+        return Err(Status::NotFound.into());
+    }
 
-    if is_own_technique && !can_edit_all {
-        if let Some(notes) = &technique.student_notes {
-            update_student_notes(db, id, &user, notes).await?;
+    let changed = if !can_edit_all {
+        match &technique.student_notes {
+            Some(notes) => update_student_notes(conn, id, user, notes).await?,
+            None => Vec::new(),
+        }
+    } else {
+        if let Some(next) = technique.status.as_deref()
+            && next != student_technique.status
+        {
+            require_known_status(db, next).await?;
+            check_status_transition(db, user, &student_technique.status, next).await?;
         }
 
-        return Ok(Status::Ok);
-    } else if can_edit_all {
         let status = technique.status.clone().unwrap_or(student_technique.status);
         let student_notes = technique
             .student_notes
             .clone()
             .unwrap_or(student_technique.student_notes);
-        let coach_notes = technique
-            .coach_notes
-            .clone()
-            .unwrap_or(student_technique.coach_notes);
-
-        update_student_technique(db, id, &user, &status, &student_notes, &coach_notes).await?;
+        let coach_notes = match (&technique.coach_notes, technique.coach_notes_template_id) {
+            (Some(_), Some(_)) => {
+                let mut errors = ValidationErrors::new();
+                errors.add(
+                    "coach_notes_template_id",
+                    ValidationError::new("coach_notes.template_conflict").with_message(
+                        "Send either coach notes or a template, not both".into(),
+                    ),
+                );
+                return Err(ApiError::Validation(errors));
+            }
+            (None, Some(template_id)) => get_note_template(db, template_id, user.id).await?.body,
+            (notes, None) => notes.clone().unwrap_or(student_technique.coach_notes),
+        };
+
+        // Visibility first, so notes made private here don't notify the
+        // student and notes made public do.
+        if let Some(private) = technique.coach_notes_private
+            && private != student_technique.coach_notes_private
+        {
+            set_coach_notes_private(&mut *conn, id, private).await?;
+        }
+        let mut changed =
+            update_student_technique(conn, id, user, &status, &student_notes, &coach_notes)
+                .await?;
+        award_badges_quietly(conn, student_technique.student_id).await;
 
         if technique.technique_name.is_some() || technique.technique_description.is_some() {
             let technique_name = technique
@@ -454,1129 +808,3831 @@ pub async fn api_update_student_technique(
                 .clone()
                 .unwrap_or(student_technique.technique_description);
 
-            update_technique(
-                db,
-                student_technique.technique_id,
-                &technique_name,
-                &technique_description,
-            )
-            .await?;
+            changed.extend(
+                update_technique(
+                    conn,
+                    student_technique.technique_id,
+                    &technique_name,
+                    &technique_description,
+                )
+                .await?,
+            );
         }
+        changed
+    };
 
-        return Ok(Status::Ok);
-    }
+    // Read back through the transaction, which is where the writes are.
+    let updated = get_student_technique_in(conn, id, user.id).await?;
+    Ok(AppliedUpdate {
+        announce: is_own_technique && !changed.is_empty(),
+        response: StudentTechniqueUpdateResponse {
+            technique: technique_response(updated, user),
+            changed,
+        },
+    })
+}
 
-    Err(Status::BadRequest.into())
+/// One item of `PUT /student_techniques/batch`: an id and the fields a
+/// single update takes.
+#[derive(Deserialize)]
+pub struct BatchTechniqueUpdate {
+    pub id: StudentTechniqueId,
+    #[serde(flatten)]
+    pub update: TechniqueUpdateRequest,
 }
 
-#[derive(FromForm)]
-pub struct StudentsQueryParams {
-    sort_by: Option<String>,
-    include_archived: Option<bool>,
+/// Most updates one batch request may carry.
+pub const MAX_BATCH_UPDATES: usize = 100;
+
+/// What became of one item, in the order they were sent.
+#[derive(Serialize)]
+pub struct BatchUpdateResult {
+    pub id: StudentTechniqueId,
+    /// What a single update of this item would have answered with.
+    pub status: u16,
+    /// The saved technique; `None` when the item failed.
+    pub technique: Option<TechniqueResponse>,
+    pub changed: Vec<StudentTechniqueField>,
+    /// Why the item failed, in the shape of a single update's error body.
+    pub error: Option<ValidationResponse>,
 }
 
-#[get("/students?<params..>")]
-pub async fn api_get_students(
-    params: StudentsQueryParams,
+#[derive(Serialize)]
+pub struct BatchUpdateResponse {
+    pub results: Vec<BatchUpdateResult>,
+}
+
+/// Applies several updates, such as a night's gradings, in one
+/// transaction. Each item is validated and permission-checked as if it were
+/// sent alone; one that fails is rolled back on its own and reported, and
+/// the rest are still saved. Each technique may appear once.
+#[put("/student_techniques/batch", data = "<body>")]
+pub async fn api_batch_update_student_techniques(
+    body: Json<Vec<BatchTechniqueUpdate>>,
     user: User,
+    tx: Tx,
+    locale: Locale,
+    limits: &State<ValidationConfig>,
+    feed: &State<ActivityFeed>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<UserData>>> {
-    user.require_permission(Permission::ViewAllStudents)?;
+) -> ApiResult<Json<BatchUpdateResponse>> {
+    let items = body.into_inner();
+    if items.is_empty() || items.len() > MAX_BATCH_UPDATES {
+        let mut error = ValidationError::new("updates.count")
+            .with_message(format!("Send between 1 and {} updates", MAX_BATCH_UPDATES).into());
+        error.add_param("max".into(), &MAX_BATCH_UPDATES);
+        let mut errors = ValidationErrors::new();
+        errors.add("updates", error);
+        return Err(ApiError::Validation(errors));
+    }
 
-    let include_archived = params.include_archived.unwrap_or(false);
+    let mut conn = tx.conn().await?;
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        let outcome = if seen.insert(item.id) {
+            // A savepoint, so a failure part-way through an item undoes
+            // only that item.
+            let mut savepoint = conn.begin().await.map_err(AppError::from)?;
+            let update = &item.update;
+            let outcome =
+                apply_technique_update(&mut savepoint, db, &user, item.id, update, limits).await;
+            match &outcome {
+                Ok(applied) => {
+                    savepoint.commit().await.map_err(AppError::from)?;
+                    if applied.announce {
+                        let kind = ActivityKind::TechniqueUpdated;
+                        feed.publish_on_commit(&tx, kind, user.id, item.id);
+                    }
+                }
+                Err(_) => savepoint.rollback().await.map_err(AppError::from)?,
+            }
+            outcome.map(|applied| applied.response)
+        } else {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "id",
+                ValidationError::new("updates.duplicate")
+                    .with_message("Each technique can only be updated once per batch".into()),
+            );
+            Err(ApiError::Validation(errors))
+        };
 
-    // Always use the aggregating query so the response carries per-student
-    // counts and activity flags. Sort order is handled client-side.
-    let _ = params.sort_by;
-    let students = get_students_by_recent_updates(db, include_archived, user.id).await?;
+        results.push(match outcome {
+            Ok(response) => BatchUpdateResult {
+                id: item.id,
+                status: Status::Ok.code,
+                technique: Some(response.technique),
+                changed: response.changed,
+                error: None,
+            },
+            Err(error) => {
+                let Custom(status, Json(error)) = error.into_validation_response(locale);
+                BatchUpdateResult {
+                    id: item.id,
+                    status: status.code,
+                    technique: None,
+                    changed: Vec::new(),
+                    error: Some(error),
+                }
+            }
+        });
+    }
 
-    let student_responses: Vec<UserData> = students.into_iter().map(UserData::from).collect();
+    let failed = results.iter().filter(|result| result.error.is_some()).count();
+    info!(count = results.len(), failed, "Student techniques updated in a batch");
+    Ok(Json(BatchUpdateResponse { results }))
+}
 
-    Ok(Json(student_responses))
+#[derive(Deserialize)]
+pub struct StatusTransitionRule {
+    from_status: String,
+    to_status: String,
+    #[serde(default = "default_transition_role")]
+    min_role: Role,
 }
 
-#[get("/student/<id>/unassigned_techniques")]
-pub async fn api_get_unassigned_techniques(
-    id: i64,
-    user: User,
-    db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<Technique>>> {
-    user.require_permission(Permission::AssignTechniques)?;
+fn default_transition_role() -> Role {
+    Role::Coach
+}
 
-    let techniques = get_unassigned_techniques(db, id).await?;
+#[derive(Deserialize)]
+pub struct StatusTransitionsRequest {
+    transitions: Vec<StatusTransitionRule>,
+}
 
-    Ok(Json(techniques))
+#[derive(Serialize, Deserialize)]
+pub struct StatusTransitionResponse {
+    pub from_status: String,
+    pub to_status: String,
+    pub min_role: String,
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct AssignTechniquesRequest {
-    #[validate(length(min = 1, message = "At least one technique must be selected"))]
-    technique_ids: Vec<i64>,
-    collection_id: Option<i64>,
+impl From<StatusTransition> for StatusTransitionResponse {
+    fn from(transition: StatusTransition) -> Self {
+        Self {
+            from_status: transition.from_status,
+            to_status: transition.to_status,
+            min_role: transition.min_role.as_str().to_string(),
+        }
+    }
 }
 
-#[post("/student/<student_id>/add_techniques", data = "<request>")]
-pub async fn api_assign_techniques(
-    student_id: i64,
-    request: Json<AssignTechniquesRequest>,
+#[derive(Serialize, Deserialize)]
+pub struct StatusTransitionsResponse {
+    pub transitions: Vec<StatusTransitionResponse>,
+}
+
+#[get("/status_transitions")]
+pub async fn api_get_status_transitions(
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<StatusTransitionsResponse>> {
+    let transitions = get_status_transitions(db.inner()).await?;
+    Ok(Json(StatusTransitionsResponse {
+        transitions: transitions.into_iter().map(StatusTransitionResponse::from).collect(),
+    }))
+}
+
+/// Replaces the full rule set. Students cannot change status at all, so a
+/// rule's `min_role` must be `coach` or `admin`.
+#[put("/status_transitions", data = "<body>")]
+pub async fn api_replace_status_transitions(
+    body: Json<StatusTransitionsRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    request.validate()?;
-
-    user.require_permission(Permission::AssignTechniques)?;
+    user.require_permission(Permission::ManageStatusTransitions)?;
+
+    let scale = get_statuses(db.inner()).await?;
+    let mut errors = ValidationErrors::new();
+    for rule in &body.transitions {
+        if rule.from_status.trim().is_empty() || rule.to_status.trim().is_empty() {
+            errors.add(
+                "transitions",
+                ValidationError::new("status.required")
+                    .with_message("Status names cannot be empty".into()),
+            );
+        } else if rule.from_status == rule.to_status {
+            let mut error = ValidationError::new("status.transition_to_self")
+                .with_message(format!("{} cannot transition to itself", rule.from_status).into());
+            error.add_param("status".into(), &rule.from_status);
+            errors.add("transitions", error);
+        } else {
+            for status in [&rule.from_status, &rule.to_status] {
+                if let Err(error) = check_known_status(&scale, status.trim()) {
+                    errors.add("transitions", error);
+                }
+            }
+        }
+        if rule.min_role == Role::Student {
+            errors.add(
+                "transitions",
+                ValidationError::new("status.min_role_invalid")
+                    .with_message("Transition role must be coach or admin".into()),
+            );
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
 
-    add_techniques_to_student(
-        db,
-        student_id,
-        request.technique_ids.clone(),
-        request.collection_id,
-        user.id,
-    )
-    .await?;
+    let transitions: Vec<StatusTransition> = body
+        .into_inner()
+        .transitions
+        .into_iter()
+        .map(|rule| StatusTransition {
+            from_status: rule.from_status.trim().to_string(),
+            to_status: rule.to_status.trim().to_string(),
+            min_role: rule.min_role,
+        })
+        .collect();
+    replace_status_transitions(db, &transitions).await?;
 
     Ok(Status::Ok)
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct CreateTechniqueRequest {
-    #[validate(length(
-        min = 1,
-        max = 100,
-        message = "Technique name must be between 1 and 100 characters"
-    ))]
+/// The grading scale, lowest first. Every signed-in user needs it to draw
+/// statuses.
+#[get("/statuses")]
+pub async fn api_get_statuses(
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<StatusLevel>>> {
+    Ok(Json(get_statuses(db.inner()).await?))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct StatusCreateRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(length(max = 50, code = "name.too_long", message = "Name is too long"))]
     name: String,
-    #[validate(length(min = 1, message = "Description cannot be empty"))]
-    description: String,
-    collection_id: Option<i64>,
+    #[validate(custom(function = "validate_color"))]
+    color: String,
 }
 
-#[post("/student/<student_id>/create_technique", data = "<request>")]
-pub async fn api_create_and_assign_technique(
-    student_id: i64,
-    request: Json<CreateTechniqueRequest>,
+/// Adds a status at the top of the scale. 409 if the name is taken.
+#[post("/statuses", data = "<body>")]
+pub async fn api_create_status(
+    body: Json<StatusCreateRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    request.validate()?;
-    user.require_all_permissions(&[Permission::CreateTechniques, Permission::AssignTechniques])?;
+) -> ApiResult<Json<StatusLevel>> {
+    user.require_permission(Permission::ManageStatuses)?;
+    body.validate()?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "name",
+            ValidationError::new("status.required")
+                .with_message("Status names cannot be empty".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
 
-    create_and_assign_technique(
-        db,
-        user.id,
-        student_id,
-        &request.name,
-        &request.description,
-        request.collection_id,
-    )
-    .await?;
+    match create_status(db, name, &body.color).await? {
+        Some(status) => Ok(Json(status)),
+        None => {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "name",
+                ValidationError::new("status.taken")
+                    .with_message("A status with this name already exists".into()),
+            );
+            Err(ApiError::Conflict(errors))
+        }
+    }
+}
 
-    Ok(Status::Ok)
+#[derive(Deserialize, Validate)]
+pub struct StatusUpdateRequest {
+    #[validate(custom(function = "validate_color"))]
+    color: String,
 }
 
-#[get("/me")]
-pub async fn api_me(user: User) -> Json<UserData> {
-    Json(UserData::from(user))
+/// Recolours a status. Its name is what assignments store, so it stays.
+#[put("/statuses/<name>", data = "<body>")]
+pub async fn api_update_status(
+    name: &str,
+    body: Json<StatusUpdateRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageStatuses)?;
+    body.validate()?;
+    update_status_color(db.inner(), name, &body.color).await?;
+    Ok(Status::Ok)
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct LibraryStatsResponse {
-    pub total_techniques: i64,
+#[derive(Deserialize)]
+pub struct ReorderStatusesRequest {
+    /// Every status, lowest first.
+    names: Vec<String>,
 }
 
-#[get("/library/stats")]
-pub async fn api_library_stats(
+/// 422 `statuses.mismatch` unless `names` lists each status exactly once.
+#[put("/statuses/order", data = "<body>")]
+pub async fn api_reorder_statuses(
+    body: Json<ReorderStatusesRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<LibraryStatsResponse>> {
-    user.require_permission(Permission::ViewAllStudents)?;
-
-    let total_techniques = count_techniques(db).await?;
+) -> ApiResult<Json<Vec<StatusLevel>>> {
+    user.require_permission(Permission::ManageStatuses)?;
+    let mut current: Vec<String> =
+        get_statuses(db.inner()).await?.into_iter().map(|level| level.name).collect();
+    let mut requested = body.names.clone();
+    current.sort();
+    requested.sort();
+    if current != requested {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "names",
+            ValidationError::new("statuses.mismatch")
+                .with_message("List each status exactly once".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
 
-    Ok(Json(LibraryStatsResponse { total_techniques }))
+    reorder_statuses(db, &body.names).await?;
+    Ok(Json(get_statuses(db.inner()).await?))
 }
 
-#[get("/techniques")]
-pub async fn api_list_library_techniques(
+/// 409 `status.in_use` while any assignment or rank requirement holds the
+/// status, and always for the starting status. Transition rules naming it
+/// go with it.
+#[delete("/statuses/<name>")]
+pub async fn api_delete_status(
+    name: &str,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<crate::db::LibraryTechniqueRow>>> {
-    user.require_permission(Permission::ViewAllStudents)?;
-    let rows = crate::db::list_library_techniques(db).await?;
-    Ok(Json(rows))
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageStatuses)?;
+    if name == STARTING_STATUS || count_status_uses(db.inner(), name).await? > 0 {
+        let mut error = ValidationError::new("status.in_use")
+            .with_message(format!("{} is in use and can't be deleted", name).into());
+        error.add_param("status".into(), &name);
+        let mut errors = ValidationErrors::new();
+        errors.add("name", error);
+        return Err(ApiError::Conflict(errors));
+    }
+    delete_status(db, name).await?;
+    Ok(Status::NoContent)
 }
 
-#[get("/techniques/<id>/stats")]
-pub async fn api_library_technique_stats(
-    id: i64,
+#[derive(Deserialize)]
+pub struct BulkStatusRequest {
+    /// Only techniques with this tag.
+    #[serde(default)]
+    tag_id: Option<TagId>,
+    /// Only techniques currently at this status.
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    from_status: Option<String>,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    status: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkStatusResponse {
+    pub updated: u64,
+}
+
+/// Sets the status of every technique of a student that matches the filter,
+/// such as putting everything back to amber for a new grading cycle. Each
+/// status being changed from must pass the transition rules.
+#[post("/student/<id>/techniques/bulk_status", data = "<body>")]
+pub async fn api_bulk_status(
+    id: UserId,
+    body: Json<BulkStatusRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<crate::db::LibraryTechniqueStats>> {
-    user.require_permission(Permission::ViewAllStudents)?;
-    let stats = crate::db::library_technique_stats(db, id).await?;
-    Ok(Json(stats))
-}
+) -> ApiResult<Json<BulkStatusResponse>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::NotFound.into());
+    }
+    let status = body.status.trim();
+    if status.is_empty() {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "status",
+            ValidationError::new("status.required")
+                .with_message("Status names cannot be empty".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
+    require_known_status(db, status).await?;
+    let target = get_user(db.inner(), id).await?;
+    if target.role != Role::Student {
+        return Err(Status::BadRequest.into());
+    }
 
-#[get("/me", rank = 2)]
-pub async fn api_me_unauthorized() -> Status {
-    Status::Unauthorized
+    let filter = StatusFilter {
+        tag_id: body.tag_id,
+        status: body.from_status.as_deref().map(str::trim).map(str::to_string),
+    };
+    for from in get_matching_statuses(db.inner(), id, &filter).await? {
+        if from != status {
+            check_status_transition(db, &user, &from, status).await?;
+        }
+    }
+    let updated = bulk_update_status(db.inner(), id, &user, &filter, status).await?;
+    if updated > 0 {
+        let mut conn = db.acquire().await.map_err(AppError::from)?;
+        award_badges_quietly(&mut conn, id).await;
+    }
+    Ok(Json(BulkStatusResponse { updated }))
 }
 
-#[post("/logout")]
-pub async fn api_logout(cookies: &CookieJar<'_>, db: &State<Pool<Sqlite>>) -> Redirect {
-    let token = cookies
-        .get_private("session_token")
-        .map(|cookie| cookie.value().to_string());
+#[get("/ranks")]
+pub async fn api_get_ranks(user: User, db: &State<Pool<Sqlite>>) -> ApiResult<Json<Vec<Rank>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    Ok(Json(get_ranks(db.inner()).await?))
+}
 
-    if let Some(token) = token {
-        let _ = invalidate_session(db, &token).await;
-    }
+#[derive(Deserialize)]
+pub struct RankRequirementRule {
+    tag_id: TagId,
+    min_count: i64,
+    #[serde(default = "default_requirement_status")]
+    status: String,
+}
 
-    cookies.remove_private(rocket::http::Cookie::build("session_token"));
-    cookies.remove_private(rocket::http::Cookie::build("user_id"));
-    cookies.remove_private(rocket::http::Cookie::build("logged_in"));
-    cookies.remove_private(rocket::http::Cookie::build("session_timestamp"));
-    cookies.remove_private(rocket::http::Cookie::build("user_role"));
+fn default_requirement_status() -> String {
+    "green".to_string()
+}
 
-    Redirect::to("/")
+#[derive(Deserialize)]
+pub struct RankRule {
+    name: String,
+    #[serde(default)]
+    requirements: Vec<RankRequirementRule>,
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct ProfileUpdateRequest {
-    #[validate(length(max = 100, message = "Display name must be under 100 characters"))]
-    display_name: String,
-    #[validate(length(
-        min = 1,
-        max = 50,
-        message = "Username must be 1-50 characters"
-    ))]
-    username: Option<String>,
+#[derive(Deserialize)]
+pub struct RanksRequest {
+    ranks: Vec<RankRule>,
 }
 
-#[put("/profile", data = "<profile>")]
-pub async fn api_update_profile(
-    profile: Json<ProfileUpdateRequest>,
+/// Replaces the belt ladder, lowest rank first.
+#[put("/ranks", data = "<body>")]
+pub async fn api_replace_ranks(
+    body: Json<RanksRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    profile.validate()?;
-
-    if let Some(new_username) = profile.username.as_deref() {
-        let trimmed = new_username.trim();
-        if trimmed != user.username {
-            // Field-level uniqueness check so the frontend can highlight the
-            // username input. `update_username` does its own check, but its
-            // error type collapses to a generic 500 here.
-            if let Some(other) = find_user_by_username(db, trimmed).await? {
-                if other.id != user.id {
-                    let mut errors = validator::ValidationErrors::new();
-                    let mut err = validator::ValidationError::new("unique");
-                    err.message = Some("That username is already taken".into());
-                    errors.add("username", err);
-                    return Err(errors.into());
-                }
+    user.require_permission(Permission::ManageRanks)?;
+
+    let tag_ids: Vec<i64> =
+        get_all_tags(db.inner(), true).await?.into_iter().map(|tag| tag.id).collect();
+    let scale = get_statuses(db.inner()).await?;
+    let mut errors = ValidationErrors::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for rank in &body.ranks {
+        let name = rank.name.trim();
+        if name.is_empty() {
+            errors.add(
+                "ranks",
+                ValidationError::new("rank.name_required")
+                    .with_message("Rank names cannot be empty".into()),
+            );
+        } else if seen.contains(&name) {
+            let mut error = ValidationError::new("rank.duplicate")
+                .with_message(format!("{} is listed more than once", name).into());
+            error.add_param("rank".into(), &name);
+            errors.add("ranks", error);
+        }
+        seen.push(name);
+        for requirement in &rank.requirements {
+            if !tag_ids.contains(&requirement.tag_id.0) {
+                let mut error = ValidationError::new("rank.unknown_tag")
+                    .with_message(format!("Tag {} does not exist", requirement.tag_id).into());
+                error.add_param("tag_id".into(), &requirement.tag_id);
+                errors.add("ranks", error);
+            }
+            if requirement.min_count < 1 || requirement.status.trim().is_empty() {
+                errors.add(
+                    "ranks",
+                    ValidationError::new("rank.requirement_invalid").with_message(
+                        "Requirements need a status and a count of at least 1".into(),
+                    ),
+                );
+            } else if let Err(error) = check_known_status(&scale, requirement.status.trim()) {
+                errors.add("ranks", error);
             }
-            update_username(db, user.id, trimmed).await?;
         }
     }
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
 
-    update_user_display_name(db, user.id, &profile.display_name).await?;
+    let ranks: Vec<RankDefinition> = body
+        .into_inner()
+        .ranks
+        .into_iter()
+        .map(|rank| RankDefinition {
+            name: rank.name.trim().to_string(),
+            requirements: rank
+                .requirements
+                .into_iter()
+                .map(|r| (r.tag_id, r.min_count, r.status.trim().to_string()))
+                .collect(),
+        })
+        .collect();
+    replace_ranks(db, &ranks).await?;
 
     Ok(Status::Ok)
 }
 
-#[derive(Deserialize, Validate)]
-pub struct PasswordChangeRequest {
-    #[validate(length(min = 1, message = "Current password cannot be empty"))]
-    current_password: String,
-    #[validate(length(min = 5, message = "New password must be at least 5 characters long"))]
-    new_password: String,
+/// Each active student's progress toward their next rank, for coaches to
+/// review before a grading.
+#[get("/ranks/eligibility")]
+pub async fn api_rank_eligibility(
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<RankEligibility>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let visible = visible_students(db.inner(), &user).await?;
+    let mut eligibility = get_rank_eligibility(db).await?;
+    eligibility.retain(|row| visible.as_ref().is_none_or(|ids| ids.contains(&row.student_id)));
+    Ok(Json(eligibility))
 }
 
-#[post("/change-password", data = "<password>")]
-pub async fn api_change_password(
-    password: Json<PasswordChangeRequest>,
+#[derive(Deserialize)]
+pub struct StudentRankRequest {
+    /// `None` clears the student's rank.
+    rank: Option<String>,
+}
+
+#[put("/student/<id>/rank", data = "<body>")]
+pub async fn api_set_student_rank(
+    id: UserId,
+    body: Json<StudentRankRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    password.validate()?;
-
-    let is_valid = authenticate_user(db, &user.username, &password.current_password).await?;
-
-    match is_valid {
-        Some(_) => {
-            update_user_password(db, user.id, &password.new_password).await?;
-
-            Ok(Status::Ok)
-        }
-        _ => Err(ApiError::AppError(AppError::Authentication(
-            "Current password is incorrect".to_string(),
-        ))),
+    user.require_permission(Permission::EditAllTechniques)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::NotFound.into());
     }
+    let target = get_user(db.inner(), id).await?;
+    if target.role != Role::Student {
+        return Err(Status::BadRequest.into());
+    }
+    let rank = body.rank.as_deref().map(str::trim);
+    if let Some(rank) = rank
+        && !get_ranks(db.inner()).await?.iter().any(|r| r.name == rank)
+    {
+        let mut error = ValidationError::new("rank.unknown")
+            .with_message(format!("{} is not one of the gym's ranks", rank).into());
+        error.add_param("rank".into(), &rank);
+        let mut errors = ValidationErrors::new();
+        errors.add("rank", error);
+        return Err(ApiError::Validation(errors));
+    }
+    set_student_rank(db, id, rank, user.id).await?;
+    Ok(Status::Ok)
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct UserRegistrationRequest {
-    #[validate(
-        length(
-            min = 3,
-            max = 50,
-            message = "Username must be between 3 and 50 characters"
-        ),
-        does_not_contain(pattern = " ", message = "Username cannot contain spaces")
-    )]
-    username: String,
-    #[validate(length(max = 100, message = "Display name must be under 100 characters"))]
-    display_name: String,
-    #[validate(length(min = 5, message = "Password must be at least 5 characters long"))]
-    password: String,
-    #[validate(must_match(other = "password", message = "Passwords must match"))]
-    confirm_password: String,
-    role: String,
+#[derive(FromForm)]
+pub struct StudentsQueryParams {
+    sort_by: Option<String>,
+    include_archived: Option<bool>,
+    /// Only students with this membership status.
+    membership: Option<MembershipStatus>,
+    page: Option<i64>,
+    per_page: Option<i64>,
 }
 
-#[post("/register", data = "<registration>")]
-pub async fn api_register_user(
-    registration: Json<UserRegistrationRequest>,
+#[get("/students?<params..>")]
+pub async fn api_get_students(
+    params: StudentsQueryParams,
     user: User,
+    uri: &Origin<'_>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    registration.validate()?;
-
-    let existing_user = find_user_by_username(db, &registration.username).await?;
-
-    if existing_user.is_some() {
-        return Err(ApiError::AppError(AppError::Internal(
-            "Username already exists".to_string(),
-        )));
-    }
+) -> ApiResult<ETagged<Paginated<UserData>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
 
-    match registration.role.as_str() {
-        "admin" => {
-            user.require_all_permissions(&[Permission::EditUserRoles, Permission::RegisterUsers])?
-        }
-        _ => user.require_permission(Permission::RegisterUsers)?,
-    };
+    let include_archived = params.include_archived.unwrap_or(false);
 
-    create_user(
-        db,
-        &registration.username,
-        &registration.password,
-        &registration.role,
-        Some(&registration.display_name),
-    )
-    .await?;
+    // Always use the aggregating query so the response carries per-student
+    // counts and activity flags. Sort order is handled client-side.
+    let _ = params.sort_by;
+    let students = get_students_by_recent_updates(db.inner(), include_archived, user.id).await?;
+    let visible = visible_students(db.inner(), &user).await?;
 
-    Ok(Status::Created)
-}
+    let student_responses: Vec<UserData> = students
+        .into_iter()
+        .filter(|student| visible.as_ref().is_none_or(|ids| ids.contains(&student.id)))
+        .filter(|student| params.membership.is_none_or(|m| student.membership_status == Some(m)))
+        .map(UserData::from)
+        .collect();
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct UserUpdateRequest {
-    #[validate(
-        length(
-            min = 3,
-            max = 50,
-            message = "Username must be between 3 and 50 characters"
-        ),
-        does_not_contain(pattern = " ", message = "Username cannot contain spaces")
-    )]
-    username: Option<String>,
-    #[validate(length(max = 100, message = "Display name must be under 100 characters"))]
-    display_name: Option<String>,
-    #[validate(length(min = 5, message = "Password must be at least 5 characters long"))]
-    password: Option<String>,
-    archived: Option<bool>,
-    graduated: Option<bool>,
-    role: Option<String>,
+    Ok(ETagged(Paginated::from_all(student_responses, params.page, params.per_page, uri)))
 }
 
-#[put("/admin/users/<id>", data = "<update>")]
-pub async fn api_update_user(
-    id: i64,
-    update: Json<UserUpdateRequest>,
+#[get("/student/<id>/unassigned_techniques?<page>&<per_page>")]
+pub async fn api_get_unassigned_techniques(
+    id: UserId,
+    page: Option<i64>,
+    per_page: Option<i64>,
     user: User,
+    uri: &Origin<'_>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    update.clone().validate()?;
-    user.require_permission(Permission::EditUserCredentials)?;
-
-    if update.role.is_some() {
-        user.require_permission(Permission::EditUserRoles)?;
+) -> ApiResult<Json<Paginated<Technique>>> {
+    user.require_permission(Permission::AssignTechniques)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::NotFound.into());
     }
 
-    if let Some(username) = &update.username {
-        update_username(db, id, username).await?;
-    }
+    let techniques = get_unassigned_techniques(db, id).await?;
 
-    if let Some(display_name) = &update.display_name {
-        update_user_display_name(db, id, display_name).await?;
-    }
+    Ok(Json(Paginated::from_all(techniques, page, per_page, uri)))
+}
 
-    if let Some(password) = &update.password {
-        update_user_password(db, id, password).await?;
-    }
+#[derive(Deserialize, Validate, Clone)]
+pub struct AssignTechniquesRequest {
+    #[validate(length(
+        min = 1,
+        code = "technique_ids.required",
+        message = "At least one technique must be selected"
+    ))]
+    technique_ids: Vec<TechniqueId>,
+    collection_id: Option<i64>,
+    /// Assign even if the student hasn't reached green on prerequisites.
+    #[serde(default)]
+    force: bool,
+}
 
-    if let Some(archived) = update.archived {
-        set_user_archived(db, id, archived).await?;
-    }
+#[derive(Serialize)]
+struct UnmetPrerequisites {
+    technique_id: TechniqueId,
+    prerequisites: Vec<Prerequisite>,
+}
 
-    if let Some(graduated) = update.graduated {
-        set_user_graduated(db, id, graduated, Some(user.id)).await?;
+/// 409 listing, per technique, the prerequisites `student_id` hasn't
+/// reached green on, unless there are none. Like [`reject_likely_duplicate`]
+/// this is a warning: the SPA can resend with `force: true`.
+async fn reject_unmet_prerequisites(
+    db: &Pool<Sqlite>,
+    student_id: UserId,
+    technique_ids: &[TechniqueId],
+) -> ApiResult<()> {
+    let mut unmet = Vec::new();
+    for &technique_id in technique_ids {
+        let prerequisites = get_unmet_prerequisites(db, student_id, technique_id).await?;
+        if !prerequisites.is_empty() {
+            unmet.push(UnmetPrerequisites { technique_id, prerequisites });
+        }
     }
-
-    if let Some(role) = &update.role {
-        update_user_role(db, id, role).await?;
+    if unmet.is_empty() {
+        return Ok(());
     }
 
-    Ok(Status::Ok)
+    let mut names: Vec<&str> = unmet
+        .iter()
+        .flat_map(|u| u.prerequisites.iter().map(|p| p.technique_name.as_str()))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let names = names.join(", ");
+    let mut error = ValidationError::new("technique.prerequisites_unmet")
+        .with_message(format!("Not yet green on prerequisites: {}", names).into());
+    error.add_param("names".into(), &names);
+    error.add_param("unmet".into(), &unmet);
+    let mut errors = ValidationErrors::new();
+    errors.add("technique_ids", error);
+    Err(ApiError::Conflict(errors))
 }
 
-/// Mark a student_technique row as seen by the current viewer, clearing the
-/// "unseen activity" dot for them. Used by the row-expand interaction.
-#[post("/student_technique/<id>/mark_seen")]
-pub async fn api_mark_student_technique_seen(
-    id: i64,
+#[post("/student/<student_id>/add_techniques", data = "<request>")]
+pub async fn api_assign_techniques(
+    student_id: UserId,
+    request: Json<AssignTechniquesRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    let st = get_student_technique(db, id, user.id).await?;
-    if user.id != st.student_id && !user.has_permission(Permission::ViewAllStudents) {
-        return Err(Status::Forbidden.into());
+    request.validate()?;
+
+    user.require_permission(Permission::AssignTechniques)?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
     }
-    mark_student_technique_seen(db, id, user.id).await?;
-    Ok(Status::NoContent)
+    if !request.force {
+        reject_unmet_prerequisites(db, student_id, &request.technique_ids).await?;
+    }
+
+    add_techniques_to_student(
+        db,
+        student_id,
+        request.technique_ids.clone(),
+        request.collection_id,
+        user.id,
+    )
+    .await?;
+
+    Ok(Status::Ok)
 }
 
-#[derive(Deserialize, Clone)]
-pub struct GraduateRequest {
-    graduated: bool,
+/// Send exactly one of the two.
+#[derive(Deserialize)]
+pub struct AssignGroupRequest {
+    tag_id: Option<TagId>,
+    collection_id: Option<i64>,
 }
 
-/// Coach-accessible endpoint to graduate / un-graduate a student.
-/// Distinct from `/admin/users/<id>` which is admin-only.
-#[post("/student/<id>/graduate", data = "<body>")]
-pub async fn api_set_student_graduated(
-    id: i64,
-    body: Json<GraduateRequest>,
+/// Assigns every technique carrying a tag (or one nested under it), or in a
+/// curriculum, that the student doesn't have yet. Unlike `add_techniques`
+/// this doesn't warn about unmet prerequisites.
+#[post("/student/<student_id>/assign_by_tag", data = "<request>")]
+pub async fn api_assign_by_tag(
+    student_id: UserId,
+    request: Json<AssignGroupRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::ViewAllStudents)?;
+) -> ApiResult<Json<GroupAssignment>> {
+    let group = match (request.tag_id, request.collection_id) {
+        (Some(tag_id), None) => TechniqueGroup::Tag(tag_id),
+        (None, Some(collection_id)) => TechniqueGroup::Collection(collection_id),
+        _ => {
+            let error = ValidationError::new("assign.group")
+                .with_message("Send either a tag_id or a collection_id, not both".into());
+            let mut errors = ValidationErrors::new();
+            errors.add("tag_id", error);
+            return Err(ApiError::Validation(errors));
+        }
+    };
 
-    let target = get_user(db, id).await?;
-    if !matches!(target.role, crate::auth::Role::Student) {
-        return Err(Status::BadRequest.into());
+    user.require_permission(Permission::AssignTechniques)?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
     }
-
-    set_user_graduated(db, id, body.graduated, Some(user.id)).await?;
-    Ok(Status::Ok)
+    get_user(db.inner(), student_id).await?;
+    Ok(Json(assign_group_to_student(db, student_id, group, user.id).await?))
 }
 
-#[get("/health")]
-pub fn health() -> &'static str {
-    "OK"
+#[derive(Deserialize, Validate)]
+pub struct RemoveTechniquesRequest {
+    #[validate(length(
+        min = 1,
+        code = "student_technique_ids.required",
+        message = "At least one technique must be selected"
+    ))]
+    student_technique_ids: Vec<StudentTechniqueId>,
 }
+
 #[derive(Serialize, Deserialize)]
-pub struct TagsResponse {
-    pub tags: Vec<Tag>,
+pub struct RemoveTechniquesResponse {
+    pub removed: u64,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct TagResponse {
-    pub id: i64,
-    pub name: String,
+/// Undoes assignments, such as a bulk assignment made to the wrong student.
+/// Every id must be one of this student's techniques or nothing is removed.
+#[delete("/student/<id>/techniques", data = "<request>")]
+pub async fn api_remove_techniques(
+    id: UserId,
+    request: Json<RemoveTechniquesRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<RemoveTechniquesResponse>> {
+    request.validate()?;
+
+    user.require_permission(Permission::AssignTechniques)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::NotFound.into());
+    }
+
+    let removed = remove_techniques_from_student(db, id, &request.student_technique_ids).await?;
+    Ok(Json(RemoveTechniquesResponse { removed }))
 }
 
-impl From<Tag> for TagResponse {
-    fn from(tag: Tag) -> Self {
-        Self {
-            id: tag.id,
-            name: tag.name,
-        }
-    }
-}
-
-#[get("/tags")]
-pub async fn api_get_all_tags(
-    _user: User,
-    db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<TagsResponse>> {
-    let tags = get_all_tags(db).await?;
-    Ok(Json(TagsResponse { tags }))
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct CreateTechniqueRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    name: String,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(
+        length(
+            min = 1,
+            code = "description.required",
+            message = "Description cannot be empty"
+        ),
+        custom(function = "validate_description", use_context)
+    )]
+    description: String,
+    collection_id: Option<i64>,
+    /// Create even if the library already has techniques with similar names.
+    #[serde(default)]
+    force: bool,
 }
 
-#[get("/technique/<id>/tags")]
-pub async fn api_get_technique_tags(
-    id: i64,
-    _user: User,
-    db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<TagsResponse>> {
-    let tags = get_tags_for_technique(db, id).await?;
-    Ok(Json(TagsResponse { tags }))
-}
+/// 409 naming the existing techniques `name` looks like, unless there are
+/// none. The candidates ride along as a `candidates` param so the SPA can
+/// offer them, or resend with `force: true`.
+async fn reject_likely_duplicate(db: &Pool<Sqlite>, name: &str) -> ApiResult<()> {
+    let candidates = find_similar_techniques(db, name, 5).await?;
+    if candidates.is_empty() {
+        return Ok(());
+    }
 
-#[derive(Deserialize, Validate)]
-pub struct CreateTagRequest {
-    #[validate(length(
-        min = 1,
-        max = 50,
-        message = "Tag name must be between 1 and 50 characters"
-    ))]
-    name: String,
+    let names = candidates
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut error = ValidationError::new("technique.duplicate")
+        .with_message(format!("Similar techniques already exist: {}", names).into());
+    error.add_param("names".into(), &names);
+    error.add_param("candidates".into(), &candidates);
+    let mut errors = ValidationErrors::new();
+    errors.add("name", error);
+    Err(ApiError::Conflict(errors))
 }
 
-#[post("/tags", data = "<tag>")]
-pub async fn api_create_tag(
-    tag: Json<CreateTagRequest>,
+#[post("/student/<student_id>/create_technique", data = "<request>")]
+pub async fn api_create_and_assign_technique(
+    student_id: UserId,
+    request: Json<CreateTechniqueRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    user.require_permission(Permission::ManageTags)?;
+    request.validate_with_args(limits)?;
+    user.require_all_permissions(&[Permission::CreateTechniques, Permission::AssignTechniques])?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
+    }
+    if !request.force {
+        reject_likely_duplicate(db, &request.name).await?;
+    }
 
-    create_tag(db, &tag.name).await?;
+    create_and_assign_technique(
+        db,
+        user.id,
+        student_id,
+        &request.name,
+        &request.description,
+        request.collection_id,
+    )
+    .await?;
 
     Ok(Status::Ok)
 }
 
-#[delete("/tags/<id>")]
-pub async fn api_delete_tag(id: i64, user: User, db: &State<Pool<Sqlite>>) -> ApiResult<Status> {
-    user.require_permission(Permission::ManageTags)?;
-    delete_tag(db, id).await?;
-    Ok(Status::Ok)
+/// The signed-in user, plus the badges they have earned and everything their
+/// role lets them do. The SPA shows or hides features by `permissions`
+/// rather than by role, so the two can't drift apart.
+#[derive(Serialize, Deserialize)]
+pub struct MeResponse {
+    #[serde(flatten)]
+    pub user: UserData,
+    pub badges: Vec<UserBadge>,
+    pub permissions: Vec<Permission>,
 }
 
-#[derive(Deserialize)]
-pub struct TagTechniqueRequest {
-    technique_id: i64,
-    tag_id: i64,
+#[get("/me")]
+pub async fn api_me(user: User, db: &State<Pool<Sqlite>>) -> ApiResult<Json<MeResponse>> {
+    let badges = get_user_badges(db.inner(), user.id).await?;
+    let permissions = user.role.sorted_permissions();
+    Ok(Json(MeResponse { user: UserData::from(user), badges, permissions }))
 }
 
-#[post("/technique/tag", data = "<request>")]
-pub async fn api_add_tag_to_technique(
-    request: Json<TagTechniqueRequest>,
+/// For write paths that can earn a badge. A failure is only logged: the
+/// `badge_awards` job picks up anything missed here.
+async fn award_badges_quietly(conn: &mut SqliteConnection, student_id: UserId) {
+    if let Err(e) = award_badges(conn, Some(student_id)).await {
+        warn!(student_id = %student_id, error = %e, "Failed to award badges");
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LibraryStatsResponse {
+    pub total_techniques: i64,
+}
+
+#[get("/library/stats")]
+pub async fn api_library_stats(
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::ManageTags)?;
-    add_tag_to_technique(db, request.technique_id, request.tag_id).await?;
-    Ok(Status::Ok)
+) -> ApiResult<Json<LibraryStatsResponse>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+
+    let total_techniques = count_techniques(db.inner()).await?;
+
+    Ok(Json(LibraryStatsResponse { total_techniques }))
 }
 
-#[delete("/technique/<technique_id>/tag/<tag_id>")]
-pub async fn api_remove_tag_from_technique(
-    technique_id: i64,
-    tag_id: i64,
+#[get("/techniques")]
+pub async fn api_list_library_techniques(
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::ManageTags)?;
-    remove_tag_from_technique(db, technique_id, tag_id).await?;
-    Ok(Status::Ok)
+) -> ApiResult<Json<Vec<crate::db::LibraryTechniqueRow>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let rows = crate::db::list_library_techniques(db).await?;
+    Ok(Json(rows))
 }
 
-#[get("/admin/users")]
-pub async fn api_get_all_users(
+/// Most results a technique search returns; the picker shows the top few.
+const TECHNIQUE_SEARCH_LIMIT: i64 = 50;
+
+/// Library techniques matching every word of `q` by name, description, tag
+/// or alias, best match first. Words match as prefixes, so "arm" finds
+/// "Armbar". A missing or blank `q` matches nothing.
+#[get("/techniques/search?<q>")]
+pub async fn api_search_techniques(
+    q: Option<&str>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<UserData>>> {
-    user.require_permission(Permission::EditUserRoles)?;
-
-    let users = get_all_users(db).await?;
+) -> ApiResult<Json<Vec<Technique>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let query = q.unwrap_or_default();
+    let techniques = crate::db::search_techniques(db, query, TECHNIQUE_SEARCH_LIMIT).await?;
+    Ok(Json(techniques))
+}
 
-    let user_responses: Vec<UserData> = users.into_iter().map(UserData::from).collect();
+#[get("/techniques/<id>/stats")]
+pub async fn api_library_technique_stats(
+    id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<crate::db::LibraryTechniqueStats>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let stats = crate::db::library_technique_stats(db, id).await?;
+    Ok(Json(stats))
+}
 
-    Ok(Json(user_responses))
+#[get("/me", rank = 2)]
+pub async fn api_me_unauthorized() -> Status {
+    Status::Unauthorized
 }
 
-// ---- Invite / claim flow ----
+#[post("/logout")]
+pub async fn api_logout(cookies: &CookieJar<'_>, db: &State<Pool<Sqlite>>) -> Redirect {
+    let token = cookies
+        .get_private("session_token")
+        .map(|cookie| cookie.value().to_string());
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct InviteUserRequest {
-    #[validate(length(min = 1, max = 100, message = "Display name is required"))]
-    display_name: String,
-    role: String,
+    if let Some(token) = token {
+        let _ = invalidate_session(db.inner(), &token).await;
+    }
+
+    cookies.remove_private(rocket::http::Cookie::build("session_token"));
+    cookies.remove_private(rocket::http::Cookie::build("user_id"));
+    cookies.remove_private(rocket::http::Cookie::build("logged_in"));
+    cookies.remove_private(rocket::http::Cookie::build("session_timestamp"));
+    cookies.remove_private(rocket::http::Cookie::build("user_role"));
+
+    Redirect::to("/")
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct InviteResponse {
-    pub user_id: i64,
-    pub token: String,
-    pub claim_path: String,
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct ProfileUpdateRequest {
+    /// Missing leaves the name alone; `null` clears it so the username shows.
+    #[serde(default, deserialize_with = "deserialize_nullable_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_optional_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: Option<String>,
 }
 
-/// Create a stub user and an invite token. Coach copies the claim URL and
-/// shares it with the student.
-#[post("/admin/invite_user", data = "<body>")]
-pub async fn api_invite_user(
-    body: Json<InviteUserRequest>,
+#[put("/profile", data = "<profile>")]
+pub async fn api_update_profile(
+    profile: Json<ProfileUpdateRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<InviteResponse>> {
-    body.validate()?;
-    user.require_permission(Permission::RegisterUsers)?;
+) -> ApiResult<Status> {
+    profile.validate_with_args(limits)?;
 
-    if matches!(body.role.as_str(), "admin") {
-        user.require_permission(Permission::EditUserRoles)?;
-    } else if !matches!(body.role.as_str(), "student" | "coach") {
-        return Err(Status::BadRequest.into());
+    if let Some(new_username) = profile.username.as_deref() {
+        if new_username != user.username {
+            // Field-level uniqueness check so the frontend can highlight the
+            // username input. `update_username` does its own check, but its
+            // error type collapses to a generic 500 here.
+            if let Some(other) = find_user_by_username(db.inner(), new_username).await? {
+                if other.id != user.id {
+                    let mut errors = validator::ValidationErrors::new();
+                    let mut err = validator::ValidationError::new("username.taken");
+                    err.message = Some("That username is already taken".into());
+                    errors.add("username", err);
+                    return Err(errors.into());
+                }
+            }
+            update_username(db, user.id, new_username).await?;
+        }
     }
 
-    let user_id = create_user_stub(db, &body.display_name, None, &body.role).await?;
-    let token = create_invite_token(db, user_id).await?;
-    let claim_path = format!("/invite/{}", token);
+    if let Some(display_name) = &profile.display_name {
+        update_user_display_name(db.inner(), user.id, display_name.as_deref()).await?;
+    }
 
-    Ok(Json(InviteResponse {
-        user_id,
-        token,
-        claim_path,
-    }))
+    Ok(Status::Ok)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct InviteInfoResponse {
-    pub display_name: String,
-    pub email: Option<String>,
-    pub role: String,
+/// `null` clears the preference.
+#[derive(Deserialize, Validate)]
+pub struct TimezoneUpdateRequest {
+    #[validate(custom(function = "validate_timezone"))]
+    timezone: Option<String>,
 }
 
-/// Public (no auth) endpoint to fetch info about an invite. Returns 410 Gone
-/// if the token has been used, expired, or doesn't exist.
-#[get("/invite/<token>")]
-pub async fn api_get_invite(
-    token: String,
+#[put("/profile/timezone", data = "<body>")]
+pub async fn api_update_timezone(
+    body: Json<TimezoneUpdateRequest>,
+    user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<InviteInfoResponse>> {
-    let invite = find_valid_invite_token(db, &token)
-        .await?
-        .ok_or_else(|| ApiError::from(Status { code: 410 }))?;
-    let stub = get_user(db, invite.user_id).await?;
+) -> ApiResult<Status> {
+    body.validate()?;
+    update_user_timezone(db.inner(), user.id, body.timezone.as_deref()).await?;
+    Ok(Status::Ok)
+}
 
-    Ok(Json(InviteInfoResponse {
-        display_name: stub.display_name,
-        email: stub.email,
-        role: stub.role.to_string(),
-    }))
+#[get("/me/preferences")]
+pub async fn api_get_preferences(
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<serde_json::Map<String, serde_json::Value>>> {
+    Ok(Json(get_user_preferences(db.inner(), user.id).await?))
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct ClaimInviteRequest {
-    #[validate(
-        length(
-            min = 3,
-            max = 50,
-            message = "Username must be between 3 and 50 characters"
-        ),
-        does_not_contain(pattern = " ", message = "Username cannot contain spaces")
-    )]
-    username: String,
-    #[validate(length(min = 5, message = "Password must be at least 5 characters"))]
-    password: String,
+/// Any JSON object; keys and values are up to the SPA. A body that is not an
+/// object fails to parse and comes back as `request.invalid_body`.
+#[derive(Deserialize, Validate)]
+#[serde(transparent)]
+#[validate(context = ValidationConfig)]
+pub struct PreferencesUpdateRequest {
+    #[validate(custom(function = "validate_preferences", use_context))]
+    preferences: serde_json::Map<String, serde_json::Value>,
 }
 
-/// Public endpoint to claim an invite. On success, establishes a session
-/// cookie so the user lands logged in.
-#[post("/invite/<token>/claim", data = "<body>")]
-pub async fn api_claim_invite(
-    token: String,
-    body: Json<ClaimInviteRequest>,
-    cookies: &rocket::http::CookieJar<'_>,
+/// Replaces the stored object and echoes it back.
+#[put("/me/preferences", data = "<body>")]
+pub async fn api_update_preferences(
+    body: Result<Json<PreferencesUpdateRequest>, JsonError<'_>>,
+    user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<UserData>> {
-    body.validate()?;
+) -> ApiResult<Json<serde_json::Map<String, serde_json::Value>>> {
+    let body = body?.into_inner();
+    body.validate_with_args(limits)?;
+    set_user_preferences(db.inner(), user.id, &body.preferences).await?;
+    Ok(Json(body.preferences))
+}
+
+#[get("/me/notification_preferences")]
+pub async fn api_get_notification_preferences(
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<NotificationPreferences>> {
+    Ok(Json(get_notification_preferences(db.inner(), user.id).await?))
+}
 
-    let user_id = claim_invite(db, &token, &body.username, &body.password).await?;
-    let user = get_user(db, user_id).await?;
+/// Replaces all three settings and echoes them back. An unknown `digest`
+/// value fails to parse and comes back as `request.invalid_body`.
+#[put("/me/notification_preferences", data = "<body>")]
+pub async fn api_update_notification_preferences(
+    body: Result<Json<NotificationPreferences>, JsonError<'_>>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<NotificationPreferences>> {
+    let preferences = body?.into_inner();
+    set_notification_preferences(db.inner(), user.id, &preferences).await?;
+    Ok(Json(preferences))
+}
 
-    establish_session(cookies, db, &user).await?;
+#[derive(Deserialize, Validate)]
+pub struct ApiTokenRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(length(min = 1, max = 100, code = "name.required", message = "Name is required"))]
+    name: String,
+    #[validate(length(min = 1, code = "scopes.required", message = "Pick at least one scope"))]
+    scopes: Vec<TokenScope>,
+    /// Never expires when left out.
+    #[validate(range(
+        min = 1,
+        max = 365,
+        code = "expires_in_days.range",
+        message = "Expiry must be between 1 and 365 days"
+    ))]
+    expires_in_days: Option<i64>,
+}
 
-    Ok(Json(UserData::from(user)))
+#[derive(Serialize, Deserialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    /// Shown this once; only its hash is kept.
+    pub secret: String,
 }
 
-// ---- Forgot password ----
+/// The caller's personal access tokens, without the tokens themselves.
+#[get("/me/api_tokens")]
+pub async fn api_get_api_tokens(
+    user: SessionUser,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<ApiToken>>> {
+    Ok(Json(get_api_tokens(db.inner(), user.0.id).await?))
+}
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct ForgotPasswordRequest {
-    #[validate(length(min = 1, message = "Username cannot be empty"))]
-    username: String,
+/// Makes a token for scripts to send as `Authorization: Bearer <secret>`.
+/// It acts as the caller, with whatever role they have when it's used.
+/// The secret is never stored, so an `Idempotency-Key` retry makes another.
+#[post("/me/api_tokens", data = "<body>")]
+pub async fn api_create_api_token(
+    body: Result<Json<ApiTokenRequest>, JsonError<'_>>,
+    user: SessionUser,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CreatedApiToken>> {
+    let body = body?;
+    body.validate()?;
+    let user = user.0;
+    let secret = generate_api_token();
+    let expires_at = body
+        .expires_in_days
+        .map(|days| chrono::Utc::now().naive_utc() + chrono::Duration::days(days));
+    let id =
+        create_api_token(db.inner(), user.id, &body.name, &secret, &body.scopes, expires_at).await?;
+    let token = get_api_tokens(db.inner(), user.id)
+        .await?
+        .into_iter()
+        .find(|token| token.id == id)
+        .ok_or_else(|| AppError::Internal(format!("API token {} vanished", id)))?;
+    info!(user_id = %user.id, token_id = id, "API token created");
+    Ok(Json(CreatedApiToken { token, secret }))
 }
 
-/// Public endpoint. Flags the matching user's account so coaches see a
-/// reset request on their dashboard. Always returns 200 regardless of whether
-/// the username exists, so we don't leak account existence.
-#[post("/forgot_password", data = "<body>")]
-pub async fn api_request_password_reset(
-    body: Json<ForgotPasswordRequest>,
+/// Revokes one of the caller's tokens; it stops working at once.
+#[delete("/me/api_tokens/<id>")]
+pub async fn api_revoke_api_token(
+    id: i64,
+    user: SessionUser,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    body.validate()?;
-    request_password_reset(db, &body.username).await?;
+    revoke_api_token(db.inner(), id, user.0.id).await?;
     Ok(Status::Ok)
 }
 
-// ---- Self-register + approval ----
+#[derive(Serialize, Deserialize)]
+pub struct NotificationsResponse {
+    /// Across all pages, for the badge.
+    pub unread: i64,
+    pub notifications: Paginated<Notification>,
+}
 
-#[derive(Deserialize, Validate, Clone)]
+/// The caller's own notifications, newest first, a page at a time.
+#[get("/notifications?<page>&<per_page>")]
+pub async fn api_get_notifications(
+    page: Option<i64>,
+    per_page: Option<i64>,
+    user: User,
+    uri: &Origin<'_>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<NotificationsResponse>> {
+    let (page, per_page) = page_bounds(page, per_page);
+    let (items, total) = get_notifications(db, user.id, per_page, (page - 1) * per_page).await?;
+    let unread = count_unread_notifications(db.inner(), user.id).await?;
+    Ok(Json(NotificationsResponse {
+        unread,
+        notifications: Paginated::new(items, total, page, per_page, uri),
+    }))
+}
+
+#[post("/notifications/<id>/read")]
+pub async fn api_mark_notification_read(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    mark_notification_read(db.inner(), id, user.id).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct PasswordChangeRequest {
+    #[validate(length(
+        min = 1,
+        code = "password.current_required",
+        message = "Current password cannot be empty"
+    ))]
+    current_password: String,
+    #[validate(custom(function = "validate_password", use_context))]
+    new_password: String,
+}
+
+/// Every attempt is recorded. Once a user has given
+/// `PASSWORD_ATTEMPT_LIMIT` wrong current passwords within the window, further
+/// attempts are refused with a 429 without checking the password, so a
+/// hijacked session can't be used to guess it.
+#[post("/change-password", data = "<password>")]
+pub async fn api_change_password(
+    password: Json<PasswordChangeRequest>,
+    user: User,
+    client_ip: Option<std::net::IpAddr>,
+    limits: &State<ValidationConfig>,
+    config: &State<LiveConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    password.validate_with_args(limits)?;
+
+    let config = config.get();
+    let client_ip = client_ip.map(|ip| ip.to_string());
+    let since = (chrono::Utc::now() - config.password_attempt_window()).naive_utc();
+    let (failures, attempt) = begin_password_attempt(
+        db.inner(),
+        user.id,
+        since,
+        config.password_attempt_limit,
+        client_ip.as_deref(),
+    )
+    .await?;
+    let Some(attempt) = attempt else {
+        warn!(
+            user_id = %user.id,
+            client_ip = client_ip.as_deref().unwrap_or("-"),
+            failures,
+            "Password change refused: too many wrong current passwords"
+        );
+        return Err(Status::TooManyRequests.into());
+    };
+
+    let is_valid = authenticate_user(db.inner(), &user.username, &password.current_password).await?;
+
+    match is_valid {
+        Some(_) => {
+            mark_password_attempt_succeeded(db.inner(), attempt).await?;
+            update_user_password(db.inner(), user.id, &password.new_password).await?;
+            info!(
+                user_id = %user.id,
+                client_ip = client_ip.as_deref().unwrap_or("-"),
+                "Password changed"
+            );
+
+            Ok(Status::Ok)
+        }
+        _ => {
+            warn!(
+                user_id = %user.id,
+                client_ip = client_ip.as_deref().unwrap_or("-"),
+                failures = failures + 1,
+                "Password change with wrong current password"
+            );
+            Err(ApiError::AppError(AppError::Authentication(
+                "Current password is incorrect".to_string(),
+            )))
+        }
+    }
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct UserRegistrationRequest {
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: String,
+    /// Optional; without one the username is shown.
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: Option<String>,
+    #[validate(custom(function = "validate_password", use_context))]
+    password: String,
+    #[validate(must_match(
+        other = "password",
+        code = "password.mismatch",
+        message = "Passwords must match"
+    ))]
+    confirm_password: String,
+    role: Role,
+}
+
+#[post("/register", data = "<registration>")]
+pub async fn api_register_user(
+    registration: Result<Json<UserRegistrationRequest>, JsonError<'_>>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    let registration = registration?;
+    registration.validate_with_args(limits)?;
+
+    let existing_user = find_user_by_username(db.inner(), &registration.username).await?;
+
+    if existing_user.is_some() {
+        return Err(ApiError::AppError(AppError::Internal(
+            "Username already exists".to_string(),
+        )));
+    }
+
+    match registration.role {
+        Role::Admin => {
+            user.require_all_permissions(&[Permission::EditUserRoles, Permission::RegisterUsers])?
+        }
+        _ => user.require_permission(Permission::RegisterUsers)?,
+    };
+
+    create_user(
+        db,
+        &registration.username,
+        &registration.password,
+        registration.role.as_str(),
+        registration.display_name.as_deref(),
+    )
+    .await?;
+
+    Ok(Status::Created)
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct UserUpdateRequest {
+    #[serde(default, deserialize_with = "deserialize_optional_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_nullable_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: Option<Option<String>>,
+    #[validate(custom(function = "validate_password", use_context))]
+    password: Option<String>,
+    archived: Option<bool>,
+    graduated: Option<bool>,
+    role: Option<Role>,
+}
+
+#[put("/admin/users/<id>", data = "<update>")]
+pub async fn api_update_user(
+    id: UserId,
+    update: Result<Json<UserUpdateRequest>, JsonError<'_>>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    let update = update?;
+    update.validate_with_args(limits)?;
+    user.require_permission(Permission::EditUserCredentials)?;
+
+    if update.role.is_some() {
+        user.require_permission(Permission::EditUserRoles)?;
+    }
+
+    if let Some(username) = &update.username {
+        update_username(db, id, username).await?;
+    }
+
+    if let Some(display_name) = &update.display_name {
+        update_user_display_name(db.inner(), id, display_name.as_deref()).await?;
+    }
+
+    if let Some(password) = &update.password {
+        update_user_password(db.inner(), id, password).await?;
+    }
+
+    if let Some(archived) = update.archived {
+        set_user_archived(db.inner(), id, archived).await?;
+    }
+
+    if let Some(graduated) = update.graduated {
+        set_user_graduated(db, id, graduated, Some(user.id)).await?;
+    }
+
+    if let Some(role) = &update.role {
+        update_user_role(db, id, role.as_str()).await?;
+    }
+
+    Ok(Status::Ok)
+}
+
+/// The students a coach looks after. Empty means the coach sees nobody.
+#[derive(Serialize, Deserialize)]
+pub struct CoachStudents {
+    pub student_ids: Vec<UserId>,
+}
+
+#[get("/admin/coaches/<id>/students")]
+pub async fn api_get_coach_students(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CoachStudents>> {
+    user.require_permission(Permission::EditUserRoles)?;
+    get_user(db.inner(), id).await?;
+    let student_ids = get_coach_student_ids(db.inner(), id).await?;
+    Ok(Json(CoachStudents { student_ids }))
+}
+
+/// Replaces the coach's students. Only coaches can be scoped and only
+/// students assigned (400 otherwise), since admins see everyone anyway.
+#[put("/admin/coaches/<id>/students", data = "<body>")]
+pub async fn api_set_coach_students(
+    id: UserId,
+    body: Json<CoachStudents>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::EditUserRoles)?;
+    if get_user(db.inner(), id).await?.role != Role::Coach {
+        return Err(Status::BadRequest.into());
+    }
+    for student_id in &body.student_ids {
+        if get_user(db.inner(), *student_id).await?.role != Role::Student {
+            return Err(Status::BadRequest.into());
+        }
+    }
+    replace_coach_students(db, id, &body.student_ids, user.id).await?;
+    Ok(Status::Ok)
+}
+
+/// Mark a student_technique row as seen by the current viewer, clearing the
+/// "unseen activity" dot for them. Used by the row-expand interaction.
+#[post("/student_technique/<id>/mark_seen")]
+pub async fn api_mark_student_technique_seen(
+    id: StudentTechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    let st = get_student_technique(db, id, user.id).await?;
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    mark_student_technique_seen(db.inner(), id, user.id).await?;
+    Ok(Status::NoContent)
+}
+
+/// Student-only: flag one of their own techniques as ready for a coach to
+/// look at. Cleared when a coach next changes its status.
+#[post("/student_technique/<id>/request_review")]
+pub async fn api_request_review(
+    id: StudentTechniqueId,
+    user: User,
+    feed: &State<ActivityFeed>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    let st = get_student_technique(db, id, user.id).await?;
+    if user.id != st.student_id {
+        return Err(Status::Forbidden.into());
+    }
+    if request_review(db.inner(), id).await? {
+        info!(student_technique_id = %id, "Review requested");
+        feed.publish(ActivityKind::ReviewRequested, user.id, id);
+    }
+    Ok(Status::NoContent)
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GraduateRequest {
+    graduated: bool,
+}
+
+/// Coach-accessible endpoint to graduate / un-graduate a student.
+/// Distinct from `/admin/users/<id>` which is admin-only.
+#[post("/student/<id>/graduate", data = "<body>")]
+pub async fn api_set_student_graduated(
+    id: UserId,
+    body: Json<GraduateRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::NotFound.into());
+    }
+
+    let target = get_user(db.inner(), id).await?;
+    if !matches!(target.role, crate::auth::Role::Student) {
+        return Err(Status::BadRequest.into());
+    }
+
+    set_user_graduated(db, id, body.graduated, Some(user.id)).await?;
+    Ok(Status::Ok)
+}
+
+#[get("/health")]
+pub fn health() -> &'static str {
+    "OK"
+}
+
+// ---- Public syllabus ----
+
+#[derive(Responder)]
+pub struct PublicSyllabusResponse {
+    body: Json<PublicSyllabus>,
+    cors: Header<'static>,
+    cache: Header<'static>,
+}
+
+/// The technique library grouped into curricula, for gyms to embed on their
+/// own website. No sign-in, so it is off (404) unless an admin turns on
+/// `public_sharing`. Any origin may fetch it, and caches may keep it briefly.
+#[get("/public/syllabus")]
+pub async fn api_public_syllabus(
+    flags: Flags,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<PublicSyllabusResponse> {
+    if !flags.is_enabled(Flag::PublicSharing) {
+        return Err(Status::NotFound.into());
+    }
+    let syllabus = get_public_syllabus(db).await?;
+    Ok(PublicSyllabusResponse {
+        body: Json(syllabus),
+        cors: Header::new("Access-Control-Allow-Origin", "*"),
+        cache: Header::new("Cache-Control", "public, max-age=300"),
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagsResponse {
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagResponse {
+    pub id: i64,
+    pub name: String,
+    pub category: Option<String>,
+    pub color: Option<String>,
+}
+
+impl From<Tag> for TagResponse {
+    fn from(tag: Tag) -> Self {
+        Self {
+            id: tag.id,
+            name: tag.name,
+            category: tag.category,
+            color: tag.color,
+        }
+    }
+}
+
+#[get("/tags?<include_archived>")]
+pub async fn api_get_all_tags(
+    include_archived: Option<bool>,
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<TagsResponse>> {
+    let tags = get_all_tags(db.inner(), include_archived.unwrap_or(false)).await?;
+    Ok(Json(TagsResponse { tags }))
+}
+
+#[get("/technique/<id>/tags")]
+pub async fn api_get_technique_tags(
+    id: TechniqueId,
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<TagsResponse>> {
+    let tags = get_tags_for_technique(db.inner(), id).await?;
+    Ok(Json(TagsResponse { tags }))
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct CreateTagRequest {
+    #[serde(deserialize_with = "deserialize_tag_name")]
+    #[validate(custom(function = "validate_tag_name", use_context))]
+    name: String,
+    /// On update, missing leaves the category alone and `null` (or blank)
+    /// clears it. Likewise `color`.
+    #[serde(default, deserialize_with = "deserialize_nullable_plain_text")]
+    #[validate(length(max = 50, code = "category.too_long", message = "Category is too long"))]
+    category: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    #[validate(custom(function = "validate_color"))]
+    color: Option<Option<String>>,
+    /// On update, missing leaves the tag where it is and `null` makes it
+    /// top-level.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    parent_id: Option<Option<TagId>>,
+}
+
+impl CreateTagRequest {
+    fn category(&self) -> Option<Option<&str>> {
+        self.category
+            .as_ref()
+            .map(|category| category.as_deref().map(str::trim).filter(|c| !c.is_empty()))
+    }
+
+    fn color(&self) -> Option<Option<&str>> {
+        self.color.as_ref().map(Option::as_deref)
+    }
+}
+
+#[post("/tags", data = "<tag>")]
+pub async fn api_create_tag(
+    tag: Json<CreateTagRequest>,
+    user: User,
+    tx: Tx,
+    limits: &State<ValidationConfig>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageTags)?;
+    tag.validate_with_args(limits)?;
+
+    // One transaction (see `crate::transaction`), so an unknown parent
+    // doesn't leave the tag created without it.
+    let mut conn = tx.conn().await?;
+    if get_tag_by_name(&mut *conn, &tag.name).await?.is_some() {
+        return Err(Status::Conflict.into());
+    }
+    let id = create_tag(&mut *conn, &tag.name).await?;
+    if tag.category.is_some() || tag.color.is_some() {
+        update_tag_style(&mut *conn, id, tag.category(), tag.color()).await?;
+    }
+    if let Some(Some(parent_id)) = tag.parent_id {
+        set_tag_parent(&mut *conn, id, Some(parent_id)).await?;
+    }
+
+    Ok(Status::Ok)
+}
+
+/// Renames a tag and sets its category, colour and parent. 409 if another
+/// tag has the name; 422 if the parent is the tag itself or nested under it.
+#[put("/tags/<id>", data = "<tag>")]
+pub async fn api_rename_tag(
+    id: TagId,
+    tag: Json<CreateTagRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageTags)?;
+    tag.validate_with_args(limits)?;
+
+    if let Some(existing) = get_tag_by_name(db.inner(), &tag.name).await? {
+        if existing.id != id.0 {
+            return Err(Status::Conflict.into());
+        }
+    }
+    if let Some(parent_id) = tag.parent_id {
+        if let Some(parent_id) = parent_id
+            && get_tag_subtree_ids(db.inner(), id).await?.contains(&parent_id)
+        {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "parent_id",
+                ValidationError::new("parent_id.cycle")
+                    .with_message("A tag cannot be nested under itself".into()),
+            );
+            return Err(ApiError::Validation(errors));
+        }
+        set_tag_parent(db.inner(), id, parent_id).await?;
+    }
+    rename_tag(db.inner(), id, &tag.name).await?;
+    update_tag_style(db.inner(), id, tag.category(), tag.color()).await?;
+
+    Ok(Status::Ok)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagTreeResponse {
+    pub tags: Vec<TagNode>,
+}
+
+#[get("/tags/tree?<include_archived>")]
+pub async fn api_get_tag_tree(
+    include_archived: Option<bool>,
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<TagTreeResponse>> {
+    let tags = get_tag_tree(db.inner(), include_archived.unwrap_or(false)).await?;
+    Ok(Json(TagTreeResponse { tags }))
+}
+
+#[delete("/tags/<id>")]
+pub async fn api_delete_tag(
+    id: TagId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageTags)?;
+    delete_tag(db.inner(), id).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize)]
+pub struct TagArchivedRequest {
+    archived: bool,
+}
+
+/// Archiving hides a tag from pickers but keeps it on its techniques.
+#[put("/tags/<id>/archived", data = "<request>")]
+pub async fn api_set_tag_archived(
+    id: TagId,
+    request: Json<TagArchivedRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ArchiveTags)?;
+    set_tag_archived(db.inner(), id, request.archived).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize)]
+pub struct TagTechniqueRequest {
+    technique_id: TechniqueId,
+    tag_id: TagId,
+}
+
+#[post("/technique/tag", data = "<request>")]
+pub async fn api_add_tag_to_technique(
+    request: Json<TagTechniqueRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageTags)?;
+    add_tag_to_technique(db.inner(), request.technique_id, request.tag_id).await?;
+    Ok(Status::Ok)
+}
+
+#[delete("/technique/<technique_id>/tag/<tag_id>")]
+pub async fn api_remove_tag_from_technique(
+    technique_id: TechniqueId,
+    tag_id: TagId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageTags)?;
+    remove_tag_from_technique(db.inner(), technique_id, tag_id).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(FromForm)]
+pub struct CoachReportQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CoachReportResponse {
+    pub from: chrono::NaiveDate,
+    pub to: chrono::NaiveDate,
+    pub coaches: Vec<CoachActivity>,
+}
+
+/// Per-coach workload between `from` and `to` (YYYY-MM-DD, inclusive). The
+/// period defaults to the 30 days up to today.
+#[get("/admin/coach_report?<params..>")]
+pub async fn api_coach_report(
+    params: CoachReportQuery,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CoachReportResponse>> {
+    user.require_permission(Permission::ViewCoachReport)?;
+    let parse = |value: Option<&str>, default: chrono::NaiveDate| match value {
+        Some(s) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| {
+            warn!(raw_value = s, error = %e, "rejected coach report query: not YYYY-MM-DD");
+            ApiError::from(Status::BadRequest)
+        }),
+        None => Ok(default),
+    };
+    let today = chrono::Utc::now().date_naive();
+    let to = parse(params.to.as_deref(), today)?;
+    let from = parse(params.from.as_deref(), to - chrono::Duration::days(29))?;
+    if from > to {
+        return Err(Status::BadRequest.into());
+    }
+
+    let coaches = get_coach_report(db.inner(), from, to).await?;
+    Ok(Json(CoachReportResponse { from, to, coaches }))
+}
+
+/// One page of a longer list. `page` counts from 1; `next` and `prev` are
+/// the request's own URL with the page changed, `None` at either end.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+/// `(page, per_page)` with defaults applied and `per_page` capped.
+fn page_bounds(page: Option<i64>, per_page: Option<i64>) -> (i64, i64) {
+    (
+        page.unwrap_or(1).max(1),
+        per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE),
+    )
+}
+
+/// `uri` with `page` and `per_page` set, keeping its other parameters.
+fn page_link(uri: &Origin<'_>, page: i64, per_page: i64) -> String {
+    let mut query: Vec<String> = uri
+        .query()
+        .map(|q| q.raw_segments().map(|segment| segment.as_str().to_string()).collect())
+        .unwrap_or_default();
+    query.retain(|segment| {
+        let key = segment.split('=').next().unwrap_or_default();
+        key != "page" && key != "per_page"
+    });
+    query.push(format!("page={}", page));
+    query.push(format!("per_page={}", per_page));
+    format!("{}?{}", uri.path(), query.join("&"))
+}
+
+impl<T> Paginated<T> {
+    /// Page `page` of `total` items, linked to its neighbours.
+    fn new(items: Vec<T>, total: i64, page: i64, per_page: i64, uri: &Origin<'_>) -> Self {
+        Paginated {
+            next: (page * per_page < total).then(|| page_link(uri, page + 1, per_page)),
+            prev: (page > 1).then(|| page_link(uri, page - 1, per_page)),
+            items,
+            total,
+            page,
+            per_page,
+        }
+    }
+
+    /// A page cut from a list fetched whole. A request with neither `page`
+    /// nor `per_page` gets the whole list as its only page, which is what
+    /// the SPA still asks for.
+    fn from_all(all: Vec<T>, page: Option<i64>, per_page: Option<i64>, uri: &Origin<'_>) -> Self {
+        let total = all.len() as i64;
+        if page.is_none() && per_page.is_none() {
+            return Paginated::new(all, total, 1, total.max(1), uri);
+        }
+        let (page, per_page) = page_bounds(page, per_page);
+        let items = all
+            .into_iter()
+            .skip(((page - 1) * per_page) as usize)
+            .take(per_page as usize)
+            .collect();
+        Paginated::new(items, total, page, per_page, uri)
+    }
+}
+
+#[derive(FromForm)]
+pub struct AdminUsersQuery {
+    role: Option<String>,
+    archived: Option<bool>,
+    search: Option<String>,
+    /// `name` (the default), `username` or `role`.
+    sort: Option<String>,
+    #[field(default = false)]
+    desc: bool,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+/// Users matching the filters, a page at a time. An unknown role or sort
+/// is a 400; no matches is an empty page.
+#[get("/admin/users?<params..>")]
+pub async fn api_get_all_users(
+    params: AdminUsersQuery,
+    user: User,
+    uri: &Origin<'_>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Paginated<UserData>>> {
+    user.require_permission(Permission::EditUserRoles)?;
+
+    let role = match params.role.as_deref() {
+        Some(role) => Some(role.parse::<Role>().map_err(|_| Status::BadRequest)?),
+        None => None,
+    };
+    let sort = match params.sort.as_deref() {
+        Some(sort) => UserSort::parse(sort).ok_or(Status::BadRequest)?,
+        None => UserSort::default(),
+    };
+    let filter = UserListFilter {
+        role,
+        archived: params.archived,
+        search: params.search.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        sort,
+        descending: params.desc,
+    };
+    let (page, per_page) = page_bounds(params.page, params.per_page);
+
+    let (users, total) = get_all_users(db, &filter, per_page, (page - 1) * per_page).await?;
+
+    let items = users.into_iter().map(UserData::from).collect();
+    Ok(Json(Paginated::new(items, total, page, per_page, uri)))
+}
+
+// ---- Feature flags ----
+
+#[derive(Serialize, Deserialize)]
+pub struct FeatureFlagResponse {
+    pub key: String,
+    pub enabled: bool,
+    pub default: bool,
+    pub overridden: bool,
+    pub description: String,
+}
+
+#[get("/admin/feature_flags")]
+pub async fn api_get_feature_flags(
+    user: User,
+    flags: Flags,
+) -> ApiResult<Json<Vec<FeatureFlagResponse>>> {
+    user.require_permission(Permission::ManageFeatureFlags)?;
+
+    let response = Flag::ALL
+        .into_iter()
+        .map(|flag| FeatureFlagResponse {
+            key: flag.key().to_string(),
+            enabled: flags.is_enabled(flag),
+            default: flag.default_enabled(),
+            overridden: flags.is_overridden(flag),
+            description: flag.description().to_string(),
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct FeatureFlagUpdateRequest {
+    enabled: bool,
+}
+
+#[put("/admin/feature_flags/<key>", data = "<body>")]
+pub async fn api_set_feature_flag(
+    key: &str,
+    body: Json<FeatureFlagUpdateRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::ManageFeatureFlags)?;
+
+    let flag = Flag::from_key(key)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown feature flag '{}'", key)))?;
+    set_feature_flag(db.inner(), flag.key(), body.enabled, user.id).await?;
+
+    Ok(Status::Ok)
+}
+
+// ---- Spreadsheet import ----
+
+#[derive(Deserialize)]
+pub struct SpreadsheetImportRequest {
+    /// The sheet exported as CSV, header row first.
+    csv: String,
+    #[serde(default)]
+    columns: SheetColumns,
+    #[serde(default)]
+    dry_run: bool,
+    /// Run the import as a background job and answer 202 with its id
+    /// instead of the report, which is then the job's result.
+    #[serde(default)]
+    background: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JobStartedResponse {
+    pub job_id: i64,
+}
+
+/// An import's report, or the job that will produce it.
+#[derive(Responder)]
+pub enum ImportResponse<T> {
+    Finished(Json<T>),
+    #[response(status = 202)]
+    Started(Json<JobStartedResponse>),
+}
+
+/// Import a syllabus spreadsheet (see `db::spreadsheet`). A file that can't
+/// be read at all is a 422 on `csv`; row-level problems come back in the
+/// report alongside whatever did import.
+#[post("/admin/import/spreadsheet", data = "<body>")]
+pub async fn api_import_spreadsheet(
+    body: Json<SpreadsheetImportRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<ImportResponse<SheetImportReport>> {
+    user.require_permission(Permission::ImportSyllabus)?;
+
+    let rows = parse_spreadsheet(&body.csv, &body.columns).map_err(|message| {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "csv",
+            ValidationError::new("spreadsheet.unreadable").with_message(message.into()),
+        );
+        ApiError::Validation(errors)
+    })?;
+    if body.background {
+        let (pool, limits, dry_run) = (db.inner().clone(), limits.inner().clone(), body.dry_run);
+        let job_id = spawn_background_job(db, "spreadsheet_import", user.id, async move {
+            let report = import_spreadsheet(&pool, &rows, user.id, &limits, dry_run).await?;
+            serde_json::to_value(report).map_err(|e| AppError::Internal(e.to_string()))
+        })
+        .await?;
+        return Ok(ImportResponse::Started(Json(JobStartedResponse { job_id })));
+    }
+    let report = import_spreadsheet(db, &rows, user.id, limits, body.dry_run).await?;
+    Ok(ImportResponse::Finished(Json(report)))
+}
+
+// ---- Membership sync ----
+
+#[derive(Deserialize)]
+pub struct MembershipWebhookRequest {
+    #[serde(flatten)]
+    member: MemberRef,
+    status: MembershipStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MembershipWebhookResponse {
+    pub user_id: UserId,
+}
+
+/// Called by the billing system whenever a student's membership changes
+/// (see `db::memberships`). 404 when no student matches, 409 on `email`
+/// when several do.
+#[post("/integrations/membership", data = "<body>")]
+pub async fn api_membership_webhook(
+    _caller: BillingWebhook,
+    body: Json<MembershipWebhookRequest>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<MembershipWebhookResponse>> {
+    let mut conn = db.acquire().await.map_err(AppError::from)?;
+    match set_membership_status(&mut conn, &body.member, body.status).await? {
+        MembershipOutcome::Updated(user_id) => Ok(Json(MembershipWebhookResponse { user_id })),
+        MembershipOutcome::NoMatch => {
+            Err(AppError::NotFound("No student matches this member".to_string()).into())
+        }
+        MembershipOutcome::Ambiguous => {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "email",
+                ValidationError::new("membership.ambiguous")
+                    .with_message("More than one student has this email".into()),
+            );
+            Err(ApiError::Conflict(errors))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MembershipImportRequest {
+    /// The billing system's export as CSV, header row first.
+    csv: String,
+    #[serde(default)]
+    dry_run: bool,
+    /// As for the spreadsheet import.
+    #[serde(default)]
+    background: bool,
+}
+
+/// Bulk version of the webhook, for billing systems that can only export.
+/// Same error shape as the spreadsheet import.
+#[post("/admin/import/memberships", data = "<body>")]
+pub async fn api_import_memberships(
+    body: Json<MembershipImportRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<ImportResponse<MembershipImportReport>> {
+    user.require_permission(Permission::ManageMemberships)?;
+
+    let rows = parse_membership_csv(&body.csv).map_err(|message| {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "csv",
+            ValidationError::new("memberships.unreadable").with_message(message.into()),
+        );
+        ApiError::Validation(errors)
+    })?;
+    if body.background {
+        let (pool, dry_run) = (db.inner().clone(), body.dry_run);
+        let job_id = spawn_background_job(db, "membership_import", user.id, async move {
+            let report = import_memberships(&pool, &rows, dry_run).await?;
+            serde_json::to_value(report).map_err(|e| AppError::Internal(e.to_string()))
+        })
+        .await?;
+        return Ok(ImportResponse::Started(Json(JobStartedResponse { job_id })));
+    }
+    let report = import_memberships(db, &rows, body.dry_run).await?;
+    Ok(ImportResponse::Finished(Json(report)))
+}
+
+/// A background job started by one of the caller's requests. Admins can see
+/// anyone's; to everyone else another user's job is not found.
+#[get("/jobs/<id>")]
+pub async fn api_get_job(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<BackgroundJob>> {
+    let job = get_background_job(db.inner(), id).await?;
+    if job.requested_by_id != Some(user.id) && !user.has_permission(Permission::ViewSystemStatus) {
+        return Err(AppError::NotFound(format!("Job {} not found", id)).into());
+    }
+    Ok(Json(job))
+}
+
+// ---- Runtime config ----
+
+/// Re-read the env files and apply the settings that can change without a
+/// restart (see `config::RELOADABLE`); same as sending the process SIGHUP.
+/// A file that fails validation is a 422 on `config` and changes nothing.
+#[post("/admin/config/reload")]
+pub async fn api_reload_config(
+    user: User,
+    config: &State<LiveConfig>,
+) -> ApiResult<Json<ReloadReport>> {
+    user.require_permission(Permission::ManageConfig)?;
+
+    let report = config.reload().map_err(|e| {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "config",
+            ValidationError::new("config.invalid").with_message(e.to_string().into()),
+        );
+        ApiError::Validation(errors)
+    })?;
+    Ok(Json(report))
+}
+
+// ---- Invite / claim flow ----
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct InviteUserRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: String,
+    role: Role,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InviteResponse {
+    pub user_id: UserId,
+    pub token: String,
+    pub claim_path: String,
+}
+
+/// Create a stub user and an invite token. Coach copies the claim URL and
+/// shares it with the student.
+#[post("/admin/invite_user", data = "<body>")]
+pub async fn api_invite_user(
+    body: Result<Json<InviteUserRequest>, JsonError<'_>>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<InviteResponse>> {
+    let body = body?;
+    body.validate_with_args(limits)?;
+    Ok(Json(create_invitation(db, &user, &body.display_name, body.role).await?))
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct InvitationRequest {
+    role: Role,
+    /// Left out, the invitee picks one when they register.
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: Option<String>,
+}
+
+/// Invites someone with a role chosen up front. They pick their own
+/// username and password at `POST /register/invite/<token>`, through the
+/// claim link, so nobody has to set a first password for them.
+#[post("/admin/invitations", data = "<body>")]
+pub async fn api_create_invitation(
+    body: Result<Json<InvitationRequest>, JsonError<'_>>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<InviteResponse>> {
+    let body = body?;
+    body.validate_with_args(limits)?;
+    let display_name = body.display_name.as_deref().unwrap_or_default();
+    Ok(Json(create_invitation(db, &user, display_name, body.role).await?))
+}
+
+/// A stub user with `role` and a single-use token to claim it. Only those
+/// who can change roles may invite an admin.
+async fn create_invitation(
+    db: &Pool<Sqlite>,
+    user: &User,
+    display_name: &str,
+    role: Role,
+) -> ApiResult<InviteResponse> {
+    user.require_permission(Permission::RegisterUsers)?;
+    if matches!(role, Role::Admin) {
+        user.require_permission(Permission::EditUserRoles)?;
+    }
+
+    let user_id = create_user_stub(db, display_name, None, role.as_str()).await?;
+    let token = create_invite_token(db, user_id).await?;
+    let claim_path = format!("/invite/{}", token);
+    info!(user_id = %user_id, role = role.as_str(), "Invitation created");
+
+    Ok(InviteResponse { user_id, token, claim_path })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InviteInfoResponse {
+    pub display_name: String,
+    pub email: Option<String>,
+    pub role: String,
+}
+
+/// Public (no auth) endpoint to fetch info about an invite. Returns 410 Gone
+/// if the token has been used, expired, or doesn't exist.
+#[get("/invite/<token>")]
+pub async fn api_get_invite(
+    token: String,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<InviteInfoResponse>> {
+    let invite = find_valid_invite_token(db.inner(), &token)
+        .await?
+        .ok_or_else(|| ApiError::from(Status { code: 410 }))?;
+    let stub = get_user(db.inner(), invite.user_id).await?;
+
+    Ok(Json(InviteInfoResponse {
+        display_name: stub.display_name,
+        email: stub.email,
+        role: stub.role.to_string(),
+    }))
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct ClaimInviteRequest {
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: String,
+    #[validate(custom(function = "validate_password", use_context))]
+    password: String,
+}
+
+/// Public endpoint to claim an invite. On success, establishes a session
+/// cookie so the user lands logged in.
+#[post("/invite/<token>/claim", data = "<body>")]
+pub async fn api_claim_invite(
+    token: String,
+    body: Json<ClaimInviteRequest>,
+    cookies: &rocket::http::CookieJar<'_>,
+    config: &State<LiveConfig>,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<UserData>> {
+    body.validate_with_args(limits)?;
+    let user = claim_invitation(db, &token, &body.username, &body.password, None).await?;
+    establish_session(cookies, db, config, &user).await?;
+    Ok(Json(UserData::from(user)))
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct InviteRegistrationRequest {
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: String,
+    #[validate(custom(function = "validate_password", use_context))]
+    password: String,
+    /// Replaces the one the invitation was made with, if any.
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_display_name", use_context))]
+    display_name: Option<String>,
+}
+
+/// Public. Signs up through an invitation: the account gets the role it was
+/// made with, and the invitee lands logged in. 410 once the invitation is
+/// used or expired.
+#[post("/register/invite/<token>", data = "<body>")]
+pub async fn api_register_with_invite(
+    token: &str,
+    body: Json<InviteRegistrationRequest>,
+    cookies: &rocket::http::CookieJar<'_>,
+    config: &State<LiveConfig>,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<UserData>> {
+    body.validate_with_args(limits)?;
+    let display_name = body.display_name.as_deref();
+    let user = claim_invitation(db, token, &body.username, &body.password, display_name).await?;
+    establish_session(cookies, db, config, &user).await?;
+    info!(user_id = %user.id, "Registered through an invitation");
+    Ok(Json(UserData::from(user)))
+}
+
+/// Claims `token` for the invitee, with 410 for a token that's used,
+/// expired or unknown and 422 on `username` when someone else has it.
+async fn claim_invitation(
+    db: &Pool<Sqlite>,
+    token: &str,
+    username: &str,
+    password: &str,
+    display_name: Option<&str>,
+) -> ApiResult<User> {
+    let Some(invite) = find_valid_invite_token(db, token).await? else {
+        return Err(Status::Gone.into());
+    };
+    // The stub may have been made with a username, which the invitee keeps.
+    if let Some(other) = find_user_by_username(db, username).await?
+        && other.id != invite.user_id
+    {
+        let mut errors = ValidationErrors::new();
+        let mut err = ValidationError::new("username.taken");
+        err.message = Some("That username is already taken".into());
+        errors.add("username", err);
+        return Err(errors.into());
+    }
+
+    let user_id = match claim_invite(db, token, username, password, display_name).await {
+        Ok(user_id) => user_id,
+        // Another claim used the token in the meantime.
+        Err(AppError::NotFound(_)) => return Err(Status::Gone.into()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(get_user(db, user_id).await?)
+}
+
+// ---- Forgot password ----
+
+#[derive(Deserialize, Validate, Clone)]
+pub struct ForgotPasswordRequest {
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(length(min = 1, code = "username.required", message = "Username cannot be empty"))]
+    username: String,
+}
+
+/// Public endpoint. Flags the matching user's account so coaches see a
+/// reset request on their dashboard. Always returns 200 regardless of whether
+/// the username exists, so we don't leak account existence.
+#[post("/forgot_password", data = "<body>")]
+pub async fn api_request_password_reset(
+    body: Json<ForgotPasswordRequest>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    body.validate()?;
+    request_password_reset(db.inner(), &body.username).await?;
+    Ok(Status::Ok)
+}
+
+// ---- Self-register + approval ----
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
 pub struct SelfRegisterRequest {
+    #[serde(deserialize_with = "deserialize_username")]
+    #[validate(custom(function = "validate_username", use_context))]
+    username: String,
+    #[validate(custom(function = "validate_password", use_context))]
+    password: String,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_person_name", use_context))]
+    first_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_person_name", use_context))]
+    last_name: Option<String>,
+}
+
+/// Public endpoint for students to self-register. Account is created in
+/// pending state (`approved_at IS NULL`) until a coach approves it.
+#[post("/register/self", data = "<body>")]
+pub async fn api_self_register(
+    body: Json<SelfRegisterRequest>,
+    cookies: &rocket::http::CookieJar<'_>,
+    config: &State<LiveConfig>,
+    flags: Flags,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<UserData>> {
+    // Disabled looks the same as never having existed.
+    if !flags.is_enabled(Flag::SelfRegistration) {
+        return Err(Status::NotFound.into());
+    }
+    body.validate_with_args(limits)?;
+
+    let user_id = create_self_registered_user(
+        db,
+        &body.username,
+        &body.password,
+        body.first_name.as_deref(),
+        body.last_name.as_deref(),
+    )
+    .await?;
+    let user = get_user(db.inner(), user_id).await?;
+
+    // Log them in immediately. The frontend will route them to the
+    // pending-approval screen since `approved_at` is None.
+    establish_session(cookies, db, config, &user).await?;
+
+    Ok(Json(UserData::from(user)))
+}
+
+#[post("/admin/users/<id>/approve")]
+pub async fn api_approve_user(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::RegisterUsers)?;
+    approve_user(db.inner(), id).await?;
+    Ok(Status::Ok)
+}
+
+/// Admin endpoint to invalidate a user's password and generate a fresh invite
+/// token. Existing sessions for the user are terminated.
+#[post("/admin/users/<id>/reset_claim")]
+pub async fn api_reset_user_claim(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<InviteResponse>> {
+    user.require_permission(Permission::EditUserCredentials)?;
+
+    let token = reset_user_claim(db, id).await?;
+    let claim_path = format!("/invite/{}", token);
+
+    Ok(Json(InviteResponse {
+        user_id: id,
+        token,
+        claim_path,
+    }))
+}
+
+// ---- Collections / syllabuses ----
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionResponse {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub coach_id: Option<i64>,
+    pub created_at: String,
+    pub belt_level: Option<String>,
+    pub technique_count: i64,
+    pub student_count: i64,
+    pub techniques: Vec<TechniqueLibraryResponse>,
+    pub can_create_techniques: bool,
+    pub can_edit_all_techniques: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TechniqueLibraryResponse {
+    pub id: TechniqueId,
+    pub name: String,
+    pub description: String,
+    pub coach_id: UserId,
+    pub coach_name: String,
+}
+
+fn collection_to_response(c: Collection, user: &User) -> CollectionResponse {
+    CollectionResponse {
+        id: c.id,
+        name: c.name,
+        description: c.description,
+        coach_id: c.coach_id,
+        created_at: to_rfc3339_utc(c.created_at),
+        belt_level: c.belt_level,
+        technique_count: c.technique_count,
+        student_count: c.student_count,
+        techniques: c
+            .techniques
+            .into_iter()
+            .map(|t| TechniqueLibraryResponse {
+                id: t.id,
+                name: t.name,
+                description: t.description,
+                coach_id: t.coach_id,
+                coach_name: t.coach_name,
+            })
+            .collect(),
+        can_create_techniques: user.has_permission(Permission::CreateTechniques),
+        can_edit_all_techniques: user.has_permission(Permission::EditAllTechniques),
+    }
+}
+
+#[get("/collections")]
+pub async fn api_get_collections(
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<CollectionResponse>>> {
+    user.require_permission(Permission::AssignTechniques)?;
+    let collections = get_all_collections(db.inner()).await?;
+    Ok(Json(
+        collections
+            .into_iter()
+            .map(|c| collection_to_response(c, &user))
+            .collect(),
+    ))
+}
+
+#[get("/collections/<id>")]
+pub async fn api_get_collection(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CollectionResponse>> {
+    user.require_permission(Permission::AssignTechniques)?;
+    let collection = get_collection(db, id).await?;
+    Ok(Json(collection_to_response(collection, &user)))
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct CollectionUpsertRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(length(min = 1, max = 100, code = "name.required", message = "Name is required"))]
+    name: String,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_description", use_context))]
+    description: Option<String>,
+    /// The rank the curriculum is for, such as "Blue". Blank clears it.
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(length(max = 50, code = "belt_level.too_long", message = "Belt level is too long"))]
+    belt_level: Option<String>,
+}
+
+impl CollectionUpsertRequest {
+    fn belt_level(&self) -> Option<&str> {
+        self.belt_level.as_deref().map(str::trim).filter(|level| !level.is_empty())
+    }
+}
+
+#[post("/collections", data = "<body>")]
+pub async fn api_create_collection(
+    body: Json<CollectionUpsertRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CollectionResponse>> {
+    body.validate_with_args(limits)?;
+    user.require_permission(Permission::CreateTechniques)?;
+    let id = create_collection(
+        db.inner(),
+        &body.name,
+        body.description.as_deref().unwrap_or(""),
+        body.belt_level(),
+        user.id,
+    )
+    .await?;
+    let collection = get_collection(db, id).await?;
+    Ok(Json(collection_to_response(collection, &user)))
+}
+
+#[put("/collections/<id>", data = "<body>")]
+pub async fn api_update_collection(
+    id: i64,
+    body: Json<CollectionUpsertRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    body.validate_with_args(limits)?;
+    user.require_permission(Permission::CreateTechniques)?;
+    update_collection(
+        db.inner(),
+        id,
+        &body.name,
+        body.description.as_deref().unwrap_or(""),
+        body.belt_level(),
+    )
+    .await?;
+    Ok(Status::Ok)
+}
+
+#[delete("/collections/<id>")]
+pub async fn api_delete_collection(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::CreateTechniques)?;
+    delete_collection(db, id).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AddTechniquesToCollectionRequest {
+    technique_ids: Vec<TechniqueId>,
+}
+
+#[post("/collections/<id>/techniques", data = "<body>")]
+pub async fn api_add_techniques_to_collection(
+    id: i64,
+    body: Json<AddTechniquesToCollectionRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::CreateTechniques)?;
+    add_techniques_to_collection(db, id, body.technique_ids.clone()).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize)]
+pub struct ReorderCollectionRequest {
+    technique_ids: Vec<TechniqueId>,
+}
+
+/// Sets the order a curriculum's techniques are taught in. The list must
+/// name each of its techniques exactly once.
+#[put("/collections/<id>/techniques/order", data = "<body>")]
+pub async fn api_reorder_collection_techniques(
+    id: i64,
+    body: Json<ReorderCollectionRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<CollectionResponse>> {
+    user.require_permission(Permission::CreateTechniques)?;
+    let collection = get_collection(db, id).await?;
+
+    let mut current: Vec<TechniqueId> = collection.techniques.iter().map(|t| t.id).collect();
+    let mut requested = body.technique_ids.clone();
+    current.sort_by_key(|t| t.0);
+    requested.sort_by_key(|t| t.0);
+    if current != requested {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "technique_ids",
+            ValidationError::new("technique_ids.mismatch")
+                .with_message("List each technique in the curriculum exactly once".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
+
+    reorder_collection_techniques(db, id, &body.technique_ids).await?;
+    let collection = get_collection(db, id).await?;
+    Ok(Json(collection_to_response(collection, &user)))
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct CreateTechniqueInCollectionRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    name: String,
+    #[serde(deserialize_with = "deserialize_plain_text")]
     #[validate(
         length(
-            min = 3,
-            max = 50,
-            message = "Username must be between 3 and 50 characters"
+            min = 1,
+            code = "description.required",
+            message = "Description cannot be empty"
         ),
-        does_not_contain(pattern = " ", message = "Username cannot contain spaces")
+        custom(function = "validate_description", use_context)
     )]
-    username: String,
-    #[validate(length(min = 5, message = "Password must be at least 5 characters"))]
-    password: String,
-    #[validate(length(max = 50, message = "First name is too long"))]
-    first_name: Option<String>,
-    #[validate(length(max = 50, message = "Last name is too long"))]
-    last_name: Option<String>,
+    description: String,
+    /// Create even if the library already has techniques with similar names.
+    #[serde(default)]
+    force: bool,
+}
+
+/// 409 listing likely duplicates unless `force`, as when creating for a
+/// student.
+#[post("/collections/<id>/create_technique", data = "<body>")]
+pub async fn api_create_technique_in_collection(
+    id: i64,
+    body: Json<CreateTechniqueInCollectionRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<TechniqueLibraryResponse>> {
+    body.validate_with_args(limits)?;
+    user.require_permission(Permission::CreateTechniques)?;
+    if !body.force {
+        reject_likely_duplicate(db, &body.name).await?;
+    }
+    let technique_id =
+        create_technique_in_collection(db, user.id, id, &body.name, &body.description).await?;
+    let coach_name = user.effective_display_name().to_string();
+    Ok(Json(TechniqueLibraryResponse {
+        id: technique_id,
+        name: body.name.clone(),
+        description: body.description.clone(),
+        coach_id: user.id,
+        coach_name,
+    }))
+}
+
+#[delete("/collections/<id>/techniques/<technique_id>")]
+pub async fn api_remove_technique_from_collection(
+    id: i64,
+    technique_id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::CreateTechniques)?;
+    remove_technique_from_collection(db, id, technique_id).await?;
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct UpdateLibraryTechniqueRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    name: String,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(
+        length(
+            min = 1,
+            code = "description.required",
+            message = "Description cannot be empty"
+        ),
+        custom(function = "validate_description", use_context)
+    )]
+    description: String,
+    /// The base technique to group this under as a variant. Missing leaves
+    /// it as it is; `null` makes it a base technique again.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    parent_technique_id: Option<Option<TechniqueId>>,
+}
+
+/// 422 if `parent_technique_id` would nest variants more than one level
+/// deep.
+#[put("/techniques/<id>", data = "<body>")]
+pub async fn api_update_library_technique(
+    id: TechniqueId,
+    body: Json<UpdateLibraryTechniqueRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    tx: Tx,
+) -> ApiResult<Status> {
+    body.validate_with_args(limits)?;
+    user.require_permission(Permission::EditAllTechniques)?;
+    let mut conn = tx.conn().await?;
+    if let Some(parent_id) = body.parent_technique_id
+        && !set_technique_parent(&mut *conn, id, parent_id).await?
+    {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "parent_technique_id",
+            ValidationError::new("parent_technique_id.nested")
+                .with_message("Variants can only be one level deep".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
+    update_technique(&mut *conn, id, &body.name, &body.description).await?;
+    Ok(Status::Ok)
+}
+
+/// Takes a technique out of the library. Students who have it assigned keep
+/// it, and `POST /technique/<id>/restore` brings it back.
+#[delete("/technique/<id>")]
+pub async fn api_delete_technique(
+    id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::DeleteTechniques)?;
+    let mut conn = db.acquire().await.map_err(AppError::from)?;
+    delete_technique(&mut conn, id).await?;
+    info!(technique_id = %id, "Technique deleted");
+    Ok(Status::Ok)
+}
+
+#[post("/technique/<id>/restore")]
+pub async fn api_restore_technique(
+    id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::DeleteTechniques)?;
+    let mut conn = db.acquire().await.map_err(AppError::from)?;
+    restore_technique(&mut conn, id).await?;
+    info!(technique_id = %id, "Technique restored");
+    Ok(Status::Ok)
+}
+
+/// One row of `POST /techniques/bulk`: the same rules as creating a single
+/// technique, plus tag names, which are created if the library lacks them.
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct BulkTechniqueRow {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    name: String,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(
+        length(
+            min = 1,
+            code = "description.required",
+            message = "Description cannot be empty"
+        ),
+        custom(function = "validate_description", use_context)
+    )]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Most techniques one bulk request may create.
+pub const MAX_BULK_TECHNIQUES: usize = 200;
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkCreateTechniquesResponse {
+    /// In the order the rows were sent.
+    pub ids: Vec<TechniqueId>,
+}
+
+/// Files `error` under `<row>.<field>`, so a 422 says which row is wrong.
+fn add_row_error(errors: &mut ValidationErrors, row: usize, field: &str, error: ValidationError) {
+    let key = Cow::Owned(format!("{}.{}", row, field));
+    if let ValidationErrorsKind::Field(list) =
+        errors.errors_mut().entry(key).or_insert_with(|| ValidationErrorsKind::Field(vec![]))
+    {
+        list.push(error);
+    }
+}
+
+/// Creates a pasted syllabus section at once. Every row is checked before
+/// anything is written: one bad row fails the request with a 422 keyed
+/// `<row>.<field>` (rows counted from 0) and nothing is created. Unlike
+/// single creation, rows aren't checked for likely duplicates.
+#[post("/techniques/bulk", data = "<body>")]
+pub async fn api_create_techniques_bulk(
+    body: Json<Vec<BulkTechniqueRow>>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<BulkCreateTechniquesResponse>> {
+    user.require_permission(Permission::CreateTechniques)?;
+    let rows = body.into_inner();
+
+    let mut errors = ValidationErrors::new();
+    if rows.is_empty() || rows.len() > MAX_BULK_TECHNIQUES {
+        let mut error = ValidationError::new("techniques.count").with_message(
+            format!("Send between 1 and {} techniques", MAX_BULK_TECHNIQUES).into(),
+        );
+        error.add_param("max".into(), &MAX_BULK_TECHNIQUES);
+        errors.add("techniques", error);
+        return Err(ApiError::Validation(errors));
+    }
+
+    let mut techniques = Vec::with_capacity(rows.len());
+    for (index, row) in rows.into_iter().enumerate() {
+        if let Err(row_errors) = row.validate_with_args(limits) {
+            for (field, kind) in row_errors.into_errors() {
+                if let ValidationErrorsKind::Field(list) = kind {
+                    for error in list {
+                        add_row_error(&mut errors, index, &field, error);
+                    }
+                }
+            }
+        }
+        let mut tags: Vec<String> = Vec::with_capacity(row.tags.len());
+        for tag in row.tags.iter().map(|tag| normalize_tag_name(tag)) {
+            match validate_tag_name(&tag, limits) {
+                Err(error) => add_row_error(&mut errors, index, "tags", error),
+                Ok(()) if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) => tags.push(tag),
+                Ok(()) => {}
+            }
+        }
+        techniques.push(NewTechnique { name: row.name, description: row.description, tags });
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+    if techniques.iter().any(|technique| !technique.tags.is_empty()) {
+        user.require_permission(Permission::ManageTags)?;
+    }
+
+    let ids = create_techniques(db, &techniques, user.id).await?;
+    info!(count = ids.len(), "Techniques created in bulk");
+    Ok(Json(BulkCreateTechniquesResponse { ids }))
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct TechniqueAliasRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_name", use_context))]
+    alias: String,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(length(max = 35, code = "language.too_long", message = "Language code is too long"))]
+    language: Option<String>,
+}
+
+/// Adds another name for a library technique and returns all of them.
+#[post("/techniques/<id>/aliases", data = "<body>")]
+pub async fn api_add_technique_alias(
+    id: TechniqueId,
+    body: Json<TechniqueAliasRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<TechniqueAlias>>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    body.validate_with_args(limits)?;
+    let language = body.language.as_deref().map(str::trim).filter(|l| !l.is_empty());
+    if add_technique_alias(db, id, body.alias.trim(), language).await?.is_none() {
+        let mut error = ValidationError::new("alias.duplicate")
+            .with_message(format!("{} is already an alias of this technique", body.alias).into());
+        error.add_param("alias".into(), &body.alias);
+        let mut errors = ValidationErrors::new();
+        errors.add("alias", error);
+        return Err(ApiError::Conflict(errors));
+    }
+    Ok(Json(get_technique_aliases(db.inner(), id).await?))
+}
+
+#[delete("/techniques/<id>/aliases/<alias_id>")]
+pub async fn api_remove_technique_alias(
+    id: TechniqueId,
+    alias_id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    remove_technique_alias(db.inner(), id, alias_id).await?;
+    Ok(Status::Ok)
+}
+
+#[get("/techniques/<id>/prerequisites")]
+pub async fn api_get_technique_prerequisites(
+    id: TechniqueId,
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<Prerequisite>>> {
+    Ok(Json(get_prerequisites(db.inner(), id).await?))
+}
+
+#[derive(Deserialize)]
+pub struct PrerequisiteRequest {
+    prerequisite_id: TechniqueId,
+}
+
+/// Adds a prerequisite and returns all of them. 422 if it would make a
+/// cycle.
+#[post("/techniques/<id>/prerequisites", data = "<body>")]
+pub async fn api_add_technique_prerequisite(
+    id: TechniqueId,
+    body: Json<PrerequisiteRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<Prerequisite>>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    if !add_prerequisite(db, id, body.prerequisite_id).await? {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "prerequisite_id",
+            ValidationError::new("prerequisite.cycle")
+                .with_message("A technique cannot be its own prerequisite".into()),
+        );
+        return Err(ApiError::Validation(errors));
+    }
+    Ok(Json(get_prerequisites(db.inner(), id).await?))
+}
+
+#[delete("/techniques/<id>/prerequisites/<prerequisite_id>")]
+pub async fn api_remove_technique_prerequisite(
+    id: TechniqueId,
+    prerequisite_id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    remove_prerequisite(db.inner(), id, prerequisite_id).await?;
+    Ok(Status::Ok)
+}
+
+#[get("/techniques/<id>/media")]
+pub async fn api_get_technique_media(
+    id: TechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<TechniqueMedia>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    Ok(Json(get_technique_media(db.inner(), id).await?))
+}
+
+/// Media to link to a technique: either `url` or the id of an uploaded
+/// `attachment_id`, not both.
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct TechniqueMediaRequest {
+    kind: MediaKind,
+    #[serde(default, deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_description", use_context))]
+    title: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    attachment_id: Option<i64>,
+}
+
+/// Links a demonstration video or image to a library technique and returns
+/// all of its media.
+#[post("/techniques/<id>/media", data = "<body>")]
+pub async fn api_add_technique_media(
+    id: TechniqueId,
+    body: Json<TechniqueMediaRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<TechniqueMedia>>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    body.validate_with_args(limits)?;
+    let source = match (body.url.as_deref().map(str::trim), body.attachment_id) {
+        (Some(url), None) => {
+            if let Err(error) = validate_media_url(url) {
+                let mut errors = ValidationErrors::new();
+                errors.add("url", error);
+                return Err(ApiError::Validation(errors));
+            }
+            MediaSource::Url(url.to_string())
+        }
+        (None, Some(attachment_id)) => MediaSource::Attachment(attachment_id),
+        _ => {
+            let error = ValidationError::new("media.source")
+                .with_message("Send either a url or an attachment_id, not both".into());
+            let mut errors = ValidationErrors::new();
+            errors.add("url", error);
+            return Err(ApiError::Validation(errors));
+        }
+    };
+    add_technique_media(db, id, body.kind, body.title.trim(), &source, user.id).await?;
+    Ok(Json(get_technique_media(db.inner(), id).await?))
+}
+
+#[delete("/techniques/<id>/media/<media_id>")]
+pub async fn api_remove_technique_media(
+    id: TechniqueId,
+    media_id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    remove_technique_media(db.inner(), id, media_id).await?;
+    Ok(Status::Ok)
+}
+
+#[get("/collections/<id>/students")]
+pub async fn api_get_collection_students(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<UserData>>> {
+    user.require_permission(Permission::ViewAllStudents)?;
+    let students = get_students_with_collection(db.inner(), id).await?;
+    let visible = visible_students(db.inner(), &user).await?;
+    Ok(Json(
+        students
+            .into_iter()
+            .filter(|student| visible.as_ref().is_none_or(|ids| ids.contains(&student.id)))
+            .map(UserData::from)
+            .collect(),
+    ))
+}
+
+#[post("/student/<student_id>/assign_collection/<collection_id>")]
+pub async fn api_assign_collection(
+    student_id: UserId,
+    collection_id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Status> {
+    user.require_permission(Permission::AssignTechniques)?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
+    }
+    assign_collection_to_student(db, student_id, collection_id, user.id).await?;
+    Ok(Status::Ok)
+}
+
+// ---- Attempts ----
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttemptResponse {
+    pub id: i64,
+    pub student_technique_id: i64,
+    pub recorded_by_id: i64,
+    pub recorded_by_name: Option<String>,
+    pub attempted_at: String,
+    pub coach_note: Option<String>,
+    pub coach_note_by_id: Option<i64>,
+    pub coach_note_by_name: Option<String>,
+    pub coach_note_at: Option<String>,
+    pub student_note: Option<String>,
+    pub student_note_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<crate::models::Attempt> for AttemptResponse {
+    fn from(a: crate::models::Attempt) -> Self {
+        Self {
+            id: a.id,
+            student_technique_id: a.student_technique_id,
+            recorded_by_id: a.recorded_by_id,
+            recorded_by_name: a.recorded_by_name,
+            attempted_at: to_rfc3339_utc(a.attempted_at),
+            coach_note: a.coach_note,
+            coach_note_by_id: a.coach_note_by_id,
+            coach_note_by_name: a.coach_note_by_name,
+            coach_note_at: a.coach_note_at.map(to_rfc3339_utc),
+            student_note: a.student_note,
+            student_note_at: a.student_note_at.map(to_rfc3339_utc),
+            created_at: to_rfc3339_utc(a.created_at),
+        }
+    }
 }
 
-/// Public endpoint for students to self-register. Account is created in
-/// pending state (`approved_at IS NULL`) until a coach approves it.
-#[post("/register/self", data = "<body>")]
-pub async fn api_self_register(
-    body: Json<SelfRegisterRequest>,
-    cookies: &rocket::http::CookieJar<'_>,
-    db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<UserData>> {
-    body.validate()?;
+#[derive(Serialize, Deserialize)]
+pub struct AttemptListResponse {
+    pub attempts: Vec<AttemptResponse>,
+}
 
-    let user_id = create_self_registered_user(
-        db,
-        &body.username,
-        &body.password,
-        body.first_name.as_deref(),
-        body.last_name.as_deref(),
-    )
-    .await?;
-    let user = get_user(db, user_id).await?;
+#[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
+pub struct CreateAttemptRequest {
+    pub attempted_at: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_note", use_context))]
+    pub note: Option<String>,
+}
 
-    // Log them in immediately. The frontend will route them to the
-    // pending-approval screen since `approved_at` is None.
-    establish_session(cookies, db, &user).await?;
+#[derive(Serialize, Deserialize)]
+pub struct CreateAttemptResponse {
+    pub attempt: AttemptResponse,
+    pub status_suggestion: Option<String>,
+}
 
-    Ok(Json(UserData::from(user)))
+fn parse_optional_datetime(raw: Option<&str>) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+    match raw {
+        None => Ok(None),
+        Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
+            Ok(dt) => Ok(Some(dt.with_timezone(&chrono::Utc))),
+            Err(_) => Err(Status::BadRequest.into()),
+        },
+    }
 }
 
-#[post("/admin/users/<id>/approve")]
-pub async fn api_approve_user(
-    id: i64,
-    user: User,
-    db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::RegisterUsers)?;
-    approve_user(db, id).await?;
-    Ok(Status::Ok)
+#[derive(Serialize, Deserialize)]
+pub struct SingleStudentTechniqueResponse {
+    pub technique: TechniqueResponse,
+    pub student: StudentResponse,
+    pub can_edit_all_techniques: bool,
+    pub can_manage_tags: bool,
 }
 
-/// Admin endpoint to invalidate a user's password and generate a fresh invite
-/// token. Existing sessions for the user are terminated.
-#[post("/admin/users/<id>/reset_claim")]
-pub async fn api_reset_user_claim(
-    id: i64,
+#[get("/student_technique/<id>")]
+pub async fn api_get_single_student_technique(
+    id: StudentTechniqueId,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<InviteResponse>> {
-    user.require_permission(Permission::EditUserCredentials)?;
+) -> ApiResult<Json<SingleStudentTechniqueResponse>> {
+    let st = get_student_technique(db, id, user.id).await?;
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    let student = get_user(db.inner(), st.student_id).await?;
 
-    let token = reset_user_claim(db, id).await?;
-    let claim_path = format!("/invite/{}", token);
+    let technique_response = technique_response(st, &user);
 
-    Ok(Json(InviteResponse {
-        user_id: id,
-        token,
-        claim_path,
+    Ok(Json(SingleStudentTechniqueResponse {
+        technique: technique_response,
+        student: StudentResponse {
+            id: student.id,
+            display_name: student.effective_display_name().to_string(),
+            username: student.username,
+            archived: student.archived,
+            graduated_at: student.graduated_at,
+        },
+        can_edit_all_techniques: user.has_permission(Permission::EditAllTechniques),
+        can_manage_tags: user.has_permission(Permission::ManageTags),
     }))
 }
 
-// ---- Collections / syllabuses ----
+#[derive(Serialize, Deserialize)]
+pub struct NoteHistoryResponse {
+    pub student_notes: String,
+    pub coach_notes: String,
+    /// Earlier versions of both fields, newest first.
+    pub revisions: Vec<NoteRevision>,
+}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CollectionResponse {
-    pub id: i64,
-    pub name: String,
-    pub description: String,
-    pub coach_id: Option<i64>,
-    pub created_at: String,
-    pub technique_count: i64,
-    pub student_count: i64,
-    pub techniques: Vec<TechniqueLibraryResponse>,
-    pub can_create_techniques: bool,
-    pub can_edit_all_techniques: bool,
+#[get("/student_technique/<id>/notes/history")]
+pub async fn api_note_history(
+    id: StudentTechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<NoteHistoryResponse>> {
+    let st = get_student_technique(db, id, user.id).await?;
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    let mut revisions = get_note_revisions(db.inner(), id).await?;
+    let mut coach_notes = st.coach_notes.clone();
+    if hides_coach_notes(&st, &user) {
+        coach_notes.clear();
+        revisions.retain(|revision| revision.field != NoteField::CoachNotes);
+    }
+    Ok(Json(NoteHistoryResponse {
+        student_notes: st.student_notes,
+        coach_notes,
+        revisions,
+    }))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct TechniqueLibraryResponse {
-    pub id: i64,
-    pub name: String,
-    pub description: String,
-    pub coach_id: i64,
-    pub coach_name: String,
+#[derive(Serialize, Deserialize)]
+pub struct StudentTechniqueHistoryResponse {
+    /// Every recorded change to the status and notes, newest first.
+    pub revisions: Vec<StudentTechniqueRevision>,
 }
 
-fn collection_to_response(c: Collection, user: &User) -> CollectionResponse {
-    CollectionResponse {
-        id: c.id,
-        name: c.name,
-        description: c.description,
-        coach_id: c.coach_id,
-        created_at: c.created_at.to_rfc3339(),
-        technique_count: c.technique_count,
-        student_count: c.student_count,
-        techniques: c
-            .techniques
-            .into_iter()
-            .map(|t| TechniqueLibraryResponse {
-                id: t.id,
-                name: t.name,
-                description: t.description,
-                coach_id: t.coach_id,
-                coach_name: t.coach_name,
-            })
-            .collect(),
-        can_create_techniques: user.has_permission(Permission::CreateTechniques),
-        can_edit_all_techniques: user.has_permission(Permission::EditAllTechniques),
+/// Who changed the status and notes, to what, and when; for reviewing how a
+/// technique came along before a grading.
+#[get("/student_technique/<id>/history")]
+pub async fn api_student_technique_history(
+    id: StudentTechniqueId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<StudentTechniqueHistoryResponse>> {
+    let st = get_student_technique(db, id, user.id).await?;
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    let mut revisions = get_student_technique_revisions(db.inner(), id).await?;
+    if hides_coach_notes(&st, &user) {
+        revisions.retain(|revision| revision.field != StudentTechniqueField::CoachNotes);
     }
+    Ok(Json(StudentTechniqueHistoryResponse { revisions }))
 }
 
-#[get("/collections")]
-pub async fn api_get_collections(
+#[derive(Deserialize)]
+pub struct RestoreNoteRequest {
+    revision_id: i64,
+}
+
+/// Puts a revision's text back in its field. This is an ordinary edit, so
+/// the text it replaces becomes a revision in turn. Who may restore follows
+/// who may edit: students their own notes, coaches either field.
+#[post("/student_technique/<id>/notes/restore", data = "<body>")]
+pub async fn api_restore_note(
+    id: StudentTechniqueId,
+    body: Json<RestoreNoteRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<CollectionResponse>>> {
-    user.require_permission(Permission::AssignTechniques)?;
-    let collections = get_all_collections(db).await?;
-    Ok(Json(
-        collections
-            .into_iter()
-            .map(|c| collection_to_response(c, &user))
-            .collect(),
-    ))
+) -> ApiResult<Status> {
+    let st = get_student_technique(db, id, user.id).await?;
+    let can_edit_all = user.has_permission(Permission::EditAllTechniques);
+    if user.id != st.student_id && !can_edit_all {
+        return Err(Status::Forbidden.into());
+    }
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::NotFound.into());
+    }
+    let (field, content) = get_note_revision(db.inner(), id, body.revision_id).await?;
+    let mut conn = db.acquire().await.map_err(AppError::from)?;
+    match field {
+        NoteField::StudentNotes => update_student_notes(&mut conn, id, &user, &content).await?,
+        NoteField::CoachNotes if can_edit_all => {
+            let (status, student_notes) = (&st.status, &st.student_notes);
+            update_student_technique(&mut conn, id, &user, status, student_notes, &content)
+                .await?
+        }
+        NoteField::CoachNotes => return Err(Status::Forbidden.into()),
+    };
+    info!(student_technique_id = %id, field = field.as_str(), "Note restored");
+    Ok(Status::Ok)
 }
 
-#[get("/collections/<id>")]
-pub async fn api_get_collection(
-    id: i64,
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct NoteTemplateRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(length(min = 1, max = 100, code = "name.required", message = "Name is required"))]
+    name: String,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_technique_notes", use_context))]
+    body: String,
+    /// Share with every coach instead of keeping it personal. Read on create
+    /// only; needs `ManageNoteTemplates`.
+    #[serde(default)]
+    shared: bool,
+}
+
+/// Coaches edit their own templates; shared ones need `ManageNoteTemplates`.
+fn require_note_template_access(user: &User, template: &NoteTemplate) -> ApiResult<()> {
+    match template.owner_id {
+        Some(owner_id) if owner_id == user.id => Ok(()),
+        Some(_) => Err(Status::Forbidden.into()),
+        None => Ok(user.require_permission(Permission::ManageNoteTemplates)?),
+    }
+}
+
+#[get("/note_templates")]
+pub async fn api_get_note_templates(
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<CollectionResponse>> {
-    user.require_permission(Permission::AssignTechniques)?;
-    let collection = get_collection(db, id).await?;
-    Ok(Json(collection_to_response(collection, &user)))
+) -> ApiResult<Json<Vec<NoteTemplate>>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    Ok(Json(get_note_templates(db.inner(), user.id).await?))
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct CollectionUpsertRequest {
-    #[validate(length(min = 1, max = 100, message = "Name is required"))]
-    name: String,
-    description: Option<String>,
+#[post("/note_templates", data = "<body>")]
+pub async fn api_create_note_template(
+    body: Json<NoteTemplateRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<NoteTemplate>> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    body.validate_with_args(limits)?;
+    let owner_id = if body.shared {
+        user.require_permission(Permission::ManageNoteTemplates)?;
+        None
+    } else {
+        Some(user.id)
+    };
+    let id = create_note_template(db.inner(), owner_id, &body.name, &body.body).await?;
+    Ok(Json(get_note_template(db.inner(), id, user.id).await?))
 }
 
-#[post("/collections", data = "<body>")]
-pub async fn api_create_collection(
-    body: Json<CollectionUpsertRequest>,
+#[put("/note_templates/<id>", data = "<body>")]
+pub async fn api_update_note_template(
+    id: i64,
+    body: Json<NoteTemplateRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<CollectionResponse>> {
-    body.validate()?;
-    user.require_permission(Permission::CreateTechniques)?;
-    let id = create_collection(
-        db,
-        &body.name,
-        body.description.as_deref().unwrap_or(""),
-        user.id,
-    )
-    .await?;
-    let collection = get_collection(db, id).await?;
-    Ok(Json(collection_to_response(collection, &user)))
+) -> ApiResult<Status> {
+    user.require_permission(Permission::EditAllTechniques)?;
+    body.validate_with_args(limits)?;
+    let template = get_note_template(db.inner(), id, user.id).await?;
+    require_note_template_access(&user, &template)?;
+    update_note_template(db.inner(), id, &body.name, &body.body).await?;
+    Ok(Status::Ok)
 }
 
-#[put("/collections/<id>", data = "<body>")]
-pub async fn api_update_collection(
+#[delete("/note_templates/<id>")]
+pub async fn api_delete_note_template(
     id: i64,
-    body: Json<CollectionUpsertRequest>,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    body.validate()?;
-    user.require_permission(Permission::CreateTechniques)?;
-    update_collection(
-        db,
-        id,
-        &body.name,
-        body.description.as_deref().unwrap_or(""),
-    )
-    .await?;
+    user.require_permission(Permission::EditAllTechniques)?;
+    let template = get_note_template(db.inner(), id, user.id).await?;
+    require_note_template_access(&user, &template)?;
+    delete_note_template(db.inner(), id).await?;
     Ok(Status::Ok)
 }
 
-#[delete("/collections/<id>")]
-pub async fn api_delete_collection(
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct JournalEntryRequest {
+    technique_id: Option<TechniqueId>,
+    /// Defaults to today (UTC).
+    entry_date: Option<chrono::NaiveDate>,
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(
+        length(min = 1, code = "body.required", message = "Entry cannot be empty"),
+        custom(function = "validate_note", use_context)
+    )]
+    body: String,
+    /// Lets coaches read the entry. New entries are private unless set;
+    /// an update without it keeps the entry's current setting.
+    shared: Option<bool>,
+}
+
+impl JournalEntryRequest {
+    fn input(&self) -> JournalEntryInput<'_> {
+        JournalEntryInput {
+            technique_id: self.technique_id,
+            entry_date: self.entry_date.unwrap_or_else(|| chrono::Utc::now().date_naive()),
+            body: &self.body,
+            shared: self.shared,
+        }
+    }
+}
+
+/// The caller's own journal, shared entries or not.
+#[get("/journal?<technique_id>")]
+pub async fn api_get_journal(
+    technique_id: Option<i64>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<JournalEntry>>> {
+    user.require_permission(Permission::EditOwnNotes)?;
+    let technique_id = technique_id.map(TechniqueId);
+    Ok(Json(get_journal_entries(db.inner(), user.id, technique_id, false).await?))
+}
+
+/// The entries a student has shared with their coaches. Entries they keep
+/// private can't be read by anyone else, whatever the caller's role.
+#[get("/student/<id>/journal?<technique_id>")]
+pub async fn api_get_student_journal(
+    id: UserId,
+    technique_id: Option<i64>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<JournalEntry>>> {
+    user.require_permission(Permission::ViewStudentJournals)?;
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    let technique_id = technique_id.map(TechniqueId);
+    Ok(Json(get_journal_entries(db.inner(), id, technique_id, true).await?))
+}
+
+#[post("/journal", data = "<body>")]
+pub async fn api_create_journal_entry(
+    body: Json<JournalEntryRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<JournalEntry>> {
+    user.require_permission(Permission::EditOwnNotes)?;
+    body.validate_with_args(limits)?;
+    let id = create_journal_entry(db, user.id, &body.input()).await?;
+    Ok(Json(get_journal_entry(db.inner(), id, user.id).await?))
+}
+
+#[put("/journal/<id>", data = "<body>")]
+pub async fn api_update_journal_entry(
+    id: i64,
+    body: Json<JournalEntryRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<JournalEntry>> {
+    user.require_permission(Permission::EditOwnNotes)?;
+    body.validate_with_args(limits)?;
+    update_journal_entry(db, id, user.id, &body.input()).await?;
+    Ok(Json(get_journal_entry(db.inner(), id, user.id).await?))
+}
+
+#[delete("/journal/<id>")]
+pub async fn api_delete_journal_entry(
     id: i64,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    user.require_permission(Permission::CreateTechniques)?;
-    delete_collection(db, id).await?;
+    user.require_permission(Permission::EditOwnNotes)?;
+    delete_journal_entry(db.inner(), id, user.id).await?;
     Ok(Status::Ok)
 }
 
-#[derive(Deserialize, Clone)]
-pub struct AddTechniquesToCollectionRequest {
-    technique_ids: Vec<i64>,
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct ClassRequest {
+    #[serde(default, deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_description", use_context))]
+    title: String,
+    starts_at: chrono::DateTime<chrono::Utc>,
+    /// Defaults to an hour.
+    duration_minutes: Option<i64>,
 }
 
-#[post("/collections/<id>/techniques", data = "<body>")]
-pub async fn api_add_techniques_to_collection(
-    id: i64,
-    body: Json<AddTechniquesToCollectionRequest>,
+/// Schedules a class run by the caller.
+#[post("/classes", data = "<body>")]
+pub async fn api_create_class(
+    body: Json<ClassRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::CreateTechniques)?;
-    add_techniques_to_collection(db, id, body.technique_ids.clone()).await?;
-    Ok(Status::Ok)
+) -> ApiResult<Json<ClassSession>> {
+    user.require_permission(Permission::TrackAttendance)?;
+    body.validate_with_args(limits)?;
+    let duration = body.duration_minutes.unwrap_or(60);
+    if let Err(error) = validate_class_duration(duration) {
+        let mut errors = ValidationErrors::new();
+        errors.add("duration_minutes", error);
+        return Err(ApiError::Validation(errors));
+    }
+    let title = body.title.trim();
+    let id = create_class(db.inner(), title, body.starts_at, duration, user.id).await?;
+    Ok(Json(get_class(db, id).await?))
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct CreateTechniqueInCollectionRequest {
-    #[validate(length(
-        min = 1,
-        max = 100,
-        message = "Technique name must be between 1 and 100 characters"
-    ))]
-    name: String,
-    #[validate(length(min = 1, message = "Description cannot be empty"))]
-    description: String,
+#[derive(FromForm)]
+pub struct ClassesQuery {
+    from: Option<String>,
+    to: Option<String>,
 }
 
-#[post("/collections/<id>/create_technique", data = "<body>")]
-pub async fn api_create_technique_in_collection(
-    id: i64,
-    body: Json<CreateTechniqueInCollectionRequest>,
+/// Classes starting between `from` and `to` (YYYY-MM-DD, both inclusive,
+/// UTC). Defaults to the last four weeks.
+#[get("/classes?<params..>")]
+pub async fn api_list_classes(
+    params: ClassesQuery,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<TechniqueLibraryResponse>> {
-    body.validate()?;
-    user.require_permission(Permission::CreateTechniques)?;
-    let technique_id =
-        create_technique_in_collection(db, user.id, id, &body.name, &body.description).await?;
-    let coach_name = if user.display_name.is_empty() {
-        user.username.clone()
-    } else {
-        user.display_name.clone()
+) -> ApiResult<Json<Vec<ClassSession>>> {
+    user.require_permission(Permission::TrackAttendance)?;
+    let today = chrono::Utc::now().date_naive();
+    let parse = |value: Option<&str>, default: chrono::NaiveDate| match value {
+        Some(s) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| {
+            warn!(raw_value = s, error = %e, "rejected classes query: date not YYYY-MM-DD");
+            ApiError::from(Status::BadRequest)
+        }),
+        None => Ok(default),
     };
-    Ok(Json(TechniqueLibraryResponse {
-        id: technique_id,
-        name: body.name.clone(),
-        description: body.description.clone(),
-        coach_id: user.id,
-        coach_name,
-    }))
+    let from = parse(params.from.as_deref(), today - chrono::Duration::days(28))?;
+    let to = parse(params.to.as_deref(), today)? + chrono::Duration::days(1);
+    let from = from.and_time(chrono::NaiveTime::MIN).and_utc();
+    let to = to.and_time(chrono::NaiveTime::MIN).and_utc();
+    Ok(Json(list_classes(db, from, to).await?))
 }
 
-#[delete("/collections/<id>/techniques/<technique_id>")]
-pub async fn api_remove_technique_from_collection(
+#[get("/classes/<id>")]
+pub async fn api_get_class(
     id: i64,
-    technique_id: i64,
     user: User,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    user.require_permission(Permission::CreateTechniques)?;
-    remove_technique_from_collection(db, id, technique_id).await?;
-    Ok(Status::Ok)
+) -> ApiResult<Json<ClassSession>> {
+    user.require_permission(Permission::TrackAttendance)?;
+    Ok(Json(get_class(db, id).await?))
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct UpdateLibraryTechniqueRequest {
-    #[validate(length(
+#[derive(Serialize, Deserialize)]
+pub struct AttendanceRequest {
+    pub student_ids: Vec<UserId>,
+}
+
+/// Replaces who attended the class. Everyone listed must be a student (400
+/// otherwise).
+#[put("/classes/<id>/attendance", data = "<body>")]
+pub async fn api_set_class_attendance(
+    id: i64,
+    body: Json<AttendanceRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<ClassSession>> {
+    user.require_permission(Permission::TrackAttendance)?;
+    for student_id in &body.student_ids {
+        if get_user(db.inner(), *student_id).await?.role != Role::Student {
+            return Err(Status::BadRequest.into());
+        }
+    }
+    set_class_attendance(db, id, &body.student_ids, user.id).await?;
+    Ok(Json(get_class(db, id).await?))
+}
+
+/// The classes a student attended, most recent first.
+#[get("/student/<id>/attendance")]
+pub async fn api_student_attendance(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<AttendanceRecord>>> {
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    Ok(Json(get_student_attendance(db.inner(), id).await?))
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct ScheduleSlotRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
+    #[validate(
+        length(min = 1, code = "name.required", message = "Title is required"),
+        custom(function = "validate_description", use_context)
+    )]
+    title: String,
+    /// ISO weekday: 1 is Monday, 7 is Sunday.
+    #[validate(range(
         min = 1,
-        max = 100,
-        message = "Technique name must be between 1 and 100 characters"
+        max = 7,
+        code = "weekday.invalid",
+        message = "Weekday must be between 1 and 7"
     ))]
-    name: String,
-    #[validate(length(min = 1, message = "Description cannot be empty"))]
-    description: String,
+    weekday: i64,
+    start_time: chrono::NaiveTime,
+    /// Defaults to an hour.
+    duration_minutes: Option<i64>,
+    /// Defaults to the caller's timezone, else UTC.
+    #[validate(custom(function = "validate_timezone"))]
+    timezone: Option<String>,
+    /// Defaults to today (UTC).
+    starts_on: Option<chrono::NaiveDate>,
+    ends_on: Option<chrono::NaiveDate>,
 }
 
-#[put("/techniques/<id>", data = "<body>")]
-pub async fn api_update_library_technique(
-    id: i64,
-    body: Json<UpdateLibraryTechniqueRequest>,
+impl ScheduleSlotRequest {
+    /// The slot to store, or a 422 for what the derive can't check.
+    fn input<'a>(&'a self, user: &'a User) -> ApiResult<ScheduleSlotInput<'a>> {
+        let mut errors = ValidationErrors::new();
+        let duration_minutes = self.duration_minutes.unwrap_or(60);
+        if let Err(error) = validate_class_duration(duration_minutes) {
+            errors.add("duration_minutes", error);
+        }
+        let starts_on = self.starts_on.unwrap_or_else(|| chrono::Utc::now().date_naive());
+        if self.ends_on.is_some_and(|ends_on| ends_on < starts_on) {
+            let error = ValidationError::new("ends_on.before_start")
+                .with_message("ends_on can't be before starts_on".into());
+            errors.add("ends_on", error);
+        }
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+        Ok(ScheduleSlotInput {
+            title: self.title.trim(),
+            weekday: self.weekday,
+            start_time: self.start_time,
+            duration_minutes,
+            timezone: self.timezone.as_deref().or(user.timezone.as_deref()).unwrap_or("UTC"),
+            starts_on,
+            ends_on: self.ends_on,
+        })
+    }
+}
+
+/// The weekly timetable, for everyone signed in.
+#[get("/schedule")]
+pub async fn api_get_schedule(
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<ScheduleSlot>>> {
+    Ok(Json(get_schedule(db.inner()).await?))
+}
+
+#[post("/schedule", data = "<body>")]
+pub async fn api_create_schedule_slot(
+    body: Json<ScheduleSlotRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Status> {
-    body.validate()?;
-    user.require_permission(Permission::EditAllTechniques)?;
-    update_technique(db, id, &body.name, &body.description).await?;
-    Ok(Status::Ok)
+) -> ApiResult<Json<ScheduleSlot>> {
+    user.require_permission(Permission::ManageSchedule)?;
+    body.validate_with_args(limits)?;
+    let id = create_schedule_slot(db.inner(), &body.input(&user)?, user.id).await?;
+    Ok(Json(get_schedule_slot(db.inner(), id).await?))
 }
 
-#[get("/collections/<id>/students")]
-pub async fn api_get_collection_students(
+#[put("/schedule/<id>", data = "<body>")]
+pub async fn api_update_schedule_slot(
     id: i64,
+    body: Json<ScheduleSlotRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<Vec<UserData>>> {
-    user.require_permission(Permission::ViewAllStudents)?;
-    let students = get_students_with_collection(db, id).await?;
-    Ok(Json(students.into_iter().map(UserData::from).collect()))
+) -> ApiResult<Json<ScheduleSlot>> {
+    user.require_permission(Permission::ManageSchedule)?;
+    body.validate_with_args(limits)?;
+    update_schedule_slot(db.inner(), id, &body.input(&user)?).await?;
+    Ok(Json(get_schedule_slot(db.inner(), id).await?))
 }
 
-#[post("/student/<student_id>/assign_collection/<collection_id>")]
-pub async fn api_assign_collection(
-    student_id: i64,
-    collection_id: i64,
+#[delete("/schedule/<id>")]
+pub async fn api_delete_schedule_slot(
+    id: i64,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    user.require_permission(Permission::AssignTechniques)?;
-    assign_collection_to_student(db, student_id, collection_id, user.id).await?;
+    user.require_permission(Permission::ManageSchedule)?;
+    delete_schedule_slot(db.inner(), id).await?;
     Ok(Status::Ok)
 }
 
-// ---- Attempts ----
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct AttemptResponse {
-    pub id: i64,
-    pub student_technique_id: i64,
-    pub recorded_by_id: i64,
-    pub recorded_by_name: Option<String>,
-    pub attempted_at: String,
-    pub coach_note: Option<String>,
-    pub coach_note_by_id: Option<i64>,
-    pub coach_note_by_name: Option<String>,
-    pub coach_note_at: Option<String>,
-    pub student_note: Option<String>,
-    pub student_note_at: Option<String>,
-    pub created_at: String,
+#[derive(Responder)]
+#[response(content_type = "text/calendar")]
+pub struct CalendarFeed {
+    body: String,
+    cache: Header<'static>,
 }
 
-impl From<crate::models::Attempt> for AttemptResponse {
-    fn from(a: crate::models::Attempt) -> Self {
-        Self {
-            id: a.id,
-            student_technique_id: a.student_technique_id,
-            recorded_by_id: a.recorded_by_id,
-            recorded_by_name: a.recorded_by_name,
-            attempted_at: a.attempted_at.to_rfc3339(),
-            coach_note: a.coach_note,
-            coach_note_by_id: a.coach_note_by_id,
-            coach_note_by_name: a.coach_note_by_name,
-            coach_note_at: a.coach_note_at.map(|d| d.to_rfc3339()),
-            student_note: a.student_note,
-            student_note_at: a.student_note_at.map(|d| d.to_rfc3339()),
-            created_at: a.created_at.to_rfc3339(),
-        }
+/// The timetable as an iCalendar feed for calendar apps, which subscribe
+/// without signing in. Like `/public/syllabus` it is off (404) unless an
+/// admin turns on `public_sharing`.
+#[get("/schedule.ics")]
+pub async fn api_schedule_ics(flags: Flags, db: &State<Pool<Sqlite>>) -> ApiResult<CalendarFeed> {
+    if !flags.is_enabled(Flag::PublicSharing) {
+        return Err(Status::NotFound.into());
     }
+    let slots = get_schedule(db.inner()).await?;
+    Ok(CalendarFeed {
+        body: schedule_calendar(&slots, chrono::Utc::now()),
+        cache: Header::new("Cache-Control", "public, max-age=300"),
+    })
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct AttemptListResponse {
-    pub attempts: Vec<AttemptResponse>,
+#[derive(Deserialize)]
+pub struct OpenGradingRequest {
+    student_id: UserId,
+    collection_id: i64,
 }
 
-#[derive(Deserialize, Validate, Clone)]
-pub struct CreateAttemptRequest {
-    pub attempted_at: Option<String>,
-    #[validate(length(max = 2000, message = "Note must be under 2000 characters"))]
-    pub note: Option<String>,
+/// Opens a grading of a student against a curriculum. The student must be
+/// one the caller can see, and a student (400 otherwise).
+#[post("/gradings", data = "<body>")]
+pub async fn api_open_grading(
+    body: Json<OpenGradingRequest>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Grading>> {
+    user.require_permission(Permission::ConductGradings)?;
+    if !can_view_student(db.inner(), &user, body.student_id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    if get_user(db.inner(), body.student_id).await?.role != Role::Student {
+        return Err(Status::BadRequest.into());
+    }
+    let id = open_grading(db, body.student_id, body.collection_id, user.id).await?;
+    Ok(Json(get_grading(db, id).await?))
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct CreateAttemptResponse {
-    pub attempt: AttemptResponse,
-    pub status_suggestion: Option<String>,
+/// One student's gradings, newest first, or without `student_id` every
+/// grading of the students the caller sees.
+#[get("/gradings?<student_id>")]
+pub async fn api_list_gradings(
+    student_id: Option<UserId>,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Vec<GradingSummary>>> {
+    if let Some(student_id) = student_id {
+        if !can_view_student(db.inner(), &user, student_id).await? {
+            return Err(Status::Forbidden.into());
+        }
+        return Ok(Json(list_gradings(db.inner(), Some(student_id)).await?));
+    }
+    user.require_permission(Permission::ConductGradings)?;
+    let mut gradings = list_gradings(db.inner(), None).await?;
+    if let Some(visible) = visible_students(db.inner(), &user).await? {
+        gradings.retain(|g| visible.contains(&g.student_id));
+    }
+    Ok(Json(gradings))
 }
 
-fn parse_optional_datetime(raw: Option<&str>) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
-    match raw {
-        None => Ok(None),
-        Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
-            Ok(dt) => Ok(Some(dt.with_timezone(&chrono::Utc))),
-            Err(_) => Err(Status::BadRequest.into()),
-        },
+/// A grading the caller may see (their own, or a student's they can see),
+/// else 403.
+async fn viewable_grading(db: &Pool<Sqlite>, user: &User, id: i64) -> ApiResult<Grading> {
+    let grading = get_grading(db, id).await?;
+    if !can_view_student(db, user, grading.student_id).await? {
+        return Err(Status::Forbidden.into());
     }
+    Ok(grading)
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct SingleStudentTechniqueResponse {
-    pub technique: TechniqueResponse,
-    pub student: StudentResponse,
-    pub can_edit_all_techniques: bool,
-    pub can_manage_tags: bool,
+#[get("/gradings/<id>")]
+pub async fn api_get_grading(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Grading>> {
+    Ok(Json(viewable_grading(db, &user, id).await?))
 }
 
-#[get("/student_technique/<id>")]
-pub async fn api_get_single_student_technique(
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct GradingResultRequest {
+    /// `null` puts the technique back to ungraded.
+    passed: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_note", use_context))]
+    note: String,
+}
+
+/// Records pass or fail for one technique. A closed grading can't change
+/// (409).
+#[put("/gradings/<id>/results/<technique_id>", data = "<body>")]
+pub async fn api_record_grading_result(
     id: i64,
+    technique_id: TechniqueId,
+    body: Json<GradingResultRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
-) -> ApiResult<Json<SingleStudentTechniqueResponse>> {
-    let st = get_student_technique(db, id, user.id).await?;
-    if user.id != st.student_id && !user.has_permission(Permission::ViewAllStudents) {
-        return Err(Status::Forbidden.into());
+) -> ApiResult<Json<Grading>> {
+    user.require_permission(Permission::ConductGradings)?;
+    body.validate_with_args(limits)?;
+    if viewable_grading(db, &user, id).await?.closed_at.is_some() {
+        return Err(Status::Conflict.into());
     }
-    let student = get_user(db, st.student_id).await?;
-
-    let has_unseen_activity = compute_has_unseen_activity(
-        user.id == st.student_id,
-        st.last_coach_update_at,
-        st.last_student_update_at,
-        st.viewer_seen_at,
-    );
+    record_grading_result(db.inner(), id, technique_id, body.passed, body.note.trim()).await?;
+    Ok(Json(get_grading(db, id).await?))
+}
 
-    let technique_response = TechniqueResponse {
-        id: st.id,
-        technique_id: st.technique_id,
-        technique_name: st.technique_name,
-        technique_description: st.technique_description,
-        status: st.status,
-        student_notes: st.student_notes,
-        coach_notes: st.coach_notes,
-        created_at: st.created_at.to_rfc3339(),
-        updated_at: st.updated_at.to_rfc3339(),
-        last_coach_update_at: st.last_coach_update_at.map(|d| d.to_rfc3339()),
-        last_coach_update_by_name: st.last_coach_update_by_name,
-        last_student_update_at: st.last_student_update_at.map(|d| d.to_rfc3339()),
-        last_student_update_by_name: st.last_student_update_by_name,
-        has_unseen_activity,
-        collection_id: st.collection_id,
-        collection_name: st.collection_name,
-        tags: st.tags.into_iter().map(TagResponse::from).collect(),
-        attempt_count: st.attempt_count,
-        last_attempt_at: st.last_attempt_at.map(|d| d.to_rfc3339()),
-    };
+#[derive(Deserialize, Validate)]
+#[validate(context = ValidationConfig)]
+pub struct CloseGradingRequest {
+    outcome: GradingOutcome,
+    #[serde(default, deserialize_with = "deserialize_plain_text")]
+    #[validate(custom(function = "validate_note", use_context))]
+    notes: String,
+}
 
-    Ok(Json(SingleStudentTechniqueResponse {
-        technique: technique_response,
-        student: StudentResponse {
-            id: student.id,
-            username: student.username,
-            display_name: student.display_name,
-            archived: student.archived,
-            graduated_at: student.graduated_at,
-        },
-        can_edit_all_techniques: user.has_permission(Permission::EditAllTechniques),
-        can_manage_tags: user.has_permission(Permission::ManageTags),
-    }))
+/// Closes the grading with an outcome; 409 if it is already closed.
+/// Techniques left ungraded stay that way.
+#[post("/gradings/<id>/close", data = "<body>")]
+pub async fn api_close_grading(
+    id: i64,
+    body: Json<CloseGradingRequest>,
+    user: User,
+    limits: &State<ValidationConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<Grading>> {
+    user.require_permission(Permission::ConductGradings)?;
+    body.validate_with_args(limits)?;
+    viewable_grading(db, &user, id).await?;
+    if !close_grading(db.inner(), id, body.outcome, body.notes.trim()).await? {
+        return Err(Status::Conflict.into());
+    }
+    Ok(Json(get_grading(db, id).await?))
 }
 
 #[get("/student_technique/<id>/attempts")]
 pub async fn api_list_attempts(
-    id: i64,
+    id: StudentTechniqueId,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<AttemptListResponse>> {
     let st = get_student_technique(db, id, user.id).await?;
-    if user.id != st.student_id && !user.has_permission(Permission::ViewAllStudents) {
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
         return Err(Status::Forbidden.into());
     }
-    let attempts = list_attempts(db, id).await?;
+    let attempts = list_attempts(db.inner(), id).await?;
     Ok(Json(AttemptListResponse {
         attempts: attempts.into_iter().map(AttemptResponse::from).collect(),
     }))
@@ -1584,15 +4640,26 @@ pub async fn api_list_attempts(
 
 #[post("/student_technique/<id>/attempts", data = "<body>")]
 pub async fn api_create_attempt(
-    id: i64,
+    id: StudentTechniqueId,
     body: Json<CreateAttemptRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
+    feed: &State<ActivityFeed>,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<CreateAttemptResponse>> {
-    body.validate()?;
+    body.validate_with_args(limits)?;
+    let st = get_student_technique(db, id, user.id).await?;
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
+        return Err(Status::NotFound.into());
+    }
     let attempted_at = parse_optional_datetime(body.attempted_at.as_deref())?
         .unwrap_or_else(chrono::Utc::now);
     let result = create_attempt(db, &user, id, attempted_at, body.note.as_deref()).await?;
+    if result.student_id == user.id {
+        feed.publish(ActivityKind::AttemptLogged, user.id, id);
+    }
+    let mut conn = db.acquire().await.map_err(AppError::from)?;
+    award_badges_quietly(&mut conn, result.student_id).await;
     let suggestion = match result.suggestion {
         AttemptSuggestion::Amber => Some("amber".to_string()),
         AttemptSuggestion::None => None,
@@ -1604,9 +4671,11 @@ pub async fn api_create_attempt(
 }
 
 #[derive(Deserialize, Validate, Clone)]
+#[validate(context = ValidationConfig)]
 pub struct UpdateAttemptRequest {
     pub attempted_at: Option<String>,
-    #[validate(length(max = 2000, message = "Note must be under 2000 characters"))]
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
+    #[validate(custom(function = "validate_note", use_context))]
     pub note: Option<String>,
     pub clear_note: Option<bool>,
 }
@@ -1616,9 +4685,14 @@ pub async fn api_update_attempt(
     id: i64,
     body: Json<UpdateAttemptRequest>,
     user: User,
+    limits: &State<ValidationConfig>,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
-    body.validate()?;
+    body.validate_with_args(limits)?;
+    let student_id = get_attempt_student_id(db.inner(), id).await?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
+    }
     if let Some(raw) = body.attempted_at.as_deref() {
         let dt = chrono::DateTime::parse_from_rfc3339(raw)
             .map_err(|e| {
@@ -1647,6 +4721,10 @@ pub async fn api_delete_attempt(
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Status> {
+    let student_id = get_attempt_student_id(db.inner(), id).await?;
+    if !can_view_student(db.inner(), &user, student_id).await? {
+        return Err(Status::NotFound.into());
+    }
     delete_attempt(db, &user, id).await?;
     Ok(Status::Ok)
 }
@@ -1674,16 +4752,16 @@ pub struct RecentAttemptsQuery {
 
 #[get("/student/<id>/attempts/recent?<params..>")]
 pub async fn api_recent_attempts(
-    id: i64,
+    id: UserId,
     params: RecentAttemptsQuery,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<RecentAttemptsResponse>> {
-    if user.id != id && !user.has_permission(Permission::ViewAllStudents) {
+    if !can_view_student(db.inner(), &user, id).await? {
         return Err(Status::Forbidden.into());
     }
     let limit = params.limit.unwrap_or(5).clamp(1, 50);
-    let items = list_recent_attempts_for_student(db, id, limit).await?;
+    let items = list_recent_attempts_for_student(db.inner(), id, limit).await?;
     Ok(Json(RecentAttemptsResponse {
         attempts: items
             .into_iter()
@@ -1692,7 +4770,7 @@ pub async fn api_recent_attempts(
                 student_technique_id: item.student_technique_id,
                 technique_id: item.technique_id,
                 technique_name: item.technique_name,
-                attempted_at: item.attempted_at.to_rfc3339(),
+                attempted_at: to_rfc3339_utc(item.attempted_at),
                 coach_note: item.coach_note,
                 student_note: item.student_note,
             })
@@ -1709,14 +4787,14 @@ pub struct AttemptSummaryResponse {
 
 #[get("/student/<id>/attempts/summary")]
 pub async fn api_attempt_summary(
-    id: i64,
+    id: UserId,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<AttemptSummaryResponse>> {
-    if user.id != id && !user.has_permission(Permission::ViewAllStudents) {
+    if !can_view_student(db.inner(), &user, id).await? {
         return Err(Status::Forbidden.into());
     }
-    let summary = attempt_summary_for_student(db, id).await?;
+    let summary = attempt_summary_for_student(db.inner(), id).await?;
     Ok(Json(AttemptSummaryResponse {
         this_week: summary.this_week,
         this_month: summary.this_month,
@@ -1724,6 +4802,157 @@ pub async fn api_attempt_summary(
     }))
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct StudentAnalyticsResponse {
+    pub tags: Vec<GroupProgress>,
+    pub curricula: Vec<GroupProgress>,
+}
+
+/// Completion (green / assigned) per tag and per curriculum, for progress
+/// bars on the student page.
+#[get("/student/<id>/analytics")]
+pub async fn api_student_analytics(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<StudentAnalyticsResponse>> {
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    Ok(Json(StudentAnalyticsResponse {
+        tags: get_tag_progress(db.inner(), id).await?,
+        curricula: get_curriculum_progress(db.inner(), id).await?,
+    }))
+}
+
+/// Status counts overall and per tag, with last-updated times, for the
+/// dashboard.
+#[get("/student/<id>/progress")]
+pub async fn api_student_progress(
+    id: UserId,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<StudentProgress>> {
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    Ok(Json(get_student_progress(db, id).await?))
+}
+
+/// Rows read per query while streaming a CSV export.
+const EXPORT_BATCH_SIZE: i64 = 200;
+
+const EXPORT_CSV_HEADER: [&str; 8] = [
+    "Technique",
+    "Status",
+    "Student notes",
+    "Coach notes",
+    "Assigned at",
+    "Updated at",
+    "Last coach update",
+    "Last student update",
+];
+
+#[derive(Responder)]
+#[response(content_type = "text/csv")]
+pub struct CsvDownload<S> {
+    body: S,
+    disposition: Header<'static>,
+}
+
+/// Spreadsheets run a cell starting with `=`, `+`, `-` or `@` as a formula,
+/// and notes are free text a student can write, so such cells get a leading
+/// `'` to keep them as text.
+fn spreadsheet_safe(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@']) {
+        Cow::Owned(format!("'{}", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn export_csv_chunk<I, R>(records: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        // Writing to a Vec can't fail.
+        let _ = writer.write_record(record);
+    }
+    writer.into_inner().unwrap_or_default()
+}
+
+/// Private coach notes come out empty unless `show_private_notes`.
+fn export_csv_record(row: &StudentTechniqueExportRow, show_private_notes: bool) -> [String; 8] {
+    let time = |t: Option<chrono::NaiveDateTime>| t.map(naive_to_rfc3339).unwrap_or_default();
+    let coach_notes = if row.coach_notes_private && !show_private_notes {
+        ""
+    } else {
+        &row.coach_notes
+    };
+    [
+        spreadsheet_safe(&row.technique_name).into_owned(),
+        row.status.clone(),
+        spreadsheet_safe(&row.student_notes).into_owned(),
+        spreadsheet_safe(coach_notes).into_owned(),
+        time(row.created_at),
+        time(row.updated_at),
+        time(row.last_coach_update_at),
+        time(row.last_student_update_at),
+    ]
+}
+
+/// A student's syllabus as a CSV download, one row per assigned technique in
+/// assignment order, for grading sessions run from a spreadsheet. Rows are
+/// read a batch at a time as the body goes out. A database error part way
+/// through ends the file early, as the status has already been sent.
+#[get("/student/<id>/techniques/export.csv")]
+pub async fn api_export_student_techniques<'r>(
+    id: UserId,
+    user: User,
+    db: &'r State<Pool<Sqlite>>,
+) -> ApiResult<CsvDownload<ByteStream![Vec<u8> + 'r]>> {
+    if !can_view_student(db.inner(), &user, id).await? {
+        return Err(Status::Forbidden.into());
+    }
+    let student = get_user(db.inner(), id).await?;
+    let filename = format!("{}-syllabus.csv", student.username);
+    let show_private_notes = user.has_permission(Permission::EditAllTechniques);
+
+    let body = ByteStream! {
+        yield export_csv_chunk([EXPORT_CSV_HEADER]);
+        let mut after_id = 0;
+        loop {
+            let rows =
+                match get_student_techniques_for_export(db.inner(), id, after_id, EXPORT_BATCH_SIZE)
+                    .await
+                {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        error!(student_id = %id, error = %e, "CSV export stopped early");
+                        break;
+                    }
+                };
+            let Some(last) = rows.last() else { break };
+            after_id = last.id;
+            yield export_csv_chunk(
+                rows.iter().map(|row| export_csv_record(row, show_private_notes)),
+            );
+            if (rows.len() as i64) < EXPORT_BATCH_SIZE {
+                break;
+            }
+        }
+    };
+
+    Ok(CsvDownload {
+        body,
+        disposition: Header::new("Content-Disposition", content_disposition(&filename)),
+    })
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AttemptBucketResponse {
     pub date: String,
@@ -1743,12 +4972,12 @@ pub struct HeatmapQuery {
 
 #[get("/student/<id>/attempts/heatmap?<params..>")]
 pub async fn api_attempt_heatmap(
-    id: i64,
+    id: UserId,
     params: HeatmapQuery,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<AttemptBucketsResponse>> {
-    if user.id != id && !user.has_permission(Permission::ViewAllStudents) {
+    if !can_view_student(db.inner(), &user, id).await? {
         return Err(Status::Forbidden.into());
     }
     let today = chrono::Utc::now().date_naive();
@@ -1756,7 +4985,7 @@ pub async fn api_attempt_heatmap(
     let from = match params.from.as_deref() {
         Some(s) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| {
             warn!(
-                student_id = id,
+                student_id = %id,
                 raw_value = s,
                 error = %e,
                 "rejected heatmap query: from not YYYY-MM-DD"
@@ -1768,7 +4997,7 @@ pub async fn api_attempt_heatmap(
     let to = match params.to.as_deref() {
         Some(s) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| {
             warn!(
-                student_id = id,
+                student_id = %id,
                 raw_value = s,
                 error = %e,
                 "rejected heatmap query: to not YYYY-MM-DD"
@@ -1777,7 +5006,7 @@ pub async fn api_attempt_heatmap(
         })?,
         None => today,
     };
-    let buckets = attempt_buckets_for_student(db, id, from, to).await?;
+    let buckets = attempt_buckets_for_student(db.inner(), id, from, to).await?;
     Ok(Json(AttemptBucketsResponse {
         buckets: buckets
             .into_iter()
@@ -1796,17 +5025,17 @@ pub struct SparklineQuery {
 
 #[get("/student_technique/<id>/attempts/sparkline?<params..>")]
 pub async fn api_attempt_sparkline(
-    id: i64,
+    id: StudentTechniqueId,
     params: SparklineQuery,
     user: User,
     db: &State<Pool<Sqlite>>,
 ) -> ApiResult<Json<AttemptBucketsResponse>> {
     let st = get_student_technique(db, id, user.id).await?;
-    if user.id != st.student_id && !user.has_permission(Permission::ViewAllStudents) {
+    if !can_view_student(db.inner(), &user, st.student_id).await? {
         return Err(Status::Forbidden.into());
     }
     let weeks = params.weeks.unwrap_or(12).clamp(1, 104);
-    let buckets = attempt_weekly_buckets_for_technique(db, id, weeks).await?;
+    let buckets = attempt_weekly_buckets_for_technique(db.inner(), id, weeks).await?;
     Ok(Json(AttemptBucketsResponse {
         buckets: buckets
             .into_iter()
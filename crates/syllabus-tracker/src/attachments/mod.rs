@@ -0,0 +1,167 @@
+//! Files users attach to things, kept behind the `Storage` trait. Which
+//! backend runs is config (`ATTACHMENT_STORAGE`): `local` writes to
+//! `ATTACHMENT_DIR` and serves downloads through the app on HMAC-signed URLs;
+//! `s3` uses the bucket the video pipeline is configured with (`S3_*`) and
+//! hands out presigned URLs. Either way a download URL is minted per request
+//! and expires after `ATTACHMENT_URL_TTL_SECONDS`. Files come in through
+//! `POST /api/uploads`, limited to `UPLOAD_MAX_BYTES`.
+//!
+//! Each object has a row in `attachments`. The object is written first, so a
+//! failure in between leaves an object nothing points at; the
+//! `attachment_cleanup` job deletes those once they are old enough that no
+//! upload could still be about to write the row.
+
+pub mod routes;
+pub mod storage;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tracing::warn;
+use uuid::Uuid;
+
+pub use routes::*;
+pub use storage::{DynStorage, LocalStorage, S3Storage, Storage, StoredObject, UrlSigner};
+
+use crate::config::AppConfig;
+use crate::db::{self, Attachment};
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::scheduler::Job;
+use crate::videos::storage::{S3Config, StorageError};
+
+/// Objects younger than this are never treated as orphans.
+pub const ORPHAN_GRACE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+impl StorageBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StorageBackend::Local => "local",
+            StorageBackend::S3 => "s3",
+        }
+    }
+}
+
+/// The configured backend. The local one is also returned on its own, since
+/// its downloads need a route that reads from it directly.
+pub fn storage_from_config(
+    config: &AppConfig,
+) -> Result<(DynStorage, Option<Arc<LocalStorage>>), StorageError> {
+    match config.attachment_storage {
+        StorageBackend::Local => {
+            let local = Arc::new(LocalStorage::new(
+                config.attachment_dir.clone(),
+                UrlSigner::from_key(config.attachment_signing_key.as_deref()),
+            ));
+            Ok((local.clone(), Some(local)))
+        }
+        StorageBackend::S3 => Ok((Arc::new(S3Storage::new(&S3Config::from_env()?)), None)),
+    }
+}
+
+fn storage_error(e: StorageError) -> AppError {
+    AppError::ExternalService(format!("Attachment storage: {}", e))
+}
+
+/// Stores the file at `source` and records it.
+pub async fn store_attachment(
+    pool: &SqlitePool,
+    storage: &DynStorage,
+    uploaded_by: UserId,
+    filename: &str,
+    content_type: &str,
+    source: &Path,
+) -> Result<Attachment, AppError> {
+    let bytes = tokio::fs::metadata(source)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to read upload: {}", e)))?
+        .len();
+    let key = Uuid::new_v4().to_string();
+    storage
+        .put_file(&key, content_type, source)
+        .await
+        .map_err(storage_error)?;
+    let bytes = i64::try_from(bytes).unwrap_or(i64::MAX);
+    match db::create_attachment(pool, &key, filename, content_type, bytes, uploaded_by).await {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            if let Err(delete_error) = storage.delete(&key).await {
+                warn!(key, error = %delete_error, "Failed to remove unrecorded attachment");
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Deletes the row, then the object. An object that fails to delete is left
+/// for the cleanup job rather than failing the call.
+pub async fn remove_attachment(
+    pool: &SqlitePool,
+    storage: &DynStorage,
+    attachment: &Attachment,
+) -> Result<(), AppError> {
+    db::delete_attachment(pool, attachment.id).await?;
+    if let Err(e) = storage.delete(&attachment.storage_key).await {
+        warn!(key = attachment.storage_key, error = %e, "Failed to delete attachment object");
+    }
+    Ok(())
+}
+
+/// Deletes stored objects with no `attachments` row, once older than
+/// `grace`.
+pub struct AttachmentCleanup {
+    storage: DynStorage,
+    grace: Duration,
+}
+
+impl AttachmentCleanup {
+    pub fn new(storage: DynStorage) -> Self {
+        Self { storage, grace: ORPHAN_GRACE }
+    }
+
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+}
+
+#[async_trait]
+impl Job for AttachmentCleanup {
+    fn name(&self) -> &'static str {
+        "attachment_cleanup"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "0 4 * * *"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let cutoff = Utc::now() - chrono::Duration::from_std(self.grace).unwrap_or_default();
+        // Listed before reading the keys: an upload that lands in between has
+        // its row read, and one that doesn't is inside the grace period.
+        let objects = self.storage.list().await.map_err(storage_error)?;
+        let live = db::get_attachment_keys(pool).await?;
+        let mut removed = 0;
+        for object in objects {
+            if live.contains(&object.key) || object.modified > cutoff {
+                continue;
+            }
+            self.storage.delete(&object.key).await.map_err(storage_error)?;
+            removed += 1;
+        }
+        Ok(format!("Removed {} orphaned attachments", removed))
+    }
+}
@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rocket::State;
+use rocket::data::{ByteUnit, ToByteUnit};
+use rocket::form::{Errors as FormErrors, Form};
+use rocket::fs::{NamedFile, TempFile};
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tracing::{error, instrument, warn};
+use uuid::Uuid;
+
+use crate::api::ApiResult;
+use crate::attachments::storage::{DynStorage, LocalStorage, content_disposition};
+use crate::attachments::{remove_attachment, store_attachment};
+use crate::auth::{Permission, User};
+use crate::config::{AppConfig, LiveConfig};
+use crate::db::{self, Attachment};
+use crate::ids::UserId;
+use crate::videos::SignedUrlResponse;
+use crate::videos::pipeline;
+
+/// What `POST /api/uploads` accepts: pictures, short clips, and PDFs.
+const UPLOAD_CONTENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/webp",
+    "image/gif",
+    "video/mp4",
+    "video/webm",
+    "application/pdf",
+];
+
+const AVATAR_CONTENT_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/gif"];
+
+/// The multipart limit uploads need, with room for the form around the
+/// file. `init_rocket` takes the larger of this and the video limit.
+pub fn upload_byte_limit(config: &AppConfig) -> ByteUnit {
+    config.upload_max_bytes.bytes() + 1.mebibytes()
+}
+
+#[derive(FromForm)]
+pub struct FileUploadForm<'r> {
+    pub file: TempFile<'r>,
+}
+
+#[derive(Serialize)]
+pub struct UploadedFileResponse {
+    pub attachment: Attachment,
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct AvatarResponse {
+    pub avatar_url: Option<String>,
+}
+
+/// `type/subtype` without parameters, lower-cased.
+fn essence(content_type: &ContentType) -> String {
+    format!("{}/{}", content_type.top(), content_type.sub()).to_ascii_lowercase()
+}
+
+fn parse_upload_form<'r>(
+    form: Result<Form<FileUploadForm<'r>>, FormErrors<'r>>,
+) -> Result<Form<FileUploadForm<'r>>, Status> {
+    form.map_err(|errs| {
+        error!(errors = %errs, "upload form failed to parse");
+        Status::BadRequest
+    })
+}
+
+/// Checks the file against `allowed` and `UPLOAD_MAX_BYTES`, then stores it.
+/// A type outside `allowed` is a 415; a file over the limit a 413.
+async fn store_upload(
+    file: &mut TempFile<'_>,
+    allowed: &[&str],
+    pool: &Pool<Sqlite>,
+    storage: &DynStorage,
+    config: &AppConfig,
+    uploaded_by: UserId,
+) -> ApiResult<Attachment> {
+    let content_type = file
+        .content_type()
+        .map(essence)
+        .filter(|ct| allowed.contains(&ct.as_str()))
+        .ok_or(Status::UnsupportedMediaType)?;
+    if file.len() > config.upload_max_bytes {
+        return Err(Status::PayloadTooLarge.into());
+    }
+    let filename = file
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str().to_string())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "upload".to_string());
+
+    let temp_dir = pipeline::temp_dir();
+    tokio::fs::create_dir_all(&temp_dir).await.map_err(|e| {
+        error!(temp_dir = ?temp_dir, error = %e, "failed to create upload temp dir");
+        Status::InternalServerError
+    })?;
+    let dest = temp_dir.join(format!("upload-{}", Uuid::new_v4()));
+    file.persist_to(&dest).await.map_err(|e| {
+        error!(dest = ?dest, error = %e, "failed to persist upload to disk");
+        Status::InternalServerError
+    })?;
+    let stored =
+        store_attachment(pool, storage, uploaded_by, &filename, &content_type, &dest).await;
+    if let Err(e) = tokio::fs::remove_file(&dest).await {
+        warn!(dest = ?dest, error = %e, "failed to remove upload temp file");
+    }
+    Ok(stored?)
+}
+
+async fn sign_download(
+    storage: &DynStorage,
+    attachment: &Attachment,
+    config: &AppConfig,
+) -> ApiResult<SignedUrlResponse> {
+    let ttl = config.attachment_url_ttl();
+    let url = storage
+        .signed_url(&attachment.storage_key, &attachment.filename, ttl)
+        .await
+        .map_err(|e| {
+            error!(attachment_id = attachment.id, error = %e, "Failed to sign attachment url");
+            Status::InternalServerError
+        })?;
+    let expires_at = Utc::now() + chrono::Duration::from_std(ttl).unwrap_or_default();
+    Ok(SignedUrlResponse { url, expires_at })
+}
+
+/// Stores a file in the configured attachment backend and returns it with a
+/// download URL. The attachment id is what technique media links to.
+#[instrument(skip(form, db, storage, config))]
+#[post("/uploads", data = "<form>")]
+pub async fn api_upload_file(
+    user: User,
+    form: Result<Form<FileUploadForm<'_>>, FormErrors<'_>>,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<DynStorage>,
+    config: &State<LiveConfig>,
+) -> ApiResult<Json<UploadedFileResponse>> {
+    let mut form = parse_upload_form(form)?;
+    let config = config.get();
+    let attachment =
+        store_upload(&mut form.file, UPLOAD_CONTENT_TYPES, db, storage, &config, user.id).await?;
+    let SignedUrlResponse { url, expires_at } = sign_download(storage, &attachment, &config).await?;
+    Ok(Json(UploadedFileResponse { attachment, url, expires_at }))
+}
+
+/// Replaces the signed-in user's profile picture. The previous picture's
+/// file is deleted.
+#[instrument(skip(form, db, storage, config))]
+#[post("/profile/avatar", data = "<form>")]
+pub async fn api_upload_avatar(
+    user: User,
+    form: Result<Form<FileUploadForm<'_>>, FormErrors<'_>>,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<DynStorage>,
+    config: &State<LiveConfig>,
+) -> ApiResult<Json<AvatarResponse>> {
+    let mut form = parse_upload_form(form)?;
+    let config = config.get();
+    let attachment =
+        store_upload(&mut form.file, AVATAR_CONTENT_TYPES, db, storage, &config, user.id).await?;
+    db::set_user_avatar(db.inner(), user.id, Some(attachment.id)).await?;
+    remove_previous_avatar(db, storage, &user).await;
+    Ok(Json(AvatarResponse { avatar_url: Some(avatar_url(user.id, attachment.id)) }))
+}
+
+#[delete("/profile/avatar")]
+pub async fn api_delete_avatar(
+    user: User,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<DynStorage>,
+) -> ApiResult<Json<AvatarResponse>> {
+    db::set_user_avatar(db.inner(), user.id, None).await?;
+    remove_previous_avatar(db, storage, &user).await;
+    Ok(Json(AvatarResponse { avatar_url: None }))
+}
+
+/// Best effort: the user already points at the new picture, so a failure
+/// here only leaves a file behind.
+async fn remove_previous_avatar(db: &Pool<Sqlite>, storage: &DynStorage, user: &User) {
+    let Some(previous) = user.avatar_attachment_id else {
+        return;
+    };
+    let removed = match db::get_attachment(db, previous).await {
+        Ok(Some(attachment)) => remove_attachment(db, storage, &attachment).await,
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = removed {
+        warn!(attachment_id = previous, error = %e, "Failed to remove previous avatar");
+    }
+}
+
+/// Where `UserData::avatar_url` points. The attachment id changes with every
+/// new picture, so clients can cache the image under this URL.
+pub fn avatar_url(user_id: UserId, attachment_id: i64) -> String {
+    format!("/api/users/{}/avatar?v={}", user_id, attachment_id)
+}
+
+/// Redirects to a fresh download URL for the user's profile picture, so an
+/// `<img>` can point here for as long as the picture stays the same. Any
+/// signed-in user may look; a user without a picture is a 404.
+#[get("/users/<id>/avatar")]
+pub async fn api_user_avatar(
+    id: i64,
+    _user: User,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<DynStorage>,
+    config: &State<LiveConfig>,
+) -> ApiResult<Redirect> {
+    let attachment_id = db::get_user(db.inner(), UserId(id))
+        .await?
+        .avatar_attachment_id
+        .ok_or(Status::NotFound)?;
+    let attachment = db::get_attachment(db.inner(), attachment_id)
+        .await?
+        .ok_or(Status::NotFound)?;
+    let signed = sign_download(storage, &attachment, &config.get()).await?;
+    Ok(Redirect::to(signed.url))
+}
+
+/// A short-lived URL to download an attachment from. Coaches can fetch any
+/// attachment; everyone else only the ones they uploaded or that are linked
+/// to a technique as media, and gets a 404 for the rest.
+#[get("/attachments/<id>/download-url")]
+pub async fn api_attachment_download_url(
+    id: i64,
+    user: User,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<DynStorage>,
+    config: &State<LiveConfig>,
+) -> ApiResult<Json<SignedUrlResponse>> {
+    let attachment = db::get_attachment(db.inner(), id).await?.ok_or(Status::NotFound)?;
+    let is_coach = user.has_permission(Permission::ViewAllStudents);
+    if !is_coach
+        && attachment.uploaded_by_id != Some(user.id.0)
+        && !db::is_technique_media_attachment(db.inner(), id).await?
+    {
+        return Err(Status::NotFound.into());
+    }
+    Ok(Json(sign_download(storage, &attachment, &config.get()).await?))
+}
+
+#[derive(Responder)]
+pub struct AttachmentFile {
+    file: NamedFile,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+/// Serves a file from `LocalStorage` to whoever holds a URL it signed; the
+/// signature is the only check. Mounted only with the local backend. A bad
+/// or expired signature is a 404, the same as a key that doesn't exist.
+#[get("/attachments/file/<key>?<expires>&<signature>")]
+pub async fn api_attachment_file(
+    key: &str,
+    expires: i64,
+    signature: &str,
+    db: &State<Pool<Sqlite>>,
+    storage: &State<Arc<LocalStorage>>,
+) -> ApiResult<AttachmentFile> {
+    if !storage.signer().verify(key, expires, signature, Utc::now()) {
+        return Err(Status::NotFound.into());
+    }
+    let attachment = db::get_attachment_by_key(db.inner(), key)
+        .await?
+        .ok_or(Status::NotFound)?;
+    let path = storage.path_for(key).ok_or(Status::NotFound)?;
+    let file = NamedFile::open(path).await.map_err(|_| Status::NotFound)?;
+    Ok(AttachmentFile {
+        file,
+        content_type: ContentType::parse_flexible(&attachment.content_type)
+            .unwrap_or(ContentType::Binary),
+        disposition: Header::new(
+            "Content-Disposition",
+            content_disposition(&attachment.filename),
+        ),
+    })
+}
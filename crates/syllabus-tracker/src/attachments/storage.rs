@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::{instrument, warn};
+
+use crate::videos::storage::{S3Config, StorageError, build_client};
+
+pub type DynStorage = Arc<dyn Storage + Send + Sync>;
+
+/// Where `LocalStorage` points its signed URLs (see `routes::api_attachment_file`).
+pub const LOCAL_FILE_ROUTE: &str = "/api/attachments/file";
+
+/// Key prefix for attachments in the bucket, which videos share.
+const S3_PREFIX: &str = "attachments/";
+
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub modified: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait Storage {
+    async fn put_file(
+        &self,
+        key: &str,
+        content_type: &str,
+        source: &Path,
+    ) -> Result<(), StorageError>;
+
+    /// Deleting a key that isn't there is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Every stored object, for the orphan sweep.
+    async fn list(&self) -> Result<Vec<StoredObject>, StorageError>;
+
+    /// A URL anyone holding it can download the object from until `ttl` runs
+    /// out, served as an attachment named `filename`.
+    async fn signed_url(
+        &self,
+        key: &str,
+        filename: &str,
+        ttl: Duration,
+    ) -> Result<String, StorageError>;
+}
+
+/// `attachment; filename="..."` with anything outside a safe ASCII set
+/// replaced, so the header can't be broken by the uploader's file name.
+pub fn content_disposition(filename: &str) -> String {
+    let cleaned: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let trimmed = cleaned.trim();
+    let name = if trimmed.is_empty() { "attachment" } else { trimmed };
+    format!("attachment; filename=\"{}\"", name)
+}
+
+/// Signs local download URLs: HMAC-SHA256 over the key and expiry.
+#[derive(Clone)]
+pub struct UrlSigner {
+    secret: Vec<u8>,
+}
+
+impl UrlSigner {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self { secret: secret.into() }
+    }
+
+    /// Signs with `key` (`ATTACHMENT_SIGNING_KEY`), or a random key when it
+    /// is unset. A random key is fine for one process, but links it signed
+    /// stop working on restart.
+    pub fn from_key(key: Option<&str>) -> Self {
+        match key {
+            Some(secret) => Self::new(secret),
+            None => {
+                warn!("ATTACHMENT_SIGNING_KEY unset; signing with a per-process key");
+                Self::new(rand::random::<[u8; 32]>().to_vec())
+            }
+        }
+    }
+
+    fn mac(&self, key: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(key.as_bytes());
+        mac.update(b"\n");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+
+    pub fn sign(&self, key: &str, expires: i64) -> String {
+        hex::encode(self.mac(key, expires).finalize().into_bytes())
+    }
+
+    /// Whether `signature` is ours for `key` and `expires`, and `expires`
+    /// (unix seconds) is still ahead of `now`.
+    pub fn verify(&self, key: &str, expires: i64, signature: &str, now: DateTime<Utc>) -> bool {
+        if expires <= now.timestamp() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        self.mac(key, expires).verify_slice(&signature).is_ok()
+    }
+}
+
+/// Files in a directory on local disk, downloaded through the app with
+/// signed URLs checked by `UrlSigner`.
+pub struct LocalStorage {
+    root: PathBuf,
+    signer: UrlSigner,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf, signer: UrlSigner) -> Self {
+        Self { root, signer }
+    }
+
+    pub fn signer(&self) -> &UrlSigner {
+        &self.signer
+    }
+
+    /// Where `key` lives on disk. `None` for anything but the keys
+    /// `attachments::new_storage_key` makes, which keeps a crafted key from
+    /// reaching outside `root`.
+    pub fn path_for(&self, key: &str) -> Option<PathBuf> {
+        let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        valid.then(|| self.root.join(key))
+    }
+
+    fn require_path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        self.path_for(key)
+            .ok_or_else(|| StorageError::Backend(format!("invalid storage key '{}'", key)))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    #[instrument(skip(self, source), fields(root = %self.root.display(), key = %key))]
+    async fn put_file(
+        &self,
+        key: &str,
+        _content_type: &str,
+        source: &Path,
+    ) -> Result<(), StorageError> {
+        let path = self.require_path(key)?;
+        tokio::fs::create_dir_all(&self.root).await?;
+        // Copied under a temporary name and renamed, so the object never
+        // exists half-written.
+        let partial = path.with_extension("partial");
+        tokio::fs::copy(source, &partial).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(root = %self.root.display(), key = %key))]
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.require_path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    #[instrument(skip(self), fields(root = %self.root.display()))]
+    async fn list(&self) -> Result<Vec<StoredObject>, StorageError> {
+        let mut entries = match tokio::fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut objects = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            // Skips anything that isn't a key, such as a `.partial` left by
+            // an interrupted copy.
+            let Ok(key) = entry.file_name().into_string() else {
+                continue;
+            };
+            if self.path_for(&key).is_none() {
+                continue;
+            }
+            objects.push(StoredObject { key, modified: metadata.modified()?.into() });
+        }
+        Ok(objects)
+    }
+
+    async fn signed_url(
+        &self,
+        key: &str,
+        _filename: &str,
+        ttl: Duration,
+    ) -> Result<String, StorageError> {
+        self.require_path(key)?;
+        let ttl = chrono::Duration::from_std(ttl).map_err(|e| StorageError::Presign(e.to_string()))?;
+        let expires = (Utc::now() + ttl).timestamp();
+        let signature = self.signer.sign(key, expires);
+        Ok(format!(
+            "{}/{}?expires={}&signature={}",
+            LOCAL_FILE_ROUTE, key, expires, signature
+        ))
+    }
+}
+
+/// Objects under `attachments/` in the S3-compatible bucket videos use,
+/// downloaded straight from the bucket with presigned URLs.
+pub struct S3Storage {
+    client: S3Client,
+    presign_client: S3Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub fn new(config: &S3Config) -> Self {
+        // Presigned through the public endpoint for the same reason as
+        // `S3VideoStorage::new`.
+        let client = build_client(config, &config.endpoint);
+        let presign_client = if config.public_endpoint == config.endpoint {
+            client.clone()
+        } else {
+            build_client(config, &config.public_endpoint)
+        };
+        Self {
+            client,
+            presign_client,
+            bucket: config.bucket.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    #[instrument(skip(self, source), fields(bucket = %self.bucket, key = %key))]
+    async fn put_file(
+        &self,
+        key: &str,
+        content_type: &str,
+        source: &Path,
+    ) -> Result<(), StorageError> {
+        let body = ByteStream::from_path(source)
+            .await
+            .map_err(|e| StorageError::Backend(format!("read source: {}", e)))?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(format!("{}{}", S3_PREFIX, key))
+            .content_type(content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("put_object: {}", e)))?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(bucket = %self.bucket, key = %key))]
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(format!("{}{}", S3_PREFIX, key))
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("delete_object: {}", e)))?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(bucket = %self.bucket))]
+    async fn list(&self) -> Result<Vec<StoredObject>, StorageError> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(S3_PREFIX)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page =
+                page.map_err(|e| StorageError::Backend(format!("list_objects_v2: {}", e)))?;
+            for object in page.contents() {
+                let (Some(key), Some(modified)) = (object.key(), object.last_modified()) else {
+                    continue;
+                };
+                let Some(key) = key.strip_prefix(S3_PREFIX) else {
+                    continue;
+                };
+                let Some(modified) =
+                    DateTime::from_timestamp(modified.secs(), modified.subsec_nanos())
+                else {
+                    continue;
+                };
+                objects.push(StoredObject { key: key.to_string(), modified });
+            }
+        }
+        Ok(objects)
+    }
+
+    #[instrument(skip(self), fields(bucket = %self.bucket, key = %key))]
+    async fn signed_url(
+        &self,
+        key: &str,
+        filename: &str,
+        ttl: Duration,
+    ) -> Result<String, StorageError> {
+        let presign = PresigningConfig::expires_in(ttl)
+            .map_err(|e| StorageError::Presign(e.to_string()))?;
+        let req = self
+            .presign_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(format!("{}{}", S3_PREFIX, key))
+            .response_content_disposition(content_disposition(filename))
+            .presigned(presign)
+            .await
+            .map_err(|e| StorageError::Presign(e.to_string()))?;
+        Ok(req.uri().to_string())
+    }
+}
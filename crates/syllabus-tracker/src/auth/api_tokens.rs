@@ -0,0 +1,80 @@
+//! Personal access tokens, so scripts and the CLI can call the API without
+//! a cookie session. A token is sent as `Authorization: Bearer <token>`, and
+//! the `User` guard signs the request in as its owner with their current
+//! role. The token's scopes then limit which methods it may use.
+//!
+//! Tokens are managed through `/api/me/api_tokens`, which only a session
+//! reaches (see `SessionUser`), so a leaked token can't make more of itself.
+
+use rand::{Rng, distr::Alphanumeric, rng};
+use rocket::http::Method;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Starts every token, so one pasted into a log or a repo is easy to spot.
+pub const API_TOKEN_PREFIX: &str = "stpat_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// GET and HEAD requests.
+    Read,
+    /// Every other method.
+    Write,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Write => "write",
+        }
+    }
+
+    /// The scope a request with `method` needs.
+    pub fn for_method(method: Method) -> Self {
+        match method {
+            Method::Get | Method::Head => TokenScope::Read,
+            _ => TokenScope::Write,
+        }
+    }
+
+    /// `scopes` as stored in `api_tokens.scopes`.
+    pub fn to_db(scopes: &[TokenScope]) -> String {
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+        scopes.iter().map(TokenScope::as_str).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Unknown words are dropped, which grants less rather than more.
+    pub fn from_db(scopes: &str) -> Vec<TokenScope> {
+        scopes
+            .split_whitespace()
+            .filter_map(|scope| match scope {
+                "read" => Some(TokenScope::Read),
+                "write" => Some(TokenScope::Write),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// How the `User` guard signed a request in, kept in the request's local
+/// cache. Only set for tokens; anything else was a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Credential {
+    Session,
+    ApiToken,
+}
+
+pub fn generate_api_token() -> String {
+    let secret: String = rng().sample_iter(Alphanumeric).take(40).map(char::from).collect();
+    format!("{}{}", API_TOKEN_PREFIX, secret)
+}
+
+/// What `api_tokens.token_hash` holds for `token`. A plain digest is enough:
+/// tokens are random, so there's nothing to guess a preimage from.
+pub fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
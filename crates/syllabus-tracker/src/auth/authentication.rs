@@ -5,11 +5,75 @@ use rocket::response::Redirect;
 use rocket::response::status::Custom;
 use rocket::serde::json::Json;
 use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
 use sqlx::SqlitePool;
 
-use crate::db::{extend_session_expiry, get_session_by_token, get_user};
+use crate::config::LiveConfig;
+use crate::db::{
+    extend_session_expiry, find_api_token_grant, get_session_by_token, get_user, touch_api_token,
+};
+use crate::ids::UserId;
 
-use super::{User, UserSession};
+use super::{Credential, TokenScope, User};
+
+/// The token in an `Authorization: Bearer` header.
+pub fn bearer_token<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    let value = request.headers().get_one("Authorization")?;
+    value.strip_prefix("Bearer ").map(str::trim)
+}
+
+/// Signs a request in with a personal access token (see `auth::api_tokens`).
+/// A token that's unknown, expired or an archived user's is refused outright
+/// rather than falling back to the session cookie.
+async fn api_token_user(request: &Request<'_>, token: &str) -> Outcome<User, ()> {
+    let Some(db) = request.rocket().state::<SqlitePool>() else {
+        tracing::error!("Database pool not found in managed state");
+        return Outcome::Error((Status::InternalServerError, ()));
+    };
+    let grant = match find_api_token_grant(db, token).await {
+        Ok(grant) => grant,
+        Err(err) => {
+            tracing::error!(error = ?err, "Failed to look up API token");
+            return Outcome::Error((Status::InternalServerError, ()));
+        }
+    };
+    let Some(grant) = grant.filter(|grant| grant.is_valid()) else {
+        tracing::warn!("Unknown or expired API token");
+        return Outcome::Forward(Status::Unauthorized);
+    };
+    let user = match get_user(db, grant.user_id).await {
+        Ok(user) => user,
+        Err(err) => {
+            tracing::error!(
+                user_id = %grant.user_id,
+                error = ?err,
+                "Failed to fetch user for API token"
+            );
+            return Outcome::Error((Status::InternalServerError, ()));
+        }
+    };
+    // Archiving someone stops their scripts too.
+    if user.archived {
+        tracing::warn!(user_id = %user.id, token_id = grant.id, "API token of archived user");
+        return Outcome::Forward(Status::Unauthorized);
+    }
+    let needed = TokenScope::for_method(request.method());
+    if !grant.scopes.contains(&needed) {
+        tracing::warn!(token_id = grant.id, scope = needed.as_str(), "API token lacks scope");
+        return Outcome::Error((Status::Forbidden, ()));
+    }
+
+    if let Err(err) = touch_api_token(db, grant.id).await {
+        tracing::warn!(error = ?err, "Failed to record API token use");
+    }
+    request.local_cache(|| Credential::ApiToken);
+    tracing::info!(
+        username = %user.username,
+        token_id = grant.id,
+        "User authenticated via API token"
+    );
+    Outcome::Success(user)
+}
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for User {
@@ -19,6 +83,10 @@ impl<'r> FromRequest<'r> for User {
         let auth_span = tracing::info_span!("user_auth_guard");
         let _guard = auth_span.enter();
 
+        if let Some(token) = bearer_token(request) {
+            return api_token_user(request, token).await;
+        }
+
         let cookies = request.cookies();
 
         let token = cookies
@@ -48,7 +116,13 @@ impl<'r> FromRequest<'r> for User {
                     // (encrypted, server-issued) tokens so we re-emit them
                     // with the same token + a fresh max_age.
                     let now = chrono::Utc::now().naive_utc();
-                    let lifetime = chrono::Duration::days(UserSession::LIFETIME_DAYS);
+                    let Some(config) = request.rocket().state::<LiveConfig>() else {
+                        tracing::error!("LiveConfig not found in managed state");
+                        return Outcome::Error((Status::InternalServerError, ()));
+                    };
+                    let ttl_days = config.get().session_ttl_days;
+
+                    let lifetime = chrono::Duration::days(ttl_days);
                     let remaining = session.expires_at.signed_duration_since(now);
                     if remaining < lifetime / 2 {
                         let new_expiry = now + lifetime;
@@ -56,7 +130,7 @@ impl<'r> FromRequest<'r> for User {
                             tracing::warn!(error = ?err, "Failed to slide session expiry");
                         } else {
                             use rocket::http::{Cookie, SameSite};
-                            let max_age = rocket::time::Duration::days(UserSession::LIFETIME_DAYS);
+                            let max_age = rocket::time::Duration::days(ttl_days);
                             cookies.add_private(
                                 Cookie::build(("session_token", token.clone()))
                                     .same_site(SameSite::Lax)
@@ -67,7 +141,7 @@ impl<'r> FromRequest<'r> for User {
                     }
 
                     // Fetch the associated user
-                    match get_user(db, session.user_id).await {
+                    match get_user(db, UserId(session.user_id)).await {
                         Ok(user) => {
                             tracing::info!(username = %user.username, role = %user.role.as_str(), "User authenticated via session token");
                             return Outcome::Success(user);
@@ -89,6 +163,60 @@ impl<'r> FromRequest<'r> for User {
     }
 }
 
+/// A `User` signed in with a session cookie, for routes an API token mustn't
+/// reach, like making more tokens. A token gets a 403.
+pub struct SessionUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SessionUser {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = rocket::outcome::try_outcome!(request.guard::<User>().await);
+        if *request.local_cache(|| Credential::Session) == Credential::ApiToken {
+            tracing::warn!(user_id = %user.id, "API token used where a session is required");
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+        Outcome::Success(SessionUser(user))
+    }
+}
+
+/// A call from the billing system, which sends the configured
+/// `MEMBERSHIP_WEBHOOK_SECRET` as a bearer token. With no secret set the
+/// webhook is off and answers 404.
+pub struct BillingWebhook;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BillingWebhook {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(config) = request.rocket().state::<LiveConfig>() else {
+            tracing::error!("LiveConfig not found in managed state");
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        let config = config.get();
+        let Some(secret) = config.membership_webhook_secret.as_deref() else {
+            return Outcome::Error((Status::NotFound, ()));
+        };
+        let token = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        // Comparing digests keeps the comparison time independent of how
+        // much of the secret a guess gets right.
+        match token {
+            Some(token) if Sha256::digest(token) == Sha256::digest(secret) => {
+                Outcome::Success(BillingWebhook)
+            }
+            _ => {
+                tracing::warn!("Billing webhook called with a missing or wrong secret");
+                Outcome::Error((Status::Unauthorized, ()))
+            }
+        }
+    }
+}
+
 #[catch(401)]
 pub fn unauthorized_api(_req: &Request) -> Result<Redirect, Custom<Json<Value>>> {
     let error_json = json!({
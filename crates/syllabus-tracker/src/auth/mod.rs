@@ -1,7 +1,9 @@
+pub mod api_tokens;
 pub mod authentication;
 pub mod permissions;
 pub mod user;
 
+pub use api_tokens::*;
 pub use authentication::*;
 pub use permissions::*;
 pub use user::*;
@@ -1,11 +1,14 @@
 use anyhow::Error;
 use once_cell::sync::Lazy;
-use rocket::serde::Serialize;
+use rocket::serde::{Deserialize, Deserializer, Serialize, de};
 use std::collections::HashSet;
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Serialized in snake_case for `/api/me`, which lists the signed-in user's
+/// permissions in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Permission {
     ViewOwnProfile,
     EditOwnProfile,
@@ -13,11 +16,19 @@ pub enum Permission {
     EditOwnNotes,
 
     ViewAllStudents,
+    /// Every student, even for a coach with students assigned (see
+    /// `db::coach_students`).
+    ViewAllGymStudents,
     EditAllTechniques,
     AssignTechniques,
     CreateTechniques,
+    DeleteTechniques,
     RegisterUsers,
     ManageTags,
+    TrackAttendance,
+    ManageSchedule,
+    ConductGradings,
+    ViewStudentJournals,
 
     EditUserRoles,
     DeleteUsers,
@@ -28,9 +39,23 @@ pub enum Permission {
     ManageVideoVisibility,
     ViewWatchStats,
     ViewStorageStats,
+    ViewCoachReport,
+
+    ArchiveTags,
+    ManageStatusTransitions,
+    ManageStatuses,
+    ManageRanks,
+    ManageFeatureFlags,
+    ImportSyllabus,
+    ManageMemberships,
+    ManageNoteTemplates,
+    ManageConfig,
+    ViewSystemStatus,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// Variants are declared in ascending order of privilege, so `Ord` compares
+/// seniority (`Role::Admin > Role::Coach`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum Role {
     Student,
     Coach,
@@ -57,8 +82,13 @@ static COACH_PERMISSIONS: Lazy<HashSet<Permission>> = Lazy::new(|| {
     permissions.insert(Permission::EditAllTechniques);
     permissions.insert(Permission::AssignTechniques);
     permissions.insert(Permission::CreateTechniques);
+    permissions.insert(Permission::DeleteTechniques);
     permissions.insert(Permission::RegisterUsers);
     permissions.insert(Permission::ManageTags);
+    permissions.insert(Permission::TrackAttendance);
+    permissions.insert(Permission::ManageSchedule);
+    permissions.insert(Permission::ConductGradings);
+    permissions.insert(Permission::ViewStudentJournals);
 
     permissions.insert(Permission::UploadVideos);
     permissions.insert(Permission::DeleteVideos);
@@ -76,8 +106,20 @@ static ADMIN_PERMISSIONS: Lazy<HashSet<Permission>> = Lazy::new(|| {
     permissions.insert(Permission::EditUserRoles);
     permissions.insert(Permission::DeleteUsers);
     permissions.insert(Permission::EditUserCredentials);
+    permissions.insert(Permission::ViewAllGymStudents);
 
     permissions.insert(Permission::ViewStorageStats);
+    permissions.insert(Permission::ViewCoachReport);
+    permissions.insert(Permission::ArchiveTags);
+    permissions.insert(Permission::ManageStatusTransitions);
+    permissions.insert(Permission::ManageStatuses);
+    permissions.insert(Permission::ManageRanks);
+    permissions.insert(Permission::ManageFeatureFlags);
+    permissions.insert(Permission::ImportSyllabus);
+    permissions.insert(Permission::ManageMemberships);
+    permissions.insert(Permission::ManageNoteTemplates);
+    permissions.insert(Permission::ManageConfig);
+    permissions.insert(Permission::ViewSystemStatus);
 
     permissions
 });
@@ -95,6 +137,13 @@ impl Role {
         self.permissions().contains(&permission)
     }
 
+    /// `permissions()` in declaration order, for responses.
+    pub fn sorted_permissions(&self) -> Vec<Permission> {
+        let mut permissions: Vec<Permission> = self.permissions().iter().copied().collect();
+        permissions.sort();
+        permissions
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             Role::Student => "student",
@@ -118,6 +167,20 @@ impl FromStr for Role {
     }
 }
 
+/// Request DTOs take `Role` directly so an unknown role is rejected while the
+/// body is parsed, before it can reach the database.
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Role::from_str(&s).map_err(|_| {
+            de::Error::custom(format!(
+                "unknown role '{}', expected one of: student, coach, admin",
+                s
+            ))
+        })
+    }
+}
+
 impl fmt::Display for Role {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
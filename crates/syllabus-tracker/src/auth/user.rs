@@ -5,10 +5,14 @@ use serde::Serialize;
 use std::str::FromStr;
 
 use super::{Permission, Role};
+use crate::db::MembershipStatus;
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::{naive_to_rfc3339, required};
 
 #[derive(Debug, Serialize, Clone)]
 pub struct User {
-    pub id: i64,
+    pub id: UserId,
     pub username: String,
     pub role: Role,
     pub display_name: String,
@@ -27,9 +31,15 @@ pub struct User {
     pub amber_count: Option<i64>,
     pub green_count: Option<i64>,
     pub has_unseen_activity: Option<bool>,
+    /// Assigned techniques the student has asked a coach to review.
+    pub review_requests: Option<i64>,
     pub last_student_initiative_at: Option<String>,
     pub last_watch_at: Option<String>,
     pub last_watch_video_title: Option<String>,
+    pub timezone: Option<String>,
+    pub membership_status: Option<MembershipStatus>,
+    /// The uploaded profile picture (see `api_upload_avatar`).
+    pub avatar_attachment_id: Option<i64>,
 }
 
 #[derive(sqlx::FromRow, Clone)]
@@ -46,27 +56,41 @@ pub struct DbUser {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub reset_requested_at: Option<chrono::NaiveDateTime>,
+    pub timezone: Option<String>,
+    pub membership_status: Option<String>,
+    pub avatar_attachment_id: Option<i64>,
 }
 
-fn naive_to_iso(dt: chrono::NaiveDateTime) -> String {
-    chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(dt, chrono::Utc).to_rfc3339()
+/// Parses `users.role`, which is free text in SQLite. Shared with the
+/// reporting queries that build `User` from their own row types.
+pub(crate) fn role_from_db(id: i64, role: Option<String>) -> Result<Role, AppError> {
+    let role = required(role, "users.role")?;
+    Role::from_str(&role).map_err(|_| {
+        AppError::Internal(format!("User {} has unknown role '{}' in database", id, role))
+    })
 }
 
-impl From<DbUser> for User {
-    fn from(user: DbUser) -> Self {
-        Self {
-            id: user.id.unwrap_or_default(),
+impl TryFrom<DbUser> for User {
+    type Error = AppError;
+
+    fn try_from(user: DbUser) -> Result<Self, Self::Error> {
+        let id = required(user.id, "users.id")?;
+        let role = role_from_db(id, user.role)?;
+
+        Ok(Self {
+            id: UserId(id),
+            // Invited users have no username until they claim the invite.
             username: user.username.unwrap_or_default(),
-            role: Role::from_str(&user.role.unwrap_or_default()).unwrap(),
+            role,
             display_name: user.display_name.unwrap_or_default(),
-            archived: user.archived.unwrap_or_default(),
-            graduated_at: user.graduated_at.map(naive_to_iso),
+            archived: required(user.archived, "users.archived")?,
+            graduated_at: user.graduated_at.map(naive_to_rfc3339),
             email: user.email,
-            claimed_at: user.claimed_at.map(naive_to_iso),
-            approved_at: user.approved_at.map(naive_to_iso),
+            claimed_at: user.claimed_at.map(naive_to_rfc3339),
+            approved_at: user.approved_at.map(naive_to_rfc3339),
             first_name: user.first_name,
             last_name: user.last_name,
-            reset_requested_at: user.reset_requested_at.map(naive_to_iso),
+            reset_requested_at: user.reset_requested_at.map(naive_to_rfc3339),
             last_update: None,
             last_coach_update_at: None,
             total_techniques: None,
@@ -74,14 +98,29 @@ impl From<DbUser> for User {
             amber_count: None,
             green_count: None,
             has_unseen_activity: None,
+            review_requests: None,
             last_student_initiative_at: None,
             last_watch_at: None,
             last_watch_video_title: None,
-        }
+            timezone: user.timezone,
+            membership_status: MembershipStatus::from_db(id, user.membership_status)?,
+            avatar_attachment_id: user.avatar_attachment_id,
+        })
     }
 }
 
 impl User {
+    /// What every response shows as the user's name. Falls back to the
+    /// username when no display name is set, so lists never render a blank
+    /// entry.
+    pub fn effective_display_name(&self) -> &str {
+        if self.display_name.trim().is_empty() {
+            &self.username
+        } else {
+            &self.display_name
+        }
+    }
+
     pub fn has_permission(&self, permission: Permission) -> bool {
         self.role.has_permission(permission)
     }
@@ -164,11 +203,6 @@ impl From<DbUserSession> for UserSession {
 }
 
 impl UserSession {
-    /// How long a freshly issued session lasts before requiring re-login.
-    /// The auth guard slides this window forward on use, so an active user
-    /// effectively never logs in again.
-    pub const LIFETIME_DAYS: i64 = 30;
-
     pub fn is_valid(&self) -> bool {
         let now = Utc::now().naive_utc();
         self.expires_at > now
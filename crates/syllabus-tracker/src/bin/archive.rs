@@ -0,0 +1,150 @@
+//! Export the database at `DATABASE_URL` to a versioned JSON archive, or
+//! merge an archive into it. See `db::archive` for what is included and how
+//! ids are matched on import.
+//!
+//! Usage:
+//! - `archive export [--out FILE]`: write the archive to FILE (default stdout).
+//! - `archive import FILE [--dry-run]`: merge FILE in one transaction;
+//!   `--dry-run` prints what would change and rolls back.
+
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use syllabus_tracker::db::{Archive, ImportCounts, export_archive, import_archive};
+use syllabus_tracker::env;
+
+enum Command {
+    Export { out: Option<String> },
+    Import { file: String, dry_run: bool },
+}
+
+fn parse_args() -> Result<Command> {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("export") => {
+            let mut out = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--out" | "-o" => out = Some(args.next().context("--out needs a path")?),
+                    other => anyhow::bail!("Unknown argument: {}", other),
+                }
+            }
+            Command::Export { out }
+        }
+        Some("import") => {
+            let mut file = None;
+            let mut dry_run = false;
+            for arg in args {
+                match arg.as_str() {
+                    "--dry-run" => dry_run = true,
+                    other if other.starts_with('-') => {
+                        anyhow::bail!("Unknown argument: {}", other)
+                    }
+                    path => file = Some(path.to_string()),
+                }
+            }
+            Command::Import {
+                file: file.context("import needs an archive file")?,
+                dry_run,
+            }
+        }
+        Some("--help" | "-h") | None => {
+            print_help();
+            std::process::exit(0);
+        }
+        Some(other) => anyhow::bail!("Unknown command: {}", other),
+    };
+    Ok(command)
+}
+
+fn print_help() {
+    println!("Usage: archive export [--out FILE]");
+    println!("       archive import FILE [--dry-run]");
+    println!();
+    println!("Exports the database at $DATABASE_URL to versioned JSON, or merges");
+    println!("an export into it. Passwords and sessions are never exported;");
+    println!("imported users must be re-invited.");
+    println!();
+    println!("Options:");
+    println!("  --out FILE    write the export to FILE instead of stdout.");
+    println!("  --dry-run     report what an import would change, then roll back.");
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if let Err(e) = run().await {
+        eprintln!("Error: {:#}", e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+async fn run() -> Result<()> {
+    env::load_environment().ok();
+    let command = parse_args()?;
+
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://sqlite.db".to_string());
+    let opts = SqliteConnectOptions::from_str(&url)
+        .with_context(|| format!("Invalid DATABASE_URL: {}", url))?;
+    let pool = SqlitePool::connect_with(opts)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+
+    match command {
+        Command::Export { out } => {
+            let archive = export_archive(&pool).await?;
+            let json = serde_json::to_string_pretty(&archive)?;
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, json).with_context(|| format!("Writing {}", path))?;
+                    eprintln!(
+                        "Exported {} users, {} techniques, {} assignments, {} attempts to {}",
+                        archive.users.len(),
+                        archive.techniques.len(),
+                        archive.student_techniques.len(),
+                        archive.attempts.len(),
+                        path
+                    );
+                }
+                None => println!("{}", json),
+            }
+        }
+        Command::Import { file, dry_run } => {
+            let json =
+                std::fs::read_to_string(&file).with_context(|| format!("Reading {}", file))?;
+            let archive: Archive =
+                serde_json::from_str(&json).with_context(|| format!("Parsing {}", file))?;
+            let summary = import_archive(&pool, &archive, dry_run).await?;
+
+            let row = |label: &str, counts: ImportCounts| {
+                println!(
+                    "  {:<20} {:>6} new  {:>6} existing",
+                    label, counts.created, counts.existing
+                )
+            };
+            println!(
+                "{} {} into {}:",
+                if dry_run { "Would import" } else { "Imported" },
+                file,
+                url
+            );
+            row("users", summary.users);
+            row("tags", summary.tags);
+            row("techniques", summary.techniques);
+            row("collections", summary.collections);
+            row("student techniques", summary.student_techniques);
+            row("attempts", summary.attempts);
+            row("status transitions", summary.status_transitions);
+            row("statuses", summary.statuses);
+            if dry_run {
+                println!("Dry run: nothing was written.");
+            } else if summary.users.created > 0 {
+                println!("New users have no password; send them invites from the admin page.");
+            }
+        }
+    }
+    Ok(())
+}
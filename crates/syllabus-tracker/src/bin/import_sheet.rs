@@ -0,0 +1,141 @@
+//! Import a syllabus spreadsheet, exported as CSV, into the database at
+//! `DATABASE_URL`. Same pipeline as `POST /api/admin/import/spreadsheet`;
+//! see `db::spreadsheet` for how rows are matched on re-import.
+//!
+//! Usage: `import_sheet FILE --coach USERNAME [--dry-run] [column options]`
+
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use syllabus_tracker::db::{
+    ImportCounts, SheetColumns, find_user_by_username, import_spreadsheet, parse_spreadsheet,
+};
+use syllabus_tracker::env;
+use syllabus_tracker::validation::ValidationConfig;
+
+struct Args {
+    file: String,
+    coach: String,
+    columns: SheetColumns,
+    dry_run: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut file = None;
+    let mut coach = None;
+    let mut columns = SheetColumns::default();
+    let mut dry_run = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--coach" => coach = Some(value()?),
+            "--name-column" => columns.name = value()?,
+            "--description-column" => columns.description = value()?,
+            "--tags-column" => columns.tags = value()?,
+            "--curriculum-column" => columns.curriculum = value()?,
+            "--tag-separator" => {
+                let separator = value()?;
+                let mut chars = separator.chars();
+                columns.tag_separator = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => anyhow::bail!("--tag-separator must be one character"),
+                };
+            }
+            "--dry-run" => dry_run = true,
+            "--help" | "-h" => {
+                print_help();
+                std::process::exit(0);
+            }
+            other if other.starts_with('-') => anyhow::bail!("Unknown argument: {}", other),
+            path => file = Some(path.to_string()),
+        }
+    }
+
+    Ok(Args {
+        file: file.context("Missing the CSV file to import (see --help)")?,
+        coach: coach.context("--coach is required: the owner of created techniques")?,
+        columns,
+        dry_run,
+    })
+}
+
+fn print_help() {
+    let defaults = SheetColumns::default();
+    println!("Usage: import_sheet FILE --coach USERNAME [options]");
+    println!();
+    println!("Imports a syllabus spreadsheet saved as CSV. Each row is a technique;");
+    println!("techniques, tags and curricula that already exist are reused, so an");
+    println!("edited sheet can be imported again.");
+    println!();
+    println!("Options:");
+    println!("  --coach USERNAME            owner of created techniques and curricula.");
+    println!("  --name-column NAME          default '{}'.", defaults.name);
+    println!("  --description-column NAME   default '{}'.", defaults.description);
+    println!("  --tags-column NAME          default '{}'.", defaults.tags);
+    println!("  --curriculum-column NAME    default '{}'.", defaults.curriculum);
+    println!("  --tag-separator CHAR        default '{}'.", defaults.tag_separator);
+    println!("  --dry-run                   report what would change, then roll back.");
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if let Err(e) = run().await {
+        eprintln!("Error: {:#}", e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+async fn run() -> Result<()> {
+    env::load_environment().ok();
+    let args = parse_args()?;
+
+    let csv =
+        std::fs::read_to_string(&args.file).with_context(|| format!("Reading {}", args.file))?;
+    let rows = parse_spreadsheet(&csv, &args.columns).map_err(anyhow::Error::msg)?;
+
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://sqlite.db".to_string());
+    let opts = SqliteConnectOptions::from_str(&url)
+        .with_context(|| format!("Invalid DATABASE_URL: {}", url))?;
+    let pool = SqlitePool::connect_with(opts)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+
+    let coach = find_user_by_username(&pool, &args.coach)
+        .await?
+        .with_context(|| format!("No user named '{}'", args.coach))?;
+    let config = ValidationConfig::from_env()?;
+    let report = import_spreadsheet(&pool, &rows, coach.id, &config, args.dry_run).await?;
+
+    let row = |label: &str, counts: ImportCounts| {
+        println!(
+            "  {:<12} {:>6} new  {:>6} existing",
+            label, counts.created, counts.existing
+        )
+    };
+    println!(
+        "{} {} rows from {}:",
+        if args.dry_run { "Would import" } else { "Imported" },
+        report.rows,
+        args.file
+    );
+    row("techniques", report.techniques);
+    row("tags", report.tags);
+    row("curricula", report.curricula);
+    if !report.problems.is_empty() {
+        println!("Problems:");
+        for problem in &report.problems {
+            let outcome = if problem.skipped { "skipped" } else { "imported" };
+            println!("  line {} ({}): {}", problem.line, outcome, problem.message);
+        }
+    }
+    if args.dry_run {
+        println!("Dry run: nothing was written.");
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+//! Load generator for a running instance. Each worker logs in as one of the
+//! `--user` accounts, then loops the flow the SPA drives hardest: list
+//! students (coaches and admins only), open one student's techniques, and
+//! save a note on one of them. Latency is recorded per step and reported as
+//! percentiles at the end, so a slow query in `db/` shows up as a number
+//! before release rather than as a complaint after.
+//!
+//! Requests and responses go through the same DTOs as `api.rs`, so a
+//! breaking API change fails here at compile time or as a decode error.
+//!
+//! Point it at a database filled by `seed_demo` (or `seed`) and run with
+//! `just loadtest`. Each update overwrites the chosen note, so never aim it
+//! at production data.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use reqwest::{Client, StatusCode};
+use syllabus_tracker::api::{
+    LoginRequest, LoginResponse, Paginated, StudentTechniquesResponse, TechniqueUpdateRequest,
+    UserData,
+};
+use syllabus_tracker::ids::UserId;
+
+struct Args {
+    base_url: String,
+    users: Vec<(String, String)>,
+    concurrency: usize,
+    duration: Duration,
+    login_every: usize,
+    think: Duration,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut base_url = "http://localhost:8000".to_string();
+    let mut users = Vec::new();
+    let mut concurrency = 8;
+    let mut duration = 30;
+    let mut login_every = 0;
+    let mut think = 0;
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            print_help();
+            std::process::exit(0);
+        }
+        let mut value = || {
+            iter.next()
+                .with_context(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--base-url" => base_url = value()?,
+            "--user" => {
+                let spec = value()?;
+                let (name, password) = spec
+                    .split_once(':')
+                    .with_context(|| format!("--user expects USER:PASSWORD, got {}", spec))?;
+                users.push((name.to_string(), password.to_string()));
+            }
+            "--concurrency" | "-c" => concurrency = value()?.parse().context("--concurrency")?,
+            "--duration" | "-d" => duration = value()?.parse().context("--duration")?,
+            "--login-every" => login_every = value()?.parse().context("--login-every")?,
+            "--think-ms" => think = value()?.parse().context("--think-ms")?,
+            other => anyhow::bail!("Unknown argument: {}", other),
+        }
+    }
+    if users.is_empty() {
+        users.push(("demo_coach".to_string(), "password".to_string()));
+    }
+    anyhow::ensure!(concurrency > 0, "--concurrency must be at least 1");
+    Ok(Args {
+        base_url: base_url.trim_end_matches('/').to_string(),
+        users,
+        concurrency,
+        duration: Duration::from_secs(duration),
+        login_every,
+        think: Duration::from_millis(think),
+    })
+}
+
+fn print_help() {
+    println!("Usage: loadtest [options]");
+    println!();
+    println!("Drives login, list and update flows against a running API and");
+    println!("prints latency percentiles per step.");
+    println!();
+    println!("Options:");
+    println!("  --base-url URL       server to hit (default http://localhost:8000)");
+    println!("  --user USER:PASS     account to log in as; repeat to spread workers");
+    println!("                       over several (default demo_coach:password)");
+    println!("  -c, --concurrency N  parallel workers (default 8)");
+    println!("  -d, --duration S     seconds to run (default 30)");
+    println!("  --login-every N      log in again every N iterations; 0 logs in");
+    println!("                       once per worker (default 0)");
+    println!("  --think-ms N         pause between iterations (default 0)");
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Step {
+    Login,
+    ListStudents,
+    StudentTechniques,
+    UpdateTechnique,
+}
+
+impl Step {
+    fn label(self) -> &'static str {
+        match self {
+            Step::Login => "POST /api/login",
+            Step::ListStudents => "GET /api/students",
+            Step::StudentTechniques => "GET /api/student/<id>/techniques",
+            Step::UpdateTechnique => "PUT /api/student_technique/<id>",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    ok: Vec<Duration>,
+    errors: BTreeMap<String, usize>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    steps: BTreeMap<Step, Samples>,
+}
+
+impl Recorder {
+    fn ok(&mut self, step: Step, elapsed: Duration) {
+        self.steps.entry(step).or_default().ok.push(elapsed);
+    }
+
+    fn error(&mut self, step: Step, reason: String) {
+        *self
+            .steps
+            .entry(step)
+            .or_default()
+            .errors
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    fn merge(&mut self, other: Recorder) {
+        for (step, samples) in other.steps {
+            let into = self.steps.entry(step).or_default();
+            into.ok.extend(samples.ok);
+            for (reason, n) in samples.errors {
+                *into.errors.entry(reason).or_default() += n;
+            }
+        }
+    }
+}
+
+/// Nearest-rank percentile over an already sorted slice.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.1}", d.as_secs_f64() * 1000.0)
+}
+
+/// Times one request and decodes the body as `T`. Non-2xx and decode
+/// failures are recorded as errors and come back as `None`.
+async fn timed<T: serde::de::DeserializeOwned>(
+    recorder: &mut Recorder,
+    step: Step,
+    request: reqwest::RequestBuilder,
+) -> Option<T> {
+    let started = Instant::now();
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            recorder.error(step, format!("transport: {}", e.without_url()));
+            return None;
+        }
+    };
+    let status = response.status();
+    if !status.is_success() {
+        recorder.error(step, status.to_string());
+        return None;
+    }
+    // An empty body (`Status::Ok` handlers) decodes as unit.
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            recorder.error(step, format!("body: {}", e.without_url()));
+            return None;
+        }
+    };
+    let elapsed = started.elapsed();
+    let body = if bytes.is_empty() {
+        &b"null"[..]
+    } else {
+        &bytes[..]
+    };
+    match serde_json::from_slice(body) {
+        Ok(value) => {
+            recorder.ok(step, elapsed);
+            Some(value)
+        }
+        Err(e) => {
+            recorder.error(step, format!("decode: {}", e));
+            None
+        }
+    }
+}
+
+struct Session {
+    user_id: UserId,
+    can_list: bool,
+}
+
+async fn login(
+    client: &Client,
+    base_url: &str,
+    recorder: &mut Recorder,
+    (username, password): &(String, String),
+) -> Option<Session> {
+    let body = LoginRequest {
+        username: username.clone(),
+        password: password.clone(),
+    };
+    let request = client.post(format!("{}/api/login", base_url)).json(&body);
+    let response: LoginResponse = timed(recorder, Step::Login, request).await?;
+    let user = response.user?;
+    Some(Session {
+        user_id: user.id,
+        can_list: user.role != "student",
+    })
+}
+
+async fn worker(
+    n: usize,
+    base_url: String,
+    account: (String, String),
+    deadline: Instant,
+    login_every: usize,
+    think: Duration,
+) -> Result<Recorder> {
+    let client = Client::builder()
+        .cookie_store(true)
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut rng = StdRng::seed_from_u64(n as u64);
+    let mut recorder = Recorder::default();
+    let mut session = None;
+    let mut iteration = 0usize;
+
+    while Instant::now() < deadline {
+        if session.is_none() || (login_every > 0 && iteration % login_every == 0) {
+            session = login(&client, &base_url, &mut recorder, &account).await;
+        }
+        iteration += 1;
+        let Some(current) = &session else {
+            // A failed login is already recorded; back off so a wrong
+            // password doesn't turn into a tight loop.
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            continue;
+        };
+
+        let student_id = if current.can_list {
+            let request = client.get(format!("{}/api/students", base_url));
+            let students: Option<Paginated<UserData>> =
+                timed(&mut recorder, Step::ListStudents, request).await;
+            match students.as_ref().and_then(|s| s.items.choose(&mut rng)) {
+                Some(student) => student.id,
+                None => continue,
+            }
+        } else {
+            current.user_id
+        };
+
+        let request = client.get(format!(
+            "{}/api/student/{}/techniques",
+            base_url, student_id
+        ));
+        let techniques: Option<StudentTechniquesResponse> =
+            timed(&mut recorder, Step::StudentTechniques, request).await;
+        let Some(technique) = techniques
+            .as_ref()
+            .and_then(|t| t.techniques.items.choose(&mut rng))
+        else {
+            continue;
+        };
+
+        let note = format!("loadtest {} #{}", n, rng.random::<u16>());
+        let update = if current.can_list {
+            TechniqueUpdateRequest {
+                coach_notes: Some(note),
+                ..Default::default()
+            }
+        } else {
+            TechniqueUpdateRequest {
+                student_notes: Some(note),
+                ..Default::default()
+            }
+        };
+        let request = client
+            .put(format!(
+                "{}/api/student_technique/{}",
+                base_url, technique.id
+            ))
+            .json(&update);
+        let _: Option<()> = timed(&mut recorder, Step::UpdateTechnique, request).await;
+
+        if !think.is_zero() {
+            tokio::time::sleep(think).await;
+        }
+    }
+    Ok(recorder)
+}
+
+fn report(recorder: &Recorder, wall: Duration) {
+    println!();
+    println!(
+        "{:<34} {:>8} {:>7} {:>9} {:>9} {:>9} {:>9} {:>8}",
+        "step", "ok", "errors", "p50 ms", "p90 ms", "p99 ms", "max ms", "req/s"
+    );
+    for (step, samples) in &recorder.steps {
+        let mut sorted = samples.ok.clone();
+        sorted.sort();
+        let errors: usize = samples.errors.values().sum();
+        let rate = (sorted.len() + errors) as f64 / wall.as_secs_f64();
+        println!(
+            "{:<34} {:>8} {:>7} {:>9} {:>9} {:>9} {:>9} {:>8.1}",
+            step.label(),
+            sorted.len(),
+            errors,
+            ms(percentile(&sorted, 50.0)),
+            ms(percentile(&sorted, 90.0)),
+            ms(percentile(&sorted, 99.0)),
+            ms(sorted.last().copied().unwrap_or_default()),
+            rate,
+        );
+    }
+    let failures: Vec<_> = recorder
+        .steps
+        .iter()
+        .flat_map(|(step, s)| s.errors.iter().map(move |(reason, n)| (step, reason, n)))
+        .collect();
+    if !failures.is_empty() {
+        println!();
+        println!("Errors:");
+        for (step, reason, n) in failures {
+            println!("  {:<34} {:>6} x {}", step.label(), n, reason);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(true) => ExitCode::SUCCESS,
+        // Finished, but some requests failed.
+        Ok(false) => ExitCode::from(2),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+async fn run() -> Result<bool> {
+    let args = parse_args()?;
+
+    // Fail fast on a wrong URL instead of reporting a wall of transport
+    // errors after the full duration.
+    let health = reqwest::get(format!("{}/api/health", args.base_url))
+        .await
+        .with_context(|| format!("Cannot reach {}", args.base_url))?;
+    anyhow::ensure!(
+        health.status() == StatusCode::OK,
+        "{}/api/health returned {}",
+        args.base_url,
+        health.status()
+    );
+
+    println!(
+        "Load testing {} with {} workers for {}s",
+        args.base_url,
+        args.concurrency,
+        args.duration.as_secs()
+    );
+
+    let started = Instant::now();
+    let deadline = started + args.duration;
+    let handles: Vec<_> = (0..args.concurrency)
+        .map(|n| {
+            let account = args.users[n % args.users.len()].clone();
+            tokio::spawn(worker(
+                n,
+                args.base_url.clone(),
+                account,
+                deadline,
+                args.login_every,
+                args.think,
+            ))
+        })
+        .collect();
+
+    let mut total = Recorder::default();
+    for handle in handles {
+        total.merge(handle.await.context("worker panicked")??);
+    }
+    let wall = started.elapsed();
+
+    report(&total, wall);
+    Ok(total.steps.values().all(|s| s.errors.is_empty()))
+}
@@ -21,6 +21,7 @@ use syllabus_tracker::db::{
     get_tag_by_name,
 };
 use syllabus_tracker::env;
+use syllabus_tracker::ids::{TagId, TechniqueId, UserId};
 use syllabus_tracker::lib::seed::{ItemOutcome, SeedReporter, TerminalSeedReporter};
 
 const STUDENT_NAMES: &[(&str, &str)] = &[
@@ -195,7 +196,7 @@ async fn ensure_user(
     password: &str,
     role: Role,
     display_name: &str,
-) -> Result<(i64, ItemOutcome)> {
+) -> Result<(UserId, ItemOutcome)> {
     let (id, outcome) = match find_user_by_username(pool, username).await? {
         Some(existing) => (existing.id, ItemOutcome::Existed),
         None => (
@@ -225,9 +226,9 @@ async fn ensure_user(
     Ok((id, outcome))
 }
 
-async fn ensure_tag(pool: &SqlitePool, name: &str) -> Result<(i64, ItemOutcome)> {
+async fn ensure_tag(pool: &SqlitePool, name: &str) -> Result<(TagId, ItemOutcome)> {
     if let Some(tag) = get_tag_by_name(pool, name).await? {
-        return Ok((tag.id, ItemOutcome::Existed));
+        return Ok((TagId(tag.id), ItemOutcome::Existed));
     }
     Ok((create_tag(pool, name).await?, ItemOutcome::Created))
 }
@@ -236,11 +237,11 @@ async fn ensure_technique(
     pool: &SqlitePool,
     name: &str,
     description: &str,
-    coach_id: i64,
-    tag_ids: &[i64],
-) -> Result<(i64, ItemOutcome)> {
+    coach_id: UserId,
+    tag_ids: &[TagId],
+) -> Result<(TechniqueId, ItemOutcome)> {
     // Idempotency: look up by name.
-    let existing: Option<(i64,)> = sqlx::query_as("SELECT id FROM techniques WHERE name = ? LIMIT 1")
+    let existing: Option<(TechniqueId,)> = sqlx::query_as("SELECT id FROM techniques WHERE name = ? LIMIT 1")
         .bind(name)
         .fetch_optional(pool)
         .await?;
@@ -340,7 +341,7 @@ async fn run() -> Result<()> {
         "Passes",
     ];
     reporter.phase_started(phases[3], Some(TAGS.len() as u64));
-    let mut tag_ids: std::collections::HashMap<&str, i64> = std::collections::HashMap::new();
+    let mut tag_ids: std::collections::HashMap<&str, TagId> = std::collections::HashMap::new();
     for tag in TAGS {
         let (id, outcome) = ensure_tag(&pool, tag).await?;
         tag_ids.insert(tag, id);
@@ -350,9 +351,9 @@ async fn run() -> Result<()> {
 
     // 3. Techniques (with tags)
     reporter.phase_started(phases[4], Some(TECHNIQUES.len() as u64));
-    let mut technique_ids: Vec<i64> = Vec::with_capacity(TECHNIQUES.len());
+    let mut technique_ids: Vec<TechniqueId> = Vec::with_capacity(TECHNIQUES.len());
     for (name, description, tags) in TECHNIQUES {
-        let tids: Vec<i64> = tags
+        let tids: Vec<TagId> = tags
             .iter()
             .filter_map(|t| tag_ids.get(*t).copied())
             .collect();
@@ -377,6 +378,7 @@ async fn run() -> Result<()> {
                     &pool,
                     "Blue Belt Fundamentals",
                     "Core syllabus for blue belt students.",
+                    Some("Blue"),
                     coach_id,
                 )
                 .await?;
@@ -392,7 +394,7 @@ async fn run() -> Result<()> {
 
     // 4. Students
     reporter.phase_started(phases[6], Some(STUDENT_NAMES.len() as u64));
-    let mut student_ids: Vec<i64> = Vec::with_capacity(STUDENT_NAMES.len());
+    let mut student_ids: Vec<UserId> = Vec::with_capacity(STUDENT_NAMES.len());
     for (username, display_name) in STUDENT_NAMES {
         let (id, outcome) =
             ensure_user(&pool, username, "demo", Role::Student, display_name).await?;
@@ -428,7 +430,7 @@ async fn run() -> Result<()> {
             continue;
         }
 
-        let technique_indices = pick_indices(technique_ids.len(), count, student_id as usize);
+        let technique_indices = pick_indices(technique_ids.len(), count, student_id.0 as usize);
 
         let coach_update_time = now - Duration::days(days_since_coach);
         let student_update_time = if has_new_activity {
@@ -577,10 +579,10 @@ async fn run() -> Result<()> {
             // Status-driven distribution. The deterministic stride mixed
             // with the index keeps the spread different per technique.
             let target = match status.as_str() {
-                "green" => 4 + ((student_id + *st_id) as usize % 5), // 4..=8
-                "amber" => 1 + ((student_id + *st_id) as usize % 3), // 1..=3
+                "green" => 4 + ((student_id.0 + *st_id) as usize % 5), // 4..=8
+                "amber" => 1 + ((student_id.0 + *st_id) as usize % 3), // 1..=3
                 _ => {
-                    if (student_id as usize + idx) % 6 == 0 {
+                    if (student_id.0 as usize + idx) % 6 == 0 {
                         1
                     } else {
                         0
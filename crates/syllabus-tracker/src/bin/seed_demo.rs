@@ -0,0 +1,563 @@
+//! Bulk fake-data seed for demos, SPA development and load testing. Unlike
+//! `seed`, which writes a small hand-written data set and is safe to re-run,
+//! this one generates as much as you ask for with the `fake` crate: gyms,
+//! coaches, students, techniques, tags, and a history of status changes and
+//! attempts spread over the last `--history-days`.
+//!
+//! The schema has no gym entity, so a "gym" here is a head coach plus a
+//! curriculum collection that all of that gym's students are subscribed to.
+//! Every generated username starts with `--prefix` (default `fake`); the run
+//! refuses to start if users with that prefix already exist, so pick another
+//! prefix or `just clean` first.
+//!
+//! Output is reproducible for a given `--seed`. Run with `just seed-demo`.
+
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use fake::Fake;
+use fake::faker::company::en::CompanyName;
+use fake::faker::lorem::en::Sentence;
+use fake::faker::name::en::{FirstName, LastName};
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use syllabus_tracker::auth::Role;
+use syllabus_tracker::db::{
+    BCRYPT_COST_RANGE, add_tag_to_technique, add_technique_to_collection,
+    assign_collection_to_student, assign_technique_to_student, create_collection, create_tag,
+    create_technique, create_user, init_bcrypt_cost,
+};
+use syllabus_tracker::env;
+use syllabus_tracker::ids::{TagId, TechniqueId, UserId};
+use syllabus_tracker::lib::seed::{ItemOutcome, SeedReporter, TerminalSeedReporter};
+
+const POSITIONS: &[&str] = &[
+    "Closed Guard",
+    "Half Guard",
+    "Butterfly Guard",
+    "De La Riva",
+    "Spider Guard",
+    "Side Control",
+    "Mount",
+    "Back Control",
+    "North South",
+    "Turtle",
+    "Knee on Belly",
+    "Standing",
+];
+
+const MOVES: &[&str] = &[
+    "Armbar",
+    "Triangle",
+    "Kimura",
+    "Americana",
+    "Guillotine",
+    "Cross Collar Choke",
+    "Scissor Sweep",
+    "Hip Bump Sweep",
+    "Pendulum Sweep",
+    "Elbow Escape",
+    "Bridge Escape",
+    "Knee Slice Pass",
+    "Toreando Pass",
+    "Back Take",
+    "Single Leg",
+    "Double Leg",
+    "Wrist Lock",
+    "Ezekiel Choke",
+];
+
+const TAG_NAMES: &[&str] = &[
+    "Guard",
+    "Passing",
+    "Submissions",
+    "Sweeps",
+    "Escapes",
+    "Takedowns",
+    "Back",
+    "Mount",
+    "Half Guard",
+    "Leg Locks",
+    "Gi",
+    "No-Gi",
+    "Fundamentals",
+    "Competition",
+    "Self Defence",
+];
+
+struct Args {
+    gyms: usize,
+    coaches_per_gym: usize,
+    students_per_gym: usize,
+    techniques: usize,
+    tags: usize,
+    history_days: i64,
+    seed: u64,
+    prefix: String,
+    password: String,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            gyms: 2,
+            coaches_per_gym: 2,
+            students_per_gym: 20,
+            techniques: 60,
+            tags: 10,
+            history_days: 120,
+            seed: 42,
+            prefix: "fake".to_string(),
+            password: "demo".to_string(),
+        }
+    }
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            print_help();
+            std::process::exit(0);
+        }
+        let mut value = || {
+            iter.next()
+                .with_context(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--gyms" => args.gyms = value()?.parse().context("--gyms")?,
+            "--coaches-per-gym" => {
+                args.coaches_per_gym = value()?.parse().context("--coaches-per-gym")?
+            }
+            "--students-per-gym" => {
+                args.students_per_gym = value()?.parse().context("--students-per-gym")?
+            }
+            "--techniques" => args.techniques = value()?.parse().context("--techniques")?,
+            "--tags" => args.tags = value()?.parse().context("--tags")?,
+            "--history-days" => args.history_days = value()?.parse().context("--history-days")?,
+            "--seed" => args.seed = value()?.parse().context("--seed")?,
+            "--prefix" => args.prefix = value()?,
+            "--password" => args.password = value()?,
+            other => anyhow::bail!("Unknown argument: {}", other),
+        }
+    }
+    anyhow::ensure!(args.gyms > 0, "--gyms must be at least 1");
+    anyhow::ensure!(
+        args.coaches_per_gym > 0,
+        "--coaches-per-gym must be at least 1"
+    );
+    anyhow::ensure!(args.techniques > 0, "--techniques must be at least 1");
+    anyhow::ensure!(args.history_days > 0, "--history-days must be at least 1");
+    let max_techniques = POSITIONS.len() * MOVES.len();
+    anyhow::ensure!(
+        args.techniques <= max_techniques,
+        "--techniques can be at most {}",
+        max_techniques
+    );
+    anyhow::ensure!(
+        args.tags <= TAG_NAMES.len(),
+        "--tags can be at most {}",
+        TAG_NAMES.len()
+    );
+    Ok(args)
+}
+
+fn print_help() {
+    let d = Args::default();
+    println!("Usage: seed_demo [options]");
+    println!();
+    println!("Fills the database at $DATABASE_URL with generated demo data.");
+    println!();
+    println!("Options:");
+    println!(
+        "  --gyms N               gyms to create (default {})",
+        d.gyms
+    );
+    println!(
+        "  --coaches-per-gym N    coaches per gym, head coach included (default {})",
+        d.coaches_per_gym
+    );
+    println!(
+        "  --students-per-gym N   students per gym (default {})",
+        d.students_per_gym
+    );
+    println!(
+        "  --techniques N         techniques shared by all gyms (default {})",
+        d.techniques
+    );
+    println!(
+        "  --tags N               tags to spread over techniques (default {})",
+        d.tags
+    );
+    println!(
+        "  --history-days N       how far back history goes (default {})",
+        d.history_days
+    );
+    println!(
+        "  --seed N               RNG seed; same seed, same data (default {})",
+        d.seed
+    );
+    println!(
+        "  --prefix P             username prefix (default {})",
+        d.prefix
+    );
+    println!(
+        "  --password P           password for every account (default {})",
+        d.password
+    );
+    println!();
+    println!("Env:");
+    println!("  DATABASE_URL           sqlite:// URL of the target DB.");
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if let Err(e) = run().await {
+        eprintln!("Error: {:#}", e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Username-safe slug: lowercase ASCII letters only.
+fn slug(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// A random moment in the last `days` days, as the naive UTC the rest of the
+/// schema stores (see the note in `seed.rs` on why not `DateTime<Utc>`).
+fn random_past(rng: &mut StdRng, now: NaiveDateTime, days: i64) -> NaiveDateTime {
+    now - Duration::minutes(rng.random_range(0..days * 24 * 60))
+}
+
+struct Person {
+    username: String,
+    first_name: String,
+    last_name: String,
+}
+
+fn fake_person(rng: &mut StdRng, prefix: &str, gym: usize, n: usize) -> Person {
+    let first_name: String = FirstName().fake_with_rng(rng);
+    let last_name: String = LastName().fake_with_rng(rng);
+    // The gym and sequence number keep usernames unique however often
+    // `fake` repeats a name.
+    let username = format!("{}_{}_{}{}", prefix, gym + 1, slug(&first_name), n + 1);
+    Person {
+        username,
+        first_name,
+        last_name,
+    }
+}
+
+async fn insert_person(
+    pool: &SqlitePool,
+    person: &Person,
+    password: &str,
+    role: Role,
+    joined_at: NaiveDateTime,
+) -> Result<UserId> {
+    let display_name = format!("{} {}", person.first_name, person.last_name);
+    let id = create_user(
+        pool,
+        &person.username,
+        password,
+        role.as_str(),
+        Some(&display_name),
+    )
+    .await?;
+    sqlx::query(
+        r#"UPDATE users
+           SET first_name = ?, last_name = ?, email = ?, claimed_at = ?, approved_at = ?
+           WHERE id = ?"#,
+    )
+    .bind(&person.first_name)
+    .bind(&person.last_name)
+    .bind(format!("{}@example.com", person.username))
+    .bind(joined_at)
+    .bind(joined_at)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+async fn run() -> Result<()> {
+    env::load_environment().ok();
+    let args = parse_args()?;
+
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://sqlite.db".to_string());
+    println!("Seeding fake demo data into {}", url);
+
+    // Thousands of demo accounts at the production cost would take minutes,
+    // and nobody needs these hashes to be slow.
+    init_bcrypt_cost(*BCRYPT_COST_RANGE.start());
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let now = Utc::now().naive_utc();
+    let students_total = (args.gyms * args.students_per_gym) as u64;
+
+    let reporter = TerminalSeedReporter::new();
+    let phases = [
+        "Connecting to database",
+        "Seeding tags",
+        "Seeding gyms and coaches",
+        "Seeding techniques",
+        "Seeding gym curricula",
+        "Seeding students",
+        "Generating progress history",
+    ];
+    reporter.seed_started(&phases);
+
+    reporter.phase_started(phases[0], Some(1));
+    let opts = SqliteConnectOptions::from_str(&url)
+        .with_context(|| format!("Invalid DATABASE_URL: {}", url))?
+        .create_if_missing(true);
+    let pool = SqlitePool::connect_with(opts)
+        .await
+        .context("Failed to connect to database")?;
+    let existing: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM users WHERE username LIKE ? ESCAPE '\\'")
+            .bind(format!("{}\\_%", args.prefix.replace('_', "\\_")))
+            .fetch_one(&pool)
+            .await?;
+    anyhow::ensure!(
+        existing.0 == 0,
+        "{} users with prefix '{}' already exist; pass a different --prefix",
+        existing.0,
+        args.prefix
+    );
+    reporter.phase_finished();
+
+    // Tags are global, so reuse any that already exist under the same name.
+    reporter.phase_started(phases[1], Some(args.tags as u64));
+    let mut tag_ids: Vec<TagId> = Vec::with_capacity(args.tags);
+    for name in TAG_NAMES.iter().take(args.tags) {
+        let found: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM tags WHERE name = ? COLLATE NOCASE")
+                .bind(name)
+                .fetch_optional(&pool)
+                .await?;
+        match found {
+            Some((id,)) => {
+                tag_ids.push(TagId(id));
+                reporter.phase_item(ItemOutcome::Existed);
+            }
+            None => {
+                tag_ids.push(create_tag(&pool, name).await?);
+                reporter.phase_item(ItemOutcome::Created);
+            }
+        }
+    }
+    reporter.phase_finished();
+
+    // Gyms: a name and a staff list, head coach first.
+    reporter.phase_started(phases[2], Some((args.gyms * args.coaches_per_gym) as u64));
+    let mut gyms: Vec<(String, Vec<UserId>)> = Vec::with_capacity(args.gyms);
+    for g in 0..args.gyms {
+        let name: String = CompanyName().fake_with_rng(&mut rng);
+        let mut coaches = Vec::with_capacity(args.coaches_per_gym);
+        for c in 0..args.coaches_per_gym {
+            let mut person = fake_person(&mut rng, &args.prefix, g, c);
+            person.username = format!("{}_coach", person.username);
+            // Coaches were there before the history window opens.
+            let joined = random_past(&mut rng, now - Duration::days(args.history_days), 365);
+            let id = insert_person(&pool, &person, &args.password, Role::Coach, joined).await?;
+            coaches.push(id);
+            reporter.phase_item(ItemOutcome::Created);
+        }
+        gyms.push((name, coaches));
+    }
+    reporter.phase_finished();
+
+    // Techniques: distinct position x move pairs, owned by a random coach,
+    // each with one to three tags.
+    reporter.phase_started(phases[3], Some(args.techniques as u64));
+    let mut pairs: Vec<(&str, &str)> = POSITIONS
+        .iter()
+        .flat_map(|p| MOVES.iter().map(move |m| (*p, *m)))
+        .collect();
+    pairs.shuffle(&mut rng);
+    let all_coaches: Vec<UserId> = gyms.iter().flat_map(|(_, c)| c.iter().copied()).collect();
+    let mut technique_ids: Vec<TechniqueId> = Vec::with_capacity(args.techniques);
+    for (position, mv) in pairs.into_iter().take(args.techniques) {
+        let name = format!("{} from {}", mv, position);
+        let description: String = Sentence(8..16).fake_with_rng(&mut rng);
+        let owner = *all_coaches.choose(&mut rng).expect("at least one coach");
+        let id = create_technique(&pool, &name, &description, owner).await?;
+        if !tag_ids.is_empty() {
+            let n = rng.random_range(1..=tag_ids.len().min(3));
+            for tag_id in tag_ids.choose_multiple(&mut rng, n) {
+                add_tag_to_technique(&pool, id, *tag_id).await?;
+            }
+        }
+        technique_ids.push(id);
+        reporter.phase_item(ItemOutcome::Created);
+    }
+    reporter.phase_finished();
+
+    // Each gym's curriculum is a random slice of roughly a third of the
+    // techniques, so gyms overlap without being identical.
+    reporter.phase_started(phases[4], Some(args.gyms as u64));
+    let curriculum_len = (technique_ids.len() / 3).max(1);
+    let mut curricula: Vec<i64> = Vec::with_capacity(args.gyms);
+    for (name, coaches) in &gyms {
+        let collection_id = create_collection(
+            &pool,
+            &format!("{} Curriculum", name),
+            &format!("Core syllabus at {}.", name),
+            None,
+            coaches[0],
+        )
+        .await?;
+        for tid in technique_ids.choose_multiple(&mut rng, curriculum_len) {
+            add_technique_to_collection(&pool, collection_id, *tid).await?;
+        }
+        curricula.push(collection_id);
+        reporter.phase_item(ItemOutcome::Created);
+    }
+    reporter.phase_finished();
+
+    // Students join at a random point in the history window, pick up their
+    // gym's curriculum plus a few extras from a random coach.
+    reporter.phase_started(phases[5], Some(students_total));
+    let mut students: Vec<(UserId, usize, NaiveDateTime)> =
+        Vec::with_capacity(students_total as usize);
+    for (g, (_, coaches)) in gyms.iter().enumerate() {
+        for s in 0..args.students_per_gym {
+            let person = fake_person(&mut rng, &args.prefix, g, s);
+            let joined = random_past(&mut rng, now, args.history_days);
+            let id = insert_person(&pool, &person, &args.password, Role::Student, joined).await?;
+            assign_collection_to_student(&pool, id, curricula[g], coaches[0]).await?;
+            let extras = rng.random_range(0..=technique_ids.len().min(5));
+            for tid in technique_ids.choose_multiple(&mut rng, extras) {
+                let coach = *coaches.choose(&mut rng).expect("at least one coach");
+                assign_technique_to_student(&pool, *tid, id, None, coach).await?;
+            }
+            students.push((id, g, joined));
+            reporter.phase_item(ItemOutcome::Created);
+        }
+    }
+    reporter.phase_finished();
+
+    // History: every assignment dates from the student's join day, moves
+    // along red -> amber -> green at random, and collects attempts with the
+    // odd note from either side. One write transaction per student keeps
+    // big runs quick.
+    reporter.phase_started(phases[6], None);
+    for (student_id, g, joined) in &students {
+        let coaches = &gyms[*g].1;
+        let mut tx = pool.begin().await?;
+        let rows: Vec<(i64,)> =
+            sqlx::query_as("SELECT id FROM student_techniques WHERE student_id = ?")
+                .bind(student_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let tenure_minutes = (now - *joined).num_minutes().max(1);
+        for (st_id,) in rows {
+            let roll: f64 = rng.random();
+            let status = if roll < 0.45 {
+                "red"
+            } else if roll < 0.8 {
+                "amber"
+            } else {
+                "green"
+            };
+            let coach = *coaches.choose(&mut rng).expect("at least one coach");
+            let coach_at = *joined + Duration::minutes(rng.random_range(0..tenure_minutes));
+            let student_at = rng
+                .random_bool(0.3)
+                .then(|| coach_at + Duration::minutes(rng.random_range(30..3 * 24 * 60)))
+                .filter(|t| *t < now);
+            let coach_note = match status {
+                "red" => String::new(),
+                _ => Sentence(4..10).fake_with_rng(&mut rng),
+            };
+            let student_note = match student_at {
+                Some(_) => Sentence(4..10).fake_with_rng(&mut rng),
+                None => String::new(),
+            };
+
+            sqlx::query(
+                r#"UPDATE student_techniques
+                   SET status = ?,
+                       coach_notes = ?,
+                       student_notes = ?,
+                       created_at = ?,
+                       updated_at = ?,
+                       last_coach_update_at = ?,
+                       last_coach_update_by_id = ?,
+                       last_student_update_at = ?,
+                       last_student_update_by_id = ?
+                   WHERE id = ?"#,
+            )
+            .bind(status)
+            .bind(coach_note)
+            .bind(student_note)
+            .bind(*joined)
+            .bind(student_at.unwrap_or(coach_at))
+            .bind(coach_at)
+            .bind(coach)
+            .bind(student_at)
+            .bind(student_at.map(|_| *student_id))
+            .bind(st_id)
+            .execute(&mut *tx)
+            .await?;
+
+            let attempts = match status {
+                "green" => rng.random_range(3..=10),
+                "amber" => rng.random_range(1..=4),
+                _ => rng.random_range(0..=1),
+            };
+            for _ in 0..attempts {
+                let attempted_at = *joined + Duration::minutes(rng.random_range(0..tenure_minutes));
+                let recorder = if rng.random_bool(0.6) {
+                    *student_id
+                } else {
+                    coach
+                };
+                let student_note: Option<String> = rng
+                    .random_bool(0.25)
+                    .then(|| Sentence(3..8).fake_with_rng(&mut rng));
+                let coach_note: Option<String> = rng
+                    .random_bool(0.2)
+                    .then(|| Sentence(3..8).fake_with_rng(&mut rng));
+                sqlx::query(
+                    r#"INSERT INTO attempts (
+                          student_technique_id, recorded_by_id, attempted_at,
+                          coach_note, coach_note_by_id, coach_note_at,
+                          student_note, student_note_at
+                       ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+                )
+                .bind(st_id)
+                .bind(recorder)
+                .bind(attempted_at)
+                .bind(&coach_note)
+                .bind(coach_note.as_ref().map(|_| coach))
+                .bind(coach_note.as_ref().map(|_| attempted_at))
+                .bind(&student_note)
+                .bind(student_note.as_ref().map(|_| attempted_at))
+                .execute(&mut *tx)
+                .await?;
+                reporter.phase_item(ItemOutcome::Created);
+            }
+        }
+        tx.commit().await?;
+    }
+    reporter.phase_finished();
+
+    reporter.seed_finished();
+    println!(
+        "Log in as any {}_* user with password '{}'.",
+        args.prefix, args.password
+    );
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! First run of a brand-new deployment. Normally the migrate binary creates
+//! and migrates the database before the server starts; with
+//! `CREATE_DATABASE=true` the server does both itself when the file is
+//! missing or empty, so a fresh container can come up on its own.
+//!
+//! Either way, a database without any users gets an initial admin: from
+//! `BOOTSTRAP_ADMIN_USERNAME` / `BOOTSTRAP_ADMIN_PASSWORD` when both are set,
+//! otherwise an unclaimed admin invite whose link is printed once at startup.
+//!
+//! The connect and schema steps are retried with backoff for up to
+//! `DB_CONNECT_MAX_WAIT_SECONDS`, so a database volume that is slow to mount
+//! delays startup instead of crash-looping the container.
+
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use migration_engine::migrations::{MigrationError, migrate_database_declaratively};
+use rocket::tokio::time::sleep;
+use sqlx::{Pool, Sqlite};
+use tracing::{error, info, instrument, warn};
+
+use crate::auth::Role;
+use crate::db::{create_invite_token, create_user, create_user_stub};
+use crate::error::AppError;
+
+/// How the first admin was set up.
+#[derive(Debug, PartialEq)]
+pub enum AdminBootstrap {
+    /// From the env credentials, ready to log in.
+    Created { username: String },
+    /// Waiting to be claimed at `claim_path` (a 7-day invite).
+    Invited { claim_path: String },
+}
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Runs `op` until it succeeds or `max_wait` has passed, doubling the delay
+/// between attempts from 250ms up to 5s. A `max_wait` of zero tries once.
+/// `what` names the step in the logs, e.g. "connect".
+pub async fn retry_startup<T, E, F, Fut>(what: &str, max_wait: Duration, mut op: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        let e = match op().await {
+            Ok(value) => {
+                if attempt > 1 {
+                    info!(attempt, "Database {} succeeded after retrying", what);
+                }
+                return Ok(value);
+            }
+            Err(e) => e,
+        };
+        let remaining = max_wait.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            error!(
+                attempt,
+                error = %e,
+                "Database {} failed; giving up after {}s",
+                what,
+                max_wait.as_secs()
+            );
+            return Err(e);
+        }
+        let wait = delay.min(remaining);
+        warn!(
+            attempt,
+            error = %e,
+            retry_in_ms = wait.as_millis() as u64,
+            "Database {} failed; retrying",
+            what
+        );
+        sleep(wait).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Applies `schema` to a database with no tables yet. Returns whether it
+/// did; a database that already has tables is left to the migrate binary.
+#[instrument(skip_all)]
+pub async fn migrate_empty_database(
+    pool: &Pool<Sqlite>,
+    schema: &str,
+) -> Result<bool, MigrationError> {
+    let tables: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+    )
+    .fetch_one(pool)
+    .await?;
+    if tables > 0 {
+        return Ok(false);
+    }
+    info!("Database is empty; applying schema");
+    migrate_database_declaratively(pool.clone(), schema, false).await?;
+    Ok(true)
+}
+
+/// Creates the first admin when there are no users at all. Returns `None`
+/// once anyone exists, including an admin whose invite is still unclaimed.
+#[instrument(skip_all)]
+pub async fn bootstrap_admin(
+    pool: &Pool<Sqlite>,
+    credentials: Option<(&str, &str)>,
+) -> Result<Option<AdminBootstrap>, AppError> {
+    let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(pool)
+        .await?;
+    if users > 0 {
+        return Ok(None);
+    }
+
+    let role = Role::Admin.as_str();
+    let outcome = match credentials {
+        Some((username, password)) => {
+            create_user(pool, username, password, role, Some("Administrator")).await?;
+            AdminBootstrap::Created { username: username.to_string() }
+        }
+        None => {
+            let user_id = create_user_stub(pool, "Administrator", None, role).await?;
+            let token = create_invite_token(pool, user_id).await?;
+            AdminBootstrap::Invited { claim_path: format!("/invite/{}", token) }
+        }
+    };
+    // Not the outcome itself: the claim path carries the invite token.
+    info!("Created initial admin");
+    Ok(Some(outcome))
+}
@@ -1,12 +1,30 @@
+use std::collections::BTreeMap;
+
 use rocket::State;
 use rocket::serde::{Deserialize, Serialize, json::Json};
 
+use crate::flags::Flags;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Capabilities {
     pub videos: bool,
+    /// Sign-in with an OpenID Connect provider is configured.
+    pub oidc: bool,
+}
+
+/// Startup capabilities plus the current runtime flags, so the frontend can
+/// hide surfaces (e.g. the sign-up link) that are switched off.
+#[derive(Debug, Serialize)]
+pub struct CapabilitiesResponse {
+    #[serde(flatten)]
+    pub capabilities: Capabilities,
+    pub flags: BTreeMap<&'static str, bool>,
 }
 
 #[get("/capabilities")]
-pub fn api_capabilities(caps: &State<Capabilities>) -> Json<Capabilities> {
-    Json(**caps)
+pub fn api_capabilities(caps: &State<Capabilities>, flags: Flags) -> Json<CapabilitiesResponse> {
+    Json(CapabilitiesResponse {
+        capabilities: **caps,
+        flags: flags.snapshot(),
+    })
 }
@@ -0,0 +1,647 @@
+//! Process-wide settings, read at startup. Values come from Rocket's
+//! Figment (`Rocket.toml` for the active profile, then `ROCKET_*` env vars),
+//! with the unprefixed env vars the deployments already set (`DATABASE_URL`,
+//! `SCHEMA_PATH`, ...) merged on top so existing env files keep working.
+//!
+//! A few settings (`RELOADABLE`) can change without a restart: SIGHUP or
+//! `POST /api/admin/config/reload` re-reads the env files and swaps them into
+//! the running `LiveConfig`. Anything else that changed is reported as
+//! needing a restart and keeps its running value.
+//!
+//! The migrate binary lives in `migration-engine`, which does not depend on
+//! Rocket, so it still reads its own env vars directly.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use rocket::data::{ByteUnit, ToByteUnit};
+use rocket::figment::Figment;
+use rocket::figment::providers::Env;
+use rocket::figment::value::Value;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use rocket::fairing::AdHoc;
+use rocket::tokio;
+use rocket::tokio::signal::unix::{SignalKind, signal};
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+use crate::attachments::StorageBackend;
+use crate::db::BCRYPT_COST_RANGE;
+use crate::scheduler::Schedule;
+use crate::telemetry::OtlpTransport;
+use crate::validation::ValidationConfig;
+use crate::{env, telemetry};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppConfig {
+    pub database_url: String,
+    pub schema_path: PathBuf,
+    /// Create and migrate a missing or empty database at startup instead of
+    /// refusing to start (see `crate::bootstrap`).
+    #[serde(default, deserialize_with = "rocket::figment::util::bool_from_str_or_int")]
+    pub create_database: bool,
+    #[serde(default, deserialize_with = "rocket::figment::util::bool_from_str_or_int")]
+    pub videos_enabled: bool,
+    /// Unset keeps the build's default (see `db::bcrypt_cost`).
+    #[serde(default)]
+    pub bcrypt_cost: Option<u32>,
+    /// Schedule overrides by job name (see `crate::scheduler`), from
+    /// `JOB_<NAME>` env vars or a `[<profile>.jobs]` table in Rocket.toml.
+    #[serde(default)]
+    pub jobs: HashMap<String, String>,
+    /// `tracing` filter directives, e.g. `info,sqlx=warn`.
+    #[serde(default = "default_rust_log")]
+    pub rust_log: String,
+    /// How long a freshly issued session lasts before requiring re-login.
+    /// The auth guard slides this window forward on use, so an active user
+    /// effectively never logs in again. After a reload, existing sessions
+    /// keep their expiry until they next slide.
+    #[serde(default = "default_session_ttl_days")]
+    pub session_ttl_days: i64,
+    /// Requests slower than this are flagged (see `SlowRequestFairing`).
+    #[serde(default = "default_slow_request_threshold_ms")]
+    pub slow_request_threshold_ms: u64,
+    /// Where attachments are kept (see `crate::attachments`).
+    #[serde(default)]
+    pub attachment_storage: StorageBackend,
+    /// Directory the `local` attachment backend writes to.
+    #[serde(default = "default_attachment_dir")]
+    pub attachment_dir: PathBuf,
+    /// How long an attachment download URL works once handed out.
+    #[serde(default = "default_attachment_url_ttl_seconds")]
+    pub attachment_url_ttl_seconds: u64,
+    /// Largest file `POST /api/uploads` accepts.
+    #[serde(default = "default_upload_max_bytes")]
+    pub upload_max_bytes: u64,
+    /// Bearer token the billing system sends to the membership webhook.
+    /// Unset turns the webhook off.
+    #[serde(default)]
+    pub membership_webhook_secret: Option<String>,
+    /// Wrong current passwords a user may give to change-password within
+    /// `password_attempt_window_minutes` before further tries get a 429.
+    #[serde(default = "default_password_attempt_limit")]
+    pub password_attempt_limit: i64,
+    #[serde(default = "default_password_attempt_window_minutes")]
+    pub password_attempt_window_minutes: i64,
+    /// How long startup keeps retrying the database connection and schema
+    /// check before giving up. Zero tries once.
+    #[serde(default = "default_db_connect_max_wait_seconds")]
+    pub db_connect_max_wait_seconds: u64,
+    /// OpenID Connect sign-in (see `crate::oidc`), e.g.
+    /// `https://accounts.google.com`. Set together with the client id and
+    /// secret and the redirect URL, or not at all.
+    #[serde(default)]
+    pub oidc_issuer: Option<String>,
+    #[serde(default)]
+    pub oidc_client_id: Option<String>,
+    #[serde(default)]
+    pub oidc_client_secret: Option<String>,
+    /// The callback as registered with the provider, e.g.
+    /// `https://syllabus.example.com/api/auth/oidc/callback`.
+    #[serde(default)]
+    pub oidc_redirect_url: Option<String>,
+    /// Field length limits, from `VALIDATION_<FIELD>` env vars such as
+    /// `VALIDATION_PASSWORD_MIN` (see `ValidationConfig`).
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    /// Ceiling for every JSON request body (Rocket's `json` data limit).
+    /// Field validators cap individual strings; this stops an oversized body
+    /// before it is buffered and parsed at all.
+    #[serde(default = "default_json_body_limit_bytes")]
+    pub json_body_limit_bytes: u64,
+    /// Key for signing local attachment download URLs. Unset signs with a
+    /// per-process key, so links stop working on restart.
+    #[serde(default)]
+    pub attachment_signing_key: Option<String>,
+    /// First admin's credentials for an empty database (see
+    /// `crate::bootstrap`). Set both or neither.
+    #[serde(default)]
+    pub bootstrap_admin_username: Option<String>,
+    #[serde(default)]
+    pub bootstrap_admin_password: Option<String>,
+    /// Where traces and metrics go. The exporters read the endpoint (and
+    /// headers and timeout) themselves; it is here for the startup check.
+    #[serde(default)]
+    pub otel_exporter_otlp_endpoint: Option<String>,
+    #[serde(default)]
+    pub otel_exporter_otlp_protocol: OtlpTransport,
+}
+
+fn default_rust_log() -> String {
+    "info".to_string()
+}
+
+fn default_session_ttl_days() -> i64 {
+    30
+}
+
+fn default_slow_request_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_attachment_dir() -> PathBuf {
+    PathBuf::from("data/attachments")
+}
+
+fn default_attachment_url_ttl_seconds() -> u64 {
+    300
+}
+
+fn default_upload_max_bytes() -> u64 {
+    20 * 1024 * 1024
+}
+
+fn default_password_attempt_limit() -> i64 {
+    5
+}
+
+fn default_password_attempt_window_minutes() -> i64 {
+    15
+}
+
+fn default_db_connect_max_wait_seconds() -> u64 {
+    30
+}
+
+fn default_json_body_limit_bytes() -> u64 {
+    256 * 1024
+}
+
+/// S3 rejects presigned URLs that live longer than a week.
+const MAX_ATTACHMENT_URL_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Unprefixed env vars read into `AppConfig`. Figment lower-cases env keys,
+/// so `DATABASE_URL` lands on `database_url`.
+const ENV_KEYS: &[&str] = &[
+    "DATABASE_URL",
+    "SCHEMA_PATH",
+    "CREATE_DATABASE",
+    "VIDEOS_ENABLED",
+    "BCRYPT_COST",
+    "RUST_LOG",
+    "SESSION_TTL_DAYS",
+    "SLOW_REQUEST_THRESHOLD_MS",
+    "ATTACHMENT_STORAGE",
+    "ATTACHMENT_DIR",
+    "ATTACHMENT_URL_TTL_SECONDS",
+    "UPLOAD_MAX_BYTES",
+    "MEMBERSHIP_WEBHOOK_SECRET",
+    "PASSWORD_ATTEMPT_LIMIT",
+    "PASSWORD_ATTEMPT_WINDOW_MINUTES",
+    "DB_CONNECT_MAX_WAIT_SECONDS",
+    "OIDC_ISSUER",
+    "OIDC_CLIENT_ID",
+    "OIDC_CLIENT_SECRET",
+    "OIDC_REDIRECT_URL",
+    "JSON_BODY_LIMIT_BYTES",
+    "ATTACHMENT_SIGNING_KEY",
+    "BOOTSTRAP_ADMIN_USERNAME",
+    "BOOTSTRAP_ADMIN_PASSWORD",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_PROTOCOL",
+];
+
+/// Only ever reported as set or unset (see `AppConfig::summary`). The S3
+/// keys, the OTLP headers and Rocket's secret key are read straight from the
+/// environment by the code that needs them.
+const SECRET_KEYS: &[&str] = &[
+    "ROCKET_SECRET_KEY",
+    "S3_ACCESS_KEY",
+    "S3_SECRET_KEY",
+    "OTEL_EXPORTER_OTLP_HEADERS",
+    "ATTACHMENT_SIGNING_KEY",
+    "MEMBERSHIP_WEBHOOK_SECRET",
+    "OIDC_CLIENT_SECRET",
+    "BOOTSTRAP_ADMIN_PASSWORD",
+];
+
+/// Settings a reload applies to the running process.
+pub const RELOADABLE: &[&str] = &[
+    "RUST_LOG",
+    "SESSION_TTL_DAYS",
+    "SLOW_REQUEST_THRESHOLD_MS",
+    "ATTACHMENT_URL_TTL_SECONDS",
+    "MEMBERSHIP_WEBHOOK_SECRET",
+    "PASSWORD_ATTEMPT_LIMIT",
+    "PASSWORD_ATTEMPT_WINDOW_MINUTES",
+];
+
+const REQUIRED_KEYS: &[&str] = &["database_url", "schema_path"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("missing required config: {}", .0.join(", "))]
+    Missing(Vec<String>),
+    #[error("invalid config: {0}")]
+    Invalid(String),
+}
+
+impl AppConfig {
+    pub fn figment() -> Figment {
+        rocket::Config::figment()
+            .merge(Env::raw().only(ENV_KEYS))
+            .merge(Env::prefixed("JOB_").map(|key| format!("jobs.{}", key).into()))
+            .merge(Env::prefixed("VALIDATION_").map(|key| format!("validation.{}", key).into()))
+    }
+
+    /// `figment()` with the env files read afresh (see `env::read_env_files`),
+    /// since the process environment still holds their values from startup.
+    pub fn reload_figment() -> Result<Figment, ConfigError> {
+        let files = env::read_env_files().map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        let mut figment = Self::figment();
+        for (key, value) in files {
+            let path = if ENV_KEYS.contains(&key.as_str()) {
+                key.to_ascii_lowercase()
+            } else if let Some(job) = key.strip_prefix("JOB_") {
+                format!("jobs.{}", job.to_ascii_lowercase())
+            } else if let Some(field) = key.strip_prefix("VALIDATION_") {
+                format!("validation.{}", field.to_ascii_lowercase())
+            } else {
+                continue;
+            };
+            // Parsed the way Figment parses env values, so "30" is a number.
+            let Ok(value) = Value::from_str(&value);
+            figment = figment.merge((path, value));
+        }
+        Ok(figment)
+    }
+
+    /// The running settings by env var name, for operators. Secrets appear
+    /// only as "set" or "unset".
+    pub fn summary(&self) -> BTreeMap<String, String> {
+        let mut summary = BTreeMap::from([
+            ("DATABASE_URL".to_string(), self.database_url.clone()),
+            ("SCHEMA_PATH".to_string(), self.schema_path.display().to_string()),
+            ("CREATE_DATABASE".to_string(), self.create_database.to_string()),
+            ("VIDEOS_ENABLED".to_string(), self.videos_enabled.to_string()),
+            ("BCRYPT_COST".to_string(), crate::db::bcrypt_cost().to_string()),
+            ("RUST_LOG".to_string(), self.rust_log.clone()),
+            ("SESSION_TTL_DAYS".to_string(), self.session_ttl_days.to_string()),
+            (
+                "SLOW_REQUEST_THRESHOLD_MS".to_string(),
+                self.slow_request_threshold_ms.to_string(),
+            ),
+            (
+                "ATTACHMENT_STORAGE".to_string(),
+                self.attachment_storage.as_str().to_string(),
+            ),
+            ("ATTACHMENT_DIR".to_string(), self.attachment_dir.display().to_string()),
+            (
+                "ATTACHMENT_URL_TTL_SECONDS".to_string(),
+                self.attachment_url_ttl_seconds.to_string(),
+            ),
+            ("UPLOAD_MAX_BYTES".to_string(), self.upload_max_bytes.to_string()),
+            ("PASSWORD_ATTEMPT_LIMIT".to_string(), self.password_attempt_limit.to_string()),
+            (
+                "PASSWORD_ATTEMPT_WINDOW_MINUTES".to_string(),
+                self.password_attempt_window_minutes.to_string(),
+            ),
+            (
+                "DB_CONNECT_MAX_WAIT_SECONDS".to_string(),
+                self.db_connect_max_wait_seconds.to_string(),
+            ),
+            ("JSON_BODY_LIMIT_BYTES".to_string(), self.json_body_limit_bytes.to_string()),
+            (
+                "OTEL_EXPORTER_OTLP_PROTOCOL".to_string(),
+                self.otel_exporter_otlp_protocol.as_str().to_string(),
+            ),
+        ]);
+        let optional = [
+            ("OIDC_ISSUER", &self.oidc_issuer),
+            ("OIDC_CLIENT_ID", &self.oidc_client_id),
+            ("OIDC_REDIRECT_URL", &self.oidc_redirect_url),
+            ("BOOTSTRAP_ADMIN_USERNAME", &self.bootstrap_admin_username),
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", &self.otel_exporter_otlp_endpoint),
+        ];
+        for (key, value) in optional {
+            let value = value.clone().unwrap_or_else(|| "unset".to_string());
+            summary.insert(key.to_string(), value);
+        }
+        for (job, spec) in &self.jobs {
+            summary.insert(format!("JOB_{}", job.to_ascii_uppercase()), spec.clone());
+        }
+        for (field, limit) in self.validation.limits() {
+            summary.insert(format!("VALIDATION_{}", field.to_ascii_uppercase()), limit.to_string());
+        }
+        for key in SECRET_KEYS {
+            let set = match *key {
+                "MEMBERSHIP_WEBHOOK_SECRET" => self.membership_webhook_secret.is_some(),
+                "OIDC_CLIENT_SECRET" => self.oidc_client_secret.is_some(),
+                "ATTACHMENT_SIGNING_KEY" => self.attachment_signing_key.is_some(),
+                "BOOTSTRAP_ADMIN_PASSWORD" => self.bootstrap_admin_password.is_some(),
+                _ => dotenvy::var(key).is_ok(),
+            };
+            let state = if set { "set" } else { "unset" };
+            summary.insert(key.to_string(), state.to_string());
+        }
+        summary
+    }
+
+    pub fn slow_request_threshold(&self) -> Duration {
+        Duration::from_millis(self.slow_request_threshold_ms)
+    }
+
+    pub fn session_ttl(&self) -> chrono::Duration {
+        chrono::Duration::days(self.session_ttl_days)
+    }
+
+    pub fn attachment_url_ttl(&self) -> Duration {
+        Duration::from_secs(self.attachment_url_ttl_seconds)
+    }
+
+    pub fn password_attempt_window(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.password_attempt_window_minutes)
+    }
+
+    pub fn db_connect_max_wait(&self) -> Duration {
+        Duration::from_secs(self.db_connect_max_wait_seconds)
+    }
+
+    pub fn json_body_limit(&self) -> ByteUnit {
+        self.json_body_limit_bytes.bytes()
+    }
+
+    /// The first admin's username and password, when both are set. The
+    /// username is trimmed the way login trims it.
+    pub fn bootstrap_admin_credentials(&self) -> Option<(&str, &str)> {
+        let username = self.bootstrap_admin_username.as_deref()?.trim();
+        let password = self.bootstrap_admin_password.as_deref()?;
+        Some((username, password))
+    }
+
+    /// Reports every missing required key at once, by its env var name,
+    /// rather than stopping at the first one Figment trips over.
+    pub fn from_figment(figment: &Figment) -> Result<Self, ConfigError> {
+        let missing: Vec<String> = REQUIRED_KEYS
+            .iter()
+            .filter(|key| !figment.contains(key))
+            .map(|key| key.to_ascii_uppercase())
+            .collect();
+        if !missing.is_empty() {
+            return Err(ConfigError::Missing(missing));
+        }
+
+        let config: AppConfig = figment
+            .extract()
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(cost) = self.bcrypt_cost
+            && !BCRYPT_COST_RANGE.contains(&cost)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "BCRYPT_COST must be between {} and {}, got {}",
+                BCRYPT_COST_RANGE.start(),
+                BCRYPT_COST_RANGE.end(),
+                cost
+            )));
+        }
+        for (job, spec) in &self.jobs {
+            Schedule::from_str(spec).map_err(|e| {
+                ConfigError::Invalid(format!("JOB_{}: {}", job.to_ascii_uppercase(), e))
+            })?;
+        }
+        EnvFilter::try_new(&self.rust_log)
+            .map_err(|e| ConfigError::Invalid(format!("RUST_LOG: {}", e)))?;
+        if self.session_ttl_days < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "SESSION_TTL_DAYS must be at least 1, got {}",
+                self.session_ttl_days
+            )));
+        }
+        if !(1..=MAX_ATTACHMENT_URL_TTL_SECONDS).contains(&self.attachment_url_ttl_seconds) {
+            return Err(ConfigError::Invalid(format!(
+                "ATTACHMENT_URL_TTL_SECONDS must be between 1 and {}, got {}",
+                MAX_ATTACHMENT_URL_TTL_SECONDS, self.attachment_url_ttl_seconds
+            )));
+        }
+        if self.upload_max_bytes < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "UPLOAD_MAX_BYTES must be at least 1, got {}",
+                self.upload_max_bytes
+            )));
+        }
+        if self.password_attempt_limit < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "PASSWORD_ATTEMPT_LIMIT must be at least 1, got {}",
+                self.password_attempt_limit
+            )));
+        }
+        if self.password_attempt_window_minutes < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "PASSWORD_ATTEMPT_WINDOW_MINUTES must be at least 1, got {}",
+                self.password_attempt_window_minutes
+            )));
+        }
+        let oidc = [
+            &self.oidc_issuer,
+            &self.oidc_client_id,
+            &self.oidc_client_secret,
+            &self.oidc_redirect_url,
+        ];
+        if oidc.iter().any(|value| value.is_some()) && oidc.iter().any(|value| value.is_none()) {
+            return Err(ConfigError::Invalid(
+                "OIDC_ISSUER, OIDC_CLIENT_ID, OIDC_CLIENT_SECRET and OIDC_REDIRECT_URL \
+                 must be set together"
+                    .to_string(),
+            ));
+        }
+        self.validation.check().map_err(ConfigError::Invalid)?;
+        if self.json_body_limit_bytes < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "JSON_BODY_LIMIT_BYTES must be at least 1, got {}",
+                self.json_body_limit_bytes
+            )));
+        }
+        if self.attachment_signing_key.as_deref() == Some("") {
+            return Err(ConfigError::Invalid(
+                "ATTACHMENT_SIGNING_KEY is empty; set a key or remove it".to_string(),
+            ));
+        }
+        let bootstrap = [&self.bootstrap_admin_username, &self.bootstrap_admin_password];
+        if bootstrap.iter().any(|value| value.as_deref().is_none_or(|v| v.trim().is_empty()))
+            && bootstrap.iter().any(|value| value.is_some())
+        {
+            return Err(ConfigError::Invalid(
+                "BOOTSTRAP_ADMIN_USERNAME and BOOTSTRAP_ADMIN_PASSWORD must both be set \
+                 (and non-empty), or neither"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `self` with the `RELOADABLE` settings taken from `new`, and a report
+    /// of what differed.
+    fn reloaded(&self, new: AppConfig) -> (AppConfig, ReloadReport) {
+        let mut report = ReloadReport::default();
+        let mut compare = |changed: bool, key: &'static str| {
+            if !changed {
+                return;
+            }
+            if RELOADABLE.contains(&key) {
+                report.applied.push(key.to_string());
+            } else {
+                report.restart_required.push(key.to_string());
+            }
+        };
+        compare(self.database_url != new.database_url, "DATABASE_URL");
+        compare(self.schema_path != new.schema_path, "SCHEMA_PATH");
+        compare(self.create_database != new.create_database, "CREATE_DATABASE");
+        compare(self.videos_enabled != new.videos_enabled, "VIDEOS_ENABLED");
+        compare(self.bcrypt_cost != new.bcrypt_cost, "BCRYPT_COST");
+        compare(self.jobs != new.jobs, "JOB_*");
+        compare(self.rust_log != new.rust_log, "RUST_LOG");
+        compare(self.session_ttl_days != new.session_ttl_days, "SESSION_TTL_DAYS");
+        compare(
+            self.slow_request_threshold_ms != new.slow_request_threshold_ms,
+            "SLOW_REQUEST_THRESHOLD_MS",
+        );
+        compare(self.attachment_storage != new.attachment_storage, "ATTACHMENT_STORAGE");
+        compare(self.attachment_dir != new.attachment_dir, "ATTACHMENT_DIR");
+        compare(
+            self.attachment_url_ttl_seconds != new.attachment_url_ttl_seconds,
+            "ATTACHMENT_URL_TTL_SECONDS",
+        );
+        compare(self.upload_max_bytes != new.upload_max_bytes, "UPLOAD_MAX_BYTES");
+        compare(
+            self.membership_webhook_secret != new.membership_webhook_secret,
+            "MEMBERSHIP_WEBHOOK_SECRET",
+        );
+        compare(
+            self.password_attempt_limit != new.password_attempt_limit,
+            "PASSWORD_ATTEMPT_LIMIT",
+        );
+        compare(
+            self.password_attempt_window_minutes != new.password_attempt_window_minutes,
+            "PASSWORD_ATTEMPT_WINDOW_MINUTES",
+        );
+        compare(
+            self.db_connect_max_wait_seconds != new.db_connect_max_wait_seconds,
+            "DB_CONNECT_MAX_WAIT_SECONDS",
+        );
+        compare(self.oidc_issuer != new.oidc_issuer, "OIDC_ISSUER");
+        compare(self.oidc_client_id != new.oidc_client_id, "OIDC_CLIENT_ID");
+        compare(self.oidc_client_secret != new.oidc_client_secret, "OIDC_CLIENT_SECRET");
+        compare(self.oidc_redirect_url != new.oidc_redirect_url, "OIDC_REDIRECT_URL");
+        compare(self.validation != new.validation, "VALIDATION_*");
+        compare(self.json_body_limit_bytes != new.json_body_limit_bytes, "JSON_BODY_LIMIT_BYTES");
+        compare(
+            self.attachment_signing_key != new.attachment_signing_key,
+            "ATTACHMENT_SIGNING_KEY",
+        );
+        compare(
+            self.bootstrap_admin_username != new.bootstrap_admin_username,
+            "BOOTSTRAP_ADMIN_USERNAME",
+        );
+        compare(
+            self.bootstrap_admin_password != new.bootstrap_admin_password,
+            "BOOTSTRAP_ADMIN_PASSWORD",
+        );
+        compare(
+            self.otel_exporter_otlp_endpoint != new.otel_exporter_otlp_endpoint,
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+        );
+        compare(
+            self.otel_exporter_otlp_protocol != new.otel_exporter_otlp_protocol,
+            "OTEL_EXPORTER_OTLP_PROTOCOL",
+        );
+
+        let next = AppConfig {
+            rust_log: new.rust_log,
+            session_ttl_days: new.session_ttl_days,
+            slow_request_threshold_ms: new.slow_request_threshold_ms,
+            attachment_url_ttl_seconds: new.attachment_url_ttl_seconds,
+            membership_webhook_secret: new.membership_webhook_secret,
+            password_attempt_limit: new.password_attempt_limit,
+            password_attempt_window_minutes: new.password_attempt_window_minutes,
+            ..self.clone()
+        };
+        (next, report)
+    }
+}
+
+/// Env var names of the settings a reload found changed.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReloadReport {
+    pub applied: Vec<String>,
+    /// Changed in the source but left at the running value.
+    pub restart_required: Vec<String>,
+}
+
+/// The running `AppConfig`, managed as Rocket state and swapped whole on
+/// reload. Readers take a snapshot with `get`; clones share the same value.
+#[derive(Clone)]
+pub struct LiveConfig {
+    current: Arc<ArcSwap<AppConfig>>,
+    /// Where `reload` reads from: `AppConfig::reload_figment` in the app, a
+    /// fixed figment in tests.
+    source: fn() -> Result<Figment, ConfigError>,
+    reloading: Arc<Mutex<()>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: AppConfig, source: fn() -> Result<Figment, ConfigError>) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::from_pointee(config)),
+            source,
+            reloading: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.current.load_full()
+    }
+
+    /// Re-reads the source and applies what can be applied. On any error
+    /// (unreadable file, invalid value) the running config is left as is.
+    pub fn reload(&self) -> Result<ReloadReport, ConfigError> {
+        let _guard = self.reloading.lock().unwrap_or_else(|e| e.into_inner());
+        let new = AppConfig::from_figment(&(self.source)()?)?;
+        let current = self.get();
+        let (next, report) = current.reloaded(new);
+        if next.rust_log != current.rust_log {
+            telemetry::set_log_filter(&next.rust_log).map_err(ConfigError::Invalid)?;
+        }
+        self.current.store(Arc::new(next));
+        info!(
+            applied = ?report.applied,
+            restart_required = ?report.restart_required,
+            "Config reloaded"
+        );
+        Ok(report)
+    }
+
+    /// Reloads on SIGHUP for as long as the server runs. A failed reload is
+    /// logged and the running config kept.
+    pub fn reload_on_hangup(&self) -> AdHoc {
+        let config = self.clone();
+        AdHoc::on_liftoff("Config reload on SIGHUP", |_| {
+            Box::pin(async move {
+                let mut hangup = match signal(SignalKind::hangup()) {
+                    Ok(hangup) => hangup,
+                    Err(e) => {
+                        error!(error = %e, "Failed to listen for SIGHUP; reload via the API only");
+                        return;
+                    }
+                };
+                tokio::spawn(async move {
+                    while hangup.recv().await.is_some() {
+                        info!("SIGHUP received; reloading config");
+                        if let Err(e) = config.reload() {
+                            error!(error = %e, "Config reload failed; keeping the running config");
+                        }
+                    }
+                });
+            })
+        })
+    }
+}
@@ -0,0 +1,151 @@
+//! Personal access tokens (see `auth::api_tokens`). Only a token's hash is
+//! stored, so a token that's lost can be revoked but not shown again.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+use tracing::{info, instrument};
+
+use crate::auth::{API_TOKEN_PREFIX, TokenScope, hash_api_token};
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::naive_to_utc;
+
+/// A token as its owner sees it, without the token itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// The token's first characters, to tell tokens apart.
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// What a request sent with a token may do, for the `User` guard.
+#[derive(Debug, Clone)]
+pub struct ApiTokenGrant {
+    pub id: i64,
+    pub user_id: UserId,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl ApiTokenGrant {
+    pub fn is_valid(&self) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > Utc::now().naive_utc())
+    }
+}
+
+#[instrument(skip(executor, token))]
+pub async fn create_api_token(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    name: &str,
+    token: &str,
+    scopes: &[TokenScope],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<i64, AppError> {
+    info!("Creating API token");
+    let token_hash = hash_api_token(token);
+    let token_prefix: String = token.chars().take(API_TOKEN_PREFIX.len() + 4).collect();
+    let scopes = TokenScope::to_db(scopes);
+    let res = sqlx::query!(
+        "INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        user_id.0,
+        name,
+        token_hash,
+        token_prefix,
+        scopes,
+        expires_at
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+/// `user_id`'s tokens, newest first, expired ones included.
+#[instrument(skip(executor))]
+pub async fn get_api_tokens(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<Vec<ApiToken>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", name, token_prefix, scopes,
+                  created_at AS "created_at: NaiveDateTime",
+                  expires_at AS "expires_at: NaiveDateTime",
+                  last_used_at AS "last_used_at: NaiveDateTime"
+           FROM api_tokens WHERE user_id = ?
+           ORDER BY created_at DESC, id DESC"#,
+        user_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ApiToken {
+            id: row.id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            scopes: TokenScope::from_db(&row.scopes),
+            created_at: naive_to_utc(row.created_at),
+            expires_at: row.expires_at.map(naive_to_utc),
+            last_used_at: row.last_used_at.map(naive_to_utc),
+        })
+        .collect())
+}
+
+/// The grant for `token`, if it was ever issued. Expiry is left to the
+/// caller (see `ApiTokenGrant::is_valid`).
+#[instrument(skip(executor, token))]
+pub async fn find_api_token_grant(
+    executor: impl SqliteExecutor<'_>,
+    token: &str,
+) -> Result<Option<ApiTokenGrant>, AppError> {
+    let token_hash = hash_api_token(token);
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", user_id, scopes,
+                  expires_at AS "expires_at: NaiveDateTime"
+           FROM api_tokens WHERE token_hash = ?"#,
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row.map(|row| ApiTokenGrant {
+        id: row.id,
+        user_id: UserId(row.user_id),
+        scopes: TokenScope::from_db(&row.scopes),
+        expires_at: row.expires_at,
+    }))
+}
+
+#[instrument(skip(executor))]
+pub async fn touch_api_token(executor: impl SqliteExecutor<'_>, id: i64) -> Result<(), AppError> {
+    let now = Utc::now().naive_utc();
+    sqlx::query!("UPDATE api_tokens SET last_used_at = ? WHERE id = ?", now, id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Deletes one of `user_id`'s tokens. Anyone else's is reported as not found.
+#[instrument(skip(executor))]
+pub async fn revoke_api_token(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    user_id: UserId,
+) -> Result<(), AppError> {
+    let res = sqlx::query!("DELETE FROM api_tokens WHERE id = ? AND user_id = ?", id, user_id.0)
+        .execute(executor)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("API token {} not found", id)));
+    }
+    info!("API token revoked");
+    Ok(())
+}
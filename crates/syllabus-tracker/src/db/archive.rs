@@ -0,0 +1,609 @@
+//! Whole-database export to a versioned JSON document, and a merging import
+//! of that document into another (possibly non-empty) database. Used by the
+//! `archive` bin for moving a gym between instances and for offline copies.
+//!
+//! What travels: users (never password hashes or sessions), tags,
+//! techniques with their tags, collections with their technique order,
+//! student assignments with notes, attempts, and status transition rules.
+//! Videos are left out because their media lives in object storage; so are
+//! per-viewer state (seen markers, preferences) and feature flags.
+//!
+//! Ids in the document are the source database's. Import matches existing
+//! rows by natural key (username, tag name, technique name, collection name,
+//! student + technique) and reuses them, inserts the rest, and rewrites every
+//! reference through the resulting id map. Re-importing the same document is
+//! a no-op. Imported users have no password and are left unclaimed, so an
+//! admin re-invites them.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use tracing::{info, instrument};
+
+use super::index_technique;
+use crate::error::AppError;
+use crate::ids::TechniqueId;
+use crate::validation::normalize_tag_name;
+
+/// Identifies the document type, so a stray JSON file fails loudly.
+pub const ARCHIVE_FORMAT: &str = "syllabus-tracker-archive";
+/// Bump when a field is removed or changes meaning. Added optional fields
+/// don't need a bump; import rejects versions newer than this.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub users: Vec<ArchiveUser>,
+    pub tags: Vec<ArchiveTag>,
+    pub techniques: Vec<ArchiveTechnique>,
+    pub collections: Vec<ArchiveCollection>,
+    pub student_techniques: Vec<ArchiveStudentTechnique>,
+    pub attempts: Vec<ArchiveAttempt>,
+    pub status_transitions: Vec<ArchiveStatusTransition>,
+    /// Missing from archives written before the grading scale was
+    /// configurable; those used the default scale.
+    #[serde(default)]
+    pub statuses: Vec<ArchiveStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveUser {
+    pub id: i64,
+    pub username: String,
+    pub role: String,
+    pub display_name: Option<String>,
+    pub archived: bool,
+    pub graduated_at: Option<NaiveDateTime>,
+    pub graduated_by_id: Option<i64>,
+    pub email: Option<String>,
+    pub claimed_at: Option<NaiveDateTime>,
+    pub approved_at: Option<NaiveDateTime>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub reset_requested_at: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveTag {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveTechnique {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub coach_id: Option<i64>,
+    #[sqlx(skip)]
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveCollection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub coach_id: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+    /// Absent from archives made before curricula had one.
+    pub belt_level: Option<String>,
+    /// In display order.
+    #[sqlx(skip)]
+    pub technique_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveStudentTechnique {
+    pub id: i64,
+    pub technique_id: i64,
+    pub student_id: i64,
+    pub technique_name: Option<String>,
+    pub technique_description: Option<String>,
+    pub status: Option<String>,
+    pub student_notes: Option<String>,
+    pub coach_notes: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub last_coach_update_at: Option<NaiveDateTime>,
+    pub last_coach_update_by_id: Option<i64>,
+    pub last_student_update_at: Option<NaiveDateTime>,
+    pub last_student_update_by_id: Option<i64>,
+    pub collection_id: Option<i64>,
+    pub review_requested_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveAttempt {
+    pub id: i64,
+    pub student_technique_id: i64,
+    pub recorded_by_id: i64,
+    pub attempted_at: NaiveDateTime,
+    pub coach_note: Option<String>,
+    pub coach_note_by_id: Option<i64>,
+    pub coach_note_at: Option<NaiveDateTime>,
+    pub student_note: Option<String>,
+    pub student_note_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveStatusTransition {
+    pub from_status: String,
+    pub to_status: String,
+    pub min_role: String,
+}
+
+/// Lowest first, in the order exported.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchiveStatus {
+    pub name: String,
+    pub color: String,
+}
+
+/// Rows inserted vs matched to an existing row, per kind.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportCounts {
+    pub created: usize,
+    pub existing: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub users: ImportCounts,
+    pub tags: ImportCounts,
+    pub techniques: ImportCounts,
+    pub collections: ImportCounts,
+    pub student_techniques: ImportCounts,
+    pub attempts: ImportCounts,
+    pub status_transitions: ImportCounts,
+    pub statuses: ImportCounts,
+}
+
+#[instrument(skip(pool))]
+pub async fn export_archive(pool: &Pool<Sqlite>) -> Result<Archive, AppError> {
+    info!("Exporting archive");
+    // One read transaction so the snapshot is consistent.
+    let mut tx = pool.begin().await?;
+
+    let users: Vec<ArchiveUser> = sqlx::query_as(
+        "SELECT id, username, role, display_name, archived, graduated_at, graduated_by_id,
+                email, claimed_at, approved_at, first_name, last_name, reset_requested_at,
+                timezone
+         FROM users WHERE username IS NOT NULL ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let tags: Vec<ArchiveTag> = sqlx::query_as("SELECT id, name FROM tags ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut techniques: Vec<ArchiveTechnique> =
+        sqlx::query_as("SELECT id, name, description, coach_id FROM techniques ORDER BY id")
+            .fetch_all(&mut *tx)
+            .await?;
+    let technique_tags: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT technique_id, tag_id FROM technique_tags ORDER BY technique_id, tag_id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut tags_by_technique: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (technique_id, tag_id) in technique_tags {
+        tags_by_technique.entry(technique_id).or_default().push(tag_id);
+    }
+    for technique in &mut techniques {
+        technique.tag_ids = tags_by_technique.remove(&technique.id).unwrap_or_default();
+    }
+
+    let mut collections: Vec<ArchiveCollection> = sqlx::query_as(
+        "SELECT id, name, description, coach_id, created_at, belt_level
+         FROM collections
+         ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let members: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT collection_id, technique_id FROM collection_techniques
+         ORDER BY collection_id, position, technique_id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut members_by_collection: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (collection_id, technique_id) in members {
+        members_by_collection.entry(collection_id).or_default().push(technique_id);
+    }
+    for collection in &mut collections {
+        collection.technique_ids = members_by_collection
+            .remove(&collection.id)
+            .unwrap_or_default();
+    }
+
+    // Assignments with a dangling technique or student can't be imported
+    // anywhere, so they're left out here rather than failing the import.
+    let student_techniques: Vec<ArchiveStudentTechnique> = sqlx::query_as(
+        "SELECT st.id, st.technique_id, st.student_id, st.technique_name,
+                st.technique_description, st.status, st.student_notes, st.coach_notes,
+                st.created_at, st.updated_at, st.last_coach_update_at,
+                st.last_coach_update_by_id, st.last_student_update_at,
+                st.last_student_update_by_id, st.collection_id, st.review_requested_at
+         FROM student_techniques st
+         JOIN techniques t ON t.id = st.technique_id
+         JOIN users u ON u.id = st.student_id AND u.username IS NOT NULL
+         ORDER BY st.id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Same for attempts on those assignments or by a user not exported.
+    let attempts: Vec<ArchiveAttempt> = sqlx::query_as(
+        "SELECT a.id, a.student_technique_id, a.recorded_by_id, a.attempted_at, a.coach_note,
+                a.coach_note_by_id, a.coach_note_at, a.student_note, a.student_note_at,
+                a.created_at
+         FROM attempts a
+         JOIN student_techniques st ON st.id = a.student_technique_id
+         JOIN techniques t ON t.id = st.technique_id
+         JOIN users s ON s.id = st.student_id AND s.username IS NOT NULL
+         JOIN users r ON r.id = a.recorded_by_id AND r.username IS NOT NULL
+         ORDER BY a.id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let status_transitions: Vec<ArchiveStatusTransition> = sqlx::query_as(
+        "SELECT from_status, to_status, min_role FROM status_transitions
+         ORDER BY from_status, to_status",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let statuses: Vec<ArchiveStatus> =
+        sqlx::query_as("SELECT name, color FROM statuses ORDER BY position, name")
+            .fetch_all(&mut *tx)
+            .await?;
+
+    tx.commit().await?;
+
+    Ok(Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        users,
+        tags,
+        techniques,
+        collections,
+        student_techniques,
+        attempts,
+        status_transitions,
+        statuses,
+    })
+}
+
+/// Source id -> destination id for one table.
+#[derive(Default)]
+struct IdMap(HashMap<i64, i64>);
+
+impl IdMap {
+    fn get(&self, kind: &str, id: i64) -> Result<i64, AppError> {
+        self.0.get(&id).copied().ok_or_else(|| {
+            AppError::Internal(format!("Archive references unknown {} id {}", kind, id))
+        })
+    }
+
+    /// For nullable references: a dangling one is dropped, not an error.
+    fn get_opt(&self, id: Option<i64>) -> Option<i64> {
+        id.and_then(|id| self.0.get(&id).copied())
+    }
+}
+
+pub(super) fn count(counts: &mut ImportCounts, created: bool) {
+    if created {
+        counts.created += 1;
+    } else {
+        counts.existing += 1;
+    }
+}
+
+/// Merges `archive` into the database in one transaction; any error leaves
+/// the database untouched. With `dry_run` the transaction is rolled back
+/// after counting, so the summary shows what would happen.
+#[instrument(skip(pool, archive))]
+pub async fn import_archive(
+    pool: &Pool<Sqlite>,
+    archive: &Archive,
+    dry_run: bool,
+) -> Result<ImportSummary, AppError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppError::Internal(format!(
+            "Not a {} document (format is '{}')",
+            ARCHIVE_FORMAT, archive.format
+        )));
+    }
+    if archive.version > ARCHIVE_VERSION {
+        return Err(AppError::Internal(format!(
+            "Archive version {} is newer than this build supports ({})",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+    info!(dry_run, "Importing archive");
+
+    let mut tx = pool.begin().await?;
+    let summary = import_into(&mut tx, archive).await?;
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(summary)
+}
+
+async fn import_into(
+    conn: &mut SqliteConnection,
+    archive: &Archive,
+) -> Result<ImportSummary, AppError> {
+    let mut summary = ImportSummary::default();
+
+    // Users first without graduated_by_id, which can point at any user.
+    let mut users = IdMap::default();
+    let mut new_users = Vec::new();
+    for user in &archive.users {
+        let existing: Option<(i64,)> = sqlx::query_as("SELECT id FROM users WHERE username = ?")
+            .bind(&user.username)
+            .fetch_optional(&mut *conn)
+            .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => {
+                let res = sqlx::query(
+                    "INSERT INTO users (username, role, password, display_name, archived,
+                                        graduated_at, email, approved_at, first_name,
+                                        last_name, timezone)
+                     VALUES (?, ?, '', ?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&user.username)
+                .bind(&user.role)
+                .bind(&user.display_name)
+                .bind(user.archived)
+                .bind(user.graduated_at)
+                .bind(&user.email)
+                .bind(user.approved_at)
+                .bind(&user.first_name)
+                .bind(&user.last_name)
+                .bind(&user.timezone)
+                .execute(&mut *conn)
+                .await?;
+                new_users.push(user);
+                res.last_insert_rowid()
+            }
+        };
+        count(&mut summary.users, existing.is_none());
+        users.0.insert(user.id, id);
+    }
+    for user in new_users {
+        if let Some(graduated_by) = users.get_opt(user.graduated_by_id) {
+            sqlx::query("UPDATE users SET graduated_by_id = ? WHERE id = ?")
+                .bind(graduated_by)
+                .bind(users.get("user", user.id)?)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    let mut tags = IdMap::default();
+    for tag in &archive.tags {
+        // Normalized and matched the way `create_tag` does, so "guard" meets "Guard".
+        let name = normalize_tag_name(&tag.name);
+        let existing: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM tags WHERE name = ? COLLATE NOCASE")
+                .bind(&name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query("INSERT INTO tags (name) VALUES (?)")
+                .bind(&name)
+                .execute(&mut *conn)
+                .await?
+                .last_insert_rowid(),
+        };
+        count(&mut summary.tags, existing.is_none());
+        tags.0.insert(tag.id, id);
+    }
+
+    let mut techniques = IdMap::default();
+    for technique in &archive.techniques {
+        let existing: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM techniques WHERE name = ? ORDER BY id LIMIT 1")
+                .bind(&technique.name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO techniques (name, description, coach_id) VALUES (?, ?, ?)",
+            )
+            .bind(&technique.name)
+            .bind(&technique.description)
+            .bind(users.get_opt(technique.coach_id))
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+        };
+        for tag_id in &technique.tag_ids {
+            sqlx::query("INSERT OR IGNORE INTO technique_tags (technique_id, tag_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(tags.get("tag", *tag_id)?)
+                .execute(&mut *conn)
+                .await?;
+        }
+        index_technique(conn, TechniqueId(id)).await?;
+        count(&mut summary.techniques, existing.is_none());
+        techniques.0.insert(technique.id, id);
+    }
+
+    let mut collections = IdMap::default();
+    for collection in &archive.collections {
+        let existing: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM collections WHERE name = ? ORDER BY id LIMIT 1")
+                .bind(&collection.name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO collections (name, description, coach_id, created_at, belt_level)
+                 VALUES (?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), ?)",
+            )
+            .bind(&collection.name)
+            .bind(&collection.description)
+            .bind(users.get_opt(collection.coach_id))
+            .bind(collection.created_at)
+            .bind(&collection.belt_level)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+        };
+        // Appended after any techniques the destination collection
+        // already has, keeping the archive's relative order.
+        for technique_id in &collection.technique_ids {
+            sqlx::query(
+                "INSERT OR IGNORE INTO collection_techniques (collection_id, technique_id, position)
+                 VALUES (?, ?, (SELECT COALESCE(MAX(position), -1) + 1
+                                FROM collection_techniques WHERE collection_id = ?))",
+            )
+            .bind(id)
+            .bind(techniques.get("technique", *technique_id)?)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        count(&mut summary.collections, existing.is_none());
+        collections.0.insert(collection.id, id);
+    }
+
+    let mut student_techniques = IdMap::default();
+    for st in &archive.student_techniques {
+        let student_id = users.get("user", st.student_id)?;
+        let technique_id = techniques.get("technique", st.technique_id)?;
+        let existing: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM student_techniques WHERE student_id = ? AND technique_id = ?
+             ORDER BY id LIMIT 1",
+        )
+        .bind(student_id)
+        .bind(technique_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        // An assignment that already exists keeps its own status and notes;
+        // the destination is treated as the more current copy.
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO student_techniques (
+                    technique_id, technique_name, technique_description, student_id, status,
+                    student_notes, coach_notes, created_at, updated_at, last_coach_update_at,
+                    last_coach_update_by_id, last_student_update_at,
+                    last_student_update_by_id, collection_id, review_requested_at
+                 ) VALUES (?, ?, ?, ?, COALESCE(?, 'red'), ?, ?,
+                           COALESCE(?, CURRENT_TIMESTAMP), COALESCE(?, CURRENT_TIMESTAMP),
+                           ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(technique_id)
+            .bind(&st.technique_name)
+            .bind(&st.technique_description)
+            .bind(student_id)
+            .bind(&st.status)
+            .bind(&st.student_notes)
+            .bind(&st.coach_notes)
+            .bind(st.created_at)
+            .bind(st.updated_at)
+            .bind(st.last_coach_update_at)
+            .bind(users.get_opt(st.last_coach_update_by_id))
+            .bind(st.last_student_update_at)
+            .bind(users.get_opt(st.last_student_update_by_id))
+            .bind(collections.get_opt(st.collection_id))
+            .bind(st.review_requested_at)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+        };
+        count(&mut summary.student_techniques, existing.is_none());
+        student_techniques.0.insert(st.id, id);
+    }
+
+    // Same assignment, recorder and moment means the same attempt. Two
+    // genuine attempts can share that key, so the n-th occurrence in the
+    // archive only counts as existing if the database already holds n.
+    let mut seen: HashMap<(i64, i64, NaiveDateTime), i64> = HashMap::new();
+    for attempt in &archive.attempts {
+        let st_id = student_techniques.get("student technique", attempt.student_technique_id)?;
+        let recorded_by = users.get("user", attempt.recorded_by_id)?;
+        let occurrence = seen.entry((st_id, recorded_by, attempt.attempted_at)).or_default();
+        let (stored,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM attempts
+             WHERE student_technique_id = ? AND recorded_by_id = ? AND attempted_at = ?",
+        )
+        .bind(st_id)
+        .bind(recorded_by)
+        .bind(attempt.attempted_at)
+        .fetch_one(&mut *conn)
+        .await?;
+        let exists = *occurrence < stored;
+        *occurrence += 1;
+        if !exists {
+            sqlx::query(
+                "INSERT INTO attempts (
+                    student_technique_id, recorded_by_id, attempted_at, coach_note,
+                    coach_note_by_id, coach_note_at, student_note, student_note_at, created_at
+                 ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(st_id)
+            .bind(recorded_by)
+            .bind(attempt.attempted_at)
+            .bind(&attempt.coach_note)
+            .bind(users.get_opt(attempt.coach_note_by_id))
+            .bind(attempt.coach_note_at)
+            .bind(&attempt.student_note)
+            .bind(attempt.student_note_at)
+            .bind(attempt.created_at)
+            .execute(&mut *conn)
+            .await?;
+        }
+        count(&mut summary.attempts, !exists);
+    }
+
+    // The destination's scale keeps its colours and order; statuses it
+    // lacks go on top, in the archive's order.
+    for status in &archive.statuses {
+        let res = sqlx::query(
+            "INSERT OR IGNORE INTO statuses (name, color, position)
+             SELECT ?, ?, COALESCE(MAX(position) + 1, 0) FROM statuses",
+        )
+        .bind(&status.name)
+        .bind(&status.color)
+        .execute(&mut *conn)
+        .await?;
+        count(&mut summary.statuses, res.rows_affected() > 0);
+    }
+
+    // The destination's own rules win on a clash.
+    for rule in &archive.status_transitions {
+        let res = sqlx::query(
+            "INSERT OR IGNORE INTO status_transitions (from_status, to_status, min_role)
+             VALUES (?, ?, ?)",
+        )
+        .bind(&rule.from_status)
+        .bind(&rule.to_status)
+        .bind(&rule.min_role)
+        .execute(&mut *conn)
+        .await?;
+        count(&mut summary.status_transitions, res.rows_affected() > 0);
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+use tracing::instrument;
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// A stored file (see `crate::attachments`). `storage_key` locates the object
+/// in whichever backend is configured.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attachment {
+    pub id: i64,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub filename: String,
+    pub content_type: String,
+    pub bytes: i64,
+    pub uploaded_by_id: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+#[instrument(skip(executor))]
+pub async fn create_attachment(
+    executor: impl SqliteExecutor<'_>,
+    storage_key: &str,
+    filename: &str,
+    content_type: &str,
+    bytes: i64,
+    uploaded_by: UserId,
+) -> Result<Attachment, AppError> {
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"INSERT INTO attachments (storage_key, filename, content_type, bytes, uploaded_by_id)
+           VALUES (?, ?, ?, ?, ?)
+           RETURNING id AS "id!", storage_key, filename, content_type, bytes, uploaded_by_id,
+                     created_at"#,
+        storage_key,
+        filename,
+        content_type,
+        bytes,
+        uploaded_by.0
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(attachment)
+}
+
+#[instrument(skip(executor))]
+pub async fn get_attachment(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<Option<Attachment>, AppError> {
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"SELECT id AS "id!", storage_key, filename, content_type, bytes, uploaded_by_id,
+                  created_at
+           FROM attachments WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(attachment)
+}
+
+#[instrument(skip(executor))]
+pub async fn get_attachment_by_key(
+    executor: impl SqliteExecutor<'_>,
+    storage_key: &str,
+) -> Result<Option<Attachment>, AppError> {
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"SELECT id AS "id!", storage_key, filename, content_type, bytes, uploaded_by_id,
+                  created_at
+           FROM attachments WHERE storage_key = ?"#,
+        storage_key
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(attachment)
+}
+
+/// Removes the row only; the caller deletes the object. If that fails the
+/// object is left for the cleanup job.
+#[instrument(skip(executor))]
+pub async fn delete_attachment(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<bool, AppError> {
+    let res = sqlx::query!("DELETE FROM attachments WHERE id = ?", id)
+        .execute(executor)
+        .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+/// Every key with a row, for telling live objects from orphans.
+#[instrument(skip(executor))]
+pub async fn get_attachment_keys(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<HashSet<String>, AppError> {
+    let keys = sqlx::query_scalar!("SELECT storage_key FROM attachments")
+        .fetch_all(executor)
+        .await?;
+    Ok(keys.into_iter().collect())
+}
@@ -1,12 +1,13 @@
 use chrono::{NaiveDateTime, Utc};
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::{info, instrument};
 
 use crate::auth::{Role, User};
 use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, UserId};
 use crate::models::{
     Attempt, AttemptBucket, AttemptCreateResult, AttemptListItem, AttemptSuggestion,
-    AttemptSummary, naive_to_utc,
+    AttemptSummary, display_name_or_username, naive_to_utc,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -40,20 +41,16 @@ fn hydrate_attempt_row(
     }
 }
 
-fn prefer_display_name(display: Option<String>, username: Option<String>) -> Option<String> {
-    display.filter(|s| !s.is_empty()).or(username)
-}
-
 /// Bump the parent student_technique's activity timestamps to "now" using
 /// the actor's role to pick the right slot. Mirrors how note edits via
 /// `update_student_technique` track activity.
 async fn bump_student_technique_activity(
     tx: &mut sqlx::Transaction<'_, Sqlite>,
-    student_technique_id: i64,
+    student_technique_id: StudentTechniqueId,
     actor: &User,
 ) -> Result<(), AppError> {
     let now = Utc::now().naive_utc();
-    let actor_id = actor.id;
+    let actor_id = actor.id.0;
     match actor.role {
         Role::Coach | Role::Admin => {
             sqlx::query!(
@@ -65,7 +62,7 @@ async fn bump_student_technique_activity(
                 now,
                 now,
                 actor_id,
-                student_technique_id,
+                student_technique_id.0,
             )
             .execute(&mut **tx)
             .await?;
@@ -80,7 +77,7 @@ async fn bump_student_technique_activity(
                 now,
                 now,
                 actor_id,
-                student_technique_id,
+                student_technique_id.0,
             )
             .execute(&mut **tx)
             .await?;
@@ -90,15 +87,16 @@ async fn bump_student_technique_activity(
 }
 
 /// Authorise an actor to read/append attempts for a given student technique.
-/// Coach/admin can act on anyone; a student can only act on their own.
+/// Coach/admin can act on anyone here; the API narrows coaches to their
+/// students before calling in. A student can only act on their own.
 async fn ensure_can_access_student_technique(
     pool: &Pool<Sqlite>,
     actor: &User,
-    student_technique_id: i64,
+    student_technique_id: StudentTechniqueId,
 ) -> Result<i64, AppError> {
     let row = sqlx::query!(
         "SELECT student_id FROM student_techniques WHERE id = ?",
-        student_technique_id
+        student_technique_id.0
     )
     .fetch_optional(pool)
     .await?;
@@ -110,7 +108,7 @@ async fn ensure_can_access_student_technique(
     match actor.role {
         Role::Coach | Role::Admin => Ok(student_id),
         Role::Student => {
-            if actor.id == student_id {
+            if actor.id.0 == student_id {
                 Ok(student_id)
             } else {
                 Err(AppError::Authorization(
@@ -125,13 +123,13 @@ async fn ensure_can_access_student_technique(
 pub async fn create_attempt(
     pool: &Pool<Sqlite>,
     actor: &User,
-    student_technique_id: i64,
+    student_technique_id: StudentTechniqueId,
     attempted_at: chrono::DateTime<Utc>,
     note: Option<&str>,
 ) -> Result<AttemptCreateResult, AppError> {
     info!("Creating attempt");
 
-    ensure_can_access_student_technique(pool, actor, student_technique_id).await?;
+    let student_id = ensure_can_access_student_technique(pool, actor, student_technique_id).await?;
 
     let mut tx = pool.begin().await?;
 
@@ -142,7 +140,7 @@ pub async fn create_attempt(
            LEFT JOIN attempts a ON a.student_technique_id = st.id
            WHERE st.id = ?
            GROUP BY st.id"#,
-        student_technique_id
+        student_technique_id.0
     )
     .fetch_optional(&mut *tx)
     .await?;
@@ -152,12 +150,12 @@ pub async fn create_attempt(
         None => {
             return Err(AppError::NotFound(format!(
                 "student_technique {}",
-                student_technique_id
+                student_technique_id.0
             )));
         }
     };
 
-    let actor_id = actor.id;
+    let actor_id = actor.id.0;
     let attempted_naive = attempted_at.naive_utc();
     let note_owned = note.map(|n| n.to_string());
 
@@ -185,7 +183,7 @@ pub async fn create_attempt(
             coach_note, coach_note_by_id, coach_note_at,
             student_note, student_note_at
          ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-        student_technique_id,
+        student_technique_id.0,
         actor_id,
         attempted_naive,
         coach_note,
@@ -217,12 +215,16 @@ pub async fn create_attempt(
 
     Ok(AttemptCreateResult {
         attempt,
+        student_id: UserId(student_id),
         suggestion,
     })
 }
 
-#[instrument]
-pub async fn get_attempt(pool: &Pool<Sqlite>, attempt_id: i64) -> Result<Attempt, AppError> {
+#[instrument(skip(executor))]
+pub async fn get_attempt(
+    executor: impl SqliteExecutor<'_>,
+    attempt_id: i64,
+) -> Result<Attempt, AppError> {
     let row = sqlx::query!(
         r#"SELECT a.id as "id!: i64", a.student_technique_id as "student_technique_id!: i64",
                   a.recorded_by_id as "recorded_by_id!: i64",
@@ -239,7 +241,7 @@ pub async fn get_attempt(pool: &Pool<Sqlite>, attempt_id: i64) -> Result<Attempt
            WHERE a.id = ?"#,
         attempt_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?
     .ok_or_else(|| AppError::NotFound(format!("attempt {}", attempt_id)))?;
 
@@ -247,11 +249,11 @@ pub async fn get_attempt(pool: &Pool<Sqlite>, attempt_id: i64) -> Result<Attempt
         row.id,
         row.student_technique_id,
         row.recorded_by_id,
-        prefer_display_name(row.rec_display, row.rec_username),
+        display_name_or_username(row.rec_display, row.rec_username),
         row.attempted_at,
         row.coach_note,
         row.coach_note_by_id,
-        prefer_display_name(row.cn_display, row.cn_username),
+        display_name_or_username(row.cn_display, row.cn_username),
         row.coach_note_at,
         row.student_note,
         row.student_note_at,
@@ -259,10 +261,29 @@ pub async fn get_attempt(pool: &Pool<Sqlite>, attempt_id: i64) -> Result<Attempt
     ))
 }
 
-#[instrument]
+/// The student whose technique `attempt_id` was logged against.
+#[instrument(skip(executor))]
+pub async fn get_attempt_student_id(
+    executor: impl SqliteExecutor<'_>,
+    attempt_id: i64,
+) -> Result<UserId, AppError> {
+    let student_id = sqlx::query_scalar!(
+        "SELECT st.student_id FROM attempts a
+         JOIN student_techniques st ON st.id = a.student_technique_id
+         WHERE a.id = ?",
+        attempt_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .flatten()
+    .ok_or_else(|| AppError::NotFound(format!("attempt {}", attempt_id)))?;
+    Ok(UserId(student_id))
+}
+
+#[instrument(skip(executor))]
 pub async fn list_attempts(
-    pool: &Pool<Sqlite>,
-    student_technique_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_technique_id: StudentTechniqueId,
 ) -> Result<Vec<Attempt>, AppError> {
     let rows = sqlx::query!(
         r#"SELECT a.id as "id!: i64", a.student_technique_id as "student_technique_id!: i64",
@@ -279,9 +300,9 @@ pub async fn list_attempts(
            LEFT JOIN users cnb ON cnb.id = a.coach_note_by_id
            WHERE a.student_technique_id = ?
            ORDER BY a.attempted_at DESC, a.id DESC"#,
-        student_technique_id,
+        student_technique_id.0,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows
@@ -291,11 +312,11 @@ pub async fn list_attempts(
                 row.id,
                 row.student_technique_id,
                 row.recorded_by_id,
-                prefer_display_name(row.rec_display, row.rec_username),
+                display_name_or_username(row.rec_display, row.rec_username),
                 row.attempted_at,
                 row.coach_note,
                 row.coach_note_by_id,
-                prefer_display_name(row.cn_display, row.cn_username),
+                display_name_or_username(row.cn_display, row.cn_username),
                 row.coach_note_at,
                 row.student_note,
                 row.student_note_at,
@@ -305,10 +326,10 @@ pub async fn list_attempts(
         .collect())
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn list_recent_attempts_for_student(
-    pool: &Pool<Sqlite>,
-    student_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
     limit: i64,
 ) -> Result<Vec<AttemptListItem>, AppError> {
     let rows = sqlx::query!(
@@ -323,10 +344,10 @@ pub async fn list_recent_attempts_for_student(
            WHERE st.student_id = ?
            ORDER BY a.attempted_at DESC, a.id DESC
            LIMIT ?"#,
-        student_id,
+        student_id.0,
         limit,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows
@@ -361,11 +382,21 @@ pub async fn delete_attempt(
     // Student can only delete attempts they recorded themselves.
     match actor.role {
         Role::Coach | Role::Admin => {
-            ensure_can_access_student_technique(pool, actor, row.student_technique_id).await?;
+            ensure_can_access_student_technique(
+                pool,
+                actor,
+                StudentTechniqueId(row.student_technique_id),
+            )
+            .await?;
         }
         Role::Student => {
-            ensure_can_access_student_technique(pool, actor, row.student_technique_id).await?;
-            if row.recorded_by_id != actor.id {
+            ensure_can_access_student_technique(
+                pool,
+                actor,
+                StudentTechniqueId(row.student_technique_id),
+            )
+            .await?;
+            if row.recorded_by_id != actor.id.0 {
                 return Err(AppError::Authorization(
                     "Students can only remove their own attempts".into(),
                 ));
@@ -395,10 +426,15 @@ pub async fn update_attempt_note(
     .await?
     .ok_or_else(|| AppError::NotFound(format!("attempt {}", attempt_id)))?;
 
-    ensure_can_access_student_technique(pool, actor, row.student_technique_id).await?;
+    ensure_can_access_student_technique(
+                pool,
+                actor,
+                StudentTechniqueId(row.student_technique_id),
+            )
+            .await?;
 
     let now = Utc::now().naive_utc();
-    let actor_id = actor.id;
+    let actor_id = actor.id.0;
     // Empty string clears the note.
     let normalised: Option<String> = note
         .map(|s| s.trim().to_string())
@@ -438,7 +474,8 @@ pub async fn update_attempt_note(
 
     // Editing or adding a note is meaningful activity on the technique, so
     // surface it in the dashboard's "recently updated" view too.
-    bump_student_technique_activity(&mut tx, row.student_technique_id, actor).await?;
+    bump_student_technique_activity(&mut tx, StudentTechniqueId(row.student_technique_id), actor)
+        .await?;
     tx.commit().await?;
 
     Ok(())
@@ -461,11 +498,21 @@ pub async fn update_attempt_timestamp(
 
     match actor.role {
         Role::Coach | Role::Admin => {
-            ensure_can_access_student_technique(pool, actor, row.student_technique_id).await?;
+            ensure_can_access_student_technique(
+                pool,
+                actor,
+                StudentTechniqueId(row.student_technique_id),
+            )
+            .await?;
         }
         Role::Student => {
-            ensure_can_access_student_technique(pool, actor, row.student_technique_id).await?;
-            if row.recorded_by_id != actor.id {
+            ensure_can_access_student_technique(
+                pool,
+                actor,
+                StudentTechniqueId(row.student_technique_id),
+            )
+            .await?;
+            if row.recorded_by_id != actor.id.0 {
                 return Err(AppError::Authorization(
                     "Students can only edit their own attempts".into(),
                 ));
@@ -485,10 +532,10 @@ pub async fn update_attempt_timestamp(
     Ok(())
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn attempt_summary_for_student(
-    pool: &Pool<Sqlite>,
-    student_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
 ) -> Result<AttemptSummary, AppError> {
     // Use SQLite's date arithmetic so "this week" / "this month" line up with
     // the server clock without juggling timezones in Rust.
@@ -500,9 +547,9 @@ pub async fn attempt_summary_for_student(
            FROM attempts a
            JOIN student_techniques st ON st.id = a.student_technique_id
            WHERE st.student_id = ?"#,
-        student_id
+        student_id.0
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
 
     Ok(AttemptSummary {
@@ -512,10 +559,10 @@ pub async fn attempt_summary_for_student(
     })
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn attempt_buckets_for_student(
-    pool: &Pool<Sqlite>,
-    student_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
     from: chrono::NaiveDate,
     to: chrono::NaiveDate,
 ) -> Result<Vec<AttemptBucket>, AppError> {
@@ -531,11 +578,11 @@ pub async fn attempt_buckets_for_student(
              AND date(a.attempted_at) <= ?
            GROUP BY date(a.attempted_at)
            ORDER BY 1"#,
-        student_id,
+        student_id.0,
         from_str,
         to_str,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows
@@ -551,10 +598,10 @@ pub async fn attempt_buckets_for_student(
         .collect())
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn attempt_weekly_buckets_for_technique(
-    pool: &Pool<Sqlite>,
-    student_technique_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_technique_id: StudentTechniqueId,
     weeks: i64,
 ) -> Result<Vec<AttemptBucket>, AppError> {
     // Bucket by ISO week (year-week). We resolve buckets to the Monday of each
@@ -568,10 +615,10 @@ pub async fn attempt_weekly_buckets_for_technique(
              AND a.attempted_at >= datetime('now', ?)
            GROUP BY date(a.attempted_at, 'weekday 0', '-6 days')
            ORDER BY 1"#,
-        student_technique_id,
+        student_technique_id.0,
         start_clause,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows
@@ -0,0 +1,217 @@
+//! Class sessions and attendance. A class is a scheduled session; marking
+//! attendance replaces the set of students who were there.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::naive_to_utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClassSession {
+    pub id: i64,
+    pub title: String,
+    pub starts_at: DateTime<Utc>,
+    pub duration_minutes: i64,
+    /// `None` once the coach who ran it is deleted.
+    pub coach_id: Option<UserId>,
+    /// Lowest id first.
+    pub student_ids: Vec<UserId>,
+}
+
+/// One class a student attended, for their attendance history.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AttendanceRecord {
+    pub class_id: i64,
+    pub title: String,
+    pub starts_at: DateTime<Utc>,
+    pub duration_minutes: i64,
+}
+
+#[instrument(skip(executor))]
+pub async fn create_class(
+    executor: impl SqliteExecutor<'_>,
+    title: &str,
+    starts_at: DateTime<Utc>,
+    duration_minutes: i64,
+    coach_id: UserId,
+) -> Result<i64, AppError> {
+    info!("Creating class");
+    let starts_at = starts_at.naive_utc();
+    let res = sqlx::query!(
+        "INSERT INTO classes (title, starts_at, duration_minutes, coach_id) VALUES (?, ?, ?, ?)",
+        title,
+        starts_at,
+        duration_minutes,
+        coach_id.0
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+#[instrument(skip(pool))]
+pub async fn get_class(pool: &Pool<Sqlite>, id: i64) -> Result<ClassSession, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", title, starts_at AS "starts_at: NaiveDateTime",
+                  duration_minutes, coach_id
+           FROM classes WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Class {} not found", id)))?;
+
+    let student_ids = sqlx::query_scalar!(
+        "SELECT student_id FROM attendance WHERE class_id = ? ORDER BY student_id",
+        id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ClassSession {
+        id: row.id,
+        title: row.title,
+        starts_at: naive_to_utc(row.starts_at),
+        duration_minutes: row.duration_minutes,
+        coach_id: row.coach_id.map(UserId),
+        student_ids: student_ids.into_iter().map(UserId).collect(),
+    })
+}
+
+/// Classes starting in `[from, to)`, earliest first, with who attended.
+#[instrument(skip(pool))]
+pub async fn list_classes(
+    pool: &Pool<Sqlite>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<ClassSession>, AppError> {
+    let (from, to) = (from.naive_utc(), to.naive_utc());
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", title, starts_at AS "starts_at: NaiveDateTime",
+                  duration_minutes, coach_id
+           FROM classes
+           WHERE starts_at >= ? AND starts_at < ?
+           ORDER BY starts_at, id"#,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let attendance = sqlx::query!(
+        "SELECT a.class_id, a.student_id
+         FROM attendance a
+         JOIN classes c ON c.id = a.class_id
+         WHERE c.starts_at >= ? AND c.starts_at < ?
+         ORDER BY a.student_id",
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut students: HashMap<i64, Vec<UserId>> = HashMap::new();
+    for row in attendance {
+        students.entry(row.class_id).or_default().push(UserId(row.student_id));
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ClassSession {
+            student_ids: students.remove(&row.id).unwrap_or_default(),
+            id: row.id,
+            title: row.title,
+            starts_at: naive_to_utc(row.starts_at),
+            duration_minutes: row.duration_minutes,
+            coach_id: row.coach_id.map(UserId),
+        })
+        .collect())
+}
+
+/// Makes `student_ids` the class's attendees. Students who stay keep their
+/// original `marked_at`.
+#[instrument(skip(pool))]
+pub async fn set_class_attendance(
+    pool: &Pool<Sqlite>,
+    class_id: i64,
+    student_ids: &[UserId],
+    marked_by: UserId,
+) -> Result<(), AppError> {
+    info!(count = student_ids.len(), "Marking attendance");
+    let mut tx = pool.begin().await?;
+
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM classes WHERE id = ?) AS "exists!: bool""#,
+        class_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Class {} not found", class_id)));
+    }
+
+    let wanted: HashSet<UserId> = student_ids.iter().copied().collect();
+    let existing =
+        sqlx::query_scalar!("SELECT student_id FROM attendance WHERE class_id = ?", class_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    for student_id in existing.into_iter().map(UserId) {
+        if !wanted.contains(&student_id) {
+            sqlx::query!(
+                "DELETE FROM attendance WHERE class_id = ? AND student_id = ?",
+                class_id,
+                student_id.0
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    for student_id in &wanted {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO attendance (class_id, student_id, marked_by_id)
+             VALUES (?, ?, ?)",
+            class_id,
+            student_id.0,
+            marked_by.0
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The classes `student_id` attended, most recent first.
+#[instrument(skip(executor))]
+pub async fn get_student_attendance(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+) -> Result<Vec<AttendanceRecord>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT c.id AS "id!", c.title, c.starts_at AS "starts_at: NaiveDateTime",
+                  c.duration_minutes
+           FROM attendance a
+           JOIN classes c ON c.id = a.class_id
+           WHERE a.student_id = ?
+           ORDER BY c.starts_at DESC, c.id DESC"#,
+        student_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| AttendanceRecord {
+            class_id: row.id,
+            title: row.title,
+            starts_at: naive_to_utc(row.starts_at),
+            duration_minutes: row.duration_minutes,
+        })
+        .collect())
+}
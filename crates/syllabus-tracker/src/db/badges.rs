@@ -0,0 +1,152 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::naive_to_utc;
+
+/// Distinct days with a recorded attempt that earn `TrainingDays`.
+pub const TRAINING_DAYS_FOR_BADGE: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeKind {
+    /// A first technique at green.
+    FirstGreen,
+    /// Attempts recorded on `TRAINING_DAYS_FOR_BADGE` different days. There
+    /// is no attendance record, so days trained stand in for classes.
+    TrainingDays,
+    /// Every technique in a collection at green. One per collection.
+    CurriculumComplete,
+}
+
+impl BadgeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BadgeKind::FirstGreen => "first_green",
+            BadgeKind::TrainingDays => "training_days",
+            BadgeKind::CurriculumComplete => "curriculum_complete",
+        }
+    }
+
+    fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "first_green" => Ok(BadgeKind::FirstGreen),
+            "training_days" => Ok(BadgeKind::TrainingDays),
+            "curriculum_complete" => Ok(BadgeKind::CurriculumComplete),
+            other => Err(AppError::Internal(format!("Unknown badge '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserBadge {
+    pub badge: BadgeKind,
+    /// The collection, for `CurriculumComplete`.
+    pub subject_id: Option<i64>,
+    /// `None` once the collection has been deleted.
+    pub subject_name: Option<String>,
+    pub awarded_at: DateTime<Utc>,
+}
+
+/// Awards every badge `student` (or, with `None`, any student) has earned
+/// and doesn't hold yet. Safe to call as often as needed; returns how many
+/// were new.
+#[instrument(skip(conn))]
+pub async fn award_badges(
+    conn: &mut SqliteConnection,
+    student: Option<UserId>,
+) -> Result<u64, AppError> {
+    let student = student.map(|id| id.0);
+    let first_green = BadgeKind::FirstGreen.as_str();
+    let training_days = BadgeKind::TrainingDays.as_str();
+    let curriculum = BadgeKind::CurriculumComplete.as_str();
+
+    let mut awarded = sqlx::query!(
+        "INSERT OR IGNORE INTO user_badges (user_id, badge)
+         SELECT DISTINCT student_id, ?
+         FROM student_techniques
+         WHERE status = 'green' AND student_id IS NOT NULL
+           AND (? IS NULL OR student_id = ?)",
+        first_green,
+        student,
+        student
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    awarded += sqlx::query!(
+        "INSERT OR IGNORE INTO user_badges (user_id, badge)
+         SELECT st.student_id, ?
+         FROM attempts a
+         JOIN student_techniques st ON st.id = a.student_technique_id
+         WHERE st.student_id IS NOT NULL AND (? IS NULL OR st.student_id = ?)
+         GROUP BY st.student_id
+         HAVING COUNT(DISTINCT date(a.attempted_at)) >= ?",
+        training_days,
+        student,
+        student,
+        TRAINING_DAYS_FOR_BADGE
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    awarded += sqlx::query!(
+        "INSERT OR IGNORE INTO user_badges (user_id, badge, subject_id)
+         SELECT st.student_id, ?, ct.collection_id
+         FROM collection_techniques ct
+         JOIN student_techniques st
+             ON st.technique_id = ct.technique_id AND st.status = 'green'
+         WHERE st.student_id IS NOT NULL AND (? IS NULL OR st.student_id = ?)
+         GROUP BY st.student_id, ct.collection_id
+         HAVING COUNT(DISTINCT ct.technique_id) = (
+             SELECT COUNT(*) FROM collection_techniques
+             WHERE collection_id = ct.collection_id
+         )",
+        curriculum,
+        student,
+        student
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    if awarded > 0 {
+        info!(awarded, "Badges awarded");
+    }
+    Ok(awarded)
+}
+
+/// Oldest first.
+#[instrument(skip(executor))]
+pub async fn get_user_badges(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<Vec<UserBadge>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT b.badge, b.subject_id, c.name AS "subject_name?: String",
+                  b.awarded_at AS "awarded_at: NaiveDateTime"
+           FROM user_badges b
+           LEFT JOIN collections c ON c.id = b.subject_id
+           WHERE b.user_id = ?
+           ORDER BY b.awarded_at, b.rowid"#,
+        user_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(UserBadge {
+                badge: BadgeKind::from_db(&row.badge)?,
+                subject_id: (row.subject_id != 0).then_some(row.subject_id),
+                subject_name: row.subject_name,
+                awarded_at: naive_to_utc(row.awarded_at),
+            })
+        })
+        .collect()
+}
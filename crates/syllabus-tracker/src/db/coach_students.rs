@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// The students assigned to a coach, lowest id first.
+#[instrument(skip(executor))]
+pub async fn get_coach_student_ids(
+    executor: impl SqliteExecutor<'_>,
+    coach_id: UserId,
+) -> Result<Vec<UserId>, AppError> {
+    let ids = sqlx::query_scalar!(
+        "SELECT student_id FROM coach_students WHERE coach_id = ? ORDER BY student_id",
+        coach_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(ids.into_iter().map(UserId).collect())
+}
+
+/// Which students a coach sees. A coach with none assigned sees nobody, so
+/// removing their last student never widens what they can reach.
+pub async fn get_coach_student_scope(
+    executor: impl SqliteExecutor<'_>,
+    coach_id: UserId,
+) -> Result<HashSet<UserId>, AppError> {
+    let ids = get_coach_student_ids(executor, coach_id).await?;
+    Ok(ids.into_iter().collect())
+}
+
+/// Makes `student_ids` the coach's students. Students who stay keep their
+/// original `assigned_at`; an empty list leaves the coach with nobody.
+#[instrument(skip(pool))]
+pub async fn replace_coach_students(
+    pool: &Pool<Sqlite>,
+    coach_id: UserId,
+    student_ids: &[UserId],
+    assigned_by: UserId,
+) -> Result<(), AppError> {
+    info!(count = student_ids.len(), "Replacing coach students");
+    let mut tx = pool.begin().await?;
+
+    let wanted: HashSet<UserId> = student_ids.iter().copied().collect();
+    for existing in get_coach_student_ids(&mut *tx, coach_id).await? {
+        if !wanted.contains(&existing) {
+            sqlx::query!(
+                "DELETE FROM coach_students WHERE coach_id = ? AND student_id = ?",
+                coach_id.0,
+                existing.0
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    for student_id in &wanted {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO coach_students (coach_id, student_id, assigned_by_id)
+             VALUES (?, ?, ?)",
+            coach_id.0,
+            student_id.0,
+            assigned_by.0
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -1,44 +1,49 @@
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::{info, instrument};
 
 use crate::auth::{DbUser, User};
 use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
 use crate::models::{Collection, Technique, naive_to_utc};
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn create_collection(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     name: &str,
     description: &str,
-    coach_id: i64,
+    belt_level: Option<&str>,
+    coach_id: UserId,
 ) -> Result<i64, AppError> {
     info!("Creating collection");
     let res = sqlx::query!(
-        "INSERT INTO collections (name, description, coach_id) VALUES (?, ?, ?)",
+        "INSERT INTO collections (name, description, belt_level, coach_id) VALUES (?, ?, ?, ?)",
         name,
         description,
-        coach_id
+        belt_level,
+        coach_id.0
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(res.last_insert_rowid())
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn update_collection(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     collection_id: i64,
     name: &str,
     description: &str,
+    belt_level: Option<&str>,
 ) -> Result<(), AppError> {
     info!("Updating collection");
     sqlx::query!(
-        "UPDATE collections SET name = ?, description = ? WHERE id = ?",
+        "UPDATE collections SET name = ?, description = ?, belt_level = ? WHERE id = ?",
         name,
         description,
+        belt_level,
         collection_id
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
@@ -60,23 +65,25 @@ pub async fn delete_collection(pool: &Pool<Sqlite>, collection_id: i64) -> Resul
     Ok(())
 }
 
-#[instrument]
-pub async fn get_all_collections(pool: &Pool<Sqlite>) -> Result<Vec<Collection>, AppError> {
+#[instrument(skip(executor))]
+pub async fn get_all_collections(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<Vec<Collection>, AppError> {
     info!("Listing collections");
     let rows = sqlx::query!(
         r#"
         SELECT
-            c.id, c.name, c.description, c.coach_id,
+            c.id, c.name, c.description, c.coach_id, c.belt_level,
             c.created_at as "created_at: chrono::NaiveDateTime",
             (SELECT COUNT(*) FROM collection_techniques WHERE collection_id = c.id)
                 as "technique_count!: i64",
             (SELECT COUNT(DISTINCT student_id) FROM student_techniques WHERE collection_id = c.id)
                 as "student_count!: i64"
         FROM collections c
-        ORDER BY c.name
+        ORDER BY c.name, c.id
         "#
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows
@@ -93,6 +100,7 @@ pub async fn get_all_collections(pool: &Pool<Sqlite>) -> Result<Vec<Collection>,
             technique_count: r.technique_count,
             student_count: r.student_count,
             techniques: Vec::new(),
+            belt_level: r.belt_level,
         })
         .collect())
 }
@@ -106,7 +114,7 @@ pub async fn get_collection(
     let row = sqlx::query!(
         r#"
         SELECT
-            c.id, c.name, c.description, c.coach_id,
+            c.id, c.name, c.description, c.coach_id, c.belt_level,
             c.created_at as "created_at: chrono::NaiveDateTime",
             (SELECT COUNT(*) FROM collection_techniques WHERE collection_id = c.id)
                 as "technique_count!: i64",
@@ -123,11 +131,11 @@ pub async fn get_collection(
 
     let technique_rows = sqlx::query!(
         r#"
-        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name
+        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name, t.parent_technique_id
         FROM collection_techniques ct
         JOIN techniques t ON t.id = ct.technique_id
-        WHERE ct.collection_id = ?
-        ORDER BY ct.position, t.name
+        WHERE ct.collection_id = ? AND t.deleted_at IS NULL
+        ORDER BY ct.position, t.name, t.id
         "#,
         collection_id
     )
@@ -137,12 +145,14 @@ pub async fn get_collection(
     let techniques: Vec<Technique> = technique_rows
         .into_iter()
         .map(|r| Technique {
-            id: r.id.unwrap_or_default(),
+            id: TechniqueId(r.id.unwrap_or_default()),
             name: r.name,
             description: r.description.unwrap_or_default(),
-            coach_id: r.coach_id.unwrap_or_default(),
+            coach_id: UserId(r.coach_id.unwrap_or_default()),
             coach_name: r.coach_name.unwrap_or_default(),
+            parent_technique_id: r.parent_technique_id.map(TechniqueId),
             tags: Vec::new(),
+            aliases: Vec::new(),
         })
         .collect();
 
@@ -158,14 +168,15 @@ pub async fn get_collection(
         technique_count: row.technique_count,
         student_count: row.student_count,
         techniques,
+        belt_level: row.belt_level,
     })
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn add_technique_to_collection(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     collection_id: i64,
-    technique_id: i64,
+    technique_id: TechniqueId,
 ) -> Result<(), AppError> {
     info!("Adding technique to collection");
     sqlx::query!(
@@ -173,10 +184,10 @@ pub async fn add_technique_to_collection(
          VALUES (?, ?,
             (SELECT COALESCE(MAX(position), -1) + 1 FROM collection_techniques WHERE collection_id = ?))",
         collection_id,
-        technique_id,
+        technique_id.0,
         collection_id
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
@@ -185,7 +196,7 @@ pub async fn add_technique_to_collection(
 pub async fn add_techniques_to_collection(
     pool: &Pool<Sqlite>,
     collection_id: i64,
-    technique_ids: Vec<i64>,
+    technique_ids: Vec<TechniqueId>,
 ) -> Result<(), AppError> {
     info!("Adding techniques to collection");
     for technique_id in technique_ids {
@@ -197,11 +208,11 @@ pub async fn add_techniques_to_collection(
 #[instrument]
 pub async fn create_technique_in_collection(
     pool: &Pool<Sqlite>,
-    coach_id: i64,
+    coach_id: UserId,
     collection_id: i64,
     name: &str,
     description: &str,
-) -> Result<i64, AppError> {
+) -> Result<TechniqueId, AppError> {
     info!("Creating technique in collection");
     let technique_id = super::create_technique(pool, name, description, coach_id).await?;
     add_technique_to_collection(pool, collection_id, technique_id).await?;
@@ -212,13 +223,13 @@ pub async fn create_technique_in_collection(
 pub async fn remove_technique_from_collection(
     pool: &Pool<Sqlite>,
     collection_id: i64,
-    technique_id: i64,
+    technique_id: TechniqueId,
 ) -> Result<(), AppError> {
     info!("Removing technique from collection");
     sqlx::query!(
         "DELETE FROM collection_techniques WHERE collection_id = ? AND technique_id = ?",
         collection_id,
-        technique_id
+        technique_id.0
     )
     .execute(pool)
     .await?;
@@ -229,13 +240,39 @@ pub async fn remove_technique_from_collection(
          SET collection_id = NULL
          WHERE collection_id = ? AND technique_id = ?",
         collection_id,
-        technique_id
+        technique_id.0
     )
     .execute(pool)
     .await?;
     Ok(())
 }
 
+/// Puts the collection's techniques in the given order. The caller checks
+/// that `technique_ids` is exactly the collection's techniques; any other id
+/// is ignored.
+#[instrument(skip(pool))]
+pub async fn reorder_collection_techniques(
+    pool: &Pool<Sqlite>,
+    collection_id: i64,
+    technique_ids: &[TechniqueId],
+) -> Result<(), AppError> {
+    info!("Reordering collection techniques");
+    let mut tx = pool.begin().await?;
+    for (position, technique_id) in (0_i64..).zip(technique_ids) {
+        sqlx::query!(
+            "UPDATE collection_techniques SET position = ?
+             WHERE collection_id = ? AND technique_id = ?",
+            position,
+            collection_id,
+            technique_id.0
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
 /// Bulk-assign every technique in a collection to a student. Idempotent:
 /// techniques the student already has are moved into this collection
 /// (collection_id update), techniques they don't have are inserted. Returns
@@ -243,13 +280,17 @@ pub async fn remove_technique_from_collection(
 #[instrument]
 pub async fn assign_collection_to_student(
     pool: &Pool<Sqlite>,
-    student_id: i64,
+    student_id: UserId,
     collection_id: i64,
-    actor_id: i64,
+    actor_id: UserId,
 ) -> Result<usize, AppError> {
     info!("Assigning collection to student");
     let technique_ids: Vec<i64> = sqlx::query_scalar!(
-        "SELECT technique_id FROM collection_techniques WHERE collection_id = ? ORDER BY position",
+        "SELECT ct.technique_id
+         FROM collection_techniques ct
+         JOIN techniques t ON t.id = ct.technique_id
+         WHERE ct.collection_id = ? AND t.deleted_at IS NULL
+         ORDER BY ct.position",
         collection_id
     )
     .fetch_all(pool)
@@ -257,19 +298,25 @@ pub async fn assign_collection_to_student(
 
     let before: i64 = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM student_techniques WHERE student_id = ?",
-        student_id
+        student_id.0
     )
     .fetch_one(pool)
     .await?;
 
     for tid in technique_ids {
-        super::assign_technique_to_student(pool, tid, student_id, Some(collection_id), actor_id)
-            .await?;
+        super::assign_technique_to_student(
+            pool,
+            TechniqueId(tid),
+            student_id,
+            Some(collection_id),
+            actor_id,
+        )
+        .await?;
     }
 
     let after: i64 = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM student_techniques WHERE student_id = ?",
-        student_id
+        student_id.0
     )
     .fetch_one(pool)
     .await?;
@@ -277,9 +324,9 @@ pub async fn assign_collection_to_student(
     Ok((after - before).max(0) as usize)
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn get_students_with_collection(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     collection_id: i64,
 ) -> Result<Vec<User>, AppError> {
     info!("Listing students with collection");
@@ -292,15 +339,16 @@ pub async fn get_students_with_collection(
                u.claimed_at as "claimed_at: chrono::NaiveDateTime",
                u.approved_at as "approved_at: chrono::NaiveDateTime",
                u.first_name, u.last_name,
-               u.reset_requested_at as "reset_requested_at: chrono::NaiveDateTime"
+               u.reset_requested_at as "reset_requested_at: chrono::NaiveDateTime",
+               u.timezone, u.membership_status, u.avatar_attachment_id
         FROM users u
         JOIN student_techniques st ON st.student_id = u.id
         WHERE st.collection_id = ?
-        ORDER BY u.display_name, u.username
+        ORDER BY u.display_name, u.username, u.id
         "#,
         collection_id
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
-    Ok(rows.into_iter().map(User::from).collect())
+    rows.into_iter().map(User::try_from).collect()
 }
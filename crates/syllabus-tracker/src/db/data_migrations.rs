@@ -0,0 +1,86 @@
+//! Data migrations: idempotent row rewrites that run on startup, after the
+//! schema check. The declarative migrator only reconciles table shapes, so
+//! when a new write-time rule applies to data that already exists, its
+//! backfill is registered here. Every step must be safe to run on each boot.
+
+use sqlx::{Pool, Row, Sqlite};
+use tracing::{info, instrument};
+
+use super::statuses::seed_default_statuses;
+use super::student_techniques::normalize_legacy_update_timestamps;
+use super::tags::normalize_existing_tag_names;
+use super::technique_search::rebuild_technique_search;
+use super::users::normalize_existing_usernames;
+use crate::error::AppError;
+use crate::validation::sanitize_plain_text;
+
+#[instrument(skip(pool))]
+pub async fn run_data_migrations(pool: &Pool<Sqlite>) -> Result<(), AppError> {
+    info!("Running data migrations");
+    normalize_existing_usernames(pool).await?;
+    normalize_existing_tag_names(pool).await?;
+    normalize_legacy_update_timestamps(pool).await?;
+    sanitize_stored_text(pool).await?;
+    seed_default_statuses(pool).await?;
+    // Last, so the index sees the rewrites above.
+    rebuild_technique_search(pool).await?;
+    Ok(())
+}
+
+/// Every free-text column the API writes through `deserialize_plain_text`.
+const FREE_TEXT_COLUMNS: &[(&str, &str)] = &[
+    ("student_techniques", "student_notes"),
+    ("student_techniques", "coach_notes"),
+    ("student_techniques", "technique_name"),
+    ("student_techniques", "technique_description"),
+    ("techniques", "name"),
+    ("techniques", "description"),
+    ("users", "display_name"),
+    ("users", "first_name"),
+    ("users", "last_name"),
+    ("collections", "name"),
+    ("collections", "description"),
+    ("attempts", "coach_note"),
+    ("attempts", "student_note"),
+    ("videos", "title"),
+    ("videos", "description"),
+];
+
+/// Strips HTML from rows written before the API sanitised free text. Only
+/// values containing `<` are read, and `sanitize_plain_text` output is
+/// stable, so after the first boot this finds nothing to change. Returns the
+/// number of values rewritten.
+#[instrument(skip(pool))]
+pub async fn sanitize_stored_text(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let mut rewritten = 0;
+
+    for (table, column) in FREE_TEXT_COLUMNS {
+        // Table and column names come from the constant above, never input.
+        let rows = sqlx::query(&format!(
+            "SELECT id, {column} FROM {table} WHERE {column} LIKE '%<%'"
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for row in rows {
+            let id: i64 = row.try_get(0)?;
+            let value: String = row.try_get(1)?;
+            let cleaned = sanitize_plain_text(&value);
+            if cleaned != value {
+                sqlx::query(&format!("UPDATE {table} SET {column} = ? WHERE id = ?"))
+                    .bind(cleaned)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                rewritten += 1;
+            }
+        }
+    }
+
+    tx.commit().await?;
+    if rewritten > 0 {
+        info!(rewritten, "Stripped HTML from stored free text");
+    }
+    Ok(rewritten)
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use sqlx::SqliteExecutor;
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// Stored overrides only, keyed by flag key. Flags without a row use their
+/// default, which lives with the flag definition in `crate::flags`.
+#[instrument(skip(executor))]
+pub async fn get_feature_flag_overrides(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<HashMap<String, bool>, AppError> {
+    let rows = sqlx::query!(r#"SELECT key AS "key!", enabled FROM feature_flags"#)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(rows.into_iter().map(|row| (row.key, row.enabled != 0)).collect())
+}
+
+#[instrument(skip(executor))]
+pub async fn set_feature_flag(
+    executor: impl SqliteExecutor<'_>,
+    key: &str,
+    enabled: bool,
+    updated_by: UserId,
+) -> Result<(), AppError> {
+    info!("Setting feature flag");
+    sqlx::query!(
+        "INSERT INTO feature_flags (key, enabled, updated_at, updated_by_id)
+         VALUES (?, ?, CURRENT_TIMESTAMP, ?)
+         ON CONFLICT (key) DO UPDATE SET
+             enabled = excluded.enabled,
+             updated_at = excluded.updated_at,
+             updated_by_id = excluded.updated_by_id",
+        key,
+        enabled,
+        updated_by.0
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+//! Gradings: a coach assesses a student against a curriculum technique by
+//! technique, then closes the grading with an outcome. Unlike a technique's
+//! status, a grading is a dated record of one assessment that stays as it
+//! was once closed.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
+use crate::models::naive_to_utc;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GradingOutcome {
+    Passed,
+    Failed,
+    /// Not decided this time; the student is to be graded again later.
+    Deferred,
+}
+
+impl GradingOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GradingOutcome::Passed => "passed",
+            GradingOutcome::Failed => "failed",
+            GradingOutcome::Deferred => "deferred",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "passed" => Ok(GradingOutcome::Passed),
+            "failed" => Ok(GradingOutcome::Failed),
+            "deferred" => Ok(GradingOutcome::Deferred),
+            other => Err(AppError::Internal(format!("Unknown grading outcome '{}'", other))),
+        }
+    }
+}
+
+/// One curriculum technique within a grading.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GradingResult {
+    pub technique_id: TechniqueId,
+    pub technique_name: String,
+    /// `None` until the coach has graded it.
+    pub passed: Option<bool>,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Grading {
+    pub id: i64,
+    pub student_id: UserId,
+    /// `None` once the curriculum is deleted; `collection_name` remains.
+    pub collection_id: Option<i64>,
+    pub collection_name: String,
+    pub coach_id: Option<UserId>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Set when the grading is closed.
+    pub outcome: Option<GradingOutcome>,
+    pub notes: String,
+    /// In curriculum order.
+    pub results: Vec<GradingResult>,
+}
+
+/// A grading without its results, with how many techniques are in each
+/// state, for listings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GradingSummary {
+    pub id: i64,
+    pub student_id: UserId,
+    pub collection_id: Option<i64>,
+    pub collection_name: String,
+    pub coach_id: Option<UserId>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub outcome: Option<GradingOutcome>,
+    pub passed: i64,
+    pub failed: i64,
+    pub pending: i64,
+}
+
+/// Opens a grading of `student_id` against the curriculum, copying in its
+/// current techniques. Deleted techniques are left out.
+#[instrument(skip(pool))]
+pub async fn open_grading(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+    collection_id: i64,
+    coach_id: UserId,
+) -> Result<i64, AppError> {
+    info!("Opening grading");
+    let mut tx = pool.begin().await?;
+
+    let collection_name =
+        sqlx::query_scalar!("SELECT name FROM collections WHERE id = ?", collection_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Collection {} not found", collection_id)))?;
+
+    let grading_id = sqlx::query!(
+        "INSERT INTO gradings (student_id, collection_id, collection_name, coach_id)
+         VALUES (?, ?, ?, ?)",
+        student_id.0,
+        collection_id,
+        collection_name,
+        coach_id.0
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query!(
+        "INSERT INTO grading_results (grading_id, technique_id, technique_name, position)
+         SELECT ?, t.id, t.name, ct.position
+         FROM collection_techniques ct
+         JOIN techniques t ON t.id = ct.technique_id
+         WHERE ct.collection_id = ? AND t.deleted_at IS NULL",
+        grading_id,
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(grading_id)
+}
+
+#[instrument(skip(pool))]
+pub async fn get_grading(pool: &Pool<Sqlite>, id: i64) -> Result<Grading, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", student_id, collection_id, collection_name, coach_id,
+                  opened_at AS "opened_at: NaiveDateTime",
+                  closed_at AS "closed_at: NaiveDateTime", outcome, notes
+           FROM gradings WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Grading {} not found", id)))?;
+
+    let results = sqlx::query!(
+        r#"SELECT technique_id, technique_name, passed AS "passed: bool", note
+           FROM grading_results
+           WHERE grading_id = ?
+           ORDER BY position, technique_id"#,
+        id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| GradingResult {
+        technique_id: TechniqueId(r.technique_id),
+        technique_name: r.technique_name,
+        passed: r.passed,
+        note: r.note,
+    })
+    .collect();
+
+    Ok(Grading {
+        id: row.id,
+        student_id: UserId(row.student_id),
+        collection_id: row.collection_id,
+        collection_name: row.collection_name,
+        coach_id: row.coach_id.map(UserId),
+        opened_at: naive_to_utc(row.opened_at),
+        closed_at: row.closed_at.map(naive_to_utc),
+        outcome: row.outcome.as_deref().map(GradingOutcome::from_db).transpose()?,
+        notes: row.notes,
+        results,
+    })
+}
+
+/// Gradings newest first, of one student or of everyone.
+#[instrument(skip(executor))]
+pub async fn list_gradings(
+    executor: impl SqliteExecutor<'_>,
+    student_id: Option<UserId>,
+) -> Result<Vec<GradingSummary>, AppError> {
+    let student_id = student_id.map(|id| id.0);
+    let rows = sqlx::query!(
+        r#"SELECT g.id AS "id!", g.student_id, g.collection_id, g.collection_name, g.coach_id,
+                  g.opened_at AS "opened_at: NaiveDateTime",
+                  g.closed_at AS "closed_at: NaiveDateTime", g.outcome,
+                  COUNT(CASE WHEN r.passed = 1 THEN 1 END) AS "passed!: i64",
+                  COUNT(CASE WHEN r.passed = 0 THEN 1 END) AS "failed!: i64",
+                  COUNT(CASE WHEN r.technique_id IS NOT NULL AND r.passed IS NULL THEN 1 END)
+                      AS "pending!: i64"
+           FROM gradings g
+           LEFT JOIN grading_results r ON r.grading_id = g.id
+           WHERE ? IS NULL OR g.student_id = ?
+           GROUP BY g.id
+           ORDER BY g.opened_at DESC, g.id DESC"#,
+        student_id,
+        student_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(GradingSummary {
+                id: row.id,
+                student_id: UserId(row.student_id),
+                collection_id: row.collection_id,
+                collection_name: row.collection_name,
+                coach_id: row.coach_id.map(UserId),
+                opened_at: naive_to_utc(row.opened_at),
+                closed_at: row.closed_at.map(naive_to_utc),
+                outcome: row.outcome.as_deref().map(GradingOutcome::from_db).transpose()?,
+                passed: row.passed,
+                failed: row.failed,
+                pending: row.pending,
+            })
+        })
+        .collect()
+}
+
+/// Records whether the student passed one technique; `None` puts it back to
+/// ungraded. Not found if the technique isn't part of the grading.
+#[instrument(skip(executor, note))]
+pub async fn record_grading_result(
+    executor: impl SqliteExecutor<'_>,
+    grading_id: i64,
+    technique_id: TechniqueId,
+    passed: Option<bool>,
+    note: &str,
+) -> Result<(), AppError> {
+    info!("Recording grading result");
+    let res = sqlx::query!(
+        "UPDATE grading_results SET passed = ?, note = ?
+         WHERE grading_id = ? AND technique_id = ?",
+        passed,
+        note,
+        grading_id,
+        technique_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "Technique {} is not part of grading {}",
+            technique_id, grading_id
+        )));
+    }
+    Ok(())
+}
+
+/// Closes an open grading. Returns false if it was already closed.
+#[instrument(skip(executor, notes))]
+pub async fn close_grading(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    outcome: GradingOutcome,
+    notes: &str,
+) -> Result<bool, AppError> {
+    info!(outcome = outcome.as_str(), "Closing grading");
+    let now = Utc::now().naive_utc();
+    let outcome = outcome.as_str();
+    let res = sqlx::query!(
+        "UPDATE gradings SET closed_at = ?, outcome = ?, notes = ?
+         WHERE id = ? AND closed_at IS NULL",
+        now,
+        outcome,
+        notes,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
@@ -0,0 +1,144 @@
+//! Stored responses for `Idempotency-Key` retries. The fairing in
+//! `crate::idempotency` claims a key before the handler runs, and either
+//! stores what the handler answered or releases the key so it can be tried
+//! again.
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+
+/// How long a key is remembered. A claim left in progress by a crash also
+/// frees up after this long.
+pub const IDEMPOTENCY_KEY_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotencyClaim {
+    /// The key is new (or expired): run the request.
+    Claimed,
+    /// The first request with this key hasn't answered yet.
+    InProgress,
+    Replay(StoredResponse),
+    /// The key was first sent with a different method, path or body.
+    Mismatch,
+}
+
+fn expired_before() -> NaiveDateTime {
+    Utc::now().naive_utc() - chrono::Duration::hours(IDEMPOTENCY_KEY_TTL_HOURS)
+}
+
+/// Claims `key` in `scope` for a request, or says what became of the
+/// request that claimed it first. `request_hash` fingerprints the request
+/// body, so a key reused for different content is refused, not replayed.
+#[instrument(skip(pool))]
+pub async fn claim_idempotency_key(
+    pool: &Pool<Sqlite>,
+    scope: &str,
+    key: &str,
+    method: &str,
+    path: &str,
+    request_hash: &str,
+) -> Result<IdempotencyClaim, AppError> {
+    let now = Utc::now().naive_utc();
+    let expired_before = expired_before();
+    let res = sqlx::query!(
+        "INSERT INTO idempotency_keys (scope, key, method, path, request_hash, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (scope, key) DO UPDATE
+         SET method = ?3, path = ?4, request_hash = ?5, status = NULL, content_type = NULL,
+             body = NULL, created_at = ?6
+         WHERE idempotency_keys.created_at < ?7",
+        scope,
+        key,
+        method,
+        path,
+        request_hash,
+        now,
+        expired_before
+    )
+    .execute(pool)
+    .await?;
+    if res.rows_affected() > 0 {
+        return Ok(IdempotencyClaim::Claimed);
+    }
+
+    let row = sqlx::query!(
+        r#"SELECT method, path, request_hash, status AS "status: i64", content_type, body
+           FROM idempotency_keys WHERE scope = ? AND key = ?"#,
+        scope,
+        key
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if row.method != method || row.path != path || row.request_hash != request_hash {
+        return Ok(IdempotencyClaim::Mismatch);
+    }
+    Ok(match row.status {
+        None => IdempotencyClaim::InProgress,
+        Some(status) => IdempotencyClaim::Replay(StoredResponse {
+            status: u16::try_from(status).unwrap_or(500),
+            content_type: row.content_type,
+            body: row.body.unwrap_or_default(),
+        }),
+    })
+}
+
+#[instrument(skip(executor, response))]
+pub async fn store_idempotent_response(
+    executor: impl SqliteExecutor<'_>,
+    scope: &str,
+    key: &str,
+    response: &StoredResponse,
+) -> Result<(), AppError> {
+    let status = i64::from(response.status);
+    sqlx::query!(
+        "UPDATE idempotency_keys SET status = ?, content_type = ?, body = ?
+         WHERE scope = ? AND key = ?",
+        status,
+        response.content_type,
+        response.body,
+        scope,
+        key
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Forgets a claim whose request failed, so a retry runs it again.
+#[instrument(skip(executor))]
+pub async fn release_idempotency_key(
+    executor: impl SqliteExecutor<'_>,
+    scope: &str,
+    key: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM idempotency_keys WHERE scope = ? AND key = ? AND status IS NULL",
+        scope,
+        key
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[instrument(skip(executor))]
+pub async fn clean_expired_idempotency_keys(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<u64, AppError> {
+    info!("Cleaning expired idempotency keys");
+    let before = expired_before();
+    let res = sqlx::query!("DELETE FROM idempotency_keys WHERE created_at < ?", before)
+        .execute(executor)
+        .await?;
+    Ok(res.rows_affected())
+}
@@ -0,0 +1,76 @@
+//! Links between accounts at an OpenID Connect provider and local users, for
+//! `crate::oidc`.
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// The user an external account signs in as. An account seen for the first
+/// time is linked to the user with `email`, if exactly one user with a
+/// username has it; `email` should be one the provider has verified.
+/// `None` when there is no such user.
+#[instrument(skip(pool))]
+pub async fn resolve_identity(
+    pool: &Pool<Sqlite>,
+    issuer: &str,
+    subject: &str,
+    email: Option<&str>,
+) -> Result<Option<UserId>, AppError> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let linked = sqlx::query_scalar!(
+        "SELECT user_id FROM user_identities WHERE issuer = ? AND subject = ?",
+        issuer,
+        subject
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(user_id) = linked {
+        sqlx::query!(
+            "UPDATE user_identities SET email = COALESCE(?, email), last_login_at = ?
+             WHERE issuer = ? AND subject = ?",
+            email,
+            now,
+            issuer,
+            subject
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        return Ok(Some(UserId(user_id)));
+    }
+
+    let Some(email) = email else {
+        return Ok(None);
+    };
+    // Stubs have no username until they claim their invite, and two users
+    // sharing an email can't be told apart.
+    let matches = sqlx::query_scalar!(
+        "SELECT id FROM users WHERE email = ? COLLATE NOCASE AND username IS NOT NULL",
+        email
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let [Some(user_id)] = matches[..] else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        "INSERT INTO user_identities (user_id, issuer, subject, email, last_login_at)
+         VALUES (?, ?, ?, ?, ?)",
+        user_id,
+        issuer,
+        subject,
+        email,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!(user_id, "Linked external identity by email");
+    Ok(Some(UserId(user_id)))
+}
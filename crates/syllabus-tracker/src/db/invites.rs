@@ -1,31 +1,36 @@
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::{info, instrument};
 
+use super::delete_user_sessions;
 use crate::error::AppError;
+use crate::ids::UserId;
 
 #[derive(Debug, Clone)]
 pub struct InviteToken {
     pub id: i64,
-    pub user_id: i64,
+    pub user_id: UserId,
 }
 
 /// Create an invite token tied to a user. Token expires in 7 days. The token
 /// value is generated via the same `UserSession::generate_token` used for
 /// session cookies.
-#[instrument]
-pub async fn create_invite_token(pool: &Pool<Sqlite>, user_id: i64) -> Result<String, AppError> {
+#[instrument(skip(executor))]
+pub async fn create_invite_token(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<String, AppError> {
     info!("Creating invite token");
     let token = crate::auth::UserSession::generate_token();
     let expires_at = (Utc::now() + chrono::Duration::days(7)).naive_utc();
 
     sqlx::query!(
         "INSERT INTO invite_tokens (user_id, token, expires_at) VALUES (?, ?, ?)",
-        user_id,
+        user_id.0,
         token,
         expires_at
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(token)
@@ -33,9 +38,9 @@ pub async fn create_invite_token(pool: &Pool<Sqlite>, user_id: i64) -> Result<St
 
 /// Look up an invite token. Returns the row only when it's still valid
 /// (not used, not expired). Otherwise returns None.
-#[instrument(skip(token))]
+#[instrument(skip(executor, token))]
 pub async fn find_valid_invite_token(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     token: &str,
 ) -> Result<Option<InviteToken>, AppError> {
     let row = sqlx::query!(
@@ -44,7 +49,7 @@ pub async fn find_valid_invite_token(
            FROM invite_tokens WHERE token = ?"#,
         token
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     let row = match row {
@@ -62,20 +67,23 @@ pub async fn find_valid_invite_token(
 
     Ok(Some(InviteToken {
         id: row.id.unwrap_or_default(),
-        user_id: row.user_id,
+        user_id: UserId(row.user_id),
     }))
 }
 
-/// Atomically claim an invite. Sets the user's username and (bcrypt-hashed)
-/// password, marks claimed_at on the user and used_at on the token.
-/// Returns the user id on success. Errors if the username is taken.
+/// Claim an invite. Sets the user's username and (bcrypt-hashed) password,
+/// and their display name if given, marks claimed_at on the user and
+/// used_at on the token. Returns the user id on success. Errors if the
+/// username is taken, or with `NotFound` if the token isn't valid, including
+/// when another claim of it got there first.
 #[instrument(skip(pool, token, password))]
 pub async fn claim_invite(
     pool: &Pool<Sqlite>,
     token: &str,
     username: &str,
     password: &str,
-) -> Result<i64, AppError> {
+    display_name: Option<&str>,
+) -> Result<UserId, AppError> {
     info!("Claiming invite");
 
     let invite = find_valid_invite_token(pool, token)
@@ -85,7 +93,7 @@ pub async fn claim_invite(
     let existing = sqlx::query!(
         "SELECT id FROM users WHERE username = ? AND id != ?",
         username,
-        invite.user_id
+        invite.user_id.0
     )
     .fetch_optional(pool)
     .await?;
@@ -93,38 +101,45 @@ pub async fn claim_invite(
         return Err(AppError::Internal("Username already taken".to_string()));
     }
 
-    let hashed = bcrypt::hash(password, crate::db::BCRYPT_COST)?;
+    let hashed = bcrypt::hash(password, crate::db::bcrypt_cost())?;
     let now = Utc::now().naive_utc();
 
-    // Apply both updates. SQLite single-connection writes are serialized by the
-    // pool, so this is effectively atomic for our purposes.
-    sqlx::query!(
-        "UPDATE users SET username = ?, password = ?, claimed_at = ? WHERE id = ?",
-        username,
-        hashed,
+    let mut tx = pool.begin().await?;
+    // The token first, so of two claims racing for it only one goes on.
+    let res = sqlx::query!(
+        "UPDATE invite_tokens SET used_at = ? WHERE id = ? AND used_at IS NULL",
         now,
-        invite.user_id
+        invite.id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound("Invite token not valid".to_string()));
+    }
 
     sqlx::query!(
-        "UPDATE invite_tokens SET used_at = ? WHERE id = ?",
+        "UPDATE users
+         SET username = ?, password = ?, claimed_at = ?, display_name = COALESCE(?, display_name)
+         WHERE id = ?",
+        username,
+        hashed,
         now,
-        invite.id
+        display_name,
+        invite.user_id.0
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     Ok(invite.user_id)
 }
 
 /// Reset a claimed user back to stub state: clear password, invalidate
-/// existing sessions, return a fresh invite token. Username stays so existing
+/// existing sessions and API tokens, return a fresh invite token. Username stays so existing
 /// references are unaffected, but the user can re-claim with a new password
 /// (and optionally change the username again during claim).
 #[instrument]
-pub async fn reset_user_claim(pool: &Pool<Sqlite>, user_id: i64) -> Result<String, AppError> {
+pub async fn reset_user_claim(pool: &Pool<Sqlite>, user_id: UserId) -> Result<String, AppError> {
     info!("Resetting user claim");
 
     // Also clear any standing password-reset request so it stops showing
@@ -133,13 +148,14 @@ pub async fn reset_user_claim(pool: &Pool<Sqlite>, user_id: i64) -> Result<Strin
         "UPDATE users
          SET password = '', claimed_at = NULL, reset_requested_at = NULL
          WHERE id = ?",
-        user_id
+        user_id.0
     )
     .execute(pool)
     .await?;
 
-    // Invalidate any existing sessions for this user.
-    sqlx::query!("DELETE FROM user_sessions WHERE user_id = ?", user_id)
+    // Invalidate any existing sessions and API tokens for this user.
+    delete_user_sessions(pool, user_id).await?;
+    sqlx::query!("DELETE FROM api_tokens WHERE user_id = ?", user_id.0)
         .execute(pool)
         .await?;
 
@@ -0,0 +1,224 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+use tracing::instrument;
+
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::naive_to_utc;
+
+/// The persisted state of one scheduled job (see `crate::scheduler`).
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub name: String,
+    pub running_since: Option<NaiveDateTime>,
+    pub last_started_at: Option<NaiveDateTime>,
+    pub last_finished_at: Option<NaiveDateTime>,
+    pub last_status: Option<String>,
+    pub last_message: Option<String>,
+}
+
+#[instrument(skip(executor))]
+pub async fn get_job_run(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+) -> Result<Option<JobRun>, AppError> {
+    let run = sqlx::query_as!(
+        JobRun,
+        r#"SELECT name AS "name!", running_since, last_started_at, last_finished_at,
+                  last_status, last_message
+           FROM scheduled_jobs WHERE name = ?"#,
+        name
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(run)
+}
+
+#[instrument(skip(executor))]
+pub async fn get_job_runs(executor: impl SqliteExecutor<'_>) -> Result<Vec<JobRun>, AppError> {
+    let runs = sqlx::query_as!(
+        JobRun,
+        r#"SELECT name AS "name!", running_since, last_started_at, last_finished_at,
+                  last_status, last_message
+           FROM scheduled_jobs ORDER BY name"#
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(runs)
+}
+
+/// Marks `name` as running from `now`, unless a run started after
+/// `stale_before` still holds it. Returns whether this caller got the run.
+#[instrument(skip(executor))]
+pub async fn try_start_job(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+    now: NaiveDateTime,
+    stale_before: NaiveDateTime,
+) -> Result<bool, AppError> {
+    let res = sqlx::query!(
+        "INSERT INTO scheduled_jobs (name, running_since, last_started_at) VALUES (?, ?, ?)
+         ON CONFLICT (name) DO UPDATE SET
+             running_since = excluded.running_since,
+             last_started_at = excluded.last_started_at
+         WHERE scheduled_jobs.running_since IS NULL OR scheduled_jobs.running_since < ?",
+        name,
+        now,
+        now,
+        stale_before
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+#[instrument(skip(executor))]
+pub async fn finish_job(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+    now: NaiveDateTime,
+    status: &str,
+    message: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE scheduled_jobs
+         SET running_since = NULL, last_finished_at = ?, last_status = ?, last_message = ?
+         WHERE name = ?",
+        now,
+        status,
+        message,
+        name
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// A row of `jobs`: work a request handed off to a background task (see
+/// `crate::scheduler::spawn_background_job`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackgroundJob {
+    pub id: i64,
+    pub kind: String,
+    pub requested_by_id: Option<UserId>,
+    /// `queued`, `running`, `succeeded` or `failed`.
+    pub status: String,
+    /// The work's output, once it has succeeded.
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[instrument(skip(executor))]
+pub async fn create_background_job(
+    executor: impl SqliteExecutor<'_>,
+    kind: &str,
+    requested_by: UserId,
+) -> Result<i64, AppError> {
+    let res = sqlx::query!(
+        "INSERT INTO jobs (kind, requested_by_id) VALUES (?, ?)",
+        kind,
+        requested_by.0
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+#[instrument(skip(executor))]
+pub async fn get_background_job(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<BackgroundJob, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", kind, requested_by_id, status, result, error,
+                  created_at AS "created_at: NaiveDateTime",
+                  started_at AS "started_at: NaiveDateTime",
+                  finished_at AS "finished_at: NaiveDateTime"
+           FROM jobs WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Job {} not found", id)))?;
+
+    let result = row
+        .result
+        .map(|result| serde_json::from_str(&result))
+        .transpose()
+        .map_err(|e| AppError::Internal(format!("Job {} has an unreadable result: {}", id, e)))?;
+    Ok(BackgroundJob {
+        id: row.id,
+        kind: row.kind,
+        requested_by_id: row.requested_by_id.map(UserId),
+        status: row.status,
+        result,
+        error: row.error,
+        created_at: naive_to_utc(row.created_at),
+        started_at: row.started_at.map(naive_to_utc),
+        finished_at: row.finished_at.map(naive_to_utc),
+    })
+}
+
+#[instrument(skip(executor))]
+pub async fn start_background_job(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<(), AppError> {
+    let now = Utc::now().naive_utc();
+    sqlx::query!(
+        "UPDATE jobs SET status = 'running', started_at = ? WHERE id = ?",
+        now,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Records how the work ended: its JSON output, or the error that stopped it.
+#[instrument(skip(executor, outcome))]
+pub async fn finish_background_job(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    outcome: Result<&serde_json::Value, &str>,
+) -> Result<(), AppError> {
+    let now = Utc::now().naive_utc();
+    let (status, result, error) = match outcome {
+        Ok(result) => ("succeeded", Some(result.to_string()), None),
+        Err(error) => ("failed", None, Some(error)),
+    };
+    sqlx::query!(
+        "UPDATE jobs SET status = ?, result = ?, error = ?, finished_at = ? WHERE id = ?",
+        status,
+        result,
+        error,
+        now,
+        id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Fails jobs still queued or running that were created before
+/// `stale_before`, on the assumption that the process running them died.
+#[instrument(skip(executor))]
+pub async fn fail_stale_background_jobs(
+    executor: impl SqliteExecutor<'_>,
+    stale_before: NaiveDateTime,
+) -> Result<u64, AppError> {
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE jobs SET status = 'failed', error = 'Stopped responding', finished_at = ?
+         WHERE status IN ('queued', 'running') AND created_at < ?",
+        now,
+        stale_before
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected())
+}
@@ -0,0 +1,211 @@
+//! Training journal entries. Every query is scoped to the author's id, so an
+//! entry that isn't theirs reads as not found whatever the caller's role.
+//! Entries are private unless the author shares them, and even then coaches
+//! can only list them (`get_journal_entries` with `shared_only`).
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
+use crate::models::naive_to_utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub id: i64,
+    pub technique_id: Option<TechniqueId>,
+    /// `None` when the entry isn't about a technique, or it was deleted.
+    pub technique_name: Option<String>,
+    pub entry_date: NaiveDate,
+    pub body: String,
+    /// Readable by coaches as well as the author.
+    pub shared: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What an entry says and what it is about, for create and update.
+#[derive(Debug, Clone)]
+pub struct JournalEntryInput<'a> {
+    pub technique_id: Option<TechniqueId>,
+    pub entry_date: NaiveDate,
+    pub body: &'a str,
+    /// `None` creates a private entry and leaves an existing one as it was.
+    pub shared: Option<bool>,
+}
+
+/// `user_id`'s entries, newest day first, optionally only those about
+/// `technique_id`. `shared_only` leaves out the ones they keep private.
+#[instrument(skip(executor))]
+pub async fn get_journal_entries(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    technique_id: Option<TechniqueId>,
+    shared_only: bool,
+) -> Result<Vec<JournalEntry>, AppError> {
+    let technique_id = technique_id.map(|id| id.0);
+    let rows = sqlx::query!(
+        r#"SELECT j.id AS "id!", j.technique_id, t.name AS "technique_name?: String",
+                  j.entry_date AS "entry_date: NaiveDate", j.body, j.shared AS "shared: bool",
+                  j.created_at AS "created_at: NaiveDateTime",
+                  j.updated_at AS "updated_at: NaiveDateTime"
+           FROM journal_entries j
+           LEFT JOIN techniques t ON t.id = j.technique_id
+           WHERE j.user_id = ? AND (? IS NULL OR j.technique_id = ?)
+             AND (j.shared OR NOT ?)
+           ORDER BY j.entry_date DESC, j.id DESC"#,
+        user_id.0,
+        technique_id,
+        technique_id,
+        shared_only
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| JournalEntry {
+            id: row.id,
+            technique_id: row.technique_id.map(TechniqueId),
+            technique_name: row.technique_name,
+            entry_date: row.entry_date,
+            body: row.body,
+            shared: row.shared,
+            created_at: naive_to_utc(row.created_at),
+            updated_at: naive_to_utc(row.updated_at),
+        })
+        .collect())
+}
+
+/// One of `user_id`'s entries. Anyone else's is reported as not found.
+#[instrument(skip(executor))]
+pub async fn get_journal_entry(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    user_id: UserId,
+) -> Result<JournalEntry, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT j.id AS "id!", j.technique_id, t.name AS "technique_name?: String",
+                  j.entry_date AS "entry_date: NaiveDate", j.body, j.shared AS "shared: bool",
+                  j.created_at AS "created_at: NaiveDateTime",
+                  j.updated_at AS "updated_at: NaiveDateTime"
+           FROM journal_entries j
+           LEFT JOIN techniques t ON t.id = j.technique_id
+           WHERE j.id = ? AND j.user_id = ?"#,
+        id,
+        user_id.0
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Journal entry {} not found", id)))?;
+
+    Ok(JournalEntry {
+        id: row.id,
+        technique_id: row.technique_id.map(TechniqueId),
+        technique_name: row.technique_name,
+        entry_date: row.entry_date,
+        body: row.body,
+        shared: row.shared,
+        created_at: naive_to_utc(row.created_at),
+        updated_at: naive_to_utc(row.updated_at),
+    })
+}
+
+async fn require_technique(
+    pool: &Pool<Sqlite>,
+    technique_id: Option<TechniqueId>,
+) -> Result<(), AppError> {
+    let Some(technique_id) = technique_id else {
+        return Ok(());
+    };
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM techniques WHERE id = ?) AS "exists!: bool""#,
+        technique_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Technique {} not found", technique_id)));
+    }
+    Ok(())
+}
+
+#[instrument(skip(pool, entry))]
+pub async fn create_journal_entry(
+    pool: &Pool<Sqlite>,
+    user_id: UserId,
+    entry: &JournalEntryInput<'_>,
+) -> Result<i64, AppError> {
+    info!("Creating journal entry");
+    require_technique(pool, entry.technique_id).await?;
+    let technique_id = entry.technique_id.map(|id| id.0);
+    let shared = entry.shared.unwrap_or(false);
+    let res = sqlx::query!(
+        "INSERT INTO journal_entries (user_id, technique_id, entry_date, body, shared)
+         VALUES (?, ?, ?, ?, ?)",
+        user_id.0,
+        technique_id,
+        entry.entry_date,
+        entry.body,
+        shared
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+#[instrument(skip(pool, entry))]
+pub async fn update_journal_entry(
+    pool: &Pool<Sqlite>,
+    id: i64,
+    user_id: UserId,
+    entry: &JournalEntryInput<'_>,
+) -> Result<(), AppError> {
+    info!("Updating journal entry");
+    require_technique(pool, entry.technique_id).await?;
+    let technique_id = entry.technique_id.map(|id| id.0);
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE journal_entries
+         SET technique_id = ?, entry_date = ?, body = ?, shared = COALESCE(?, shared),
+             updated_at = ?
+         WHERE id = ? AND user_id = ?",
+        technique_id,
+        entry.entry_date,
+        entry.body,
+        entry.shared,
+        now,
+        id,
+        user_id.0
+    )
+    .execute(pool)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Journal entry {} not found", id)));
+    }
+    Ok(())
+}
+
+#[instrument(skip(executor))]
+pub async fn delete_journal_entry(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    user_id: UserId,
+) -> Result<(), AppError> {
+    info!("Deleting journal entry");
+    let res = sqlx::query!(
+        "DELETE FROM journal_entries WHERE id = ? AND user_id = ?",
+        id,
+        user_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Journal entry {} not found", id)));
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+//! Membership status synced in from outside: the gym's billing system calls
+//! the webhook (`api::api_membership_webhook`) when a payment lands or
+//! lapses, or an admin uploads a CSV export of it. Either way a student is
+//! matched by email (case-insensitive) or, failing that, username. The app
+//! never changes the status itself; coaches only filter on it.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use tracing::{info, instrument};
+
+use super::RowProblem;
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::validation::sanitize_plain_text;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rocket::FromFormField)]
+#[serde(rename_all = "lowercase")]
+pub enum MembershipStatus {
+    Active,
+    Lapsed,
+}
+
+impl MembershipStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MembershipStatus::Active => "active",
+            MembershipStatus::Lapsed => "lapsed",
+        }
+    }
+
+    /// Case-insensitive, since CSV exports rarely agree on capitalisation.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "active" => Some(MembershipStatus::Active),
+            "lapsed" => Some(MembershipStatus::Lapsed),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_db(id: i64, value: Option<String>) -> Result<Option<Self>, AppError> {
+        value
+            .map(|value| {
+                Self::parse(&value).ok_or_else(|| {
+                    AppError::Internal(format!(
+                        "User {} has unknown membership status '{}' in database",
+                        id, value
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Who a status change is for. Email wins when both are given.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MemberRef {
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MembershipOutcome {
+    Updated(UserId),
+    NoMatch,
+    /// More than one student has the email.
+    Ambiguous,
+}
+
+/// Sets the membership status of the one student `member` refers to.
+#[instrument(skip(conn))]
+pub async fn set_membership_status(
+    conn: &mut SqliteConnection,
+    member: &MemberRef,
+    status: MembershipStatus,
+) -> Result<MembershipOutcome, AppError> {
+    let email = member.email.as_deref().map(str::trim).filter(|e| !e.is_empty());
+    let username = member.username.as_deref().map(str::trim).filter(|u| !u.is_empty());
+    let ids: Vec<i64> = match (email, username) {
+        (Some(email), _) => {
+            sqlx::query_scalar!(
+                r#"SELECT id AS "id!" FROM users
+                   WHERE role = 'student' AND lower(email) = lower(?)"#,
+                email
+            )
+            .fetch_all(&mut *conn)
+            .await?
+        }
+        (None, Some(username)) => {
+            sqlx::query_scalar!(
+                r#"SELECT id AS "id!" FROM users WHERE role = 'student' AND username = ?"#,
+                username
+            )
+            .fetch_all(&mut *conn)
+            .await?
+        }
+        (None, None) => Vec::new(),
+    };
+    let id = match ids.as_slice() {
+        [] => return Ok(MembershipOutcome::NoMatch),
+        [id] => *id,
+        _ => return Ok(MembershipOutcome::Ambiguous),
+    };
+
+    let status = status.as_str();
+    let now = chrono::Utc::now().naive_utc();
+    sqlx::query!(
+        "UPDATE users SET membership_status = ?, membership_updated_at = ? WHERE id = ?",
+        status,
+        now,
+        id
+    )
+    .execute(&mut *conn)
+    .await?;
+    info!(user_id = id, status, "Membership status updated");
+    Ok(MembershipOutcome::Updated(UserId(id)))
+}
+
+/// One non-blank data row of a membership CSV, cells trimmed.
+#[derive(Debug)]
+pub struct MembershipRow {
+    /// Line in the CSV file, counting the header as line 1.
+    pub line: u64,
+    pub member: MemberRef,
+    pub status: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MembershipImportReport {
+    pub rows: usize,
+    pub updated: usize,
+    pub problems: Vec<RowProblem>,
+}
+
+/// Reads a CSV with a `Status` column and an `Email` and/or `Username`
+/// column (headers matched case-insensitively). Like `parse_spreadsheet`,
+/// fails only when the file itself is unusable.
+pub fn parse_membership_csv(csv: &str) -> Result<Vec<MembershipRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Could not read the header row: {}", e))?
+        .clone();
+    let find = |wanted: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(wanted));
+    let found = || headers.iter().collect::<Vec<_>>().join(", ");
+    let status = find("status")
+        .ok_or_else(|| format!("No 'Status' column in the header row (found: {})", found()))?;
+    let (email, username) = (find("email"), find("username"));
+    if email.is_none() && username.is_none() {
+        return Err(format!(
+            "No 'Email' or 'Username' column in the header row (found: {})",
+            found()
+        ));
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Could not read the file: {}", e))?;
+        if record.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(|raw| sanitize_plain_text(raw.trim()))
+                .filter(|value| !value.is_empty())
+        };
+        rows.push(MembershipRow {
+            line: record.position().map_or(0, |p| p.line()),
+            member: MemberRef { email: cell(email), username: cell(username) },
+            status: cell(Some(status)).unwrap_or_default(),
+        });
+    }
+    Ok(rows)
+}
+
+/// Applies parsed rows in one transaction, rolled back with `dry_run`.
+/// Rows that don't name exactly one student are reported and skipped.
+#[instrument(skip(pool, rows))]
+pub async fn import_memberships(
+    pool: &Pool<Sqlite>,
+    rows: &[MembershipRow],
+    dry_run: bool,
+) -> Result<MembershipImportReport, AppError> {
+    info!(rows = rows.len(), dry_run, "Importing membership statuses");
+    let mut tx = pool.begin().await?;
+    let mut report = MembershipImportReport::default();
+    for row in rows {
+        report.rows += 1;
+        let message = match MembershipStatus::parse(&row.status) {
+            None => format!("Unknown status '{}'; use active or lapsed", row.status),
+            Some(status) => match set_membership_status(&mut tx, &row.member, status).await? {
+                MembershipOutcome::Updated(_) => {
+                    report.updated += 1;
+                    continue;
+                }
+                MembershipOutcome::NoMatch => "No student matches this row".to_string(),
+                MembershipOutcome::Ambiguous => "More than one student has this email".to_string(),
+            },
+        };
+        report.problems.push(RowProblem { line: row.line, message, skipped: true });
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(report)
+}
@@ -3,26 +3,93 @@
 //! operations belong to the file that owns the outer transaction, with calls
 //! fanning out one-way to leaf modules. Each submodule re-exports its public
 //! names through this `mod.rs` so call sites stay flat (`crate::db::foo`).
+//!
+//! A function that runs a single statement takes `impl SqliteExecutor`, so
+//! the caller picks what it runs on: the pool, a transaction (`&mut *tx`), or
+//! later a read replica. Handlers pass `db.inner()`, since `&State<Pool>`
+//! isn't an executor itself. Functions that run several statements or begin
+//! their own transaction still take `&Pool<Sqlite>`, or `&mut
+//! SqliteConnection` when a caller needs them inside its transaction.
 
+use once_cell::sync::OnceCell;
+
+mod api_tokens;
+mod archive;
+mod attachments;
+mod attendance;
 mod attempts;
+mod badges;
+mod coach_students;
 mod collections;
+mod data_migrations;
+mod feature_flags;
+mod gradings;
+mod idempotency;
+mod identities;
 mod invites;
+mod jobs;
+mod journal;
+mod memberships;
+mod note_revisions;
+mod note_templates;
+mod notifications;
+mod password_attempts;
+mod preferences;
+mod prerequisites;
+mod ranks;
 mod reporting;
+mod schedule;
+mod schema_migrations;
 mod sessions;
+mod spreadsheet;
+mod statuses;
+mod student_technique_revisions;
 mod student_techniques;
 mod tags;
+mod technique_aliases;
+mod technique_media;
+mod technique_search;
 mod techniques;
 mod users;
 mod videos;
 mod watch;
 
+pub use api_tokens::*;
+pub use archive::*;
+pub use attachments::*;
+pub use attendance::*;
 pub use attempts::*;
+pub use badges::*;
+pub use coach_students::*;
 pub use collections::*;
+pub use data_migrations::*;
+pub use feature_flags::*;
+pub use gradings::*;
+pub use idempotency::*;
+pub use identities::*;
 pub use invites::*;
+pub use jobs::*;
+pub use journal::*;
+pub use memberships::*;
+pub use note_revisions::*;
+pub use note_templates::*;
+pub use notifications::*;
+pub use password_attempts::*;
+pub use preferences::*;
+pub use prerequisites::*;
+pub use ranks::*;
 pub use reporting::*;
+pub use schedule::*;
+pub use schema_migrations::*;
 pub use sessions::*;
+pub use spreadsheet::*;
+pub use statuses::*;
+pub use student_technique_revisions::*;
 pub use student_techniques::*;
 pub use tags::*;
+pub use technique_aliases::*;
+pub use technique_media::*;
+pub use technique_search::*;
 pub use techniques::*;
 pub use users::*;
 pub use videos::*;
@@ -37,12 +104,32 @@ pub use crate::models::{
     StudentWatchActivityRow, VideoStatsSnapshot, WatchAggregateRow,
 };
 
-// Production uses bcrypt's default cost (currently 12). Tests use the minimum
-// (4) because each hash at cost 12 takes ~220ms, which dominates test runtime
-// on suites that create users in setup. Cost 4 is ~250x faster. Gated on the
-// `test-support` feature, not `cfg(test)`, because tests live in the binary
-// crate but call into this library crate; `cfg(test)` is not propagated.
+// Production defaults to bcrypt's default cost (currently 12), overridable with
+// `BCRYPT_COST`. Tests use the minimum (4) because each hash at cost 12 takes
+// ~220ms, which dominates test runtime on suites that create users in setup.
+// Cost 4 is ~250x faster. Gated on the `test-support` feature, not
+// `cfg(test)`, because tests live in the binary crate but call into this
+// library crate; `cfg(test)` is not propagated.
 #[cfg(feature = "test-support")]
-pub(crate) const BCRYPT_COST: u32 = 4;
+const DEFAULT_BCRYPT_COST: u32 = 4;
 #[cfg(not(feature = "test-support"))]
-pub(crate) const BCRYPT_COST: u32 = bcrypt::DEFAULT_COST;
+const DEFAULT_BCRYPT_COST: u32 = bcrypt::DEFAULT_COST;
+
+/// bcrypt rejects costs outside this range; the crate does not export the
+/// bounds.
+pub const BCRYPT_COST_RANGE: std::ops::RangeInclusive<u32> = 4..=31;
+
+static BCRYPT_COST: OnceCell<u32> = OnceCell::new();
+
+/// Cost for every new password hash. Existing hashes carry their own cost, so
+/// changing it only affects passwords set afterwards.
+pub fn bcrypt_cost() -> u32 {
+    BCRYPT_COST.get().copied().unwrap_or(DEFAULT_BCRYPT_COST)
+}
+
+/// Fixes the hashing cost for the life of the process. The value is checked
+/// against `BCRYPT_COST_RANGE` when `AppConfig` is loaded; later calls are
+/// ignored.
+pub fn init_bcrypt_cost(cost: u32) {
+    let _ = BCRYPT_COST.set(cost);
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor};
+use tracing::instrument;
+
+use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, UserId};
+use crate::models::naive_to_utc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteField {
+    StudentNotes,
+    CoachNotes,
+}
+
+impl NoteField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoteField::StudentNotes => "student_notes",
+            NoteField::CoachNotes => "coach_notes",
+        }
+    }
+
+    fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "student_notes" => Ok(NoteField::StudentNotes),
+            "coach_notes" => Ok(NoteField::CoachNotes),
+            other => Err(AppError::Internal(format!("Unknown note field '{}'", other))),
+        }
+    }
+}
+
+/// Text that was in `field` until `replaced_by` overwrote it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoteRevision {
+    pub id: i64,
+    pub field: NoteField,
+    pub content: String,
+    pub replaced_at: DateTime<Utc>,
+    /// `None` once that user has been deleted.
+    pub replaced_by_id: Option<UserId>,
+    pub replaced_by_name: Option<String>,
+}
+
+/// Keeps the current text of each field about to change. Call in the same
+/// transaction as the update, before it. A `None` field isn't being
+/// written; empty text and unchanged text aren't worth keeping.
+#[instrument(skip(conn, student_notes, coach_notes))]
+pub async fn record_note_revisions(
+    conn: &mut SqliteConnection,
+    id: StudentTechniqueId,
+    actor_id: UserId,
+    now: NaiveDateTime,
+    student_notes: Option<&str>,
+    coach_notes: Option<&str>,
+) -> Result<(), AppError> {
+    if let Some(next) = student_notes {
+        sqlx::query!(
+            "INSERT INTO note_revisions
+                 (student_technique_id, field, content, replaced_at, replaced_by_id)
+             SELECT id, 'student_notes', student_notes, ?, ?
+             FROM student_techniques
+             WHERE id = ? AND COALESCE(student_notes, '') NOT IN ('', ?)",
+            now,
+            actor_id.0,
+            id.0,
+            next
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if let Some(next) = coach_notes {
+        sqlx::query!(
+            "INSERT INTO note_revisions
+                 (student_technique_id, field, content, replaced_at, replaced_by_id)
+             SELECT id, 'coach_notes', coach_notes, ?, ?
+             FROM student_techniques
+             WHERE id = ? AND COALESCE(coach_notes, '') NOT IN ('', ?)",
+            now,
+            actor_id.0,
+            id.0,
+            next
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Newest first.
+#[instrument(skip(executor))]
+pub async fn get_note_revisions(
+    executor: impl SqliteExecutor<'_>,
+    id: StudentTechniqueId,
+) -> Result<Vec<NoteRevision>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT r.id AS "id!", r.field, r.content,
+                  r.replaced_at AS "replaced_at: NaiveDateTime",
+                  r.replaced_by_id,
+                  COALESCE(NULLIF(u.display_name, ''), u.username) AS "replaced_by_name?: String"
+           FROM note_revisions r
+           LEFT JOIN users u ON u.id = r.replaced_by_id
+           WHERE r.student_technique_id = ?
+           ORDER BY r.replaced_at DESC, r.id DESC"#,
+        id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(NoteRevision {
+                id: row.id,
+                field: NoteField::from_db(&row.field)?,
+                content: row.content,
+                replaced_at: naive_to_utc(row.replaced_at),
+                replaced_by_id: row.replaced_by_id.map(UserId),
+                replaced_by_name: row.replaced_by_name,
+            })
+        })
+        .collect()
+}
+
+/// The field and text of one revision, if it belongs to `id`.
+#[instrument(skip(executor))]
+pub async fn get_note_revision(
+    executor: impl SqliteExecutor<'_>,
+    id: StudentTechniqueId,
+    revision_id: i64,
+) -> Result<(NoteField, String), AppError> {
+    let row = sqlx::query!(
+        "SELECT field, content FROM note_revisions WHERE id = ? AND student_technique_id = ?",
+        revision_id,
+        id.0
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Note revision {} not found", revision_id)))?;
+    Ok((NoteField::from_db(&row.field)?, row.content))
+}
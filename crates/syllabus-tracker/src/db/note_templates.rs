@@ -0,0 +1,133 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::naive_to_utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoteTemplate {
+    pub id: i64,
+    pub name: String,
+    pub body: String,
+    /// The coach who keeps it, or `None` for a template the whole gym shares.
+    pub owner_id: Option<UserId>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Shared templates first, then `user_id`'s own, each by name.
+#[instrument(skip(executor))]
+pub async fn get_note_templates(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<Vec<NoteTemplate>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", name, body, owner_id,
+                  updated_at AS "updated_at: NaiveDateTime"
+           FROM note_templates
+           WHERE owner_id IS NULL OR owner_id = ?
+           ORDER BY owner_id IS NOT NULL, name COLLATE NOCASE, id"#,
+        user_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| NoteTemplate {
+            id: row.id,
+            name: row.name,
+            body: row.body,
+            owner_id: row.owner_id.map(UserId),
+            updated_at: naive_to_utc(row.updated_at),
+        })
+        .collect())
+}
+
+/// A template `user_id` can see: a shared one or their own. Anyone else's
+/// is reported as not found.
+#[instrument(skip(executor))]
+pub async fn get_note_template(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    user_id: UserId,
+) -> Result<NoteTemplate, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", name, body, owner_id,
+                  updated_at AS "updated_at: NaiveDateTime"
+           FROM note_templates
+           WHERE id = ? AND (owner_id IS NULL OR owner_id = ?)"#,
+        id,
+        user_id.0
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Note template {} not found", id)))?;
+
+    Ok(NoteTemplate {
+        id: row.id,
+        name: row.name,
+        body: row.body,
+        owner_id: row.owner_id.map(UserId),
+        updated_at: naive_to_utc(row.updated_at),
+    })
+}
+
+#[instrument(skip(executor, body))]
+pub async fn create_note_template(
+    executor: impl SqliteExecutor<'_>,
+    owner_id: Option<UserId>,
+    name: &str,
+    body: &str,
+) -> Result<i64, AppError> {
+    info!("Creating note template");
+    let owner_id = owner_id.map(|id| id.0);
+    let res = sqlx::query!(
+        "INSERT INTO note_templates (owner_id, name, body) VALUES (?, ?, ?)",
+        owner_id,
+        name,
+        body
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+#[instrument(skip(executor, body))]
+pub async fn update_note_template(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    name: &str,
+    body: &str,
+) -> Result<(), AppError> {
+    info!("Updating note template");
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE note_templates SET name = ?, body = ?, updated_at = ? WHERE id = ?",
+        name,
+        body,
+        now,
+        id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Note template {} not found", id)));
+    }
+    Ok(())
+}
+
+#[instrument(skip(executor))]
+pub async fn delete_note_template(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<(), AppError> {
+    info!("Deleting note template");
+    sqlx::query!("DELETE FROM note_templates WHERE id = ?", id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
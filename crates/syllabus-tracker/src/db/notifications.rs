@@ -0,0 +1,151 @@
+//! In-app notifications: a row per thing a user should know happened,
+//! written alongside the change that caused it and kept until the user is
+//! deleted. Emails are separate and follow `NotificationPreferences`.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use super::NotificationKind;
+use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, UserId};
+use crate::models::{display_name_or_username, naive_to_utc};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Notification {
+    pub id: i64,
+    pub kind: NotificationKind,
+    pub student_technique_id: Option<StudentTechniqueId>,
+    pub technique_name: Option<String>,
+    /// Who made the change; `None` once they are deleted.
+    pub actor_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// `None` until the user marks it read.
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// Tells the student a technique of theirs was assigned or had its coach
+/// notes changed. Nothing is written when they made the change themselves,
+/// when the coach notes are private, or when an unread notification already
+/// says the same thing.
+#[instrument(skip(executor))]
+pub async fn notify_student(
+    executor: impl SqliteExecutor<'_>,
+    kind: NotificationKind,
+    student_technique_id: StudentTechniqueId,
+    actor_id: UserId,
+) -> Result<(), AppError> {
+    let kind = kind.as_str();
+    let res = sqlx::query!(
+        "INSERT INTO notifications (user_id, kind, student_technique_id, actor_id)
+         SELECT st.student_id, ?1, st.id, ?2
+         FROM student_techniques st
+         WHERE st.id = ?3 AND st.student_id != ?2
+           AND NOT (?1 = 'coach_note' AND st.coach_notes_private)
+           AND NOT EXISTS (SELECT 1 FROM notifications n
+                           WHERE n.user_id = st.student_id AND n.kind = ?1
+                             AND n.student_technique_id = st.id AND n.read_at IS NULL)",
+        kind,
+        actor_id.0,
+        student_technique_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() > 0 {
+        info!(kind, "Notification created");
+    }
+    Ok(())
+}
+
+/// A page of `user_id`'s notifications, newest first, and how many they
+/// have in all.
+#[instrument(skip(pool))]
+pub async fn get_notifications(
+    pool: &Pool<Sqlite>,
+    user_id: UserId,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<Notification>, i64), AppError> {
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64" FROM notifications WHERE user_id = ?"#,
+        user_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let rows = sqlx::query!(
+        r#"SELECT n.id AS "id!", n.kind, n.student_technique_id,
+                  st.technique_name AS "technique_name?: String",
+                  a.display_name AS "actor_display_name?: String",
+                  a.username AS "actor_username?: String",
+                  n.created_at AS "created_at: NaiveDateTime",
+                  n.read_at AS "read_at: NaiveDateTime"
+           FROM notifications n
+           LEFT JOIN student_techniques st ON st.id = n.student_technique_id
+           LEFT JOIN users a ON a.id = n.actor_id
+           WHERE n.user_id = ?
+           ORDER BY n.created_at DESC, n.id DESC
+           LIMIT ? OFFSET ?"#,
+        user_id.0,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let notifications = rows
+        .into_iter()
+        .map(|row| {
+            Ok(Notification {
+                id: row.id,
+                kind: NotificationKind::from_db(&row.kind)?,
+                student_technique_id: row.student_technique_id.map(StudentTechniqueId),
+                technique_name: row.technique_name,
+                actor_name: display_name_or_username(row.actor_display_name, row.actor_username),
+                created_at: naive_to_utc(row.created_at),
+                read_at: row.read_at.map(naive_to_utc),
+            })
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    Ok((notifications, total))
+}
+
+#[instrument(skip(executor))]
+pub async fn count_unread_notifications(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<i64, AppError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64" FROM notifications
+           WHERE user_id = ? AND read_at IS NULL"#,
+        user_id.0
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Marks one of `user_id`'s notifications read. Someone else's reads as not
+/// found; one already read keeps its first `read_at`.
+#[instrument(skip(executor))]
+pub async fn mark_notification_read(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    user_id: UserId,
+) -> Result<(), AppError> {
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE notifications SET read_at = COALESCE(read_at, ?) WHERE id = ? AND user_id = ?",
+        now,
+        id,
+        user_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Notification {} not found", id)));
+    }
+    Ok(())
+}
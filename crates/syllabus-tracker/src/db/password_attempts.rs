@@ -0,0 +1,83 @@
+use chrono::NaiveDateTime;
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::instrument;
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// Failed change-password attempts by `user_id` at or after `since` and
+/// after their last successful one.
+#[instrument(skip(executor))]
+pub async fn count_recent_password_failures(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    since: NaiveDateTime,
+) -> Result<i64, AppError> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64"
+           FROM password_change_attempts
+           WHERE user_id = ? AND succeeded = FALSE AND attempted_at >= ?
+             AND attempted_at > COALESCE(
+                 (SELECT MAX(attempted_at) FROM password_change_attempts
+                  WHERE user_id = ? AND succeeded = TRUE),
+                 '')"#,
+        user_id.0,
+        since,
+        user_id.0
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(count)
+}
+
+/// Records a change-password attempt by `user_id` before the current password
+/// is checked, unless they already have `limit` failures since `since`.
+///
+/// The attempt is stored as failed and counted in the same write transaction,
+/// so concurrent requests can't all read a count under the limit and then
+/// try a password each. Returns the failures before this attempt, and the
+/// new attempt's id when it may go ahead; pass that to
+/// [`mark_password_attempt_succeeded`] if the password turns out right.
+#[instrument(skip(pool))]
+pub async fn begin_password_attempt(
+    pool: &Pool<Sqlite>,
+    user_id: UserId,
+    since: NaiveDateTime,
+    limit: i64,
+    client_ip: Option<&str>,
+) -> Result<(i64, Option<i64>), AppError> {
+    let mut tx = pool.begin().await?;
+    let now = chrono::Utc::now().naive_utc();
+    let id = sqlx::query!(
+        "INSERT INTO password_change_attempts (user_id, attempted_at, succeeded, client_ip)
+         VALUES (?, ?, FALSE, ?)",
+        user_id.0,
+        now,
+        client_ip
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+    let failures = count_recent_password_failures(&mut *tx, user_id, since).await? - 1;
+    if failures >= limit {
+        // Refused attempts never reach the password check, so they aren't kept.
+        tx.rollback().await?;
+        return Ok((failures, None));
+    }
+    tx.commit().await?;
+    Ok((failures, Some(id)))
+}
+
+#[instrument(skip(executor))]
+pub async fn mark_password_attempt_succeeded(
+    executor: impl SqliteExecutor<'_>,
+    attempt_id: i64,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE password_change_attempts SET succeeded = TRUE WHERE id = ?",
+        attempt_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::SqliteExecutor;
+use tracing::{error, info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// The stored object, or an empty one for users who have never saved any.
+#[instrument(skip(executor))]
+pub async fn get_user_preferences(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<Map<String, Value>, AppError> {
+    let row = sqlx::query!(
+        "SELECT preferences FROM user_preferences WHERE user_id = ?",
+        user_id.0
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(Map::new());
+    };
+
+    serde_json::from_str(&row.preferences).map_err(|e| {
+        error!(error = %e, "Stored preferences are not a JSON object");
+        AppError::Internal(format!("Corrupt preferences for user {}", user_id.0))
+    })
+}
+
+/// Replaces the whole object; clients merge before saving.
+#[instrument(skip(executor, preferences))]
+pub async fn set_user_preferences(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    preferences: &Map<String, Value>,
+) -> Result<(), AppError> {
+    info!("Saving user preferences");
+    let json = serde_json::to_string(preferences)
+        .map_err(|e| AppError::Internal(format!("Failed to encode preferences: {}", e)))?;
+
+    sqlx::query!(
+        "INSERT INTO user_preferences (user_id, preferences, updated_at)
+         VALUES (?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT (user_id) DO UPDATE SET
+             preferences = excluded.preferences,
+             updated_at = excluded.updated_at",
+        user_id.0,
+        json
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DigestFrequency::Off => "off",
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "off" => Ok(DigestFrequency::Off),
+            "daily" => Ok(DigestFrequency::Daily),
+            "weekly" => Ok(DigestFrequency::Weekly),
+            other => Err(AppError::Internal(format!("Unknown digest frequency '{}'", other))),
+        }
+    }
+}
+
+/// Something the app might tell a user about, by email or in the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A technique or curriculum was assigned to them.
+    Assignment,
+    /// A coach wrote or changed notes on one of their techniques.
+    CoachNote,
+    /// The periodic summary; `digest` says how often.
+    Digest,
+}
+
+impl NotificationKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::Assignment => "assignment",
+            NotificationKind::CoachNote => "coach_note",
+            NotificationKind::Digest => "digest",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "assignment" => Ok(NotificationKind::Assignment),
+            "coach_note" => Ok(NotificationKind::CoachNote),
+            "digest" => Ok(NotificationKind::Digest),
+            other => Err(AppError::Internal(format!("Unknown notification kind '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    pub email_on_assignment: bool,
+    pub email_on_coach_note: bool,
+    pub digest: DigestFrequency,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self { email_on_assignment: true, email_on_coach_note: true, digest: DigestFrequency::Off }
+    }
+}
+
+impl NotificationPreferences {
+    /// Whether the user wants email about `kind`. Anything that sends mail
+    /// checks this first.
+    pub fn wants_email(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::Assignment => self.email_on_assignment,
+            NotificationKind::CoachNote => self.email_on_coach_note,
+            NotificationKind::Digest => self.digest != DigestFrequency::Off,
+        }
+    }
+}
+
+/// The saved preferences, or the defaults for users who have never saved.
+#[instrument(skip(executor))]
+pub async fn get_notification_preferences(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<NotificationPreferences, AppError> {
+    let row = sqlx::query!(
+        "SELECT email_on_assignment, email_on_coach_note, digest_frequency
+         FROM notification_preferences WHERE user_id = ?",
+        user_id.0
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(NotificationPreferences::default());
+    };
+    Ok(NotificationPreferences {
+        email_on_assignment: row.email_on_assignment,
+        email_on_coach_note: row.email_on_coach_note,
+        digest: DigestFrequency::from_db(&row.digest_frequency)?,
+    })
+}
+
+#[instrument(skip(executor))]
+pub async fn set_notification_preferences(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    preferences: &NotificationPreferences,
+) -> Result<(), AppError> {
+    info!("Saving notification preferences");
+    let digest = preferences.digest.as_str();
+    sqlx::query!(
+        "INSERT INTO notification_preferences
+             (user_id, email_on_assignment, email_on_coach_note, digest_frequency, updated_at)
+         VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT (user_id) DO UPDATE SET
+             email_on_assignment = excluded.email_on_assignment,
+             email_on_coach_note = excluded.email_on_coach_note,
+             digest_frequency = excluded.digest_frequency,
+             updated_at = excluded.updated_at",
+        user_id.0,
+        preferences.email_on_assignment,
+        preferences.email_on_coach_note,
+        digest
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Technique prerequisites: which techniques a student should have reached
+//! green on before a coach assigns another. The links form a directed graph
+//! with no cycles.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
+
+/// The status a prerequisite counts as met at, along with anything above
+/// it on the scale. If the scale has no such status, only its top counts.
+pub const PREREQUISITE_STATUS: &str = "green";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Prerequisite {
+    pub technique_id: TechniqueId,
+    pub technique_name: String,
+}
+
+/// The direct prerequisites of `technique_id`, by name. Deleted techniques
+/// are left out.
+#[instrument(skip(executor))]
+pub async fn get_prerequisites(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
+) -> Result<Vec<Prerequisite>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT t.id AS "id!", t.name
+           FROM technique_prerequisites p
+           JOIN techniques t ON t.id = p.prerequisite_id
+           WHERE p.technique_id = ? AND t.deleted_at IS NULL
+           ORDER BY t.name COLLATE NOCASE, t.id"#,
+        technique_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Prerequisite { technique_id: TechniqueId(row.id), technique_name: row.name })
+        .collect())
+}
+
+/// Makes `prerequisite_id` a prerequisite of `technique_id`. Returns false,
+/// adding nothing, if that would make a technique its own prerequisite,
+/// directly or through others.
+#[instrument(skip(pool))]
+pub async fn add_prerequisite(
+    pool: &Pool<Sqlite>,
+    technique_id: TechniqueId,
+    prerequisite_id: TechniqueId,
+) -> Result<bool, AppError> {
+    info!("Adding technique prerequisite");
+    let mut tx = pool.begin().await?;
+
+    for id in [technique_id, prerequisite_id] {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM techniques WHERE id = ? AND deleted_at IS NULL)
+                   AS "exists!: bool""#,
+            id.0
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !exists {
+            return Err(AppError::NotFound(format!("Technique {} not found", id)));
+        }
+    }
+
+    // A cycle would close if `technique_id` is already required, at any
+    // depth, before `prerequisite_id`.
+    let cycle = sqlx::query_scalar!(
+        r#"WITH RECURSIVE required(id) AS (
+               SELECT ?
+               UNION SELECT p.prerequisite_id
+                     FROM technique_prerequisites p JOIN required ON p.technique_id = required.id)
+           SELECT EXISTS(SELECT 1 FROM required WHERE id = ?) AS "cycle!: bool""#,
+        prerequisite_id.0,
+        technique_id.0
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if cycle {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "INSERT OR IGNORE INTO technique_prerequisites (technique_id, prerequisite_id)
+         VALUES (?, ?)",
+        technique_id.0,
+        prerequisite_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[instrument(skip(executor))]
+pub async fn remove_prerequisite(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
+    prerequisite_id: TechniqueId,
+) -> Result<(), AppError> {
+    info!("Removing technique prerequisite");
+    let res = sqlx::query!(
+        "DELETE FROM technique_prerequisites WHERE technique_id = ? AND prerequisite_id = ?",
+        technique_id.0,
+        prerequisite_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "Technique {} is not a prerequisite of {}",
+            prerequisite_id, technique_id
+        )));
+    }
+    Ok(())
+}
+
+/// The direct prerequisites of `technique_id` that `student_id` hasn't
+/// reached [`PREREQUISITE_STATUS`] on, including ones not assigned to them.
+#[instrument(skip(executor))]
+pub async fn get_unmet_prerequisites(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+    technique_id: TechniqueId,
+) -> Result<Vec<Prerequisite>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT t.id AS "id!", t.name
+           FROM technique_prerequisites p
+           JOIN techniques t ON t.id = p.prerequisite_id
+           WHERE p.technique_id = ? AND t.deleted_at IS NULL
+             AND NOT EXISTS (
+                 SELECT 1
+                 FROM student_techniques st
+                 JOIN statuses s ON s.name = COALESCE(st.status, 'red')
+                 WHERE st.student_id = ? AND st.technique_id = p.prerequisite_id
+                   AND s.position >= COALESCE(
+                       (SELECT position FROM statuses WHERE name = ?),
+                       (SELECT MAX(position) FROM statuses)))
+           ORDER BY t.name COLLATE NOCASE, t.id"#,
+        technique_id.0,
+        student_id.0,
+        PREREQUISITE_STATUS
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Prerequisite { technique_id: TechniqueId(row.id), technique_name: row.name })
+        .collect())
+}
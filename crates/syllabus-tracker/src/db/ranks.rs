@@ -0,0 +1,251 @@
+//! Belt (rank) requirements: each rank asks for a number of techniques with
+//! a tag at a status or better, e.g. "green on 10 techniques tagged 'blue
+//! belt'". Coaches record the rank a student holds; eligibility is worked
+//! out on demand against the next rank up, for review before a grading.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use super::statuses::{StatusLevel, get_statuses};
+use crate::error::AppError;
+use crate::ids::{TagId, UserId};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RankRequirement {
+    pub tag_id: TagId,
+    pub tag_name: String,
+    pub min_count: i64,
+    /// Techniques at this status or better count.
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Rank {
+    pub name: String,
+    pub requirements: Vec<RankRequirement>,
+}
+
+/// A rank as submitted, before tag names are looked up.
+#[derive(Debug, Clone)]
+pub struct RankDefinition {
+    pub name: String,
+    /// `(tag, min_count, status)`.
+    pub requirements: Vec<(TagId, i64, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequirementProgress {
+    #[serde(flatten)]
+    pub requirement: RankRequirement,
+    pub count: i64,
+    pub met: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RankEligibility {
+    pub student_id: UserId,
+    pub student_name: String,
+    pub current_rank: Option<String>,
+    /// `None` once the student holds the top rank.
+    pub next_rank: Option<String>,
+    pub requirements: Vec<RequirementProgress>,
+    /// Every requirement of `next_rank` is met.
+    pub eligible: bool,
+}
+
+/// `status` and the statuses positioned above it on `scale`. A status not
+/// on the scale only counts as itself.
+fn statuses_at_least<'a>(scale: &'a [StatusLevel], status: &str) -> Vec<&'a str> {
+    scale
+        .iter()
+        .position(|level| level.name == status)
+        .map(|i| scale[i..].iter().map(|level| level.name.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Lowest rank first.
+#[instrument(skip(executor))]
+pub async fn get_ranks(executor: impl SqliteExecutor<'_>) -> Result<Vec<Rank>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT r.name, q.tag_id AS "tag_id?: i64", t.name AS "tag_name?: String",
+                  q.min_count AS "min_count?: i64", q.status AS "status?: String"
+           FROM ranks r
+           LEFT JOIN rank_requirements q ON q.rank_id = r.id
+           LEFT JOIN tags t ON t.id = q.tag_id
+           ORDER BY r.position, r.id, t.name COLLATE NOCASE"#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut ranks: Vec<Rank> = Vec::new();
+    for row in rows {
+        if ranks.last().is_none_or(|rank| rank.name != row.name) {
+            ranks.push(Rank { name: row.name, requirements: Vec::new() });
+        }
+        if let (Some(rank), Some(tag_id), Some(tag_name), Some(min_count), Some(status)) =
+            (ranks.last_mut(), row.tag_id, row.tag_name, row.min_count, row.status)
+        {
+            rank.requirements.push(RankRequirement {
+                tag_id: TagId(tag_id),
+                tag_name,
+                min_count,
+                status,
+            });
+        }
+    }
+    Ok(ranks)
+}
+
+/// Replaces the rank ladder in one transaction, lowest rank first. Students
+/// keep the rank name they hold even if it is no longer defined.
+#[instrument(skip(pool, ranks))]
+pub async fn replace_ranks(pool: &Pool<Sqlite>, ranks: &[RankDefinition]) -> Result<(), AppError> {
+    info!(count = ranks.len(), "Replacing ranks");
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM ranks").execute(&mut *tx).await?;
+    for (position, rank) in ranks.iter().enumerate() {
+        let position = position as i64;
+        let rank_id = sqlx::query!(
+            "INSERT INTO ranks (name, position) VALUES (?, ?)",
+            rank.name,
+            position
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        for (tag_id, min_count, status) in &rank.requirements {
+            sqlx::query!(
+                "INSERT OR REPLACE INTO rank_requirements (rank_id, tag_id, min_count, status)
+                 VALUES (?, ?, ?, ?)",
+                rank_id,
+                tag_id.0,
+                min_count,
+                status
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Records the rank a student holds; `None` clears it.
+#[instrument(skip(pool))]
+pub async fn set_student_rank(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+    rank: Option<&str>,
+    awarded_by: UserId,
+) -> Result<(), AppError> {
+    info!("Setting student rank");
+    match rank {
+        Some(rank) => {
+            let now = chrono::Utc::now().naive_utc();
+            sqlx::query!(
+                "INSERT INTO student_ranks (user_id, rank, awarded_at, awarded_by_id)
+                 VALUES (?, ?, ?, ?)
+                 ON CONFLICT (user_id) DO UPDATE SET
+                     rank = excluded.rank,
+                     awarded_at = excluded.awarded_at,
+                     awarded_by_id = excluded.awarded_by_id",
+                student_id.0,
+                rank,
+                now,
+                awarded_by.0
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!("DELETE FROM student_ranks WHERE user_id = ?", student_id.0)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Progress of every active student toward the rank above the one they
+/// hold (the first rank when they hold none, or one no longer defined).
+/// Ordered by student name.
+#[instrument(skip(pool))]
+pub async fn get_rank_eligibility(pool: &Pool<Sqlite>) -> Result<Vec<RankEligibility>, AppError> {
+    let ranks = get_ranks(pool).await?;
+    let scale = get_statuses(pool).await?;
+
+    let students = sqlx::query!(
+        r#"SELECT u.id AS "id!",
+                  COALESCE(NULLIF(u.display_name, ''), u.username) AS "name!: String",
+                  sr.rank AS "rank?: String"
+           FROM users u
+           LEFT JOIN student_ranks sr ON sr.user_id = u.id
+           WHERE u.role = 'student' AND u.archived = FALSE
+           ORDER BY 2 COLLATE NOCASE, u.id"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut counts: HashMap<(i64, i64), Vec<(String, i64)>> = HashMap::new();
+    let rows = sqlx::query!(
+        r#"SELECT st.student_id AS "student_id!", tt.tag_id,
+                  COALESCE(st.status, 'red') AS "status!: String",
+                  COUNT(DISTINCT st.technique_id) AS "count!: i64"
+           FROM student_techniques st
+           JOIN technique_tags tt ON tt.technique_id = st.technique_id
+           WHERE st.student_id IS NOT NULL
+             AND tt.tag_id IN (SELECT tag_id FROM rank_requirements)
+           GROUP BY st.student_id, tt.tag_id, 3"#
+    )
+    .fetch_all(pool)
+    .await?;
+    for row in rows {
+        counts.entry((row.student_id, row.tag_id)).or_default().push((row.status, row.count));
+    }
+
+    Ok(students
+        .into_iter()
+        .map(|student| {
+            let held = student
+                .rank
+                .as_deref()
+                .and_then(|name| ranks.iter().position(|rank| rank.name == name));
+            let next = ranks.get(held.map_or(0, |i| i + 1));
+            let requirements: Vec<RequirementProgress> = next
+                .map(|rank| rank.requirements.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(|requirement| {
+                    let at_least = statuses_at_least(&scale, &requirement.status);
+                    let count = counts
+                        .get(&(student.id, requirement.tag_id.0))
+                        .map_or(&[][..], Vec::as_slice)
+                        .iter()
+                        .filter(|(status, _)| {
+                            *status == requirement.status || at_least.contains(&status.as_str())
+                        })
+                        .map(|(_, count)| count)
+                        .sum();
+                    RequirementProgress {
+                        requirement: requirement.clone(),
+                        count,
+                        met: count >= requirement.min_count,
+                    }
+                })
+                .collect();
+            RankEligibility {
+                student_id: UserId(student.id),
+                student_name: student.name,
+                current_rank: student.rank,
+                next_rank: next.map(|rank| rank.name.clone()),
+                eligible: next.is_some() && requirements.iter().all(|r| r.met),
+                requirements,
+            }
+        })
+        .collect())
+}
@@ -9,17 +9,19 @@
 //! - Cross-domain joins. If a query touches only one domain, push it back
 //!   into that domain's file.
 
-use std::str::FromStr;
-
-use chrono::{DateTime, NaiveDateTime, Utc};
-use sqlx::{Pool, Sqlite};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::instrument;
 
-use crate::auth::{Role, User};
+use super::MembershipStatus;
+use crate::auth::{User, role_from_db};
 use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
 use crate::models::{
-    DashboardVideoOverview, DashboardVideoRow, StorageObjectRow, StorageOverview,
-    StudentWatchActivityRow, VideoStatsSnapshot, naive_to_utc,
+    CoachActivity, DashboardVideoOverview, DashboardVideoRow, GroupProgress, PublicCurriculum,
+    PublicSyllabus, PublicTechnique, StatusCount, StorageObjectRow, StorageOverview,
+    StudentProgress, StudentWatchActivityRow, TagStatusBreakdown, VideoStatsSnapshot,
+    naive_to_rfc3339, naive_to_utc, required,
 };
 
 #[derive(sqlx::FromRow)]
@@ -43,16 +45,20 @@ struct UserWithActivityDto {
     pub amber_count: Option<i64>,
     pub green_count: Option<i64>,
     pub has_unseen_activity: Option<i64>,
+    pub review_requests: Option<i64>,
     pub latest_student_note_at: Option<NaiveDateTime>,
     pub latest_watch_at: Option<NaiveDateTime>,
     pub latest_watch_video_title: Option<String>,
+    pub timezone: Option<String>,
+    pub membership_status: Option<String>,
+    pub avatar_attachment_id: Option<i64>,
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn get_students_by_recent_updates(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     include_archived: bool,
-    viewer_id: i64,
+    viewer_id: UserId,
 ) -> Result<Vec<User>, AppError> {
     // Aggregate flag: does this student have any student_technique where the
     // student has touched it since the viewing coach last looked? `stv.seen_at`
@@ -80,21 +86,15 @@ pub async fn get_students_by_recent_updates(
             COALESCE(SUM(CASE WHEN st.status = 'red'   THEN 1 ELSE 0 END), 0) as "red_count?: i64",
             COALESCE(SUM(CASE WHEN st.status = 'amber' THEN 1 ELSE 0 END), 0) as "amber_count?: i64",
             COALESCE(SUM(CASE WHEN st.status = 'green' THEN 1 ELSE 0 END), 0) as "green_count?: i64",
-            -- `datetime(...)` wrapping defends against legacy rows where
-            -- `last_student_update_at` was written as RFC3339 with offset
-            -- (`2026-05-31T10:00:00+00:00`) while `seen_at` was written naive
-            -- (`2026-05-31 10:00:00`). Raw TEXT comparison would treat the
-            -- legacy format as always greater (because 'T' > ' '), producing
-            -- a stuck-on unseen dot. Remove once legacy timestamps are
-            -- migrated, see TODO.md.
             COALESCE(MAX(
                 CASE
                     WHEN st.last_student_update_at IS NULL THEN 0
                     WHEN stv.seen_at IS NULL THEN 1
-                    WHEN datetime(st.last_student_update_at) > datetime(stv.seen_at) THEN 1
+                    WHEN st.last_student_update_at > stv.seen_at THEN 1
                     ELSE 0
                 END
             ), 0) as "has_unseen_activity?: i64",
+            COUNT(st.review_requested_at) as "review_requests?: i64",
             MAX(st.last_student_update_at) as "latest_student_note_at?: NaiveDateTime",
             (SELECT MAX(last_watched_at)
                FROM video_watch_aggregates
@@ -104,18 +104,21 @@ pub async fn get_students_by_recent_updates(
                JOIN videos v ON v.id = a.video_id
               WHERE a.user_id = u.id AND v.deleted_at IS NULL
               ORDER BY a.last_watched_at DESC
-              LIMIT 1) as "latest_watch_video_title?: String"
+              LIMIT 1) as "latest_watch_video_title?: String",
+            u.timezone,
+            u.membership_status,
+            u.avatar_attachment_id
         FROM users u
         LEFT JOIN student_techniques st ON u.id = st.student_id
         LEFT JOIN student_technique_views stv
                ON stv.student_technique_id = st.id AND stv.user_id = ?
         WHERE u.role = 'student'
         GROUP BY u.id
-        ORDER BY MAX(st.updated_at) DESC NULLS LAST
+        ORDER BY MAX(st.updated_at) DESC NULLS LAST, u.id
         "#,
-        viewer_id
+        viewer_id.0
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     let users: Vec<User> = dtos
@@ -131,38 +134,43 @@ pub async fn get_students_by_recent_updates(
                 (None, Some(b)) => Some(b),
                 (None, None) => None,
             };
-            User {
-                id: dto.id.unwrap_or_default(),
+            let id = required(dto.id, "users.id")?;
+            Ok(User {
+                id: UserId(id),
                 username: dto.username.unwrap_or_default(),
-                role: Role::from_str(&dto.role.unwrap_or_default()).unwrap(),
+                role: role_from_db(id, dto.role)?,
                 display_name: dto.display_name.unwrap_or_default(),
-                archived: dto.archived.unwrap_or_default(),
-                graduated_at: dto.graduated_at.map(|dt| naive_to_utc(dt).to_rfc3339()),
+                archived: required(dto.archived, "users.archived")?,
+                graduated_at: dto.graduated_at.map(naive_to_rfc3339),
                 email: dto.email,
-                claimed_at: dto.claimed_at.map(|dt| naive_to_utc(dt).to_rfc3339()),
-                approved_at: dto.approved_at.map(|dt| naive_to_utc(dt).to_rfc3339()),
+                claimed_at: dto.claimed_at.map(naive_to_rfc3339),
+                approved_at: dto.approved_at.map(naive_to_rfc3339),
                 first_name: dto.first_name,
                 last_name: dto.last_name,
                 reset_requested_at: dto
                     .reset_requested_at
-                    .map(|dt| naive_to_utc(dt).to_rfc3339()),
-                last_update: dto.last_update.map(|dt| naive_to_utc(dt).to_rfc3339()),
+                    .map(naive_to_rfc3339),
+                last_update: dto.last_update.map(naive_to_rfc3339),
                 last_coach_update_at: dto
                     .last_coach_update_at
-                    .map(|dt| naive_to_utc(dt).to_rfc3339()),
+                    .map(naive_to_rfc3339),
                 total_techniques: dto.total_techniques,
                 red_count: dto.red_count,
                 amber_count: dto.amber_count,
                 green_count: dto.green_count,
                 has_unseen_activity: dto.has_unseen_activity.map(|v| v != 0),
-                last_student_initiative_at: initiative.map(|dt| naive_to_utc(dt).to_rfc3339()),
+                review_requests: dto.review_requests,
+                last_student_initiative_at: initiative.map(naive_to_rfc3339),
                 last_watch_at: dto
                     .latest_watch_at
-                    .map(|dt| naive_to_utc(dt).to_rfc3339()),
+                    .map(naive_to_rfc3339),
                 last_watch_video_title: dto.latest_watch_video_title,
-            }
+                timezone: dto.timezone,
+                membership_status: MembershipStatus::from_db(id, dto.membership_status)?,
+                avatar_attachment_id: dto.avatar_attachment_id,
+            })
         })
-        .collect();
+        .collect::<Result<_, AppError>>()?;
 
     if include_archived {
         Ok(users)
@@ -171,9 +179,9 @@ pub async fn get_students_by_recent_updates(
     }
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn get_video_stats(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     video_id: i64,
 ) -> Result<VideoStatsSnapshot, AppError> {
     let row = sqlx::query!(
@@ -186,7 +194,7 @@ pub async fn get_video_stats(
          WHERE video_id = ?",
         video_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     let completion_rate = if row.total_plays > 0 {
         row.completed_plays as f64 / row.total_plays as f64
@@ -203,10 +211,10 @@ pub async fn get_video_stats(
     })
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn get_student_watch_activity(
-    pool: &Pool<Sqlite>,
-    student_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
     since: DateTime<Utc>,
 ) -> Result<Vec<StudentWatchActivityRow>, AppError> {
     let rows = sqlx::query!(
@@ -224,10 +232,10 @@ pub async fn get_student_watch_activity(
            WHERE a.user_id = ? AND a.last_watched_at >= ? AND v.deleted_at IS NULL
            ORDER BY a.last_watched_at DESC
            LIMIT 50"#,
-        student_id,
+        student_id.0,
         since,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
     Ok(rows
         .into_iter()
@@ -244,6 +252,238 @@ pub async fn get_student_watch_activity(
         .collect())
 }
 
+/// Activity per coach and admin between `from` and `to` inclusive, including
+/// those with nothing to show. Ordered by name. Techniques and assignments
+/// from before their timestamps were recorded are not counted.
+#[instrument(skip(executor))]
+pub async fn get_coach_report(
+    executor: impl SqliteExecutor<'_>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<CoachActivity>, AppError> {
+    let from = from.format("%Y-%m-%d").to_string();
+    let to = to.format("%Y-%m-%d").to_string();
+    let rows = sqlx::query!(
+        r#"WITH period(from_date, to_date) AS (SELECT ?, ?),
+           activity(user_id, kind) AS (
+               SELECT t.coach_id, 'technique' FROM techniques t, period p
+               WHERE date(t.created_at) BETWEEN p.from_date AND p.to_date
+               UNION ALL
+               SELECT st.assigned_by_id, 'assignment' FROM student_techniques st, period p
+               WHERE date(st.created_at) BETWEEN p.from_date AND p.to_date
+               UNION ALL
+               SELECT a.coach_note_by_id, 'note' FROM attempts a, period p
+               WHERE date(a.coach_note_at) BETWEEN p.from_date AND p.to_date
+               UNION ALL
+               SELECT r.replaced_by_id, 'note' FROM note_revisions r, period p
+               WHERE r.field = 'coach_notes'
+                 AND date(r.replaced_at) BETWEEN p.from_date AND p.to_date
+           )
+           SELECT u.id AS "id!: i64",
+                  COALESCE(NULLIF(u.display_name, ''), u.username) AS "name!: String",
+                  COUNT(CASE WHEN a.kind = 'technique' THEN 1 END) AS "techniques!: i64",
+                  COUNT(CASE WHEN a.kind = 'assignment' THEN 1 END) AS "assignments!: i64",
+                  COUNT(CASE WHEN a.kind = 'note' THEN 1 END) AS "notes!: i64"
+           FROM users u
+           LEFT JOIN activity a ON a.user_id = u.id
+           WHERE u.role IN ('coach', 'admin')
+           GROUP BY u.id
+           ORDER BY 2 COLLATE NOCASE, u.id"#,
+        from,
+        to
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| CoachActivity {
+            user_id: UserId(r.id),
+            name: r.name,
+            techniques_created: r.techniques,
+            assignments: r.assignments,
+            notes_written: r.notes,
+        })
+        .collect())
+}
+
+/// Progress per tag, over the tags on at least one of the student's assigned
+/// techniques. Ordered by tag name.
+#[instrument(skip(executor))]
+pub async fn get_tag_progress(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+) -> Result<Vec<GroupProgress>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT g.id AS "id!: i64",
+                  g.name AS "name!: String",
+                  (SELECT COUNT(*) FROM technique_tags WHERE tag_id = g.id) AS "techniques!: i64",
+                  COUNT(DISTINCT st.id) AS "assigned!: i64",
+                  COUNT(DISTINCT CASE WHEN st.status = 'green' THEN st.id END) AS "green!: i64"
+           FROM student_techniques st
+           JOIN technique_tags tt ON tt.technique_id = st.technique_id
+           JOIN tags g ON g.id = tt.tag_id
+           WHERE st.student_id = ?
+           GROUP BY g.id
+           ORDER BY g.name COLLATE NOCASE"#,
+        student_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| GroupProgress {
+            id: r.id,
+            name: r.name,
+            techniques: r.techniques,
+            assigned: r.assigned,
+            green: r.green,
+            percent: GroupProgress::percent(r.green, r.assigned),
+        })
+        .collect())
+}
+
+/// Progress per curriculum (collection), over the curricula containing at
+/// least one of the student's assigned techniques, however it was assigned.
+/// Ordered by curriculum name.
+#[instrument(skip(executor))]
+pub async fn get_curriculum_progress(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+) -> Result<Vec<GroupProgress>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT c.id AS "id!: i64",
+                  c.name AS "name!: String",
+                  (SELECT COUNT(*) FROM collection_techniques WHERE collection_id = c.id)
+                      AS "techniques!: i64",
+                  COUNT(DISTINCT st.id) AS "assigned!: i64",
+                  COUNT(DISTINCT CASE WHEN st.status = 'green' THEN st.id END) AS "green!: i64"
+           FROM student_techniques st
+           JOIN collection_techniques ct ON ct.technique_id = st.technique_id
+           JOIN collections c ON c.id = ct.collection_id
+           WHERE st.student_id = ?
+           GROUP BY c.id
+           ORDER BY c.name COLLATE NOCASE"#,
+        student_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| GroupProgress {
+            id: r.id,
+            name: r.name,
+            techniques: r.techniques,
+            assigned: r.assigned,
+            green: r.green,
+            percent: GroupProgress::percent(r.green, r.assigned),
+        })
+        .collect())
+}
+
+/// Counts by status overall and per tag, plus when the student's techniques
+/// were last touched. Aggregated in SQL so the dashboard never loads the
+/// rows themselves.
+#[instrument(skip(pool))]
+pub async fn get_student_progress(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+) -> Result<StudentProgress, AppError> {
+    let summary = sqlx::query!(
+        r#"SELECT COUNT(*) AS "total!: i64",
+                  MAX(updated_at) AS "last_updated_at: NaiveDateTime",
+                  MAX(last_coach_update_at) AS "last_coach_update_at: NaiveDateTime",
+                  MAX(last_student_update_at) AS "last_student_update_at: NaiveDateTime"
+           FROM student_techniques
+           WHERE student_id = ?"#,
+        student_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let attendance = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!: i64", MAX(c.starts_at) AS "last: NaiveDateTime"
+           FROM attendance a
+           JOIN classes c ON c.id = a.class_id
+           WHERE a.student_id = ?"#,
+        student_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let statuses = sqlx::query!(
+        r#"SELECT COALESCE(st.status, 'red') AS "status!: String", COUNT(*) AS "count!: i64"
+           FROM student_techniques st
+           LEFT JOIN statuses s ON s.name = COALESCE(st.status, 'red')
+           WHERE st.student_id = ?
+           GROUP BY 1
+           ORDER BY s.position IS NULL, s.position, 1"#,
+        student_id.0
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| StatusCount { status: r.status, count: r.count })
+    .collect();
+
+    let tag_rows = sqlx::query!(
+        r#"SELECT g.id AS "id!: i64",
+                  g.name AS "name!: String",
+                  COALESCE(st.status, 'red') AS "status!: String",
+                  COUNT(*) AS "count!: i64",
+                  MAX(st.updated_at) AS "last_updated_at: NaiveDateTime"
+           FROM student_techniques st
+           JOIN technique_tags tt ON tt.technique_id = st.technique_id
+           JOIN tags g ON g.id = tt.tag_id
+           LEFT JOIN statuses s ON s.name = COALESCE(st.status, 'red')
+           WHERE st.student_id = ?
+           GROUP BY g.id, 3
+           ORDER BY g.name COLLATE NOCASE, g.id, s.position IS NULL, s.position, 3"#,
+        student_id.0
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // A tag's rows are adjacent, so each folds into the last breakdown; the
+    // latest update rides alongside until the end.
+    let mut tags: Vec<(TagStatusBreakdown, Option<NaiveDateTime>)> = Vec::new();
+    for row in tag_rows {
+        if tags.last().is_none_or(|(tag, _)| tag.id != row.id) {
+            let tag = TagStatusBreakdown {
+                id: row.id,
+                name: row.name,
+                total: 0,
+                statuses: Vec::new(),
+                last_updated_at: None,
+            };
+            tags.push((tag, None));
+        }
+        if let Some((tag, updated)) = tags.last_mut() {
+            tag.total += row.count;
+            tag.statuses.push(StatusCount { status: row.status, count: row.count });
+            *updated = (*updated).max(row.last_updated_at);
+        }
+    }
+    let tags = tags
+        .into_iter()
+        .map(|(tag, updated)| TagStatusBreakdown {
+            last_updated_at: updated.map(naive_to_rfc3339),
+            ..tag
+        })
+        .collect();
+
+    Ok(StudentProgress {
+        total: summary.total,
+        statuses,
+        tags,
+        last_updated_at: summary.last_updated_at.map(naive_to_rfc3339),
+        last_coach_update_at: summary.last_coach_update_at.map(naive_to_rfc3339),
+        last_student_update_at: summary.last_student_update_at.map(naive_to_rfc3339),
+        classes_attended: attendance.count,
+        last_attended_at: attendance.last.map(naive_to_rfc3339),
+    })
+}
+
 #[instrument(skip(pool))]
 pub async fn get_dashboard_video_overview(
     pool: &Pool<Sqlite>,
@@ -314,7 +554,7 @@ pub async fn get_storage_overview(
            FROM videos v
            JOIN techniques t ON t.id = v.technique_id
            WHERE v.bytes IS NOT NULL AND v.storage_key IS NOT NULL
-           ORDER BY v.bytes DESC
+           ORDER BY v.bytes DESC, v.id
            LIMIT ?"#,
         top,
     )
@@ -335,3 +575,44 @@ pub async fn get_storage_overview(
             .collect(),
     })
 }
+
+#[instrument(skip(pool))]
+pub async fn get_public_syllabus(pool: &Pool<Sqlite>) -> Result<PublicSyllabus, AppError> {
+    let techniques: Vec<PublicTechnique> = super::get_all_techniques(pool)
+        .await?
+        .into_iter()
+        .map(|technique| PublicTechnique {
+            id: technique.id,
+            name: technique.name,
+            description: technique.description,
+            tags: technique.tags.into_iter().map(|tag| tag.name).collect(),
+            aliases: technique.aliases.into_iter().map(|alias| alias.alias).collect(),
+        })
+        .collect();
+
+    let rows = sqlx::query!(
+        r#"SELECT c.id, c.name, c.description, ct.technique_id AS "technique_id?: i64"
+           FROM collections c
+           LEFT JOIN collection_techniques ct ON ct.collection_id = c.id
+           LEFT JOIN techniques t ON t.id = ct.technique_id
+           ORDER BY c.name, c.id, ct.position, t.name"#
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut curricula: Vec<PublicCurriculum> = Vec::new();
+    for row in rows {
+        if curricula.last().is_none_or(|c| c.id != row.id) {
+            curricula.push(PublicCurriculum {
+                id: row.id,
+                name: row.name,
+                description: row.description.unwrap_or_default(),
+                technique_ids: Vec::new(),
+            });
+        }
+        if let (Some(curriculum), Some(technique_id)) = (curricula.last_mut(), row.technique_id) {
+            curriculum.technique_ids.push(TechniqueId(technique_id));
+        }
+    }
+
+    Ok(PublicSyllabus { curricula, techniques })
+}
@@ -0,0 +1,172 @@
+//! The weekly class timetable. Each slot repeats on one weekday at a local
+//! time in its own timezone, from `starts_on` until `ends_on` (if any).
+
+use chrono::{NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteExecutor;
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::UserId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleSlot {
+    pub id: i64,
+    pub title: String,
+    /// ISO weekday: 1 is Monday, 7 is Sunday.
+    pub weekday: i64,
+    pub start_time: NaiveTime,
+    pub duration_minutes: i64,
+    /// IANA name, e.g. `Europe/London`.
+    pub timezone: String,
+    pub coach_id: Option<UserId>,
+    pub starts_on: NaiveDate,
+    pub ends_on: Option<NaiveDate>,
+}
+
+/// Everything about a slot but its id and coach, for create and update.
+#[derive(Debug, Clone)]
+pub struct ScheduleSlotInput<'a> {
+    pub title: &'a str,
+    pub weekday: i64,
+    pub start_time: NaiveTime,
+    pub duration_minutes: i64,
+    pub timezone: &'a str,
+    pub starts_on: NaiveDate,
+    pub ends_on: Option<NaiveDate>,
+}
+
+/// Every slot, Monday first and then by start time, including ones that
+/// have ended.
+#[instrument(skip(executor))]
+pub async fn get_schedule(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<Vec<ScheduleSlot>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", title, weekday, start_time AS "start_time: NaiveTime",
+                  duration_minutes, timezone, coach_id,
+                  starts_on AS "starts_on: NaiveDate", ends_on AS "ends_on: NaiveDate"
+           FROM class_schedule
+           ORDER BY weekday, start_time, id"#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ScheduleSlot {
+            id: row.id,
+            title: row.title,
+            weekday: row.weekday,
+            start_time: row.start_time,
+            duration_minutes: row.duration_minutes,
+            timezone: row.timezone,
+            coach_id: row.coach_id.map(UserId),
+            starts_on: row.starts_on,
+            ends_on: row.ends_on,
+        })
+        .collect())
+}
+
+#[instrument(skip(executor))]
+pub async fn get_schedule_slot(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<ScheduleSlot, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT id AS "id!", title, weekday, start_time AS "start_time: NaiveTime",
+                  duration_minutes, timezone, coach_id,
+                  starts_on AS "starts_on: NaiveDate", ends_on AS "ends_on: NaiveDate"
+           FROM class_schedule
+           WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Schedule slot {} not found", id)))?;
+
+    Ok(ScheduleSlot {
+        id: row.id,
+        title: row.title,
+        weekday: row.weekday,
+        start_time: row.start_time,
+        duration_minutes: row.duration_minutes,
+        timezone: row.timezone,
+        coach_id: row.coach_id.map(UserId),
+        starts_on: row.starts_on,
+        ends_on: row.ends_on,
+    })
+}
+
+#[instrument(skip(executor, slot))]
+pub async fn create_schedule_slot(
+    executor: impl SqliteExecutor<'_>,
+    slot: &ScheduleSlotInput<'_>,
+    coach_id: UserId,
+) -> Result<i64, AppError> {
+    info!("Creating schedule slot");
+    let res = sqlx::query!(
+        "INSERT INTO class_schedule (title, weekday, start_time, duration_minutes, timezone,
+                                     coach_id, starts_on, ends_on)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        slot.title,
+        slot.weekday,
+        slot.start_time,
+        slot.duration_minutes,
+        slot.timezone,
+        coach_id.0,
+        slot.starts_on,
+        slot.ends_on
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+#[instrument(skip(executor, slot))]
+pub async fn update_schedule_slot(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+    slot: &ScheduleSlotInput<'_>,
+) -> Result<(), AppError> {
+    info!("Updating schedule slot");
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE class_schedule
+         SET title = ?, weekday = ?, start_time = ?, duration_minutes = ?, timezone = ?,
+             starts_on = ?, ends_on = ?, updated_at = ?
+         WHERE id = ?",
+        slot.title,
+        slot.weekday,
+        slot.start_time,
+        slot.duration_minutes,
+        slot.timezone,
+        slot.starts_on,
+        slot.ends_on,
+        now,
+        id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Schedule slot {} not found", id)));
+    }
+    Ok(())
+}
+
+#[instrument(skip(executor))]
+pub async fn delete_schedule_slot(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<(), AppError> {
+    info!("Deleting schedule slot");
+    let res = sqlx::query!("DELETE FROM class_schedule WHERE id = ?", id)
+        .execute(executor)
+        .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Schedule slot {} not found", id)));
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::SqliteExecutor;
+use tracing::instrument;
+
+use crate::error::AppError;
+
+/// A run of the migrate binary that changed the schema. Rows are written by
+/// that binary, never by the app.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaMigration {
+    pub applied_at: NaiveDateTime,
+    pub schema_sha256: String,
+    /// One step per line, as the migrate binary printed them.
+    pub steps: String,
+}
+
+/// Most recent first.
+#[instrument(skip(executor))]
+pub async fn get_schema_migrations(
+    executor: impl SqliteExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<SchemaMigration>, AppError> {
+    let rows = sqlx::query_as!(
+        SchemaMigration,
+        "SELECT applied_at, schema_sha256, steps FROM schema_migrations
+         ORDER BY id DESC LIMIT ?",
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows)
+}
@@ -1,14 +1,15 @@
 use chrono::{NaiveDateTime, Utc};
-use sqlx::{Pool, Sqlite};
+use sqlx::SqliteExecutor;
 use tracing::{info, instrument};
 
 use crate::auth::{DbUserSession, UserSession};
 use crate::error::AppError;
+use crate::ids::UserId;
 
-#[instrument(skip(pool, token))]
+#[instrument(skip(executor, token))]
 pub async fn create_user_session(
-    pool: &Pool<Sqlite>,
-    user_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
     token: &str,
     expires_at: NaiveDateTime,
 ) -> Result<i64, AppError> {
@@ -16,19 +17,19 @@ pub async fn create_user_session(
 
     let res = sqlx::query!(
         "INSERT INTO user_sessions (user_id, token, expires_at) VALUES (?, ?, ?)",
-        user_id,
+        user_id.0,
         token,
         expires_at
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(res.last_insert_rowid())
 }
 
-#[instrument(skip(pool, token))]
+#[instrument(skip(executor, token))]
 pub async fn get_session_by_token(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     token: &str,
 ) -> Result<UserSession, AppError> {
     info!("Getting session by token");
@@ -38,7 +39,7 @@ pub async fn get_session_by_token(
         "SELECT id, user_id, token, created_at, expires_at FROM user_sessions WHERE token = ?",
         token
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     match session {
@@ -49,9 +50,9 @@ pub async fn get_session_by_token(
     }
 }
 
-#[instrument(skip(pool, token))]
+#[instrument(skip(executor, token))]
 pub async fn extend_session_expiry(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     token: &str,
     new_expires_at: NaiveDateTime,
 ) -> Result<(), AppError> {
@@ -60,30 +61,47 @@ pub async fn extend_session_expiry(
         new_expires_at,
         token
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
-#[instrument(skip(pool, token))]
-pub async fn invalidate_session(pool: &Pool<Sqlite>, token: &str) -> Result<(), AppError> {
+/// Signs `user_id` out everywhere. Call whenever what the user is allowed
+/// to do changes, so no token issued before the change outlives it.
+#[instrument(skip(executor))]
+pub async fn delete_user_sessions(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<u64, AppError> {
+    info!("Deleting the user's sessions");
+    let res = sqlx::query!("DELETE FROM user_sessions WHERE user_id = ?", user_id.0)
+        .execute(executor)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+#[instrument(skip(executor, token))]
+pub async fn invalidate_session(
+    executor: impl SqliteExecutor<'_>,
+    token: &str,
+) -> Result<(), AppError> {
     info!("Invalidating session");
 
     sqlx::query!("DELETE FROM user_sessions WHERE token = ?", token)
-        .execute(pool)
+        .execute(executor)
         .await?;
 
     Ok(())
 }
 
-#[instrument(skip(pool))]
-pub async fn clean_expired_sessions(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+#[instrument(skip(executor))]
+pub async fn clean_expired_sessions(executor: impl SqliteExecutor<'_>) -> Result<u64, AppError> {
     info!("Cleaning expired sessions");
 
     let now = Utc::now().naive_utc();
 
     let result = sqlx::query!("DELETE FROM user_sessions WHERE expires_at < ?", now)
-        .execute(pool)
+        .execute(executor)
         .await?;
 
     Ok(result.rows_affected())
@@ -0,0 +1,321 @@
+//! Import of a syllabus kept in a spreadsheet (exported as CSV, e.g. from
+//! Google Sheets via File > Download > CSV). Each row is a technique; which
+//! header holds the name, description, tags and curriculum is configurable
+//! through `SheetColumns`, since every gym lays its sheet out differently.
+//!
+//! Rows are matched against the library by technique name (case-insensitive),
+//! tags by their normalized name and curricula by collection name, so
+//! re-importing an edited sheet only adds what is new: existing techniques
+//! keep their description and gain any extra tags or curricula. A row that
+//! can't be imported is skipped and reported with its line number instead of
+//! failing the whole sheet.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use tracing::{info, instrument};
+
+use super::{ImportCounts, index_technique};
+use super::archive::count;
+use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
+use crate::validation::{
+    ValidationConfig, normalize_tag_name, sanitize_plain_text, validate_description,
+    validate_tag_name, validate_technique_name,
+};
+
+/// Header names to read each field from, matched case-insensitively. Only
+/// the name column has to be present; the sheet may have any other columns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetColumns {
+    pub name: String,
+    pub description: String,
+    pub tags: String,
+    pub curriculum: String,
+    /// Splits the tags cell, e.g. "Guard, Sweep".
+    pub tag_separator: char,
+}
+
+impl Default for SheetColumns {
+    fn default() -> Self {
+        Self {
+            name: "Technique".to_string(),
+            description: "Description".to_string(),
+            tags: "Tags".to_string(),
+            curriculum: "Curriculum".to_string(),
+            tag_separator: ',',
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RowProblem {
+    /// Line in the CSV file, counting the header as line 1.
+    pub line: u64,
+    pub message: String,
+    /// False when the row went in but part of it (e.g. one tag) was dropped.
+    pub skipped: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SheetImportReport {
+    pub rows: usize,
+    pub techniques: ImportCounts,
+    pub tags: ImportCounts,
+    pub curricula: ImportCounts,
+    pub problems: Vec<RowProblem>,
+}
+
+/// One non-blank data row, with cells trimmed and sanitized.
+#[derive(Debug)]
+pub struct SheetRow {
+    /// Line in the CSV file, counting the header as line 1.
+    pub line: u64,
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub curriculum: String,
+}
+
+/// Reads the header row and every data row of `csv`. Fails only when the
+/// file can't be read as CSV or has no name column, with a message meant
+/// for whoever exported the sheet; problems within a row are left for
+/// `import_spreadsheet` to report.
+pub fn parse_spreadsheet(csv: &str, columns: &SheetColumns) -> Result<Vec<SheetRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Could not read the header row: {}", e))?
+        .clone();
+    let find = |wanted: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(wanted.trim()))
+    };
+    let name = find(&columns.name).ok_or_else(|| {
+        format!(
+            "No '{}' column in the header row (found: {})",
+            columns.name,
+            headers.iter().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    let (description, tags, curriculum) = (
+        find(&columns.description),
+        find(&columns.tags),
+        find(&columns.curriculum),
+    );
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Could not read the file: {}", e))?;
+        if record.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(|raw| sanitize_plain_text(raw.trim()))
+                .unwrap_or_default()
+        };
+        rows.push(SheetRow {
+            line: record.position().map_or(0, |p| p.line()),
+            name: cell(Some(name)),
+            description: cell(description),
+            tags: cell(tags)
+                .split(columns.tag_separator)
+                .map(normalize_tag_name)
+                .filter(|t| !t.is_empty())
+                .collect(),
+            curriculum: cell(curriculum),
+        });
+    }
+    Ok(rows)
+}
+
+/// Imports parsed rows in one transaction. Techniques and curricula created
+/// here are owned by `coach_id`. With `dry_run` the transaction is rolled
+/// back, so the report shows what an import would do.
+#[instrument(skip(pool, rows, config))]
+pub async fn import_spreadsheet(
+    pool: &Pool<Sqlite>,
+    rows: &[SheetRow],
+    coach_id: UserId,
+    config: &ValidationConfig,
+    dry_run: bool,
+) -> Result<SheetImportReport, AppError> {
+    info!(rows = rows.len(), dry_run, "Importing spreadsheet");
+    let mut tx = pool.begin().await?;
+    let mut sheet = SheetImport::default();
+    for row in rows {
+        sheet.report.rows += 1;
+        sheet.import_row(&mut tx, row, coach_id, config).await?;
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(sheet.report)
+}
+
+/// State carried across rows, so a tag or curriculum the sheet mentions on
+/// many rows is looked up (and counted) once.
+#[derive(Default)]
+struct SheetImport {
+    report: SheetImportReport,
+    tags: HashMap<String, i64>,
+    curricula: HashMap<String, i64>,
+    /// Lowercased technique name -> first line it appeared on.
+    seen: HashMap<String, u64>,
+}
+
+impl SheetImport {
+    fn problem(&mut self, line: u64, message: String, skipped: bool) {
+        self.report.problems.push(RowProblem { line, message, skipped });
+    }
+
+    async fn import_row(
+        &mut self,
+        conn: &mut SqliteConnection,
+        row: &SheetRow,
+        coach_id: UserId,
+        config: &ValidationConfig,
+    ) -> Result<(), AppError> {
+        let SheetRow { line, name, description, tags, curriculum } = row;
+        let line = *line;
+        if let Err(e) = validate_technique_name(name, config) {
+            self.problem(line, format!("{}", e), true);
+            return Ok(());
+        }
+        if let Err(e) = validate_description(description, config) {
+            self.problem(line, format!("{}", e), true);
+            return Ok(());
+        }
+
+        // A repeated name merges into the first row's technique; say so, since
+        // it is usually a copy-paste slip rather than intent.
+        match self.seen.get(&name.to_lowercase()) {
+            Some(first) => {
+                let message = format!("'{}' already appears on line {}; merged", name, first);
+                self.problem(line, message, false);
+            }
+            None => {
+                self.seen.insert(name.to_lowercase(), line);
+            }
+        }
+
+        let existing: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM techniques WHERE name = ? COLLATE NOCASE ORDER BY id LIMIT 1",
+        )
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let technique_id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO techniques (name, description, coach_id, created_at)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(name)
+            .bind(description)
+            .bind(coach_id.0)
+            .bind(chrono::Utc::now().naive_utc())
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+        };
+        count(&mut self.report.techniques, existing.is_none());
+
+        for tag in tags {
+            if let Err(e) = validate_tag_name(tag, config) {
+                self.problem(line, format!("Tag '{}' dropped: {}", tag, e), false);
+                continue;
+            }
+            let tag_id = self.tag_id(conn, tag).await?;
+            sqlx::query("INSERT OR IGNORE INTO technique_tags (technique_id, tag_id) VALUES (?, ?)")
+                .bind(technique_id)
+                .bind(tag_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        index_technique(conn, TechniqueId(technique_id)).await?;
+
+        // Same limit as `CollectionUpsertRequest`.
+        if curriculum.chars().count() > 100 {
+            let message = "Curriculum dropped: name must be under 100 characters".to_string();
+            self.problem(line, message, false);
+        } else if !curriculum.is_empty() {
+            let collection_id = self.curriculum_id(conn, curriculum, coach_id).await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO collection_techniques (collection_id, technique_id, position)
+                 VALUES (?, ?, (SELECT COALESCE(MAX(position), -1) + 1
+                                FROM collection_techniques WHERE collection_id = ?))",
+            )
+            .bind(collection_id)
+            .bind(technique_id)
+            .bind(collection_id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn tag_id(&mut self, conn: &mut SqliteConnection, name: &str) -> Result<i64, AppError> {
+        if let Some(id) = self.tags.get(name) {
+            return Ok(*id);
+        }
+        let existing: Option<(i64,)> =
+            sqlx::query_as("SELECT id FROM tags WHERE name = ? COLLATE NOCASE")
+                .bind(name)
+                .fetch_optional(&mut *conn)
+                .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query("INSERT INTO tags (name) VALUES (?)")
+                .bind(name)
+                .execute(&mut *conn)
+                .await?
+                .last_insert_rowid(),
+        };
+        count(&mut self.report.tags, existing.is_none());
+        self.tags.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    async fn curriculum_id(
+        &mut self,
+        conn: &mut SqliteConnection,
+        name: &str,
+        coach_id: UserId,
+    ) -> Result<i64, AppError> {
+        let key = name.to_lowercase();
+        if let Some(id) = self.curricula.get(&key) {
+            return Ok(*id);
+        }
+        let existing: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM collections WHERE name = ? COLLATE NOCASE ORDER BY id LIMIT 1",
+        )
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let id = match existing {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO collections (name, description, coach_id) VALUES (?, '', ?)",
+            )
+            .bind(name)
+            .bind(coach_id.0)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+        };
+        count(&mut self.report.curricula, existing.is_none());
+        self.curricula.insert(key, id);
+        Ok(id)
+    }
+}
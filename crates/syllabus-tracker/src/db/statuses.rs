@@ -0,0 +1,243 @@
+//! The gym's grading scale and the rules for moving between its levels.
+//! `student_techniques.status` holds a status name; the scale gives each
+//! name a colour and a place in the order, so "at least amber" means amber
+//! or anything positioned above it.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::auth::Role;
+use crate::error::AppError;
+
+/// Where new assignments start: the column default on
+/// `student_techniques.status`, and what a NULL status reads as.
+pub const STARTING_STATUS: &str = "red";
+
+/// The scale a fresh database gets, lowest first.
+const DEFAULT_STATUSES: &[(&str, &str)] =
+    &[("red", "#dc2626"), ("amber", "#f59e0b"), ("green", "#16a34a")];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusLevel {
+    pub name: String,
+    /// `#rrggbb`.
+    pub color: String,
+    pub position: i64,
+}
+
+/// Fills in the default red/amber/green scale when the table is empty, so
+/// existing rows keep meaning what they did. Returns the number of
+/// statuses added.
+#[instrument(skip(pool))]
+pub async fn seed_default_statuses(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let existing = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!: i64" FROM statuses"#)
+        .fetch_one(&mut *tx)
+        .await?;
+    if existing > 0 {
+        return Ok(0);
+    }
+
+    for (position, (name, color)) in (0_i64..).zip(DEFAULT_STATUSES) {
+        sqlx::query!(
+            "INSERT INTO statuses (name, color, position) VALUES (?, ?, ?)",
+            name,
+            color,
+            position
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    info!("Seeded default statuses");
+    Ok(DEFAULT_STATUSES.len() as u64)
+}
+
+/// Lowest first.
+#[instrument(skip(executor))]
+pub async fn get_statuses(executor: impl SqliteExecutor<'_>) -> Result<Vec<StatusLevel>, AppError> {
+    let statuses = sqlx::query_as!(
+        StatusLevel,
+        r#"SELECT name AS "name!", color, position FROM statuses ORDER BY position, name"#
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(statuses)
+}
+
+/// Adds `name` at the top of the scale. `None` when a status with that
+/// name already exists.
+#[instrument(skip(pool))]
+pub async fn create_status(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    color: &str,
+) -> Result<Option<StatusLevel>, AppError> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!(
+        "INSERT INTO statuses (name, color, position)
+         SELECT ?, ?, COALESCE(MAX(position) + 1, 0) FROM statuses WHERE true
+         ON CONFLICT (name) DO NOTHING",
+        name,
+        color
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let status = sqlx::query_as!(
+        StatusLevel,
+        r#"SELECT name AS "name!", color, position FROM statuses WHERE name = ?"#,
+        name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!(name, "Created status");
+    Ok(Some(status))
+}
+
+/// Changes a status's colour. Names are the stored value on every
+/// assignment, so they don't change.
+#[instrument(skip(executor))]
+pub async fn update_status_color(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+    color: &str,
+) -> Result<(), AppError> {
+    let res = sqlx::query!("UPDATE statuses SET color = ? WHERE name = ?", color, name)
+        .execute(executor)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("status {}", name)));
+    }
+    Ok(())
+}
+
+/// Sets the scale's order to `names`, lowest first. The caller checks that
+/// `names` is exactly the current set.
+#[instrument(skip(pool))]
+pub async fn reorder_statuses(pool: &Pool<Sqlite>, names: &[String]) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    for (position, name) in (0_i64..).zip(names) {
+        sqlx::query!("UPDATE statuses SET position = ? WHERE name = ?", position, name)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    info!(count = names.len(), "Reordered statuses");
+    Ok(())
+}
+
+/// How many assignments and rank requirements use `name`. A status in use
+/// can't be deleted.
+#[instrument(skip(executor))]
+pub async fn count_status_uses(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+) -> Result<i64, AppError> {
+    let uses = sqlx::query_scalar!(
+        r#"SELECT (SELECT COUNT(*) FROM student_techniques WHERE COALESCE(status, 'red') = ?)
+                + (SELECT COUNT(*) FROM rank_requirements WHERE status = ?) AS "uses!: i64""#,
+        name,
+        name
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(uses)
+}
+
+/// Removes `name` from the scale along with any transition rules naming it.
+#[instrument(skip(pool))]
+pub async fn delete_status(pool: &Pool<Sqlite>, name: &str) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!("DELETE FROM statuses WHERE name = ?", name)
+        .execute(&mut *tx)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("status {}", name)));
+    }
+    sqlx::query!(
+        "DELETE FROM status_transitions WHERE from_status = ? OR to_status = ?",
+        name,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!(name, "Deleted status");
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusTransition {
+    pub from_status: String,
+    pub to_status: String,
+    pub min_role: Role,
+}
+
+#[instrument(skip(executor))]
+pub async fn get_status_transitions(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<Vec<StatusTransition>, AppError> {
+    info!("Getting status transitions");
+    let rows = sqlx::query!(
+        "SELECT from_status, to_status, min_role
+         FROM status_transitions
+         ORDER BY from_status, to_status"
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let min_role = Role::from_str(&row.min_role).map_err(|_| {
+                AppError::Internal(format!(
+                    "Status transition {} -> {} has unknown role '{}' in database",
+                    row.from_status, row.to_status, row.min_role
+                ))
+            })?;
+            Ok(StatusTransition {
+                from_status: row.from_status,
+                to_status: row.to_status,
+                min_role,
+            })
+        })
+        .collect()
+}
+
+/// Replaces the whole rule set in one transaction, so readers never see a
+/// half-written table. An empty slice turns enforcement off.
+#[instrument(skip(transitions))]
+pub async fn replace_status_transitions(
+    pool: &Pool<Sqlite>,
+    transitions: &[StatusTransition],
+) -> Result<(), AppError> {
+    info!(count = transitions.len(), "Replacing status transitions");
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM status_transitions")
+        .execute(&mut *tx)
+        .await?;
+
+    for transition in transitions {
+        let min_role = transition.min_role.as_str();
+        sqlx::query!(
+            "INSERT OR REPLACE INTO status_transitions (from_status, to_status, min_role)
+             VALUES (?, ?, ?)",
+            transition.from_status,
+            transition.to_status,
+            min_role
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
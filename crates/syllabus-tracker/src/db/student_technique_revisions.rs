@@ -0,0 +1,93 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor};
+use tracing::instrument;
+
+use super::StudentTechniqueField;
+use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, UserId};
+use crate::models::naive_to_utc;
+
+/// One field of one save: `changed_by` set `field` from `old_value` to
+/// `new_value`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StudentTechniqueRevision {
+    pub id: i64,
+    pub field: StudentTechniqueField,
+    pub old_value: String,
+    pub new_value: String,
+    pub changed_at: DateTime<Utc>,
+    /// `None` once that user has been deleted.
+    pub changed_by_id: Option<UserId>,
+    pub changed_by_name: Option<String>,
+}
+
+/// Records `field` moving from its stored value to `new_value`. Call in the
+/// same transaction as the update, before it, and only for fields that
+/// differ. A missing row records nothing.
+#[instrument(skip(conn, new_value))]
+pub async fn record_revision(
+    conn: &mut SqliteConnection,
+    id: StudentTechniqueId,
+    actor_id: UserId,
+    now: NaiveDateTime,
+    field: StudentTechniqueField,
+    new_value: &str,
+) -> Result<(), AppError> {
+    let field = field.as_str();
+    sqlx::query!(
+        "INSERT INTO student_technique_revisions
+             (student_technique_id, field, old_value, new_value, changed_at, changed_by_id)
+         SELECT id, ?1,
+                CASE ?1 WHEN 'status' THEN COALESCE(status, 'red')
+                        WHEN 'student_notes' THEN COALESCE(student_notes, '')
+                        ELSE COALESCE(coach_notes, '')
+                END,
+                ?2, ?3, ?4
+         FROM student_techniques
+         WHERE id = ?5",
+        field,
+        new_value,
+        now,
+        actor_id.0,
+        id.0
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Newest first.
+#[instrument(skip(executor))]
+pub async fn get_student_technique_revisions(
+    executor: impl SqliteExecutor<'_>,
+    id: StudentTechniqueId,
+) -> Result<Vec<StudentTechniqueRevision>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT r.id AS "id!", r.field, r.old_value, r.new_value,
+                  r.changed_at AS "changed_at: NaiveDateTime",
+                  r.changed_by_id,
+                  COALESCE(NULLIF(u.display_name, ''), u.username) AS "changed_by_name?: String"
+           FROM student_technique_revisions r
+           LEFT JOIN users u ON u.id = r.changed_by_id
+           WHERE r.student_technique_id = ?
+           ORDER BY r.changed_at DESC, r.id DESC"#,
+        id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(StudentTechniqueRevision {
+                id: row.id,
+                field: StudentTechniqueField::from_db(&row.field)?,
+                old_value: row.old_value,
+                new_value: row.new_value,
+                changed_at: naive_to_utc(row.changed_at),
+                changed_by_id: row.changed_by_id.map(UserId),
+                changed_by_name: row.changed_by_name,
+            })
+        })
+        .collect()
+}
@@ -1,23 +1,29 @@
-use std::collections::{HashMap, hash_map::Entry};
 
-use chrono::{NaiveDateTime, Utc};
-use sqlx::{Pool, Sqlite};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection, SqliteExecutor};
 use tracing::{info, instrument};
 
+use super::{
+    NotificationKind, get_aliases_by_technique, get_media_by_technique, get_technique_media,
+    notify_student, record_note_revisions, record_revision,
+};
 use crate::auth::{Role, User};
 use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, TagId, TechniqueId, UserId};
 use crate::models::{
-    DbStudentTechnique, DbTag, StudentTechnique, Tag, Technique, naive_to_utc,
+    DbStudentTechnique, DbTag, StudentTechnique, Tag, Technique, display_name_or_username,
+    naive_to_utc, required,
 };
 
 #[instrument]
 pub async fn assign_technique_to_student(
     pool: &Pool<Sqlite>,
-    technique_id: i64,
-    student_id: i64,
+    technique_id: TechniqueId,
+    student_id: UserId,
     collection_id: Option<i64>,
-    actor_id: i64,
-) -> Result<i64, AppError> {
+    actor_id: UserId,
+) -> Result<StudentTechniqueId, AppError> {
     info!("Assigning technique to student");
     struct ReturnRow {
         id: i64,
@@ -26,8 +32,8 @@ pub async fn assign_technique_to_student(
     let exists = sqlx::query_as!(
         ReturnRow,
         "SELECT id FROM student_techniques WHERE technique_id = ? AND student_id = ?",
-        technique_id,
-        student_id
+        technique_id.0,
+        student_id.0
     )
     .fetch_optional(pool)
     .await?;
@@ -45,7 +51,7 @@ pub async fn assign_technique_to_student(
             .execute(pool)
             .await?;
         }
-        return Ok(row.id);
+        return Ok(StudentTechniqueId(row.id));
     }
 
     // Stamp the coach-update timestamps on creation so the assignment itself
@@ -54,46 +60,67 @@ pub async fn assign_technique_to_student(
     let now = Utc::now().naive_utc();
     let res = sqlx::query!(
         "INSERT INTO student_techniques
-     (student_id, student_notes, coach_notes, technique_id, technique_name, technique_description, collection_id, last_coach_update_at, last_coach_update_by_id)
-     SELECT ?, '', '', t.id, t.name, t.description, ?, ?, ?
-     FROM techniques t WHERE t.id = ?",
-        student_id,
+     (student_id, student_notes, coach_notes, technique_id, technique_name, technique_description, collection_id, last_coach_update_at, last_coach_update_by_id, assigned_by_id)
+     SELECT ?, '', '', t.id, t.name, t.description, ?, ?, ?, ?
+     FROM techniques t WHERE t.id = ? AND t.deleted_at IS NULL",
+        student_id.0,
         collection_id,
         now,
-        actor_id,
-        technique_id
+        actor_id.0,
+        actor_id.0,
+        technique_id.0
     )
     .execute(pool)
     .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Technique {} not found", technique_id)));
+    }
 
-    Ok(res.last_insert_rowid())
+    let id = StudentTechniqueId(res.last_insert_rowid());
+    notify_student(pool, NotificationKind::Assignment, id, actor_id).await?;
+    Ok(id)
 }
 
-#[instrument]
+/// Narrows a student's technique list. Unset fields don't narrow it;
+/// `tag_id` takes in its descendants.
+#[derive(Debug, Default)]
+pub struct StudentTechniqueFilter {
+    pub tag_id: Option<TagId>,
+    pub status: Option<String>,
+    pub updated_since: Option<DateTime<Utc>>,
+}
+
+#[instrument(skip(pool))]
 pub async fn get_student_techniques(
     pool: &Pool<Sqlite>,
-    student_id: i64,
-    viewer_id: i64,
+    student_id: UserId,
+    viewer_id: UserId,
+    filter: &StudentTechniqueFilter,
 ) -> Result<Vec<StudentTechnique>, AppError> {
     info!("Getting student techniques with tags");
+    let tag_id = filter.tag_id.map(|id| id.0);
+    let updated_since = filter.updated_since.map(|at| at.naive_utc());
 
     let rows = sqlx::query!(
         r#"
         SELECT st.id, st.technique_id, st.technique_name, st.technique_description,
                st.student_id, st.status, st.student_notes, st.coach_notes,
-               st.created_at, st.updated_at,
+               st.coach_notes_private, st.created_at, st.updated_at,
                st.last_coach_update_at, st.last_coach_update_by_id,
                st.last_student_update_at, st.last_student_update_by_id,
-               st.collection_id,
+               st.collection_id, st.review_requested_at,
                cu.display_name as coach_updater_display_name,
                cu.username as coach_updater_username,
                su.display_name as student_updater_display_name,
                su.username as student_updater_username,
                coll.name as "collection_name?",
                tag.id as "tag_id?: i64", tag.name as "tag_name?: String",
+               tag.archived as "tag_archived?: bool", tag.category as "tag_category?: String",
+               tag.color as "tag_color?: String", tag.parent_id as "tag_parent_id?: i64",
                COALESCE(att.attempt_count, 0) as "attempt_count!: i64",
                att.last_attempt_at as "last_attempt_at?: NaiveDateTime",
-               stv.seen_at as "viewer_seen_at?: NaiveDateTime"
+               stv.seen_at as "viewer_seen_at?: NaiveDateTime",
+               ssv.seen_at as "student_seen_at?: NaiveDateTime"
         FROM student_techniques st
         LEFT JOIN users cu ON st.last_coach_update_by_id = cu.id
         LEFT JOIN users su ON st.last_student_update_by_id = su.id
@@ -109,41 +136,67 @@ pub async fn get_student_techniques(
         ) att ON att.student_technique_id = st.id
         LEFT JOIN student_technique_views stv
                ON stv.student_technique_id = st.id AND stv.user_id = ?
+        LEFT JOIN student_technique_views ssv
+               ON ssv.student_technique_id = st.id AND ssv.user_id = st.student_id
         WHERE st.student_id = ?
-        ORDER BY st.updated_at DESC
+          AND (? IS NULL OR COALESCE(st.status, 'red') = ?)
+          AND (? IS NULL OR st.updated_at >= ?)
+          AND (? IS NULL OR st.technique_id IN
+                 (WITH RECURSIVE subtree(id) AS (
+                      SELECT ?
+                      UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+                  SELECT technique_id FROM technique_tags WHERE tag_id IN subtree))
+        ORDER BY st.updated_at DESC, st.id DESC, tag.name, tag.id
         "#,
-        viewer_id,
-        student_id
+        viewer_id.0,
+        student_id.0,
+        filter.status,
+        filter.status,
+        updated_since,
+        updated_since,
+        tag_id,
+        tag_id
     )
     .fetch_all(pool)
     .await?;
+    let mut media = get_media_by_technique(pool).await?;
 
-    let mut techniques_map: HashMap<i64, StudentTechnique> = HashMap::new();
-
+    // An assignment's rows are adjacent (ordered by id within a timestamp),
+    // so grouping them in turn keeps the query's order.
+    let mut techniques: Vec<StudentTechnique> = Vec::new();
     for row in rows {
         let technique_id = row.id;
 
-        if let Entry::Vacant(e) = techniques_map.entry(technique_id) {
-            let coach_updater_name = row
-                .coach_updater_display_name
-                .filter(|s| !s.is_empty())
-                .or(row.coach_updater_username);
-            let student_updater_name = row
-                .student_updater_display_name
-                .filter(|s| !s.is_empty())
-                .or(row.student_updater_username);
-
+        if techniques.last().is_none_or(|technique| technique.id.0 != technique_id) {
+            let coach_updater_name = display_name_or_username(
+                row.coach_updater_display_name,
+                row.coach_updater_username,
+            );
+            let student_updater_name = display_name_or_username(
+                row.student_updater_display_name,
+                row.student_updater_username,
+            );
+
+            let library_id = required(row.technique_id, "student_techniques.technique_id")?;
             let technique = StudentTechnique {
-                id: technique_id,
-                technique_id: row.technique_id.unwrap_or_default(),
-                student_id: row.student_id.unwrap_or_default(),
-                technique_name: row.technique_name.unwrap_or_default(),
+                id: StudentTechniqueId(technique_id),
+                technique_id: TechniqueId(library_id),
+                student_id: UserId(required(row.student_id, "student_techniques.student_id")?),
+                technique_name: required(row.technique_name, "student_techniques.technique_name")?,
+                // Copied from the library, where descriptions are optional.
                 technique_description: row.technique_description.unwrap_or_default(),
-                status: row.status.unwrap_or_default(),
+                status: required(row.status, "student_techniques.status")?,
                 student_notes: row.student_notes.unwrap_or_default(),
                 coach_notes: row.coach_notes.unwrap_or_default(),
-                created_at: row.created_at.map(naive_to_utc).unwrap_or_else(Utc::now),
-                updated_at: row.updated_at.map(naive_to_utc).unwrap_or_else(Utc::now),
+                coach_notes_private: row.coach_notes_private,
+                created_at: naive_to_utc(required(
+                    row.created_at,
+                    "student_techniques.created_at",
+                )?),
+                updated_at: naive_to_utc(required(
+                    row.updated_at,
+                    "student_techniques.updated_at",
+                )?),
                 last_coach_update_at: row.last_coach_update_at.map(naive_to_utc),
                 last_coach_update_by_id: row.last_coach_update_by_id,
                 last_coach_update_by_name: coach_updater_name,
@@ -152,69 +205,79 @@ pub async fn get_student_techniques(
                 last_student_update_by_name: student_updater_name,
                 collection_id: row.collection_id,
                 collection_name: row.collection_name,
+                review_requested_at: row.review_requested_at.map(naive_to_utc),
                 tags: Vec::new(),
+                media: media.remove(&library_id).unwrap_or_default(),
                 attempt_count: row.attempt_count,
                 last_attempt_at: row.last_attempt_at.map(naive_to_utc),
                 viewer_seen_at: row.viewer_seen_at.map(naive_to_utc),
+                student_seen_at: row.student_seen_at.map(naive_to_utc),
             };
-            e.insert(technique);
+            techniques.push(technique);
         }
 
-        if let (Some(tag_id), Some(tag_name)) = (row.tag_id, row.tag_name) {
-            let tag = Tag {
+        if let (Some(technique), Some(tag_id), Some(tag_name)) =
+            (techniques.last_mut(), row.tag_id, row.tag_name)
+        {
+            technique.tags.push(Tag {
                 id: tag_id,
                 name: tag_name,
-            };
-
-            let technique = techniques_map.get_mut(&technique_id).unwrap();
-            if !technique.tags.iter().any(|t| t.id == tag_id) {
-                technique.tags.push(tag);
-            }
+                archived: row.tag_archived.unwrap_or_default(),
+                category: row.tag_category,
+                color: row.tag_color,
+                parent_id: row.tag_parent_id,
+            });
         }
     }
 
-    for technique in techniques_map.values_mut() {
-        technique.tags.sort_by(|a, b| a.name.cmp(&b.name));
-    }
-
-    let mut techniques: Vec<StudentTechnique> = techniques_map.into_values().collect();
-    techniques.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-
     Ok(techniques)
 }
 
 #[instrument]
 pub async fn get_student_technique(
     pool: &Pool<Sqlite>,
-    student_technique_id: i64,
-    viewer_id: i64,
+    student_technique_id: StudentTechniqueId,
+    viewer_id: UserId,
+) -> Result<StudentTechnique, AppError> {
+    let mut conn = pool.acquire().await?;
+    get_student_technique_in(&mut conn, student_technique_id, viewer_id).await
+}
+
+/// `get_student_technique` on the caller's connection, so a handler holding
+/// a `Tx` can read back what it has written before the transaction commits.
+#[instrument(skip(conn))]
+pub async fn get_student_technique_in(
+    conn: &mut SqliteConnection,
+    student_technique_id: StudentTechniqueId,
+    viewer_id: UserId,
 ) -> Result<StudentTechnique, AppError> {
     info!("Getting student technique with tags");
 
     let row = sqlx::query_as!(
         DbStudentTechnique,
         "SELECT * FROM student_techniques WHERE id = ?",
-        student_technique_id
+        student_technique_id.0
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
-    let mut technique = StudentTechnique::from(row.clone());
+    let mut technique = StudentTechnique::try_from(row.clone())?;
 
     if let Some(technique_id) = row.technique_id {
         let tags = sqlx::query_as!(
             DbTag,
-            "SELECT t.id, t.name
+            "SELECT t.id, t.name, t.archived, t.category, t.color, t.parent_id
              FROM tags t
              JOIN technique_tags tt ON t.id = tt.tag_id
              WHERE tt.technique_id = ?
              ORDER BY t.name",
             technique_id
         )
-        .fetch_all(pool)
+        .fetch_all(&mut *conn)
         .await?;
 
         technique.tags = tags.into_iter().map(Tag::from).collect();
+        technique.media = get_technique_media(&mut *conn, TechniqueId(technique_id)).await?;
     }
 
     let agg = sqlx::query!(
@@ -222,9 +285,9 @@ pub async fn get_student_technique(
                   MAX(attempted_at) as "last?: NaiveDateTime"
            FROM attempts
            WHERE student_technique_id = ?"#,
-        student_technique_id
+        student_technique_id.0
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
     technique.attempt_count = agg.count;
     technique.last_attempt_at = agg.last.map(naive_to_utc);
@@ -233,35 +296,141 @@ pub async fn get_student_technique(
         r#"SELECT seen_at as "seen_at?: NaiveDateTime"
            FROM student_technique_views
            WHERE student_technique_id = ? AND user_id = ?"#,
-        student_technique_id,
-        viewer_id
+        student_technique_id.0,
+        viewer_id.0
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?;
     technique.viewer_seen_at = seen.and_then(|r| r.seen_at).map(naive_to_utc);
+    technique.student_seen_at = if viewer_id == technique.student_id {
+        technique.viewer_seen_at
+    } else {
+        let seen = sqlx::query!(
+            r#"SELECT seen_at as "seen_at?: NaiveDateTime"
+               FROM student_technique_views
+               WHERE student_technique_id = ? AND user_id = ?"#,
+            student_technique_id.0,
+            technique.student_id.0
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        seen.and_then(|r| r.seen_at).map(naive_to_utc)
+    };
 
     Ok(technique)
 }
 
-#[instrument(skip(actor))]
+/// A field an update changed, as reported back to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StudentTechniqueField {
+    Status,
+    StudentNotes,
+    CoachNotes,
+    TechniqueName,
+    TechniqueDescription,
+}
+
+impl StudentTechniqueField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StudentTechniqueField::Status => "status",
+            StudentTechniqueField::StudentNotes => "student_notes",
+            StudentTechniqueField::CoachNotes => "coach_notes",
+            StudentTechniqueField::TechniqueName => "technique_name",
+            StudentTechniqueField::TechniqueDescription => "technique_description",
+        }
+    }
+
+    pub(crate) fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "status" => Ok(StudentTechniqueField::Status),
+            "student_notes" => Ok(StudentTechniqueField::StudentNotes),
+            "coach_notes" => Ok(StudentTechniqueField::CoachNotes),
+            "technique_name" => Ok(StudentTechniqueField::TechniqueName),
+            "technique_description" => Ok(StudentTechniqueField::TechniqueDescription),
+            other => Err(AppError::Internal(format!("Unknown field '{}'", other))),
+        }
+    }
+}
+
+/// Which of `status` and the notes differ from what is stored; a missing
+/// row reports nothing changed.
+async fn changed_fields(
+    conn: &mut SqliteConnection,
+    id: StudentTechniqueId,
+    status: Option<&str>,
+    student_notes: Option<&str>,
+    coach_notes: Option<&str>,
+) -> Result<Vec<StudentTechniqueField>, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT ? IS NOT NULL AND COALESCE(status, 'red') IS NOT ? AS "status!: bool",
+                  ? IS NOT NULL AND COALESCE(student_notes, '') IS NOT ? AS "student_notes!: bool",
+                  ? IS NOT NULL AND COALESCE(coach_notes, '') IS NOT ? AS "coach_notes!: bool"
+           FROM student_techniques
+           WHERE id = ?"#,
+        status,
+        status,
+        student_notes,
+        student_notes,
+        coach_notes,
+        coach_notes,
+        id.0
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(Vec::new());
+    };
+    Ok([
+        (row.status, StudentTechniqueField::Status),
+        (row.student_notes, StudentTechniqueField::StudentNotes),
+        (row.coach_notes, StudentTechniqueField::CoachNotes),
+    ]
+    .into_iter()
+    .filter_map(|(changed, field)| changed.then_some(field))
+    .collect())
+}
+
+/// Saves the status and both notes. Only touches the row (and its
+/// last-update stamps) when something differs; returns what did.
+#[instrument(skip(conn, actor))]
 pub async fn update_student_technique(
-    pool: &Pool<Sqlite>,
-    id: i64,
+    conn: &mut SqliteConnection,
+    id: StudentTechniqueId,
     actor: &User,
     status: &str,
     student_notes: &str,
     coach_notes: &str,
-) -> Result<(), AppError> {
+) -> Result<Vec<StudentTechniqueField>, AppError> {
     info!("Updating student technique");
     let now = Utc::now().naive_utc();
-    let actor_id = actor.id;
+    let actor_id = actor.id.0;
+    let mut tx = conn.begin().await?;
+    let changed =
+        changed_fields(&mut tx, id, Some(status), Some(student_notes), Some(coach_notes)).await?;
+    if changed.is_empty() {
+        return Ok(changed);
+    }
+    record_note_revisions(&mut tx, id, actor.id, now, Some(student_notes), Some(coach_notes))
+        .await?;
+    for field in &changed {
+        let value = match field {
+            StudentTechniqueField::Status => status,
+            StudentTechniqueField::StudentNotes => student_notes,
+            _ => coach_notes,
+        };
+        record_revision(&mut tx, id, actor.id, now, *field, value).await?;
+    }
 
     match actor.role {
         Role::Coach | Role::Admin => {
             sqlx::query!(
                 "UPDATE student_techniques
                  SET status = ?, student_notes = ?, coach_notes = ?, updated_at = ?,
-                     last_coach_update_at = ?, last_coach_update_by_id = ?
+                     last_coach_update_at = ?, last_coach_update_by_id = ?,
+                     review_requested_at = CASE WHEN status = ? THEN review_requested_at END
                  WHERE id = ?",
                 status,
                 student_notes,
@@ -269,9 +438,10 @@ pub async fn update_student_technique(
                 now,
                 now,
                 actor_id,
-                id
+                status,
+                id.0
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
         }
         Role::Student => {
@@ -286,26 +456,62 @@ pub async fn update_student_technique(
                 now,
                 now,
                 actor_id,
-                id
+                id.0
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
         }
     }
+    if changed.contains(&StudentTechniqueField::CoachNotes) {
+        notify_student(&mut *tx, NotificationKind::CoachNote, id, actor.id).await?;
+    }
 
+    tx.commit().await?;
+    Ok(changed)
+}
+
+/// Hides the coach notes from the student, or shows them again. Not a
+/// change to the notes themselves, so nothing is stamped or recorded.
+#[instrument(skip(executor))]
+pub async fn set_coach_notes_private(
+    executor: impl SqliteExecutor<'_>,
+    id: StudentTechniqueId,
+    private: bool,
+) -> Result<(), AppError> {
+    info!("Setting coach notes visibility");
+    let res = sqlx::query!(
+        "UPDATE student_techniques SET coach_notes_private = ? WHERE id = ?",
+        private,
+        id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Student technique {} not found", id)));
+    }
     Ok(())
 }
 
-#[instrument(skip(actor))]
+/// Like `update_student_technique`, for the student notes alone.
+#[instrument(skip(conn, actor))]
 pub async fn update_student_notes(
-    pool: &Pool<Sqlite>,
-    id: i64,
+    conn: &mut SqliteConnection,
+    id: StudentTechniqueId,
     actor: &User,
     student_notes: &str,
-) -> Result<(), AppError> {
+) -> Result<Vec<StudentTechniqueField>, AppError> {
     info!("Updating student notes");
     let now = Utc::now().naive_utc();
-    let actor_id = actor.id;
+    let actor_id = actor.id.0;
+    let mut tx = conn.begin().await?;
+    let changed = changed_fields(&mut tx, id, None, Some(student_notes), None).await?;
+    if changed.is_empty() {
+        return Ok(changed);
+    }
+    record_note_revisions(&mut tx, id, actor.id, now, Some(student_notes), None).await?;
+    record_revision(&mut tx, id, actor.id, now, StudentTechniqueField::StudentNotes, student_notes)
+        .await?;
 
     match actor.role {
         Role::Coach | Role::Admin => {
@@ -318,9 +524,9 @@ pub async fn update_student_notes(
                 now,
                 now,
                 actor_id,
-                id
+                id.0
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
         }
         Role::Student => {
@@ -333,86 +539,214 @@ pub async fn update_student_notes(
                 now,
                 now,
                 actor_id,
-                id
+                id.0
             )
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
         }
     }
 
-    Ok(())
+    tx.commit().await?;
+    Ok(changed)
+}
+
+/// Flags the technique as ready for a coach to review. Asking again while a
+/// request is pending keeps the original time. Returns whether this call set
+/// it.
+#[instrument(skip(executor))]
+pub async fn request_review(
+    executor: impl SqliteExecutor<'_>,
+    id: StudentTechniqueId,
+) -> Result<bool, AppError> {
+    let now = Utc::now().naive_utc();
+    let res = sqlx::query!(
+        "UPDATE student_techniques SET review_requested_at = ?
+         WHERE id = ? AND review_requested_at IS NULL",
+        now,
+        id.0
+    )
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+/// Which of a student's techniques a bulk status change applies to. Unset
+/// fields don't narrow the selection; `tag_id` takes in its descendants.
+#[derive(Debug, Default)]
+pub struct StatusFilter {
+    pub tag_id: Option<TagId>,
+    pub status: Option<String>,
+}
+
+/// The distinct statuses held by `student_id`'s techniques that match
+/// `filter`, so a bulk change can be checked against the transition rules
+/// before it runs.
+#[instrument(skip(executor))]
+pub async fn get_matching_statuses(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+    filter: &StatusFilter,
+) -> Result<Vec<String>, AppError> {
+    let tag_id = filter.tag_id.map(|id| id.0);
+    let statuses = sqlx::query_scalar!(
+        r#"SELECT DISTINCT COALESCE(status, 'red') AS "status!: String"
+           FROM student_techniques
+           WHERE student_id = ?
+             AND (? IS NULL OR COALESCE(status, 'red') = ?)
+             AND (? IS NULL OR technique_id IN
+                    (WITH RECURSIVE subtree(id) AS (
+                         SELECT ?
+                         UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+                     SELECT technique_id FROM technique_tags WHERE tag_id IN subtree))
+           ORDER BY 1"#,
+        student_id.0,
+        filter.status,
+        filter.status,
+        tag_id,
+        tag_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(statuses)
+}
+
+/// Moves every technique of `student_id` that matches `filter` to `status`
+/// in one transaction, as a coach update, recording a revision for each.
+/// Techniques already there are left alone. Returns how many changed.
+#[instrument(skip(pool, actor))]
+pub async fn bulk_update_status(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+    actor: &User,
+    filter: &StatusFilter,
+    status: &str,
+) -> Result<u64, AppError> {
+    let now = Utc::now().naive_utc();
+    let tag_id = filter.tag_id.map(|id| id.0);
+    let mut tx = pool.begin().await?;
+    // The same selection as the update below, read before it runs.
+    sqlx::query!(
+        "INSERT INTO student_technique_revisions
+             (student_technique_id, field, old_value, new_value, changed_at, changed_by_id)
+         SELECT id, 'status', COALESCE(status, 'red'), ?, ?, ?
+         FROM student_techniques
+         WHERE student_id = ? AND COALESCE(status, 'red') != ?
+           AND (? IS NULL OR COALESCE(status, 'red') = ?)
+           AND (? IS NULL OR technique_id IN
+                  (WITH RECURSIVE subtree(id) AS (
+                       SELECT ?
+                       UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+                   SELECT technique_id FROM technique_tags WHERE tag_id IN subtree))",
+        status,
+        now,
+        actor.id.0,
+        student_id.0,
+        status,
+        filter.status,
+        filter.status,
+        tag_id,
+        tag_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let res = sqlx::query!(
+        "UPDATE student_techniques
+         SET status = ?, updated_at = ?, last_coach_update_at = ?, last_coach_update_by_id = ?,
+             review_requested_at = NULL
+         WHERE student_id = ? AND COALESCE(status, 'red') != ?
+           AND (? IS NULL OR COALESCE(status, 'red') = ?)
+           AND (? IS NULL OR technique_id IN
+                  (WITH RECURSIVE subtree(id) AS (
+                       SELECT ?
+                       UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+                   SELECT technique_id FROM technique_tags WHERE tag_id IN subtree))",
+        status,
+        now,
+        now,
+        actor.id.0,
+        student_id.0,
+        status,
+        filter.status,
+        filter.status,
+        tag_id,
+        tag_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!(updated = res.rows_affected(), "Bulk status change");
+    Ok(res.rows_affected())
 }
 
 #[instrument]
 pub async fn get_unassigned_techniques(
     pool: &Pool<Sqlite>,
-    student_id: i64,
+    student_id: UserId,
 ) -> Result<Vec<Technique>, AppError> {
     info!("Getting unassigned techniques with tags");
 
     let rows = sqlx::query!(
         r#"
-        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name,
-               tag.id as tag_id, tag.name as tag_name
+        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name, t.parent_technique_id,
+               tag.id as tag_id, tag.name as tag_name, tag.archived as "tag_archived?: bool",
+               tag.category as tag_category, tag.color as tag_color,
+               tag.parent_id as tag_parent_id
         FROM techniques t
         LEFT JOIN technique_tags tt ON t.id = tt.technique_id
         LEFT JOIN tags tag ON tt.tag_id = tag.id
-        WHERE t.id NOT IN (
+        WHERE t.deleted_at IS NULL
+          AND t.id NOT IN (
             SELECT technique_id FROM student_techniques
             WHERE student_id = ?
         )
-        ORDER BY t.name
+        ORDER BY t.name, t.id, tag.name, tag.id
         "#,
-        student_id
+        student_id.0
     )
     .fetch_all(pool)
     .await?;
 
-    let mut techniques_map: HashMap<i64, Technique> = HashMap::new();
-
+    // Grouped in turn, as in `get_all_techniques`.
+    let mut techniques: Vec<Technique> = Vec::new();
     for row in rows {
-        let technique_id = row.id;
-
-        if let Entry::Vacant(e) = techniques_map.entry(technique_id) {
-            let technique = Technique {
-                id: technique_id,
+        if techniques.last().is_none_or(|technique| technique.id.0 != row.id) {
+            techniques.push(Technique {
+                id: TechniqueId(row.id),
                 name: row.name,
                 description: row.description.unwrap_or_default(),
-                coach_id: row.coach_id.unwrap_or_default(),
+                coach_id: UserId(row.coach_id.unwrap_or_default()),
                 coach_name: row.coach_name.unwrap_or_default(),
+                parent_technique_id: row.parent_technique_id.map(TechniqueId),
                 tags: Vec::new(),
-            };
-            e.insert(technique);
+                aliases: Vec::new(),
+            });
         }
-
-        if let (tag_id, Some(tag_name)) = (row.tag_id, row.tag_name) {
-            let tag = Tag {
-                id: tag_id,
+        if let (Some(technique), Some(tag_name)) = (techniques.last_mut(), row.tag_name) {
+            technique.tags.push(Tag {
+                id: row.tag_id,
                 name: tag_name,
-            };
-
-            let technique = techniques_map.get_mut(&technique_id).unwrap();
-            if !technique.tags.iter().any(|t| t.id == tag_id) {
-                technique.tags.push(tag);
-            }
+                archived: row.tag_archived.unwrap_or_default(),
+                category: row.tag_category,
+                color: row.tag_color,
+                parent_id: row.tag_parent_id,
+            });
         }
     }
 
-    for technique in techniques_map.values_mut() {
-        technique.tags.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut aliases = get_aliases_by_technique(pool).await?;
+    for technique in &mut techniques {
+        technique.aliases = aliases.remove(&technique.id.0).unwrap_or_default();
     }
-
-    let techniques: Vec<Technique> = techniques_map.into_values().collect();
     Ok(techniques)
 }
 
 #[instrument]
 pub async fn add_techniques_to_student(
     pool: &Pool<Sqlite>,
-    student_id: i64,
-    technique_ids: Vec<i64>,
+    student_id: UserId,
+    technique_ids: Vec<TechniqueId>,
     collection_id: Option<i64>,
-    actor_id: i64,
+    actor_id: UserId,
 ) -> Result<(), AppError> {
     info!("Adding techniques to student");
     for technique_id in technique_ids {
@@ -423,14 +757,207 @@ pub async fn add_techniques_to_student(
     Ok(())
 }
 
+/// What `assign_group_to_student` assigns: every technique carrying a tag
+/// or one nested under it, or every technique in a curriculum.
+#[derive(Debug, Clone, Copy)]
+pub enum TechniqueGroup {
+    Tag(TagId),
+    Collection(i64),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GroupAssignment {
+    pub created: u64,
+    /// Techniques in the group the student already had, left as they were.
+    pub skipped: u64,
+}
+
+/// Assigns every technique in `group` the student doesn't already have, in
+/// one transaction. New assignments from a curriculum are filed under it.
+/// Deleted techniques are left out.
+#[instrument(skip(pool))]
+pub async fn assign_group_to_student(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+    group: TechniqueGroup,
+    actor_id: UserId,
+) -> Result<GroupAssignment, AppError> {
+    info!("Assigning technique group to student");
+    let mut tx = pool.begin().await?;
+
+    let (technique_ids, collection_id) = match group {
+        TechniqueGroup::Tag(tag_id) => {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM tags WHERE id = ?) AS "exists!: bool""#,
+                tag_id.0
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !exists {
+                return Err(AppError::NotFound(format!("Tag {} not found", tag_id)));
+            }
+            let ids = sqlx::query_scalar!(
+                r#"WITH RECURSIVE subtree(id) AS (
+                       SELECT ?
+                       UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+                   SELECT t.id AS "id!: i64"
+                   FROM techniques t
+                   WHERE t.deleted_at IS NULL
+                     AND t.id IN (SELECT technique_id FROM technique_tags WHERE tag_id IN subtree)
+                   ORDER BY t.name, t.id"#,
+                tag_id.0
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            (ids, None)
+        }
+        TechniqueGroup::Collection(collection_id) => {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM collections WHERE id = ?) AS "exists!: bool""#,
+                collection_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !exists {
+                return Err(AppError::NotFound(format!("Collection {} not found", collection_id)));
+            }
+            let ids = sqlx::query_scalar!(
+                "SELECT ct.technique_id
+                 FROM collection_techniques ct
+                 JOIN techniques t ON t.id = ct.technique_id
+                 WHERE ct.collection_id = ? AND t.deleted_at IS NULL
+                 ORDER BY ct.position",
+                collection_id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            (ids, Some(collection_id))
+        }
+    };
+
+    // As in `assign_technique_to_student`, the assignment counts as a coach
+    // update.
+    let now = Utc::now().naive_utc();
+    let mut created = 0;
+    for &technique_id in &technique_ids {
+        let res = sqlx::query!(
+            "INSERT INTO student_techniques
+                 (student_id, student_notes, coach_notes, technique_id, technique_name,
+                  technique_description, collection_id, last_coach_update_at,
+                  last_coach_update_by_id, assigned_by_id)
+             SELECT ?, '', '', t.id, t.name, t.description, ?, ?, ?, ?
+             FROM techniques t
+             WHERE t.id = ?
+               AND NOT EXISTS (SELECT 1 FROM student_techniques
+                               WHERE student_id = ? AND technique_id = t.id)",
+            student_id.0,
+            collection_id,
+            now,
+            actor_id.0,
+            actor_id.0,
+            technique_id,
+            student_id.0
+        )
+        .execute(&mut *tx)
+        .await?;
+        if res.rows_affected() > 0 {
+            created += 1;
+            let id = StudentTechniqueId(res.last_insert_rowid());
+            notify_student(&mut *tx, NotificationKind::Assignment, id, actor_id).await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(GroupAssignment { created, skipped: technique_ids.len() as u64 - created })
+}
+
+/// One assigned technique as written to a CSV export.
+#[derive(Debug, Clone)]
+pub struct StudentTechniqueExportRow {
+    pub id: i64,
+    pub technique_name: String,
+    pub status: String,
+    pub student_notes: String,
+    pub coach_notes: String,
+    pub coach_notes_private: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub last_coach_update_at: Option<NaiveDateTime>,
+    pub last_student_update_at: Option<NaiveDateTime>,
+}
+
+/// Up to `limit` of a student's techniques with ids above `after_id`, in id
+/// (assignment) order. The export streams through a syllabus a batch at a
+/// time, passing the last id it saw.
+#[instrument(skip(executor))]
+pub async fn get_student_techniques_for_export(
+    executor: impl SqliteExecutor<'_>,
+    student_id: UserId,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<StudentTechniqueExportRow>, AppError> {
+    let rows = sqlx::query_as!(
+        StudentTechniqueExportRow,
+        r#"SELECT id AS "id!: i64",
+                  COALESCE(technique_name, '') AS "technique_name!: String",
+                  COALESCE(status, 'red') AS "status!: String",
+                  COALESCE(student_notes, '') AS "student_notes!: String",
+                  COALESCE(coach_notes, '') AS "coach_notes!: String", coach_notes_private,
+                  created_at, updated_at, last_coach_update_at, last_student_update_at
+           FROM student_techniques
+           WHERE student_id = ? AND id > ?
+           ORDER BY id
+           LIMIT ?"#,
+        student_id.0,
+        after_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows)
+}
+
+/// Unassigns techniques from a student, all or none: if any id isn't one of
+/// the student's techniques nothing is removed. Notes, attempts and views go
+/// with each row (ON DELETE CASCADE). Returns the number removed.
+#[instrument(skip(pool))]
+pub async fn remove_techniques_from_student(
+    pool: &Pool<Sqlite>,
+    student_id: UserId,
+    student_technique_ids: &[StudentTechniqueId],
+) -> Result<u64, AppError> {
+    info!("Removing techniques from student");
+    let mut tx = pool.begin().await?;
+    let mut removed = 0;
+    for id in student_technique_ids {
+        let res = sqlx::query!(
+            "DELETE FROM student_techniques WHERE id = ? AND student_id = ?",
+            id.0,
+            student_id.0
+        )
+        .execute(&mut *tx)
+        .await?;
+        if res.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Student technique {} not found for student {}",
+                id, student_id
+            )));
+        }
+        removed += res.rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(removed)
+}
+
 /// Upsert the `seen_at` for `(student_technique_id, user_id)` to NOW. Used by
 /// the row-expand "mark seen" interaction to clear the unseen-activity dot
 /// for the viewer.
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn mark_student_technique_seen(
-    pool: &Pool<Sqlite>,
-    student_technique_id: i64,
-    user_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    student_technique_id: StudentTechniqueId,
+    user_id: UserId,
 ) -> Result<(), AppError> {
     let now = Utc::now().naive_utc();
     sqlx::query!(
@@ -438,12 +965,42 @@ pub async fn mark_student_technique_seen(
          VALUES (?, ?, ?)
          ON CONFLICT(student_technique_id, user_id)
          DO UPDATE SET seen_at = excluded.seen_at",
-        student_technique_id,
-        user_id,
+        student_technique_id.0,
+        user_id.0,
         now
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
+
+/// Rewrites `last_student_update_at` / `last_coach_update_at` values that an
+/// older build stored as RFC3339 with an offset (`2026-05-31T10:00:00+00:00`)
+/// into the naive UTC form every other TIMESTAMP uses, so plain TEXT
+/// comparisons against `seen_at` order correctly. Returns rows rewritten.
+#[instrument(skip(executor))]
+pub async fn normalize_legacy_update_timestamps(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"UPDATE student_techniques
+           SET last_student_update_at = CASE
+                   WHEN last_student_update_at LIKE '%T%' THEN datetime(last_student_update_at)
+                   ELSE last_student_update_at
+               END,
+               last_coach_update_at = CASE
+                   WHEN last_coach_update_at LIKE '%T%' THEN datetime(last_coach_update_at)
+                   ELSE last_coach_update_at
+               END
+           WHERE last_student_update_at LIKE '%T%' OR last_coach_update_at LIKE '%T%'"#
+    )
+    .execute(executor)
+    .await?;
+
+    let rewritten = result.rows_affected();
+    if rewritten > 0 {
+        info!(rewritten, "Normalized legacy student technique timestamps");
+    }
+    Ok(rewritten)
+}
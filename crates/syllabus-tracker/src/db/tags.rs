@@ -1,123 +1,379 @@
-use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::{info, instrument};
 
+use super::{index_technique, tagged_technique_ids};
 use crate::error::AppError;
+use crate::ids::{TagId, TechniqueId};
 use crate::models::{DbTag, DbTechnique, Tag, Technique};
+use crate::validation::normalize_tag_name;
 
-#[instrument]
-pub async fn create_tag(pool: &Pool<Sqlite>, name: &str) -> Result<i64, AppError> {
+#[instrument(skip(executor))]
+pub async fn create_tag(executor: impl SqliteExecutor<'_>, name: &str) -> Result<TagId, AppError> {
     info!("Creating tag");
+    let name = normalize_tag_name(name);
     let res = sqlx::query!("INSERT INTO tags (name) VALUES (?)", name)
-        .execute(pool)
+        .execute(executor)
         .await?;
-    Ok(res.last_insert_rowid())
+    Ok(TagId(res.last_insert_rowid()))
 }
 
-#[instrument]
-pub async fn get_all_tags(pool: &Pool<Sqlite>) -> Result<Vec<Tag>, AppError> {
-    info!("Getting all tags");
-    let rows = sqlx::query_as!(DbTag, "SELECT id, name FROM tags ORDER BY name")
-        .fetch_all(pool)
+#[instrument(skip(pool))]
+pub async fn rename_tag(pool: &Pool<Sqlite>, tag_id: TagId, name: &str) -> Result<(), AppError> {
+    info!("Renaming tag");
+    let name = normalize_tag_name(name);
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!("UPDATE tags SET name = ? WHERE id = ?", name, tag_id.0)
+        .execute(&mut *tx)
         .await?;
 
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Tag {} not found", tag_id)));
+    }
+
+    for technique_id in tagged_technique_ids(&mut tx, tag_id).await? {
+        index_technique(&mut tx, technique_id).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Sets how a tag is grouped and coloured. `None` leaves that field as it
+/// is; `Some(None)` clears it.
+#[instrument(skip(executor))]
+pub async fn update_tag_style(
+    executor: impl SqliteExecutor<'_>,
+    tag_id: TagId,
+    category: Option<Option<&str>>,
+    color: Option<Option<&str>>,
+) -> Result<(), AppError> {
+    info!("Updating tag style");
+    let (set_category, category) = (category.is_some(), category.flatten());
+    let (set_color, color) = (color.is_some(), color.flatten());
+    let res = sqlx::query!(
+        "UPDATE tags
+         SET category = CASE WHEN ? THEN ? ELSE category END,
+             color = CASE WHEN ? THEN ? ELSE color END
+         WHERE id = ?",
+        set_category,
+        category,
+        set_color,
+        color,
+        tag_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Tag {} not found", tag_id)));
+    }
+    Ok(())
+}
+
+/// Moves a tag under `parent_id`, or to the top level. Callers check the
+/// move doesn't make a cycle (see [`get_tag_subtree_ids`]).
+#[instrument(skip(executor))]
+pub async fn set_tag_parent(
+    executor: impl SqliteExecutor<'_>,
+    tag_id: TagId,
+    parent_id: Option<TagId>,
+) -> Result<(), AppError> {
+    info!("Setting tag parent");
+    let parent_id = parent_id.map(|id| id.0);
+    let res = sqlx::query!(
+        "UPDATE tags SET parent_id = ?
+         WHERE id = ? AND (? IS NULL OR EXISTS(SELECT 1 FROM tags WHERE id = ?))",
+        parent_id,
+        tag_id.0,
+        parent_id,
+        parent_id
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Tag {} or its parent not found", tag_id)));
+    }
+    Ok(())
+}
+
+/// `tag_id` and every tag nested under it.
+#[instrument(skip(executor))]
+pub async fn get_tag_subtree_ids(
+    executor: impl SqliteExecutor<'_>,
+    tag_id: TagId,
+) -> Result<Vec<TagId>, AppError> {
+    let ids = sqlx::query_scalar!(
+        r#"WITH RECURSIVE subtree(id) AS (
+               SELECT ?
+               UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+           SELECT id AS "id!: i64" FROM subtree"#,
+        tag_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(ids.into_iter().map(TagId).collect())
+}
+
+/// A tag with the tags nested under it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagNode {
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// By name.
+    pub children: Vec<TagNode>,
+}
+
+/// Every tag as a forest, top-level tags first by name. A tag whose parent
+/// is left out (archived, when `include_archived` is false) shows at the top.
+#[instrument(skip(executor))]
+pub async fn get_tag_tree(
+    executor: impl SqliteExecutor<'_>,
+    include_archived: bool,
+) -> Result<Vec<TagNode>, AppError> {
+    let tags = get_all_tags(executor, include_archived).await?;
+    let ids: HashSet<i64> = tags.iter().map(|tag| tag.id).collect();
+    let mut children: HashMap<Option<i64>, Vec<Tag>> = HashMap::new();
+    for tag in tags {
+        let parent = tag.parent_id.filter(|id| ids.contains(id));
+        children.entry(parent).or_default().push(tag);
+    }
+
+    fn build(parent: Option<i64>, children: &mut HashMap<Option<i64>, Vec<Tag>>) -> Vec<TagNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| TagNode { children: build(Some(tag.id), children), tag })
+            .collect()
+    }
+    Ok(build(None, &mut children))
+}
+
+/// Archived tags are left out unless `include_archived`, as pickers only
+/// offer live ones.
+#[instrument(skip(executor))]
+pub async fn get_all_tags(
+    executor: impl SqliteExecutor<'_>,
+    include_archived: bool,
+) -> Result<Vec<Tag>, AppError> {
+    info!("Getting all tags");
+    let rows = sqlx::query_as!(
+        DbTag,
+        "SELECT id, name, archived, category, color, parent_id FROM tags
+         WHERE archived = FALSE OR ?
+         ORDER BY name",
+        include_archived
+    )
+    .fetch_all(executor)
+    .await?;
+
     Ok(rows.into_iter().map(Tag::from).collect())
 }
 
-#[instrument]
+/// Hides a tag from pickers without touching the techniques that carry it,
+/// unlike [`delete_tag`].
+#[instrument(skip(executor))]
+pub async fn set_tag_archived(
+    executor: impl SqliteExecutor<'_>,
+    tag_id: TagId,
+    archived: bool,
+) -> Result<(), AppError> {
+    info!("Setting tag archived");
+    let res = sqlx::query!("UPDATE tags SET archived = ? WHERE id = ?", archived, tag_id.0)
+        .execute(executor)
+        .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Tag {} not found", tag_id)));
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(executor))]
 pub async fn get_tags_for_technique(
-    pool: &Pool<Sqlite>,
-    technique_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
 ) -> Result<Vec<Tag>, AppError> {
     info!("Getting tags for technique");
     let rows = sqlx::query_as!(
         DbTag,
-        "SELECT t.id, t.name
+        "SELECT t.id, t.name, t.archived, t.category, t.color, t.parent_id
          FROM tags t
          JOIN technique_tags tt ON t.id = tt.tag_id
          WHERE tt.technique_id = ?
          ORDER BY t.name",
-        technique_id
+        technique_id.0
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows.into_iter().map(Tag::from).collect())
 }
 
-#[instrument]
+#[instrument(skip(pool))]
 pub async fn add_tag_to_technique(
     pool: &Pool<Sqlite>,
-    technique_id: i64,
-    tag_id: i64,
+    technique_id: TechniqueId,
+    tag_id: TagId,
 ) -> Result<(), AppError> {
     info!("Adding tag to technique");
+    let mut tx = pool.begin().await?;
     sqlx::query!(
         "INSERT OR IGNORE INTO technique_tags (technique_id, tag_id) VALUES (?, ?)",
-        technique_id,
-        tag_id
+        technique_id.0,
+        tag_id.0
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-#[instrument]
+#[instrument(skip(pool))]
 pub async fn remove_tag_from_technique(
     pool: &Pool<Sqlite>,
-    technique_id: i64,
-    tag_id: i64,
+    technique_id: TechniqueId,
+    tag_id: TagId,
 ) -> Result<(), AppError> {
     info!("Removing tag from technique");
+    let mut tx = pool.begin().await?;
     sqlx::query!(
         "DELETE FROM technique_tags WHERE technique_id = ? AND tag_id = ?",
-        technique_id,
-        tag_id
+        technique_id.0,
+        tag_id.0
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-#[instrument]
-pub async fn delete_tag(pool: &Pool<Sqlite>, tag_id: i64) -> Result<(), AppError> {
+#[instrument(skip(pool))]
+pub async fn delete_tag(pool: &Pool<Sqlite>, tag_id: TagId) -> Result<(), AppError> {
     info!("Deleting tag");
+    let mut tx = pool.begin().await?;
+    let tagged = tagged_technique_ids(&mut tx, tag_id).await?;
     // technique_tags rows are cleaned up by the ON DELETE CASCADE constraint.
-    sqlx::query!("DELETE FROM tags WHERE id = ?", tag_id)
-        .execute(pool)
+    sqlx::query!("DELETE FROM tags WHERE id = ?", tag_id.0)
+        .execute(&mut *tx)
         .await?;
 
+    for technique_id in tagged {
+        index_technique(&mut tx, technique_id).await?;
+    }
+    tx.commit().await?;
     Ok(())
 }
 
-#[instrument]
-pub async fn get_tag_by_name(pool: &Pool<Sqlite>, name: &str) -> Result<Option<Tag>, AppError> {
+#[instrument(skip(executor))]
+pub async fn get_tag_by_name(
+    executor: impl SqliteExecutor<'_>,
+    name: &str,
+) -> Result<Option<Tag>, AppError> {
     info!("Getting tag by name");
-    let row = sqlx::query_as!(DbTag, "SELECT id, name FROM tags WHERE name = ?", name)
-        .fetch_optional(pool)
-        .await?;
+    let name = normalize_tag_name(name);
+    let row = sqlx::query_as!(
+        DbTag,
+        "SELECT id, name, archived, category, color, parent_id FROM tags
+         WHERE name = ? COLLATE NOCASE",
+        name
+    )
+    .fetch_optional(executor)
+    .await?;
 
     Ok(row.map(Tag::from))
 }
 
-#[instrument]
+/// Techniques carrying `tag_id` or any tag nested under it.
+#[instrument(skip(executor))]
 pub async fn get_techniques_by_tag(
-    pool: &Pool<Sqlite>,
-    tag_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    tag_id: TagId,
 ) -> Result<Vec<Technique>, AppError> {
     info!("Getting techniques by tag");
     let rows = sqlx::query_as!(
         DbTechnique,
-        "SELECT t.*
+        "WITH RECURSIVE subtree(id) AS (
+             SELECT ?
+             UNION SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id)
+         SELECT t.id, t.name, t.description, t.coach_id, t.coach_name, t.parent_technique_id
          FROM techniques t
-         JOIN technique_tags tt ON t.id = tt.technique_id
-         WHERE tt.tag_id = ?
-         ORDER BY t.name",
-        tag_id
+         WHERE t.deleted_at IS NULL
+           AND t.id IN (SELECT technique_id FROM technique_tags WHERE tag_id IN subtree)
+         ORDER BY t.name, t.id",
+        tag_id.0
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
 
     Ok(rows.into_iter().map(Technique::from).collect())
 }
+
+/// Backfill for rows written before tag names were normalised. Renames each
+/// tag to its canonical form; where two tags collapse to names that differ
+/// only in case, the later one's technique links move to the survivor and it
+/// is deleted. Returns how many tags were renamed or merged. Idempotent.
+#[instrument]
+pub async fn normalize_existing_tag_names(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let mut tags: Vec<Tag> = sqlx::query_as!(
+        DbTag,
+        "SELECT id, name, archived, category, color, parent_id FROM tags ORDER BY name"
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(Tag::from)
+    .collect();
+
+    // Tags already in canonical form go first so they keep their ids and
+    // the renames below never hit the UNIQUE constraint.
+    tags.sort_by_key(|tag| (normalize_tag_name(&tag.name) != tag.name, tag.id));
+
+    // Keyed the way `COLLATE NOCASE` compares: ASCII letters case-folded.
+    let mut survivors: HashMap<String, i64> = HashMap::new();
+    let mut changed = 0;
+    for tag in tags {
+        let normalized = normalize_tag_name(&tag.name);
+        let key = normalized.to_ascii_lowercase();
+        if let Some(&survivor_id) = survivors.get(&key) {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO technique_tags (technique_id, tag_id)
+                 SELECT technique_id, ? FROM technique_tags WHERE tag_id = ?",
+                survivor_id,
+                tag.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!("DELETE FROM tags WHERE id = ?", tag.id)
+                .execute(&mut *tx)
+                .await?;
+            changed += 1;
+            continue;
+        }
+
+        if normalized != tag.name {
+            sqlx::query!("UPDATE tags SET name = ? WHERE id = ?", normalized, tag.id)
+                .execute(&mut *tx)
+                .await?;
+            changed += 1;
+        }
+        survivors.insert(key, tag.id);
+    }
+
+    tx.commit().await?;
+
+    if changed > 0 {
+        info!(changed, "Normalized existing tag names");
+    }
+    Ok(changed)
+}
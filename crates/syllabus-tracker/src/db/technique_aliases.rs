@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use super::index_technique;
+use crate::error::AppError;
+use crate::ids::TechniqueId;
+use crate::models::TechniqueAlias;
+
+/// Every alias in the library keyed by technique id, each list by alias.
+/// For listings that attach aliases to many techniques at once.
+#[instrument(skip(executor))]
+pub async fn get_aliases_by_technique(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<HashMap<i64, Vec<TechniqueAlias>>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", technique_id, alias, language
+           FROM technique_aliases
+           ORDER BY alias COLLATE NOCASE, id"#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut aliases: HashMap<i64, Vec<TechniqueAlias>> = HashMap::new();
+    for row in rows {
+        aliases.entry(row.technique_id).or_default().push(TechniqueAlias {
+            id: row.id,
+            alias: row.alias,
+            language: row.language,
+        });
+    }
+    Ok(aliases)
+}
+
+#[instrument(skip(executor))]
+pub async fn get_technique_aliases(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
+) -> Result<Vec<TechniqueAlias>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", alias, language
+           FROM technique_aliases
+           WHERE technique_id = ?
+           ORDER BY alias COLLATE NOCASE, id"#,
+        technique_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TechniqueAlias { id: row.id, alias: row.alias, language: row.language })
+        .collect())
+}
+
+/// Adds another name for a technique. Returns `None` when the technique
+/// already has that alias (compared case-insensitively).
+#[instrument(skip(pool))]
+pub async fn add_technique_alias(
+    pool: &Pool<Sqlite>,
+    technique_id: TechniqueId,
+    alias: &str,
+    language: Option<&str>,
+) -> Result<Option<i64>, AppError> {
+    info!("Adding technique alias");
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM techniques WHERE id = ?) AS "exists!: bool""#,
+        technique_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Technique {} not found", technique_id)));
+    }
+
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!(
+        "INSERT INTO technique_aliases (technique_id, alias, language) VALUES (?, ?, ?)
+         ON CONFLICT DO NOTHING",
+        technique_id.0,
+        alias,
+        language
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
+    Ok(Some(res.last_insert_rowid()))
+}
+
+#[instrument(skip(pool))]
+pub async fn remove_technique_alias(
+    pool: &Pool<Sqlite>,
+    technique_id: TechniqueId,
+    alias_id: i64,
+) -> Result<(), AppError> {
+    info!("Removing technique alias");
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!(
+        "DELETE FROM technique_aliases WHERE id = ? AND technique_id = ?",
+        alias_id,
+        technique_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Alias {} not found", alias_id)));
+    }
+
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
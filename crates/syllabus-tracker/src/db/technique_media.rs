@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument};
+
+use crate::error::AppError;
+use crate::ids::{TechniqueId, UserId};
+use crate::models::{MediaKind, TechniqueMedia, naive_to_utc};
+
+/// Where a new piece of media lives: an outside URL or an attachment that
+/// has already been uploaded.
+#[derive(Debug, Clone)]
+pub enum MediaSource {
+    Url(String),
+    Attachment(i64),
+}
+
+/// Every technique's media keyed by technique id, each list in the order it
+/// was added. For listings that attach media to many techniques at once.
+#[instrument(skip(executor))]
+pub async fn get_media_by_technique(
+    executor: impl SqliteExecutor<'_>,
+) -> Result<HashMap<i64, Vec<TechniqueMedia>>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", technique_id, kind, title, url, attachment_id,
+                  created_at AS "created_at: NaiveDateTime"
+           FROM technique_media
+           ORDER BY id"#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut media: HashMap<i64, Vec<TechniqueMedia>> = HashMap::new();
+    for row in rows {
+        media.entry(row.technique_id).or_default().push(TechniqueMedia {
+            id: row.id,
+            kind: MediaKind::from_db(&row.kind)?,
+            title: row.title,
+            url: row.url,
+            attachment_id: row.attachment_id,
+            created_at: naive_to_utc(row.created_at),
+        });
+    }
+    Ok(media)
+}
+
+#[instrument(skip(executor))]
+pub async fn get_technique_media(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
+) -> Result<Vec<TechniqueMedia>, AppError> {
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", kind, title, url, attachment_id,
+                  created_at AS "created_at: NaiveDateTime"
+           FROM technique_media
+           WHERE technique_id = ?
+           ORDER BY id"#,
+        technique_id.0
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(TechniqueMedia {
+                id: row.id,
+                kind: MediaKind::from_db(&row.kind)?,
+                title: row.title,
+                url: row.url,
+                attachment_id: row.attachment_id,
+                created_at: naive_to_utc(row.created_at),
+            })
+        })
+        .collect()
+}
+
+/// Links media to a technique. A deleted or missing technique, or a missing
+/// attachment, is a not-found.
+#[instrument(skip(pool))]
+pub async fn add_technique_media(
+    pool: &Pool<Sqlite>,
+    technique_id: TechniqueId,
+    kind: MediaKind,
+    title: &str,
+    source: &MediaSource,
+    added_by: UserId,
+) -> Result<i64, AppError> {
+    info!("Adding technique media");
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM techniques WHERE id = ? AND deleted_at IS NULL)
+               AS "exists!: bool""#,
+        technique_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Technique {} not found", technique_id)));
+    }
+
+    let (url, attachment_id) = match source {
+        MediaSource::Url(url) => (Some(url.as_str()), None),
+        MediaSource::Attachment(id) => {
+            if super::get_attachment(pool, *id).await?.is_none() {
+                return Err(AppError::NotFound(format!("Attachment {} not found", id)));
+            }
+            (None, Some(*id))
+        }
+    };
+    let kind = kind.as_str();
+    let res = sqlx::query!(
+        "INSERT INTO technique_media (technique_id, kind, title, url, attachment_id, added_by_id)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        technique_id.0,
+        kind,
+        title,
+        url,
+        attachment_id,
+        added_by.0
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.last_insert_rowid())
+}
+
+/// Unlinks the media. An uploaded file stays in `attachments`.
+#[instrument(skip(executor))]
+pub async fn remove_technique_media(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: TechniqueId,
+    media_id: i64,
+) -> Result<(), AppError> {
+    info!("Removing technique media");
+    let res = sqlx::query!(
+        "DELETE FROM technique_media WHERE id = ? AND technique_id = ?",
+        media_id,
+        technique_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Media {} not found", media_id)));
+    }
+    Ok(())
+}
+
+/// Whether the attachment is linked to any technique, which makes it
+/// library material every signed-in user may download.
+#[instrument(skip(executor))]
+pub async fn is_technique_media_attachment(
+    executor: impl SqliteExecutor<'_>,
+    attachment_id: i64,
+) -> Result<bool, AppError> {
+    let linked = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM technique_media WHERE attachment_id = ?) AS "linked!: bool""#,
+        attachment_id
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(linked)
+}
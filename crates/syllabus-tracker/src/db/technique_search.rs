@@ -0,0 +1,134 @@
+//! The `technique_search` FTS5 index over each technique's name,
+//! description, tag names and aliases. FTS5 tables can't be kept in sync by
+//! foreign keys, so every write that changes one of those calls
+//! [`index_technique`] in the same transaction, and
+//! [`rebuild_technique_search`] runs on each boot to cover rows written
+//! before the index existed.
+
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection};
+use tracing::{info, instrument};
+
+use super::get_all_techniques;
+use crate::error::AppError;
+use crate::ids::{TagId, TechniqueId};
+use crate::models::Technique;
+
+/// Replaces the index entry for one technique with its current name,
+/// description, tags and aliases. A technique that no longer exists, or is
+/// deleted, just loses its entry.
+#[instrument(skip(conn))]
+pub async fn index_technique(
+    conn: &mut SqliteConnection,
+    technique_id: TechniqueId,
+) -> Result<(), AppError> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!("DELETE FROM technique_search WHERE rowid = ?", technique_id.0)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO technique_search (rowid, name, description, tags, aliases)
+         SELECT t.id, t.name, COALESCE(t.description, ''),
+                COALESCE((SELECT group_concat(tag.name, ' ')
+                          FROM technique_tags tt JOIN tags tag ON tag.id = tt.tag_id
+                          WHERE tt.technique_id = t.id), ''),
+                COALESCE((SELECT group_concat(a.alias, ' ')
+                          FROM technique_aliases a
+                          WHERE a.technique_id = t.id), '')
+         FROM techniques t
+         WHERE t.id = ? AND t.deleted_at IS NULL",
+        technique_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The techniques carrying `tag_id`, to reindex after a write that changes
+/// the tag rather than one technique. Read them before deleting the tag, as
+/// the links go with it.
+#[instrument(skip(conn))]
+pub async fn tagged_technique_ids(
+    conn: &mut SqliteConnection,
+    tag_id: TagId,
+) -> Result<Vec<TechniqueId>, AppError> {
+    let ids = sqlx::query_scalar!(
+        "SELECT technique_id FROM technique_tags WHERE tag_id = ? ORDER BY technique_id",
+        tag_id.0
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(ids.into_iter().map(TechniqueId).collect())
+}
+
+/// Rebuilds the whole index from the techniques table. The library is small
+/// enough that doing this on every boot is cheaper than tracking which
+/// entries are stale. Returns the number of techniques indexed.
+#[instrument(skip(pool))]
+pub async fn rebuild_technique_search(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("DELETE FROM technique_search").execute(&mut *tx).await?;
+    let res = sqlx::query!(
+        "INSERT INTO technique_search (rowid, name, description, tags, aliases)
+         SELECT t.id, t.name, COALESCE(t.description, ''),
+                COALESCE((SELECT group_concat(tag.name, ' ')
+                          FROM technique_tags tt JOIN tags tag ON tag.id = tt.tag_id
+                          WHERE tt.technique_id = t.id), ''),
+                COALESCE((SELECT group_concat(a.alias, ' ')
+                          FROM technique_aliases a
+                          WHERE a.technique_id = t.id), '')
+         FROM techniques t
+         WHERE t.deleted_at IS NULL"
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!(indexed = res.rows_affected(), "Rebuilt technique search index");
+    Ok(res.rows_affected())
+}
+
+/// Turns free text into an FTS5 query that can't be a syntax error: each
+/// word becomes a quoted prefix term, and the terms are ANDed. `None` when
+/// there are no words to search for.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Techniques matching every word of `query`, best match first. A hit in
+/// the name outranks one in the tags or aliases, which outranks the
+/// description.
+#[instrument(skip(pool))]
+pub async fn search_techniques(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<Technique>, AppError> {
+    info!("Searching techniques");
+    let Some(expression) = match_expression(query) else {
+        return Ok(Vec::new());
+    };
+
+    let ids = sqlx::query_scalar!(
+        r#"SELECT rowid AS "id!: i64"
+           FROM technique_search
+           WHERE technique_search MATCH ?
+           ORDER BY bm25(technique_search, 10.0, 1.0, 5.0, 5.0), rowid
+           LIMIT ?"#,
+        expression,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut techniques = get_all_techniques(pool).await?;
+    techniques.retain(|technique| ids.contains(&technique.id.0));
+    techniques.sort_by_key(|technique| ids.iter().position(|id| *id == technique.id.0));
+    Ok(techniques)
+}
@@ -2,11 +2,13 @@ use std::collections::{HashMap, hash_map::Entry};
 
 use chrono::NaiveDateTime;
 use serde::Serialize;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection, SqliteExecutor};
 use tracing::{info, instrument};
 
+use super::{StudentTechniqueField, get_aliases_by_technique, index_technique};
 use crate::error::AppError;
-use crate::models::{AttemptBucket, Tag, Technique};
+use crate::ids::{TechniqueId, UserId};
+use crate::models::{AttemptBucket, Tag, Technique, TechniqueAlias, naive_to_rfc3339};
 
 /// One row in the library / full-techniques admin list. Aggregates collection
 /// membership count, how many students have the technique assigned, and the
@@ -16,7 +18,10 @@ pub struct LibraryTechniqueRow {
     pub id: i64,
     pub name: String,
     pub description: String,
+    /// The base technique this is a variant of, if any.
+    pub parent_technique_id: Option<i64>,
     pub tags: Vec<Tag>,
+    pub aliases: Vec<TechniqueAlias>,
     /// IDs of the collections this technique belongs to. Sent alongside
     /// `collection_count` so the frontend can render bubble filters that
     /// scope the list to "techniques in collection X" without an extra
@@ -40,12 +45,14 @@ pub async fn list_library_techniques(
             t.id AS "id!: i64",
             t.name,
             t.description,
+            t.parent_technique_id,
             COALESCE((SELECT COUNT(*) FROM collection_techniques ct WHERE ct.technique_id = t.id), 0) AS "collection_count!: i64",
             COALESCE((SELECT COUNT(DISTINCT st.student_id) FROM student_techniques st WHERE st.technique_id = t.id), 0) AS "student_count!: i64",
             COALESCE((SELECT COUNT(*) FROM videos v WHERE v.technique_id = t.id AND v.deleted_at IS NULL), 0) AS "video_count!: i64",
             (SELECT MAX(st.updated_at) FROM student_techniques st WHERE st.technique_id = t.id) AS "last_activity_at?: NaiveDateTime"
         FROM techniques t
-        ORDER BY t.name
+        WHERE t.deleted_at IS NULL
+        ORDER BY t.name, t.id
         "#
     )
     .fetch_all(pool)
@@ -54,10 +61,13 @@ pub async fn list_library_techniques(
     let tag_rows = sqlx::query!(
         r#"SELECT tt.technique_id AS "technique_id!: i64",
                   tag.id AS "tag_id!: i64",
-                  tag.name AS "tag_name!: String"
+                  tag.name AS "tag_name!: String",
+                  tag.archived AS "tag_archived!: bool",
+                  tag.category AS tag_category, tag.color AS tag_color,
+                  tag.parent_id AS tag_parent_id
            FROM technique_tags tt
            JOIN tags tag ON tag.id = tt.tag_id
-           ORDER BY tag.name"#
+           ORDER BY tag.name, tag.id"#
     )
     .fetch_all(pool)
     .await?;
@@ -70,13 +80,18 @@ pub async fn list_library_techniques(
             .push(Tag {
                 id: row.tag_id,
                 name: row.tag_name,
+                archived: row.tag_archived,
+                category: row.tag_category,
+                color: row.tag_color,
+                parent_id: row.tag_parent_id,
             });
     }
 
     let collection_rows = sqlx::query!(
         r#"SELECT technique_id AS "technique_id!: i64",
                   collection_id AS "collection_id!: i64"
-           FROM collection_techniques"#
+           FROM collection_techniques
+           ORDER BY collection_id"#
     )
     .fetch_all(pool)
     .await?;
@@ -87,78 +102,83 @@ pub async fn list_library_techniques(
             .or_default()
             .push(row.collection_id);
     }
+    let mut aliases = get_aliases_by_technique(pool).await?;
 
     Ok(rows
         .into_iter()
         .map(|r| LibraryTechniqueRow {
             id: r.id,
             tags: tags_by_technique.remove(&r.id).unwrap_or_default(),
+            aliases: aliases.remove(&r.id).unwrap_or_default(),
             collection_ids: collections_by_technique.remove(&r.id).unwrap_or_default(),
             name: r.name,
             description: r.description.unwrap_or_default(),
+            parent_technique_id: r.parent_technique_id,
             collection_count: r.collection_count,
             student_count: r.student_count,
             video_count: r.video_count,
-            last_activity_at: r.last_activity_at.map(|dt| {
-                chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(dt, chrono::Utc)
-                    .to_rfc3339()
-            }),
+            last_activity_at: r.last_activity_at.map(naive_to_rfc3339),
         })
         .collect())
 }
 
+/// Every technique in the library, grouped by base technique: each base
+/// is followed by its variants, by name. A variant whose base is deleted
+/// stands on its own.
 #[instrument]
 pub async fn get_all_techniques(pool: &Pool<Sqlite>) -> Result<Vec<Technique>, AppError> {
     info!("Getting all techniques with tags");
 
     let rows = sqlx::query!(
         r#"
-        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name,
-               tag.id as tag_id, tag.name as tag_name
+        SELECT t.id, t.name, t.description, t.coach_id, t.coach_name, t.parent_technique_id,
+               tag.id as tag_id, tag.name as tag_name, tag.archived as "tag_archived?: bool",
+               tag.category as tag_category, tag.color as tag_color,
+               tag.parent_id as tag_parent_id
         FROM techniques t
+        LEFT JOIN techniques base ON base.id = t.parent_technique_id AND base.deleted_at IS NULL
         LEFT JOIN technique_tags tt ON t.id = tt.technique_id
         LEFT JOIN tags tag ON tt.tag_id = tag.id
-        ORDER BY t.name
+        WHERE t.deleted_at IS NULL
+        ORDER BY COALESCE(base.name, t.name), COALESCE(base.id, t.id), base.id IS NOT NULL,
+                 t.name, t.id, tag.name, tag.id
         "#
     )
     .fetch_all(pool)
     .await?;
 
-    let mut techniques_map: HashMap<i64, Technique> = HashMap::new();
-
+    // A technique's rows are adjacent (ordered by id within a name, after
+    // its base), so grouping them in turn keeps the query's order.
+    let mut techniques: Vec<Technique> = Vec::new();
     for row in rows {
-        let technique_id = row.id;
-
-        if let Entry::Vacant(e) = techniques_map.entry(technique_id) {
-            let technique = Technique {
-                id: technique_id,
+        if techniques.last().is_none_or(|technique| technique.id.0 != row.id) {
+            techniques.push(Technique {
+                id: TechniqueId(row.id),
                 name: row.name,
                 description: row.description.unwrap_or_default(),
-                coach_id: row.coach_id.unwrap_or_default(),
+                coach_id: UserId(row.coach_id.unwrap_or_default()),
                 coach_name: row.coach_name.unwrap_or_default(),
+                parent_technique_id: row.parent_technique_id.map(TechniqueId),
                 tags: Vec::new(),
-            };
-            e.insert(technique);
+                aliases: Vec::new(),
+            });
         }
-
-        if let (tag_id, Some(tag_name)) = (row.tag_id, row.tag_name) {
-            let tag = Tag {
-                id: tag_id,
+        if let (Some(technique), Some(tag_name)) = (techniques.last_mut(), row.tag_name) {
+            technique.tags.push(Tag {
+                id: row.tag_id,
                 name: tag_name,
-            };
-
-            let technique = techniques_map.get_mut(&technique_id).unwrap();
-            if !technique.tags.iter().any(|t| t.id == tag_id) {
-                technique.tags.push(tag);
-            }
+                archived: row.tag_archived.unwrap_or_default(),
+                category: row.tag_category,
+                color: row.tag_color,
+                parent_id: row.tag_parent_id,
+            });
         }
     }
 
-    for technique in techniques_map.values_mut() {
-        technique.tags.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut aliases = get_aliases_by_technique(pool).await?;
+    for technique in &mut techniques {
+        technique.aliases = aliases.remove(&technique.id.0).unwrap_or_default();
     }
-
-    let techniques: Vec<Technique> = techniques_map.into_values().collect();
     Ok(techniques)
 }
 
@@ -190,15 +210,15 @@ pub struct LibraryTechniqueStats {
 #[instrument]
 pub async fn library_technique_stats(
     pool: &Pool<Sqlite>,
-    technique_id: i64,
+    technique_id: TechniqueId,
 ) -> Result<LibraryTechniqueStats, AppError> {
     let collections_rows = sqlx::query!(
         r#"SELECT c.id AS "id!: i64", c.name AS "name!: String"
            FROM collection_techniques ct
            JOIN collections c ON c.id = ct.collection_id
            WHERE ct.technique_id = ?
-           ORDER BY c.name"#,
-        technique_id
+           ORDER BY c.name, c.id"#,
+        technique_id.0
     )
     .fetch_all(pool)
     .await?;
@@ -216,7 +236,7 @@ pub async fn library_technique_stats(
             COALESCE(SUM(CASE WHEN status = 'amber' THEN 1 ELSE 0 END), 0) AS "amber!: i64",
             COALESCE(SUM(CASE WHEN status = 'green' THEN 1 ELSE 0 END), 0) AS "green!: i64"
            FROM student_techniques WHERE technique_id = ?"#,
-        technique_id
+        technique_id.0
     )
     .fetch_one(pool)
     .await?;
@@ -232,7 +252,7 @@ pub async fn library_technique_stats(
            JOIN student_techniques st ON st.id = a.student_technique_id
            WHERE st.technique_id = ?
              AND a.attempted_at >= datetime('now', '-30 days')"#,
-        technique_id
+        technique_id.0
     )
     .fetch_one(pool)
     .await?;
@@ -246,7 +266,7 @@ pub async fn library_technique_stats(
              AND a.attempted_at >= datetime('now', '-56 days')
            GROUP BY date(a.attempted_at, 'weekday 0', '-6 days')
            ORDER BY 1"#,
-        technique_id,
+        technique_id.0,
     )
     .fetch_all(pool)
     .await?;
@@ -267,7 +287,7 @@ pub async fn library_technique_stats(
            FROM video_watch_aggregates a
            JOIN videos v ON v.id = a.video_id
            WHERE v.technique_id = ? AND v.deleted_at IS NULL"#,
-        technique_id
+        technique_id.0
     )
     .fetch_one(pool)
     .await?;
@@ -281,23 +301,50 @@ pub async fn library_technique_stats(
     })
 }
 
-#[instrument]
+/// Renames the technique and the copies held by its assignments, together.
+/// Returns which of the two differed; when neither does nothing is written.
+#[instrument(skip(conn))]
 pub async fn update_technique(
-    pool: &Pool<Sqlite>,
-    technique_id: i64,
+    conn: &mut SqliteConnection,
+    technique_id: TechniqueId,
     name: &str,
     description: &str,
-) -> Result<(), AppError> {
+) -> Result<Vec<StudentTechniqueField>, AppError> {
     info!("Updating technique");
+    let mut tx = conn.begin().await?;
+    let row = sqlx::query!(
+        r#"SELECT name IS NOT ? AS "name!: bool", description IS NOT ? AS "description!: bool"
+           FROM techniques
+           WHERE id = ?"#,
+        name,
+        description,
+        technique_id.0
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let changed: Vec<StudentTechniqueField> = row
+        .map(|row| {
+            [
+                (row.name, StudentTechniqueField::TechniqueName),
+                (row.description, StudentTechniqueField::TechniqueDescription),
+            ]
+        })
+        .into_iter()
+        .flatten()
+        .filter_map(|(changed, field)| changed.then_some(field))
+        .collect();
+    if changed.is_empty() {
+        return Ok(changed);
+    }
     sqlx::query!(
         "UPDATE techniques
          SET name = ?, description = ?
          WHERE id = ?",
         name,
         description,
-        technique_id
+        technique_id.0
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
     sqlx::query!(
@@ -306,39 +353,211 @@ pub async fn update_technique(
          WHERE technique_id = ?",
         name,
         description,
-        technique_id
+        technique_id.0
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
+    Ok(changed)
+}
+
+/// Soft-deletes a technique: it drops out of the library, pickers and
+/// search, while its assignments, notes and attempts stay as they are.
+/// Deleting one that is already deleted is a not-found.
+#[instrument(skip(conn))]
+pub async fn delete_technique(
+    conn: &mut SqliteConnection,
+    technique_id: TechniqueId,
+) -> Result<(), AppError> {
+    info!("Deleting technique");
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = conn.begin().await?;
+    let res = sqlx::query!(
+        "UPDATE techniques SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+        now,
+        technique_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Technique {} not found", technique_id)));
+    }
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-#[instrument]
+/// Undoes `delete_technique`. Restoring one that isn't deleted is a
+/// not-found.
+#[instrument(skip(conn))]
+pub async fn restore_technique(
+    conn: &mut SqliteConnection,
+    technique_id: TechniqueId,
+) -> Result<(), AppError> {
+    info!("Restoring technique");
+    let mut tx = conn.begin().await?;
+    let res = sqlx::query!(
+        "UPDATE techniques SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        technique_id.0
+    )
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Deleted technique {} not found", technique_id)));
+    }
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Makes `technique_id` a variant of `parent_id`, or a base technique again
+/// with `None`. Returns false, changing nothing, if that would nest variants
+/// more than one level deep: the parent must be a base technique other than
+/// `technique_id`, and a technique with variants can't become one.
+#[instrument(skip(conn))]
+pub async fn set_technique_parent(
+    conn: &mut SqliteConnection,
+    technique_id: TechniqueId,
+    parent_id: Option<TechniqueId>,
+) -> Result<bool, AppError> {
+    info!("Setting technique parent");
+    let has_variants = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM techniques v WHERE v.parent_technique_id = t.id)
+               AS "has_variants!: bool"
+           FROM techniques t
+           WHERE t.id = ? AND t.deleted_at IS NULL"#,
+        technique_id.0
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Technique {} not found", technique_id)))?;
+
+    if let Some(parent_id) = parent_id {
+        let grandparent = sqlx::query_scalar!(
+            "SELECT parent_technique_id FROM techniques WHERE id = ? AND deleted_at IS NULL",
+            parent_id.0
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Technique {} not found", parent_id)))?;
+        if has_variants || grandparent.is_some() || parent_id == technique_id {
+            return Ok(false);
+        }
+    }
+
+    let parent_id = parent_id.map(|id| id.0);
+    sqlx::query!(
+        "UPDATE techniques SET parent_technique_id = ? WHERE id = ?",
+        parent_id,
+        technique_id.0
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(true)
+}
+
+#[instrument(skip(pool))]
 pub async fn create_technique(
     pool: &Pool<Sqlite>,
     name: &str,
     description: &str,
-    coach_id: i64,
-) -> Result<i64, AppError> {
+    coach_id: UserId,
+) -> Result<TechniqueId, AppError> {
     info!("Creating technique");
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
     let res = sqlx::query!(
-        "INSERT INTO techniques (name, description, coach_id)
-         VALUES (?, ?, ?)",
+        "INSERT INTO techniques (name, description, coach_id, created_at)
+         VALUES (?, ?, ?, ?)",
         name,
         description,
-        coach_id
+        coach_id.0,
+        now
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-    Ok(res.last_insert_rowid())
+    let technique_id = TechniqueId(res.last_insert_rowid());
+    index_technique(&mut tx, technique_id).await?;
+    tx.commit().await?;
+    Ok(technique_id)
+}
+
+/// A technique for `create_techniques`, with the (normalized) names of its
+/// tags.
+#[derive(Debug, Clone)]
+pub struct NewTechnique {
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+}
+
+/// Creates every technique in one transaction, owned by `coach_id`, and tags
+/// each, creating tags that don't exist yet. Returns the new ids in input
+/// order; on any error nothing is created.
+#[instrument(skip(pool, techniques))]
+pub async fn create_techniques(
+    pool: &Pool<Sqlite>,
+    techniques: &[NewTechnique],
+    coach_id: UserId,
+) -> Result<Vec<TechniqueId>, AppError> {
+    info!(count = techniques.len(), "Creating techniques");
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    let mut tag_ids: HashMap<&str, i64> = HashMap::new();
+    let mut ids = Vec::with_capacity(techniques.len());
+
+    for technique in techniques {
+        let technique_id = sqlx::query!(
+            "INSERT INTO techniques (name, description, coach_id, created_at)
+             VALUES (?, ?, ?, ?)",
+            technique.name,
+            technique.description,
+            coach_id.0,
+            now
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for tag in &technique.tags {
+            let tag_id = match tag_ids.entry(tag.as_str()) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    sqlx::query!("INSERT INTO tags (name) VALUES (?) ON CONFLICT DO NOTHING", tag)
+                        .execute(&mut *tx)
+                        .await?;
+                    let id = sqlx::query_scalar!(
+                        r#"SELECT id AS "id!" FROM tags WHERE name = ? COLLATE NOCASE"#,
+                        tag
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    *entry.insert(id)
+                }
+            };
+            sqlx::query!(
+                "INSERT OR IGNORE INTO technique_tags (technique_id, tag_id) VALUES (?, ?)",
+                technique_id,
+                tag_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        index_technique(&mut tx, TechniqueId(technique_id)).await?;
+        ids.push(TechniqueId(technique_id));
+    }
+
+    tx.commit().await?;
+    Ok(ids)
 }
 
 #[instrument]
 pub async fn create_and_assign_technique(
     pool: &Pool<Sqlite>,
-    coach_id: i64,
-    student_id: i64,
+    coach_id: UserId,
+    student_id: UserId,
     technique_name: &str,
     technique_description: &str,
     collection_id: Option<i64>,
@@ -353,10 +572,135 @@ pub async fn create_and_assign_technique(
     Ok(())
 }
 
-#[instrument]
-pub async fn count_techniques(pool: &Pool<Sqlite>) -> Result<i64, AppError> {
-    let row = sqlx::query!("SELECT COUNT(*) as count FROM techniques")
-        .fetch_one(pool)
+/// An existing technique whose name is close enough to a proposed one that
+/// creating both would probably duplicate the library entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarTechnique {
+    pub id: TechniqueId,
+    pub name: String,
+    /// 0.0 to 1.0, higher is closer.
+    pub similarity: f64,
+}
+
+/// Names scoring at or above this are reported as likely duplicates.
+const DUPLICATE_SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// Techniques whose names look like `name`, best match first. Matching runs
+/// in Rust rather than SQL because SQLite has no trigram or edit-distance
+/// functions; the library is small enough to scan.
+#[instrument(skip(pool))]
+pub async fn find_similar_techniques(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    limit: usize,
+) -> Result<Vec<SimilarTechnique>, AppError> {
+    let rows = sqlx::query!(r#"SELECT id as "id!: i64", name FROM techniques WHERE deleted_at IS NULL"#)
+        .fetch_all(pool)
+        .await?;
+    let mut aliases = get_aliases_by_technique(pool).await?;
+
+    let mut matches: Vec<SimilarTechnique> = rows
+        .into_iter()
+        .filter_map(|row| {
+            // An alias counts as the technique's name, so "Juji-gatame"
+            // finds "Armbar" once the alias is recorded.
+            let similarity = aliases
+                .remove(&row.id)
+                .unwrap_or_default()
+                .iter()
+                .map(|alias| name_similarity(name, &alias.alias))
+                .fold(name_similarity(name, &row.name), f64::max);
+            (similarity >= DUPLICATE_SIMILARITY_THRESHOLD).then_some(SimilarTechnique {
+                id: TechniqueId(row.id),
+                name: row.name,
+                similarity,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+/// Best of trigram overlap (catches reordered or extra words, "Armbar from
+/// guard" vs "Guard armbar") and normalised edit distance (catches typos in
+/// short names, "Kimura" vs "Kimora"). Case, punctuation and repeated
+/// whitespace are ignored.
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let a = comparable_name(a);
+    let b = comparable_name(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    trigram_similarity(&a, &b).max(levenshtein_similarity(&a, &b))
+}
+
+fn comparable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Jaccard index of the two names' trigram sets, with each word padded the
+/// way Postgres' pg_trgm does so short words still produce trigrams.
+fn trigram_similarity(a: &str, b: &str) -> f64 {
+    fn trigrams(s: &str) -> std::collections::HashSet<[char; 3]> {
+        s.split_whitespace()
+            .flat_map(|word| {
+                let padded: Vec<char> = "  "
+                    .chars()
+                    .chain(word.chars())
+                    .chain(" ".chars())
+                    .collect();
+                padded
+                    .windows(3)
+                    .map(|w| [w[0], w[1], w[2]])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.intersection(&b).count();
+    let total = a.union(&b).count();
+    if total == 0 {
+        0.0
+    } else {
+        shared as f64 / total as f64
+    }
+}
+
+fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    let distance = previous[b.len()];
+    1.0 - distance as f64 / a.len().max(b.len()) as f64
+}
+
+#[instrument(skip(executor))]
+pub async fn count_techniques(executor: impl SqliteExecutor<'_>) -> Result<i64, AppError> {
+    let row = sqlx::query!("SELECT COUNT(*) as count FROM techniques WHERE deleted_at IS NULL")
+        .fetch_one(executor)
         .await?;
     Ok(row.count as i64)
 }
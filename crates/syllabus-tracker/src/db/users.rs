@@ -1,25 +1,30 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
-use tracing::{info, instrument};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tracing::{info, instrument, warn};
 
+use super::{MembershipStatus, delete_user_sessions};
 use crate::auth::{DbUser, Role, User};
 use crate::error::AppError;
+use crate::ids::UserId;
+use crate::models::{naive_to_rfc3339, required};
+use crate::validation::normalize_username;
 
-#[instrument]
-pub async fn get_user(pool: &Pool<Sqlite>, id: i64) -> Result<User, AppError> {
+#[instrument(skip(executor))]
+pub async fn get_user(executor: impl SqliteExecutor<'_>, id: UserId) -> Result<User, AppError> {
     info!("Fetching user by ID");
     let row = sqlx::query_as!(
         DbUser,
-        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at FROM users WHERE id=?",
-        id
+        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at, timezone, membership_status, avatar_attachment_id FROM users WHERE id=?",
+        id.0
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     match row {
-        Some(user) => Ok(User::from(user)),
+        Some(user) => User::try_from(user),
         _ => Err(AppError::NotFound(format!(
             "User with id {} not found in database",
             id
@@ -27,39 +32,78 @@ pub async fn get_user(pool: &Pool<Sqlite>, id: i64) -> Result<User, AppError> {
     }
 }
 
-#[instrument]
+#[instrument(skip(executor))]
+/// `None` clears the name, after which the username is shown instead.
 pub async fn update_user_display_name(
-    pool: &Pool<Sqlite>,
-    user_id: i64,
-    display_name: &str,
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    display_name: Option<&str>,
 ) -> Result<(), AppError> {
     info!("Updating user display name");
     sqlx::query!(
         "UPDATE users SET display_name = ? WHERE id = ?",
         display_name,
-        user_id
+        user_id.0
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(())
 }
 
-#[instrument(skip(pool, new_password))]
+#[instrument(skip(executor, new_password))]
 pub async fn update_user_password(
-    pool: &Pool<Sqlite>,
-    user_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
     new_password: &str,
 ) -> Result<(), AppError> {
     info!("Updating user password");
-    let hashed_password = bcrypt::hash(new_password, crate::db::BCRYPT_COST)?;
+    let hashed_password = bcrypt::hash(new_password, crate::db::bcrypt_cost())?;
 
     sqlx::query!(
         "UPDATE users SET password = ? WHERE id = ?",
         hashed_password,
-        user_id
+        user_id.0
     )
-    .execute(pool)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// `None` clears the preference so the SPA falls back to the browser zone.
+#[instrument(skip(executor))]
+pub async fn update_user_timezone(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    timezone: Option<&str>,
+) -> Result<(), AppError> {
+    info!("Updating user timezone");
+    sqlx::query!(
+        "UPDATE users SET timezone = ? WHERE id = ?",
+        timezone,
+        user_id.0
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// `None` clears the picture. The attachment itself is left to the caller.
+#[instrument(skip(executor))]
+pub async fn set_user_avatar(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+    attachment_id: Option<i64>,
+) -> Result<(), AppError> {
+    info!("Updating user avatar");
+    sqlx::query!(
+        "UPDATE users SET avatar_attachment_id = ? WHERE id = ?",
+        attachment_id,
+        user_id.0
+    )
+    .execute(executor)
     .await?;
 
     Ok(())
@@ -68,14 +112,14 @@ pub async fn update_user_password(
 #[instrument]
 pub async fn update_username(
     pool: &Pool<Sqlite>,
-    user_id: i64,
+    user_id: UserId,
     new_username: &str,
 ) -> Result<(), AppError> {
     info!("Updating user username");
     let existing_user = sqlx::query!(
         "SELECT id FROM users WHERE username = ? AND id != ?",
         new_username,
-        user_id
+        user_id.0
     )
     .fetch_optional(pool)
     .await?;
@@ -89,7 +133,7 @@ pub async fn update_username(
     sqlx::query!(
         "UPDATE users SET username = ? WHERE id = ?",
         new_username,
-        user_id
+        user_id.0
     )
     .execute(pool)
     .await?;
@@ -97,9 +141,9 @@ pub async fn update_username(
     Ok(())
 }
 
-#[instrument(skip(pool, password))]
+#[instrument(skip(executor, password))]
 pub async fn authenticate_user(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     username: &str,
     password: &str,
 ) -> Result<Option<User>, AppError> {
@@ -109,11 +153,12 @@ pub async fn authenticate_user(
                   graduated_at as "graduated_at?: chrono::NaiveDateTime",
                   claimed_at as "claimed_at?: chrono::NaiveDateTime",
                   approved_at as "approved_at?: chrono::NaiveDateTime",
-                  reset_requested_at as "reset_requested_at?: chrono::NaiveDateTime"
+                  reset_requested_at as "reset_requested_at?: chrono::NaiveDateTime",
+                  timezone, membership_status, avatar_attachment_id
            FROM users WHERE username = ?"#,
         username
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     match user_auth {
@@ -124,22 +169,20 @@ pub async fn authenticate_user(
                 return Ok(None);
             }
             if bcrypt::verify(password, &user.password)? {
-                let to_iso = |dt: chrono::NaiveDateTime| {
-                    chrono::DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc).to_rfc3339()
-                };
+                let id = required(user.id, "users.id")?;
                 Ok(Some(User {
-                    id: user.id.unwrap(),
+                    id: UserId(id),
                     username: user.username.clone().unwrap_or_default(),
                     role: Role::from_str(&user.role)?,
                     display_name: user.display_name.unwrap_or_default(),
                     archived: user.archived,
-                    graduated_at: user.graduated_at.map(to_iso),
+                    graduated_at: user.graduated_at.map(naive_to_rfc3339),
                     email: user.email,
-                    claimed_at: user.claimed_at.map(to_iso),
-                    approved_at: user.approved_at.map(to_iso),
+                    claimed_at: user.claimed_at.map(naive_to_rfc3339),
+                    approved_at: user.approved_at.map(naive_to_rfc3339),
                     first_name: user.first_name,
                     last_name: user.last_name,
-                    reset_requested_at: user.reset_requested_at.map(to_iso),
+                    reset_requested_at: user.reset_requested_at.map(naive_to_rfc3339),
                     last_update: None,
                     last_coach_update_at: None,
                     total_techniques: None,
@@ -147,9 +190,13 @@ pub async fn authenticate_user(
                     amber_count: None,
                     green_count: None,
                     has_unseen_activity: None,
+                    review_requests: None,
                     last_student_initiative_at: None,
                     last_watch_at: None,
                     last_watch_video_title: None,
+                    timezone: user.timezone,
+                    membership_status: MembershipStatus::from_db(id, user.membership_status)?,
+                    avatar_attachment_id: user.avatar_attachment_id,
                 }))
             } else {
                 Ok(None)
@@ -166,7 +213,7 @@ pub async fn create_user(
     password: &str,
     role: &str,
     display_name: Option<&str>,
-) -> Result<i64, AppError> {
+) -> Result<UserId, AppError> {
     info!("Creating new user");
 
     let existing_user = sqlx::query!("SELECT id FROM users WHERE username = ?", username)
@@ -179,7 +226,7 @@ pub async fn create_user(
         ));
     }
 
-    let hashed_password = bcrypt::hash(password, crate::db::BCRYPT_COST)?;
+    let hashed_password = bcrypt::hash(password, crate::db::bcrypt_cost())?;
 
     let res = sqlx::query!(
         "INSERT INTO users (username, display_name, password, role) VALUES (?, ?, ?, ?)",
@@ -191,65 +238,156 @@ pub async fn create_user(
     .execute(pool)
     .await?;
 
-    Ok(res.last_insert_rowid())
+    Ok(UserId(res.last_insert_rowid()))
 }
 
 pub async fn find_user_by_username(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     username: &str,
 ) -> Result<Option<User>, AppError> {
     let row = sqlx::query_as!(
         DbUser,
-        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at FROM users WHERE username = ?",
+        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at, timezone, membership_status, avatar_attachment_id FROM users WHERE username = ?",
         username
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
-    Ok(row.map(User::from))
+    row.map(User::try_from).transpose()
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn get_users_by_role(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     role: &str,
     show_archived: bool,
 ) -> Result<Vec<User>, AppError> {
     info!(role = %role, show_archived = %show_archived, "Getting users by role");
 
     let query = if show_archived {
-        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at FROM users WHERE role = ?"
+        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at, timezone, membership_status, avatar_attachment_id FROM users WHERE role = ?"
     } else {
-        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at FROM users WHERE role = ? AND archived IS 0"
+        "SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at, approved_at, first_name, last_name, reset_requested_at, timezone, membership_status, avatar_attachment_id FROM users WHERE role = ? AND archived IS 0"
     };
 
     let rows = sqlx::query_as::<_, DbUser>(query)
         .bind(role)
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await?;
 
-    Ok(rows.into_iter().map(User::from).collect())
+    rows.into_iter().map(User::try_from).collect()
 }
 
-#[instrument]
-pub async fn get_all_users(pool: &Pool<Sqlite>) -> Result<Vec<User>, AppError> {
-    let rows = sqlx::query_as::<_, DbUser>("SELECT * FROM Users")
-        .fetch_all(pool)
-        .await?;
+/// The order of the admin user list. Ties fall back to id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UserSort {
+    /// Display name, or username when there is none.
+    #[default]
+    Name,
+    Username,
+    Role,
+}
 
-    let users: Vec<User> = rows.into_iter().map(User::from).collect();
+impl UserSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "name" => Some(UserSort::Name),
+            "username" => Some(UserSort::Username),
+            "role" => Some(UserSort::Role),
+            _ => None,
+        }
+    }
 
-    if users.is_empty() {
-        return Err(AppError::NotFound("No users found".to_string()));
+    fn as_str(self) -> &'static str {
+        match self {
+            UserSort::Name => "name",
+            UserSort::Username => "username",
+            UserSort::Role => "role",
+        }
     }
+}
+
+/// Which users the admin list shows and in what order. Unset fields don't
+/// narrow it.
+#[derive(Debug, Default)]
+pub struct UserListFilter {
+    pub role: Option<Role>,
+    pub archived: Option<bool>,
+    /// Matched case-insensitively against username, display name and email.
+    pub search: Option<String>,
+    pub sort: UserSort,
+    pub descending: bool,
+}
 
-    Ok(users)
+/// One page of the users matching `filter`, and how many match in all.
+#[instrument(skip(pool))]
+pub async fn get_all_users(
+    pool: &Pool<Sqlite>,
+    filter: &UserListFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<User>, i64), AppError> {
+    let role = filter.role.as_ref().map(Role::as_str);
+    let search = filter.search.as_deref();
+    let sort = filter.sort.as_str();
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!: i64" FROM users
+           WHERE (?1 IS NULL OR role = ?1)
+             AND (?2 IS NULL OR archived = ?2)
+             AND (?3 IS NULL OR username LIKE '%' || ?3 || '%'
+                  OR display_name LIKE '%' || ?3 || '%' OR email LIKE '%' || ?3 || '%')"#,
+        role,
+        filter.archived,
+        search
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // Only one of the two sort terms varies per query: the ascending one is
+    // NULL throughout when descending, and the descending one only breaks
+    // ties between keys that are already equal when ascending.
+    let rows = sqlx::query_as!(
+        DbUser,
+        r#"SELECT id, username, role, display_name, archived, graduated_at, email, claimed_at,
+                  approved_at, first_name, last_name, reset_requested_at, timezone,
+                  membership_status, avatar_attachment_id
+           FROM users
+           WHERE (?1 IS NULL OR role = ?1)
+             AND (?2 IS NULL OR archived = ?2)
+             AND (?3 IS NULL OR username LIKE '%' || ?3 || '%'
+                  OR display_name LIKE '%' || ?3 || '%' OR email LIKE '%' || ?3 || '%')
+           ORDER BY
+             CASE WHEN ?5 THEN NULL ELSE
+               CASE ?4 WHEN 'username' THEN COALESCE(username, '') WHEN 'role' THEN role
+                 ELSE COALESCE(NULLIF(display_name, ''), username, '') END
+             END COLLATE NOCASE,
+             CASE ?4 WHEN 'username' THEN COALESCE(username, '') WHEN 'role' THEN role
+               ELSE COALESCE(NULLIF(display_name, ''), username, '') END COLLATE NOCASE DESC,
+             id
+           LIMIT ?6 OFFSET ?7"#,
+        role,
+        filter.archived,
+        search,
+        sort,
+        filter.descending,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let users = rows
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<Vec<User>, _>>()?;
+    Ok((users, total))
 }
 
 #[instrument]
 pub async fn update_user_admin(
     pool: &Pool<Sqlite>,
-    user_id: i64,
+    user_id: UserId,
     username: &str,
     display_name: &str,
     role: &str,
@@ -259,7 +397,7 @@ pub async fn update_user_admin(
     let existing_user = sqlx::query!(
         "SELECT id FROM users WHERE username = ? AND id != ?",
         username,
-        user_id
+        user_id.0
     )
     .fetch_optional(pool)
     .await?;
@@ -275,7 +413,7 @@ pub async fn update_user_admin(
         username,
         display_name,
         role,
-        user_id
+        user_id.0
     )
     .execute(pool)
     .await?;
@@ -286,26 +424,27 @@ pub async fn update_user_admin(
 #[instrument]
 pub async fn set_user_graduated(
     pool: &Pool<Sqlite>,
-    user_id: i64,
+    user_id: UserId,
     graduated: bool,
-    actor_id: Option<i64>,
+    actor_id: Option<UserId>,
 ) -> Result<bool, AppError> {
     info!("Setting graduated state");
 
     if graduated {
         let now = Utc::now().naive_utc();
+        let actor_id = actor_id.map(i64::from);
         sqlx::query!(
             "UPDATE users SET graduated_at = ?, graduated_by_id = ? WHERE id = ?",
             now,
             actor_id,
-            user_id
+            user_id.0
         )
         .execute(pool)
         .await?;
     } else {
         sqlx::query!(
             "UPDATE users SET graduated_at = NULL, graduated_by_id = NULL WHERE id = ?",
-            user_id
+            user_id.0
         )
         .execute(pool)
         .await?;
@@ -323,7 +462,7 @@ pub async fn create_self_registered_user(
     password: &str,
     first_name: Option<&str>,
     last_name: Option<&str>,
-) -> Result<i64, AppError> {
+) -> Result<UserId, AppError> {
     info!("Self-registering user");
 
     let existing = sqlx::query!("SELECT id FROM users WHERE username = ?", username)
@@ -333,7 +472,7 @@ pub async fn create_self_registered_user(
         return Err(AppError::Internal("Username already taken".to_string()));
     }
 
-    let hashed = bcrypt::hash(password, crate::db::BCRYPT_COST)?;
+    let hashed = bcrypt::hash(password, crate::db::bcrypt_cost())?;
     let display_name = match (first_name, last_name) {
         (Some(f), Some(l)) => format!("{} {}", f, l),
         (Some(f), None) => f.to_string(),
@@ -356,33 +495,36 @@ pub async fn create_self_registered_user(
     .execute(pool)
     .await?;
 
-    Ok(res.last_insert_rowid())
+    Ok(UserId(res.last_insert_rowid()))
 }
 
 /// Approve a self-registered user. Idempotent.
-#[instrument]
-pub async fn approve_user(pool: &Pool<Sqlite>, user_id: i64) -> Result<(), AppError> {
+#[instrument(skip(executor))]
+pub async fn approve_user(
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
+) -> Result<(), AppError> {
     info!("Approving user");
     let now = Utc::now().naive_utc();
     sqlx::query!(
         "UPDATE users SET approved_at = ? WHERE id = ? AND approved_at IS NULL",
         now,
-        user_id
+        user_id.0
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
 /// Create a "stub" user: no username, no password, just display name and role.
 /// Coaches use this to pre-populate a student's record before they claim.
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn create_user_stub(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     display_name: &str,
     email: Option<&str>,
     role: &str,
-) -> Result<i64, AppError> {
+) -> Result<UserId, AppError> {
     info!("Creating stub user");
     // Coach-driven creates are implicitly approved.
     let now = Utc::now().naive_utc();
@@ -394,17 +536,17 @@ pub async fn create_user_stub(
         email,
         now
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
-    Ok(res.last_insert_rowid())
+    Ok(UserId(res.last_insert_rowid()))
 }
 
 /// Flag a user as having requested a password reset. Silently no-ops if the
 /// username doesn't exist (we don't want to leak whether usernames are real
 /// to anonymous callers).
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn request_password_reset(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     username: &str,
 ) -> Result<(), AppError> {
     info!("Recording password reset request");
@@ -414,15 +556,15 @@ pub async fn request_password_reset(
         now,
         username
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
-#[instrument]
+#[instrument(skip(executor))]
 pub async fn set_user_archived(
-    pool: &Pool<Sqlite>,
-    user_id: i64,
+    executor: impl SqliteExecutor<'_>,
+    user_id: UserId,
     archive: bool,
 ) -> Result<bool, AppError> {
     info!("Toggling user archived status");
@@ -430,9 +572,9 @@ pub async fn set_user_archived(
     sqlx::query!(
         "UPDATE users SET archived = ? WHERE id = ?",
         archive,
-        user_id
+        user_id.0
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(archive)
@@ -441,13 +583,72 @@ pub async fn set_user_archived(
 #[instrument]
 pub async fn update_user_role(
     pool: &Pool<Sqlite>,
-    user_id: i64,
+    user_id: UserId,
     role: &str,
 ) -> Result<(), AppError> {
     info!("Updating user role");
-    sqlx::query!("UPDATE users SET role = ? WHERE id = ?", role, user_id)
-        .execute(pool)
-        .await?;
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query!(
+        "UPDATE users SET role = ? WHERE id = ? AND role != ?",
+        role,
+        user_id.0,
+        role
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if res.rows_affected() > 0 {
+        delete_user_sessions(&mut *tx, user_id).await?;
+    }
+    tx.commit().await?;
 
     Ok(())
 }
+
+/// Backfill for accounts created before usernames were trimmed and
+/// NFC-normalised, which login can no longer reach. Renames each username to
+/// its normal form unless another account already holds that name; those
+/// collisions are logged and left for an admin to resolve. Returns how many
+/// usernames were rewritten. Idempotent.
+#[instrument(skip(pool))]
+pub async fn normalize_existing_usernames(pool: &Pool<Sqlite>) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let rows = sqlx::query!("SELECT id, username FROM users WHERE username IS NOT NULL ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await?;
+    let mut taken: HashSet<String> = rows.iter().filter_map(|row| row.username.clone()).collect();
+
+    let mut rewritten = 0;
+    for row in rows {
+        let id = required(row.id, "users.id")?;
+        let username = required(row.username, "users.username")?;
+        let normalized = normalize_username(&username);
+        if normalized == username {
+            continue;
+        }
+        if normalized.is_empty() || taken.contains(&normalized) {
+            warn!(
+                user_id = id,
+                username = %username,
+                normalized = %normalized,
+                "Username can't be normalized without colliding; left unchanged"
+            );
+            continue;
+        }
+
+        sqlx::query!("UPDATE users SET username = ? WHERE id = ?", normalized, id)
+            .execute(&mut *tx)
+            .await?;
+        taken.remove(&username);
+        taken.insert(normalized);
+        rewritten += 1;
+    }
+
+    tx.commit().await?;
+
+    if rewritten > 0 {
+        info!(rewritten, "Normalized existing usernames");
+    }
+    Ok(rewritten)
+}
@@ -1,21 +1,24 @@
 use std::collections::HashMap;
 
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::{info, instrument};
 
 use crate::error::AppError;
 use crate::models::{DbVideo, ProcessingStatus, Video, VideoKind};
 
-#[instrument(skip(pool))]
-pub async fn next_video_position(pool: &Pool<Sqlite>, technique_id: i64) -> Result<i64, AppError> {
+#[instrument(skip(executor))]
+pub async fn next_video_position(
+    executor: impl SqliteExecutor<'_>,
+    technique_id: i64,
+) -> Result<i64, AppError> {
     let row = sqlx::query!(
         "SELECT COALESCE(MAX(position), -1) AS max_position
          FROM videos
          WHERE technique_id = ? AND deleted_at IS NULL",
         technique_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     Ok(row.max_position + 1)
 }
@@ -91,9 +94,9 @@ pub async fn create_external_video(
     Ok(res.last_insert_rowid())
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn finalize_video_ready(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     id: i64,
     storage_key: &str,
     bytes: i64,
@@ -123,14 +126,14 @@ pub async fn finalize_video_ready(
         now,
         id,
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn mark_video_failed(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     id: i64,
     error: &str,
 ) -> Result<(), AppError> {
@@ -145,13 +148,16 @@ pub async fn mark_video_failed(
         now,
         id,
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
 
-#[instrument(skip(pool))]
-pub async fn get_db_video(pool: &Pool<Sqlite>, id: i64) -> Result<Option<DbVideo>, AppError> {
+#[instrument(skip(executor))]
+pub async fn get_db_video(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<Option<DbVideo>, AppError> {
     let row = sqlx::query_as!(
         DbVideo,
         "SELECT id, technique_id, title, description, position, kind,
@@ -163,7 +169,7 @@ pub async fn get_db_video(pool: &Pool<Sqlite>, id: i64) -> Result<Option<DbVideo
          WHERE id = ? AND deleted_at IS NULL",
         id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
     Ok(row)
 }
@@ -177,9 +183,9 @@ pub async fn get_video(pool: &Pool<Sqlite>, id: i64) -> Result<Option<Video>, Ap
 /// view: hidden videos are returned, the caller decides whether to badge
 /// them. For the student-facing view that filters down to effective
 /// visibility, use [`list_videos_for_technique_visible_to`].
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn list_videos_for_technique(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     technique_id: i64,
 ) -> Result<Vec<Video>, AppError> {
     let rows = sqlx::query_as!(
@@ -194,7 +200,7 @@ pub async fn list_videos_for_technique(
          ORDER BY position ASC, id ASC",
         technique_id
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
     Ok(rows.into_iter().map(Video::from).collect())
 }
@@ -202,9 +208,9 @@ pub async fn list_videos_for_technique(
 /// Lists videos for a technique, filtered to what `student_id` should
 /// actually see (effective visibility: per-student override beats global
 /// hide, soft-deleted videos always excluded).
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn list_videos_for_technique_visible_to(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     technique_id: i64,
     student_id: i64,
 ) -> Result<Vec<Video>, AppError> {
@@ -225,7 +231,7 @@ pub async fn list_videos_for_technique_visible_to(
         student_id,
         technique_id,
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
     Ok(rows.into_iter().map(Video::from).collect())
 }
@@ -233,9 +239,9 @@ pub async fn list_videos_for_technique_visible_to(
 /// Returns the effective visibility for a single (video, student) pair.
 /// Used by playback / download guards to refuse access if the student
 /// shouldn't be able to see the video. Coaches bypass this check.
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn video_visible_to_student(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     video_id: i64,
     student_id: i64,
 ) -> Result<bool, AppError> {
@@ -254,7 +260,7 @@ pub async fn video_visible_to_student(
         student_id,
         video_id,
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
     Ok(row.map(|r| r.visible != 0).unwrap_or(false))
 }
@@ -339,9 +345,9 @@ pub async fn set_video_student_visibility(
 /// Returns a map of video_id -> override.visible for a batch of videos
 /// against a single student. Used to annotate the coach's view of a
 /// student's technique page.
-#[instrument(skip(pool, video_ids))]
+#[instrument(skip(executor, video_ids))]
 pub async fn list_video_student_overrides(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     video_ids: &[i64],
     student_id: i64,
 ) -> Result<HashMap<i64, bool>, AppError> {
@@ -361,7 +367,7 @@ pub async fn list_video_student_overrides(
     );
     let rows: Vec<(i64, bool)> = sqlx::query_as(&sql)
         .bind(student_id)
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await?;
     Ok(rows.into_iter().collect())
 }
@@ -433,8 +439,8 @@ pub async fn reorder_videos(
 /// intact so the video can be recovered by clearing `deleted_at`. Read
 /// queries filter out deleted rows, so the video disappears from the UI.
 /// Returns `true` if a row was marked deleted (matched and was alive).
-#[instrument(skip(pool))]
-pub async fn delete_video(pool: &Pool<Sqlite>, id: i64) -> Result<bool, AppError> {
+#[instrument(skip(executor))]
+pub async fn delete_video(executor: impl SqliteExecutor<'_>, id: i64) -> Result<bool, AppError> {
     let now = Utc::now().naive_utc();
     let result = sqlx::query!(
         "UPDATE videos
@@ -444,13 +450,16 @@ pub async fn delete_video(pool: &Pool<Sqlite>, id: i64) -> Result<bool, AppError
         now,
         id,
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(result.rows_affected() > 0)
 }
 
-#[instrument(skip(pool))]
-pub async fn reset_video_to_processing(pool: &Pool<Sqlite>, id: i64) -> Result<(), AppError> {
+#[instrument(skip(executor))]
+pub async fn reset_video_to_processing(
+    executor: impl SqliteExecutor<'_>,
+    id: i64,
+) -> Result<(), AppError> {
     let status = ProcessingStatus::Processing.as_str();
     let kind = VideoKind::Native.as_str();
     let now = Utc::now().naive_utc();
@@ -469,7 +478,7 @@ pub async fn reset_video_to_processing(pool: &Pool<Sqlite>, id: i64) -> Result<(
         now,
         id,
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
@@ -493,26 +502,26 @@ pub async fn clear_video_watch_state(pool: &Pool<Sqlite>, video_id: i64) -> Resu
 // Storage stats include soft-deleted videos on purpose: their blobs are
 // still in R2 and still cost storage until a future hard-purge step
 // removes them.
-#[instrument(skip(pool))]
-pub async fn total_video_storage_bytes(pool: &Pool<Sqlite>) -> Result<i64, AppError> {
+#[instrument(skip(executor))]
+pub async fn total_video_storage_bytes(executor: impl SqliteExecutor<'_>) -> Result<i64, AppError> {
     let row = sqlx::query!(
         "SELECT COALESCE(SUM(bytes), 0) AS total
          FROM videos
          WHERE storage_key IS NOT NULL"
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     Ok(row.total)
 }
 
-#[instrument(skip(pool))]
-pub async fn total_video_objects(pool: &Pool<Sqlite>) -> Result<i64, AppError> {
+#[instrument(skip(executor))]
+pub async fn total_video_objects(executor: impl SqliteExecutor<'_>) -> Result<i64, AppError> {
     let row = sqlx::query!(
         "SELECT COUNT(*) AS count
          FROM videos
          WHERE storage_key IS NOT NULL"
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     Ok(row.count)
 }
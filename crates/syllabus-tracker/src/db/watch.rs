@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use chrono::Utc;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 use tracing::instrument;
 
 use crate::error::AppError;
@@ -123,9 +123,9 @@ pub async fn ingest_watch_events(
     Ok(())
 }
 
-#[instrument(skip(pool))]
+#[instrument(skip(executor))]
 pub async fn get_my_watch_state(
-    pool: &Pool<Sqlite>,
+    executor: impl SqliteExecutor<'_>,
     user_id: i64,
     video_ids: &[i64],
 ) -> Result<HashMap<i64, WatchAggregateRow>, AppError> {
@@ -144,7 +144,7 @@ pub async fn get_my_watch_state(
     for id in video_ids {
         q = q.bind(*id);
     }
-    let rows = q.fetch_all(pool).await?;
+    let rows = q.fetch_all(executor).await?;
     for (video_id, play_count, completed_count, total_seconds_watched) in rows {
         result.insert(
             video_id,
@@ -158,26 +158,32 @@ pub async fn get_my_watch_state(
     Ok(result)
 }
 
-#[instrument(skip(pool))]
-pub async fn has_privacy_ack(pool: &Pool<Sqlite>, user_id: i64) -> Result<bool, AppError> {
+#[instrument(skip(executor))]
+pub async fn has_privacy_ack(
+    executor: impl SqliteExecutor<'_>,
+    user_id: i64,
+) -> Result<bool, AppError> {
     let row = sqlx::query!(
         "SELECT user_id FROM video_privacy_acks WHERE user_id = ?",
         user_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
     Ok(row.is_some())
 }
 
-#[instrument(skip(pool))]
-pub async fn record_privacy_ack(pool: &Pool<Sqlite>, user_id: i64) -> Result<(), AppError> {
+#[instrument(skip(executor))]
+pub async fn record_privacy_ack(
+    executor: impl SqliteExecutor<'_>,
+    user_id: i64,
+) -> Result<(), AppError> {
     sqlx::query!(
         "INSERT INTO video_privacy_acks (user_id)
          VALUES (?)
          ON CONFLICT (user_id) DO NOTHING",
         user_id
     )
-    .execute(pool)
+    .execute(executor)
     .await?;
     Ok(())
 }
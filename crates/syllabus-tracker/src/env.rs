@@ -1,26 +1,57 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+use once_cell::sync::OnceCell;
 use tracing::{info, warn};
 
-pub fn load_environment() -> Result<(), Box<dyn std::error::Error>> {
+/// Variables already set when `load_environment` first ran, i.e. by the
+/// shell or container rather than an env file.
+static SHELL_KEYS: OnceCell<HashSet<String>> = OnceCell::new();
+
+/// Most-specific first: secrets, then environment-specific, then common
+/// defaults. The first file to set a variable wins.
+fn env_files() -> Vec<&'static str> {
     let is_production =
         dotenvy::var("ROCKET_PROFILE").unwrap_or("development".to_string()) == "production";
-
-    // Load most-specific files first so existing shell env wins, then secrets,
-    // then environment-specific, then common defaults.
-    let env_files = if is_production {
+    if is_production {
         vec!["config/prod.env", "config/common.env"]
     } else {
         vec![".secrets.env", "config/dev.env", "config/common.env"]
-    };
+    }
+}
 
-    for env_file in env_files {
+pub fn load_environment() -> Result<(), Box<dyn std::error::Error>> {
+    SHELL_KEYS.get_or_init(|| std::env::vars().map(|(key, _)| key).collect());
+
+    // Existing shell env wins, since dotenvy never overwrites a set variable.
+    for env_file in env_files() {
         load_env_file(Path::new(env_file))?;
     }
 
     Ok(())
 }
 
+/// What the env files say now, for a config reload: each variable's value
+/// from the most specific file, leaving out variables the shell set, which
+/// still win. Reads the files without touching the process environment.
+pub fn read_env_files() -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let shell = SHELL_KEYS.get();
+    let mut values = HashMap::new();
+    for env_file in env_files() {
+        if !Path::new(env_file).exists() {
+            continue;
+        }
+        for item in dotenvy::from_filename_iter(env_file)? {
+            let (key, value) = item?;
+            if shell.is_some_and(|keys| keys.contains(&key)) {
+                continue;
+            }
+            values.entry(key).or_insert(value);
+        }
+    }
+    Ok(values)
+}
+
 fn load_env_file(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
     if !path.exists() {
         warn!("Warning: Environment file {} not found, skipping", path.display());
@@ -0,0 +1,58 @@
+//! Conditional GETs for lists the SPA refetches often. `ETagged` sends a
+//! JSON body with a weak ETag taken from the body itself, so anything that
+//! changes what a user would see changes the tag, including per-viewer
+//! fields like unseen activity. A request whose `If-None-Match` names the
+//! current tag gets a bodiless 304.
+//!
+//! The handler still does its queries; what a 304 saves is sending the list
+//! again. The browser's HTTP cache sends `If-None-Match` and fills in the
+//! cached body on its own, so `fetch` callers need no changes.
+
+use std::io::Cursor;
+
+use rocket::Request;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+/// A JSON body answered with an ETag, or with 304 Not Modified when the
+/// request already has it.
+pub struct ETagged<T>(pub T);
+
+/// `W/"<hash>"` for `body`.
+pub fn weak_etag(body: &[u8]) -> String {
+    format!("W/\"{}\"", hex::encode(&Sha256::digest(body)[..16]))
+}
+
+/// Whether an `If-None-Match` value names `etag`. Comparison is weak, so
+/// `W/` prefixes are ignored on both sides.
+fn if_none_match(header: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    header.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for ETagged<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_string(&self.0).map_err(|e| {
+            error!(error = %e, "Failed to serialize response");
+            Status::InternalServerError
+        })?;
+        let etag = weak_etag(body.as_bytes());
+
+        let mut response = Response::build();
+        response
+            .raw_header("ETag", etag.clone())
+            // Cache it, but ask every time before using it.
+            .header(Header::new("Cache-Control", "private, no-cache"));
+        if request.headers().get("If-None-Match").any(|header| if_none_match(header, &etag)) {
+            return response.status(Status::NotModified).ok();
+        }
+        response
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
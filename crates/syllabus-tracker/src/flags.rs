@@ -0,0 +1,107 @@
+//! Feature flags that admins can flip at runtime through
+//! `/api/admin/feature_flags`. Each flag's default is compiled in; the
+//! `feature_flags` table only holds overrides, so a fresh deploy behaves the
+//! same as before a flag existed.
+//!
+//! `VIDEOS_ENABLED` is deliberately not one of these: it decides which routes
+//! get mounted, so it can only change with a restart (see `Capabilities`).
+
+use std::collections::{BTreeMap, HashMap};
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use sqlx::SqlitePool;
+
+use crate::db::get_feature_flag_overrides;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flag {
+    SelfRegistration,
+    PublicSharing,
+    Webhooks,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 3] = [Flag::SelfRegistration, Flag::PublicSharing, Flag::Webhooks];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Flag::SelfRegistration => "self_registration",
+            Flag::PublicSharing => "public_sharing",
+            Flag::Webhooks => "webhooks",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|flag| flag.key() == key)
+    }
+
+    /// Self-registration predates the flag table, so it stays on until an
+    /// admin turns it off.
+    pub fn default_enabled(&self) -> bool {
+        match self {
+            Flag::SelfRegistration => true,
+            Flag::PublicSharing | Flag::Webhooks => false,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Flag::SelfRegistration => "Let visitors create pending student accounts",
+            Flag::PublicSharing => "Publish the technique library and class schedule publicly",
+            Flag::Webhooks => "Send outbound webhooks for syllabus events",
+        }
+    }
+}
+
+/// Flag values for the current request, read once from the database when the
+/// guard runs.
+#[derive(Debug, Clone)]
+pub struct Flags {
+    overrides: HashMap<String, bool>,
+}
+
+impl Flags {
+    pub fn from_overrides(overrides: HashMap<String, bool>) -> Self {
+        Self { overrides }
+    }
+
+    pub fn is_enabled(&self, flag: Flag) -> bool {
+        self.overrides
+            .get(flag.key())
+            .copied()
+            .unwrap_or_else(|| flag.default_enabled())
+    }
+
+    pub fn is_overridden(&self, flag: Flag) -> bool {
+        self.overrides.contains_key(flag.key())
+    }
+
+    /// Every flag's effective value, keyed for JSON responses.
+    pub fn snapshot(&self) -> BTreeMap<&'static str, bool> {
+        Flag::ALL
+            .into_iter()
+            .map(|flag| (flag.key(), self.is_enabled(flag)))
+            .collect()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Flags {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(db) = request.rocket().state::<SqlitePool>() else {
+            tracing::error!("Database pool not found in managed state");
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+
+        match get_feature_flag_overrides(db).await {
+            Ok(overrides) => Outcome::Success(Flags::from_overrides(overrides)),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to load feature flags");
+                Outcome::Error((Status::InternalServerError, ()))
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Message catalogs for user-facing error text, selected per request from
+//! `Accept-Language`. English is the source language: each message's English
+//! text lives where the error is raised, keyed by a stable code such as
+//! `password.too_short`. The catalogs here hold translations only, and any
+//! key a locale lacks falls back to that English text.
+//!
+//! Templates use `{name}` placeholders filled from the error's params, so
+//! `{min}` in `password.too_short` becomes the configured minimum.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use rocket::Request;
+use rocket::request::{FromRequest, Outcome};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    Pt,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+            Locale::Pt => "pt",
+        }
+    }
+
+    /// Matches on the primary subtag only, so `pt-BR` and `pt-PT` both get
+    /// the Portuguese catalog.
+    fn from_language_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?.trim().to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            "pt" => Some(Locale::Pt),
+            _ => None,
+        }
+    }
+
+    /// Picks the highest-weighted supported language from an
+    /// `Accept-Language` value, e.g. `pt-BR,pt;q=0.9,en;q=0.8`. Entries with
+    /// `q=0` are refused; anything unparseable or unsupported falls back to
+    /// English.
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut candidates: Vec<(f32, Locale)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = Self::from_language_tag(parts.next()?)?;
+                let weight = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (weight > 0.0).then_some((weight, locale))
+            })
+            .collect();
+        // Stable sort keeps header order among equal weights.
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, l)| *l).unwrap_or_default()
+    }
+
+    pub fn from_request(req: &Request<'_>) -> Self {
+        req.headers()
+            .get_one("Accept-Language")
+            .map(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+}
+
+/// For handlers that localize errors inside a successful response, like a
+/// batch reporting each item.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Locale {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Locale::from_request(request))
+    }
+}
+
+/// Translation of `key` for `locale` with placeholders filled from `params`.
+/// `None` when the locale has no entry, in which case the caller keeps its
+/// English message.
+pub fn translate(
+    locale: Locale,
+    key: &str,
+    params: &HashMap<Cow<'static, str>, Value>,
+) -> Option<String> {
+    let template = catalog(locale)
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, t)| *t)?;
+
+    let mut message = template.to_string();
+    for (name, value) in params {
+        let placeholder = format!("{{{}}}", name);
+        if message.contains(&placeholder) {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            message = message.replace(&placeholder, &value);
+        }
+    }
+    Some(message)
+}
+
+fn catalog(locale: Locale) -> &'static [(&'static str, &'static str)] {
+    match locale {
+        Locale::En => &[],
+        Locale::Es => ES,
+        Locale::Pt => PT,
+    }
+}
+
+const ES: &[(&str, &str)] = &[
+    ("request.invalid_body", "El cuerpo de la solicitud no es válido: {detail}"),
+    ("username.required", "El nombre de usuario no puede estar vacío"),
+    ("username.length", "El nombre de usuario debe tener entre {min} y {max} caracteres"),
+    (
+        "username.invalid_chars",
+        "El nombre de usuario solo puede contener letras, números, '.', '_' y '-'",
+    ),
+    ("username.taken", "Ese nombre de usuario ya está en uso"),
+    ("password.required", "La contraseña no puede estar vacía"),
+    ("password.current_required", "La contraseña actual no puede estar vacía"),
+    ("password.too_short", "La contraseña debe tener al menos {min} caracteres"),
+    ("password.mismatch", "Las contraseñas deben coincidir"),
+    ("display_name.required", "El nombre visible es obligatorio"),
+    ("display_name.too_long", "El nombre visible debe tener menos de {max} caracteres"),
+    ("name.required", "El nombre es obligatorio"),
+    ("name.too_long", "El nombre debe tener menos de {max} caracteres"),
+    ("technique_name.length", "El nombre de la técnica debe tener entre 1 y {max} caracteres"),
+    ("technique.duplicate", "Ya existen técnicas similares: {names}"),
+    ("tag_name.length", "El nombre de la etiqueta debe tener entre 1 y {max} caracteres"),
+    ("description.required", "La descripción no puede estar vacía"),
+    ("note.too_long", "La nota debe tener menos de {max} caracteres"),
+    ("notes.too_long", "Las notas deben tener menos de {max} caracteres"),
+    ("description.too_long", "La descripción debe tener menos de {max} caracteres"),
+    ("timezone.invalid", "Zona horaria desconocida"),
+    ("preferences.too_large", "Las preferencias deben ocupar menos de {max} bytes"),
+    ("status.required", "Los nombres de estado no pueden estar vacíos"),
+    ("status.transition_not_allowed", "El estado no puede pasar de {from} a {to}"),
+    (
+        "status.transition_requires_role",
+        "Solo un {min_role} puede cambiar el estado de {from} a {to}",
+    ),
+    ("status.transition_to_self", "{status} no puede pasar a sí mismo"),
+    ("status.min_role_invalid", "El rol de la transición debe ser coach o admin"),
+    ("status.unknown", "{status} no es un estado"),
+    ("status.taken", "Ya existe un estado con ese nombre"),
+    ("status.in_use", "{status} está en uso y no se puede eliminar"),
+    ("statuses.mismatch", "Incluye cada estado exactamente una vez"),
+    ("color.invalid", "El color debe tener el formato #rrggbb"),
+    ("category.too_long", "La categoría debe tener menos de {max} caracteres"),
+    ("parent_id.cycle", "Una etiqueta no puede anidarse dentro de sí misma"),
+    ("prerequisite.cycle", "Una técnica no puede ser requisito previo de sí misma"),
+    ("parent_technique_id.nested", "Las variantes solo pueden tener un nivel de profundidad"),
+    ("technique.prerequisites_unmet", "Aún no está en verde en los requisitos previos: {names}"),
+    ("url.invalid", "El enlace debe empezar por http:// o https://"),
+    ("media.source", "Indica un enlace o un archivo adjunto, no ambos"),
+    ("assign.group", "Indica una etiqueta o un plan de estudios, no ambos"),
+    ("duration.invalid", "La duración debe estar entre 1 y {max} minutos"),
+    ("weekday.invalid", "El día debe ir del 1 (lunes) al 7 (domingo)"),
+    ("ends_on.before_start", "La fecha de fin no puede ser anterior a la de inicio"),
+    ("technique_ids.required", "Debes seleccionar al menos una técnica"),
+    ("updates.count", "Envía entre 1 y {max} cambios"),
+    ("updates.duplicate", "Cada técnica solo puede cambiarse una vez por lote"),
+    ("scopes.required", "Elige al menos un permiso"),
+    ("expires_in_days.range", "La caducidad debe estar entre 1 y 365 días"),
+    ("validation.invalid", "Valor no válido"),
+    ("error.permission", "No tienes permiso para realizar esta acción"),
+    ("error.authentication_required", "Debes iniciar sesión"),
+    ("error.not_found", "Recurso no encontrado"),
+    ("error.conflict", "El recurso ya existe"),
+    ("error.bad_request", "Solicitud incorrecta"),
+    ("error.payload_too_large", "El cuerpo de la solicitud es demasiado grande"),
+    ("error.validation_failed", "La validación ha fallado"),
+    ("error.internal", "Error interno del servidor"),
+    ("error.unavailable", "Servicio no disponible"),
+    ("error.generic", "Se ha producido un error"),
+    ("error.database", "Error de base de datos: {detail}"),
+    ("error.authentication", "Error de autenticación: {detail}"),
+    ("error.authorization", "Permiso denegado: {detail}"),
+    ("error.resource_not_found", "No encontrado: {detail}"),
+    ("error.service", "Error del servicio: {detail}"),
+];
+
+const PT: &[(&str, &str)] = &[
+    ("request.invalid_body", "O corpo da requisição é inválido: {detail}"),
+    ("username.required", "O nome de usuário não pode ficar vazio"),
+    ("username.length", "O nome de usuário deve ter entre {min} e {max} caracteres"),
+    (
+        "username.invalid_chars",
+        "O nome de usuário só pode conter letras, números, '.', '_' e '-'",
+    ),
+    ("username.taken", "Esse nome de usuário já está em uso"),
+    ("password.required", "A senha não pode ficar vazia"),
+    ("password.current_required", "A senha atual não pode ficar vazia"),
+    ("password.too_short", "A senha deve ter pelo menos {min} caracteres"),
+    ("password.mismatch", "As senhas devem coincidir"),
+    ("display_name.required", "O nome de exibição é obrigatório"),
+    ("display_name.too_long", "O nome de exibição deve ter menos de {max} caracteres"),
+    ("name.required", "O nome é obrigatório"),
+    ("name.too_long", "O nome deve ter menos de {max} caracteres"),
+    ("technique_name.length", "O nome da técnica deve ter entre 1 e {max} caracteres"),
+    ("technique.duplicate", "Já existem técnicas parecidas: {names}"),
+    ("tag_name.length", "O nome da tag deve ter entre 1 e {max} caracteres"),
+    ("description.required", "A descrição não pode ficar vazia"),
+    ("note.too_long", "A nota deve ter menos de {max} caracteres"),
+    ("notes.too_long", "As notas devem ter menos de {max} caracteres"),
+    ("description.too_long", "A descrição deve ter menos de {max} caracteres"),
+    ("timezone.invalid", "Fuso horário desconhecido"),
+    ("preferences.too_large", "As preferências devem ocupar menos de {max} bytes"),
+    ("status.required", "Os nomes de status não podem ficar vazios"),
+    ("status.transition_not_allowed", "O status não pode mudar de {from} para {to}"),
+    (
+        "status.transition_requires_role",
+        "Somente um {min_role} pode mudar o status de {from} para {to}",
+    ),
+    ("status.transition_to_self", "{status} não pode mudar para si mesmo"),
+    ("status.min_role_invalid", "O papel da transição deve ser coach ou admin"),
+    ("status.unknown", "{status} não é um status"),
+    ("status.taken", "Já existe um status com esse nome"),
+    ("status.in_use", "{status} está em uso e não pode ser excluído"),
+    ("statuses.mismatch", "Inclua cada status exatamente uma vez"),
+    ("color.invalid", "A cor deve estar no formato #rrggbb"),
+    ("category.too_long", "A categoria deve ter menos de {max} caracteres"),
+    ("parent_id.cycle", "Uma etiqueta não pode ser aninhada dentro de si mesma"),
+    ("prerequisite.cycle", "Uma técnica não pode ser pré-requisito de si mesma"),
+    ("parent_technique_id.nested", "As variantes só podem ter um nível de profundidade"),
+    ("technique.prerequisites_unmet", "Ainda não está no verde nos pré-requisitos: {names}"),
+    ("url.invalid", "O link deve começar com http:// ou https://"),
+    ("media.source", "Informe um link ou um anexo, não ambos"),
+    ("assign.group", "Informe uma etiqueta ou um currículo, não ambos"),
+    ("duration.invalid", "A duração deve estar entre 1 e {max} minutos"),
+    ("weekday.invalid", "O dia deve ir de 1 (segunda) a 7 (domingo)"),
+    ("ends_on.before_start", "A data de término não pode ser anterior à de início"),
+    ("technique_ids.required", "Selecione pelo menos uma técnica"),
+    ("updates.count", "Envie entre 1 e {max} alterações"),
+    ("updates.duplicate", "Cada técnica só pode ser alterada uma vez por lote"),
+    ("scopes.required", "Escolha pelo menos uma permissão"),
+    ("expires_in_days.range", "A validade deve estar entre 1 e 365 dias"),
+    ("validation.invalid", "Valor inválido"),
+    ("error.permission", "Você não tem permissão para realizar esta ação"),
+    ("error.authentication_required", "É necessário fazer login"),
+    ("error.not_found", "Recurso não encontrado"),
+    ("error.conflict", "O recurso já existe"),
+    ("error.bad_request", "Requisição inválida"),
+    ("error.payload_too_large", "O corpo da requisição é grande demais"),
+    ("error.validation_failed", "A validação falhou"),
+    ("error.internal", "Erro interno do servidor"),
+    ("error.unavailable", "Serviço indisponível"),
+    ("error.generic", "Ocorreu um erro"),
+    ("error.database", "Erro de banco de dados: {detail}"),
+    ("error.authentication", "Erro de autenticação: {detail}"),
+    ("error.authorization", "Permissão negada: {detail}"),
+    ("error.resource_not_found", "Não encontrado: {detail}"),
+    ("error.service", "Erro do serviço: {detail}"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_language_picks_the_highest_weighted_supported_locale() {
+        assert_eq!(Locale::from_accept_language("pt-BR,pt;q=0.9,en;q=0.8"), Locale::Pt);
+        assert_eq!(Locale::from_accept_language("fr-CA, es;q=0.5"), Locale::Es);
+    }
+
+    #[test]
+    fn accept_language_refuses_zero_weights_and_falls_back_to_english() {
+        assert_eq!(Locale::from_accept_language("es;q=0, pt"), Locale::Pt);
+        assert_eq!(Locale::from_accept_language("fr"), Locale::En);
+    }
+}
@@ -0,0 +1,92 @@
+//! iCalendar (RFC 5545) export of the class timetable, for calendar apps to
+//! subscribe to. Each schedule slot becomes one weekly recurring event.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+
+use crate::db::ScheduleSlot;
+
+const BYDAY: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+/// The whole timetable as a `VCALENDAR`, with CRLF line endings. Slots
+/// keep their own `TZID`, so calendar apps follow daylight saving.
+pub fn schedule_calendar(slots: &[ScheduleSlot], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Syllabus Tracker//Class schedule//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Class schedule".to_string(),
+    ];
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    for slot in slots {
+        let Some(byday) = usize::try_from(slot.weekday - 1).ok().and_then(|i| BYDAY.get(i)) else {
+            continue;
+        };
+        let first = first_on_weekday(slot.starts_on, slot.weekday);
+        let mut rrule = format!("RRULE:FREQ=WEEKLY;BYDAY={}", byday);
+        if let Some(ends_on) = slot.ends_on {
+            rrule.push_str(&format!(";UNTIL={}", until(ends_on, &slot.timezone)));
+        }
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:schedule-{}@syllabus-tracker", slot.id),
+            format!("DTSTAMP:{}", stamp),
+            format!(
+                "DTSTART;TZID={}:{}",
+                slot.timezone,
+                first.and_time(slot.start_time).format("%Y%m%dT%H%M%S")
+            ),
+            format!("DURATION:PT{}M", slot.duration_minutes),
+            rrule,
+            format!("SUMMARY:{}", escape_text(&slot.title)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines {
+        fold_line(&line, &mut calendar);
+    }
+    calendar
+}
+
+/// The first date on or after `from` that falls on ISO `weekday`.
+fn first_on_weekday(from: NaiveDate, weekday: i64) -> NaiveDate {
+    let offset = (weekday - i64::from(from.weekday().number_from_monday())).rem_euclid(7);
+    from + Duration::days(offset)
+}
+
+/// `UNTIL` must be UTC when `DTSTART` has a `TZID`: the end of `ends_on` in
+/// the slot's timezone.
+fn until(ends_on: NaiveDate, timezone: &str) -> String {
+    let end_of_day = ends_on.and_hms_opt(23, 59, 59).unwrap_or_default();
+    let utc = timezone
+        .parse::<chrono_tz::Tz>()
+        .ok()
+        .and_then(|tz| tz.from_local_datetime(&end_of_day).earliest())
+        .map(|local| local.with_timezone(&Utc))
+        .unwrap_or_else(|| end_of_day.and_utc());
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines longer than 75 octets continue on the next line after a space.
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
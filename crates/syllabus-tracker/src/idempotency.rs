@@ -0,0 +1,264 @@
+//! `Idempotency-Key` support for POSTs, so a client retrying over a flaky
+//! connection doesn't assign or create something twice. The first request
+//! with a key runs as normal, and if it succeeds its response is stored;
+//! a retry with the same key gets that response back, marked with
+//! `Idempotent-Replayed: true`, without the handler running again.
+//!
+//! Keys belong to the signed-in user, whether by session or API token, and
+//! are remembered for `IDEMPOTENCY_KEY_TTL_HOURS`. Signed-out requests
+//! ignore the header, as there is nobody to keep the key apart for. A key
+//! reused with a different body is refused with 422. A request that fails
+//! releases its key, so retrying it does the work. Routes that set cookies
+//! or answer with a secret are never stored (see `UNSTORED_ROUTES`).
+
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{ContentType, Header, Method, Status};
+use rocket::{Data, Request, Response};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Sqlite};
+use tracing::{error, info, warn};
+
+use crate::auth::bearer_token;
+use crate::db::{
+    IdempotencyClaim, StoredResponse, claim_idempotency_key, find_api_token_grant,
+    get_session_by_token, release_idempotency_key, store_idempotent_response,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// Longer keys are refused rather than stored. A UUID is 36.
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// The most of a body a fairing can read before the handler does. A keyed
+/// request with a longer one is refused, since a retry's body couldn't be
+/// checked against the first.
+pub const MAX_BODY_LENGTH: usize = 512;
+
+/// Handlers whose responses are never stored, so a retry runs them again.
+const UNSTORED_ROUTES: &[&str] = &[
+    // They sign the caller in or out, and a replay couldn't set the cookies.
+    // Keep in step with the callers of `api::establish_session`.
+    "api_login",
+    "api_logout",
+    "api_claim_invite",
+    "api_register_with_invite",
+    "api_self_register",
+    // The response carries a secret that must never reach the database.
+    "api_create_api_token",
+];
+
+/// Where a request that mustn't reach its handler is sent. Nothing is
+/// mounted under v0.
+const NO_ROUTE: &str = "/api/v0/idempotent";
+
+/// What `IdempotencyFairing` settled on for a request.
+enum Idempotent {
+    /// No key, or not a POST to the API.
+    NotApplicable,
+    /// This request claimed the key and runs as normal.
+    Claimed { scope: String, key: String },
+    /// This request is answered without running, with the stored response
+    /// or the reason it was refused.
+    Answered(Answer),
+}
+
+enum Answer {
+    Replay(StoredResponse),
+    InProgress,
+    Mismatch,
+    BadKey,
+    BodyTooLarge,
+}
+
+/// The scope a key is claimed in: the user an API token or session cookie
+/// belongs to, or `None` when signed out.
+async fn key_scope(request: &Request<'_>, pool: &Pool<Sqlite>) -> Option<String> {
+    if let Some(token) = bearer_token(request) {
+        return match find_api_token_grant(pool, token).await {
+            Ok(Some(grant)) if grant.is_valid() => Some(format!("user:{}", grant.user_id)),
+            _ => None,
+        };
+    }
+    let token = request.cookies().get_private("session_token")?;
+    match get_session_by_token(pool, token.value()).await {
+        Ok(session) if session.is_valid() => Some(format!("user:{}", session.user_id)),
+        _ => None,
+    }
+}
+
+/// SHA-256 of the request's content type and body, or `None` if the body is
+/// longer than `MAX_BODY_LENGTH`.
+async fn request_hash(request: &Request<'_>, data: &mut Data<'_>) -> Option<String> {
+    let body = data.peek(MAX_BODY_LENGTH).await.to_vec();
+    if !data.peek_complete() {
+        return None;
+    }
+    let content_type = request.content_type().map(|c| c.to_string()).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(content_type.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(&body);
+    Some(hex::encode(hasher.finalize()))
+}
+
+async fn claim(request: &Request<'_>, data: &mut Data<'_>) -> Idempotent {
+    let Some(key) = request.headers().get_one(IDEMPOTENCY_KEY_HEADER) else {
+        return Idempotent::NotApplicable;
+    };
+    let key = key.trim();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Idempotent::Answered(Answer::BadKey);
+    }
+    let Some(pool) = request.rocket().state::<Pool<Sqlite>>() else {
+        error!("Database pool not found in managed state");
+        return Idempotent::NotApplicable;
+    };
+
+    let Some(scope) = key_scope(request, pool).await else {
+        return Idempotent::NotApplicable;
+    };
+    let Some(hash) = request_hash(request, data).await else {
+        return Idempotent::Answered(Answer::BodyTooLarge);
+    };
+    let path = request.uri().path().to_string();
+    let method = request.method().as_str();
+    match claim_idempotency_key(pool, &scope, key, method, &path, &hash).await {
+        Ok(IdempotencyClaim::Claimed) => Idempotent::Claimed { scope, key: key.to_string() },
+        Ok(IdempotencyClaim::InProgress) => Idempotent::Answered(Answer::InProgress),
+        Ok(IdempotencyClaim::Mismatch) => Idempotent::Answered(Answer::Mismatch),
+        Ok(IdempotencyClaim::Replay(stored)) => {
+            info!(path = %path, "Replaying idempotent response");
+            Idempotent::Answered(Answer::Replay(stored))
+        }
+        // Without the table the request still runs, just without the guard.
+        Err(e) => {
+            error!(error = %e, "Failed to claim idempotency key");
+            Idempotent::NotApplicable
+        }
+    }
+}
+
+fn set_json_error(response: &mut Response<'_>, status: Status, hint: &str) {
+    let body = json!({
+        "error": status.reason().unwrap_or("Error"),
+        "status": status.code,
+        "hint": hint,
+    })
+    .to_string();
+    response.set_status(status);
+    response.set_header(ContentType::JSON);
+    response.set_sized_body(body.len(), Cursor::new(body));
+}
+
+/// A successful response as it will be stored, read out of `response` and
+/// put back. `None` for any other, or one with a body that isn't text.
+async fn readable_success(response: &mut Response<'_>) -> Option<StoredResponse> {
+    if !response.status().class().is_success() {
+        return None;
+    }
+    let bytes = match response.body_mut().to_bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!(error = %e, "Failed to read response for idempotency key");
+            return None;
+        }
+    };
+    let body = String::from_utf8(bytes.clone()).ok();
+    response.set_sized_body(bytes.len(), Cursor::new(bytes));
+    Some(StoredResponse {
+        status: response.status().code,
+        content_type: response.content_type().map(|c| c.to_string()),
+        body: body?,
+    })
+}
+
+/// Stores a successful response for `key`, or releases the key for any
+/// other. Attach after `TransactionFairing` so a failed commit counts as a
+/// failure.
+#[derive(Debug)]
+pub struct IdempotencyFairing;
+
+#[rocket::async_trait]
+impl Fairing for IdempotencyFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Idempotency keys",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, data: &mut Data<'_>) {
+        if request.method() != Method::Post || !request.uri().path().starts_with("/api/") {
+            return;
+        }
+
+        let idempotent = claim(request, data).await;
+        if matches!(idempotent, Idempotent::Answered(_)) {
+            match Origin::parse(NO_ROUTE) {
+                Ok(origin) => request.set_uri(origin),
+                Err(e) => error!(error = %e, "Failed to hold back an idempotent request"),
+            }
+        }
+        request.local_cache(|| idempotent);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        match request.local_cache(|| Idempotent::NotApplicable) {
+            Idempotent::NotApplicable => {}
+            Idempotent::Claimed { scope, key } => {
+                let Some(pool) = request.rocket().state::<Pool<Sqlite>>() else {
+                    return;
+                };
+                let route = request.route().and_then(|route| route.name.as_deref());
+                let stored = if route.is_some_and(|name| UNSTORED_ROUTES.contains(&name)) {
+                    None
+                } else {
+                    readable_success(response).await
+                };
+                let result = match stored {
+                    Some(stored) => store_idempotent_response(pool, scope, key, &stored).await,
+                    None => release_idempotency_key(pool, scope, key).await,
+                };
+                if let Err(e) = result {
+                    warn!(error = %e, "Failed to settle idempotency key");
+                }
+            }
+            Idempotent::Answered(Answer::Replay(stored)) => {
+                let status = Status::from_code(stored.status).unwrap_or(Status::Ok);
+                response.set_status(status);
+                response.remove_header("Content-Type");
+                let content_type = stored.content_type.as_deref();
+                if let Some(content_type) = content_type.and_then(ContentType::parse_flexible) {
+                    response.set_header(content_type);
+                }
+                response.set_sized_body(stored.body.len(), Cursor::new(stored.body.clone()));
+                response.set_header(Header::new(REPLAYED_HEADER, "true"));
+            }
+            Idempotent::Answered(Answer::InProgress) => set_json_error(
+                response,
+                Status::Conflict,
+                "A request with this Idempotency-Key is still being handled.",
+            ),
+            Idempotent::Answered(Answer::Mismatch) => set_json_error(
+                response,
+                Status::UnprocessableEntity,
+                "This Idempotency-Key was first used for a different request.",
+            ),
+            Idempotent::Answered(Answer::BadKey) => set_json_error(
+                response,
+                Status::BadRequest,
+                "Idempotency-Key must be 1 to 255 characters.",
+            ),
+            Idempotent::Answered(Answer::BodyTooLarge) => set_json_error(
+                response,
+                Status::PayloadTooLarge,
+                "Requests with an Idempotency-Key can have a body of at most 512 bytes.",
+            ),
+        }
+    }
+}
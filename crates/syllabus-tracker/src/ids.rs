@@ -0,0 +1,68 @@
+//! Typed row ids. `student_techniques.id` and `techniques.id` are both plain
+//! integers in SQLite, and passing one where the other is expected used to
+//! compile fine and fail quietly at runtime. These wrap the raw `i64` so the
+//! db and route signatures say which table an id belongs to.
+//!
+//! All of them are transparent on the wire (serde), in the database (sqlx)
+//! and in route paths (Rocket), so JSON shapes and URLs are unchanged.
+
+use rocket::request::FromParam;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::num::ParseIntError;
+
+macro_rules! id_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            sqlx::Type,
+        )]
+        #[serde(transparent)]
+        #[sqlx(transparent)]
+        pub struct $name(pub i64);
+
+        impl From<i64> for $name {
+            fn from(id: i64) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl<'a> FromParam<'a> for $name {
+            type Error = ParseIntError;
+
+            fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+                param.parse().map(Self)
+            }
+        }
+    };
+}
+
+id_newtype!(
+    /// `users.id`.
+    UserId
+);
+id_newtype!(
+    /// `techniques.id`: the library technique, shared by every student.
+    TechniqueId
+);
+id_newtype!(
+    /// `student_techniques.id`: one student's assignment of a technique.
+    StudentTechniqueId
+);
+id_newtype!(
+    /// `tags.id`.
+    TagId
+);
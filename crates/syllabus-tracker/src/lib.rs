@@ -1,16 +1,33 @@
 #[macro_use]
 extern crate rocket;
 
+pub mod activity;
 pub mod api;
+pub mod attachments;
 pub mod auth;
+pub mod bootstrap;
 pub mod capabilities;
 pub mod catchers;
+pub mod config;
 pub mod db;
 pub mod env;
 pub mod error;
+pub mod etag;
+pub mod flags;
+pub mod i18n;
+pub mod ical;
+pub mod idempotency;
+pub mod ids;
 pub mod models;
+pub mod oidc;
+pub mod preflight;
+pub mod scheduler;
+pub mod system;
 pub mod telemetry;
+pub mod transaction;
 pub mod validation;
+pub mod version;
+pub mod versioning;
 pub mod videos;
 
 pub mod lib {
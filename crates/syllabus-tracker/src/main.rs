@@ -2,45 +2,92 @@
 extern crate rocket;
 
 pub use syllabus_tracker::{
-    api, auth, capabilities, catchers, db, env, error, models, telemetry, validation, videos,
+    activity, api, attachments, auth, bootstrap, capabilities, catchers, config, db, env, error,
+    etag, flags, i18n, ical, idempotency, ids, models, oidc, preflight, scheduler, system,
+    telemetry, transaction, validation, version, versioning, videos,
 };
 
 #[cfg(test)]
 mod test;
 
+use activity::{ActivityFeed, api_coach_live};
 use api::api_get_all_users;
 use api::{
     api_add_tag_to_technique, api_add_techniques_to_collection, api_approve_user,
-    api_assign_collection, api_assign_techniques, api_attempt_heatmap, api_attempt_sparkline,
+    api_assign_by_tag, api_assign_collection, api_assign_techniques, api_attempt_heatmap,
+    api_attempt_sparkline,
+    api_remove_techniques, api_reorder_collection_techniques,
     api_attempt_summary, api_change_password, api_claim_invite,
-    api_create_and_assign_technique, api_create_attempt, api_create_collection, api_create_tag,
-    api_create_technique_in_collection, api_delete_attempt, api_delete_collection, api_delete_tag,
+    api_create_and_assign_technique, api_create_attempt, api_create_collection,
+    api_create_invitation, api_create_tag,
+    api_create_technique_in_collection, api_create_techniques_bulk, api_delete_attempt,
+    api_delete_collection, api_delete_tag,
     api_get_all_tags, api_get_collection, api_get_collection_students, api_get_collections,
-    api_get_invite, api_get_single_student_technique, api_get_student_techniques,
-    api_get_students, api_get_technique_tags,
-    api_get_unassigned_techniques, api_invite_user, api_library_stats,
+    api_get_api_tokens, api_create_api_token, api_revoke_api_token,
+    api_get_feature_flags, api_get_invite, api_get_notification_preferences,
+    api_get_notifications, api_get_preferences, api_mark_notification_read,
+    api_get_single_student_technique, api_get_student_techniques,
+    api_get_status_transitions, api_get_students, api_get_tag_tree, api_get_technique_tags,
+    api_get_unassigned_techniques, api_import_memberships, api_import_spreadsheet,
+    api_invite_user, api_library_stats, api_register_with_invite,
     api_library_technique_stats, api_list_library_techniques, api_list_attempts,
+    api_search_techniques,
     api_login, api_logout, api_mark_student_technique_seen, api_me, api_me_unauthorized,
-    api_recent_attempts, api_register_user,
-    api_remove_tag_from_technique, api_remove_technique_from_collection,
+    api_membership_webhook, api_note_history, api_restore_note,
+    api_student_technique_history, api_get_note_templates,
+    api_bulk_status, api_create_note_template, api_update_note_template,
+    api_delete_note_template, api_add_technique_alias, api_remove_technique_alias, api_get_ranks,
+    api_get_technique_prerequisites, api_add_technique_prerequisite,
+    api_remove_technique_prerequisite,
+    api_replace_ranks, api_rank_eligibility, api_set_student_rank, api_set_tag_archived,
+    api_coach_report, api_get_job, api_get_journal, api_get_student_journal,
+    api_create_journal_entry,
+    api_update_journal_entry,
+    api_delete_journal_entry, api_create_class, api_list_classes, api_get_class,
+    api_set_class_attendance, api_student_attendance, api_get_schedule, api_create_schedule_slot,
+    api_update_schedule_slot, api_delete_schedule_slot, api_schedule_ics, api_open_grading,
+    api_list_gradings, api_get_grading, api_record_grading_result, api_close_grading,
+    api_recent_attempts, api_register_user, api_reload_config, api_rename_tag,
+    api_remove_tag_from_technique, api_remove_technique_from_collection, api_request_review,
+    api_replace_status_transitions, api_get_statuses, api_create_status, api_update_status,
+    api_reorder_statuses, api_delete_status,
     api_request_password_reset, api_reset_user_claim, api_self_register,
-    api_set_student_graduated, api_update_attempt, api_update_collection,
-    api_update_library_technique, api_update_profile, api_update_student_technique,
-    api_update_user, health,
+    api_set_feature_flag, api_set_student_graduated, api_student_analytics, api_update_attempt,
+    api_student_progress, api_export_student_techniques,
+    api_update_collection,
+    api_update_library_technique, api_delete_technique, api_restore_technique,
+    api_get_technique_media, api_add_technique_media, api_remove_technique_media,
+    api_update_profile, api_update_student_technique, api_batch_update_student_techniques,
+    api_update_notification_preferences, api_update_preferences, api_update_timezone,
+    api_public_syllabus, api_update_user, api_get_coach_students, api_set_coach_students, health,
+};
+use attachments::{
+    AttachmentCleanup, api_attachment_download_url, api_attachment_file, api_delete_avatar,
+    api_upload_avatar, api_upload_file, api_user_avatar,
 };
 use auth::unauthorized_api;
+use bootstrap::AdminBootstrap;
 use capabilities::{Capabilities, api_capabilities};
 use catchers::{
     bad_request, default_catcher, internal_error, not_found, payload_too_large,
     unprocessable_entity,
 };
-use db::clean_expired_sessions;
+use config::{AppConfig, LiveConfig};
+use db::run_data_migrations;
 use error::AppError;
-use rocket::{Build, Rocket, tokio};
+use idempotency::IdempotencyFairing;
+use oidc::{api_oidc_callback, api_oidc_login};
+use rocket::{Build, Rocket};
 use migration_engine::migrations::{get_schema_changes, read_schema_file_to_string};
-use telemetry::TelemetryFairing;
+use scheduler::{BadgeAwards, IdempotencyKeyCleanup, Scheduler, SessionCleanup, StaleBackgroundJobs};
+use system::api_system;
+use telemetry::{SlowRequestFairing, TelemetryFairing};
 use telemetry::init_tracing;
+use transaction::TransactionFairing;
 use thiserror::Error;
+use version::api_version;
+use versioning::{ApiVersionFairing, MountApi};
+use videos::metrics::VideoGauges;
 use videos::{
     api_admin_storage, api_dashboard_video_overview, api_delete_video, api_list_technique_videos,
     api_my_watch_state, api_reorder_videos, api_replace_video,
@@ -86,56 +133,58 @@ async fn rocket() -> _ {
         eprintln!("Failed to load environment variables: {}", e);
     }
 
-    let videos_enabled = dotenvy::var("VIDEOS_ENABLED")
-        .map(|v| v == "true" || v == "1")
-        .unwrap_or(false);
+    let (report, config) = preflight::run(&AppConfig::figment()).await;
+    eprint!("{}", report);
+    let config = match config {
+        Some(config) if report.passed() => config,
+        _ => {
+            eprintln!("Startup checks failed; fix the items marked FAIL above.");
+            std::process::exit(1);
+        }
+    };
+    preflight::keep_startup_report(report);
+    let videos_enabled = config.videos_enabled;
 
-    init_tracing(videos_enabled);
+    init_tracing(videos_enabled, &config.rust_log, config.otel_exporter_otlp_protocol);
 
     info!("Feature flag VIDEOS_ENABLED = {}", videos_enabled);
 
-    let database_url =
-        dotenvy::var("DATABASE_URL").expect("Failed to get database url from environment");
+    if let Some(cost) = config.bcrypt_cost {
+        db::init_bcrypt_cost(cost);
+    }
+    info!("Password hashing uses bcrypt cost {}", db::bcrypt_cost());
 
-    let opts = SqliteConnectOptions::from_str(&database_url)
+    let opts = SqliteConnectOptions::from_str(&config.database_url)
         .expect("Failed to parse DATABASE_URL")
+        .create_if_missing(config.create_database)
         .pragma("journal_mode", "WAL")
         .pragma("synchronous", "NORMAL")
         .pragma("busy_timeout", "5000")
         .pragma("foreign_keys", "ON");
-    let pool = SqlitePool::connect_with(opts)
-        .await
-        .expect("Failed to connect to SQLite database");
-
-    let pool_clone = pool.clone();
-
-    tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-
-        loop {
-            match clean_expired_sessions(&pool_clone).await {
-                Ok(count) => {
-                    if count > 0 {
-                        info!("Cleaned up {} expired sessions", count);
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to clean expired sessions: {}", e);
-                }
-            }
-
-            tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
-        }
-    });
+    let max_wait = config.db_connect_max_wait();
+    let pool = bootstrap::retry_startup("connect", max_wait, || {
+        SqlitePool::connect_with(opts.clone())
+    })
+    .await
+    .expect("Failed to connect to SQLite database");
 
     // Panic if db schema isn't up to date or database doesn't exist
-    let schema_path =
-        dotenvy::var("SCHEMA_PATH").expect("SCHEMA_PATH environment variable not set");
-    let schema = read_schema_file_to_string(std::path::Path::new(&schema_path))
+    let schema = read_schema_file_to_string(&config.schema_path)
         .expect("Failed to read schema file");
-    let changes = get_schema_changes(pool.clone(), &schema)
+    if config.create_database
+        && bootstrap::retry_startup("schema creation", max_wait, || {
+            bootstrap::migrate_empty_database(&pool, &schema)
+        })
         .await
-        .unwrap_or_else(|e| panic!("Failed to analyze database schema: {:?}", e));
+        .unwrap_or_else(|e| panic!("Failed to create database schema: {:?}", e))
+    {
+        info!("Created database schema from {}", config.schema_path.display());
+    }
+    let changes = bootstrap::retry_startup("schema check", max_wait, || {
+        get_schema_changes(pool.clone(), &schema)
+    })
+    .await
+    .unwrap_or_else(|e| panic!("Failed to analyze database schema: {:?}", e));
 
     if changes.has_any_changes() {
         error!("Database schema is out of sync with config/schema.sql:");
@@ -173,6 +222,26 @@ async fn rocket() -> _ {
     }
     info!("Database schema matches config/schema.sql");
 
+    run_data_migrations(&pool)
+        .await
+        .unwrap_or_else(|e| panic!("Data migrations failed: {}", e));
+
+    match bootstrap::bootstrap_admin(&pool, config.bootstrap_admin_credentials()).await {
+        Ok(Some(AdminBootstrap::Created { username })) => {
+            eprintln!("First run: created admin '{}' from BOOTSTRAP_ADMIN_*.", username);
+        }
+        Ok(Some(AdminBootstrap::Invited { claim_path })) => {
+            // Printed rather than logged so the token stays out of traces.
+            eprintln!(
+                "First run: no users yet. Set up the admin account within 7 days at\n  {}\n\
+                 This link is only shown once.",
+                claim_path
+            );
+        }
+        Ok(None) => {}
+        Err(e) => panic!("Failed to create initial admin: {}", e),
+    }
+
     let video_stack = if videos_enabled {
         let storage_config = videos::S3Config::from_env()
             .expect("VIDEOS_ENABLED=true but S3 config missing from environment");
@@ -185,32 +254,40 @@ async fn rocket() -> _ {
         None
     };
 
-    init_rocket(pool, video_stack).await
-}
-
-async fn sample_video_gauges(pool: &SqlitePool, active_jobs: i64) {
-    let metrics = videos::metrics::video_metrics();
-    metrics.processing_jobs_active.record(active_jobs, &[]);
-    match db::total_video_storage_bytes(pool).await {
-        Ok(bytes) => metrics.storage_bytes_total.record(bytes.max(0) as u64, &[]),
-        Err(e) => error!("failed to sample storage bytes: {}", e),
+    let config = LiveConfig::new(config, AppConfig::reload_figment);
+    let rocket = init_rocket(pool.clone(), video_stack, config.clone()).await;
+
+    // Attached here rather than in `init_rocket` so test clients, which
+    // also lift off, don't start background jobs or take over SIGHUP.
+    let mut scheduler = Scheduler::new(pool)
+        .register(SessionCleanup)
+        .register(BadgeAwards)
+        .register(StaleBackgroundJobs)
+        .register(IdempotencyKeyCleanup);
+    if let Some(storage) = rocket.state::<attachments::DynStorage>() {
+        scheduler = scheduler.register(AttachmentCleanup::new(storage.clone()));
     }
-    match db::total_video_objects(pool).await {
-        Ok(count) => metrics.storage_objects_total.record(count.max(0) as u64, &[]),
-        Err(e) => error!("failed to sample storage objects: {}", e),
+    if let Some(jobs) = rocket.state::<std::sync::Arc<videos::ProcessingJobs>>() {
+        scheduler = scheduler.register(VideoGauges::new(jobs.clone()));
     }
+    rocket
+        .attach(scheduler.fairing())
+        .attach(config.reload_on_hangup())
 }
 
 pub async fn init_rocket(
     pool: SqlitePool,
     video_stack: Option<videos::VideoStack>,
+    config: LiveConfig,
 ) -> Rocket<Build> {
     info!("Starting syllabus tracker");
 
     let videos_enabled = video_stack.is_some();
 
-    let upload_limit = videos::routes::upload_byte_limit();
+    let upload_limit =
+        videos::routes::upload_byte_limit().max(attachments::upload_byte_limit(&config.get()));
     let limits = rocket::data::Limits::default()
+        .limit("json", config.get().json_body_limit())
         .limit("file", upload_limit)
         .limit("data-form", upload_limit);
 
@@ -231,66 +308,167 @@ pub async fn init_rocket(
         .merge(("limits", limits))
         .merge(("temp_dir", &temp_dir));
 
+    let (attachment_storage, local_attachments) = attachments::storage_from_config(&config.get())
+        .expect("ATTACHMENT_STORAGE=s3 but S3 config missing from environment");
+    let identity_provider = oidc::provider_from_config(&config.get());
+
     let mut rocket = rocket::custom(figment)
-        .manage(Capabilities { videos: videos_enabled })
-        .mount(
-            "/api",
-            routes![
-                api_login,
-                api_me,
-                api_me_unauthorized,
-                api_update_student_technique,
-                api_get_student_techniques,
-                api_logout,
-                api_get_students,
-                api_get_unassigned_techniques,
-                api_assign_techniques,
-                api_create_and_assign_technique,
-                api_register_user,
-                api_change_password,
-                api_update_profile,
-                api_update_user,
-                api_get_all_tags,
-                api_create_tag,
-                api_delete_tag,
-                api_add_tag_to_technique,
-                api_remove_tag_from_technique,
-                api_get_technique_tags,
-                api_get_all_users,
-                api_library_stats,
-                api_list_library_techniques,
-                api_library_technique_stats,
-                api_set_student_graduated,
-                api_mark_student_technique_seen,
-                api_invite_user,
-                api_get_invite,
-                api_claim_invite,
-                api_reset_user_claim,
-                api_self_register,
-                api_approve_user,
-                api_request_password_reset,
-                api_get_collections,
-                api_get_collection,
-                api_create_collection,
-                api_update_collection,
-                api_delete_collection,
-                api_add_techniques_to_collection,
-                api_create_technique_in_collection,
-                api_update_library_technique,
-                api_remove_technique_from_collection,
-                api_get_collection_students,
-                api_assign_collection,
-                api_get_single_student_technique,
-                api_list_attempts,
-                api_create_attempt,
-                api_update_attempt,
-                api_delete_attempt,
-                api_recent_attempts,
-                api_attempt_summary,
-                api_attempt_heatmap,
-                api_attempt_sparkline,
-            ],
-        )
+        .manage(Capabilities {
+            videos: videos_enabled,
+            oidc: identity_provider.is_some(),
+        })
+        .manage(config)
+        .manage(attachment_storage)
+        .manage(config.get().validation.clone())
+        .manage(ActivityFeed::new())
+        .mount_api(routes![
+            api_login,
+            api_oidc_login,
+            api_oidc_callback,
+            api_me,
+            api_me_unauthorized,
+            api_update_student_technique,
+            api_batch_update_student_techniques,
+            api_bulk_status,
+            api_get_ranks,
+            api_replace_ranks,
+            api_rank_eligibility,
+            api_set_student_rank,
+            api_set_tag_archived,
+            api_coach_report,
+            api_coach_live,
+            api_get_job,
+            api_get_journal,
+            api_get_student_journal,
+            api_create_journal_entry,
+            api_update_journal_entry,
+            api_delete_journal_entry,
+            api_create_class,
+            api_list_classes,
+            api_get_class,
+            api_set_class_attendance,
+            api_student_attendance,
+            api_get_schedule,
+            api_create_schedule_slot,
+            api_update_schedule_slot,
+            api_delete_schedule_slot,
+            api_schedule_ics,
+            api_open_grading,
+            api_list_gradings,
+            api_get_grading,
+            api_record_grading_result,
+            api_close_grading,
+            api_get_status_transitions,
+            api_replace_status_transitions,
+            api_get_statuses,
+            api_create_status,
+            api_update_status,
+            api_reorder_statuses,
+            api_delete_status,
+            api_get_student_techniques,
+            api_logout,
+            api_get_students,
+            api_get_unassigned_techniques,
+            api_assign_techniques,
+            api_assign_by_tag,
+            api_remove_techniques,
+            api_create_and_assign_technique,
+            api_register_user,
+            api_change_password,
+            api_update_profile,
+            api_update_timezone,
+            api_get_preferences,
+            api_update_preferences,
+            api_get_notification_preferences,
+            api_update_notification_preferences,
+            api_get_api_tokens,
+            api_create_api_token,
+            api_revoke_api_token,
+            api_get_notifications,
+            api_mark_notification_read,
+            api_update_user,
+            api_get_coach_students,
+            api_set_coach_students,
+            api_get_all_tags,
+            api_get_tag_tree,
+            api_create_tag,
+            api_rename_tag,
+            api_delete_tag,
+            api_add_tag_to_technique,
+            api_remove_tag_from_technique,
+            api_get_technique_tags,
+            api_get_all_users,
+            api_get_feature_flags,
+            api_set_feature_flag,
+            api_import_spreadsheet,
+            api_import_memberships,
+            api_membership_webhook,
+            api_reload_config,
+            api_system,
+            api_library_stats,
+            api_list_library_techniques,
+            api_library_technique_stats,
+            api_search_techniques,
+            api_set_student_graduated,
+            api_mark_student_technique_seen,
+            api_request_review,
+            api_invite_user,
+            api_create_invitation,
+            api_get_invite,
+            api_claim_invite,
+            api_register_with_invite,
+            api_reset_user_claim,
+            api_self_register,
+            api_approve_user,
+            api_request_password_reset,
+            api_get_collections,
+            api_get_collection,
+            api_create_collection,
+            api_update_collection,
+            api_delete_collection,
+            api_add_techniques_to_collection,
+            api_create_technique_in_collection,
+            api_update_library_technique,
+            api_delete_technique,
+            api_restore_technique,
+            api_get_technique_media,
+            api_add_technique_media,
+            api_remove_technique_media,
+            api_create_techniques_bulk,
+            api_add_technique_alias,
+            api_remove_technique_alias,
+            api_get_technique_prerequisites,
+            api_add_technique_prerequisite,
+            api_remove_technique_prerequisite,
+            api_remove_technique_from_collection,
+            api_reorder_collection_techniques,
+            api_get_collection_students,
+            api_assign_collection,
+            api_get_single_student_technique,
+            api_note_history,
+            api_student_technique_history,
+            api_restore_note,
+            api_get_note_templates,
+            api_create_note_template,
+            api_update_note_template,
+            api_delete_note_template,
+            api_list_attempts,
+            api_create_attempt,
+            api_update_attempt,
+            api_delete_attempt,
+            api_recent_attempts,
+            api_attempt_summary,
+            api_attempt_heatmap,
+            api_attempt_sparkline,
+            api_student_analytics,
+            api_student_progress,
+            api_export_student_techniques,
+            api_attachment_download_url,
+            api_upload_file,
+            api_upload_avatar,
+            api_delete_avatar,
+            api_user_avatar,
+        ])
         .register(
             "/api",
             catchers![
@@ -303,8 +481,22 @@ pub async fn init_rocket(
                 default_catcher,
             ],
         )
-        .mount("/api", routes![health, api_capabilities])
-        .attach(TelemetryFairing);
+        .mount_api(routes![health, api_capabilities, api_version, api_public_syllabus])
+        .attach(ApiVersionFairing)
+        .attach(TelemetryFairing)
+        .attach(SlowRequestFairing)
+        .attach(TransactionFairing)
+        .attach(IdempotencyFairing);
+
+    if let Some(provider) = identity_provider {
+        rocket = rocket.manage(provider);
+    }
+
+    if let Some(local) = local_attachments {
+        rocket = rocket
+            .manage(local)
+            .mount_api(routes![api_attachment_file]);
+    }
 
     if let Some(stack) = video_stack {
         let jobs = std::sync::Arc::new(videos::ProcessingJobs::new());
@@ -317,44 +509,32 @@ pub async fn init_rocket(
             max_duration_seconds: videos::pipeline::max_video_duration_seconds(),
         });
 
-        let sampler_pool = pool.clone();
-        let sampler_jobs = jobs.clone();
-        tokio::spawn(async move {
-            loop {
-                sample_video_gauges(&sampler_pool, sampler_jobs.snapshot()).await;
-                tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
-            }
-        });
-
         rocket = rocket
             .manage(stack.storage)
             .manage(pipeline_ctx)
             .manage(jobs)
-            .mount(
-                "/api",
-                routes![
-                    api_video_upload,
-                    api_video_status,
-                    api_video_link,
-                    api_list_technique_videos,
-                    api_update_video,
-                    api_reorder_videos,
-                    api_replace_video,
-                    api_delete_video,
-                    api_set_video_global_hidden,
-                    api_set_video_student_visibility,
-                    api_video_playback_url,
-                    api_video_download_url,
-                    api_video_watch_events,
-                    api_video_privacy_ack,
-                    api_video_privacy_ack_status,
-                    api_video_stats,
-                    api_student_watch_activity,
-                    api_my_watch_state,
-                    api_dashboard_video_overview,
-                    api_admin_storage,
-                ],
-            );
+            .mount_api(routes![
+                api_video_upload,
+                api_video_status,
+                api_video_link,
+                api_list_technique_videos,
+                api_update_video,
+                api_reorder_videos,
+                api_replace_video,
+                api_delete_video,
+                api_set_video_global_hidden,
+                api_set_video_student_visibility,
+                api_video_playback_url,
+                api_video_download_url,
+                api_video_watch_events,
+                api_video_privacy_ack,
+                api_video_privacy_ack_status,
+                api_video_stats,
+                api_student_watch_activity,
+                api_my_watch_state,
+                api_dashboard_video_overview,
+                api_admin_storage,
+            ]);
     }
 
     rocket.manage(pool)
@@ -1,14 +1,68 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::error::AppError;
+use crate::ids::{StudentTechniqueId, TechniqueId, UserId};
+
 #[derive(Debug, Serialize, Clone)]
 pub struct Technique {
-    pub id: i64,
+    pub id: TechniqueId,
     pub name: String,
     pub description: String,
-    pub coach_id: i64,
+    pub coach_id: UserId,
     pub coach_name: String, // Denormalized for convenience
+    /// The base technique this is a variant of, if any.
+    pub parent_technique_id: Option<TechniqueId>,
     pub tags: Vec<Tag>,
+    /// Other names for the technique. Left empty by the queries that don't
+    /// feed a search box (by tag, by collection).
+    pub aliases: Vec<TechniqueAlias>,
+}
+
+/// Another name a technique goes by, such as "Juji-gatame" for "Armbar".
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TechniqueAlias {
+    pub id: i64,
+    pub alias: String,
+    /// Language code, e.g. `pt` or `ja`, when the alias is a translation.
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Video,
+    Image,
+}
+
+impl MediaKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Video => "video",
+            MediaKind::Image => "image",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Result<Self, AppError> {
+        match value {
+            "video" => Ok(MediaKind::Video),
+            "image" => Ok(MediaKind::Image),
+            other => Err(AppError::Internal(format!("Unknown media kind '{}'", other))),
+        }
+    }
+}
+
+/// A demonstration linked to a library technique: an outside URL, or an
+/// uploaded attachment fetched through `/api/attachments/<id>/download-url`.
+/// Exactly one of `url` and `attachment_id` is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TechniqueMedia {
+    pub id: i64,
+    pub kind: MediaKind,
+    pub title: String,
+    pub url: Option<String>,
+    pub attachment_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(sqlx::FromRow, Clone)]
@@ -18,31 +72,37 @@ pub struct DbTechnique {
     pub description: Option<String>,
     pub coach_id: Option<i64>,
     pub coach_name: Option<String>,
+    pub parent_technique_id: Option<i64>,
 }
 
 impl From<DbTechnique> for Technique {
     fn from(technique: DbTechnique) -> Self {
         Self {
-            id: technique.id.unwrap_or_default(),
+            id: TechniqueId(technique.id.unwrap_or_default()),
             name: technique.name.unwrap_or_default(),
             description: technique.description.unwrap_or_default(),
-            coach_id: technique.coach_id.unwrap_or_default(),
+            coach_id: UserId(technique.coach_id.unwrap_or_default()),
             coach_name: technique.coach_name.unwrap_or_default(),
+            parent_technique_id: technique.parent_technique_id.map(TechniqueId),
             tags: Vec::new(),
+            aliases: Vec::new(),
         }
     }
 }
 
 #[derive(Serialize)]
 pub struct StudentTechnique {
-    pub id: i64,
-    pub technique_id: i64,
-    pub student_id: i64,
+    pub id: StudentTechniqueId,
+    pub technique_id: TechniqueId,
+    pub student_id: UserId,
     pub technique_name: String,
     pub technique_description: String,
     pub status: String,
     pub student_notes: String,
     pub coach_notes: String,
+    /// Coach notes hidden from anyone who can't edit every technique,
+    /// including the student.
+    pub coach_notes_private: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub last_coach_update_at: Option<DateTime<Utc>>,
@@ -53,13 +113,20 @@ pub struct StudentTechnique {
     pub last_student_update_by_name: Option<String>,
     pub collection_id: Option<i64>,
     pub collection_name: Option<String>,
+    /// Set while the student is waiting on a coach review.
+    pub review_requested_at: Option<DateTime<Utc>>,
     pub tags: Vec<Tag>,
+    /// The library technique's media, in the order it was added.
+    pub media: Vec<TechniqueMedia>,
     pub attempt_count: i64,
     pub last_attempt_at: Option<DateTime<Utc>>,
     /// When the viewer (whoever the request was made for) last opened this
     /// row. `None` means they have never opened it. Drives `has_unseen_activity`
     /// in the API response.
     pub viewer_seen_at: Option<DateTime<Utc>>,
+    /// When the student last opened this row, whoever is asking. Read
+    /// receipts for coach feedback compare it to `last_coach_update_at`.
+    pub student_seen_at: Option<DateTime<Utc>>,
 }
 
 #[derive(sqlx::FromRow, Clone, Default)]
@@ -79,25 +146,66 @@ pub struct DbStudentTechnique {
     pub last_student_update_at: Option<NaiveDateTime>,
     pub last_student_update_by_id: Option<i64>,
     pub collection_id: Option<i64>,
+    pub review_requested_at: Option<NaiveDateTime>,
+    pub assigned_by_id: Option<i64>,
+    pub coach_notes_private: bool,
 }
 
+/// Every TIMESTAMP column holds naive UTC (`YYYY-MM-DD HH:MM:SS`), written
+/// either by `CURRENT_TIMESTAMP` or from `Utc::now().naive_utc()`. This
+/// attaches the offset back on the way out.
 pub fn naive_to_utc(dt: NaiveDateTime) -> DateTime<Utc> {
     DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)
 }
 
-impl From<DbStudentTechnique> for StudentTechnique {
-    fn from(db: DbStudentTechnique) -> Self {
-        Self {
-            id: db.id.unwrap_or_default(),
-            technique_id: db.technique_id.unwrap_or_default(),
-            student_id: db.student_id.unwrap_or_default(),
-            technique_name: db.technique_name.unwrap_or_default(),
+/// Wire format for timestamps in API responses: RFC3339 in UTC with a `Z`
+/// suffix, the same shape serde gives `DateTime<Utc>` fields. Formatting
+/// string fields through here keeps the two indistinguishable to clients.
+pub fn to_rfc3339_utc(dt: DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+pub fn naive_to_rfc3339(dt: NaiveDateTime) -> String {
+    to_rfc3339_utc(naive_to_utc(dt))
+}
+
+/// Name to show for a user: their display name unless it is missing or
+/// blank, else their username. Row-level counterpart of
+/// `User::effective_display_name` for queries that join in another user's
+/// name columns.
+pub fn display_name_or_username(
+    display_name: Option<String>,
+    username: Option<String>,
+) -> Option<String> {
+    display_name.filter(|s| !s.trim().is_empty()).or(username)
+}
+
+/// Unwraps a column sqlx reports as nullable but that the app relies on
+/// being set. A NULL here means the row is corrupt, so it surfaces as an
+/// internal error naming the column rather than defaulting to 0 or "".
+pub(crate) fn required<T>(value: Option<T>, column: &str) -> Result<T, AppError> {
+    value.ok_or_else(|| AppError::Internal(format!("Unexpected NULL in column {}", column)))
+}
+
+impl TryFrom<DbStudentTechnique> for StudentTechnique {
+    type Error = AppError;
+
+    fn try_from(db: DbStudentTechnique) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: StudentTechniqueId(required(db.id, "student_techniques.id")?),
+            technique_id: TechniqueId(required(
+                db.technique_id,
+                "student_techniques.technique_id",
+            )?),
+            student_id: UserId(required(db.student_id, "student_techniques.student_id")?),
+            technique_name: required(db.technique_name, "student_techniques.technique_name")?,
             technique_description: db.technique_description.unwrap_or_default(),
-            status: db.status.unwrap_or_default(),
+            status: required(db.status, "student_techniques.status")?,
             student_notes: db.student_notes.unwrap_or_default(),
             coach_notes: db.coach_notes.unwrap_or_default(),
-            created_at: db.created_at.map(naive_to_utc).unwrap_or_else(Utc::now),
-            updated_at: db.updated_at.map(naive_to_utc).unwrap_or_else(Utc::now),
+            coach_notes_private: db.coach_notes_private,
+            created_at: naive_to_utc(required(db.created_at, "student_techniques.created_at")?),
+            updated_at: naive_to_utc(required(db.updated_at, "student_techniques.updated_at")?),
             last_coach_update_at: db.last_coach_update_at.map(naive_to_utc),
             last_coach_update_by_id: db.last_coach_update_by_id,
             last_coach_update_by_name: None,
@@ -106,11 +214,14 @@ impl From<DbStudentTechnique> for StudentTechnique {
             last_student_update_by_name: None,
             collection_id: db.collection_id,
             collection_name: None,
+            review_requested_at: db.review_requested_at.map(naive_to_utc),
             tags: Vec::new(),
+            media: Vec::new(),
             attempt_count: 0,
             last_attempt_at: None,
             viewer_seen_at: None,
-        }
+            student_seen_at: None,
+        })
     }
 }
 
@@ -275,12 +386,21 @@ impl From<DbVideo> for Video {
 pub struct Tag {
     pub id: i64,
     pub name: String,
+    pub archived: bool,
+    pub category: Option<String>,
+    /// `#rrggbb`.
+    pub color: Option<String>,
+    pub parent_id: Option<i64>,
 }
 
 #[derive(sqlx::FromRow, Clone, Default)]
 pub struct DbTag {
     pub id: Option<i64>,
     pub name: Option<String>,
+    pub archived: Option<bool>,
+    pub category: Option<String>,
+    pub color: Option<String>,
+    pub parent_id: Option<i64>,
 }
 
 impl From<DbTag> for Tag {
@@ -288,6 +408,10 @@ impl From<DbTag> for Tag {
         Self {
             id: tag.id.unwrap_or_default(),
             name: tag.name.unwrap_or_default(),
+            archived: tag.archived.unwrap_or_default(),
+            category: tag.category,
+            color: tag.color,
+            parent_id: tag.parent_id,
         }
     }
 }
@@ -302,6 +426,7 @@ pub struct Collection {
     pub technique_count: i64,
     pub student_count: i64,
     pub techniques: Vec<Technique>,
+    pub belt_level: Option<String>,
 }
 
 /// What the UI should suggest after a successful attempt log. Only the
@@ -317,6 +442,7 @@ pub enum AttemptSuggestion {
 #[derive(Debug, Clone, Serialize)]
 pub struct AttemptCreateResult {
     pub attempt: Attempt,
+    pub student_id: UserId,
     pub suggestion: AttemptSuggestion,
 }
 
@@ -405,3 +531,99 @@ pub struct StorageOverview {
     pub total_objects: i64,
     pub top_objects: Vec<StorageObjectRow>,
 }
+
+/// One coach's (or admin's) share of the work over a reporting period.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CoachActivity {
+    pub user_id: UserId,
+    pub name: String,
+    pub techniques_created: i64,
+    pub assignments: i64,
+    /// Coach notes on attempts, plus edits that replaced a technique's
+    /// earlier coach notes.
+    pub notes_written: i64,
+}
+
+/// How far a student is through one group of techniques (a tag or a
+/// curriculum): of the techniques in the group assigned to them, how many
+/// are green.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupProgress {
+    pub id: i64,
+    pub name: String,
+    /// Techniques in the group across the whole library, assigned or not.
+    pub techniques: i64,
+    pub assigned: i64,
+    pub green: i64,
+    /// `green / assigned`, as a whole percentage rounded down.
+    pub percent: i64,
+}
+
+impl GroupProgress {
+    pub fn percent(green: i64, assigned: i64) -> i64 {
+        if assigned > 0 { green * 100 / assigned } else { 0 }
+    }
+}
+
+/// How many of a student's techniques are at one status.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusCount {
+    pub status: String,
+    pub count: i64,
+}
+
+/// A student's techniques carrying one tag, by status.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagStatusBreakdown {
+    pub id: i64,
+    pub name: String,
+    pub total: i64,
+    pub statuses: Vec<StatusCount>,
+    pub last_updated_at: Option<String>,
+}
+
+/// Aggregate view of a student's syllabus for the dashboard, so it doesn't
+/// have to fetch every technique row. Statuses follow the grading scale,
+/// then any not on it by name; statuses with no techniques are left out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StudentProgress {
+    pub total: i64,
+    pub statuses: Vec<StatusCount>,
+    pub tags: Vec<TagStatusBreakdown>,
+    pub last_updated_at: Option<String>,
+    pub last_coach_update_at: Option<String>,
+    pub last_student_update_at: Option<String>,
+    /// Classes the student was marked present at (see `db::attendance`).
+    pub classes_attended: i64,
+    pub last_attended_at: Option<String>,
+}
+
+/// The technique library as published to the gym's website (see
+/// `api::api_public_syllabus`). Names and descriptions only: nothing about
+/// students, coaches or usage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PublicSyllabus {
+    pub curricula: Vec<PublicCurriculum>,
+    /// Every technique, by name, whether or not it is in a curriculum.
+    pub techniques: Vec<PublicTechnique>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PublicCurriculum {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    /// In curriculum order; each is in `PublicSyllabus::techniques`.
+    pub technique_ids: Vec<TechniqueId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PublicTechnique {
+    pub id: TechniqueId,
+    pub name: String,
+    pub description: String,
+    /// Tag names, sorted.
+    pub tags: Vec<String>,
+    /// Other names for the technique, sorted.
+    pub aliases: Vec<String>,
+}
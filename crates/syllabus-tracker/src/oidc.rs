@@ -0,0 +1,276 @@
+//! Sign-in through an OpenID Connect provider, alongside passwords. The gym
+//! uses Google Workspace, but any provider with discovery and a userinfo
+//! endpoint works.
+//!
+//! `GET /api/auth/oidc/login` sends the browser to the provider with a
+//! random `state`, kept in a private cookie. The provider sends the browser
+//! back to `/api/auth/oidc/callback` with a code, which is swapped for an
+//! access token and then for the account's userinfo. Those calls go straight
+//! to the provider over TLS, so there is no ID token to verify.
+//!
+//! An account signs in as the user it is linked to in `user_identities`,
+//! and is linked on first use to the user with the same email (see
+//! `db::resolve_identity`), provided the provider has verified the email.
+//! Nobody signs up this way; an account with no user goes back to the login
+//! page with `?error=oidc`.
+//!
+//! Configured by the `OIDC_*` settings (see `AppConfig`). Without them the
+//! routes answer 404.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rocket::Request;
+use rocket::State;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::tokio::sync::OnceCell;
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::api::{ApiResult, establish_session};
+use crate::auth::UserSession;
+use crate::config::{AppConfig, LiveConfig};
+use crate::db::{get_user, resolve_identity};
+
+const STATE_COOKIE: &str = "oidc_state";
+
+/// How long the provider's sign-in page may take.
+const STATE_TTL_MINUTES: i64 = 10;
+
+const LOGIN_FAILED: &str = "/login?error=oidc";
+
+pub type DynIdentityProvider = Arc<dyn IdentityProvider + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("request to provider failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("provider answered unexpectedly: {0}")]
+    Provider(String),
+}
+
+/// Who the provider says signed in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    pub subject: String,
+    /// Only when the provider has verified it.
+    pub email: Option<String>,
+}
+
+#[async_trait]
+pub trait IdentityProvider {
+    /// Recorded with each linked account.
+    fn issuer(&self) -> &str;
+
+    /// Where to send the browser to sign in.
+    async fn authorization_url(&self, state: &str) -> Result<String, OidcError>;
+
+    /// The account behind a code the provider sent to the callback.
+    async fn identify(&self, code: &str) -> Result<ExternalIdentity, OidcError>;
+}
+
+/// The configured provider, if `OIDC_*` is set.
+pub fn provider_from_config(config: &AppConfig) -> Option<DynIdentityProvider> {
+    let provider = OidcProvider {
+        issuer: config.oidc_issuer.clone()?.trim_end_matches('/').to_string(),
+        client_id: config.oidc_client_id.clone()?,
+        client_secret: config.oidc_client_secret.clone()?,
+        redirect_url: config.oidc_redirect_url.clone()?,
+        http: reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .ok()?,
+        discovery: OnceCell::new(),
+    };
+    Some(Arc::new(provider))
+}
+
+/// The endpoints a provider publishes at `/.well-known/openid-configuration`.
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    sub: String,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+pub struct OidcProvider {
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    redirect_url: String,
+    http: reqwest::Client,
+    /// Fetched on first use, so startup doesn't wait on the provider.
+    discovery: OnceCell<Discovery>,
+}
+
+impl OidcProvider {
+    async fn discovery(&self) -> Result<&Discovery, OidcError> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer);
+                let discovery: Discovery =
+                    self.http.get(url).send().await?.error_for_status()?.json().await?;
+                if discovery.issuer.trim_end_matches('/') != self.issuer {
+                    return Err(OidcError::Provider(format!(
+                        "discovery names issuer {}",
+                        discovery.issuer
+                    )));
+                }
+                Ok(discovery)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OidcProvider {
+    fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    async fn authorization_url(&self, state: &str) -> Result<String, OidcError> {
+        let discovery = self.discovery().await?;
+        let url = reqwest::Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("scope", "openid email"),
+                ("state", state),
+            ],
+        )
+        .map_err(|e| OidcError::Provider(format!("authorization endpoint: {}", e)))?;
+        Ok(url.into())
+    }
+
+    async fn identify(&self, code: &str) -> Result<ExternalIdentity, OidcError> {
+        let discovery = self.discovery().await?;
+        let token: TokenResponse = self
+            .http
+            .post(&discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let info: UserInfo = self
+            .http
+            .get(&discovery.userinfo_endpoint)
+            .bearer_auth(token.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(ExternalIdentity {
+            subject: info.sub,
+            email: info.email.filter(|_| info.email_verified),
+        })
+    }
+}
+
+/// The configured provider, for the routes below. 404 when there is none.
+pub struct Oidc<'r>(&'r DynIdentityProvider);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Oidc<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.rocket().state::<DynIdentityProvider>() {
+            Some(provider) => Outcome::Success(Oidc(provider)),
+            None => Outcome::Error((Status::NotFound, ())),
+        }
+    }
+}
+
+/// Public. Sends the browser to the provider's sign-in page.
+#[get("/auth/oidc/login")]
+pub async fn api_oidc_login(oidc: Oidc<'_>, cookies: &CookieJar<'_>) -> Redirect {
+    let state = UserSession::generate_token();
+    match oidc.0.authorization_url(&state).await {
+        Ok(url) => {
+            cookies.add_private(
+                Cookie::build((STATE_COOKIE, state))
+                    // Lax still sends it on the provider's redirect back.
+                    .same_site(SameSite::Lax)
+                    .http_only(true)
+                    .max_age(rocket::time::Duration::minutes(STATE_TTL_MINUTES)),
+            );
+            Redirect::to(url)
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to start OIDC sign-in");
+            Redirect::to(LOGIN_FAILED)
+        }
+    }
+}
+
+/// Public. Where the provider sends the browser back. Signs the linked user
+/// in and goes to the dashboard, or back to the login page.
+#[get("/auth/oidc/callback?<code>&<state>")]
+pub async fn api_oidc_callback(
+    code: Option<&str>,
+    state: Option<&str>,
+    oidc: Oidc<'_>,
+    cookies: &CookieJar<'_>,
+    config: &State<LiveConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Redirect> {
+    let expected = cookies.get_private(STATE_COOKIE);
+    cookies.remove_private(Cookie::build(STATE_COOKIE));
+    // No code means the provider sent back an error, e.g. the user cancelled.
+    let (Some(code), Some(state), Some(expected)) = (code, state, expected) else {
+        return Ok(Redirect::to(LOGIN_FAILED));
+    };
+    if state != expected.value() {
+        warn!("OIDC callback with a state that doesn't match its cookie");
+        return Ok(Redirect::to(LOGIN_FAILED));
+    }
+
+    let identity = match oidc.0.identify(code).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            warn!(error = %e, "Failed to identify OIDC account");
+            return Ok(Redirect::to(LOGIN_FAILED));
+        }
+    };
+    let issuer = oidc.0.issuer();
+    let email = identity.email.as_deref();
+    let Some(user_id) = resolve_identity(db, issuer, &identity.subject, email).await? else {
+        info!(issuer, subject = %identity.subject, "OIDC account has no user");
+        return Ok(Redirect::to(LOGIN_FAILED));
+    };
+
+    let user = get_user(db.inner(), user_id).await?;
+    establish_session(cookies, db, config, &user).await?;
+    info!(user_id = %user.id, "Signed in with OIDC");
+    Ok(Redirect::to("/dashboard"))
+}
@@ -0,0 +1,286 @@
+//! Startup self-check. Runs before anything else reads the environment, so a
+//! misconfigured deploy exits with one report listing every problem instead of
+//! panicking at whichever `expect` it happens to reach first.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+use rocket::figment::Figment;
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Connection};
+
+use crate::attachments::StorageBackend;
+use crate::config::AppConfig;
+
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    /// Reported but does not stop startup.
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PreflightReport {
+    pub checks: Vec<Check>,
+}
+
+static STARTUP_REPORT: OnceCell<PreflightReport> = OnceCell::new();
+
+/// Keeps the server's startup report for `GET /api/admin/system`.
+pub fn keep_startup_report(report: PreflightReport) {
+    let _ = STARTUP_REPORT.set(report);
+}
+
+pub fn startup_report() -> Option<&'static PreflightReport> {
+    STARTUP_REPORT.get()
+}
+
+impl PreflightReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.outcome != Outcome::Fail)
+    }
+
+    fn push(&mut self, name: &'static str, outcome: Outcome, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            outcome,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Startup checks:")?;
+        for check in &self.checks {
+            let label = match check.outcome {
+                Outcome::Ok => "ok",
+                Outcome::Warn => "warn",
+                Outcome::Fail => "FAIL",
+            };
+            writeln!(f, "  {:<4}  {:<10} {}", label, check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every check against `figment` (normally `AppConfig::figment()`) and
+/// returns the parsed config alongside the report. Checks that need a config
+/// value are skipped when the config itself failed to load.
+pub async fn run(figment: &Figment) -> (PreflightReport, Option<AppConfig>) {
+    let mut report = PreflightReport::default();
+
+    let config = match AppConfig::from_figment(figment) {
+        Ok(config) => {
+            report.push("config", Outcome::Ok, "loaded");
+            Some(config)
+        }
+        Err(e) => {
+            report.push("config", Outcome::Fail, e.to_string());
+            None
+        }
+    };
+
+    if let Some(config) = &config {
+        check_schema(&mut report, config).await;
+        check_database(&mut report, config).await;
+        if config.videos_enabled {
+            match crate::videos::S3Config::from_env() {
+                Ok(_) => report.push("videos", Outcome::Ok, "S3 config present"),
+                Err(e) => {
+                    let detail = format!("VIDEOS_ENABLED but {}", e);
+                    report.push("videos", Outcome::Fail, detail)
+                }
+            }
+        }
+        check_attachments(&mut report, config);
+        check_telemetry(&mut report, config).await;
+    }
+
+    check_secret_key(&mut report, figment);
+
+    (report, config)
+}
+
+/// S3 needs its config present; local storage only warns about a missing
+/// signing key, since the per-process fallback works until a restart.
+fn check_attachments(report: &mut PreflightReport, config: &AppConfig) {
+    match config.attachment_storage {
+        StorageBackend::S3 => match crate::videos::S3Config::from_env() {
+            Ok(_) => report.push("attachments", Outcome::Ok, "S3 config present"),
+            Err(e) => {
+                let detail = format!("ATTACHMENT_STORAGE=s3 but {}", e);
+                report.push("attachments", Outcome::Fail, detail)
+            }
+        },
+        StorageBackend::Local => {
+            if config.attachment_signing_key.is_none() {
+                let detail = "ATTACHMENT_SIGNING_KEY unset; download links stop working on restart";
+                return report.push("attachments", Outcome::Warn, detail);
+            }
+            let detail = format!("local, in {}", config.attachment_dir.display());
+            report.push("attachments", Outcome::Ok, detail)
+        }
+    }
+}
+
+/// Loads the schema into a throwaway in-memory database, which catches both a
+/// missing file and SQL that SQLite will not accept.
+async fn check_schema(report: &mut PreflightReport, config: &AppConfig) {
+    let path = &config.schema_path;
+    let schema = match std::fs::read_to_string(path) {
+        Ok(schema) => schema,
+        Err(e) => {
+            let detail = format!("cannot read SCHEMA_PATH {}: {}", path.display(), e);
+            return report.push("schema", Outcome::Fail, detail);
+        }
+    };
+
+    let result = async {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::raw_sql(&schema).execute(&pool).await?;
+        pool.close().await;
+        Ok::<_, sqlx::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => report.push("schema", Outcome::Ok, path.display().to_string()),
+        Err(e) => {
+            let detail = format!("{} does not parse: {}", path.display(), e);
+            report.push("schema", Outcome::Fail, detail)
+        }
+    }
+}
+
+/// The migrate binary normally creates the database, so a missing file fails
+/// here with a pointer to that step, unless `CREATE_DATABASE` lets the
+/// server create it (see `crate::bootstrap`).
+async fn check_database(report: &mut PreflightReport, config: &AppConfig) {
+    let opts = match SqliteConnectOptions::from_str(&config.database_url) {
+        Ok(opts) => opts,
+        Err(e) => {
+            let detail = format!("DATABASE_URL is not a valid SQLite URL: {}", e);
+            return report.push("database", Outcome::Fail, detail);
+        }
+    };
+
+    let filename = opts.get_filename().to_path_buf();
+    let in_memory = filename.as_os_str() == ":memory:";
+    if !in_memory && !filename.exists() {
+        if config.create_database {
+            let detail = format!("{} will be created (CREATE_DATABASE)", filename.display());
+            return report.push("database", Outcome::Ok, detail);
+        }
+        let detail = format!(
+            "{} does not exist; run the migrate binary first (`just migrate` locally)",
+            filename.display()
+        );
+        return report.push("database", Outcome::Fail, detail);
+    }
+
+    let result = async {
+        let mut conn = opts.connect().await?;
+        sqlx::query("SELECT 1").execute(&mut conn).await?;
+        conn.close().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => report.push("database", Outcome::Ok, filename.display().to_string()),
+        // Startup retries the connection, so a transient failure here only
+        // warns unless retrying is turned off.
+        Err(e) if config.db_connect_max_wait_seconds > 0 => {
+            let detail = format!(
+                "cannot open {} yet, startup retries for up to {}s: {}",
+                filename.display(),
+                config.db_connect_max_wait_seconds,
+                e
+            );
+            report.push("database", Outcome::Warn, detail)
+        }
+        Err(e) => {
+            let detail = format!("cannot open {}: {}", filename.display(), e);
+            report.push("database", Outcome::Fail, detail)
+        }
+    }
+}
+
+/// Rocket refuses to launch a non-debug profile with the `secrets` feature
+/// and no stable key; checking here gets that into the same report.
+fn check_secret_key(report: &mut PreflightReport, figment: &Figment) {
+    let profile = figment.profile();
+    if figment.contains(rocket::Config::SECRET_KEY) {
+        report.push("secret", Outcome::Ok, "ROCKET_SECRET_KEY set");
+    } else if *profile == rocket::Config::DEBUG_PROFILE {
+        report.push("secret", Outcome::Ok, "generated per launch (debug profile)");
+    } else {
+        let detail = format!(
+            "ROCKET_SECRET_KEY is required for the {} profile; generate one with \
+             `openssl rand -base64 32`",
+            profile
+        );
+        report.push("secret", Outcome::Fail, detail);
+    }
+}
+
+/// The exporter retries in the background, so an unresolvable collector only
+/// warns: traces are lost but the app still serves requests.
+async fn check_telemetry(report: &mut PreflightReport, config: &AppConfig) {
+    let Some(endpoint) = config.otel_exporter_otlp_endpoint.clone() else {
+        return report.push("telemetry", Outcome::Ok, "no OTLP endpoint configured");
+    };
+
+    let Some(host_port) = endpoint_host_port(&endpoint) else {
+        let detail = format!("OTEL_EXPORTER_OTLP_ENDPOINT {} is not an http(s) URL", endpoint);
+        return report.push("telemetry", Outcome::Warn, detail);
+    };
+
+    let lookup = tokio::time::timeout(DNS_TIMEOUT, tokio::net::lookup_host(&host_port)).await;
+    let (outcome, detail) = match lookup.map(|r| r.map(|addrs| addrs.count())) {
+        Ok(Ok(count)) if count > 0 => (Outcome::Ok, endpoint),
+        Ok(Ok(_)) => (Outcome::Warn, format!("{} resolved to nothing", host_port)),
+        Ok(Err(e)) => (Outcome::Warn, format!("cannot resolve {}: {}", host_port, e)),
+        Err(_) => (Outcome::Warn, format!("timed out resolving {}", host_port)),
+    };
+    report.push("telemetry", outcome, detail);
+}
+
+/// `http://collector:4317/v1/traces` -> `collector:4317`, filling in the
+/// scheme's default port when the URL has none.
+fn endpoint_host_port(endpoint: &str) -> Option<String> {
+    let (rest, default_port) = if let Some(rest) = endpoint.strip_prefix("https://") {
+        (rest, 443)
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        (rest, 80)
+    } else {
+        return None;
+    };
+
+    let authority = rest.split('/').next().filter(|a| !a.is_empty())?;
+    let has_port = authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    if has_port {
+        Some(authority.to_string())
+    } else {
+        Some(format!("{}:{}", authority, default_port))
+    }
+}
@@ -0,0 +1,363 @@
+//! Background jobs on a schedule. Each registered `Job` runs in its own task,
+//! either on an interval (`every 1h`) or a five-field cron expression in UTC
+//! (`0 3 * * *`). Jobs carry a default schedule; config can override it or
+//! turn the job `off` (see `AppConfig::jobs`), e.g. `JOB_SESSION_CLEANUP=off`.
+//! Schedules are read at launch; changing one needs a restart.
+//!
+//! Every run is recorded in `scheduled_jobs`. The row is the overlap guard: a
+//! run is skipped while another run of the same job holds it, whether that is
+//! a slow previous run or a second process on the same database. It also
+//! outlives restarts, so an hourly job that ran just before a deploy waits
+//! out the rest of its hour instead of firing again on boot.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rocket::fairing::AdHoc;
+use rocket::tokio;
+use sqlx::SqlitePool;
+use tracing::{error, info, warn};
+
+use crate::config::LiveConfig;
+use crate::db::{
+    award_badges, clean_expired_idempotency_keys, clean_expired_sessions, create_background_job,
+    fail_stale_background_jobs, finish_background_job, finish_job, get_job_run,
+    start_background_job, try_start_job,
+};
+use crate::error::AppError;
+use crate::ids::UserId;
+
+/// A run still marked as running after this long is assumed to have died
+/// with its process, and stops blocking the next one.
+pub const STALE_RUN: Duration = Duration::from_secs(60 * 60);
+
+/// Gives startup (schema check, first requests) a moment before any job runs.
+const STARTUP_DELAY: Duration = Duration::from_secs(5);
+
+/// Pause before trying again after a run was skipped or couldn't be
+/// recorded, so neither turns an overdue job into a busy loop.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[async_trait]
+pub trait Job: Send + Sync {
+    /// Stable key for config overrides and the `scheduled_jobs` row.
+    fn name(&self) -> &'static str;
+
+    /// Used unless config overrides it; any string `Schedule` parses.
+    fn default_schedule(&self) -> &'static str;
+
+    /// Returns a one-line summary for the log and `scheduled_jobs`.
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+    Off,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("off") {
+            return Ok(Schedule::Off);
+        }
+        if let Some(every) = s.strip_prefix("every ") {
+            return parse_interval(every.trim()).map(Schedule::Every);
+        }
+        // The cron crate wants a leading seconds field; schedules here are
+        // written in the usual five fields.
+        let expr = match s.split_whitespace().count() {
+            5 => format!("0 {}", s),
+            _ => return Err(format!("'{}' is not 'off', 'every <n>[smhd]' or cron", s)),
+        };
+        cron::Schedule::from_str(&expr)
+            .map(|c| Schedule::Cron(Box::new(c)))
+            .map_err(|e| format!("invalid cron expression '{}': {}", s, e))
+    }
+}
+
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid interval '{}', expected e.g. 30s, 15m, 1h or 1d", s);
+    let unit_len = s.chars().last().ok_or_else(invalid)?.len_utf8();
+    let (count, unit) = s.split_at(s.len() - unit_len);
+    let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    if count == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(count * seconds))
+}
+
+impl Schedule {
+    /// When the job should next start, given when it last started (if ever).
+    /// An interval counts from the last start and is due immediately if that
+    /// is long past; cron skips occurrences missed while the app was down.
+    /// `None` means never.
+    pub fn next_run(
+        &self,
+        last_started: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => {
+                let interval = chrono::Duration::from_std(*interval).ok()?;
+                Some(last_started.map_or(now, |last| (last + interval).max(now)))
+            }
+            Schedule::Cron(cron) => cron.after(&now).next(),
+            Schedule::Off => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RunOutcome {
+    Finished { ok: bool, message: String },
+    /// Another run of the same job held the lock.
+    Skipped,
+}
+
+/// Runs `job` once now, unless another run of it is in progress, and records
+/// the result. `Err` only when the bookkeeping itself fails; the job's own
+/// error is part of the outcome.
+pub async fn run_job(pool: &SqlitePool, job: &dyn Job) -> Result<RunOutcome, AppError> {
+    let name = job.name();
+    let started = Utc::now().naive_utc();
+    let stale_before = started - chrono::Duration::from_std(STALE_RUN).unwrap_or_default();
+    if !try_start_job(pool, name, started, stale_before).await? {
+        info!(job = name, "Job already running; skipped");
+        return Ok(RunOutcome::Skipped);
+    }
+
+    let (ok, message) = match job.run(pool).await {
+        Ok(message) => {
+            info!(job = name, %message, "Job finished");
+            (true, message)
+        }
+        Err(e) => {
+            error!(job = name, error = %e, "Job failed");
+            (false, e.to_string())
+        }
+    };
+    let status = if ok { "ok" } else { "error" };
+    finish_job(pool, name, Utc::now().naive_utc(), status, &message).await?;
+    Ok(RunOutcome::Finished { ok, message })
+}
+
+/// Runs `work` in its own task and returns at once with the id of the `jobs`
+/// row that tracks it, for requests whose work could outlast a timeout.
+pub async fn spawn_background_job<F>(
+    pool: &SqlitePool,
+    kind: &'static str,
+    requested_by: UserId,
+    work: F,
+) -> Result<i64, AppError>
+where
+    F: Future<Output = Result<serde_json::Value, AppError>> + Send + 'static,
+{
+    let id = create_background_job(pool, kind, requested_by).await?;
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = start_background_job(&pool, id).await {
+            error!(job_id = id, kind, error = %e, "Failed to mark background job running");
+        }
+        let outcome = work.await;
+        let recorded = match &outcome {
+            Ok(result) => {
+                info!(job_id = id, kind, "Background job finished");
+                finish_background_job(&pool, id, Ok(result)).await
+            }
+            Err(e) => {
+                error!(job_id = id, kind, error = %e, "Background job failed");
+                finish_background_job(&pool, id, Err(&e.to_string())).await
+            }
+        };
+        if let Err(e) = recorded {
+            error!(job_id = id, kind, error = %e, "Failed to record background job result");
+        }
+    });
+    Ok(id)
+}
+
+pub struct Scheduler {
+    pool: SqlitePool,
+    jobs: Vec<Arc<dyn Job>>,
+}
+
+impl Scheduler {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool, jobs: Vec::new() }
+    }
+
+    pub fn register(mut self, job: impl Job + 'static) -> Self {
+        self.jobs.push(Arc::new(job));
+        self
+    }
+
+    /// Spawns a task per job that isn't `off`. `overrides` maps job name to
+    /// schedule, already checked by `AppConfig::from_figment`.
+    pub fn start(self, overrides: &HashMap<String, String>) {
+        for name in overrides.keys() {
+            if !self.jobs.iter().any(|job| job.name() == name) {
+                warn!(job = %name, "Schedule configured for unknown job; ignored");
+            }
+        }
+        for job in self.jobs {
+            let spec = overrides
+                .get(job.name())
+                .map(String::as_str)
+                .unwrap_or(job.default_schedule());
+            let schedule = match Schedule::from_str(spec) {
+                Ok(Schedule::Off) => {
+                    info!(job = job.name(), "Job is off");
+                    continue;
+                }
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    error!(job = job.name(), error = %e, "Invalid job schedule; job not started");
+                    continue;
+                }
+            };
+            info!(job = job.name(), schedule = spec, "Scheduling job");
+            tokio::spawn(drive(self.pool.clone(), job, schedule));
+        }
+    }
+
+    /// Starts the jobs once Rocket has launched, with overrides from the
+    /// managed `LiveConfig` as it is at launch.
+    pub fn fairing(self) -> AdHoc {
+        AdHoc::on_liftoff("Scheduler", |rocket| {
+            Box::pin(async move {
+                let overrides = rocket
+                    .state::<LiveConfig>()
+                    .map(|config| config.get().jobs.clone())
+                    .unwrap_or_default();
+                self.start(&overrides);
+            })
+        })
+    }
+}
+
+async fn drive(pool: SqlitePool, job: Arc<dyn Job>, schedule: Schedule) {
+    tokio::time::sleep(STARTUP_DELAY).await;
+    loop {
+        let last_started = match get_job_run(&pool, job.name()).await {
+            Ok(run) => run.and_then(|r| r.last_started_at).map(|t| t.and_utc()),
+            Err(e) => {
+                error!(job = job.name(), error = %e, "Failed to read last run");
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let now = Utc::now();
+        let Some(next) = schedule.next_run(last_started, now) else {
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        match run_job(&pool, job.as_ref()).await {
+            Ok(RunOutcome::Finished { .. }) => {}
+            Ok(RunOutcome::Skipped) => tokio::time::sleep(RETRY_DELAY).await,
+            Err(e) => {
+                error!(job = job.name(), error = %e, "Failed to record job run");
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Deletes sessions past their expiry.
+pub struct SessionCleanup;
+
+#[async_trait]
+impl Job for SessionCleanup {
+    fn name(&self) -> &'static str {
+        "session_cleanup"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "every 1h"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let count = clean_expired_sessions(pool).await?;
+        Ok(format!("Removed {} expired sessions", count))
+    }
+}
+
+/// Deletes stored `Idempotency-Key` responses past their TTL.
+pub struct IdempotencyKeyCleanup;
+
+#[async_trait]
+impl Job for IdempotencyKeyCleanup {
+    fn name(&self) -> &'static str {
+        "idempotency_key_cleanup"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "every 1h"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let count = clean_expired_idempotency_keys(pool).await?;
+        Ok(format!("Removed {} expired idempotency keys", count))
+    }
+}
+
+/// Fails background jobs that have been queued or running for longer than
+/// `STALE_RUN`, so a job lost to a restart stops reading as in progress.
+pub struct StaleBackgroundJobs;
+
+#[async_trait]
+impl Job for StaleBackgroundJobs {
+    fn name(&self) -> &'static str {
+        "stale_background_jobs"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "every 15m"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let stale_before =
+            Utc::now().naive_utc() - chrono::Duration::from_std(STALE_RUN).unwrap_or_default();
+        let count = fail_stale_background_jobs(pool, stale_before).await?;
+        Ok(format!("Failed {} stale background jobs", count))
+    }
+}
+
+/// Awards badges to every student. Write paths award them as they happen;
+/// this catches what they can't see, such as a collection losing the one
+/// technique a student hadn't finished, and anything earned before badges
+/// existed.
+pub struct BadgeAwards;
+
+#[async_trait]
+impl Job for BadgeAwards {
+    fn name(&self) -> &'static str {
+        "badge_awards"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "every 1h"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let count = award_badges(&mut *pool.acquire().await?, None).await?;
+        Ok(format!("Awarded {} badges", count))
+    }
+}
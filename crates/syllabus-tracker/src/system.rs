@@ -0,0 +1,91 @@
+//! `GET /api/admin/system`: what an operator checks after a deploy, in one
+//! call. Which build is running, whether the database matches the schema it
+//! expects and what the migrate binary last applied, how startup checks and
+//! background jobs went, and the effective config.
+
+use std::collections::BTreeMap;
+
+use migration_engine::migrations::{
+    get_schema_changes, planned_step_descriptions, read_schema_file_to_string, schema_sha256,
+};
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+
+use crate::api::ApiResult;
+use crate::auth::{Permission, User};
+use crate::config::LiveConfig;
+use crate::db::{JobRun, SchemaMigration, get_job_runs, get_schema_migrations};
+use crate::error::AppError;
+use crate::preflight::{Check, startup_report};
+use crate::version::VersionInfo;
+
+/// How many `schema_migrations` rows the report includes.
+const MIGRATION_HISTORY: i64 = 20;
+
+#[derive(Debug, Serialize)]
+pub struct SystemReport {
+    #[serde(flatten)]
+    pub build: VersionInfo,
+    pub schema: SchemaStatus,
+    /// Most recent first.
+    pub migrations: Vec<SchemaMigration>,
+    /// From this process's startup; empty in tests, which skip them.
+    pub startup_checks: Vec<Check>,
+    pub pool: PoolStats,
+    pub jobs: Vec<JobRun>,
+    pub config: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaStatus {
+    pub path: String,
+    pub sha256: String,
+    /// Steps the migrate binary would take to bring the database in line with
+    /// the schema file. Empty when they match.
+    pub pending: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
+#[get("/admin/system")]
+pub async fn api_system(
+    user: User,
+    config: &State<LiveConfig>,
+    db: &State<Pool<Sqlite>>,
+) -> ApiResult<Json<SystemReport>> {
+    user.require_permission(Permission::ViewSystemStatus)?;
+
+    let config = config.get();
+    let schema = read_schema_file_to_string(&config.schema_path)
+        .map_err(|e| AppError::Internal(format!("Failed to read schema file: {:?}", e)))?;
+    let changes = get_schema_changes(db.inner().clone(), &schema)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to analyze schema: {:?}", e)))?;
+
+    Ok(Json(SystemReport {
+        build: VersionInfo::current(),
+        schema: SchemaStatus {
+            path: config.schema_path.display().to_string(),
+            sha256: schema_sha256(&schema),
+            pending: planned_step_descriptions(&changes),
+        },
+        migrations: get_schema_migrations(db.inner(), MIGRATION_HISTORY).await?,
+        startup_checks: startup_report()
+            .map(|report| report.checks.clone())
+            .unwrap_or_default(),
+        pool: PoolStats {
+            size: db.size(),
+            idle: db.num_idle(),
+            max: db.options().get_max_connections(),
+        },
+        jobs: get_job_runs(db.inner()).await?,
+        config: config.summary(),
+    }))
+}
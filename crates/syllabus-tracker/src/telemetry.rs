@@ -1,11 +1,13 @@
 use once_cell::sync::OnceCell;
+use once_cell::sync::Lazy;
 use opentelemetry::{
     Context, KeyValue,
     global::{self},
+    metrics::Counter,
     propagation::{Extractor, TextMapCompositePropagator},
     trace::TracerProvider as _,
 };
-use opentelemetry_otlp::MetricExporter;
+use opentelemetry_otlp::{MetricExporter, Protocol, SpanExporter, WithExportConfig as _};
 use opentelemetry_sdk::{
     Resource,
     metrics::SdkMeterProvider,
@@ -20,13 +22,17 @@ use opentelemetry_semantic_conventions::{
 use rocket::{
     Data, Request, Response,
     fairing::{Fairing, Info, Kind},
-    http::Status,
+    http::{Cookie, SameSite, Status},
     request::{FromRequest, Outcome},
 };
+use serde::Deserialize;
 use std::collections::HashMap;
-use tracing::{Span, field};
+use std::time::Instant;
+use tracing::{Span, field, info, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
-use tracing_subscriber::{Registry, layer::SubscriberExt};
+use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt, reload};
+
+use crate::config::LiveConfig;
 
 static REQUEST_CONTEXT: OnceCell<Context> = OnceCell::new();
 
@@ -59,6 +65,48 @@ impl Extractor for OwnedHeaderExtractor {
     }
 }
 
+/// Cookie naming the browser in traces, shared by backend spans and the
+/// SPA's own telemetry so the two can be joined. Readable from script.
+pub const SESSION_COOKIE: &str = "otel_session_id";
+
+/// The browser's telemetry session id, from its `otel_session_id` cookie or
+/// newly issued for this request.
+#[derive(Debug)]
+struct ClientSessionId(String);
+
+impl ClientSessionId {
+    /// Ids the SPA generated before the backend issued them look like
+    /// `session_abc123`, so anything short and URL-safe is kept.
+    fn is_valid(value: &str) -> bool {
+        !value.is_empty()
+            && value.len() <= 64
+            && value.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    }
+
+    /// Reads the cookie, or issues a new id when it is missing or malformed.
+    /// Cached per request, so the cookie is set at most once.
+    fn ensure<'r>(request: &'r Request<'_>) -> &'r ClientSessionId {
+        request.local_cache(|| {
+            if let Some(cookie) = request.cookies().get(SESSION_COOKIE)
+                && Self::is_valid(cookie.value())
+            {
+                return ClientSessionId(cookie.value().to_string());
+            }
+            let id = uuid::Uuid::new_v4().to_string();
+            request.cookies().add(
+                Cookie::build((SESSION_COOKIE, id.clone()))
+                    .path("/")
+                    .same_site(SameSite::Lax)
+                    .http_only(false)
+                    .max_age(rocket::time::Duration::days(365)),
+            );
+            ClientSessionId(id)
+        })
+    }
+}
+
+/// Also issues the `otel_session_id` cookie to browsers that don't have one
+/// and records it on the request span as `session.id`.
 #[derive(Debug)]
 pub struct TelemetryFairing;
 
@@ -81,11 +129,7 @@ impl Fairing for TelemetryFairing {
             }
         }
 
-        let session_id = request
-            .cookies()
-            .get("otel_session_id")
-            .map(|cookie| cookie.value().to_string())
-            .unwrap_or_else(|| "unknown_session".to_string());
+        let session_id = ClientSessionId::ensure(request).0.clone();
 
         let user_id = request
             .cookies()
@@ -216,6 +260,92 @@ impl Fairing for ErrorTelemetryFairing {
     }
 }
 
+static SLOW_REQUESTS_TOTAL: Lazy<Counter<u64>> = Lazy::new(|| {
+    global::meter("syllabus-tracker.http")
+        .u64_counter("http_slow_requests_total")
+        .with_description("Requests that exceeded SLOW_REQUEST_THRESHOLD_MS, by route")
+        .build()
+});
+
+/// Start time of the request, stashed in the request-local cache by
+/// `SlowRequestFairing::on_request`.
+struct RequestStart(Option<Instant>);
+
+/// Times every request and flags the ones slower than the configured
+/// threshold (`SLOW_REQUEST_THRESHOLD_MS`, read from the managed
+/// `LiveConfig` so a reload takes effect) with `slow_request=true` and
+/// `slow_request.duration_ms` on the exported request span, plus a counter
+/// labelled by route template. The counter means chronically slow endpoints
+/// show up on the metrics dashboard even when none of their traces happened
+/// to be sampled.
+#[derive(Debug)]
+pub struct SlowRequestFairing;
+
+#[rocket::async_trait]
+impl Fairing for SlowRequestFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Slow request detection",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(started) = request.local_cache(|| RequestStart(None)).0 else {
+            return;
+        };
+        let Some(config) = request.rocket().state::<LiveConfig>() else {
+            return;
+        };
+        let threshold = config.get().slow_request_threshold();
+        let elapsed = started.elapsed();
+        if elapsed < threshold {
+            return;
+        }
+
+        // Label by route template rather than the concrete path so the
+        // metric's cardinality stays bounded by the number of mounted routes.
+        let route = request
+            .route()
+            .map(|route| route.uri.to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        let elapsed_ms = elapsed.as_millis() as u64;
+
+        if let Some(span) = request
+            .local_cache(|| TracingSpan::<Option<Span>>(None))
+            .0
+            .to_owned()
+        {
+            // Rocket's request span doesn't declare these fields, and
+            // `record` drops undeclared ones, so they go straight to the
+            // OpenTelemetry span.
+            span.set_attribute("slow_request", true);
+            span.set_attribute("slow_request.duration_ms", elapsed_ms as i64);
+        }
+
+        SLOW_REQUESTS_TOTAL.add(
+            1,
+            &[
+                KeyValue::new("http.route", route.clone()),
+                KeyValue::new(HTTP_REQUEST_METHOD, request.method().as_str()),
+            ],
+        );
+
+        warn!(
+            route = %route,
+            method = %request.method(),
+            status = response.status().code,
+            duration_ms = elapsed_ms,
+            threshold_ms = threshold.as_millis() as u64,
+            "slow request"
+        );
+    }
+}
+
 fn resource(videos_enabled: bool) -> Resource {
     Resource::builder()
         .with_schema_url(
@@ -229,7 +359,70 @@ fn resource(videos_enabled: bool) -> Resource {
         .build()
 }
 
-pub fn init_tracing(videos_enabled: bool) {
+/// Wire transport for the OTLP span and metric exporters, from
+/// `OTEL_EXPORTER_OTLP_PROTOCOL`: the spec's values (`grpc`,
+/// `http/protobuf`) plus a bare `http` shorthand. Unset means gRPC, which is
+/// what the bundled collector listens on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum OtlpTransport {
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http/protobuf", alias = "http")]
+    Http,
+}
+
+impl OtlpTransport {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OtlpTransport::Grpc => "grpc",
+            OtlpTransport::Http => "http/protobuf",
+        }
+    }
+}
+
+// Endpoint, headers and timeout are left to the exporter builders, which read
+// the standard `OTEL_EXPORTER_OTLP_{ENDPOINT,HEADERS,TIMEOUT}` variables (and
+// their per-signal `_TRACES_`/`_METRICS_` variants) for both transports. Over
+// HTTP the signal path (`/v1/traces`, `/v1/metrics`) is appended to the base
+// endpoint, so hosted backends only need the base URL and an auth header.
+fn span_exporter(transport: OtlpTransport) -> SpanExporter {
+    match transport {
+        OtlpTransport::Grpc => SpanExporter::builder().with_tonic().build(),
+        OtlpTransport::Http => SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .build(),
+    }
+    .unwrap()
+}
+
+fn metric_exporter(transport: OtlpTransport) -> MetricExporter {
+    match transport {
+        OtlpTransport::Grpc => MetricExporter::builder().with_tonic().build(),
+        OtlpTransport::Http => MetricExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .build(),
+    }
+    .unwrap()
+}
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Set by `init_tracing`; absent in tests, which don't install a subscriber.
+static LOG_FILTER: OnceCell<FilterHandle> = OnceCell::new();
+
+/// Swaps the log filter of the running subscriber, for config reloads.
+pub fn set_log_filter(directives: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directives).map_err(|e| format!("RUST_LOG: {}", e))?;
+    match LOG_FILTER.get() {
+        Some(handle) => handle.reload(filter).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+pub fn init_tracing(videos_enabled: bool, rust_log: &str, transport: OtlpTransport) {
     let baggage_propagator = BaggagePropagator::new();
     let trace_context_propagator = TraceContextPropagator::new();
     let composite_propagator = TextMapCompositePropagator::new(vec![
@@ -239,10 +432,7 @@ pub fn init_tracing(videos_enabled: bool) {
 
     global::set_text_map_propagator(composite_propagator);
 
-    let span_exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
-        .build()
-        .unwrap();
+    let span_exporter = span_exporter(transport);
 
     let tracer_provider = SdkTracerProvider::builder()
         .with_sampler(Sampler::AlwaysOn)
@@ -255,8 +445,10 @@ pub fn init_tracing(videos_enabled: bool) {
 
     let otel_layer = tracing_opentelemetry::layer().with_tracer(tracer);
 
-    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    // `rust_log` was checked by `AppConfig::validate`.
+    let env_filter = EnvFilter::try_new(rust_log).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    let _ = LOG_FILTER.set(handle);
 
     let subscriber = Registry::default()
         .with(env_filter)
@@ -266,7 +458,7 @@ pub fn init_tracing(videos_enabled: bool) {
     tracing::subscriber::set_global_default(subscriber)
         .expect("Failed to set global default subscriber");
 
-    let meter_exporter = MetricExporter::builder().with_tonic().build().unwrap();
+    let meter_exporter = metric_exporter(transport);
 
     let meter_provider = SdkMeterProvider::builder()
         .with_resource(resource(videos_enabled))
@@ -274,4 +466,6 @@ pub fn init_tracing(videos_enabled: bool) {
         .build();
 
     global::set_meter_provider(meter_provider);
+
+    info!(?transport, "OTLP exporters initialised");
 }
@@ -8,12 +8,36 @@ use crate::{
 
 #[cfg(test)]
 mod tests {
-    use crate::api::{LoginResponse, StudentTechniquesResponse, UserData};
-    use crate::db::get_student_technique;
+    use crate::activity::{ActivityFeed, ActivityKind};
+    use crate::api::{
+        BulkCreateTechniquesResponse, BulkStatusResponse, CoachReportResponse, CollectionResponse,
+        LoginResponse,
+        MeResponse, NoteHistoryResponse, NotificationsResponse, Paginated,
+        RemoveTechniquesResponse,
+        StudentAnalyticsResponse, StudentTechniqueHistoryResponse,
+        StudentTechniqueUpdateResponse, StudentTechniquesResponse, TagsResponse, UserData,
+    };
+    use crate::auth::{Permission, Role};
+    use crate::db::{
+        BadgeKind, DigestFrequency, Grading, GradingOutcome, GradingSummary, JournalEntry,
+        NoteField, NoteTemplate, NotificationKind,
+        NotificationPreferences, Prerequisite, RankEligibility, ScheduleSlot, StatusLevel,
+        StudentTechniqueField, TRAINING_DAYS_FOR_BADGE,
+        add_tag_to_technique, add_techniques_to_collection, award_badges, begin_password_attempt,
+        create_attempt, create_collection, create_tag,
+        get_collection, get_student_technique, get_tags_for_technique, get_user, rename_tag,
+        set_feature_flag, set_tag_parent,
+    };
+    use crate::attachments::{DynStorage, store_attachment};
+    use crate::models::{
+        GroupProgress, MediaKind, StatusCount, StudentProgress, Tag, TechniqueAlias,
+        TechniqueMedia,
+    };
+    use crate::ids::{StudentTechniqueId, TechniqueId, UserId};
     use crate::test::test_utils::{
         TestDbBuilder, create_standard_test_db, login_test_user, setup_test_client,
     };
-    use rocket::http::{ContentType, Cookie, Status};
+    use rocket::http::{ContentType, Cookie, Header, Status};
     use serde_json::json;
 
     #[rocket::async_test]
@@ -65,6 +89,42 @@ mod tests {
         assert!(login_response.error.is_some());
     }
 
+    /// The SPA follows `redirect_url` after login; it is the only
+    /// server-side page routing left now that the UI is served separately.
+    #[rocket::async_test]
+    async fn test_login_redirect_url_per_role() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+
+        let cases = [
+            ("admin_user", "/dashboard".to_string()),
+            ("coach_user", "/dashboard".to_string()),
+            ("student_user", format!("/student/{}", student_id)),
+        ];
+        for (username, expected) in cases {
+            let response = client
+                .post("/api/login")
+                .header(ContentType::JSON)
+                .body(json!({ "username": username, "password": "password123" }).to_string())
+                .dispatch()
+                .await;
+            let body = response.into_string().await.unwrap();
+            let login_response: LoginResponse = serde_json::from_str(&body).unwrap();
+            assert_eq!(login_response.redirect_url.as_deref(), Some(expected.as_str()));
+        }
+
+        let response = client
+            .post("/api/login")
+            .header(ContentType::JSON)
+            .body(json!({ "username": "student_user", "password": "nope" }).to_string())
+            .dispatch()
+            .await;
+        let body = response.into_string().await.unwrap();
+        let login_response: LoginResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(login_response.redirect_url, None);
+    }
+
     #[rocket::async_test]
     async fn test_auth_required_apis() {
         let test_db = create_standard_test_db().await;
@@ -128,6 +188,19 @@ mod tests {
         assert_eq!(user_data.username, "coach_user");
         assert_eq!(user_data.display_name, "Coach User");
         assert_eq!(user_data.role.to_lowercase(), "coach");
+
+        let me: MeResponse = serde_json::from_str(&body).unwrap();
+        assert!(me.permissions.contains(&Permission::AssignTechniques));
+        assert!(!me.permissions.contains(&Permission::EditUserRoles));
+        assert!(me.permissions.is_sorted());
+        let raw: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(raw["permissions"].as_array().unwrap().contains(&json!("view_all_students")));
+
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response = client.get("/api/me").cookies(cookies).dispatch().await;
+        let me: MeResponse = response.into_json().await.unwrap();
+        assert_eq!(me.permissions, Role::Student.sorted_permissions());
+        assert!(!me.permissions.contains(&Permission::ViewAllStudents));
     }
 
     #[rocket::async_test]
@@ -146,12 +219,13 @@ mod tests {
         assert_eq!(response.status(), Status::Ok);
 
         let body = response.into_string().await.unwrap();
-        let students: Vec<UserData> = serde_json::from_str(&body).unwrap();
+        let students: Paginated<UserData> = serde_json::from_str(&body).unwrap();
 
-        let student_exists = students.iter().any(|s| s.username == "student_user");
+        let student_exists = students.items.iter().any(|s| s.username == "student_user");
         assert!(student_exists, "student_user not found in students list");
 
         let student = students
+            .items
             .iter()
             .find(|s| s.username == "student_user")
             .unwrap();
@@ -159,6 +233,91 @@ mod tests {
         println!("students: {:?}, student: {:?}", students, student);
         assert_eq!(student.display_name, "Student User");
         assert_eq!(student.role.to_lowercase(), "student");
+
+        // The list carries each student's progress, so the SPA needn't fetch
+        // every student's techniques to show it.
+        assert_eq!(student.total_techniques, Some(1));
+        assert_eq!(student.red_count, Some(1));
+        assert_eq!(student.green_count, Some(0));
+        assert!(student.last_update.is_some());
+    }
+
+    #[rocket::async_test]
+    async fn test_coach_with_assigned_students_sees_only_them() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", Some("Other Student"))
+            .build()
+            .await
+            .unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let other_id = test_db.user_id("other_student").unwrap();
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let (client, _) = setup_test_client(test_db).await;
+
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+        let students_path = format!("/api/admin/coaches/{}/students", coach_id);
+        async fn usernames(
+            client: &rocket::local::asynchronous::Client,
+            cookies: Vec<Cookie<'static>>,
+        ) -> Vec<String> {
+            let response = client.get("/api/students").cookies(cookies).dispatch().await;
+            let page: Paginated<UserData> = response.into_json().await.unwrap();
+            page.items.into_iter().map(|s| s.username).collect()
+        }
+
+        // The fixture gives the coach everyone.
+        assert_eq!(usernames(&client, coach.clone()).await.len(), 2);
+
+        let response = client
+            .put(students_path.clone())
+            .cookies(admin.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [student_id] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get(students_path.clone()).cookies(admin.clone()).dispatch().await;
+        let assigned: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(assigned["student_ids"], json!([student_id]));
+
+        assert_eq!(usernames(&client, coach.clone()).await, vec!["student_user".to_string()]);
+        let other_techniques = format!("/api/student/{}/techniques", other_id);
+        let response =
+            client.get(other_techniques.clone()).cookies(coach.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let own_techniques = format!("/api/student/{}/techniques", student_id);
+        let response = client.get(own_techniques).cookies(coach.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // Admins see the whole gym regardless.
+        assert_eq!(usernames(&client, admin.clone()).await.len(), 2);
+        let response = client.get(other_techniques).cookies(admin.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // Taking away the last student leaves the coach with nobody, not everyone.
+        let response = client
+            .put(students_path.clone())
+            .cookies(admin.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(usernames(&client, coach.clone()).await.is_empty());
+
+        // Only students can be assigned.
+        let response = client
+            .put(students_path)
+            .cookies(admin)
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [coach_id] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
     }
 
     #[rocket::async_test]
@@ -182,9 +341,9 @@ mod tests {
         let data: StudentTechniquesResponse = serde_json::from_str(&body).unwrap();
 
         assert_eq!(data.student.username, "student_user");
-        assert!(!data.techniques.is_empty(), "No techniques found");
+        assert!(!data.techniques.items.is_empty(), "No techniques found");
 
-        let technique = &data.techniques[0];
+        let technique = &data.techniques.items[0];
         assert_eq!(technique.technique_name, "Armbar");
         assert_eq!(technique.status, "red");
         assert_eq!(technique.student_notes, "Student notes");
@@ -233,15 +392,227 @@ mod tests {
 
         assert_eq!(response.status(), Status::Ok);
 
-        let updated_technique = get_student_technique(&test_db.pool, student_technique_id, 0)
-            .await
-            .expect("Failed to get student technique");
+        let updated_technique =
+            get_student_technique(&test_db.pool, student_technique_id, UserId(0))
+                .await
+                .expect("Failed to get student technique");
 
         assert_eq!(updated_technique.status, "green");
         assert_eq!(updated_technique.coach_notes, "Updated coach notes");
         assert_eq!(updated_technique.student_notes, "Updated student notes");
     }
 
+    #[rocket::async_test]
+    async fn test_update_technique_api_reports_changed_fields() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "Mine", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_technique_id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .expect("Failed to get student technique id");
+        let url = format!("/api/student_technique/{}", student_technique_id);
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let update = json!({
+            "status": "amber",
+            "student_notes": "Mine",
+            "coach_notes": "Keep the elbow tight",
+            "technique_name": "Armbar",
+            "technique_description": "Armbar from guard"
+        });
+        let response = client
+            .put(&url)
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(update.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: StudentTechniqueUpdateResponse = response.into_json().await.unwrap();
+        assert_eq!(
+            body.changed,
+            vec![
+                StudentTechniqueField::Status,
+                StudentTechniqueField::CoachNotes,
+                StudentTechniqueField::TechniqueDescription,
+            ]
+        );
+        assert_eq!(body.technique.status, "amber");
+        assert_eq!(body.technique.coach_notes, "Keep the elbow tight");
+        assert_eq!(body.technique.technique_description, "Armbar from guard");
+        let updated_at = body.technique.updated_at;
+
+        // The same save again changes nothing, including the timestamps.
+        let response = client
+            .put(&url)
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(update.to_string())
+            .dispatch()
+            .await;
+        let body: StudentTechniqueUpdateResponse = response.into_json().await.unwrap();
+        assert!(body.changed.is_empty());
+        assert_eq!(body.technique.updated_at, updated_at);
+
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response = client
+            .put(&url)
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "student_notes": "Drilled it twice" }).to_string())
+            .dispatch()
+            .await;
+        let body: StudentTechniqueUpdateResponse = response.into_json().await.unwrap();
+        assert_eq!(body.changed, vec![StudentTechniqueField::StudentNotes]);
+        assert_eq!(body.technique.student_notes, "Drilled it twice");
+    }
+
+    #[rocket::async_test]
+    async fn test_update_technique_api_is_all_or_nothing() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+
+        let pool = test_db.pool.clone();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_technique_id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .expect("Failed to get student technique id");
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        // The rename is the last of the handler's writes; make it fail.
+        sqlx::query(
+            "CREATE TRIGGER fail_rename BEFORE UPDATE ON techniques
+             BEGIN SELECT RAISE(ABORT, 'rename failed'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let update = json!({ "status": "green", "technique_name": "Straight armbar" });
+        let response = client
+            .put(format!("/api/student_technique/{}", student_technique_id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(update.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::InternalServerError);
+
+        let unchanged = get_student_technique(&pool, student_technique_id, UserId(0))
+            .await
+            .unwrap();
+        assert_eq!(unchanged.status, "red");
+        assert_eq!(unchanged.technique_name, "Armbar");
+
+        sqlx::query("DROP TRIGGER fail_rename").execute(&pool).await.unwrap();
+        let response = client
+            .put(format!("/api/student_technique/{}", student_technique_id))
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(update.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let updated = get_student_technique(&pool, student_technique_id, UserId(0))
+            .await
+            .unwrap();
+        assert_eq!(updated.status, "green");
+        assert_eq!(updated.technique_name, "Straight armbar");
+    }
+
+    #[rocket::async_test]
+    async fn test_technique_lists_keep_a_stable_order() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Triangle", "d", Some("coach_user"))
+            .technique("Armbar", "d", Some("coach_user"))
+            .technique("Kimura", "d", Some("coach_user"))
+            .technique("Omoplata", "d", Some("coach_user"))
+            .technique("Americana", "d", Some("coach_user"))
+            .technique("Cross choke", "d", Some("coach_user"))
+            .assign_technique(Some("Triangle"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Kimura"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let pool = test_db.pool.clone();
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        for name in ["Submission", "Arm Lock"] {
+            let tag_id = create_tag(&pool, name).await.unwrap();
+            add_tag_to_technique(&pool, armbar, tag_id).await.unwrap();
+        }
+        // Ties on updated_at fall back to the newest assignment first.
+        sqlx::query("UPDATE student_techniques SET updated_at = '2024-01-01 00:00:00'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let mut assigned = Vec::new();
+        let mut unassigned = Vec::new();
+        for _ in 0..3 {
+            let response = client
+                .get(format!("/api/student/{}/techniques", student_id))
+                .cookies(cookies.clone())
+                .dispatch()
+                .await;
+            let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+            assigned.push(
+                body.techniques.items
+                    .iter()
+                    .map(|t| {
+                        let tags: Vec<String> = t.tags.iter().map(|tag| tag.name.clone()).collect();
+                        (t.technique_name.clone(), tags)
+                    })
+                    .collect::<Vec<_>>(),
+            );
+
+            let response = client
+                .get(format!("/api/student/{}/unassigned_techniques", student_id))
+                .cookies(cookies.clone())
+                .dispatch()
+                .await;
+            let body: serde_json::Value = response.into_json().await.unwrap();
+            let names: Vec<String> = body["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["name"].as_str().unwrap().to_string())
+                .collect();
+            unassigned.push(names);
+        }
+
+        let expected = vec![
+            ("Kimura".to_string(), vec![]),
+            ("Armbar".to_string(), vec!["Arm Lock".to_string(), "Submission".to_string()]),
+            ("Triangle".to_string(), vec![]),
+        ];
+        assert!(assigned.iter().all(|list| *list == expected), "{:?}", assigned);
+        let expected = ["Americana", "Cross choke", "Omoplata"];
+        assert!(unassigned.iter().all(|list| *list == expected), "{:?}", unassigned);
+    }
+
     #[rocket::async_test]
     async fn test_assign_techniques_api() {
         let test_db = TestDbBuilder::new()
@@ -287,13 +658,65 @@ mod tests {
         let data: StudentTechniquesResponse = serde_json::from_str(&body).unwrap();
 
         let has_triangle = data
-            .techniques
+            .techniques.items
             .iter()
             .any(|t| t.technique_name == "Triangle");
 
         assert!(has_triangle, "Triangle technique was not assigned");
     }
 
+    #[rocket::async_test]
+    async fn test_remove_techniques_api_is_all_or_nothing() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", Some("Other Student"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Triangle", "Description of triangle", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Armbar"), Some("other_student"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let armbar = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let triangle = test_db.student_technique_id("student_user", "Triangle").await.unwrap();
+        let others = test_db.student_technique_id("other_student", "Armbar").await.unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let remove = |ids: Vec<StudentTechniqueId>| {
+            client
+                .delete(format!("/api/student/{}/techniques", student_id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "student_technique_ids": ids }).to_string())
+                .dispatch()
+        };
+        let assigned = || async {
+            let response = client
+                .get(format!("/api/student/{}/techniques", student_id))
+                .cookies(cookies.clone())
+                .dispatch()
+                .await;
+            let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+            body.techniques.total
+        };
+
+        assert_eq!(remove(vec![]).await.status(), Status::UnprocessableEntity);
+        let response = remove(vec![armbar, others]).await;
+        assert_eq!(response.status(), Status::NotFound, "another student's row");
+        assert_eq!(assigned().await, 2, "nothing removed when one id is wrong");
+
+        let response = remove(vec![armbar, triangle]).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: RemoveTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!(body.removed, 2);
+        assert_eq!(assigned().await, 0);
+        assert!(test_db.student_technique_id("other_student", "Armbar").await.is_ok());
+    }
+
     #[rocket::async_test]
     async fn test_coach_update_bumps_coach_columns() {
         let test_db = TestDbBuilder::new()
@@ -323,7 +746,7 @@ mod tests {
             .await;
         assert_eq!(response.status(), Status::Ok);
 
-        let updated = get_student_technique(&pool, student_technique_id, 0)
+        let updated = get_student_technique(&pool, student_technique_id, UserId(0))
             .await
             .expect("Failed to fetch updated technique");
 
@@ -331,7 +754,7 @@ mod tests {
             updated.last_coach_update_at.is_some(),
             "last_coach_update_at should be set after coach update"
         );
-        assert_eq!(updated.last_coach_update_by_id, Some(coach_id));
+        assert_eq!(updated.last_coach_update_by_id, Some(coach_id.0));
         assert!(
             updated.last_student_update_at.is_none(),
             "last_student_update_at should remain unset after coach update"
@@ -359,7 +782,7 @@ mod tests {
 
         // Snapshot the coach timestamp set by the initial assignment so we can
         // assert the student edit does not overwrite it.
-        let before = get_student_technique(&pool, student_technique_id, 0)
+        let before = get_student_technique(&pool, student_technique_id, UserId(0))
             .await
             .expect("Failed to fetch baseline");
         let coach_stamp_before = before.last_coach_update_at;
@@ -374,7 +797,7 @@ mod tests {
             .await;
         assert_eq!(response.status(), Status::Ok);
 
-        let updated = get_student_technique(&pool, student_technique_id, 0)
+        let updated = get_student_technique(&pool, student_technique_id, UserId(0))
             .await
             .expect("Failed to fetch updated technique");
 
@@ -382,7 +805,7 @@ mod tests {
             updated.last_student_update_at.is_some(),
             "last_student_update_at should be set after student update"
         );
-        assert_eq!(updated.last_student_update_by_id, Some(student_id));
+        assert_eq!(updated.last_student_update_by_id, Some(student_id.0));
         assert_eq!(
             updated.last_coach_update_at, coach_stamp_before,
             "student-only update should not touch last_coach_update_at"
@@ -436,7 +859,7 @@ mod tests {
         let body = response.into_string().await.unwrap();
         let data: StudentTechniquesResponse = serde_json::from_str(&body).unwrap();
         let t = data
-            .techniques
+            .techniques.items
             .iter()
             .find(|t| t.id == student_technique_id)
             .expect("technique missing from response");
@@ -456,8 +879,8 @@ mod tests {
     async fn fetch_unseen_flag(
         client: &rocket::local::asynchronous::Client,
         cookies: Vec<Cookie<'static>>,
-        student_id: i64,
-        student_technique_id: i64,
+        student_id: UserId,
+        student_technique_id: StudentTechniqueId,
     ) -> bool {
         let response = client
             .get(format!("/api/student/{}/techniques", student_id))
@@ -467,7 +890,7 @@ mod tests {
         assert_eq!(response.status(), Status::Ok);
         let body = response.into_string().await.unwrap();
         let data: StudentTechniquesResponse = serde_json::from_str(&body).unwrap();
-        data.techniques
+        data.techniques.items
             .iter()
             .find(|t| t.id == student_technique_id)
             .expect("technique missing from response")
@@ -720,8 +1143,9 @@ mod tests {
             .await;
         assert_eq!(response.status(), Status::Ok);
         let body = response.into_string().await.unwrap();
-        let students: Vec<UserData> = serde_json::from_str(&body).unwrap();
+        let students: Paginated<UserData> = serde_json::from_str(&body).unwrap();
         let s = students
+            .items
             .iter()
             .find(|s| s.username == student_username)
             .expect("student missing from response");
@@ -946,6 +1370,60 @@ mod tests {
         assert_eq!(response.status(), Status::Forbidden);
     }
 
+    #[rocket::async_test]
+    async fn test_coach_sees_whether_student_read_their_update() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let st_id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+
+        let receipt = || async {
+            let response = client
+                .get(format!("/api/student/{}/techniques", student_id))
+                .cookies(coach_cookies.clone())
+                .dispatch()
+                .await;
+            let body: StudentTechniquesResponse =
+                serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+            body.techniques.items[0].coach_update_read
+        };
+        let coach_note = |note: &'static str| {
+            client
+                .put(format!("/api/student_technique/{}", st_id))
+                .cookies(coach_cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "coach_notes": note }).to_string())
+                .dispatch()
+        };
+        let student_opens = || {
+            client
+                .post(format!("/api/student_technique/{}/mark_seen", st_id))
+                .cookies(student_cookies.clone())
+                .dispatch()
+        };
+
+        assert_eq!(coach_note("Elbow tighter").await.status(), Status::Ok);
+        assert_eq!(receipt().await, Some(false));
+
+        assert_eq!(student_opens().await.status(), Status::NoContent);
+        assert_eq!(receipt().await, Some(true));
+
+        // The coach opening it doesn't count, and a newer note is unread again.
+        let response = client
+            .post(format!("/api/student_technique/{}/mark_seen", st_id))
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(coach_note("And hips up").await.status(), Status::Ok);
+        assert_eq!(receipt().await, Some(false));
+    }
+
     #[rocket::async_test]
     async fn test_graduate_student_keeps_edit_access() {
         let test_db = TestDbBuilder::new()
@@ -995,8 +1473,9 @@ mod tests {
             .await;
         assert_eq!(students_response.status(), Status::Ok);
         let body = students_response.into_string().await.unwrap();
-        let students: Vec<UserData> = serde_json::from_str(&body).unwrap();
+        let students: Paginated<UserData> = serde_json::from_str(&body).unwrap();
         let s = students
+            .items
             .iter()
             .find(|s| s.id == student_id)
             .expect("graduated student missing from list");
@@ -1018,8 +1497,9 @@ mod tests {
             .dispatch()
             .await;
         let body = students_after.into_string().await.unwrap();
-        let students: Vec<UserData> = serde_json::from_str(&body).unwrap();
+        let students: Paginated<UserData> = serde_json::from_str(&body).unwrap();
         let s = students
+            .items
             .iter()
             .find(|s| s.id == student_id)
             .expect("student missing from list");
@@ -1113,6 +1593,67 @@ mod tests {
         assert_eq!(login_response.status(), Status::Ok);
     }
 
+    #[rocket::async_test]
+    async fn test_register_through_a_role_preset_invitation() {
+        use crate::api::{InviteResponse, UserData};
+
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student"))
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let invite_response = client
+            .post("/api/admin/invitations")
+            .cookies(coach_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "role": "coach" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(invite_response.status(), Status::Ok);
+        let invite: InviteResponse = invite_response.into_json().await.unwrap();
+        let register = |username: &str| {
+            client
+                .post(format!("/api/register/invite/{}", invite.token))
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "username": username,
+                        "password": "secret123",
+                        "display_name": "New Coach"
+                    })
+                    .to_string(),
+                )
+        };
+
+        let response = register("student_user").dispatch().await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert!(response.into_string().await.unwrap().contains("username.taken"));
+
+        let response = register("new_coach").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.cookies().get_private("session_token").is_some());
+        let user: UserData = response.into_json().await.unwrap();
+        assert_eq!(user.username, "new_coach");
+        assert_eq!(user.display_name, "New Coach");
+        assert_eq!(user.role, "coach");
+
+        let response = register("another_coach").dispatch().await;
+        assert_eq!(response.status(), Status::Gone);
+
+        let response = client
+            .post("/api/login")
+            .header(ContentType::JSON)
+            .body(json!({ "username": "new_coach", "password": "secret123" }).to_string())
+            .dispatch()
+            .await;
+        let login: LoginResponse = response.into_json().await.unwrap();
+        assert!(login.success);
+    }
+
     #[rocket::async_test]
     async fn test_stub_user_cannot_log_in() {
         use crate::api::InviteResponse;
@@ -1211,6 +1752,3096 @@ mod tests {
         let login: LoginResponse = serde_json::from_str(&body).unwrap();
         assert!(!login.success);
     }
+
+    #[rocket::async_test]
+    async fn test_unknown_role_rejected_with_validation_error() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+        let student_id = test_db
+            .user_id("student_user")
+            .expect("student not found");
+
+        let register_response = client
+            .post("/api/register")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "username": "new_user",
+                    "display_name": "New User",
+                    "password": "password123",
+                    "confirm_password": "password123",
+                    "role": "superuser"
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(register_response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&register_response.into_string().await.unwrap()).unwrap();
+        assert!(
+            body["errors"]["request"][0]
+                .as_str()
+                .unwrap()
+                .contains("unknown role 'superuser'")
+        );
+
+        let update_response = client
+            .put(format!("/api/admin/users/{}", student_id))
+            .cookies(admin_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "role": "Coach" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(update_response.status(), Status::UnprocessableEntity);
+
+        let student = crate::db::get_user(&test_db.pool, student_id).await.unwrap();
+        assert_eq!(student.role, crate::auth::Role::Student);
+    }
+
+    #[rocket::async_test]
+    async fn test_role_change_signs_the_user_out_everywhere() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let before = login_test_user(&client, "student_user", "password123").await;
+        let other_device = login_test_user(&client, "student_user", "password123").await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let response = client
+            .put(format!("/api/admin/users/{}", student_id))
+            .cookies(admin_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "role": "coach" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // No token issued before the change carries over to the new role.
+        for cookies in [before, other_device] {
+            let response = client.get("/api/me").cookies(cookies).dispatch().await;
+            assert_eq!(response.status(), Status::Unauthorized);
+        }
+
+        let after = login_test_user(&client, "student_user", "password123").await;
+        let response = client.get("/api/me").cookies(after).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let me: MeResponse = response.into_json().await.unwrap();
+        assert_eq!(me.user.role.to_lowercase(), "coach");
+    }
+
+    #[rocket::async_test]
+    async fn test_field_limits_come_from_validation_config() {
+        let limits = crate::validation::ValidationConfig::default();
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let short_password = "x".repeat(limits.password_min - 1);
+        let response = client
+            .post("/api/register")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "username": "new_user",
+                    "display_name": "",
+                    "password": short_password,
+                    "confirm_password": short_password,
+                    "role": "student"
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(
+            body["errors"]["password"][0],
+            format!("Password must be at least {} characters long", limits.password_min)
+        );
+
+        let response = client
+            .post("/api/tags")
+            .cookies(admin_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "name": "x".repeat(limits.tag_name_max + 1) }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    #[rocket::async_test]
+    async fn test_free_text_is_stripped_of_html_before_saving() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+
+        let response = client
+            .put(format!("/api/student_technique/{}", id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "coach_notes": "<script>alert(1)</script>Keep the <b>wrist</b> & elbow",
+                    "student_notes": "Grip < 2 seconds &lt;img src=x onerror=alert(1)&gt;",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let saved = test_db.get_student_technique(id).await.unwrap();
+        assert_eq!(saved.coach_notes, "Keep the wrist & elbow");
+        assert_eq!(saved.student_notes, "Grip < 2 seconds ");
+
+        let response = client
+            .put("/api/profile")
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "display_name": "<img src=x onerror=alert(1)>Coach" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let coach = crate::db::get_user(&test_db.pool, coach_id).await.unwrap();
+        assert_eq!(coach.display_name, "Coach");
+    }
+
+    #[rocket::async_test]
+    async fn test_create_technique_flags_likely_duplicates() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let url = format!("/api/student/{}/create_technique", student_id);
+
+        let create = |name: &str, force: bool| {
+            client
+                .post(url.as_str())
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "name": name, "description": "d", "force": force }).to_string())
+        };
+
+        for near_duplicate in ["arm bar", "Armbarr", "Triangle!"] {
+            let response = create(near_duplicate, false).dispatch().await;
+            assert_eq!(response.status(), Status::Conflict, "{}", near_duplicate);
+            let body: serde_json::Value =
+                serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+            let detail = &body["details"]["name"][0];
+            assert_eq!(detail["code"], "technique.duplicate");
+            assert!(detail["params"]["candidates"][0]["id"].is_i64());
+        }
+
+        let response = create("Kimura", false).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = create("Arm bar", true).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // Creating straight into a curriculum is checked the same way.
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let curriculum =
+            create_collection(&test_db.pool, "Blue belt", "", None, coach_id).await.unwrap();
+        let create_in_collection = |force: bool| {
+            client
+                .post(format!("/api/collections/{}/create_technique", curriculum))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "name": "Kimora", "description": "d", "force": force }).to_string())
+                .dispatch()
+        };
+        assert_eq!(create_in_collection(false).await.status(), Status::Conflict);
+        assert_eq!(create_in_collection(true).await.status(), Status::Ok);
+    }
+
+    #[rocket::async_test]
+    async fn test_bulk_create_techniques_is_all_or_nothing() {
+        let test_db = create_standard_test_db().await;
+        let pool = test_db.pool.clone();
+        let (client, _) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        create_tag(&pool, "Guard").await.unwrap();
+        let technique_count = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM techniques")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        let before = technique_count().await;
+
+        let response = client
+            .post("/api/techniques/bulk")
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!([
+                    { "name": "Scissor sweep", "description": "From closed guard" },
+                    { "name": "", "description": "No name" },
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert!(body["details"]["1.name"][0]["code"].is_string(), "{}", body);
+        assert!(body["details"].get("0.name").is_none(), "{}", body);
+        assert_eq!(technique_count().await, before);
+
+        let response = client
+            .post("/api/techniques/bulk")
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(
+                json!([
+                    {
+                        "name": "Scissor sweep",
+                        "description": "From closed guard",
+                        "tags": ["Guard", "Sweep", "sweep"],
+                    },
+                    { "name": "Hip bump", "description": "Also a sweep", "tags": ["Sweep"] },
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let created: BulkCreateTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!(created.ids.len(), 2);
+        assert_eq!(technique_count().await, before + 2);
+
+        let tag_names = |tags: Vec<Tag>| tags.into_iter().map(|t| t.name).collect::<Vec<_>>();
+        let scissor = get_tags_for_technique(&pool, created.ids[0]).await.unwrap();
+        assert_eq!(tag_names(scissor), ["Guard", "Sweep"]);
+        let hip_bump = get_tags_for_technique(&pool, created.ids[1]).await.unwrap();
+        assert_eq!(tag_names(hip_bump), ["Sweep"]);
+    }
+
+    #[rocket::async_test]
+    async fn test_technique_aliases_are_listed_and_count_as_names() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+
+        let add = |alias: &str| {
+            client
+                .post(format!("/api/techniques/{}/aliases", armbar))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "alias": alias, "language": "ja" }).to_string())
+                .dispatch()
+        };
+        let response = add("Juji-gatame").await;
+        assert_eq!(response.status(), Status::Ok);
+        let aliases: Vec<TechniqueAlias> = response.into_json().await.unwrap();
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].language.as_deref(), Some("ja"));
+        assert_eq!(add("juji-GATAME").await.status(), Status::Conflict);
+
+        let response = client.get("/api/techniques").cookies(cookies.clone()).dispatch().await;
+        let library: serde_json::Value = response.into_json().await.unwrap();
+        let row = library.as_array().unwrap().iter().find(|t| t["name"] == "Armbar").unwrap();
+        assert_eq!(row["aliases"][0]["alias"], "Juji-gatame");
+
+        let student_id = test_db.user_id("student_user").unwrap();
+        let response = client
+            .post(format!("/api/student/{}/create_technique", student_id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Juji gatame", "description": "d" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client
+            .delete(format!("/api/techniques/{}/aliases/{}", armbar, aliases[0].id))
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[rocket::async_test]
+    async fn test_private_coach_notes_are_hidden_from_the_student() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let armbar = |cookies: Vec<Cookie<'static>>| {
+            let client = &client;
+            async move {
+                let response = client
+                    .get(format!("/api/student/{}/techniques", student_id))
+                    .cookies(cookies)
+                    .dispatch()
+                    .await;
+                let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+                body.techniques.items.into_iter().next().unwrap()
+            }
+        };
+        let st_id = armbar(coach.clone()).await.id;
+
+        let response = client
+            .put(format!("/api/student_technique/{}", st_id))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "coach_notes": "Not ready", "coach_notes_private": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: StudentTechniqueUpdateResponse = response.into_json().await.unwrap();
+        assert_eq!(body.technique.coach_notes, "Not ready");
+
+        assert_eq!(armbar(coach).await.coach_notes, "Not ready");
+        let seen_by_student = armbar(student.clone()).await;
+        assert!(seen_by_student.coach_notes_private);
+        assert_eq!(seen_by_student.coach_notes, "");
+
+        let response = client
+            .get(format!("/api/student_technique/{}/notes/history", st_id))
+            .cookies(student)
+            .dispatch()
+            .await;
+        let history: NoteHistoryResponse = response.into_json().await.unwrap();
+        assert_eq!(history.coach_notes, "");
+        assert!(history.revisions.iter().all(|r| r.field != NoteField::CoachNotes));
+    }
+
+    #[rocket::async_test]
+    async fn test_coach_changes_notify_the_student_until_read() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let st_id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let notifications = || {
+            let (client, student) = (&client, student.clone());
+            async move {
+                let response = client.get("/api/notifications").cookies(student).dispatch().await;
+                response.into_json::<NotificationsResponse>().await.unwrap()
+            }
+        };
+        let read = |id: i64, cookies: Vec<Cookie<'static>>| {
+            client.post(format!("/api/notifications/{}/read", id)).cookies(cookies).dispatch()
+        };
+        let write_notes = |notes: &'static str| {
+            client
+                .put(format!("/api/student_technique/{}", st_id))
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "coach_notes": notes }).to_string())
+                .dispatch()
+        };
+
+        // Seeding Armbar assigned it and wrote its coach notes.
+        let seeded = notifications().await;
+        assert_eq!(seeded.unread, 2);
+        for notification in &seeded.notifications.items {
+            assert_eq!(read(notification.id, student.clone()).await.status(), Status::Ok);
+        }
+
+        assert_eq!(write_notes("Keep the elbow tight").await.status(), Status::Ok);
+        // Still unread, so a second change doesn't add another.
+        assert_eq!(write_notes("Keep both elbows tight").await.status(), Status::Ok);
+        let body = notifications().await;
+        assert_eq!(body.unread, 1);
+        let note = &body.notifications.items[0];
+        assert_eq!(note.kind, NotificationKind::CoachNote);
+        assert_eq!(note.technique_name.as_deref(), Some("Armbar"));
+        assert_eq!(note.actor_name.as_deref(), Some("Coach User"));
+
+        assert_eq!(read(note.id, coach.clone()).await.status(), Status::NotFound);
+        assert_eq!(read(note.id, student.clone()).await.status(), Status::Ok);
+        assert_eq!(notifications().await.unread, 0);
+
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let response = client
+            .post(format!("/api/student/{}/add_techniques", student_id))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "technique_ids": [triangle] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = notifications().await;
+        assert_eq!(body.unread, 1);
+        assert_eq!(body.notifications.total, 4);
+        assert_eq!(body.notifications.items[0].kind, NotificationKind::Assignment);
+        assert_eq!(body.notifications.items[0].technique_name.as_deref(), Some("Triangle"));
+    }
+
+    #[rocket::async_test]
+    async fn test_student_activity_reaches_the_live_feed() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let st_id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let mut events = client.rocket().state::<ActivityFeed>().unwrap().subscribe();
+
+        let response = client.get("/api/coach/live").cookies(coach.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::UpgradeRequired);
+
+        // A coach's own edits aren't student activity.
+        let response = client
+            .put(format!("/api/student_technique/{}", st_id))
+            .cookies(coach)
+            .header(ContentType::JSON)
+            .body(json!({ "coach_notes": "Watch the thumb" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .post(format!("/api/student_technique/{}/request_review", st_id))
+            .cookies(student)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, ActivityKind::ReviewRequested);
+        assert_eq!(event.student_id, student_id);
+        assert_eq!(event.student_technique_id, st_id);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[rocket::async_test]
+    async fn test_assign_by_tag_assigns_the_subtree_and_skips_existing() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let pool = &test_db.pool;
+        let submissions = create_tag(pool, "Submissions").await.unwrap();
+        let arm_locks = create_tag(pool, "Arm locks").await.unwrap();
+        set_tag_parent(pool, arm_locks, Some(submissions)).await.unwrap();
+        add_tag_to_technique(pool, test_db.technique_id("Armbar").unwrap(), arm_locks)
+            .await
+            .unwrap();
+        add_tag_to_technique(pool, test_db.technique_id("Triangle").unwrap(), submissions)
+            .await
+            .unwrap();
+
+        let assign = |body: serde_json::Value| {
+            client
+                .post(format!("/api/student/{}/assign_by_tag", student_id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        // Armbar is already assigned.
+        let response = assign(json!({ "tag_id": submissions })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body, json!({ "created": 1, "skipped": 1 }));
+
+        let response = assign(json!({ "tag_id": submissions })).await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body, json!({ "created": 0, "skipped": 2 }));
+
+        let both = json!({ "tag_id": submissions, "collection_id": 1 });
+        assert_eq!(assign(both).await.status(), Status::UnprocessableEntity);
+        assert_eq!(assign(json!({ "tag_id": 999999 })).await.status(), Status::NotFound);
+    }
+
+    #[rocket::async_test]
+    async fn test_variants_group_under_their_base_technique() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Triangle", "Description of triangle", Some("coach_user"))
+            .technique("Zero-gi armbar", "Description of variant", Some("coach_user"))
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let variant = test_db.technique_id("Zero-gi armbar").unwrap();
+
+        let set_parent = |id: TechniqueId, name: &str, parent: Option<TechniqueId>| {
+            client
+                .put(format!("/api/techniques/{}", id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "name": name, "description": "d", "parent_technique_id": parent })
+                        .to_string(),
+                )
+                .dispatch()
+        };
+        let response = set_parent(variant, "Zero-gi armbar", Some(armbar)).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let names: Vec<String> = crate::db::get_all_techniques(&test_db.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["Armbar", "Zero-gi armbar", "Triangle"]);
+
+        // Variants are one level deep.
+        for (id, name, parent) in [
+            (triangle, "Triangle", variant),
+            (armbar, "Armbar", triangle),
+            (triangle, "Triangle", triangle),
+        ] {
+            let response = set_parent(id, name, Some(parent)).await;
+            assert_eq!(response.status(), Status::UnprocessableEntity, "{}", name);
+        }
+        let triangle_row = crate::db::get_all_techniques(&test_db.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|t| t.id == triangle)
+            .unwrap();
+        assert_eq!(triangle_row.description, "Description of triangle");
+        assert_eq!(triangle_row.parent_technique_id, None);
+
+        let response = set_parent(variant, "Zero-gi armbar", None).await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/api/techniques").cookies(cookies).dispatch().await;
+        let library: serde_json::Value = response.into_json().await.unwrap();
+        let row = library.as_array().unwrap().iter().find(|t| t["id"] == variant.0).unwrap();
+        assert!(row["parent_technique_id"].is_null());
+    }
+
+    #[rocket::async_test]
+    async fn test_prerequisites_reject_cycles_and_warn_on_assignment() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+
+        let add = |technique: TechniqueId, prerequisite: TechniqueId| {
+            client
+                .post(format!("/api/techniques/{}/prerequisites", technique))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "prerequisite_id": prerequisite }).to_string())
+                .dispatch()
+        };
+        let response = add(triangle, armbar).await;
+        assert_eq!(response.status(), Status::Ok);
+        let prerequisites: Vec<Prerequisite> = response.into_json().await.unwrap();
+        assert_eq!(prerequisites.len(), 1);
+        assert_eq!(prerequisites[0].technique_name, "Armbar");
+        assert_eq!(add(armbar, triangle).await.status(), Status::UnprocessableEntity);
+        assert_eq!(add(armbar, armbar).await.status(), Status::UnprocessableEntity);
+
+        // Armbar is assigned to the student but still red.
+        let assign = |force: bool| {
+            client
+                .post(format!("/api/student/{}/add_techniques", student_id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "technique_ids": [triangle], "force": force }).to_string())
+                .dispatch()
+        };
+        let response = assign(false).await;
+        assert_eq!(response.status(), Status::Conflict);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let error = &body["details"]["technique_ids"][0];
+        assert_eq!(error["code"], "technique.prerequisites_unmet");
+        assert_eq!(error["params"]["names"], "Armbar");
+        assert_eq!(assign(true).await.status(), Status::Ok);
+
+        let response = client
+            .delete(format!("/api/techniques/{}/prerequisites/{}", triangle, armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .get(format!("/api/techniques/{}/prerequisites", triangle))
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        let prerequisites: Vec<Prerequisite> = response.into_json().await.unwrap();
+        assert!(prerequisites.is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_technique_media_links_urls_and_uploads() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let storage = client.rocket().state::<DynStorage>().unwrap().clone();
+        let source = std::env::temp_dir().join(format!("media-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&source, "png").unwrap();
+        let upload =
+            store_attachment(&test_db.pool, &storage, coach_id, "grip.png", "image/png", &source)
+                .await
+                .unwrap();
+        std::fs::remove_file(&source).ok();
+
+        let add = |body: serde_json::Value| {
+            client
+                .post(format!("/api/techniques/{}/media", armbar))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let response = add(json!({ "kind": "video", "url": "javascript:alert(1)" })).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = add(json!({
+            "kind": "video",
+            "url": "https://youtu.be/abcdef",
+            "attachment_id": upload.id
+        }))
+        .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = add(json!({ "kind": "image", "attachment_id": 999_999 })).await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response =
+            add(json!({ "kind": "video", "title": "Demo", "url": "https://youtu.be/abcdef" }))
+                .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = add(json!({ "kind": "image", "attachment_id": upload.id })).await;
+        let media: Vec<TechniqueMedia> = response.into_json().await.unwrap();
+        assert_eq!(media.len(), 2);
+        assert_eq!(media[0].kind, MediaKind::Video);
+        assert_eq!(media[0].title, "Demo");
+        assert_eq!(media[1].attachment_id, Some(upload.id));
+
+        // The student sees it on their assignment and may fetch the upload.
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let response = client
+            .get(format!("/api/student/{}/techniques", student_id))
+            .cookies(student.clone())
+            .dispatch()
+            .await;
+        let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!(body.techniques.items[0].media, media);
+        let response = client
+            .get(format!("/api/attachments/{}/download-url", upload.id))
+            .cookies(student)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .delete(format!("/api/techniques/{}/media/{}", armbar, media[0].id))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .get(format!("/api/techniques/{}/media", armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        let remaining: Vec<TechniqueMedia> = response.into_json().await.unwrap();
+        assert_eq!(remaining, media[1..]);
+    }
+
+    #[rocket::async_test]
+    async fn test_oversized_notes_and_bodies_are_rejected() {
+        let limits = crate::validation::ValidationConfig::default();
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let url = format!("/api/student_technique/{}", id);
+
+        let response = client
+            .put(url.as_str())
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "coach_notes": "x".repeat(limits.technique_notes_max + 1) }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["coach_notes"][0]["code"], "notes.too_long");
+
+        let limit = crate::test::test_utils::test_live_config().get().json_body_limit();
+        let huge = "x".repeat(limit.as_u64() as usize + 1);
+        let response = client
+            .put(url.as_str())
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "coach_notes": huge }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+
+        let unchanged = test_db.get_student_technique(id).await.unwrap();
+        assert_eq!(unchanged.coach_notes, "Coach notes");
+    }
+
+    #[rocket::async_test]
+    async fn test_timezone_preference_round_trips_through_me() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+
+        let set_timezone = |timezone: serde_json::Value| {
+            client
+                .put("/api/profile/timezone")
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "timezone": timezone }).to_string())
+        };
+        let me = || async {
+            let response = client.get("/api/me").cookies(cookies.clone()).dispatch().await;
+            let body = response.into_string().await.unwrap();
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()
+        };
+
+        assert_eq!(me().await["timezone"], serde_json::Value::Null);
+
+        let response = set_timezone(json!("Australia/Sydney")).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(me().await["timezone"], "Australia/Sydney");
+
+        let response = set_timezone(json!("EST5EDT-ish")).dispatch().await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["timezone"][0]["code"], "timezone.invalid");
+        assert_eq!(me().await["timezone"], "Australia/Sydney");
+
+        let response = set_timezone(serde_json::Value::Null).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(me().await["timezone"], serde_json::Value::Null);
+    }
+
+    #[rocket::async_test]
+    async fn test_validation_response_carries_codes_and_params() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let response = client
+            .post("/api/register")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "username": "ab",
+                    "display_name": "",
+                    "password": "abc",
+                    "confirm_password": "abc",
+                    "role": "student"
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+
+        let password = &body["details"]["password"][0];
+        assert_eq!(password["code"], "password.too_short");
+        assert_eq!(password["params"]["min"], 5);
+        assert_eq!(password["message"], body["errors"]["password"][0]);
+
+        let username = &body["details"]["username"][0];
+        assert_eq!(username["code"], "username.length");
+        assert_eq!(username["params"]["min"], 3);
+        assert_eq!(username["params"]["max"], 50);
+
+        // Statuses and AppErrors get a code too.
+        let response = client
+            .get("/api/collections/999999")
+            .cookies(admin_cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["resource"][0]["code"], "error.resource_not_found");
+    }
+
+    #[rocket::async_test]
+    async fn test_validation_messages_follow_accept_language() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let body = json!({
+            "username": "new_user",
+            "display_name": "",
+            "password": "abc",
+            "confirm_password": "abd",
+            "role": "student"
+        })
+        .to_string();
+
+        let response = client
+            .post("/api/register")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .header(Header::new("Accept-Language", "pt-BR,pt;q=0.9,en;q=0.8"))
+            .body(body.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let json: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(
+            json["errors"]["password"][0],
+            "A senha deve ter pelo menos 5 caracteres"
+        );
+        assert_eq!(json["errors"]["confirm_password"][0], "As senhas devem coincidir");
+
+        // Unsupported languages keep the English text.
+        let response = client
+            .post("/api/register")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .header(Header::new("Accept-Language", "fr"))
+            .body(body)
+            .dispatch()
+            .await;
+        let json: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(
+            json["errors"]["password"][0],
+            "Password must be at least 5 characters long"
+        );
+
+        let response = client
+            .get("/api/collections/999999")
+            .cookies(admin_cookies)
+            .header(Header::new("Accept-Language", "es"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        let json: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(json["errors"]["resource"][0], "No encontrado: Collection 999999 not found");
+    }
+
+    #[rocket::async_test]
+    async fn test_usernames_are_nfc_normalized_and_validated() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let register = |username: &'static str| {
+            client
+                .post("/api/register")
+                .cookies(admin_cookies.clone())
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "username": username,
+                        "display_name": null,
+                        "password": "password123",
+                        "confirm_password": "password123",
+                        "role": "student"
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+        };
+
+        // "e" + combining acute is stored as the precomposed "é".
+        assert_eq!(register("  jose\u{301}  ").await.status(), Status::Created);
+        let stored = crate::db::find_user_by_username(&test_db.pool, "jos\u{e9}")
+            .await
+            .unwrap();
+        assert!(stored.is_some());
+
+        // Logging in with the decomposed form reaches the same account.
+        let cookies = login_test_user(&client, "jose\u{301}", "password123").await;
+        assert!(!cookies.is_empty());
+
+        // Two graphemes, three code points: too short.
+        let response = register("ne\u{301}").await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        for bad in ["bad name", "bad!name", "zero\u{200b}width"] {
+            let response = register(bad).await;
+            assert_eq!(response.status(), Status::UnprocessableEntity, "{}", bad);
+            let body: serde_json::Value =
+                serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+            assert!(body["errors"]["username"].is_array(), "{}", bad);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_legacy_usernames_are_normalized_on_startup() {
+        let test_db = create_standard_test_db().await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let rename = |id: UserId, username: &'static str| {
+            sqlx::query("UPDATE users SET username = ? WHERE id = ?")
+                .bind(username)
+                .bind(id.0)
+                .execute(&test_db.pool)
+        };
+        // Written before usernames were normalised: untrimmed and decomposed.
+        rename(student_id, " jose\u{301} ").await.unwrap();
+        // Normalises onto an existing account, so it has to stay as it is.
+        rename(coach_id, "admin_user ").await.unwrap();
+
+        crate::db::run_data_migrations(&test_db.pool).await.unwrap();
+
+        let username = |id: UserId| {
+            sqlx::query_scalar::<_, String>("SELECT username FROM users WHERE id = ?")
+                .bind(id.0)
+                .fetch_one(&test_db.pool)
+        };
+        assert_eq!(username(student_id).await.unwrap(), "jos\u{e9}");
+        assert_eq!(username(coach_id).await.unwrap(), "admin_user ");
+
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "jose\u{301}", "password123").await;
+        assert!(!cookies.is_empty());
+        let cookies = login_test_user(&client, "admin_user", "password123").await;
+        assert!(!cookies.is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_display_name_falls_back_to_username() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let update_profile = |body: serde_json::Value| {
+            client
+                .put("/api/profile")
+                .cookies(student_cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        // Blank is refused; null is the way to clear the name.
+        let response = update_profile(json!({ "display_name": "   " })).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["display_name"][0]["code"], "display_name.required");
+
+        let response = update_profile(json!({ "display_name": null })).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/me").cookies(student_cookies.clone()).dispatch().await;
+        let me: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(me["display_name"], "student_user");
+
+        // Leaving the field out keeps the cleared name cleared.
+        let response = update_profile(json!({ "username": "student_user" })).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let student_id = test_db.user_id("student_user").unwrap();
+        let response = client
+            .get(format!("/api/student/{}/techniques", student_id))
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["student"]["display_name"], "student_user");
+
+        let response = client.get("/api/students").cookies(coach_cookies).dispatch().await;
+        let students: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let listed = students["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["username"] == "student_user")
+            .unwrap();
+        assert_eq!(listed["display_name"], "student_user");
+    }
+
+    #[rocket::async_test]
+    async fn test_preferences_round_trip_per_user() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let limits = crate::validation::ValidationConfig::default();
+
+        let get_preferences = |cookies: Vec<Cookie<'static>>| {
+            client.get("/api/me/preferences").cookies(cookies).dispatch()
+        };
+        let put_preferences = |body: String| {
+            client
+                .put("/api/me/preferences")
+                .cookies(student_cookies.clone())
+                .header(ContentType::JSON)
+                .body(body)
+                .dispatch()
+        };
+
+        let response = get_preferences(student_cookies.clone()).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.unwrap(), "{}");
+
+        let prefs = json!({ "theme": "dark", "collapsed": ["tags"], "sort": { "by": "name" } });
+        let response = put_preferences(prefs.to_string()).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = get_preferences(student_cookies.clone()).await;
+        let stored: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(stored, prefs);
+
+        // Preferences belong to the caller only.
+        let response = get_preferences(coach_cookies).await;
+        assert_eq!(response.into_string().await.unwrap(), "{}");
+
+        let response = put_preferences(json!(["not", "an", "object"]).to_string()).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["request"][0]["code"], "request.invalid_body");
+
+        let oversized = json!({ "blob": "x".repeat(limits.preferences_max_bytes) });
+        let response = put_preferences(oversized.to_string()).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["preferences"][0]["code"], "preferences.too_large");
+
+        // Rejected writes leave the stored object alone.
+        let response = get_preferences(student_cookies.clone()).await;
+        let stored: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(stored, prefs);
+    }
+
+    #[rocket::async_test]
+    async fn test_notification_preferences_default_and_round_trip() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let get = || {
+            client
+                .get("/api/me/notification_preferences")
+                .cookies(cookies.clone())
+                .dispatch()
+        };
+        let put = |body: serde_json::Value| {
+            client
+                .put("/api/me/notification_preferences")
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        let response = get().await;
+        assert_eq!(response.status(), Status::Ok);
+        let defaults: NotificationPreferences =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(defaults, NotificationPreferences::default());
+        assert!(defaults.wants_email(NotificationKind::Assignment));
+        assert!(!defaults.wants_email(NotificationKind::Digest));
+
+        let saved = json!({
+            "email_on_assignment": false,
+            "email_on_coach_note": true,
+            "digest": "weekly"
+        });
+        let response = put(saved.clone()).await;
+        assert_eq!(response.status(), Status::Ok);
+        let stored: NotificationPreferences =
+            serde_json::from_str(&get().await.into_string().await.unwrap()).unwrap();
+        assert_eq!(stored.digest, DigestFrequency::Weekly);
+        assert!(!stored.wants_email(NotificationKind::Assignment));
+        assert!(stored.wants_email(NotificationKind::Digest));
+
+        let response = put(json!({
+            "email_on_assignment": true,
+            "email_on_coach_note": true,
+            "digest": "hourly"
+        }))
+        .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["details"]["request"][0]["code"], "request.invalid_body");
+        let unchanged: serde_json::Value =
+            serde_json::from_str(&get().await.into_string().await.unwrap()).unwrap();
+        assert_eq!(unchanged, saved);
+    }
+
+    #[rocket::async_test]
+    async fn test_status_changes_follow_configured_transitions() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        let url = format!("/api/student_technique/{}", id);
+
+        let rules = json!({
+            "transitions": [
+                { "from_status": "red", "to_status": "amber" },
+                { "from_status": "amber", "to_status": "green", "min_role": "admin" }
+            ]
+        })
+        .to_string();
+        let response = client
+            .put("/api/status_transitions")
+            .cookies(coach_cookies.clone())
+            .header(ContentType::JSON)
+            .body(rules.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .put("/api/status_transitions")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(rules)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .get("/api/status_transitions")
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        // Ordered by from_status, so amber -> green comes first.
+        assert_eq!(body["transitions"][0]["min_role"], "admin");
+        assert_eq!(body["transitions"][1]["min_role"], "coach");
+
+        let set_status = |cookies: Vec<Cookie<'static>>, status: &'static str| {
+            client
+                .put(url.as_str())
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(json!({ "status": status }).to_string())
+                .dispatch()
+        };
+
+        // Skipping amber is not a configured transition.
+        let response = set_status(coach_cookies.clone(), "green").await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let detail = &body["details"]["status"][0];
+        assert_eq!(detail["code"], "status.transition_not_allowed");
+        assert_eq!(detail["params"]["from"], "red");
+        assert_eq!(detail["params"]["to"], "green");
+
+        let response = set_status(coach_cookies.clone(), "amber").await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // amber -> green is reserved for admins.
+        let response = set_status(coach_cookies.clone(), "green").await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let detail = &body["details"]["status"][0];
+        assert_eq!(detail["code"], "status.transition_requires_role");
+        assert_eq!(detail["params"]["min_role"], "admin");
+
+        // Re-sending the current status is not a transition.
+        let response = set_status(coach_cookies, "amber").await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = set_status(admin_cookies, "green").await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(test_db.get_student_technique(id).await.unwrap().status, "green");
+    }
+
+    #[rocket::async_test]
+    async fn test_review_request_flags_until_coach_changes_status() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        let request_url = format!("/api/student_technique/{}/request_review", id);
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        // Only the student can ask.
+        let response = client
+            .post(request_url.as_str())
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .post(request_url.as_str())
+            .cookies(student_cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        let requested_at = test_db.get_student_technique(id).await.unwrap().review_requested_at;
+        assert!(requested_at.is_some());
+
+        let response = client
+            .get("/api/students")
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        let students: Paginated<UserData> =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let student = students.items.iter().find(|s| s.username == "student_user").unwrap();
+        assert_eq!(student.review_requests, Some(1));
+
+        let update = |body: serde_json::Value| {
+            client
+                .put(format!("/api/student_technique/{}", id))
+                .cookies(coach_cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        // A note alone is not a review.
+        let response = update(json!({ "coach_notes": "Looking now" })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let st = test_db.get_student_technique(id).await.unwrap();
+        assert_eq!(st.review_requested_at, requested_at);
+
+        let response = update(json!({ "status": "amber" })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let st = test_db.get_student_technique(id).await.unwrap();
+        assert!(st.review_requested_at.is_none());
+    }
+
+    #[rocket::async_test]
+    async fn test_note_history_keeps_overwritten_notes_and_restores_them() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let update = |cookies: Vec<Cookie<'static>>, body: serde_json::Value| {
+            client
+                .put(format!("/api/student_technique/{}", id))
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        update(student_cookies.clone(), json!({ "student_notes": "Oops" })).await;
+        // Saving the same text again isn't a new version.
+        update(student_cookies.clone(), json!({ "student_notes": "Oops" })).await;
+        update(coach_cookies.clone(), json!({ "coach_notes": "Elbow tighter" })).await;
+
+        let response = client
+            .get(format!("/api/student_technique/{}/notes/history", id))
+            .cookies(student_cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let history: NoteHistoryResponse = response.into_json().await.unwrap();
+        assert_eq!(history.student_notes, "Oops");
+        assert_eq!(history.coach_notes, "Elbow tighter");
+        let revisions: Vec<(NoteField, &str, Option<&str>)> = history
+            .revisions
+            .iter()
+            .map(|r| (r.field, r.content.as_str(), r.replaced_by_name.as_deref()))
+            .collect();
+        assert_eq!(
+            revisions,
+            vec![
+                (NoteField::CoachNotes, "Coach notes", Some("Coach User")),
+                (NoteField::StudentNotes, "Student notes", Some("Student User")),
+            ]
+        );
+        let coach_revision = history.revisions[0].id;
+        let student_revision = history.revisions[1].id;
+
+        let restore = |cookies: Vec<Cookie<'static>>, revision_id: i64| {
+            client
+                .post(format!("/api/student_technique/{}/notes/restore", id))
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(json!({ "revision_id": revision_id }).to_string())
+                .dispatch()
+        };
+        let response = restore(student_cookies.clone(), coach_revision).await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = restore(student_cookies.clone(), student_revision).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let st = test_db.get_student_technique(id).await.unwrap();
+        assert_eq!(st.student_notes, "Student notes");
+        assert_eq!(st.coach_notes, "Elbow tighter");
+        let response = client
+            .get(format!("/api/student_technique/{}/notes/history", id))
+            .cookies(coach_cookies)
+            .dispatch()
+            .await;
+        let history: NoteHistoryResponse = response.into_json().await.unwrap();
+        assert_eq!(history.revisions[0].content, "Oops");
+        assert_eq!(history.revisions.len(), 3);
+    }
+
+    #[rocket::async_test]
+    async fn test_student_technique_history_records_who_changed_what() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let update = |cookies: Vec<Cookie<'static>>, body: serde_json::Value| {
+            client
+                .put(format!("/api/student_technique/{}", id))
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        update(coach_cookies.clone(), json!({ "status": "amber", "coach_notes": "Hips" })).await;
+        // An unchanged save records nothing.
+        update(coach_cookies.clone(), json!({ "status": "amber" })).await;
+        update(student_cookies.clone(), json!({ "student_notes": "Felt it" })).await;
+        let response = client
+            .post(format!("/api/student/{}/techniques/bulk_status", student_id))
+            .cookies(coach_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "status": "green" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .get(format!("/api/student_technique/{}/history", id))
+            .cookies(student_cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let history: StudentTechniqueHistoryResponse = response.into_json().await.unwrap();
+        let revisions: Vec<(StudentTechniqueField, &str, &str, Option<&str>)> = history
+            .revisions
+            .iter()
+            .map(|r| {
+                (r.field, r.old_value.as_str(), r.new_value.as_str(), r.changed_by_name.as_deref())
+            })
+            .collect();
+        let coach = Some("Coach User");
+        assert_eq!(
+            revisions,
+            vec![
+                (StudentTechniqueField::Status, "amber", "green", coach),
+                (StudentTechniqueField::StudentNotes, "", "Felt it", Some("Student User")),
+                (StudentTechniqueField::CoachNotes, "", "Hips", coach),
+                (StudentTechniqueField::Status, "red", "amber", coach),
+            ]
+        );
+
+        let other_cookies = login_test_user(&client, "other_student", "password123").await;
+        let response = client
+            .get(format!("/api/student_technique/{}/history", id))
+            .cookies(other_cookies)
+            .dispatch()
+            .await;
+        assert_ne!(response.status(), Status::Ok);
+    }
+
+    #[rocket::async_test]
+    async fn test_note_templates_are_personal_or_shared_and_fill_coach_notes() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .coach("coach_user", Some("Coach User"))
+            .coach("other_coach", Some("Other Coach"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let id = test_db
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let other_coach = login_test_user(&client, "other_coach", "password123").await;
+
+        let create = |cookies: Vec<Cookie<'static>>, body: serde_json::Value| {
+            client
+                .post("/api/note_templates")
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let shared = json!({ "name": "Pass", "body": "Ready for grading", "shared": true });
+        assert_eq!(create(coach.clone(), shared.clone()).await.status(), Status::Forbidden);
+        let shared: NoteTemplate = create(admin, shared).await.into_json().await.unwrap();
+        let own: NoteTemplate = create(coach.clone(), json!({ "name": "Grip", "body": "Grips" }))
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        assert_eq!(own.owner_id, test_db.user_id("coach_user"));
+
+        let names = |cookies: Vec<Cookie<'static>>| {
+            let client = &client;
+            async move {
+                let response = client.get("/api/note_templates").cookies(cookies).dispatch().await;
+                let templates: Vec<NoteTemplate> = response.into_json().await.unwrap();
+                templates.into_iter().map(|t| t.name).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(names(coach.clone()).await, vec!["Pass", "Grip"]);
+        assert_eq!(names(other_coach.clone()).await, vec!["Pass"]);
+
+        let response = client
+            .put(format!("/api/note_templates/{}", shared.id))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Pass", "body": "Changed" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .delete(format!("/api/note_templates/{}", own.id))
+            .cookies(other_coach.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let update = |cookies: Vec<Cookie<'static>>, body: serde_json::Value| {
+            client
+                .put(format!("/api/student_technique/{}", id))
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let both = json!({ "coach_notes": "Typed", "coach_notes_template_id": shared.id });
+        let response = update(coach.clone(), both).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response =
+            update(other_coach, json!({ "coach_notes_template_id": own.id })).await;
+        assert_eq!(response.status(), Status::NotFound);
+        let response = update(coach, json!({ "coach_notes_template_id": shared.id })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let st = test_db.get_student_technique(id).await.unwrap();
+        assert_eq!(st.coach_notes, "Ready for grading");
+    }
+
+    #[rocket::async_test]
+    async fn test_bulk_status_change_by_tag_and_current_status() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", None)
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Kimura"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let tag = create_tag(&test_db.pool, "Submissions").await.unwrap();
+        for name in ["Armbar", "Kimura"] {
+            let technique_id = test_db.technique_id(name).unwrap();
+            add_tag_to_technique(&test_db.pool, technique_id, tag).await.unwrap();
+        }
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        let bulk = |cookies: Vec<Cookie<'static>>, body: serde_json::Value| {
+            client
+                .post(format!("/api/student/{}/techniques/bulk_status", student_id))
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let statuses = || async {
+            let mut statuses = Vec::new();
+            for name in ["Armbar", "Triangle", "Kimura"] {
+                let id = test_db.student_technique_id("student_user", name).await.unwrap();
+                statuses.push(test_db.get_student_technique(id).await.unwrap().status);
+            }
+            statuses
+        };
+
+        let body = json!({ "tag_id": tag, "from_status": "green", "status": "amber" });
+        let response = bulk(coach.clone(), body).await;
+        assert_eq!(response.status(), Status::Ok);
+        let result: BulkStatusResponse = response.into_json().await.unwrap();
+        assert_eq!(result.updated, 1);
+        assert_eq!(statuses().await, vec!["amber", "green", "red"]);
+
+        client
+            .put("/api/status_transitions")
+            .cookies(admin)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "transitions": [{ "from_status": "green", "to_status": "amber" }] })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        let response = bulk(coach.clone(), json!({ "status": "amber" })).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(statuses().await, vec!["amber", "green", "red"]);
+
+        let response = bulk(coach, json!({ "from_status": "green", "status": "amber" })).await;
+        let result: BulkStatusResponse = response.into_json().await.unwrap();
+        assert_eq!(result.updated, 1);
+        assert_eq!(statuses().await, vec!["amber", "amber", "red"]);
+    }
+
+    #[rocket::async_test]
+    async fn test_rank_eligibility_follows_requirements_and_held_rank() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", None)
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "amber", "", "")
+            .assign_technique(Some("Kimura"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let tag = create_tag(&test_db.pool, "Blue belt").await.unwrap();
+        for name in ["Armbar", "Triangle", "Kimura"] {
+            let technique_id = test_db.technique_id(name).unwrap();
+            add_tag_to_technique(&test_db.pool, technique_id, tag).await.unwrap();
+        }
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        let ranks = json!({ "ranks": [
+            { "name": "Blue", "requirements": [{ "tag_id": tag, "min_count": 2 }] },
+            { "name": "Purple", "requirements": [
+                { "tag_id": tag, "min_count": 2, "status": "amber" },
+            ] },
+        ] });
+        let response = client
+            .put("/api/ranks")
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(ranks.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .put("/api/ranks")
+            .cookies(admin)
+            .header(ContentType::JSON)
+            .body(ranks.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let eligibility = || async {
+            let response = client.get("/api/ranks/eligibility").cookies(coach.clone()).dispatch();
+            let rows: Vec<RankEligibility> = response.await.into_json().await.unwrap();
+            rows.into_iter().find(|row| row.student_id == student_id).unwrap()
+        };
+
+        let row = eligibility().await;
+        assert_eq!(row.current_rank, None);
+        assert_eq!(row.next_rank.as_deref(), Some("Blue"));
+        assert_eq!(row.requirements[0].count, 1);
+        assert!(!row.eligible);
+
+        let set_rank = |rank: serde_json::Value| {
+            client
+                .put(format!("/api/student/{}/rank", student_id))
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "rank": rank }).to_string())
+                .dispatch()
+        };
+        let response = set_rank(json!("Black")).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = set_rank(json!("Blue")).await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let row = eligibility().await;
+        assert_eq!(row.current_rank.as_deref(), Some("Blue"));
+        assert_eq!(row.next_rank.as_deref(), Some("Purple"));
+        assert_eq!(row.requirements[0].count, 2);
+        assert!(row.eligible);
+    }
+
+    #[rocket::async_test]
+    async fn test_journal_entries_are_private_unless_shared() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let armbar_id = test_db.technique_id("Armbar").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        let create = |body: serde_json::Value| {
+            client
+                .post("/api/journal")
+                .cookies(student.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let response = create(json!({ "body": "" })).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = create(json!({
+            "technique_id": armbar_id,
+            "entry_date": "2026-03-01",
+            "body": "Elbow keeps slipping out",
+        }))
+        .await;
+        assert_eq!(response.status(), Status::Ok);
+        let entry: JournalEntry = response.into_json().await.unwrap();
+        assert_eq!(entry.technique_name.as_deref(), Some("Armbar"));
+        let response = create(json!({
+            "entry_date": "2026-03-02",
+            "body": "Tired today",
+            "shared": true,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::Ok);
+        let shared_entry: JournalEntry = response.into_json().await.unwrap();
+
+        let journal = |cookies: Vec<Cookie<'static>>, query: String| {
+            client.get(format!("/api/journal{}", query)).cookies(cookies).dispatch()
+        };
+        let entries: Vec<JournalEntry> =
+            journal(student.clone(), String::new()).await.into_json().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].body, "Tired today");
+        let query = format!("?technique_id={}", armbar_id);
+        let entries: Vec<JournalEntry> =
+            journal(student.clone(), query).await.into_json().await.unwrap();
+        assert_eq!(entries.len(), 1);
+
+        for cookies in [coach.clone(), admin] {
+            let entries: Vec<JournalEntry> =
+                journal(cookies.clone(), String::new()).await.into_json().await.unwrap();
+            assert!(entries.is_empty());
+            let response = client
+                .put(format!("/api/journal/{}", entry.id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "body": "Overwritten" }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::NotFound);
+            let response =
+                client.delete(format!("/api/journal/{}", entry.id)).cookies(cookies).dispatch();
+            assert_eq!(response.await.status(), Status::NotFound);
+        }
+
+        // Coaches read only what the student chose to share.
+        let student_journal = format!("/api/student/{}/journal", student_id);
+        let response = client.get(student_journal.clone()).cookies(coach).dispatch().await;
+        let shared: Vec<JournalEntry> = response.into_json().await.unwrap();
+        let bodies: Vec<&str> = shared.iter().map(|e| e.body.as_str()).collect();
+        assert_eq!(bodies, ["Tired today"]);
+        let response = client.get(student_journal).cookies(student.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        // Editing the text alone keeps the entry shared.
+        let edit = json!({ "entry_date": "2026-03-02", "body": "Tired, trained anyway" });
+        let response = client
+            .put(format!("/api/journal/{}", shared_entry.id))
+            .cookies(student.clone())
+            .header(ContentType::JSON)
+            .body(edit.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: JournalEntry = response.into_json().await.unwrap();
+        assert_eq!(updated.body, "Tired, trained anyway");
+        assert!(updated.shared);
+
+        let response =
+            client.delete(format!("/api/journal/{}", entry.id)).cookies(student.clone()).dispatch();
+        assert_eq!(response.await.status(), Status::Ok);
+        let entries: Vec<JournalEntry> =
+            journal(student, String::new()).await.into_json().await.unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[rocket::async_test]
+    async fn test_coach_report_counts_each_coachs_activity_in_the_period() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .admin("admin_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let st_id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        for notes in ["Keep the knees tight", "Keep the knees tight and hips up"] {
+            let response = client
+                .put(format!("/api/student_technique/{}", st_id))
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "coach_notes": notes }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+        }
+
+        let response = client.get("/api/admin/coach_report").cookies(coach).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let report = |query: &'static str| {
+            client
+                .get(format!("/api/admin/coach_report{}", query))
+                .cookies(admin.clone())
+                .dispatch()
+        };
+        let response = report("").await;
+        assert_eq!(response.status(), Status::Ok);
+        let report_body: CoachReportResponse = response.into_json().await.unwrap();
+        let names: Vec<&str> = report_body.coaches.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["admin_user", "coach_user"]);
+        let coach_row = &report_body.coaches[1];
+        assert_eq!(coach_row.techniques_created, 1);
+        assert_eq!(coach_row.assignments, 1);
+        assert_eq!(coach_row.notes_written, 1);
+        assert_eq!(report_body.coaches[0].techniques_created, 0);
+
+        let response = report("?from=2000-01-01&to=2000-01-31").await;
+        let report_body: CoachReportResponse = response.into_json().await.unwrap();
+        assert!(report_body.coaches.iter().all(|c| c.assignments == 0 && c.notes_written == 0));
+
+        let response = report("?from=2000-02-01&to=2000-01-01").await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn test_change_password_is_throttled_after_repeated_wrong_passwords() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let change = |cookies: Vec<Cookie<'static>>, current: &str| {
+            client
+                .post("/api/change-password")
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "current_password": current, "new_password": "newpassword456" })
+                        .to_string(),
+                )
+                .dispatch()
+        };
+
+        for _ in 0..5 {
+            let response = change(student.clone(), "wrong-password").await;
+            assert_eq!(response.status(), Status::Unauthorized);
+        }
+        // The right password no longer helps once the limit is reached.
+        let response = change(student.clone(), "password123").await;
+        assert_eq!(response.status(), Status::TooManyRequests);
+
+        let response = change(coach, "password123").await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let student_id = test_db.user_id("student_user").unwrap();
+        let attempts: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM password_change_attempts WHERE user_id = ?",
+        )
+        .bind(student_id.0)
+        .fetch_one(&test_db.pool)
+        .await
+        .unwrap();
+        assert_eq!(attempts, 5);
+
+        // An attempt counts from the moment it starts, so requests still
+        // checking their password can't let a burst past the limit.
+        let admin_id = test_db.user_id("admin_user").unwrap();
+        let since = (chrono::Utc::now() - chrono::Duration::hours(1)).naive_utc();
+        for _ in 0..5 {
+            let (_, attempt) =
+                begin_password_attempt(&test_db.pool, admin_id, since, 5, None).await.unwrap();
+            assert!(attempt.is_some());
+        }
+        let (failures, attempt) =
+            begin_password_attempt(&test_db.pool, admin_id, since, 5, None).await.unwrap();
+        assert_eq!((failures, attempt), (5, None));
+    }
+
+    #[rocket::async_test]
+    async fn test_student_analytics_progress_per_tag_and_curriculum() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .student("other_student", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "amber", "", "")
+            .build()
+            .await
+            .unwrap();
+        let pool = &test_db.pool;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let kimura = test_db.technique_id("Kimura").unwrap();
+        let submission = create_tag(pool, "Submission").await.unwrap();
+        for technique in [armbar, triangle, kimura] {
+            add_tag_to_technique(pool, technique, submission).await.unwrap();
+        }
+        // Tagged but never assigned: left out rather than shown at 0%.
+        let guard = create_tag(pool, "Guard").await.unwrap();
+        add_tag_to_technique(pool, kimura, guard).await.unwrap();
+        let coach = test_db.user_id("coach_user").unwrap();
+        let white = create_collection(pool, "White Belt", "", Some("White"), coach).await.unwrap();
+        add_techniques_to_collection(pool, white, vec![armbar, kimura])
+            .await
+            .unwrap();
+
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let url = format!("/api/student/{}/analytics", student_id);
+
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client.get(url.as_str()).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: StudentAnalyticsResponse =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(
+            body.tags,
+            vec![GroupProgress {
+                id: submission.0,
+                name: "Submission".to_string(),
+                techniques: 3,
+                assigned: 2,
+                green: 1,
+                percent: 50,
+            }]
+        );
+        assert_eq!(
+            body.curricula,
+            vec![GroupProgress {
+                id: white,
+                name: "White Belt".to_string(),
+                techniques: 2,
+                assigned: 1,
+                green: 1,
+                percent: 100,
+            }]
+        );
+
+        // Students see their own, not each other's.
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response = client.get(url.as_str()).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let cookies = login_test_user(&client, "other_student", "password123").await;
+        let response = client.get(url.as_str()).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[rocket::async_test]
+    async fn test_student_progress_counts_by_status_and_tag() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .student("other_student", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "amber", "", "")
+            .assign_technique(Some("Kimura"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Kimura"), Some("other_student"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let pool = &test_db.pool;
+        let submission = create_tag(pool, "Submission").await.unwrap();
+        for name in ["Armbar", "Kimura"] {
+            let technique = test_db.technique_id(name).unwrap();
+            add_tag_to_technique(pool, technique, submission).await.unwrap();
+        }
+
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response = client
+            .get(format!("/api/student/{}/progress", student_id))
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let progress: StudentProgress = response.into_json().await.unwrap();
+
+        let count = |status: &str, count| StatusCount { status: status.to_string(), count };
+        assert_eq!(progress.total, 3);
+        assert_eq!(progress.statuses, [count("amber", 1), count("green", 2)]);
+        assert_eq!(progress.tags.len(), 1);
+        assert_eq!(progress.tags[0].name, "Submission");
+        assert_eq!(progress.tags[0].total, 2);
+        assert_eq!(progress.tags[0].statuses, [count("green", 2)]);
+        assert!(progress.tags[0].last_updated_at.is_some());
+        assert!(progress.last_updated_at.is_some());
+        assert_eq!(progress.last_student_update_at, None);
+        assert_eq!(progress.classes_attended, 0);
+    }
+
+    #[rocket::async_test]
+    async fn test_attendance_is_marked_per_class_and_counted_in_progress() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .student("other_student", None)
+            .build()
+            .await
+            .unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let other_id = test_db.user_id("other_student").unwrap();
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let (client, _) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let mut class_ids = Vec::new();
+        for starts_at in ["2026-03-02T18:00:00Z", "2026-03-04T18:00:00Z"] {
+            let response = client
+                .post("/api/classes")
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "title": "Fundamentals", "starts_at": starts_at }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            let class: serde_json::Value = response.into_json().await.unwrap();
+            assert_eq!(class["duration_minutes"], 60);
+            assert_eq!(class["coach_id"], json!(coach_id));
+            class_ids.push(class["id"].as_i64().unwrap());
+        }
+
+        let attendance = |id: i64| format!("/api/classes/{}/attendance", id);
+        let response = client
+            .put(attendance(class_ids[0]))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [student_id, other_id] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        // Re-marking replaces the list rather than adding to it.
+        let response = client
+            .put(attendance(class_ids[0]))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [student_id] }).to_string())
+            .dispatch()
+            .await;
+        let class: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(class["student_ids"], json!([student_id]));
+        let response = client
+            .put(attendance(class_ids[1]))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [student_id] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // Coaches can't be marked present.
+        let response = client
+            .put(attendance(class_ids[1]))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_ids": [coach_id] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client
+            .get("/api/classes?from=2026-03-01&to=2026-03-03")
+            .cookies(coach.clone())
+            .dispatch()
+            .await;
+        let classes: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(classes.as_array().unwrap().len(), 1);
+        assert_eq!(classes[0]["id"], class_ids[0]);
+
+        let history = format!("/api/student/{}/attendance", student_id);
+        let response = client.get(history).cookies(coach.clone()).dispatch().await;
+        let history: serde_json::Value = response.into_json().await.unwrap();
+        let attended: Vec<i64> =
+            history.as_array().unwrap().iter().map(|r| r["class_id"].as_i64().unwrap()).collect();
+        assert_eq!(attended, [class_ids[1], class_ids[0]]);
+
+        let progress = format!("/api/student/{}/progress", student_id);
+        let response = client.get(progress).cookies(coach).dispatch().await;
+        let progress: StudentProgress = response.into_json().await.unwrap();
+        assert_eq!(progress.classes_attended, 2);
+        assert_eq!(progress.last_attended_at.as_deref(), Some("2026-03-04T18:00:00Z"));
+    }
+
+    #[rocket::async_test]
+    async fn test_grading_records_results_and_closes_with_outcome() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .build()
+            .await
+            .unwrap();
+        let pool = &test_db.pool;
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let curriculum = create_collection(pool, "Blue belt", "", None, coach_id).await.unwrap();
+        add_techniques_to_collection(pool, curriculum, vec![triangle, armbar]).await.unwrap();
+        let (client, _) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post("/api/gradings")
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "student_id": student_id, "collection_id": curriculum }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let grading: Grading = response.into_json().await.unwrap();
+        let techniques: Vec<TechniqueId> = grading.results.iter().map(|r| r.technique_id).collect();
+        assert_eq!(techniques, vec![triangle, armbar]);
+        assert!(grading.results.iter().all(|r| r.passed.is_none()));
+
+        let result = |technique: TechniqueId| {
+            format!("/api/gradings/{}/results/{}", grading.id, technique)
+        };
+        let response = client
+            .put(result(armbar))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "passed": true, "note": "Tight finish" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .put(result(triangle))
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "passed": false }).to_string())
+            .dispatch()
+            .await;
+        let graded: Grading = response.into_json().await.unwrap();
+        let passed: Vec<Option<bool>> = graded.results.iter().map(|r| r.passed).collect();
+        assert_eq!(passed, vec![Some(false), Some(true)]);
+        assert_eq!(graded.results[1].note, "Tight finish");
+
+        let close = format!("/api/gradings/{}/close", grading.id);
+        let response = client
+            .post(close.clone())
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "outcome": "deferred", "notes": "Regrade the triangle" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let closed: Grading = response.into_json().await.unwrap();
+        assert_eq!(closed.outcome, Some(GradingOutcome::Deferred));
+        assert!(closed.closed_at.is_some());
+
+        // A closed grading is a record: it can't be closed again or edited.
+        let response = client
+            .post(close)
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "outcome": "passed" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client
+            .put(result(triangle))
+            .cookies(coach)
+            .header(ContentType::JSON)
+            .body(json!({ "passed": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Conflict);
+
+        // The student can read their own gradings.
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let response = client
+            .get(format!("/api/gradings?student_id={}", student_id))
+            .cookies(student)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let gradings: Vec<GradingSummary> = response.into_json().await.unwrap();
+        assert_eq!(gradings.len(), 1);
+        assert_eq!((gradings[0].passed, gradings[0].failed, gradings[0].pending), (1, 1, 0));
+    }
+
+    #[rocket::async_test]
+    async fn test_schedule_is_listed_and_exported_as_ical() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", None)
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .build()
+            .await
+            .unwrap();
+        let admin_id = test_db.user_id("admin_user").unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post("/api/schedule")
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "title": "No-gi, advanced",
+                    "weekday": 3,
+                    "start_time": "18:30:00",
+                    "duration_minutes": 90,
+                    "timezone": "Europe/London",
+                    "starts_on": "2026-03-02",
+                    "ends_on": "2026-06-30",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let slot: ScheduleSlot = response.into_json().await.unwrap();
+        assert_eq!(slot.timezone, "Europe/London");
+
+        let response = client
+            .post("/api/schedule")
+            .cookies(coach)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "title": "Open mat", "weekday": 8, "start_time": "10:00:00" }).to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let response = client.get("/api/schedule").cookies(student).dispatch().await;
+        let schedule: Vec<ScheduleSlot> = response.into_json().await.unwrap();
+        assert_eq!(schedule, vec![slot]);
+
+        // The feed is for calendar apps, so it only exists once published.
+        let response = client.get("/api/schedule.ics").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        set_feature_flag(&test_db.pool, "public_sharing", true, admin_id).await.unwrap();
+        let response = client.get("/api/schedule.ics").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.content_type().is_some_and(|ct| ct.sub() == "calendar"));
+        let calendar = response.into_string().await.unwrap();
+        for line in [
+            "BEGIN:VCALENDAR",
+            "DTSTART;TZID=Europe/London:20260304T183000",
+            "DURATION:PT90M",
+            "RRULE:FREQ=WEEKLY;BYDAY=WE;UNTIL=20260630T225959Z",
+            "SUMMARY:No-gi\\, advanced",
+        ] {
+            assert!(calendar.contains(&format!("{}\r\n", line)), "{} missing: {}", line, calendar);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_student_techniques_export_as_csv() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .student("other_student", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "green", "Up, \"squeeze\"", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "amber", "=SUM(A1)", "Angle")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let url = format!("/api/student/{}/techniques/export.csv", student_id);
+
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client.get(url.as_str()).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+        assert_eq!(
+            response.headers().get_one("Content-Disposition"),
+            Some("attachment; filename=\"student_user-syllabus.csv\"")
+        );
+        let body = response.into_string().await.unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Technique,Status,Student notes,Coach notes,"));
+        assert!(lines[1].starts_with("Armbar,green,\"Up, \"\"squeeze\"\"\",,"));
+        assert!(lines[2].starts_with("Triangle,amber,'=SUM(A1),Angle,"), "formula defused");
+
+        let cookies = login_test_user(&client, "other_student", "password123").await;
+        let response = client.get(url.as_str()).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[rocket::async_test]
+    async fn test_curriculum_belt_level_order_and_assignment() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let [armbar, triangle, kimura] =
+            ["Armbar", "Triangle", "Kimura"].map(|name| test_db.technique_id(name).unwrap());
+        let student_id = test_db.user_id("student_user").unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post("/api/collections")
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Blue Belt", "belt_level": " Blue " }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let created: CollectionResponse = response.into_json().await.unwrap();
+        assert_eq!(created.belt_level.as_deref(), Some("Blue"));
+        let id = created.id;
+        add_techniques_to_collection(&test_db.pool, id, vec![armbar, triangle, kimura])
+            .await
+            .unwrap();
+
+        let reorder = |ids: Vec<TechniqueId>| {
+            client
+                .put(format!("/api/collections/{}/techniques/order", id))
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "technique_ids": ids }).to_string())
+                .dispatch()
+        };
+        let response = reorder(vec![kimura, armbar]).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity, "Triangle left out");
+        let response = reorder(vec![kimura, armbar, triangle]).await;
+        assert_eq!(response.status(), Status::Ok);
+        let reordered: CollectionResponse = response.into_json().await.unwrap();
+        let names: Vec<&str> = reordered.techniques.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Kimura", "Armbar", "Triangle"]);
+
+        let response = client
+            .put(format!("/api/collections/{}", id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Blue Belt", "belt_level": "" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let collection = get_collection(&test_db.pool, id).await.unwrap();
+        assert_eq!(collection.belt_level, None, "blank clears it");
+
+        let response = client
+            .post(format!("/api/student/{}/assign_collection/{}", student_id, id))
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let assigned = test_db.student_technique_ids("student_user").await.unwrap();
+        assert_eq!(assigned.len(), 3);
+    }
+
+    #[rocket::async_test]
+    async fn test_statuses_are_configurable_and_checked_on_writes() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", None)
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "green", "", "")
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let student_id = test_db.user_id("student_user").unwrap();
+
+        let names = |scale: &[StatusLevel]| -> Vec<String> {
+            scale.iter().map(|level| level.name.clone()).collect()
+        };
+        let response = client.get("/api/statuses").cookies(coach_cookies.clone()).dispatch().await;
+        let scale: Vec<StatusLevel> = response.into_json().await.unwrap();
+        assert_eq!(names(&scale), ["red", "amber", "green"]);
+
+        let purple = json!({ "name": "purple", "color": "#7c3aed" }).to_string();
+        let response = client
+            .post("/api/statuses")
+            .cookies(coach_cookies.clone())
+            .header(ContentType::JSON)
+            .body(purple.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .post("/api/statuses")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(purple.clone())
+            .dispatch()
+            .await;
+        let created: StatusLevel = response.into_json().await.unwrap();
+        assert_eq!(created.position, 3);
+        let response = client
+            .post("/api/statuses")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(purple)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client
+            .put("/api/statuses/purple")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "color": "purple" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        // Purple sits between amber and green at this gym.
+        let response = client
+            .put("/api/statuses/order")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "names": ["red", "amber", "green"] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = client
+            .put("/api/statuses/order")
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "names": ["red", "amber", "purple", "green"] }).to_string())
+            .dispatch()
+            .await;
+        let scale: Vec<StatusLevel> = response.into_json().await.unwrap();
+        assert_eq!(names(&scale), ["red", "amber", "purple", "green"]);
+
+        let armbar = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let set_status = |status: &'static str| {
+            client
+                .put(format!("/api/student_technique/{}", armbar))
+                .cookies(coach_cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "status": status }).to_string())
+                .dispatch()
+        };
+        let response = set_status("silver").await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["details"]["status"][0]["code"], "status.unknown");
+        let response = set_status("purple").await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .get(format!("/api/student/{}/progress", student_id))
+            .cookies(coach_cookies.clone())
+            .dispatch()
+            .await;
+        let progress: StudentProgress = response.into_json().await.unwrap();
+        let count = |status: &str, count| StatusCount { status: status.to_string(), count };
+        assert_eq!(progress.statuses, [count("purple", 1), count("green", 1)]);
+
+        for (name, expected) in [("purple", Status::Conflict), ("red", Status::Conflict)] {
+            let response = client
+                .delete(format!("/api/statuses/{}", name))
+                .cookies(admin_cookies.clone())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), expected, "deleting {}", name);
+        }
+        let response = client
+            .delete("/api/statuses/amber")
+            .cookies(admin_cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        let response = client.get("/api/statuses").cookies(coach_cookies).dispatch().await;
+        let scale: Vec<StatusLevel> = response.into_json().await.unwrap();
+        assert_eq!(names(&scale), ["red", "purple", "green"]);
+    }
+
+    #[rocket::async_test]
+    async fn test_badges_awarded_as_earned_and_listed_on_me() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", None)
+            .student("student_user", None)
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .unwrap();
+        let pool = &test_db.pool;
+        let coach = test_db.user_id("coach_user").unwrap();
+        let white = create_collection(pool, "White Belt", "", Some("White"), coach).await.unwrap();
+        let techniques = vec![
+            test_db.technique_id("Armbar").unwrap(),
+            test_db.technique_id("Triangle").unwrap(),
+        ];
+        add_techniques_to_collection(pool, white, techniques).await.unwrap();
+
+        let (client, test_db) = setup_test_client(test_db).await;
+        let pool = &test_db.pool;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let student_cookies = login_test_user(&client, "student_user", "password123").await;
+        async fn badges(
+            client: &rocket::local::asynchronous::Client,
+            cookies: &[Cookie<'static>],
+        ) -> Vec<(BadgeKind, Option<String>)> {
+            let response = client.get("/api/me").cookies(cookies.to_vec()).dispatch().await;
+            let me: MeResponse = response.into_json().await.unwrap();
+            assert_eq!(me.user.username, "student_user");
+            me.badges.into_iter().map(|b| (b.badge, b.subject_name)).collect()
+        }
+        assert!(badges(&client, &student_cookies).await.is_empty());
+
+        let curriculum = (BadgeKind::CurriculumComplete, Some("White Belt".to_string()));
+        let steps = [
+            ("Armbar", vec![(BadgeKind::FirstGreen, None)]),
+            ("Triangle", vec![(BadgeKind::FirstGreen, None), curriculum]),
+        ];
+        for (technique, expected) in steps {
+            let id = test_db.student_technique_id("student_user", technique).await.unwrap();
+            let response = client
+                .put(format!("/api/student_technique/{}", id))
+                .header(ContentType::JSON)
+                .cookies(coach_cookies.clone())
+                .body(json!({ "status": "green" }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(badges(&client, &student_cookies).await, expected);
+        }
+
+        // Recorded straight through the db layer, so only the sweep sees them.
+        let student = get_user(pool, test_db.user_id("student_user").unwrap()).await.unwrap();
+        let armbar = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        for days_ago in 0..TRAINING_DAYS_FOR_BADGE {
+            let at = chrono::Utc::now() - chrono::Duration::days(days_ago);
+            create_attempt(pool, &student, armbar, at, None).await.unwrap();
+        }
+        assert_eq!(award_badges(&mut pool.acquire().await.unwrap(), None).await.unwrap(), 1);
+        assert_eq!(award_badges(&mut pool.acquire().await.unwrap(), None).await.unwrap(), 0);
+        let earned = badges(&client, &student_cookies).await;
+        assert!(earned.contains(&(BadgeKind::TrainingDays, None)), "{:?}", earned);
+    }
+
+    #[rocket::async_test]
+    async fn test_admin_users_filter_sort_and_page() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .student("ann", Some("Ann"))
+            .student("bea", Some("bea"))
+            .student("cat", Some("Cat"))
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+        let names = |page: &Paginated<UserData>| -> Vec<String> {
+            page.items.iter().map(|u| u.display_name.clone()).collect()
+        };
+        let fetch = |query: &'static str| {
+            let request = client.get(format!("/api/admin/users?{}", query)).cookies(admin.clone());
+            async move {
+                let response = request.dispatch().await;
+                assert_eq!(response.status(), Status::Ok, "{}", query);
+                response.into_json::<Paginated<UserData>>().await.unwrap()
+            }
+        };
+
+        let page = fetch("role=student&sort=name&desc=true&per_page=2").await;
+        assert_eq!(page.total, 3);
+        assert_eq!(names(&page), ["Cat", "bea"], "sorted case-insensitively");
+        let next = "/api/admin/users?role=student&sort=name&desc=true&page=2&per_page=2";
+        assert_eq!((page.next.as_deref(), page.prev.as_deref()), (Some(next), None));
+        let page = fetch("role=student&sort=name&desc=true&per_page=2&page=2").await;
+        assert_eq!(names(&page), ["Ann"]);
+        let prev = "/api/admin/users?role=student&sort=name&desc=true&page=1&per_page=2";
+        assert_eq!((page.next.as_deref(), page.prev.as_deref()), (None, Some(prev)));
+
+        let page = fetch("search=AN").await;
+        assert_eq!(names(&page), ["Ann"]);
+        let page = fetch("search=nobody").await;
+        assert_eq!((page.total, page.items.len()), (0, 0));
+
+        let response = client.get("/api/admin/users?sort=age").cookies(admin).dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn test_student_lists_page_on_request() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("ann", Some("Ann"))
+            .student("bea", Some("Bea"))
+            .technique("Armbar", "", Some("coach_user"))
+            .technique("Kimura", "", Some("coach_user"))
+            .technique("Triangle", "", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("ann"), "red", "", "")
+            .assign_technique(Some("Kimura"), Some("ann"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, test_db) = setup_test_client(test_db).await;
+        let ann = test_db.user_id("ann").unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        // Without paging parameters the whole list is the only page.
+        let response = client.get("/api/students").cookies(cookies.clone()).dispatch().await;
+        let students: Paginated<UserData> = response.into_json().await.unwrap();
+        assert_eq!((students.items.len(), students.total, students.page), (2, 2, 1));
+        assert_eq!((students.next, students.prev), (None, None));
+
+        let response = client
+            .get("/api/students?include_archived=false&per_page=1")
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        let students: Paginated<UserData> = response.into_json().await.unwrap();
+        assert_eq!((students.items.len(), students.total), (1, 2));
+        assert_eq!(
+            students.next.as_deref(),
+            Some("/api/students?include_archived=false&page=2&per_page=1")
+        );
+        let response = client
+            .get(students.next.unwrap())
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        let second: Paginated<UserData> = response.into_json().await.unwrap();
+        assert_ne!(second.items[0].id, students.items[0].id);
+        assert_eq!(second.next, None);
+
+        let url = format!("/api/student/{}/techniques?page=2&per_page=1", ann);
+        let response = client.get(url).cookies(cookies.clone()).dispatch().await;
+        let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!((body.techniques.items.len(), body.techniques.total), (1, 2));
+        assert_eq!(body.techniques.next, None);
+        let prev = format!("/api/student/{}/techniques?page=1&per_page=1", ann);
+        assert_eq!(body.techniques.prev, Some(prev));
+
+        let url = format!("/api/student/{}/unassigned_techniques?per_page=5", ann);
+        let response = client.get(url).cookies(cookies).dispatch().await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["items"].as_array().unwrap().len(), 1);
+        assert_eq!(body["items"][0]["name"], "Triangle");
+    }
+
+    #[rocket::async_test]
+    async fn test_technique_search_matches_names_tags_and_aliases() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "Straight arm lock", Some("coach_user"))
+            .technique("Kimura", "Shoulder lock", Some("coach_user"))
+            .technique("Triangle", "Choke with the legs", Some("coach_user"))
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, test_db) = setup_test_client(test_db).await;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let kimura = test_db.technique_id("Kimura").unwrap();
+        let tag = create_tag(&test_db.pool, "Submission").await.unwrap();
+        add_tag_to_technique(&test_db.pool, kimura, tag).await.unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post(format!("/api/techniques/{}/aliases", armbar))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "alias": "Juji-gatame" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let search = |q: &str| {
+            let request = client.get(format!("/api/techniques/search?q={}", q));
+            let cookies = cookies.clone();
+            async move {
+                let response = request.cookies(cookies).dispatch().await;
+                assert_eq!(response.status(), Status::Ok);
+                let body: serde_json::Value = response.into_json().await.unwrap();
+                let names: Vec<String> = body
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|t| t["name"].as_str().unwrap().to_string())
+                    .collect();
+                names
+            }
+        };
+
+        assert_eq!(search("arm").await, ["Armbar"], "prefix of the name");
+        let mut locks = search("lock").await;
+        locks.sort();
+        assert_eq!(locks, ["Armbar", "Kimura"]);
+        assert_eq!(search("shoulder%20lock").await, ["Kimura"], "every word must match");
+        assert_eq!(search("submission").await, ["Kimura"], "tag name");
+        assert_eq!(search("juji").await, ["Armbar"], "alias");
+        assert_eq!(search("%22arm").await, ["Armbar"], "quotes are not FTS syntax");
+        assert!(search("%20").await.is_empty());
+
+        rename_tag(&test_db.pool, tag, "Finish").await.unwrap();
+        assert!(search("submission").await.is_empty());
+        assert_eq!(search("finish").await, ["Kimura"], "a renamed tag is reindexed");
+
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let response = client.get("/api/techniques/search?q=arm").cookies(student).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[rocket::async_test]
+    async fn test_deleted_techniques_leave_the_library_until_restored() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", None)
+            .technique("Armbar", "Straight arm lock", Some("coach_user"))
+            .technique("Kimura", "Shoulder lock", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test DB");
+        let (client, test_db) = setup_test_client(test_db).await;
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let other_id = test_db.user_id("other_student").unwrap();
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let names = |path: String, items: bool| {
+            let request = client.get(path).cookies(cookies.clone());
+            async move {
+                let body: serde_json::Value = request.dispatch().await.into_json().await.unwrap();
+                let list = if items { &body["items"] } else { &body };
+                list.as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|t| t["name"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let response = client
+            .delete(format!("/api/technique/{}", armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .delete(format!("/api/technique/{}", armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        assert_eq!(names("/api/techniques".to_string(), false).await, ["Kimura"]);
+        assert_eq!(
+            names(format!("/api/student/{}/unassigned_techniques", other_id), true).await,
+            ["Kimura"]
+        );
+        assert!(names("/api/techniques/search?q=arm".to_string(), false).await.is_empty());
+        // Assignments made before the delete stay.
+        let response = client
+            .get(format!("/api/student/{}/techniques", student_id))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!(body.techniques.items.len(), 1);
+
+        let response = client
+            .post(format!("/api/technique/{}/restore", armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .post(format!("/api/technique/{}/restore", armbar))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(names("/api/techniques".to_string(), false).await, ["Armbar", "Kimura"]);
+        assert_eq!(names("/api/techniques/search?q=arm".to_string(), false).await, ["Armbar"]);
+    }
+
+    #[rocket::async_test]
+    async fn test_tag_category_and_color_are_set_on_create_and_edited() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post("/api/tags")
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({"name": "Mount", "category": "Position", "color": "#1e90ff"}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/tags").cookies(cookies.clone()).dispatch().await;
+        let tags: TagsResponse =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let tag = tags.tags.into_iter().find(|t| t.name == "Mount").unwrap();
+        assert_eq!(tag.category.as_deref(), Some("Position"));
+        assert_eq!(tag.color.as_deref(), Some("#1e90ff"));
+
+        // Leaving out the category keeps it; null clears the colour.
+        let response = client
+            .put(format!("/api/tags/{}", tag.id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({"name": "Full Mount", "color": null}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/api/tags").cookies(cookies.clone()).dispatch().await;
+        let tags: TagsResponse =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let tag = tags.tags.into_iter().find(|t| t.id == tag.id).unwrap();
+        assert_eq!(tag.name, "Full Mount");
+        assert_eq!(tag.category.as_deref(), Some("Position"));
+        assert_eq!(tag.color, None);
+
+        let response = client
+            .put(format!("/api/tags/{}", tag.id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({"name": "Full Mount", "color": "blue"}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    #[rocket::async_test]
+    async fn test_tags_nest_into_a_tree_without_cycles() {
+        let test_db = create_standard_test_db().await;
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let create = |body: serde_json::Value| {
+            client
+                .post("/api/tags")
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+        };
+        assert_eq!(create(json!({"name": "Guard"})).dispatch().await.status(), Status::Ok);
+        assert_eq!(create(json!({"name": "GUARD"})).dispatch().await.status(), Status::Conflict);
+        let response = client.get("/api/tags").cookies(cookies.clone()).dispatch().await;
+        let tags: TagsResponse =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let guard = tags.tags.iter().find(|t| t.name == "Guard").unwrap().id;
+
+        let response = create(json!({"name": "Closed Guard", "parent_id": guard})).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = create(json!({"name": "Half Guard", "parent_id": 999999})).dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/api/tags/tree").cookies(cookies.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let tree: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let guard_node = tree["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|node| node["name"] == "Guard")
+            .unwrap();
+        assert_eq!(guard_node["children"][0]["name"], "Closed Guard");
+        let closed_guard = guard_node["children"][0]["id"].as_i64().unwrap();
+        assert!(!tree.to_string().contains("Half Guard"));
+
+        // Guard can't go under its own child.
+        let response = client
+            .put(format!("/api/tags/{}", guard))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({"name": "Guard", "parent_id": closed_guard}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    #[rocket::async_test]
+    async fn test_student_techniques_filter_by_tag_status_and_update_time() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Triangle", "Description of triangle", Some("coach_user"))
+            .technique("Kimura", "Description of kimura", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "green", "", "")
+            .assign_technique(Some("Kimura"), Some("student_user"), "green", "", "")
+            .build()
+            .await
+            .expect("Failed to build test database");
+        let guard = create_tag(&test_db.pool, "Guard").await.unwrap();
+        let closed_guard = create_tag(&test_db.pool, "Closed Guard").await.unwrap();
+        set_tag_parent(&test_db.pool, closed_guard, Some(guard)).await.unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        add_tag_to_technique(&test_db.pool, triangle, closed_guard).await.unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let names = |query: String| {
+            let client = &client;
+            let cookies = cookies.clone();
+            async move {
+                let response = client
+                    .get(format!("/api/student/{}/techniques?{}", student_id, query))
+                    .cookies(cookies)
+                    .dispatch()
+                    .await;
+                assert_eq!(response.status(), Status::Ok, "{}", query);
+                let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+                let mut names: Vec<String> =
+                    body.techniques.items.into_iter().map(|t| t.technique_name).collect();
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(names("status=green".into()).await, ["Kimura", "Triangle"]);
+        // The technique carries Closed Guard, which sits under Guard.
+        assert_eq!(names(format!("tag={}", guard)).await, ["Triangle"]);
+        assert_eq!(names(format!("tag={}&status=red", guard)).await, Vec::<String>::new());
+        assert_eq!(names("updated_since=2000-01-01T00:00:00Z".into()).await.len(), 3);
+        assert!(names("updated_since=2999-01-01T00:00:00Z".into()).await.is_empty());
+
+        let response = client
+            .get(format!("/api/student/{}/techniques?updated_since=yesterday", student_id))
+            .cookies(cookies.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn test_lists_answer_not_modified_until_they_change() {
+        let test_db = create_standard_test_db().await;
+        let student_id = test_db.user_id("student_user").unwrap();
+        let student_technique_id =
+            test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let get = |path: String, etag: Option<String>| {
+            let mut request = client.get(path).cookies(cookies.clone());
+            if let Some(etag) = etag {
+                request = request.header(Header::new("If-None-Match", etag));
+            }
+            request.dispatch()
+        };
+        let techniques = format!("/api/student/{}/techniques", student_id);
+
+        for path in ["/api/students".to_string(), techniques.clone()] {
+            let response = get(path.clone(), None).await;
+            assert_eq!(response.status(), Status::Ok);
+            let etag = response.headers().get_one("ETag").expect("tagged").to_string();
+            assert!(etag.starts_with("W/\""), "{}", etag);
+
+            let response = get(path.clone(), Some(etag.clone())).await;
+            assert_eq!(response.status(), Status::NotModified, "{}", path);
+            assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+            assert!(response.into_string().await.unwrap_or_default().is_empty());
+        }
+
+        let response = get(techniques.clone(), None).await;
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        let response = client
+            .put(format!("/api/student_technique/{}", student_technique_id))
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "status": "green" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = get(techniques, Some(etag.clone())).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_ne!(response.headers().get_one("ETag"), Some(etag.as_str()));
+        let body: StudentTechniquesResponse = response.into_json().await.unwrap();
+        assert_eq!(body.techniques.items[0].status, "green");
+    }
+
+    #[rocket::async_test]
+    async fn test_batch_update_saves_what_passes_and_reports_each_item() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Triangle", "Description of triangle", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("student_user"), "red", "", "")
+            .assign_technique(Some("Triangle"), Some("student_user"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test database");
+        let armbar = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let triangle = test_db.student_technique_id("student_user", "Triangle").await.unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        let batch = |body: serde_json::Value| {
+            client
+                .put("/api/student_techniques/batch")
+                .cookies(cookies.clone())
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        let response = batch(json!([
+            { "id": armbar, "status": "green", "coach_notes": "Graded" },
+            { "id": triangle, "status": "purple" },
+            { "id": armbar, "status": "amber" },
+            { "id": 9999, "status": "green" },
+        ]))
+        .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let results = body["results"].as_array().unwrap();
+        let statuses: Vec<u64> = results.iter().map(|r| r["status"].as_u64().unwrap()).collect();
+        assert_eq!(statuses, [200, 422, 422, 404]);
+        assert_eq!(results[0]["technique"]["status"], "green");
+        assert_eq!(results[0]["changed"], json!(["status", "coach_notes"]));
+        assert!(results[1]["error"]["details"]["status"].is_array());
+        assert!(results[1]["technique"].is_null());
+
+        let armbar_now = get_student_technique(&test_db.pool, armbar, UserId(0)).await.unwrap();
+        assert_eq!(armbar_now.status, "green");
+        assert_eq!(armbar_now.coach_notes, "Graded");
+        let triangle_now = get_student_technique(&test_db.pool, triangle, UserId(0)).await.unwrap();
+        assert_eq!(triangle_now.status, "red");
+
+        let response = batch(json!([])).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        // A student's own items save only their notes, as they would alone.
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response = client
+            .put("/api/student_techniques/batch")
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(json!([{ "id": triangle, "status": "green" }]).to_string())
+            .dispatch()
+            .await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["results"][0]["status"], 200);
+        let triangle_now = get_student_technique(&test_db.pool, triangle, UserId(0)).await.unwrap();
+        assert_eq!(triangle_now.status, "red");
+    }
+
+    #[rocket::async_test]
+    async fn test_batch_updates_reach_the_live_feed_only_when_saved() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let student = login_test_user(&client, "student_user", "password123").await;
+        let st_id = test_db.student_technique_id("student_user", "Armbar").await.unwrap();
+        let mut events = client.rocket().state::<ActivityFeed>().unwrap().subscribe();
+
+        let response = client
+            .put("/api/student_techniques/batch")
+            .cookies(student)
+            .header(ContentType::JSON)
+            .body(
+                json!([
+                    { "id": st_id, "student_notes": "Keep the knees tight" },
+                    { "id": 9999, "student_notes": "Nobody's" },
+                ])
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["results"][0]["status"], 200);
+        assert_eq!(body["results"][1]["status"], 404);
+
+        // Sent once the request's transaction committed, for the saved item only.
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, ActivityKind::TechniqueUpdated);
+        assert_eq!(event.student_technique_id, st_id);
+        assert!(events.try_recv().is_err());
+        let saved = get_student_technique(&test_db.pool, st_id, UserId(0)).await.unwrap();
+        assert_eq!(saved.student_notes, "Keep the knees tight");
+    }
 }
 
 #[rocket::async_test]
@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Header, Status};
+    use serde_json::{Value, json};
+
+    use crate::api::CreatedApiToken;
+    use crate::idempotency::{IDEMPOTENCY_KEY_HEADER, REPLAYED_HEADER};
+    use crate::test::test_utils::{create_standard_test_db, login_test_user, setup_test_client};
+
+    fn bearer(secret: &str) -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {}", secret))
+    }
+
+    #[rocket::async_test]
+    async fn test_api_tokens_act_as_their_owner_within_their_scopes() {
+        let (client, _db) = setup_test_client(create_standard_test_db().await).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let create = |name: &str, scopes: Value| {
+            client
+                .post("/api/me/api_tokens")
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "name": name, "scopes": scopes }).to_string())
+        };
+        let read: CreatedApiToken =
+            create("Reports", json!(["read"])).dispatch().await.into_json().await.unwrap();
+        assert!(read.secret.starts_with("stpat_"));
+        assert!(read.secret.starts_with(&read.token.token_prefix));
+        let write: CreatedApiToken =
+            create("Sync", json!(["read", "write"])).dispatch().await.into_json().await.unwrap();
+
+        let response = create("Nothing", json!([])).dispatch().await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        let response = client.get("/api/students").header(bearer(&read.secret)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let add_tag = |secret: &str, name: &str| {
+            client
+                .post("/api/tags")
+                .header(bearer(secret))
+                .header(ContentType::JSON)
+                .body(json!({ "name": name }).to_string())
+        };
+        let response = add_tag(&read.secret, "Read only").dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = add_tag(&write.secret, "Written").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        // Tokens can't make more tokens.
+        let response = client
+            .post("/api/me/api_tokens")
+            .header(bearer(&write.secret))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Another", "scopes": ["write"] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let tokens: Value = client
+            .get("/api/me/api_tokens")
+            .cookies(coach.clone())
+            .dispatch()
+            .await
+            .into_json()
+            .await
+            .unwrap();
+        let tokens = tokens.as_array().unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.iter().all(|token| token.get("secret").is_none()));
+
+        // Nobody else can revoke them.
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+        let response = client
+            .delete(format!("/api/me/api_tokens/{}", read.token.id))
+            .cookies(admin)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client
+            .delete(format!("/api/me/api_tokens/{}", read.token.id))
+            .cookies(coach)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/api/students").header(bearer(&read.secret)).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client.get("/api/students").header(bearer("stpat_nope")).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[rocket::async_test]
+    async fn test_api_token_secrets_are_never_kept_for_idempotent_replay() {
+        let (client, test_db) = setup_test_client(create_standard_test_db().await).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let mut secrets = Vec::new();
+        for _ in 0..2 {
+            let response = client
+                .post("/api/me/api_tokens")
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .header(Header::new(IDEMPOTENCY_KEY_HEADER, "token-1"))
+                .body(json!({ "name": "Script", "scopes": ["read"] }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.headers().get_one(REPLAYED_HEADER).is_none());
+            let created: CreatedApiToken = response.into_json().await.unwrap();
+            secrets.push(created.secret);
+        }
+        assert_ne!(secrets[0], secrets[1]);
+
+        let leaked: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM idempotency_keys WHERE body LIKE '%stpat_%'")
+                .fetch_one(&test_db.pool)
+                .await
+                .unwrap();
+        assert_eq!(leaked, 0);
+    }
+
+    #[rocket::async_test]
+    async fn test_api_tokens_stop_working_when_the_account_is_reset_or_archived() {
+        let (client, test_db) = setup_test_client(create_standard_test_db().await).await;
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        let client = &client;
+        let issue = |username: &'static str| async move {
+            let cookies = login_test_user(client, username, "password123").await;
+            let created: CreatedApiToken = client
+                .post("/api/me/api_tokens")
+                .cookies(cookies)
+                .header(ContentType::JSON)
+                .body(json!({ "name": "Script", "scopes": ["read"] }).to_string())
+                .dispatch()
+                .await
+                .into_json()
+                .await
+                .unwrap();
+            created.secret
+        };
+        let me = |secret: String| client.get("/api/me").header(bearer(&secret)).dispatch();
+
+        let coach_token = issue("coach_user").await;
+        assert_eq!(me(coach_token.clone()).await.status(), Status::Ok);
+        let response = client
+            .post(format!("/api/admin/users/{}/reset_claim", coach_id))
+            .cookies(admin.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(me(coach_token).await.status(), Status::Unauthorized);
+        let tokens: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_tokens WHERE user_id = ?")
+            .bind(coach_id.0)
+            .fetch_one(&test_db.pool)
+            .await
+            .unwrap();
+        assert_eq!(tokens, 0);
+
+        let student_token = issue("student_user").await;
+        let response = client
+            .put(format!("/api/admin/users/{}", student_id))
+            .cookies(admin)
+            .header(ContentType::JSON)
+            .body(json!({ "archived": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(me(student_token).await.status(), Status::Unauthorized);
+    }
+}
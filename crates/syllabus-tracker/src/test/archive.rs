@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::{
+        ARCHIVE_VERSION, Archive, ImportCounts, add_tag_to_technique, create_tag, export_archive,
+        import_archive,
+    };
+    use crate::test::test_utils::{TestDbBuilder, create_standard_test_db};
+
+    async fn standard_archive() -> Archive {
+        let source = create_standard_test_db().await;
+        let armbar = source.technique_id("Armbar").unwrap();
+        let tag = create_tag(&source.pool, "Submission").await.unwrap();
+        add_tag_to_technique(&source.pool, armbar, tag).await.unwrap();
+        let st_id = source
+            .student_technique_id("student_user", "Armbar")
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO attempts (student_technique_id, recorded_by_id, attempted_at, student_note)
+             VALUES (?, ?, '2025-03-01 10:00:00', 'Felt smooth')",
+        )
+        .bind(st_id)
+        .bind(source.user_id("student_user").unwrap())
+        .execute(&source.pool)
+        .await
+        .unwrap();
+
+        let archive = export_archive(&source.pool).await.unwrap();
+        // Everything goes through JSON, as it would between instances.
+        let json = serde_json::to_string(&archive).unwrap();
+        assert!(!json.contains("password"), "archive must not carry passwords");
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn test_import_merges_by_natural_key_and_is_idempotent() {
+        let archive = standard_archive().await;
+        assert_eq!(archive.version, ARCHIVE_VERSION);
+
+        // The destination already knows the coach and one of the techniques.
+        let dest = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .technique("Armbar", "Existing armbar", Some("coach_user"))
+            .build()
+            .await
+            .unwrap();
+
+        let summary = import_archive(&dest.pool, &archive, false).await.unwrap();
+        let counts = |created, existing| ImportCounts { created, existing };
+        assert_eq!(summary.users, counts(2, 1));
+        assert_eq!(summary.tags, counts(1, 0));
+        assert_eq!(summary.techniques, counts(1, 1));
+        assert_eq!(summary.student_techniques, counts(1, 0));
+        assert_eq!(summary.attempts, counts(1, 0));
+
+        // The assignment points at the destination's Armbar and new student,
+        // keeps its notes, and the student has no usable password.
+        let row: (String, String, String, String) = sqlx::query_as(
+            "SELECT u.password, t.description, st.student_notes, a.student_note
+             FROM student_techniques st
+             JOIN users u ON u.id = st.student_id
+             JOIN techniques t ON t.id = st.technique_id
+             JOIN attempts a ON a.student_technique_id = st.id
+             WHERE u.username = 'student_user'",
+        )
+        .fetch_one(&dest.pool)
+        .await
+        .unwrap();
+        assert_eq!(row.0, "");
+        assert_eq!(row.1, "Existing armbar");
+        assert_eq!(row.2, "Student notes");
+        assert_eq!(row.3, "Felt smooth");
+
+        let tagged: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM technique_tags tt JOIN tags g ON g.id = tt.tag_id
+             WHERE tt.technique_id = ? AND g.name = 'Submission'",
+        )
+        .bind(dest.technique_id("Armbar").unwrap())
+        .fetch_one(&dest.pool)
+        .await
+        .unwrap();
+        assert_eq!(tagged.0, 1);
+
+        let again = import_archive(&dest.pool, &archive, false).await.unwrap();
+        assert_eq!(again.users, counts(0, 3));
+        assert_eq!(again.techniques, counts(0, 2));
+        assert_eq!(again.student_techniques, counts(0, 1));
+        assert_eq!(again.attempts, counts(0, 1));
+    }
+
+    #[rocket::async_test]
+    async fn test_import_dry_run_and_version_check() {
+        let mut archive = standard_archive().await;
+        let dest = TestDbBuilder::new().build().await.unwrap();
+
+        let summary = import_archive(&dest.pool, &archive, true).await.unwrap();
+        assert_eq!(summary.users.created, 3);
+        let users: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+            .fetch_one(&dest.pool)
+            .await
+            .unwrap();
+        assert_eq!(users.0, 0, "dry run must not write");
+
+        archive.version = ARCHIVE_VERSION + 1;
+        assert!(import_archive(&dest.pool, &archive, false).await.is_err());
+    }
+}
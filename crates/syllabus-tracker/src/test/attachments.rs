@@ -0,0 +1,254 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use rocket::http::{ContentType, Cookie, Status};
+    use rocket::local::asynchronous::{Client, LocalResponse};
+
+    use crate::attachments::{
+        AttachmentCleanup, DynStorage, LocalStorage, UrlSigner, store_attachment,
+    };
+    use crate::scheduler::{RunOutcome, run_job};
+    use crate::test::test_utils::{TestDbBuilder, login_test_user, setup_test_client};
+
+    const BOUNDARY: &str = "----uploadboundary";
+
+    fn upload_body(filename: &str, content_type: &str, contents: &[u8]) -> Vec<u8> {
+        let mut body = format!(
+            "--{BOUNDARY}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(contents);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+        body
+    }
+
+    async fn upload_avatar<'c>(
+        client: &'c Client,
+        cookies: Vec<Cookie<'static>>,
+        filename: &str,
+        content_type: &str,
+        contents: &[u8],
+    ) -> LocalResponse<'c> {
+        let multipart =
+            ContentType::parse_flexible(&format!("multipart/form-data; boundary={}", BOUNDARY))
+                .unwrap();
+        client
+            .post("/api/profile/avatar")
+            .cookies(cookies)
+            .header(multipart)
+            .body(upload_body(filename, content_type, contents))
+            .dispatch()
+            .await
+    }
+
+    fn scratch_file(contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("attachment-src-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[rocket::async_test]
+    async fn test_local_attachment_downloads_through_signed_url() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", Some("Other Student"))
+            .build()
+            .await
+            .unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+
+        let storage = client.rocket().state::<DynStorage>().unwrap().clone();
+        let source = scratch_file("grip breaks");
+        let attachment = store_attachment(
+            &test_db.pool,
+            &storage,
+            student_id,
+            "Grip notes.txt",
+            "text/plain",
+            &source,
+        )
+        .await
+        .unwrap();
+        std::fs::remove_file(&source).ok();
+        assert_eq!(attachment.bytes, 11);
+
+        let url_path = format!("/api/attachments/{}/download-url", attachment.id);
+
+        // Someone else's upload is hidden from students, not just refused.
+        let cookies = login_test_user(&client, "other_student", "password123").await;
+        let response = client.get(&url_path).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client.get(&url_path).cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let signed: serde_json::Value = response.into_json().await.unwrap();
+        let url = signed["url"].as_str().unwrap().to_string();
+        assert!(url.starts_with("/api/attachments/file/"), "{}", url);
+
+        // The URL itself needs no session.
+        let response = client.get(url.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.headers().get_one("Content-Disposition"),
+            Some("attachment; filename=\"Grip notes.txt\"")
+        );
+        assert!(response.content_type().unwrap().is_plain());
+        assert_eq!(response.into_string().await.unwrap(), "grip breaks");
+
+        let tampered = format!("{}0", url);
+        assert_eq!(client.get(tampered).dispatch().await.status(), Status::NotFound);
+
+        let local = client.rocket().state::<Arc<LocalStorage>>().unwrap();
+        let expired = Utc::now().timestamp() - 1;
+        let signature = local.signer().sign(&attachment.storage_key, expired);
+        let expired_url = format!(
+            "/api/attachments/file/{}?expires={}&signature={}",
+            attachment.storage_key, expired, signature
+        );
+        assert_eq!(client.get(expired_url).dispatch().await.status(), Status::NotFound);
+    }
+
+    #[rocket::async_test]
+    async fn test_attachment_cleanup_removes_old_orphans_only() {
+        let test_db = TestDbBuilder::new()
+            .student("student_user", Some("Student User"))
+            .build()
+            .await
+            .unwrap();
+        let pool = &test_db.pool;
+        let root = std::env::temp_dir().join(format!("attachments-{}", uuid::Uuid::new_v4()));
+        let storage: DynStorage = Arc::new(LocalStorage::new(root.clone(), UrlSigner::new("k")));
+
+        let source = scratch_file("kept");
+        let attachment = store_attachment(
+            pool,
+            &storage,
+            test_db.user_id("student_user").unwrap(),
+            "kept.txt",
+            "text/plain",
+            &source,
+        )
+        .await
+        .unwrap();
+        // An object whose row never got written.
+        storage.put_file("orphan", "text/plain", &source).await.unwrap();
+        std::fs::remove_file(&source).ok();
+
+        // Too new to be sure the row isn't on its way.
+        let job = AttachmentCleanup::new(storage.clone());
+        let outcome = run_job(pool, &job).await.unwrap();
+        let kept_both = "Removed 0 orphaned attachments".to_string();
+        assert_eq!(outcome, RunOutcome::Finished { ok: true, message: kept_both });
+
+        let job = AttachmentCleanup::new(storage.clone()).with_grace(Duration::ZERO);
+        let outcome = run_job(pool, &job).await.unwrap();
+        let removed = "Removed 1 orphaned attachments".to_string();
+        assert_eq!(outcome, RunOutcome::Finished { ok: true, message: removed });
+
+        let keys: Vec<String> =
+            storage.list().await.unwrap().into_iter().map(|object| object.key).collect();
+        assert_eq!(keys, vec![attachment.storage_key]);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[rocket::async_test]
+    async fn test_upload_stores_file_and_returns_download_url() {
+        let test_db = TestDbBuilder::new()
+            .student("student_user", Some("Student User"))
+            .build()
+            .await
+            .unwrap();
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let multipart =
+            ContentType::parse_flexible(&format!("multipart/form-data; boundary={}", BOUNDARY))
+                .unwrap();
+
+        let response = client
+            .post("/api/uploads")
+            .cookies(cookies.clone())
+            .header(multipart.clone())
+            .body(upload_body("grip.png", "image/png", b"not really a png"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let uploaded: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(uploaded["attachment"]["filename"], "grip.png");
+        assert_eq!(uploaded["attachment"]["content_type"], "image/png");
+        assert_eq!(uploaded["attachment"]["bytes"], 16);
+
+        let url = uploaded["url"].as_str().unwrap().to_string();
+        let response = client.get(url).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.unwrap(), "not really a png");
+
+        let response = client
+            .post("/api/uploads")
+            .cookies(cookies)
+            .header(multipart)
+            .body(upload_body("notes.txt", "text/plain", b"grip breaks"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+    }
+
+    #[rocket::async_test]
+    async fn test_avatar_upload_replaces_previous_picture() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .build()
+            .await
+            .unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "student_user", "password123").await;
+        let response =
+            upload_avatar(&client, cookies.clone(), "cv.pdf", "application/pdf", b"%PDF").await;
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+
+        let response =
+            upload_avatar(&client, cookies.clone(), "a.png", "image/png", b"first").await;
+        assert_eq!(response.status(), Status::Ok);
+        let first: serde_json::Value = response.into_json().await.unwrap();
+        let first_url = first["avatar_url"].as_str().unwrap().to_string();
+
+        let response =
+            upload_avatar(&client, cookies.clone(), "b.png", "image/png", b"second").await;
+        let second: serde_json::Value = response.into_json().await.unwrap();
+        let second_url = second["avatar_url"].as_str().unwrap().to_string();
+        assert_ne!(first_url, second_url);
+
+        let response = client.get("/api/me").cookies(cookies.clone()).dispatch().await;
+        let me: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(me["avatar_url"], second_url.as_str());
+
+        // Anyone signed in can load it, through a redirect to the file.
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let response = client.get(second_url).cookies(coach).dispatch().await;
+        assert_eq!(response.status(), Status::SeeOther);
+        let location = response.headers().get_one("Location").unwrap().to_string();
+        let response = client.get(location).dispatch().await;
+        assert_eq!(response.into_string().await.unwrap(), "second");
+
+        let attachments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments")
+            .fetch_one(&test_db.pool)
+            .await
+            .unwrap();
+        assert_eq!(attachments, 1, "the first picture is deleted");
+
+        let response =
+            client.delete("/api/profile/avatar").cookies(cookies.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/api/me").cookies(cookies).dispatch().await;
+        let me: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(me["avatar_url"], serde_json::Value::Null);
+    }
+}
@@ -9,13 +9,14 @@ mod tests {
         get_user, list_attempts, list_recent_attempts_for_student, update_attempt_note,
         update_attempt_timestamp, AttemptSuggestion,
     };
+    use crate::ids::{StudentTechniqueId, UserId};
     use crate::test::test_utils::TestDbBuilder;
 
-    async fn fetch_user(pool: &sqlx::SqlitePool, user_id: i64) -> User {
+    async fn fetch_user(pool: &sqlx::SqlitePool, user_id: UserId) -> User {
         get_user(pool, user_id).await.expect("user")
     }
 
-    async fn standard_setup_red() -> (crate::test::test_utils::TestDb, i64) {
+    async fn standard_setup_red() -> (crate::test::test_utils::TestDb, StudentTechniqueId) {
         let test_db = TestDbBuilder::new()
             .admin("admin_user", Some("Admin User"))
             .coach("coach_user", Some("Coach User"))
@@ -42,7 +43,7 @@ mod tests {
             .expect("Create attempt");
 
         assert_eq!(res.suggestion, AttemptSuggestion::Amber);
-        assert_eq!(res.attempt.recorded_by_id, student.id);
+        assert_eq!(res.attempt.recorded_by_id, student.id.0);
         assert!(res.attempt.coach_note.is_none());
         assert!(res.attempt.student_note.is_none());
     }
@@ -107,7 +108,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(res.attempt.coach_note.as_deref(), Some("clean entry"));
-        assert_eq!(res.attempt.coach_note_by_id, Some(coach.id));
+        assert_eq!(res.attempt.coach_note_by_id, Some(coach.id.0));
         assert!(res.attempt.student_note.is_none());
     }
 
@@ -406,14 +407,14 @@ mod tests {
             .unwrap();
         let after_student = db.get_student_technique(st_id).await.unwrap();
         assert!(after_student.last_student_update_at.is_some());
-        assert_eq!(after_student.last_student_update_by_id, Some(student.id));
+        assert_eq!(after_student.last_student_update_by_id, Some(student.id.0));
 
         create_attempt(&db.pool, &coach, st_id, Utc::now(), None)
             .await
             .unwrap();
         let after_coach = db.get_student_technique(st_id).await.unwrap();
         assert!(after_coach.last_coach_update_at.is_some());
-        assert_eq!(after_coach.last_coach_update_by_id, Some(coach.id));
+        assert_eq!(after_coach.last_coach_update_by_id, Some(coach.id.0));
         // updated_at should advance (or at least be set) on the parent row so
         // the dashboard query (which orders by updated_at) sees activity.
         assert!(after_coach.updated_at >= after_student.updated_at);
@@ -473,7 +474,8 @@ mod tests {
         create_attempt(&db.pool, &student, st_id, Utc::now(), None)
             .await
             .unwrap();
-        let techs = crate::db::get_student_techniques(&db.pool, student_id, student_id)
+        let filter = crate::db::StudentTechniqueFilter::default();
+        let techs = crate::db::get_student_techniques(&db.pool, student_id, student_id, &filter)
             .await
             .unwrap();
         let target = techs.into_iter().find(|t| t.id == st_id).unwrap();
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use migration_engine::migrations::read_schema_file_to_string;
+    use sqlx::SqlitePool;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use crate::bootstrap::{
+        AdminBootstrap, bootstrap_admin, migrate_empty_database, retry_startup,
+    };
+    use crate::db::{find_user_by_username, find_valid_invite_token};
+
+    #[rocket::async_test]
+    async fn test_first_run_creates_schema_and_an_admin_invite() {
+        crate::env::load_test_environment().expect("load test env");
+        let schema_path = dotenvy::var("SCHEMA_PATH").expect("SCHEMA_PATH not set");
+        let schema = read_schema_file_to_string(std::path::Path::new(&schema_path)).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("bootstrap-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}", dir.join("app.db").display());
+        let opts = SqliteConnectOptions::from_str(&url).unwrap().create_if_missing(true);
+        let pool = SqlitePool::connect_with(opts).await.unwrap();
+
+        assert!(migrate_empty_database(&pool, &schema).await.unwrap());
+        assert!(!migrate_empty_database(&pool, &schema).await.unwrap(), "only when empty");
+
+        let outcome = bootstrap_admin(&pool, None).await.unwrap();
+        let Some(AdminBootstrap::Invited { claim_path }) = outcome else {
+            panic!("expected an invite, got {:?}", outcome);
+        };
+        let token = claim_path.strip_prefix("/invite/").unwrap();
+        assert!(find_valid_invite_token(&pool, token).await.unwrap().is_some());
+        assert_eq!(bootstrap_admin(&pool, None).await.unwrap(), None, "shown once");
+
+        pool.close().await;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[rocket::async_test]
+    async fn test_first_run_admin_from_credentials() {
+        let test_db = crate::test::test_utils::TestDbBuilder::new().build().await.unwrap();
+
+        let outcome = bootstrap_admin(&test_db.pool, Some(("owner", "s3cret-pass")))
+            .await
+            .unwrap();
+        assert_eq!(outcome, Some(AdminBootstrap::Created { username: "owner".to_string() }));
+        let admin = find_user_by_username(&test_db.pool, "owner").await.unwrap().unwrap();
+        assert_eq!(admin.role.as_str(), "admin");
+
+        let again = bootstrap_admin(&test_db.pool, Some(("other", "s3cret-pass"))).await;
+        assert_eq!(again.unwrap(), None);
+    }
+
+    #[rocket::async_test]
+    async fn test_startup_retries_until_the_database_answers() {
+        let mut attempts = 0;
+        let result = retry_startup("connect", Duration::from_secs(5), || {
+            attempts += 1;
+            let outcome = if attempts < 3 { Err("not mounted yet") } else { Ok(attempts) };
+            async move { outcome }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_startup("connect", Duration::ZERO, || {
+            attempts += 1;
+            async { Err("down") }
+        })
+        .await;
+        assert_eq!(result, Err("down"));
+        assert_eq!(attempts, 1, "no retries without a max wait");
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use rocket::figment::Figment;
+    use rocket::figment::value::Value;
+    use rocket::http::{ContentType, Status};
+
+    use crate::config::{AppConfig, ConfigError, LiveConfig, ReloadReport};
+    use crate::telemetry::OtlpTransport;
+    use crate::validation::ValidationConfig;
+    use crate::test::test_utils::{create_standard_test_db, login_test_user, setup_test_client};
+
+    #[test]
+    fn test_missing_config_keys_are_all_named() {
+        let err = AppConfig::from_figment(&Figment::new()).unwrap_err();
+        match err {
+            ConfigError::Missing(keys) => assert_eq!(keys, vec!["DATABASE_URL", "SCHEMA_PATH"]),
+            other => panic!("expected missing keys, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_parses_env_style_values() {
+        let figment = Figment::new()
+            .merge(("database_url", "sqlite://test.db"))
+            .merge(("schema_path", "config/schema.sql"))
+            // Figment's env provider parses VIDEOS_ENABLED=1 as a number.
+            .merge(("videos_enabled", 1));
+        let config = AppConfig::from_figment(&figment).unwrap();
+        assert!(config.videos_enabled);
+        assert_eq!(config.bcrypt_cost, None);
+
+        let err = AppConfig::from_figment(&figment.merge(("bcrypt_cost", 40))).unwrap_err();
+        assert!(err.to_string().contains("BCRYPT_COST"), "{}", err);
+    }
+
+    #[test]
+    fn test_job_schedule_overrides_are_validated() {
+        let figment = Figment::new()
+            .merge(("database_url", "sqlite://test.db"))
+            .merge(("schema_path", "config/schema.sql"))
+            .merge(("jobs.session_cleanup", "every 15m"));
+        let config = AppConfig::from_figment(&figment).unwrap();
+        assert_eq!(config.jobs["session_cleanup"], "every 15m");
+
+        let err = AppConfig::from_figment(&figment.merge(("jobs.session_cleanup", "hourly")))
+            .unwrap_err();
+        assert!(err.to_string().contains("JOB_SESSION_CLEANUP"), "{}", err);
+    }
+
+    #[test]
+    fn test_settings_used_outside_config_are_checked_at_load() {
+        let figment = Figment::new()
+            .merge(("database_url", "sqlite://test.db"))
+            .merge(("schema_path", "config/schema.sql"));
+        let config = AppConfig::from_figment(&figment).unwrap();
+        assert_eq!(config.validation, ValidationConfig::default());
+        assert_eq!(config.json_body_limit().as_u64(), 256 * 1024);
+        assert_eq!(config.otel_exporter_otlp_protocol, OtlpTransport::Grpc);
+        assert_eq!(config.bootstrap_admin_credentials(), None);
+
+        let config = AppConfig::from_figment(
+            &figment
+                .clone()
+                .merge(("validation.password_min", 12))
+                .merge(("otel_exporter_otlp_protocol", "http"))
+                .merge(("bootstrap_admin_username", " owner "))
+                .merge(("bootstrap_admin_password", "s3cret-pass")),
+        )
+        .unwrap();
+        assert_eq!(config.validation.password_min, 12);
+        assert_eq!(config.otel_exporter_otlp_protocol, OtlpTransport::Http);
+        assert_eq!(config.bootstrap_admin_credentials(), Some(("owner", "s3cret-pass")));
+
+        let invalid = [
+            ("validation.password_min", "many", "password_min"),
+            ("validation.pasword_min", "12", "pasword_min"),
+            ("validation.username_min", "80", "VALIDATION_USERNAME_MIN"),
+            ("json_body_limit_bytes", "0", "JSON_BODY_LIMIT_BYTES"),
+            ("attachment_signing_key", "", "ATTACHMENT_SIGNING_KEY"),
+            ("bootstrap_admin_username", "owner", "BOOTSTRAP_ADMIN_PASSWORD"),
+            ("otel_exporter_otlp_protocol", "thrift", "thrift"),
+        ];
+        for (key, value, expected) in invalid {
+            // Parsed the way the env provider parses values, so "0" is a number.
+            let value: Value = value.parse().unwrap();
+            let err = AppConfig::from_figment(&figment.clone().merge((key, value))).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}: {}", key, err);
+        }
+    }
+
+    /// What `changing_source` reports for SESSION_TTL_DAYS; only the reload
+    /// test below touches it.
+    static TTL_DAYS: AtomicI64 = AtomicI64::new(30);
+
+    fn changing_source() -> Result<Figment, ConfigError> {
+        Ok(Figment::new()
+            .merge(("database_url", "sqlite://other.db"))
+            .merge(("schema_path", "config/schema.sql"))
+            .merge(("session_ttl_days", TTL_DAYS.load(Ordering::SeqCst))))
+    }
+
+    #[test]
+    fn test_reload_applies_reloadable_settings_only() {
+        let figment = Figment::new()
+            .merge(("database_url", "sqlite://test.db"))
+            .merge(("schema_path", "config/schema.sql"));
+        let live = LiveConfig::new(AppConfig::from_figment(&figment).unwrap(), changing_source);
+        let before = live.get();
+        assert_eq!(before.session_ttl_days, 30);
+        assert_eq!(before.rust_log, "info");
+
+        TTL_DAYS.store(7, Ordering::SeqCst);
+        let report = live.reload().unwrap();
+        assert_eq!(
+            report,
+            ReloadReport {
+                applied: vec!["SESSION_TTL_DAYS".to_string()],
+                restart_required: vec!["DATABASE_URL".to_string()],
+            }
+        );
+        assert_eq!(live.get().session_ttl_days, 7);
+        assert_eq!(live.get().database_url, "sqlite://test.db");
+        // Snapshots taken before the reload are unaffected.
+        assert_eq!(before.session_ttl_days, 30);
+
+        // An invalid value fails the whole reload.
+        TTL_DAYS.store(0, Ordering::SeqCst);
+        let err = live.reload().unwrap_err();
+        assert!(err.to_string().contains("SESSION_TTL_DAYS"), "{}", err);
+        assert_eq!(live.get().session_ttl_days, 7);
+    }
+
+    #[rocket::async_test]
+    async fn test_reload_endpoint() {
+        let (client, _test_db) = setup_test_client(create_standard_test_db().await).await;
+
+        let cookies = login_test_user(&client, "admin_user", "password123").await;
+        let response = client
+            .post("/api/admin/config/reload")
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let report: ReloadReport =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(report, ReloadReport::default());
+
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client
+            .post("/api/admin/config/reload")
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+}
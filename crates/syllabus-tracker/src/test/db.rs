@@ -1,7 +1,12 @@
 #[cfg(test)]
 mod tests {
     use crate::auth::Role;
-    use crate::db::{create_user, find_user_by_username};
+    use crate::db::{
+        create_technique, create_user, find_user_by_username, get_student_technique,
+        normalize_legacy_update_timestamps, sanitize_stored_text,
+    };
+    use crate::error::AppError;
+    use crate::ids::{StudentTechniqueId, UserId};
 
     use migration_engine::migrations::{migrate_database_declaratively, read_schema_file_to_string};
     use rocket::tokio;
@@ -52,4 +57,103 @@ mod tests {
             _ => panic!("User wasn't defined somehow"),
         }
     }
+
+    #[tokio::test]
+    async fn test_null_column_is_internal_error() {
+        let pool = setup_test_db().await;
+
+        let student_id = create_user(&pool, "null_status", "password123", "student", None)
+            .await
+            .expect("Failed to create test user");
+        let technique_id = create_technique(&pool, "Armbar", "", student_id)
+            .await
+            .expect("Failed to create technique");
+
+        // Written directly: the app never stores a NULL status, but a bad
+        // manual edit could, and the read path should say so.
+        let id = sqlx::query(
+            "INSERT INTO student_techniques (technique_id, student_id, status) VALUES (?, ?, NULL)",
+        )
+        .bind(technique_id)
+        .bind(student_id)
+        .execute(&pool)
+        .await
+        .expect("Failed to insert row")
+        .last_insert_rowid();
+
+        let result = get_student_technique(&pool, StudentTechniqueId(id), UserId(0)).await;
+
+        match result {
+            Err(AppError::Internal(msg)) => assert!(msg.contains("student_techniques.status")),
+            Err(other) => panic!("expected internal error, got {:?}", other),
+            Ok(_) => panic!("expected NULL status to be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_rfc3339_update_timestamps_are_normalized() {
+        let pool = setup_test_db().await;
+
+        let student_id = create_user(&pool, "legacy_ts", "password123", "student", None)
+            .await
+            .expect("Failed to create test user");
+        let technique_id = create_technique(&pool, "Kimura", "", student_id)
+            .await
+            .expect("Failed to create technique");
+
+        let id = sqlx::query(
+            "INSERT INTO student_techniques
+                 (technique_id, student_id, status, last_student_update_at, last_coach_update_at)
+             VALUES (?, ?, 'red', '2026-05-31T10:00:00+02:00', '2026-05-30 09:00:00')",
+        )
+        .bind(technique_id)
+        .bind(student_id)
+        .execute(&pool)
+        .await
+        .expect("Failed to insert row")
+        .last_insert_rowid();
+
+        assert_eq!(normalize_legacy_update_timestamps(&pool).await.unwrap(), 1);
+        assert_eq!(normalize_legacy_update_timestamps(&pool).await.unwrap(), 0);
+
+        let (student_at, coach_at): (String, String) = sqlx::query_as(
+            "SELECT last_student_update_at, last_coach_update_at
+             FROM student_techniques WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(student_at, "2026-05-31 08:00:00");
+        assert_eq!(coach_at, "2026-05-30 09:00:00");
+    }
+
+    #[tokio::test]
+    async fn test_stored_html_is_stripped_by_cleanup() {
+        let pool = setup_test_db().await;
+
+        let coach_id = create_user(&pool, "html_coach", "password123", "coach", None)
+            .await
+            .expect("Failed to create test user");
+        let technique_id = create_technique(
+            &pool,
+            "<i>Omoplata</i>",
+            "Sweep <script>steal()</script>from guard",
+            coach_id,
+        )
+        .await
+        .expect("Failed to create technique");
+
+        assert_eq!(sanitize_stored_text(&pool).await.unwrap(), 2);
+        assert_eq!(sanitize_stored_text(&pool).await.unwrap(), 0);
+
+        let (name, description): (String, String) =
+            sqlx::query_as("SELECT name, description FROM techniques WHERE id = ?")
+                .bind(technique_id.0)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(name, "Omoplata");
+        assert_eq!(description, "Sweep from guard");
+    }
 }
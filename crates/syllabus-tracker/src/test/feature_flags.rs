@@ -1,9 +1,16 @@
 #[cfg(test)]
 mod tests {
-    use rocket::http::Status;
-    use serde_json::Value;
+    use rocket::http::{ContentType, Status};
+    use serde_json::{Value, json};
 
-    use crate::test::test_utils::{create_standard_test_db, setup_test_client_with};
+    use crate::db::{
+        add_tag_to_technique, add_techniques_to_collection, create_collection, create_tag,
+        set_feature_flag,
+    };
+    use crate::models::PublicSyllabus;
+    use crate::test::test_utils::{
+        create_standard_test_db, login_test_user, setup_test_client, setup_test_client_with,
+    };
 
     #[rocket::async_test]
     async fn capabilities_reports_videos_true_when_enabled() {
@@ -54,4 +61,123 @@ mod tests {
             "status route should be unmounted when videos disabled",
         );
     }
+
+    /// Runtime flags live in the database rather than the route table, so
+    /// both sides are checked on one client: the surface works while the flag
+    /// is on, and answers 404 once an admin switches it off.
+    #[rocket::async_test]
+    async fn self_registration_follows_its_runtime_flag() {
+        let test_db = create_standard_test_db().await;
+        let (client, _db) = setup_test_client(test_db).await;
+
+        let register = |username: &'static str| {
+            client
+                .post("/api/register/self")
+                .header(ContentType::JSON)
+                .body(json!({ "username": username, "password": "password123" }).to_string())
+                .dispatch()
+        };
+        let flag_url = "/api/admin/feature_flags/self_registration";
+
+        assert_eq!(register("first_signup").await.status(), Status::Ok);
+
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client
+            .put(flag_url)
+            .cookies(coach_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "enabled": false }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+        let response = client
+            .put(flag_url)
+            .cookies(admin_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "enabled": false }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        assert_eq!(register("second_signup").await.status(), Status::NotFound);
+
+        let response = client.get("/api/capabilities").dispatch().await;
+        let body: Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["flags"]["self_registration"], Value::Bool(false));
+
+        let response = client
+            .get("/api/admin/feature_flags")
+            .cookies(admin_cookies.clone())
+            .dispatch()
+            .await;
+        let body: Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        let flag = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["key"] == "self_registration")
+            .unwrap();
+        assert_eq!(flag["enabled"], Value::Bool(false));
+        assert_eq!(flag["overridden"], Value::Bool(true));
+
+        let response = client
+            .put("/api/admin/feature_flags/no_such_flag")
+            .cookies(admin_cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "enabled": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[rocket::async_test]
+    async fn public_syllabus_is_off_until_enabled_and_holds_no_student_data() {
+        let test_db = create_standard_test_db().await;
+        let pool = &test_db.pool;
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let triangle = test_db.technique_id("Triangle").unwrap();
+        let fundamentals = create_collection(pool, "Fundamentals", "Start here", None, coach_id)
+            .await
+            .unwrap();
+        add_techniques_to_collection(pool, fundamentals, vec![triangle, armbar])
+            .await
+            .unwrap();
+        let tag = create_tag(pool, "Submission").await.unwrap();
+        add_tag_to_technique(pool, armbar, tag).await.unwrap();
+        let admin_id = test_db.user_id("admin_user").unwrap();
+        let (client, test_db) = setup_test_client(test_db).await;
+
+        let response = client.get("/api/public/syllabus").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        set_feature_flag(&test_db.pool, "public_sharing", true, admin_id)
+            .await
+            .unwrap();
+        let response = client.get("/api/public/syllabus").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), Some("*"));
+        let body = response.into_string().await.unwrap();
+        for private in ["student_user", "Student notes", "Coach notes", "Coach User"] {
+            assert!(!body.contains(private), "{} leaked: {}", private, body);
+        }
+
+        let syllabus: PublicSyllabus = serde_json::from_str(&body).unwrap();
+        assert_eq!(syllabus.curricula.len(), 1);
+        assert_eq!(syllabus.curricula[0].name, "Fundamentals");
+        assert_eq!(syllabus.curricula[0].technique_ids, vec![triangle, armbar]);
+        let names: Vec<(&str, Vec<String>)> = syllabus
+            .techniques
+            .iter()
+            .map(|t| (t.name.as_str(), t.tags.clone()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("Armbar", vec!["Submission".to_string()]), ("Triangle", vec![])]
+        );
+    }
 }
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Cookie, Header, Status};
+    use serde_json::{Value, json};
+
+    use crate::idempotency::{IDEMPOTENCY_KEY_HEADER, MAX_BODY_LENGTH, REPLAYED_HEADER};
+    use crate::test::test_utils::{create_standard_test_db, login_test_user, setup_test_client};
+
+    #[rocket::async_test]
+    async fn test_retried_posts_replay_the_first_response() {
+        let (client, test_db) = setup_test_client(create_standard_test_db().await).await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+
+        let create = |key: &'static str, name: &str, cookies: &[Cookie<'static>]| {
+            client
+                .post("/api/collections")
+                .cookies(cookies.to_vec())
+                .header(ContentType::JSON)
+                .header(Header::new(IDEMPOTENCY_KEY_HEADER, key))
+                .body(json!({ "name": name }).to_string())
+        };
+        let count = |name: &'static str| {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM collections WHERE name = ?")
+                .bind(name)
+                .fetch_one(&test_db.pool)
+        };
+
+        let first = create("retry-1", "Retried", &coach).dispatch().await;
+        assert_eq!(first.status(), Status::Ok);
+        assert!(first.headers().get_one(REPLAYED_HEADER).is_none());
+        let first: Value = first.into_json().await.unwrap();
+
+        let retry = create("retry-1", "Retried", &coach).dispatch().await;
+        assert_eq!(retry.status(), Status::Ok);
+        assert_eq!(retry.headers().get_one(REPLAYED_HEADER), Some("true"));
+        assert_eq!(retry.into_json::<Value>().await.unwrap(), first);
+        assert_eq!(count("Retried").await.unwrap(), 1);
+
+        // Keys are per user.
+        let response = create("retry-1", "Retried", &admin).dispatch().await;
+        assert!(response.headers().get_one(REPLAYED_HEADER).is_none());
+        assert_eq!(count("Retried").await.unwrap(), 2);
+
+        // So is the same key with a different body.
+        let response = create("retry-1", "Renamed", &coach).dispatch().await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(count("Renamed").await.unwrap(), 0);
+
+        // The same key on another endpoint is refused, and nothing runs.
+        let response = client
+            .post("/api/tags")
+            .cookies(coach.clone())
+            .header(ContentType::JSON)
+            .header(Header::new(IDEMPOTENCY_KEY_HEADER, "retry-1"))
+            .body(json!({ "name": "Retried" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let tags: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tags WHERE name = 'Retried'")
+            .fetch_one(&test_db.pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, 0);
+
+        // A failed request frees its key for a corrected retry.
+        let response = create("retry-2", "", &coach).dispatch().await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = create("retry-2", "Corrected", &coach).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one(REPLAYED_HEADER).is_none());
+        assert_eq!(count("Corrected").await.unwrap(), 1);
+
+        let long_key = "k".repeat(256);
+        let response = client
+            .post("/api/collections")
+            .cookies(coach)
+            .header(ContentType::JSON)
+            .header(Header::new(IDEMPOTENCY_KEY_HEADER, long_key))
+            .body(json!({ "name": "Too long a key" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        // A body too long to fingerprint can't be checked on retry.
+        let response = client
+            .post("/api/collections")
+            .cookies(coach)
+            .header(ContentType::JSON)
+            .header(Header::new(IDEMPOTENCY_KEY_HEADER, "retry-3"))
+            .body(json!({ "name": "Long", "description": "d".repeat(MAX_BODY_LENGTH) }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+        assert_eq!(count("Long").await.unwrap(), 0);
+    }
+
+    #[rocket::async_test]
+    async fn test_signed_out_and_sign_in_requests_are_not_kept() {
+        let (client, test_db) = setup_test_client(create_standard_test_db().await).await;
+        let stored = || {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM idempotency_keys")
+                .fetch_one(&test_db.pool)
+        };
+
+        // Signed out, there is nobody to keep the key apart for.
+        for _ in 0..2 {
+            let response = client
+                .post("/api/forgot_password")
+                .header(ContentType::JSON)
+                .header(Header::new(IDEMPOTENCY_KEY_HEADER, "shared-key"))
+                .body(json!({ "username": "student_user" }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.headers().get_one(REPLAYED_HEADER).is_none());
+        }
+        assert_eq!(stored().await.unwrap(), 0);
+
+        // Signed in, logging in again still hands out a fresh session each time.
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+        for _ in 0..2 {
+            let response = client
+                .post("/api/login")
+                .cookies(coach.clone())
+                .header(ContentType::JSON)
+                .header(Header::new(IDEMPOTENCY_KEY_HEADER, "login-1"))
+                .body(json!({ "username": "coach_user", "password": "password123" }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.headers().get_one(REPLAYED_HEADER).is_none());
+            assert!(response.cookies().get_private("session_token").is_some());
+        }
+        assert_eq!(stored().await.unwrap(), 0);
+    }
+}
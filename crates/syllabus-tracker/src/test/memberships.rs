@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::asynchronous::Client;
+    use serde_json::json;
+
+    use crate::api::{Paginated, UserData};
+    use crate::config::{AppConfig, LiveConfig};
+    use crate::db::{MembershipImportReport, MembershipStatus};
+    use crate::init_rocket;
+    use crate::test::test_utils::{
+        TestDbBuilder, login_test_user, setup_test_client, test_config_figment,
+    };
+
+    const SECRET: &str = "billing-secret";
+
+    #[rocket::async_test]
+    async fn test_membership_webhook_needs_the_secret_and_updates_the_student() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .build()
+            .await
+            .unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        sqlx::query("UPDATE users SET email = 'Student@Example.com' WHERE id = ?")
+            .bind(student_id.0)
+            .execute(&test_db.pool)
+            .await
+            .unwrap();
+
+        let figment = test_config_figment().unwrap().merge(("membership_webhook_secret", SECRET));
+        let config = LiveConfig::new(AppConfig::from_figment(&figment).unwrap(), test_config_figment);
+        let client = Client::tracked(init_rocket(test_db.pool.clone(), None, config).await)
+            .await
+            .unwrap();
+
+        let post = |token: &str, body: serde_json::Value| {
+            client
+                .post("/api/integrations/membership")
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .body(body.to_string())
+        };
+        let lapsed = json!({ "email": "student@example.com", "status": "lapsed" });
+
+        let response = post("wrong", lapsed.clone()).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = post(SECRET, lapsed).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["user_id"], student_id.0);
+
+        let unknown = json!({ "username": "nobody", "status": "active" });
+        assert_eq!(post(SECRET, unknown).dispatch().await.status(), Status::NotFound);
+
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+        let response = client.get("/api/students").cookies(cookies).dispatch().await;
+        let students: Paginated<UserData> = response.into_json().await.unwrap();
+        assert_eq!(students.items[0].membership_status, Some(MembershipStatus::Lapsed));
+    }
+
+    #[rocket::async_test]
+    async fn test_membership_csv_import_and_dashboard_filter() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .student("paid_up", Some("Paid Up"))
+            .student("behind", Some("Behind"))
+            .student("untracked", Some("Untracked"))
+            .build()
+            .await
+            .unwrap();
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let csv = "Username,Status\npaid_up,Active\nbehind,lapsed\nnobody,active\nuntracked,paused\n";
+        let import = |dry_run: bool| {
+            client
+                .post("/api/admin/import/memberships")
+                .header(ContentType::JSON)
+                .cookies(cookies.clone())
+                .body(json!({ "csv": csv, "dry_run": dry_run }).to_string())
+        };
+
+        let report: MembershipImportReport =
+            import(false).dispatch().await.into_json().await.unwrap();
+        assert_eq!((report.rows, report.updated), (4, 2));
+        let problems: Vec<(u64, &str)> =
+            report.problems.iter().map(|p| (p.line, p.message.as_str())).collect();
+        assert_eq!(
+            problems,
+            vec![
+                (4, "No student matches this row"),
+                (5, "Unknown status 'paused'; use active or lapsed"),
+            ]
+        );
+
+        let usernames = |query: &'static str| {
+            let client = &client;
+            let cookies = cookies.clone();
+            async move {
+                let response = client.get(query).cookies(cookies).dispatch().await;
+                let students: Paginated<UserData> = response.into_json().await.unwrap();
+                let mut names: Vec<String> =
+                    students.items.into_iter().map(|s| s.username).collect();
+                names.sort();
+                names
+            }
+        };
+        assert_eq!(usernames("/api/students?membership=active").await, vec!["paid_up"]);
+        assert_eq!(usernames("/api/students?membership=lapsed").await, vec!["behind"]);
+        assert_eq!(usernames("/api/students").await.len(), 3);
+
+        let response = client
+            .post("/api/admin/import/memberships")
+            .header(ContentType::JSON)
+            .cookies(cookies.clone())
+            .body(json!({ "csv": "Name,Status\nBehind,active\n" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+}
@@ -1,8 +1,22 @@
 pub mod api;
+pub mod api_tokens;
+pub mod archive;
+pub mod attachments;
 pub mod attempts;
+pub mod bootstrap;
+pub mod config;
 pub mod db;
 pub mod feature_flags;
+pub mod idempotency;
+pub mod memberships;
+pub mod oidc;
+pub mod permissions;
+pub mod preflight;
+pub mod scheduler;
 pub mod sessions;
+pub mod snapshots;
+pub mod spreadsheet;
+pub mod system;
 pub mod tags;
 pub mod utils;
 pub mod videos;
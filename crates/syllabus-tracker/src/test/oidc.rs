@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use rocket::http::Status;
+    use rocket::local::asynchronous::Client;
+
+    use crate::api::UserData;
+    use crate::config::AppConfig;
+    use crate::init_rocket;
+    use crate::oidc::{DynIdentityProvider, ExternalIdentity, IdentityProvider, OidcError};
+    use crate::test::test_utils::{
+        TestDbBuilder, setup_test_client, test_config_figment, test_live_config,
+    };
+
+    /// Knows two accounts by their codes: the coach's and a stranger's.
+    struct FakeProvider;
+
+    #[async_trait]
+    impl IdentityProvider for FakeProvider {
+        fn issuer(&self) -> &str {
+            "https://issuer.test"
+        }
+
+        async fn authorization_url(&self, state: &str) -> Result<String, OidcError> {
+            Ok(format!("https://issuer.test/authorize?state={}", state))
+        }
+
+        async fn identify(&self, code: &str) -> Result<ExternalIdentity, OidcError> {
+            let (subject, email) = match code {
+                "coach" => ("coach-account", "Coach@Gym.example"),
+                "stranger" => ("stranger-account", "stranger@elsewhere.example"),
+                _ => return Err(OidcError::Provider("unknown code".to_string())),
+            };
+            Ok(ExternalIdentity {
+                subject: subject.to_string(),
+                email: Some(email.to_string()),
+            })
+        }
+    }
+
+    /// Starts a sign-in and returns the state the provider would send back.
+    async fn start_login(client: &Client) -> String {
+        let response = client.get("/api/auth/oidc/login").dispatch().await;
+        assert_eq!(response.status(), Status::SeeOther);
+        let location = response.headers().get_one("Location").unwrap();
+        location.split("state=").nth(1).unwrap().to_string()
+    }
+
+    async fn callback(client: &Client, code: &str, state: &str) -> String {
+        let response = client
+            .get(format!("/api/auth/oidc/callback?code={}&state={}", code, state))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::SeeOther);
+        response.headers().get_one("Location").unwrap().to_string()
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_signs_in_the_user_with_the_verified_email() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .build()
+            .await
+            .unwrap();
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        sqlx::query("UPDATE users SET email = 'coach@gym.example' WHERE id = ?")
+            .bind(coach_id.0)
+            .execute(&test_db.pool)
+            .await
+            .unwrap();
+
+        let provider: DynIdentityProvider = Arc::new(FakeProvider);
+        let rocket = init_rocket(test_db.pool.clone(), None, test_live_config()).await;
+        let client = Client::tracked(rocket.manage(provider)).await.unwrap();
+
+        // A state that isn't the one handed out is refused.
+        start_login(&client).await;
+        assert_eq!(callback(&client, "coach", "forged").await, "/login?error=oidc");
+        assert_eq!(client.get("/api/me").dispatch().await.status(), Status::Unauthorized);
+
+        // Nobody here has the stranger's email.
+        let state = start_login(&client).await;
+        assert_eq!(callback(&client, "stranger", &state).await, "/login?error=oidc");
+
+        let state = start_login(&client).await;
+        assert_eq!(callback(&client, "coach", &state).await, "/dashboard");
+        let me: UserData = client.get("/api/me").dispatch().await.into_json().await.unwrap();
+        assert_eq!(me.id, coach_id);
+
+        // The account stays linked when the user's email changes.
+        sqlx::query("UPDATE users SET email = NULL WHERE id = ?")
+            .bind(coach_id.0)
+            .execute(&test_db.pool)
+            .await
+            .unwrap();
+        client.post("/api/logout").dispatch().await;
+        let state = start_login(&client).await;
+        assert_eq!(callback(&client, "coach", &state).await, "/dashboard");
+
+        // The state works once.
+        assert_eq!(callback(&client, "coach", &state).await, "/login?error=oidc");
+    }
+
+    #[rocket::async_test]
+    async fn test_oidc_is_off_without_config_and_needs_all_of_it() {
+        let test_db = TestDbBuilder::new().build().await.unwrap();
+        let (client, _db) = setup_test_client(test_db).await;
+        let response = client.get("/api/auth/oidc/login").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let partial =
+            test_config_figment().unwrap().merge(("oidc_issuer", "https://accounts.google.com"));
+        let err = AppConfig::from_figment(&partial).unwrap_err();
+        assert!(err.to_string().contains("OIDC_CLIENT_SECRET"));
+    }
+}
@@ -0,0 +1,841 @@
+#[cfg(test)]
+mod tests {
+    //! Every mounted API route, checked against every role. `MATRIX` declares
+    //! who may call each route; the test asserts that callers without access
+    //! get 401 (anonymous) or 403 (signed in), and that callers with access
+    //! get past the guard (anything but 401/403). A route that is mounted but
+    //! missing from the matrix fails the test, so new routes have to state
+    //! their access here.
+    //!
+    //! Path parameters are filled with resources that belong to another
+    //! student (see `concrete_path`), so a route that lets students at their
+    //! own data is listed under the permission that covers everyone else's.
+
+    use chrono::Utc;
+    use rocket::http::{ContentType, Cookie, Method, Status};
+    use rocket::local::asynchronous::Client;
+
+    use crate::auth::{Permission, Role};
+    use crate::db::{create_attempt, get_user, replace_coach_students};
+    use crate::test::test_utils::{TestDbBuilder, login_test_user, setup_test_client};
+
+    use Access::{Authenticated, OwnerOnly, Public, Requires};
+    use Method::{Delete, Get, Patch, Post, Put};
+
+    #[derive(Debug, Clone, Copy)]
+    enum Access {
+        Public,
+        Authenticated,
+        Requires(Permission),
+        /// Only the student the resource belongs to, whatever their role.
+        /// The fixtures belong to someone else, so every caller is refused.
+        OwnerOnly,
+    }
+
+    impl Access {
+        fn allows(self, role: Option<&Role>) -> bool {
+            match (self, role) {
+                (Public, _) => true,
+                (_, None) => false,
+                (Authenticated, Some(_)) => true,
+                (Requires(permission), Some(role)) => role.has_permission(permission),
+                (OwnerOnly, Some(_)) => false,
+            }
+        }
+    }
+
+    struct Row {
+        method: Method,
+        path: &'static str,
+        access: Access,
+        /// A body that gets past JSON parsing and validation, for routes
+        /// that only check permissions once the body is accepted.
+        body: Option<&'static str>,
+        /// Sent as an (empty) multipart form instead of JSON, for upload
+        /// routes whose form guard rejects other content types outright.
+        multipart: bool,
+    }
+
+    const fn row(method: Method, path: &'static str, access: Access) -> Row {
+        Row { method, path, access, body: None, multipart: false }
+    }
+
+    const fn multipart(method: Method, path: &'static str, access: Access) -> Row {
+        Row { method, path, access, body: None, multipart: true }
+    }
+
+    const fn with_body(
+        method: Method,
+        path: &'static str,
+        access: Access,
+        body: &'static str,
+    ) -> Row {
+        Row { method, path, access, body: Some(body), multipart: false }
+    }
+
+    const MATRIX: &[Row] = &[
+        // Public
+        row(Get, "/api/health", Public),
+        row(Get, "/api/capabilities", Public),
+        row(Get, "/api/version", Public),
+        row(Get, "/api/public/syllabus", Public),
+        row(Post, "/api/login", Public),
+        row(Post, "/api/logout", Public),
+        row(Post, "/api/register/self", Public),
+        row(Post, "/api/forgot_password", Public),
+        row(Get, "/api/invite/<token>", Public),
+        row(Post, "/api/invite/<token>/claim", Public),
+        row(Post, "/api/register/invite/<token>", Public),
+        // Off (404) in tests, which configure no provider.
+        row(Get, "/api/auth/oidc/login", Public),
+        row(Get, "/api/auth/oidc/callback", Public),
+        // Signed URL; the signature is the access check.
+        row(Get, "/api/attachments/file/<key>", Public),
+        // Bearer secret rather than a session, and off (404) in tests.
+        row(Post, "/api/integrations/membership", Public),
+        // Any signed-in user
+        row(Get, "/api/me", Authenticated),
+        row(Get, "/api/me/preferences", Authenticated),
+        row(Put, "/api/me/preferences", Authenticated),
+        row(Get, "/api/me/notification_preferences", Authenticated),
+        with_body(
+            Put,
+            "/api/me/notification_preferences",
+            Authenticated,
+            r#"{"email_on_assignment": true, "email_on_coach_note": true, "digest": "off"}"#,
+        ),
+        row(Get, "/api/me/api_tokens", Authenticated),
+        with_body(
+            Post,
+            "/api/me/api_tokens",
+            Authenticated,
+            r#"{"name": "Probe", "scopes": ["read"]}"#,
+        ),
+        row(Delete, "/api/me/api_tokens/<id>", Authenticated),
+        row(Get, "/api/notifications", Authenticated),
+        // Someone else's notification reads as 404, not 403.
+        row(Post, "/api/notifications/<id>/read", Authenticated),
+        row(Put, "/api/profile", Authenticated),
+        row(Put, "/api/profile/timezone", Authenticated),
+        row(Post, "/api/change-password", Authenticated),
+        row(Get, "/api/status_transitions", Authenticated),
+        row(Get, "/api/statuses", Authenticated),
+        row(Get, "/api/tags", Authenticated),
+        row(Get, "/api/tags/tree", Authenticated),
+        row(Get, "/api/technique/<id>/tags", Authenticated),
+        row(Get, "/api/attachments/<id>/download-url", Authenticated),
+        multipart(Post, "/api/uploads", Authenticated),
+        multipart(Post, "/api/profile/avatar", Authenticated),
+        row(Delete, "/api/profile/avatar", Authenticated),
+        row(Get, "/api/users/<id>/avatar", Authenticated),
+        row(Get, "/api/jobs/<id>", Authenticated),
+        row(Get, "/api/journal", Requires(Permission::EditOwnNotes)),
+        with_body(Post, "/api/journal", Requires(Permission::EditOwnNotes), r#"{"body": "probe"}"#),
+        with_body(
+            Put,
+            "/api/journal/<id>",
+            Requires(Permission::EditOwnNotes),
+            r#"{"body": "probe"}"#,
+        ),
+        row(Delete, "/api/journal/<id>", Requires(Permission::EditOwnNotes)),
+        // Classes
+        with_body(
+            Post,
+            "/api/classes",
+            Requires(Permission::TrackAttendance),
+            r#"{"starts_at": "2026-01-01T18:00:00Z"}"#,
+        ),
+        row(Get, "/api/classes", Requires(Permission::TrackAttendance)),
+        row(Get, "/api/classes/<id>", Requires(Permission::TrackAttendance)),
+        with_body(
+            Put,
+            "/api/classes/<id>/attendance",
+            Requires(Permission::TrackAttendance),
+            r#"{"student_ids": []}"#,
+        ),
+        // Schedule
+        row(Get, "/api/schedule", Authenticated),
+        with_body(
+            Post,
+            "/api/schedule",
+            Requires(Permission::ManageSchedule),
+            r#"{"title": "Fundamentals", "weekday": 1, "start_time": "18:00:00"}"#,
+        ),
+        with_body(
+            Put,
+            "/api/schedule/<id>",
+            Requires(Permission::ManageSchedule),
+            r#"{"title": "Fundamentals", "weekday": 1, "start_time": "18:00:00"}"#,
+        ),
+        row(Delete, "/api/schedule/<id>", Requires(Permission::ManageSchedule)),
+        row(Get, "/api/schedule.ics", Public),
+        // Gradings
+        with_body(
+            Post,
+            "/api/gradings",
+            Requires(Permission::ConductGradings),
+            r#"{"student_id": 999999, "collection_id": 999999}"#,
+        ),
+        row(Get, "/api/gradings", Requires(Permission::ConductGradings)),
+        row(Get, "/api/gradings/<id>", Authenticated),
+        with_body(
+            Put,
+            "/api/gradings/<id>/results/<technique_id>",
+            Requires(Permission::ConductGradings),
+            r#"{"passed": true}"#,
+        ),
+        with_body(
+            Post,
+            "/api/gradings/<id>/close",
+            Requires(Permission::ConductGradings),
+            r#"{"outcome": "passed"}"#,
+        ),
+        // Students
+        row(Get, "/api/student/<id>/techniques", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student_technique/<id>", Requires(Permission::ViewAllStudents)),
+        with_body(
+            Put,
+            "/api/student_technique/<id>",
+            Requires(Permission::EditAllTechniques),
+            r#"{"coach_notes": "probe"}"#,
+        ),
+        // Checked per item, as if each were sent alone.
+        row(Put, "/api/student_techniques/batch", Authenticated),
+        row(
+            Post,
+            "/api/student_technique/<id>/mark_seen",
+            Requires(Permission::ViewAllStudents),
+        ),
+        row(Post, "/api/student_technique/<id>/request_review", OwnerOnly),
+        row(
+            Get,
+            "/api/student_technique/<id>/notes/history",
+            Requires(Permission::ViewAllStudents),
+        ),
+        row(
+            Get,
+            "/api/student_technique/<id>/history",
+            Requires(Permission::ViewAllStudents),
+        ),
+        with_body(
+            Post,
+            "/api/student_technique/<id>/notes/restore",
+            Requires(Permission::EditAllTechniques),
+            r#"{"revision_id": 999999}"#,
+        ),
+        row(Get, "/api/note_templates", Requires(Permission::EditAllTechniques)),
+        with_body(
+            Post,
+            "/api/note_templates",
+            Requires(Permission::EditAllTechniques),
+            r#"{"name": "Probe", "body": "Probe"}"#,
+        ),
+        with_body(
+            Put,
+            "/api/note_templates/<id>",
+            Requires(Permission::EditAllTechniques),
+            r#"{"name": "Probe", "body": "Probe"}"#,
+        ),
+        row(Delete, "/api/note_templates/<id>", Requires(Permission::EditAllTechniques)),
+        row(Get, "/api/students", Requires(Permission::ViewAllStudents)),
+        // Without the WebSocket upgrade an allowed caller gets a 426.
+        row(Get, "/api/coach/live", Requires(Permission::ViewAllStudents)),
+        row(
+            Get,
+            "/api/student/<id>/unassigned_techniques",
+            Requires(Permission::AssignTechniques),
+        ),
+        with_body(
+            Post,
+            "/api/student/<student_id>/add_techniques",
+            Requires(Permission::AssignTechniques),
+            r#"{"technique_ids": [999999]}"#,
+        ),
+        with_body(
+            Post,
+            "/api/student/<student_id>/assign_by_tag",
+            Requires(Permission::AssignTechniques),
+            r#"{"tag_id": 999999}"#,
+        ),
+        with_body(
+            Delete,
+            "/api/student/<id>/techniques",
+            Requires(Permission::AssignTechniques),
+            r#"{"student_technique_ids": [999999]}"#,
+        ),
+        with_body(
+            Post,
+            "/api/student/<student_id>/create_technique",
+            Requires(Permission::CreateTechniques),
+            r#"{"name": "Probe", "description": "Probe"}"#,
+        ),
+        row(
+            Post,
+            "/api/student/<student_id>/assign_collection/<collection_id>",
+            Requires(Permission::AssignTechniques),
+        ),
+        with_body(
+            Post,
+            "/api/student/<id>/graduate",
+            Requires(Permission::ViewAllStudents),
+            r#"{"graduated": false}"#,
+        ),
+        with_body(
+            Post,
+            "/api/student/<id>/techniques/bulk_status",
+            Requires(Permission::EditAllTechniques),
+            r#"{"status": "amber"}"#,
+        ),
+        row(Get, "/api/ranks", Requires(Permission::ViewAllStudents)),
+        with_body(Put, "/api/ranks", Requires(Permission::ManageRanks), r#"{"ranks": []}"#),
+        row(Get, "/api/ranks/eligibility", Requires(Permission::ViewAllStudents)),
+        with_body(
+            Put,
+            "/api/student/<id>/rank",
+            Requires(Permission::EditAllTechniques),
+            r#"{"rank": null}"#,
+        ),
+        // Attempts
+        row(Get, "/api/student_technique/<id>/attempts", Requires(Permission::ViewAllStudents)),
+        row(
+            Post,
+            "/api/student_technique/<id>/attempts",
+            Requires(Permission::EditAllTechniques),
+        ),
+        row(
+            Get,
+            "/api/student_technique/<id>/attempts/sparkline",
+            Requires(Permission::ViewAllStudents),
+        ),
+        with_body(
+            Put,
+            "/api/attempts/<id>",
+            Requires(Permission::EditAllTechniques),
+            r#"{"note": "probe"}"#,
+        ),
+        row(Delete, "/api/attempts/<id>", Requires(Permission::EditAllTechniques)),
+        row(Get, "/api/student/<id>/attempts/recent", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/attempts/summary", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/attempts/heatmap", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/analytics", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/progress", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/attendance", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/student/<id>/journal", Requires(Permission::ViewStudentJournals)),
+        row(
+            Get,
+            "/api/student/<id>/techniques/export.csv",
+            Requires(Permission::ViewAllStudents),
+        ),
+        // Library, tags and collections
+        row(Get, "/api/techniques", Requires(Permission::ViewAllStudents)),
+        with_body(
+            Put,
+            "/api/techniques/<id>",
+            Requires(Permission::EditAllTechniques),
+            r#"{"name": "Probe", "description": "Probe"}"#,
+        ),
+        with_body(
+            Post,
+            "/api/techniques/bulk",
+            Requires(Permission::CreateTechniques),
+            r#"[{"name": "Probe", "description": "Probe"}]"#,
+        ),
+        row(Delete, "/api/technique/<id>", Requires(Permission::DeleteTechniques)),
+        row(Post, "/api/technique/<id>/restore", Requires(Permission::DeleteTechniques)),
+        row(Get, "/api/techniques/<id>/stats", Requires(Permission::ViewAllStudents)),
+        row(Get, "/api/techniques/search", Requires(Permission::ViewAllStudents)),
+        with_body(
+            Post,
+            "/api/techniques/<id>/aliases",
+            Requires(Permission::EditAllTechniques),
+            r#"{"alias": "Probe"}"#,
+        ),
+        row(
+            Delete,
+            "/api/techniques/<id>/aliases/<alias_id>",
+            Requires(Permission::EditAllTechniques),
+        ),
+        row(Get, "/api/techniques/<id>/prerequisites", Authenticated),
+        with_body(
+            Post,
+            "/api/techniques/<id>/prerequisites",
+            Requires(Permission::EditAllTechniques),
+            r#"{"prerequisite_id": 999999}"#,
+        ),
+        row(
+            Delete,
+            "/api/techniques/<id>/prerequisites/<prerequisite_id>",
+            Requires(Permission::EditAllTechniques),
+        ),
+        row(Get, "/api/techniques/<id>/media", Requires(Permission::ViewAllStudents)),
+        with_body(
+            Post,
+            "/api/techniques/<id>/media",
+            Requires(Permission::EditAllTechniques),
+            r#"{"kind": "video", "url": "https://example.com/probe"}"#,
+        ),
+        row(
+            Delete,
+            "/api/techniques/<id>/media/<media_id>",
+            Requires(Permission::EditAllTechniques),
+        ),
+        row(Get, "/api/library/stats", Requires(Permission::ViewAllStudents)),
+        with_body(Post, "/api/tags", Requires(Permission::ManageTags), r#"{"name": "Probe"}"#),
+        with_body(Put, "/api/tags/<id>", Requires(Permission::ManageTags), r#"{"name": "Probe"}"#),
+        row(Delete, "/api/tags/<id>", Requires(Permission::ManageTags)),
+        with_body(
+            Put,
+            "/api/tags/<id>/archived",
+            Requires(Permission::ArchiveTags),
+            r#"{"archived": true}"#,
+        ),
+        with_body(
+            Post,
+            "/api/technique/tag",
+            Requires(Permission::ManageTags),
+            r#"{"technique_id": 999999, "tag_id": 999999}"#,
+        ),
+        row(
+            Delete,
+            "/api/technique/<technique_id>/tag/<tag_id>",
+            Requires(Permission::ManageTags),
+        ),
+        row(Get, "/api/collections", Requires(Permission::AssignTechniques)),
+        with_body(
+            Post,
+            "/api/collections",
+            Requires(Permission::CreateTechniques),
+            r#"{"name": "Probe"}"#,
+        ),
+        row(Get, "/api/collections/<id>", Requires(Permission::AssignTechniques)),
+        with_body(
+            Put,
+            "/api/collections/<id>",
+            Requires(Permission::CreateTechniques),
+            r#"{"name": "Probe"}"#,
+        ),
+        row(Delete, "/api/collections/<id>", Requires(Permission::CreateTechniques)),
+        with_body(
+            Post,
+            "/api/collections/<id>/techniques",
+            Requires(Permission::CreateTechniques),
+            r#"{"technique_ids": []}"#,
+        ),
+        with_body(
+            Post,
+            "/api/collections/<id>/create_technique",
+            Requires(Permission::CreateTechniques),
+            r#"{"name": "Probe", "description": "Probe"}"#,
+        ),
+        row(
+            Delete,
+            "/api/collections/<id>/techniques/<technique_id>",
+            Requires(Permission::CreateTechniques),
+        ),
+        with_body(
+            Put,
+            "/api/collections/<id>/techniques/order",
+            Requires(Permission::CreateTechniques),
+            r#"{"technique_ids": []}"#,
+        ),
+        row(Get, "/api/collections/<id>/students", Requires(Permission::ViewAllStudents)),
+        // Administration
+        with_body(
+            Put,
+            "/api/status_transitions",
+            Requires(Permission::ManageStatusTransitions),
+            r#"{"transitions": []}"#,
+        ),
+        with_body(
+            Post,
+            "/api/statuses",
+            Requires(Permission::ManageStatuses),
+            r##"{"name": "probe", "color": "#123456"}"##,
+        ),
+        with_body(
+            Put,
+            "/api/statuses/<name>",
+            Requires(Permission::ManageStatuses),
+            r##"{"color": "#123456"}"##,
+        ),
+        with_body(
+            Put,
+            "/api/statuses/order",
+            Requires(Permission::ManageStatuses),
+            r#"{"names": []}"#,
+        ),
+        row(Delete, "/api/statuses/<name>", Requires(Permission::ManageStatuses)),
+        with_body(
+            Post,
+            "/api/register",
+            Requires(Permission::RegisterUsers),
+            r#"{"username": "probe_user", "password": "password123",
+                "confirm_password": "password123", "role": "student"}"#,
+        ),
+        row(Get, "/api/admin/users", Requires(Permission::EditUserRoles)),
+        row(Get, "/api/admin/coach_report", Requires(Permission::ViewCoachReport)),
+        row(Put, "/api/admin/users/<id>", Requires(Permission::EditUserRoles)),
+        row(Get, "/api/admin/coaches/<id>/students", Requires(Permission::EditUserRoles)),
+        with_body(
+            Put,
+            "/api/admin/coaches/<id>/students",
+            Requires(Permission::EditUserRoles),
+            r#"{"student_ids": []}"#,
+        ),
+        row(Post, "/api/admin/users/<id>/approve", Requires(Permission::RegisterUsers)),
+        row(Post, "/api/admin/users/<id>/reset_claim", Requires(Permission::EditUserCredentials)),
+        with_body(
+            Post,
+            "/api/admin/invite_user",
+            Requires(Permission::RegisterUsers),
+            r#"{"display_name": "Probe", "role": "student"}"#,
+        ),
+        with_body(
+            Post,
+            "/api/admin/invitations",
+            Requires(Permission::RegisterUsers),
+            r#"{"role": "student"}"#,
+        ),
+        row(Get, "/api/admin/feature_flags", Requires(Permission::ManageFeatureFlags)),
+        with_body(
+            Put,
+            "/api/admin/feature_flags/<key>",
+            Requires(Permission::ManageFeatureFlags),
+            r#"{"enabled": true}"#,
+        ),
+        with_body(
+            Post,
+            "/api/admin/import/spreadsheet",
+            Requires(Permission::ImportSyllabus),
+            r#"{"csv": "Technique\n", "dry_run": true}"#,
+        ),
+        with_body(
+            Post,
+            "/api/admin/import/memberships",
+            Requires(Permission::ManageMemberships),
+            r#"{"csv": "Email,Status\n", "dry_run": true}"#,
+        ),
+        row(Post, "/api/admin/config/reload", Requires(Permission::ManageConfig)),
+        row(Get, "/api/admin/system", Requires(Permission::ViewSystemStatus)),
+        row(Get, "/api/admin/storage", Requires(Permission::ViewStorageStats)),
+        // Videos
+        row(Get, "/api/techniques/<tid>/videos", Authenticated),
+        multipart(Post, "/api/techniques/<tid>/videos/upload", Requires(Permission::UploadVideos)),
+        with_body(
+            Post,
+            "/api/techniques/<tid>/videos/link",
+            Requires(Permission::UploadVideos),
+            r#"{"title": "Probe", "url": "https://example.com/probe"}"#,
+        ),
+        with_body(
+            Post,
+            "/api/techniques/<tid>/videos/reorder",
+            Requires(Permission::UploadVideos),
+            r#"{"ordered_ids": []}"#,
+        ),
+        row(Patch, "/api/videos/<vid>", Requires(Permission::UploadVideos)),
+        row(Delete, "/api/videos/<vid>", Requires(Permission::DeleteVideos)),
+        multipart(Post, "/api/videos/<vid>/replace", Requires(Permission::UploadVideos)),
+        row(Get, "/api/videos/<vid>/status", Authenticated),
+        row(Get, "/api/videos/<vid>/playback-url", Authenticated),
+        row(Get, "/api/videos/<vid>/download-url", Authenticated),
+        with_body(
+            Put,
+            "/api/videos/<vid>/global-hidden",
+            Requires(Permission::ManageVideoVisibility),
+            r#"{"hidden": false}"#,
+        ),
+        row(
+            Put,
+            "/api/videos/<vid>/visibility/<student_id>",
+            Requires(Permission::ManageVideoVisibility),
+        ),
+        row(Get, "/api/videos/<vid>/stats", Requires(Permission::ViewWatchStats)),
+        row(Post, "/api/videos/<vid>/watch-events", Authenticated),
+        row(Get, "/api/videos/privacy-ack", Authenticated),
+        row(Post, "/api/videos/privacy-ack", Authenticated),
+        row(Get, "/api/students/<sid>/watch-activity", Requires(Permission::ViewWatchStats)),
+        row(Get, "/api/me/watch-state", Authenticated),
+        row(Get, "/api/dashboard/video-overview", Requires(Permission::ViewWatchStats)),
+    ];
+
+    /// Ids owned by `other_student`, which none of the signed-in callers is.
+    struct Fixtures {
+        student_id: i64,
+        student_technique_id: i64,
+        attempt_id: i64,
+    }
+
+    /// Fills each `<param>` from the segment before it: student, student
+    /// technique and attempt ids come from `Fixtures`; everything else gets
+    /// an id that does not exist, so handlers that do run change nothing.
+    fn concrete_path(template: &str, fixtures: &Fixtures) -> String {
+        let mut previous = "";
+        let mut segments = Vec::new();
+        for segment in template.split('/') {
+            if segment.starts_with('<') {
+                let value = match (previous, segment) {
+                    ("student_technique", _) => fixtures.student_technique_id,
+                    ("attempts", _) => fixtures.attempt_id,
+                    ("student" | "students", _) | (_, "<student_id>") => fixtures.student_id,
+                    _ => 999_999,
+                };
+                segments.push(value.to_string());
+            } else {
+                segments.push(segment.to_string());
+                previous = segment;
+            }
+        }
+        segments.join("/")
+    }
+
+    async fn dispatch(
+        client: &Client,
+        row: &Row,
+        path: &str,
+        cookies: &[Cookie<'static>],
+    ) -> Status {
+        let request = client.req(row.method, path.to_string()).cookies(cookies.to_vec());
+        let request = if row.multipart {
+            let content_type = ContentType::parse_flexible("multipart/form-data; boundary=probe");
+            request.header(content_type.unwrap()).body("--probe--\r\n")
+        } else {
+            request.header(ContentType::JSON).body(row.body.unwrap_or("{}"))
+        };
+        request.dispatch().await.status()
+    }
+
+    #[rocket::async_test]
+    async fn every_route_enforces_its_declared_access() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .coach("coach_user", Some("Coach User"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", Some("Other Student"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("other_student"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test database");
+
+        let other_id = test_db.user_id("other_student").unwrap();
+        let other_st = test_db.student_technique_id("other_student", "Armbar").await.unwrap();
+        let other = get_user(&test_db.pool, other_id).await.unwrap();
+        let attempt = create_attempt(&test_db.pool, &other, other_st, Utc::now(), None)
+            .await
+            .unwrap();
+        let fixtures = Fixtures {
+            student_id: other_id.0,
+            student_technique_id: other_st.0,
+            attempt_id: attempt.attempt.id,
+        };
+
+        let (client, _db) = setup_test_client(test_db).await;
+
+        // Each route is mounted under `/api/v1` too (see `crate::versioning`),
+        // with the same guards, so one row covers both.
+        let mut mounted: Vec<(Method, String)> = client
+            .rocket()
+            .routes()
+            .map(|route| (route.method, route.uri.path().replacen("/api/v1/", "/api/", 1)))
+            .collect();
+        mounted.sort_by(|a, b| (a.1.as_str(), a.0.as_str()).cmp(&(b.1.as_str(), b.0.as_str())));
+        mounted.dedup();
+
+        let declared = |method: Method, path: &str| {
+            MATRIX.iter().find(|row| row.method == method && row.path == path)
+        };
+        let undeclared: Vec<String> = mounted
+            .iter()
+            .filter(|(method, path)| declared(*method, path).is_none())
+            .map(|(method, path)| format!("{} {}", method, path))
+            .collect();
+        assert!(
+            undeclared.is_empty(),
+            "Routes missing from the permission matrix: {:#?}",
+            undeclared
+        );
+        let stale: Vec<String> = MATRIX
+            .iter()
+            .filter(|row| !mounted.iter().any(|(m, p)| *m == row.method && p == row.path))
+            .map(|row| format!("{} {}", row.method, row.path))
+            .collect();
+        assert!(stale.is_empty(), "Matrix rows for unmounted routes: {:#?}", stale);
+
+        // Logging out ends the caller's session, so it goes last.
+        let mut rows: Vec<&Row> = MATRIX.iter().collect();
+        rows.sort_by_key(|row| row.path == "/api/logout");
+
+        let mut failures = Vec::new();
+        let mut check = |row: &Row, caller: &str, role: Option<&Role>, status: Status| {
+            let allowed = row.access.allows(role);
+            let ok = match (allowed, role) {
+                (true, _) => status != Status::Unauthorized && status != Status::Forbidden,
+                (false, None) => status == Status::Unauthorized,
+                (false, Some(_)) => status == Status::Forbidden,
+            };
+            if !ok {
+                failures.push(format!(
+                    "{} {} as {}: got {}, expected {}",
+                    row.method,
+                    row.path,
+                    caller,
+                    status.code,
+                    if !allowed && role.is_none() {
+                        "401"
+                    } else if !allowed {
+                        "403"
+                    } else {
+                        "anything but 401/403"
+                    }
+                ));
+            }
+        };
+
+        // The client is tracked, so anonymous requests have to go out before
+        // any login puts a session cookie in its jar.
+        for row in &rows {
+            let status = dispatch(&client, row, &concrete_path(row.path, &fixtures), &[]).await;
+            check(row, "anonymous", None, status);
+        }
+
+        let callers = [
+            ("student_user", Role::Student),
+            ("coach_user", Role::Coach),
+            ("admin_user", Role::Admin),
+        ];
+        let mut sessions = Vec::new();
+        for (username, role) in callers {
+            let cookies = login_test_user(&client, username, "password123").await;
+            sessions.push((username, role, cookies));
+        }
+
+        for row in &rows {
+            let path = concrete_path(row.path, &fixtures);
+            for (username, role, cookies) in &sessions {
+                let status = dispatch(&client, row, &path, cookies).await;
+                check(row, username, Some(role), status);
+            }
+        }
+
+        assert!(failures.is_empty(), "Permission matrix mismatches:\n{}", failures.join("\n"));
+    }
+
+    /// Writes to one student's techniques, sent with their `MATRIX` bodies.
+    /// A coach scoped to other students, or to none, gets 404 from each, as
+    /// if the student weren't there.
+    const SCOPED_WRITES: &[(Method, &str)] = &[
+        (Put, "/api/student_technique/<id>"),
+        (Post, "/api/student_technique/<id>/notes/restore"),
+        (Post, "/api/student_technique/<id>/attempts"),
+        (Put, "/api/attempts/<id>"),
+        (Delete, "/api/attempts/<id>"),
+        (Post, "/api/student/<student_id>/add_techniques"),
+        (Post, "/api/student/<student_id>/assign_by_tag"),
+        (Delete, "/api/student/<id>/techniques"),
+        (Post, "/api/student/<student_id>/create_technique"),
+        (Post, "/api/student/<student_id>/assign_collection/<collection_id>"),
+        (Post, "/api/student/<id>/graduate"),
+        (Post, "/api/student/<id>/techniques/bulk_status"),
+        (Put, "/api/student/<id>/rank"),
+    ];
+
+    /// Reads about one student, which answer 403 outside a coach's scope.
+    const SCOPED_READS: &[(Method, &str)] = &[
+        (Get, "/api/student/<id>/techniques"),
+        (Get, "/api/student/<id>/unassigned_techniques"),
+        (Get, "/api/student_technique/<id>/attempts"),
+    ];
+
+    #[rocket::async_test]
+    async fn scoped_coaches_cannot_reach_other_students() {
+        let test_db = TestDbBuilder::new()
+            .admin("admin_user", Some("Admin User"))
+            .coach("coach_user", Some("Coach User"))
+            .coach("new_coach", Some("New Coach"))
+            .student("student_user", Some("Student User"))
+            .student("other_student", Some("Other Student"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .assign_technique(Some("Armbar"), Some("other_student"), "red", "", "")
+            .build()
+            .await
+            .expect("Failed to build test database");
+
+        let coach_id = test_db.user_id("coach_user").unwrap();
+        let new_coach_id = test_db.user_id("new_coach").unwrap();
+        let admin_id = test_db.user_id("admin_user").unwrap();
+        let student_id = test_db.user_id("student_user").unwrap();
+        replace_coach_students(&test_db.pool, coach_id, &[student_id], admin_id).await.unwrap();
+        // No students assigned at all, which must not mean all of them.
+        replace_coach_students(&test_db.pool, new_coach_id, &[], admin_id).await.unwrap();
+        let other_id = test_db.user_id("other_student").unwrap();
+        let other_st = test_db.student_technique_id("other_student", "Armbar").await.unwrap();
+        let other = get_user(&test_db.pool, other_id).await.unwrap();
+        let attempt = create_attempt(&test_db.pool, &other, other_st, Utc::now(), None)
+            .await
+            .unwrap();
+        let fixtures = Fixtures {
+            student_id: other_id.0,
+            student_technique_id: other_st.0,
+            attempt_id: attempt.attempt.id,
+        };
+
+        let (client, test_db) = setup_test_client(test_db).await;
+
+        let mut failures = Vec::new();
+        for username in ["coach_user", "new_coach"] {
+            let coach = login_test_user(&client, username, "password123").await;
+            let expected = SCOPED_WRITES
+                .iter()
+                .map(|route| (route, Status::NotFound))
+                .chain(SCOPED_READS.iter().map(|route| (route, Status::Forbidden)));
+            for (&(method, path), expected) in expected {
+                let row =
+                    MATRIX.iter().find(|row| row.method == method && row.path == path).unwrap();
+                let status = dispatch(&client, row, &concrete_path(path, &fixtures), &coach).await;
+                if status != expected {
+                    failures.push(format!(
+                        "{} {} as {}: got {}, expected {}",
+                        method, path, username, status.code, expected.code
+                    ));
+                }
+            }
+
+            // The batch endpoint reports it per item.
+            let response = client
+                .put("/api/student_techniques/batch")
+                .cookies(coach)
+                .header(ContentType::JSON)
+                .body(format!(r#"[{{"id": {}, "coach_notes": "probe"}}]"#, other_st))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            let body: serde_json::Value = response.into_json().await.unwrap();
+            assert_eq!(body["results"][0]["status"], 404);
+        }
+        assert!(failures.is_empty(), "Scoped coach routes:\n{}", failures.join("\n"));
+
+        let (status, coach_notes): (String, String) =
+            sqlx::query_as("SELECT status, coach_notes FROM student_techniques WHERE id = ?")
+                .bind(other_st.0)
+                .fetch_one(&test_db.pool)
+                .await
+                .unwrap();
+        assert_eq!((status.as_str(), coach_notes.as_str()), ("red", ""));
+        let techniques: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM student_techniques WHERE student_id = ?")
+                .bind(other_id.0)
+                .fetch_one(&test_db.pool)
+                .await
+                .unwrap();
+        assert_eq!(techniques, 1);
+        let (attempts, note): (i64, Option<String>) = sqlx::query_as(
+            "SELECT COUNT(*), MAX(coach_note) FROM attempts WHERE student_technique_id = ?",
+        )
+        .bind(other_st.0)
+        .fetch_one(&test_db.pool)
+        .await
+        .unwrap();
+        assert_eq!((attempts, note), (1, None));
+    }
+}
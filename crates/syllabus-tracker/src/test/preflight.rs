@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use rocket::figment::Figment;
+
+    use crate::preflight::{Outcome, PreflightReport, run};
+
+    fn outcome(report: &PreflightReport, name: &str) -> Option<Outcome> {
+        report.checks.iter().find(|c| c.name == name).map(|c| c.outcome)
+    }
+
+    #[rocket::async_test]
+    async fn test_preflight_reports_every_failure_at_once() {
+        let figment = Figment::new()
+            .merge(("database_url", "sqlite:///nonexistent/dir/app.db"))
+            .merge(("schema_path", "/nonexistent/schema.sql"))
+            .select(rocket::Config::RELEASE_PROFILE);
+        let (report, config) = run(&figment).await;
+
+        assert!(config.is_some());
+        assert!(!report.passed());
+        assert_eq!(outcome(&report, "schema"), Some(Outcome::Fail));
+        assert_eq!(outcome(&report, "database"), Some(Outcome::Fail));
+        assert_eq!(outcome(&report, "secret"), Some(Outcome::Fail));
+        let text = report.to_string();
+        assert!(text.contains("migrate"), "{}", text);
+        assert!(text.contains("ROCKET_SECRET_KEY"), "{}", text);
+    }
+
+    #[rocket::async_test]
+    async fn test_preflight_passes_with_valid_schema_and_database() {
+        let dir = std::env::temp_dir().join(format!("preflight-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("app.db");
+        std::fs::File::create(&db_path).unwrap();
+
+        let figment = Figment::new()
+            .merge(("database_url", format!("sqlite://{}", db_path.display())))
+            .merge(("schema_path", concat!(env!("CARGO_MANIFEST_DIR"), "/../../config/schema.sql")))
+            .select(rocket::Config::DEBUG_PROFILE);
+        let (report, _) = run(&figment).await;
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(outcome(&report, "schema"), Some(Outcome::Ok), "{}", report);
+        assert_eq!(outcome(&report, "database"), Some(Outcome::Ok), "{}", report);
+        assert_eq!(outcome(&report, "secret"), Some(Outcome::Ok), "{}", report);
+    }
+
+    #[rocket::async_test]
+    async fn test_preflight_allows_a_missing_database_when_creating_it() {
+        let dir = std::env::temp_dir().join(format!("preflight-{}", uuid::Uuid::new_v4()));
+        let figment = Figment::new()
+            .merge(("database_url", format!("sqlite://{}", dir.join("app.db").display())))
+            .merge(("schema_path", concat!(env!("CARGO_MANIFEST_DIR"), "/../../config/schema.sql")))
+            .merge(("create_database", true))
+            .select(rocket::Config::DEBUG_PROFILE);
+        let (report, _) = run(&figment).await;
+
+        assert_eq!(outcome(&report, "database"), Some(Outcome::Ok), "{}", report);
+        assert!(report.to_string().contains("will be created"), "{}", report);
+    }
+}
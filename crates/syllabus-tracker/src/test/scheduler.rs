@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use sqlx::SqlitePool;
+
+    use crate::db::{get_job_run, try_start_job};
+    use crate::error::AppError;
+    use crate::scheduler::{Job, RunOutcome, Schedule, run_job};
+    use crate::test::test_utils::TestDbBuilder;
+
+    #[test]
+    fn test_schedule_parsing_and_next_run() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let minutes = |m| chrono::Duration::minutes(m);
+
+        let every = Schedule::from_str("every 15m").unwrap();
+        assert!(matches!(every, Schedule::Every(d) if d == Duration::from_secs(900)));
+        assert_eq!(every.next_run(None, now), Some(now));
+        assert_eq!(
+            every.next_run(Some(now - minutes(5)), now),
+            Some(now + minutes(10))
+        );
+        assert_eq!(every.next_run(Some(now - minutes(60)), now), Some(now));
+
+        let nightly = Schedule::from_str("0 3 * * *").unwrap();
+        let tomorrow_3am = Utc.with_ymd_and_hms(2025, 6, 2, 3, 0, 0).unwrap();
+        // A missed 03:00 today is not made up.
+        assert_eq!(nightly.next_run(Some(now - minutes(60 * 48)), now), Some(tomorrow_3am));
+
+        assert_eq!(Schedule::from_str("OFF").unwrap().next_run(None, now), None);
+
+        for bad in ["every 0m", "every 5x", "every", "every 1é", "* * *", "61 * * * *", ""] {
+            assert!(Schedule::from_str(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    struct Counting {
+        runs: AtomicUsize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Job for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn default_schedule(&self) -> &'static str {
+            "every 1h"
+        }
+
+        async fn run(&self, _pool: &SqlitePool) -> Result<String, AppError> {
+            let n = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail {
+                return Err(AppError::Internal("boom".to_string()));
+            }
+            Ok(format!("run {}", n))
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_run_job_records_runs_and_prevents_overlap() {
+        let test_db = TestDbBuilder::new().build().await.unwrap();
+        let pool = &test_db.pool;
+        let job = Counting { runs: AtomicUsize::new(0), fail: false };
+
+        let outcome = run_job(pool, &job).await.unwrap();
+        assert_eq!(outcome, RunOutcome::Finished { ok: true, message: "run 1".to_string() });
+        let run = get_job_run(pool, "counting").await.unwrap().unwrap();
+        assert_eq!(run.last_status.as_deref(), Some("ok"));
+        assert!(run.running_since.is_none());
+        assert!(run.last_started_at.is_some() && run.last_finished_at.is_some());
+
+        // Someone else is mid-run: skipped, and the job body never runs.
+        let now = Utc::now().naive_utc();
+        let hour = chrono::Duration::hours(1);
+        assert!(try_start_job(pool, "counting", now, now - hour).await.unwrap());
+        assert!(!try_start_job(pool, "counting", now, now - hour).await.unwrap());
+        assert_eq!(run_job(pool, &job).await.unwrap(), RunOutcome::Skipped);
+        assert_eq!(job.runs.load(Ordering::SeqCst), 1);
+
+        // A run that died hours ago no longer blocks.
+        sqlx::query("UPDATE scheduled_jobs SET running_since = ? WHERE name = 'counting'")
+            .bind(now - hour * 3)
+            .execute(pool)
+            .await
+            .unwrap();
+        let failing = Counting { runs: AtomicUsize::new(0), fail: true };
+        let outcome = run_job(pool, &failing).await.unwrap();
+        assert!(matches!(outcome, RunOutcome::Finished { ok: false, .. }));
+        let run = get_job_run(pool, "counting").await.unwrap().unwrap();
+        assert_eq!(run.last_status.as_deref(), Some("error"));
+        assert!(run.last_message.unwrap().contains("boom"));
+    }
+}
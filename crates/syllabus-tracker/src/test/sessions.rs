@@ -5,6 +5,7 @@ mod tests {
             clean_expired_sessions, create_user_session, get_session_by_token, invalidate_session,
         },
         error::AppError,
+        ids::UserId,
         test::test_utils::TestDbBuilder,
     };
     use chrono::{Duration, NaiveDateTime, Utc};
@@ -13,7 +14,7 @@ mod tests {
     use sqlx::{Pool, Sqlite, SqlitePool};
     use uuid::Uuid;
 
-    async fn create_test_session() -> (i64, String, NaiveDateTime, Pool<Sqlite>) {
+    async fn create_test_session() -> (UserId, String, NaiveDateTime, Pool<Sqlite>) {
         let test_db = TestDbBuilder::new()
             .student("test_session_user", None)
             .build()
@@ -45,7 +46,7 @@ mod tests {
             .await
             .expect("Failed to get session");
 
-        assert_eq!(session.user_id, user_id);
+        assert_eq!(session.user_id, user_id.0);
         assert_eq!(session.token, token);
 
         let expires_diff =
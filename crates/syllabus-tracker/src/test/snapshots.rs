@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    //! Snapshots of the JSON the SPA reads from a few key endpoints, so a
+    //! renamed or dropped field shows up as a snapshot diff. Ids are stable
+    //! because every test builds a fresh database in the same order;
+    //! timestamps are checked to be RFC 3339 and then redacted. Review
+    //! changes with `cargo insta review`.
+
+    use insta::internals::Content;
+    use rocket::http::{ContentType, Cookie, Status};
+    use rocket::local::asynchronous::Client;
+    use serde_json::{Value, json};
+
+    use crate::test::test_utils::{create_standard_test_db, login_test_user, setup_test_client};
+
+    const TIMESTAMP_FIELDS: &[&str] = &[
+        "created_at",
+        "updated_at",
+        "last_update",
+        "last_coach_update_at",
+        "last_student_update_at",
+        "last_attempt_at",
+        "last_student_initiative_at",
+        "last_watch_at",
+        "graduated_at",
+        "claimed_at",
+        "approved_at",
+        "reset_requested_at",
+    ];
+
+    /// Settings that swap every non-null timestamp for `[timestamp]` once it
+    /// has parsed, so a format change still fails the snapshot.
+    fn settings() -> insta::Settings {
+        let mut settings = insta::Settings::clone_current();
+        settings.set_sort_maps(true);
+        for field in TIMESTAMP_FIELDS {
+            settings.add_dynamic_redaction(&format!(".**.{}", field), |value, path| {
+                match value.as_str() {
+                    Some(ts) => {
+                        assert!(
+                            chrono::DateTime::parse_from_rfc3339(ts).is_ok(),
+                            "{} is not RFC 3339: {}",
+                            path,
+                            ts
+                        );
+                        Content::from("[timestamp]")
+                    }
+                    None => value,
+                }
+            });
+        }
+        settings
+    }
+
+    async fn get_json(client: &Client, url: &str, cookies: &[Cookie<'static>]) -> Value {
+        let response = client.get(url.to_string()).cookies(cookies.to_vec()).dispatch().await;
+        assert_eq!(response.status(), Status::Ok, "GET {}", url);
+        serde_json::from_str(&response.into_string().await.unwrap()).unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn login_response_shape() {
+        let test_db = create_standard_test_db().await;
+        let (client, _db) = setup_test_client(test_db).await;
+
+        let response = client
+            .post("/api/login")
+            .header(ContentType::JSON)
+            .body(json!({ "username": "student_user", "password": "password123" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+
+        settings().bind(|| insta::assert_json_snapshot!("login", body));
+    }
+
+    #[rocket::async_test]
+    async fn student_techniques_and_tags_shape() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let coach_cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        for name in ["Submission", "Guard"] {
+            let response = client
+                .post("/api/tags")
+                .cookies(coach_cookies.clone())
+                .header(ContentType::JSON)
+                .body(json!({ "name": name }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+        }
+        let technique_id = test_db.technique_id("Armbar").unwrap();
+        let response = client
+            .post("/api/technique/tag")
+            .cookies(coach_cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "technique_id": technique_id, "tag_id": 1 }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let student_id = test_db.user_id("student_user").unwrap();
+        let url = format!("/api/student/{}/techniques", student_id);
+        let techniques = get_json(&client, &url, &coach_cookies).await;
+        let tags = get_json(&client, "/api/tags", &coach_cookies).await;
+
+        let settings = settings();
+        settings.bind(|| {
+            insta::assert_json_snapshot!("student_techniques", techniques);
+            insta::assert_json_snapshot!("tags", tags);
+        });
+    }
+
+    #[rocket::async_test]
+    async fn admin_users_shape() {
+        let test_db = create_standard_test_db().await;
+        let (client, _db) = setup_test_client(test_db).await;
+        let admin_cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let users = get_json(&client, "/api/admin/users", &admin_cookies).await;
+
+        settings().bind(|| insta::assert_json_snapshot!("admin_users", users));
+    }
+}
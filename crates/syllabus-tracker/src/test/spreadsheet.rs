@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Status};
+    use serde_json::json;
+
+    use crate::api::JobStartedResponse;
+    use crate::db::{
+        BackgroundJob, ImportCounts, RowProblem, SheetColumns, SheetImportReport,
+        import_spreadsheet, parse_spreadsheet,
+    };
+    use crate::test::test_utils::{create_standard_test_db, login_test_user, setup_test_client};
+    use crate::validation::ValidationConfig;
+
+    const SHEET: &str = "\
+Move,Notes,Category,Belt
+Armbar,From guard,\"submission, guard\",White Belt
+Scissor Sweep,From closed guard,Guard; Sweep,White Belt
+,,,
+kimura,Shoulder lock,Submission,Blue Belt
+,Missing a name,,White Belt
+";
+
+    fn columns() -> SheetColumns {
+        SheetColumns {
+            name: "move".to_string(),
+            description: "Notes".to_string(),
+            tags: "Category".to_string(),
+            curriculum: "Belt".to_string(),
+            tag_separator: ',',
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_spreadsheet_import_reports_problems_and_reimports_cleanly() {
+        let test_db = create_standard_test_db().await;
+        let coach = test_db.user_id("coach_user").unwrap();
+        let config = ValidationConfig::default();
+        let rows = parse_spreadsheet(SHEET, &columns()).unwrap();
+        assert_eq!(rows.len(), 4, "blank rows are dropped");
+
+        let report = import_spreadsheet(&test_db.pool, &rows, coach, &config, false)
+            .await
+            .unwrap();
+        let counts = |created, existing| ImportCounts { created, existing };
+        // Armbar is already in the library.
+        assert_eq!(report.techniques, counts(2, 1));
+        // "Guard; Sweep" is one tag under the ',' separator.
+        assert_eq!(report.tags, counts(3, 0));
+        assert_eq!(report.curricula, counts(2, 0));
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].line, 6);
+        assert!(report.problems[0].skipped);
+
+        let armbar_tags: Vec<(String,)> = sqlx::query_as(
+            "SELECT g.name FROM technique_tags tt
+             JOIN tags g ON g.id = tt.tag_id
+             JOIN techniques t ON t.id = tt.technique_id
+             WHERE t.name = 'Armbar' ORDER BY g.name",
+        )
+        .fetch_all(&test_db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            armbar_tags,
+            vec![("Guard".to_string(),), ("Submission".to_string(),)]
+        );
+        let white_belt: Vec<(String,)> = sqlx::query_as(
+            "SELECT t.name FROM collection_techniques ct
+             JOIN collections c ON c.id = ct.collection_id
+             JOIN techniques t ON t.id = ct.technique_id
+             WHERE c.name = 'White Belt' ORDER BY ct.position",
+        )
+        .fetch_all(&test_db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            white_belt,
+            vec![("Armbar".to_string(),), ("Scissor Sweep".to_string(),)]
+        );
+
+        // Same sheet again, with a different casing of an existing name and
+        // a row repeated: nothing new, and the repeat is flagged.
+        let edited = format!("{}KIMURA,Again,Submission,Blue Belt\n", SHEET);
+        let rows = parse_spreadsheet(&edited, &columns()).unwrap();
+        let again = import_spreadsheet(&test_db.pool, &rows, coach, &config, false)
+            .await
+            .unwrap();
+        assert_eq!(again.techniques, counts(0, 4));
+        assert_eq!(again.tags, counts(0, 3));
+        assert_eq!(again.curricula, counts(0, 2));
+        assert_eq!(
+            again.problems.last(),
+            Some(&RowProblem {
+                line: 7,
+                message: "'KIMURA' already appears on line 5; merged".to_string(),
+                skipped: false,
+            })
+        );
+    }
+
+    #[rocket::async_test]
+    async fn test_spreadsheet_import_endpoint() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        // Dry run reports but doesn't write.
+        let response = client
+            .post("/api/admin/import/spreadsheet")
+            .cookies(cookies.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "csv": SHEET, "columns": columns(), "dry_run": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let report: SheetImportReport =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(report.rows, 4);
+        assert_eq!(report.techniques.created, 2);
+        let techniques: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM techniques")
+            .fetch_one(&test_db.pool)
+            .await
+            .unwrap();
+        assert_eq!(techniques.0, 2, "dry run must not write");
+
+        // Default column names don't match this sheet.
+        let response = client
+            .post("/api/admin/import/spreadsheet")
+            .cookies(cookies)
+            .header(ContentType::JSON)
+            .body(json!({ "csv": SHEET }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body = response.into_string().await.unwrap();
+        assert!(body.contains("No 'Technique' column"), "{}", body);
+    }
+
+    #[rocket::async_test]
+    async fn test_background_import_reports_through_the_job() {
+        let test_db = create_standard_test_db().await;
+        let (client, test_db) = setup_test_client(test_db).await;
+        let admin = login_test_user(&client, "admin_user", "password123").await;
+        let coach = login_test_user(&client, "coach_user", "password123").await;
+
+        let response = client
+            .post("/api/admin/import/spreadsheet")
+            .cookies(admin.clone())
+            .header(ContentType::JSON)
+            .body(json!({ "csv": SHEET, "columns": columns(), "background": true }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Accepted);
+        let started: JobStartedResponse = response.into_json().await.unwrap();
+
+        let mut job: Option<BackgroundJob> = None;
+        for _ in 0..50 {
+            let polled: BackgroundJob = client
+                .get(format!("/api/jobs/{}", started.job_id))
+                .cookies(admin.clone())
+                .dispatch()
+                .await
+                .into_json()
+                .await
+                .unwrap();
+            if polled.finished_at.is_some() {
+                job = Some(polled);
+                break;
+            }
+            rocket::tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let job = job.expect("import job never finished");
+        assert_eq!(job.status, "succeeded");
+        let report: SheetImportReport = serde_json::from_value(job.result.unwrap()).unwrap();
+        assert_eq!(report.techniques.created, 2);
+        let kimura: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM techniques WHERE name = 'kimura'")
+                .fetch_one(&test_db.pool)
+                .await
+                .unwrap();
+        assert_eq!(kimura.0, 1);
+
+        let response =
+            client.get(format!("/api/jobs/{}", started.job_id)).cookies(coach).dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
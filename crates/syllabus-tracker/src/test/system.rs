@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use opentelemetry::Value as AttributeValue;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use rocket::figment::Figment;
+    use rocket::http::{Cookie, Header, Status};
+    use rocket::local::asynchronous::Client;
+    use serde_json::Value;
+    use tracing_subscriber::{Registry, layer::SubscriberExt};
+
+    use crate::config::{AppConfig, ConfigError, LiveConfig};
+    use crate::init_rocket;
+    use crate::telemetry::SESSION_COOKIE;
+    use crate::test::test_utils::{
+        TestDbBuilder, create_standard_test_db, login_test_user, setup_test_client,
+        test_config_figment,
+    };
+    use crate::version::VersionInfo;
+    use crate::versioning::{API_VERSION_HEADER, API_VERSIONS};
+
+    #[rocket::async_test]
+    async fn test_system_report() {
+        let test_db = create_standard_test_db().await;
+        sqlx::query("INSERT INTO schema_migrations (schema_sha256, steps) VALUES ('abc', 'x')")
+            .execute(&test_db.pool)
+            .await
+            .unwrap();
+        let (client, _test_db) = setup_test_client(test_db).await;
+        let cookies = login_test_user(&client, "admin_user", "password123").await;
+
+        let response = client
+            .get("/api/admin/system")
+            .cookies(cookies)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let report: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+
+        assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(report["schema"]["sha256"].as_str().unwrap().len(), 64);
+        assert_eq!(report["schema"]["pending"], Value::Array(vec![]));
+        assert_eq!(report["migrations"][0]["schema_sha256"], "abc");
+        assert!(report["pool"]["size"].as_u64().unwrap() >= 1);
+        assert_eq!(report["config"]["SESSION_TTL_DAYS"], "30");
+        // Secrets are only ever reported as present or not.
+        let secret = report["config"]["ROCKET_SECRET_KEY"].as_str().unwrap();
+        assert!(secret == "set" || secret == "unset", "{}", secret);
+    }
+
+    #[rocket::async_test]
+    async fn test_version_is_public() {
+        let (client, _test_db) = setup_test_client(create_standard_test_db().await).await;
+
+        let response = client.get("/api/version").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let info: VersionInfo =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(info, VersionInfo::current());
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.built_at.is_some());
+    }
+
+    #[rocket::async_test]
+    async fn test_api_versions_are_negotiated() {
+        let (client, _test_db) = setup_test_client(create_standard_test_db().await).await;
+        let cookies = login_test_user(&client, "coach_user", "password123").await;
+
+        // Bare `/api` is the shim for version 1, and so is asking for it.
+        let v1 = [("/api/tags", None), ("/api/v1/tags", None), ("/api/tags", Some("1"))];
+        for (path, asked) in v1 {
+            let mut request = client.get(path).cookies(cookies.clone());
+            if let Some(asked) = asked {
+                request = request.header(Header::new(API_VERSION_HEADER, asked));
+            }
+            let response = request.dispatch().await;
+            assert_eq!(response.status(), Status::Ok, "{}", path);
+            assert_eq!(response.headers().get_one(API_VERSION_HEADER), Some("1"));
+        }
+
+        let response = client
+            .get("/api/tags")
+            .cookies(cookies.clone())
+            .header(Header::new(API_VERSION_HEADER, "2"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotAcceptable);
+        let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(body["supported"], serde_json::json!(API_VERSIONS));
+        let response = client.get("/api/v2/tags").cookies(cookies).dispatch().await;
+        assert_eq!(response.status(), Status::NotAcceptable);
+
+        // Nothing is reachable only one way.
+        let paths: Vec<String> =
+            client.rocket().routes().map(|route| route.uri.path().to_string()).collect();
+        for path in &paths {
+            let twin = match path.strip_prefix("/api/v1/") {
+                Some(rest) => format!("/api/{}", rest),
+                None => path.replacen("/api/", "/api/v1/", 1),
+            };
+            assert!(paths.contains(&twin), "{} has no {}", path, twin);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_telemetry_session_cookie_is_issued_once() {
+        let (client, _test_db) = setup_test_client(create_standard_test_db().await).await;
+
+        let response = client.get("/api/version").dispatch().await;
+        let issued = response.cookies().get(SESSION_COOKIE).expect("cookie issued").clone();
+        assert_ne!(issued.http_only(), Some(true), "the SPA reads it");
+        assert!(uuid::Uuid::parse_str(issued.value()).is_ok());
+
+        // The tracked client sends it back, so it isn't issued again.
+        let response = client.get("/api/version").dispatch().await;
+        assert!(response.cookies().get(SESSION_COOKIE).is_none());
+
+        let response = client
+            .get("/api/version")
+            .cookie(Cookie::new(SESSION_COOKIE, "not a valid id!"))
+            .dispatch()
+            .await;
+        let replaced = response.cookies().get(SESSION_COOKIE).expect("cookie replaced");
+        assert_ne!(replaced.value(), issued.value());
+        assert!(uuid::Uuid::parse_str(replaced.value()).is_ok());
+    }
+
+    /// Every request counts as slow.
+    fn slow_figment() -> Result<Figment, ConfigError> {
+        Ok(test_config_figment()?.merge(("slow_request_threshold_ms", 0)))
+    }
+
+    #[rocket::async_test]
+    async fn test_slow_requests_are_flagged_on_the_exported_span() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _subscriber = tracing::subscriber::set_default(subscriber);
+
+        let test_db = TestDbBuilder::new().build().await.unwrap();
+        let config = AppConfig::from_figment(&slow_figment().unwrap()).unwrap();
+        let live_config = LiveConfig::new(config, slow_figment);
+        let rocket = init_rocket(test_db.pool.clone(), None, live_config).await;
+        let client = Client::tracked(rocket).await.unwrap();
+        let response = client.get("/api/version").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        drop(response);
+        drop(client);
+        provider.force_flush().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let attribute = |key: &str| {
+            spans
+                .iter()
+                .flat_map(|span| span.attributes.iter())
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("slow_request"), Some(AttributeValue::Bool(true)));
+        assert!(matches!(attribute("slow_request.duration_ms"), Some(AttributeValue::I64(_))));
+    }
+}
@@ -2,9 +2,12 @@
 mod tests {
     use crate::{
         db::{
-            add_tag_to_technique, create_tag, delete_tag, get_all_tags, get_tags_for_technique,
-            remove_tag_from_technique,
+            add_tag_to_technique, create_tag, delete_tag, get_all_tags, get_tag_by_name,
+            get_tag_subtree_ids, get_tag_tree, get_tags_for_technique, get_techniques_by_tag,
+            normalize_existing_tag_names, remove_tag_from_technique, rename_tag,
+            set_tag_archived, set_tag_parent,
         },
+        ids::TagId,
         test::test_utils::TestDbBuilder,
     };
 
@@ -29,7 +32,7 @@ mod tests {
             .expect("Failed to create tag");
 
         // Get all tags
-        let all_tags = get_all_tags(&test_db.pool)
+        let all_tags = get_all_tags(&test_db.pool, false)
             .await
             .expect("Failed to get all tags");
 
@@ -140,12 +143,49 @@ mod tests {
         assert_eq!(armbar_tags.len(), 0);
         assert_eq!(triangle_tags.len(), 0);
 
-        let all_tags = get_all_tags(&test_db.pool)
+        let all_tags = get_all_tags(&test_db.pool, false)
             .await
             .expect("Failed to get all tags");
         assert_eq!(all_tags.len(), 0);
     }
 
+    #[rocket::async_test]
+    async fn test_archived_tag_stays_on_techniques_but_leaves_the_list() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .build()
+            .await
+            .expect("Failed to build test database");
+
+        let armbar_id = test_db.technique_id("Armbar").expect("Technique not found");
+        let tag_id = create_tag(&test_db.pool, "Attack")
+            .await
+            .expect("Failed to create tag");
+        add_tag_to_technique(&test_db.pool, armbar_id, tag_id)
+            .await
+            .expect("Failed to add tag");
+
+        set_tag_archived(&test_db.pool, tag_id, true)
+            .await
+            .expect("Failed to archive tag");
+
+        let live = get_all_tags(&test_db.pool, false).await.unwrap();
+        assert!(live.is_empty());
+        let all = get_all_tags(&test_db.pool, true).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all[0].archived);
+
+        let armbar_tags = get_tags_for_technique(&test_db.pool, armbar_id).await.unwrap();
+        assert_eq!(armbar_tags.len(), 1);
+        assert!(armbar_tags[0].archived);
+
+        set_tag_archived(&test_db.pool, tag_id, false)
+            .await
+            .expect("Failed to unarchive tag");
+        assert_eq!(get_all_tags(&test_db.pool, false).await.unwrap().len(), 1);
+    }
+
     #[rocket::async_test]
     async fn test_duplicate_tag() {
         let test_db = TestDbBuilder::new()
@@ -194,4 +234,134 @@ mod tests {
             .expect("Failed to get technique tags");
         assert_eq!(technique_tags.len(), 1);
     }
+
+    #[rocket::async_test]
+    async fn test_tag_names_are_normalized_on_write() {
+        let test_db = TestDbBuilder::new()
+            .build()
+            .await
+            .expect("Failed to build test database");
+
+        let tag_id = create_tag(&test_db.pool, "  BJJ   basics ")
+            .await
+            .expect("Failed to create tag");
+
+        let tag = get_tag_by_name(&test_db.pool, "bjj BASICS")
+            .await
+            .expect("Failed to look up tag")
+            .expect("Tag not found by un-normalized name");
+        assert_eq!(tag.name, "BJJ basics", "Case is kept as typed");
+
+        assert!(
+            create_tag(&test_db.pool, "Bjj Basics").await.is_err(),
+            "A name differing only in case should hit the unique index"
+        );
+
+        rename_tag(&test_db.pool, tag_id, "x-guard")
+            .await
+            .expect("Failed to rename tag");
+        rename_tag(&test_db.pool, tag_id, "X-Guard")
+            .await
+            .expect("Failed to change the case of a tag's own name");
+        let tags = get_all_tags(&test_db.pool, false).await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "X-Guard");
+    }
+
+    #[rocket::async_test]
+    async fn test_backfill_merges_tags_that_normalize_to_the_same_name() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Kimura", "Description of kimura", Some("coach_user"))
+            .build()
+            .await
+            .expect("Failed to build test database");
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let kimura = test_db.technique_id("Kimura").unwrap();
+
+        // Pre-normalization data, written around create_tag. The unique
+        // index only sees these as distinct because of the whitespace.
+        let mut ids = Vec::new();
+        for name in ["Guard", " GUARD  ", "guard  ", "no  gi", "BJJ"] {
+            let res = sqlx::query("INSERT INTO tags (name) VALUES (?)")
+                .bind(name)
+                .execute(&test_db.pool)
+                .await
+                .unwrap();
+            ids.push(TagId(res.last_insert_rowid()));
+        }
+        add_tag_to_technique(&test_db.pool, armbar, ids[1]).await.unwrap();
+        add_tag_to_technique(&test_db.pool, kimura, ids[2]).await.unwrap();
+
+        let changed = normalize_existing_tag_names(&test_db.pool).await.unwrap();
+        assert_eq!(changed, 3);
+
+        let tags = get_all_tags(&test_db.pool, false).await.unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["BJJ", "Guard", "no gi"]);
+        // The tag already in canonical form keeps its id.
+        assert_eq!(tags[1].id, ids[0].0);
+
+        for technique in [armbar, kimura] {
+            let tags = get_tags_for_technique(&test_db.pool, technique).await.unwrap();
+            assert_eq!(tags.len(), 1);
+            assert_eq!(tags[0].name, "Guard");
+        }
+
+        let changed = normalize_existing_tag_names(&test_db.pool).await.unwrap();
+        assert_eq!(changed, 0, "Backfill should be idempotent");
+    }
+
+    #[rocket::async_test]
+    async fn test_nested_tags_filter_by_descendants_and_form_a_tree() {
+        let test_db = TestDbBuilder::new()
+            .coach("coach_user", Some("Coach User"))
+            .technique("Armbar", "Description of armbar", Some("coach_user"))
+            .technique("Hip Bump", "Description of hip bump", Some("coach_user"))
+            .technique("Kimura", "Description of kimura", Some("coach_user"))
+            .build()
+            .await
+            .expect("Failed to build test database");
+
+        let guard = create_tag(&test_db.pool, "Guard").await.unwrap();
+        let closed_guard = create_tag(&test_db.pool, "Closed Guard").await.unwrap();
+        let sweeps = create_tag(&test_db.pool, "Sweeps").await.unwrap();
+        let submission = create_tag(&test_db.pool, "Submission").await.unwrap();
+        set_tag_parent(&test_db.pool, closed_guard, Some(guard)).await.unwrap();
+        set_tag_parent(&test_db.pool, sweeps, Some(closed_guard)).await.unwrap();
+
+        let armbar = test_db.technique_id("Armbar").unwrap();
+        let hip_bump = test_db.technique_id("Hip Bump").unwrap();
+        let kimura = test_db.technique_id("Kimura").unwrap();
+        add_tag_to_technique(&test_db.pool, armbar, closed_guard).await.unwrap();
+        add_tag_to_technique(&test_db.pool, hip_bump, sweeps).await.unwrap();
+        add_tag_to_technique(&test_db.pool, kimura, submission).await.unwrap();
+
+        let names = |techniques: Vec<crate::models::Technique>| {
+            techniques.into_iter().map(|t| t.name).collect::<Vec<_>>()
+        };
+        let under_guard = get_techniques_by_tag(&test_db.pool, guard).await.unwrap();
+        assert_eq!(names(under_guard), ["Armbar", "Hip Bump"]);
+        let under_sweeps = get_techniques_by_tag(&test_db.pool, sweeps).await.unwrap();
+        assert_eq!(names(under_sweeps), ["Hip Bump"]);
+
+        let mut subtree = get_tag_subtree_ids(&test_db.pool, guard).await.unwrap();
+        subtree.sort();
+        assert_eq!(subtree, [guard, closed_guard, sweeps]);
+
+        let tree = get_tag_tree(&test_db.pool, false).await.unwrap();
+        let roots: Vec<&str> = tree.iter().map(|node| node.tag.name.as_str()).collect();
+        assert_eq!(roots, ["Guard", "Submission"]);
+        assert_eq!(tree[0].children[0].tag.name, "Closed Guard");
+        assert_eq!(tree[0].children[0].children[0].tag.name, "Sweeps");
+
+        assert!(set_tag_parent(&test_db.pool, sweeps, Some(TagId(999999))).await.is_err());
+
+        // Deleting a parent lifts its children to the top level.
+        delete_tag(&test_db.pool, guard).await.unwrap();
+        let tree = get_tag_tree(&test_db.pool, false).await.unwrap();
+        let roots: Vec<&str> = tree.iter().map(|node| node.tag.name.as_str()).collect();
+        assert_eq!(roots, ["Closed Guard", "Submission"]);
+    }
 }
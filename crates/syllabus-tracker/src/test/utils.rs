@@ -1,17 +1,20 @@
 #[cfg(test)]
 pub mod test_utils {
     use crate::auth::{Role, User};
+    use crate::config::{AppConfig, ConfigError, LiveConfig};
     use crate::db::{
         assign_technique_to_student, create_technique, create_user, get_student_technique,
-        update_student_technique,
+        replace_coach_students, seed_default_statuses, update_student_technique,
     };
     use crate::error::AppError;
+    use crate::ids::{StudentTechniqueId, TechniqueId, UserId};
     use crate::models::StudentTechnique;
     use crate::init_rocket;
     use crate::videos::media::test_support::{FakeMediaProbe, FakeMediaTranscode};
     use crate::videos::storage::test_support::InMemoryVideoStorage;
     use crate::videos::{DynMediaProbe, DynMediaTranscode, DynVideoStorage};
     use migration_engine::migrations::{migrate_database_declaratively, read_schema_file_to_string};
+    use rocket::figment::Figment;
     use rocket::http::{ContentType, Cookie};
     use rocket::local::asynchronous::Client;
     use serde_json::json;
@@ -157,9 +160,12 @@ pub mod test_utils {
             });
 
             migrate_database_declaratively(pool.clone(), schema, false).await?;
+            // Boot seeds the grading scale as a data migration, which the
+            // test database doesn't run.
+            seed_default_statuses(&pool).await?;
 
-            let mut user_id_map: HashMap<String, i64> = HashMap::new();
-            let mut technique_id_map: HashMap<String, i64> = HashMap::new();
+            let mut user_id_map: HashMap<String, UserId> = HashMap::new();
+            let mut technique_id_map: HashMap<String, TechniqueId> = HashMap::new();
 
             for user in &self.users {
                 let user_id = create_user(
@@ -174,6 +180,19 @@ pub mod test_utils {
                 user_id_map.insert(user.username.clone(), user_id);
             }
 
+            // Like a one-coach gym: every coach looks after every student.
+            // Tests about coach scope reassign with `replace_coach_students`.
+            let student_ids: Vec<UserId> = self
+                .users
+                .iter()
+                .filter(|u| matches!(u.role, Role::Student))
+                .map(|u| user_id_map[&u.username])
+                .collect();
+            for coach in self.users.iter().filter(|u| matches!(u.role, Role::Coach)) {
+                let coach_id = user_id_map[&coach.username];
+                replace_coach_students(&pool, coach_id, &student_ids, coach_id).await?;
+            }
+
             for technique in &self.techniques {
                 let coach_id = match &technique.coach_username {
                     Some(coach_name) => user_id_map.get(coach_name).copied(),
@@ -185,13 +204,17 @@ pub mod test_utils {
                 };
 
                 if let Some(coach_id) = coach_id {
-                    let technique_id =
-                        create_technique(&pool, &technique.name, &technique.description, coach_id)
-                            .await?;
+                    let technique_id = create_technique(
+                        &pool,
+                        &technique.name,
+                        &technique.description,
+                        coach_id,
+                    )
+                    .await?;
 
                     technique_id_map.insert(technique.name.clone(), technique_id);
                 } else if !self.users.is_empty() {
-                    let first_user_id = user_id_map.values().next().copied().unwrap_or(1);
+                    let first_user_id = user_id_map.values().next().copied().unwrap_or(UserId(1));
                     let technique_id = create_technique(
                         &pool,
                         &technique.name,
@@ -209,7 +232,7 @@ pub mod test_utils {
                 .iter()
                 .find(|u| matches!(u.role, Role::Coach | Role::Admin))
                 .and_then(|u| user_id_map.get(&u.username).copied())
-                .unwrap_or(0);
+                .unwrap_or(UserId(0));
 
             for st in &self.student_techniques {
                 let student_id = match &st.student_username {
@@ -233,9 +256,14 @@ pub mod test_utils {
                 };
 
                 if let (Some(s_id), Some(t_id)) = (student_id, technique_id) {
-                    let assignment_id =
-                        assign_technique_to_student(&pool, t_id, s_id, None, seed_coach_id)
-                            .await?;
+                    let assignment_id = assign_technique_to_student(
+                        &pool,
+                        t_id,
+                        s_id,
+                        None,
+                        seed_coach_id,
+                    )
+                    .await?;
 
                     if st.status != "red"
                         || !st.student_notes.is_empty()
@@ -261,12 +289,16 @@ pub mod test_utils {
                             amber_count: None,
                             green_count: None,
                             has_unseen_activity: None,
+                            review_requests: None,
                             last_student_initiative_at: None,
                             last_watch_at: None,
                             last_watch_video_title: None,
+                            timezone: None,
+                            membership_status: None,
+                            avatar_attachment_id: None,
                         };
                         update_student_technique(
-                            &pool,
+                            &mut pool.acquire().await.unwrap(),
                             assignment_id,
                             &seed_actor,
                             &st.status,
@@ -289,21 +321,21 @@ pub mod test_utils {
     #[derive(Debug)]
     pub struct TestDb {
         pub pool: Pool<Sqlite>,
-        pub user_id_map: HashMap<String, i64>,
-        pub technique_id_map: HashMap<String, i64>,
+        pub user_id_map: HashMap<String, UserId>,
+        pub technique_id_map: HashMap<String, TechniqueId>,
     }
 
     #[derive(sqlx::FromRow)]
     struct IdRow {
-        id: i64,
+        id: StudentTechniqueId,
     }
 
     impl TestDb {
-        pub fn user_id(&self, username: &str) -> Option<i64> {
+        pub fn user_id(&self, username: &str) -> Option<UserId> {
             self.user_id_map.get(username).copied()
         }
 
-        pub fn technique_id(&self, name: &str) -> Option<i64> {
+        pub fn technique_id(&self, name: &str) -> Option<TechniqueId> {
             self.technique_id_map.get(name).copied()
         }
 
@@ -311,7 +343,7 @@ pub mod test_utils {
             &self,
             student_username: &str,
             technique_name: &str,
-        ) -> Result<i64, sqlx::Error> {
+        ) -> Result<StudentTechniqueId, sqlx::Error> {
             let student_id = self
                 .user_id(student_username)
                 .ok_or_else(|| sqlx::Error::RowNotFound)?;
@@ -336,7 +368,7 @@ pub mod test_utils {
         pub async fn student_technique_ids(
             &self,
             student_username: &str,
-        ) -> Result<Vec<i64>, sqlx::Error> {
+        ) -> Result<Vec<StudentTechniqueId>, sqlx::Error> {
             let student_id = self
                 .user_id(student_username)
                 .ok_or_else(|| sqlx::Error::RowNotFound)?;
@@ -355,7 +387,7 @@ pub mod test_utils {
         pub async fn first_student_technique_id(
             &self,
             student_username: &str,
-        ) -> Result<i64, sqlx::Error> {
+        ) -> Result<StudentTechniqueId, sqlx::Error> {
             let student_id = self
                 .user_id(student_username)
                 .ok_or_else(|| sqlx::Error::RowNotFound)?;
@@ -371,10 +403,13 @@ pub mod test_utils {
         }
 
         #[allow(dead_code)]
-        pub async fn get_student_technique(&self, id: i64) -> Result<StudentTechnique, AppError> {
+        pub async fn get_student_technique(
+            &self,
+            id: StudentTechniqueId,
+        ) -> Result<StudentTechnique, AppError> {
             // Test callers that don't care about the viewer-relative
             // `viewer_seen_at` field; pass 0 so the LEFT JOIN never matches.
-            get_student_technique(&self.pool, id, 0).await
+            get_student_technique(&self.pool, id, UserId(0)).await
         }
     }
 
@@ -399,6 +434,20 @@ pub mod test_utils {
         response.cookies().iter().cloned().collect::<Vec<_>>()
     }
 
+    /// Config source for test clients: the required keys, defaults otherwise.
+    pub fn test_config_figment() -> Result<Figment, ConfigError> {
+        let schema_path = dotenvy::var("SCHEMA_PATH").expect("SCHEMA_PATH not set");
+        Ok(Figment::new()
+            .merge(("database_url", "sqlite::memory:"))
+            .merge(("schema_path", schema_path))
+            .merge(("attachment_dir", std::env::temp_dir().join("syllabus-test-attachments"))))
+    }
+
+    pub fn test_live_config() -> LiveConfig {
+        let config = AppConfig::from_figment(&test_config_figment().unwrap()).unwrap();
+        LiveConfig::new(config, test_config_figment)
+    }
+
     pub async fn setup_test_client(test_db: TestDb) -> (Client, TestDb) {
         setup_test_client_with(test_db, true).await
     }
@@ -422,7 +471,7 @@ pub mod test_utils {
         } else {
             None
         };
-        let rocket = init_rocket(test_db.pool.clone(), stack).await;
+        let rocket = init_rocket(test_db.pool.clone(), stack, test_live_config()).await;
 
         let client = Client::tracked(rocket)
             .await
@@ -55,7 +55,7 @@ mod tests {
     }
 
     async fn first_technique_id(db: &TestDb) -> i64 {
-        db.technique_id("Armbar").expect("Armbar technique seeded")
+        db.technique_id("Armbar").expect("Armbar technique seeded").0
     }
 
     async fn poll_status_until_ready(client: &Client, video_id: i64) -> String {
@@ -0,0 +1,134 @@
+//! One transaction per request, for handlers that make several writes. The
+//! `Tx` guard begins it; `TransactionFairing` commits it once the handler
+//! has answered with a success or redirect, and rolls it back on any other
+//! status, so an error part-way through leaves nothing half-written.
+//!
+//! SQLite has a single writer, so a handler holding a `Tx` makes all of its
+//! writes through it: a write through the pool would wait on the
+//! transaction's own lock until `busy_timeout` gives up. Reads through the
+//! pool are fine but don't see the uncommitted writes. The transaction is
+//! deferred, so list `Tx` after guards that write (like `User`'s session
+//! refresh).
+//!
+//! Side effects others can see, like live-feed events, are queued with
+//! `Tx::after_commit` so they only happen once the writes are visible, and
+//! never for a request that is rolled back.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use rocket::Response;
+use sqlx::{Pool, Sqlite, SqliteConnection, Transaction};
+use tracing::{error, warn};
+
+use crate::error::AppError;
+
+type AfterCommit = Box<dyn FnOnce() + Send>;
+
+struct Shared {
+    tx: Mutex<Option<Transaction<'static, Sqlite>>>,
+    /// Separate from `tx`, so it can be queued to while the connection is
+    /// borrowed.
+    after_commit: std::sync::Mutex<Vec<AfterCommit>>,
+}
+
+type Slot = Arc<Shared>;
+
+/// The request's transaction, cached so every `Tx` guard in a request
+/// shares it and the fairing can find it.
+struct RequestTx(Option<Slot>);
+
+#[derive(Clone)]
+pub struct Tx(Slot);
+
+impl Tx {
+    /// The transaction's connection, locked until the guard is dropped.
+    pub async fn conn(&self) -> Result<MappedMutexGuard<'_, SqliteConnection>, AppError> {
+        MutexGuard::try_map(self.0.tx.lock().await, |tx| tx.as_deref_mut())
+            .map_err(|_| AppError::Internal("Request transaction already finished".to_string()))
+    }
+
+    /// Runs `f` after the transaction commits. Dropped if it rolls back.
+    pub fn after_commit(&self, f: impl FnOnce() + Send + 'static) {
+        let mut queue = self.0.after_commit.lock().unwrap_or_else(|e| e.into_inner());
+        queue.push(Box::new(f));
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Tx {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cached = request
+            .local_cache_async(async {
+                let Some(pool) = request.rocket().state::<Pool<Sqlite>>() else {
+                    error!("Database pool not found in managed state");
+                    return RequestTx(None);
+                };
+                match pool.begin().await {
+                    Ok(tx) => RequestTx(Some(Arc::new(Shared {
+                        tx: Mutex::new(Some(tx)),
+                        after_commit: Default::default(),
+                    }))),
+                    Err(e) => {
+                        error!(error = %e, "Failed to begin request transaction");
+                        RequestTx(None)
+                    }
+                }
+            })
+            .await;
+        match cached {
+            RequestTx(Some(slot)) => Outcome::Success(Tx(slot.clone())),
+            RequestTx(None) => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+/// Ends the transaction a `Tx` guard began. Without it attached the
+/// transaction is dropped, and so rolled back, at the end of every request.
+#[derive(Debug)]
+pub struct TransactionFairing;
+
+#[rocket::async_trait]
+impl Fairing for TransactionFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request transactions",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let RequestTx(Some(slot)) = request.local_cache(|| RequestTx(None)) else {
+            return;
+        };
+        let Some(tx) = slot.tx.lock().await.take() else {
+            return;
+        };
+        let after_commit =
+            std::mem::take(&mut *slot.after_commit.lock().unwrap_or_else(|e| e.into_inner()));
+
+        let class = response.status().class();
+        if class.is_success() || class.is_redirection() {
+            if let Err(e) = tx.commit().await {
+                // The handler already reported success; don't let that stand.
+                error!(error = %e, "Failed to commit request transaction");
+                let body = r#"{"error":"Internal Server Error","status":500}"#;
+                response.set_status(Status::InternalServerError);
+                response.set_header(ContentType::JSON);
+                response.set_sized_body(body.len(), Cursor::new(body));
+                return;
+            }
+            for f in after_commit {
+                f();
+            }
+        } else if let Err(e) = tx.rollback().await {
+            warn!(error = %e, "Failed to roll back request transaction");
+        }
+    }
+}
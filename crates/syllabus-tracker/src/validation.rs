@@ -1,78 +1,199 @@
+use crate::config::ConfigError;
 use crate::error::AppError;
+use crate::i18n::{Locale, translate};
+use rocket::figment::Figment;
+use rocket::figment::providers::Env;
 use rocket::http::Status;
 use rocket::response::status::Custom;
 use rocket::serde::json::Json;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use tracing::{error, instrument, warn};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
+use validator::{ValidationError, ValidationErrors};
 
+/// Machine-readable form of one field error, so the SPA can branch on
+/// `code` and render `params` (e.g. `min`, `max`) itself instead of
+/// matching on message text. Codes are the `crate::i18n` catalog keys.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FieldErrorDetail {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub params: HashMap<String, Value>,
+}
+
+/// `errors` keeps the flat field-to-messages shape older clients read;
+/// `details` carries the same errors, in the same order, with codes.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ValidationResponse {
     pub status: &'static str,
     pub errors: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub details: HashMap<String, Vec<FieldErrorDetail>>,
 }
 
 impl ValidationResponse {
-    pub fn new(errors: HashMap<String, Vec<String>>) -> Self {
+    pub fn new(details: HashMap<String, Vec<FieldErrorDetail>>) -> Self {
+        let errors = details
+            .iter()
+            .map(|(field, entries)| {
+                let messages = entries.iter().map(|e| e.message.clone()).collect();
+                (field.clone(), messages)
+            })
+            .collect();
         Self {
             status: "error",
             errors,
+            details,
         }
     }
 
-    pub fn with_error(field: &str, message: &str) -> Self {
-        let mut errors = HashMap::new();
-        errors.insert(field.to_string(), vec![message.to_string()]);
-        Self::new(errors)
+    pub fn with_error(field: &str, code: &str, message: &str) -> Self {
+        let detail = FieldErrorDetail {
+            code: code.to_string(),
+            message: message.to_string(),
+            params: HashMap::new(),
+        };
+        Self::new(HashMap::from([(field.to_string(), vec![detail])]))
     }
 }
 
-pub trait ToValidationResponse {
-    fn to_validation_response(self) -> Custom<Json<ValidationResponse>>;
+/// Message for one field error in `locale`: the catalog entry for its code
+/// if there is one, else the English message it was raised with.
+fn localized_message(error: &ValidationError, locale: Locale) -> String {
+    translate(locale, &error.code, &error.params)
+        .or_else(|| error.message.as_ref().map(|m| m.to_string()))
+        .or_else(|| translate(locale, "validation.invalid", &HashMap::new()))
+        .unwrap_or_else(|| "Invalid value".to_string())
+}
+
+/// Shared by every path that turns `ValidationErrors` into a 422 body.
+pub fn validation_errors_response(
+    errors: &ValidationErrors,
+    locale: Locale,
+) -> Custom<Json<ValidationResponse>> {
+    let details = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, field_errors)| {
+            let entries = field_errors
+                .iter()
+                .map(|error| FieldErrorDetail {
+                    code: error.code.to_string(),
+                    message: localized_message(error, locale),
+                    // Built-in validators echo the submitted input as `value`;
+                    // never send that back (it may be a password).
+                    params: error
+                        .params
+                        .iter()
+                        .filter(|(name, _)| *name != "value")
+                        .map(|(name, value)| (name.to_string(), value.clone()))
+                        .collect(),
+                })
+                .collect();
+            (field.to_string(), entries)
+        })
+        .collect();
+
+    Custom(
+        Status::UnprocessableEntity,
+        Json(ValidationResponse::new(details)),
+    )
+}
+
+/// Message with an optional `{detail}` param, translated when the catalog
+/// has `key`.
+fn localized(locale: Locale, key: &str, english: String, detail: Option<&str>) -> String {
+    let mut params = HashMap::new();
+    if let Some(detail) = detail {
+        params.insert(Cow::Borrowed("detail"), Value::String(detail.to_string()));
+    }
+    translate(locale, key, &params).unwrap_or(english)
+}
+
+pub trait ToValidationResponse: Sized {
+    fn to_localized_validation_response(self, locale: Locale) -> Custom<Json<ValidationResponse>>;
+
+    fn to_validation_response(self) -> Custom<Json<ValidationResponse>> {
+        self.to_localized_validation_response(Locale::default())
+    }
 }
 
 impl ToValidationResponse for AppError {
     #[instrument]
-    fn to_validation_response(self) -> Custom<Json<ValidationResponse>> {
+    fn to_localized_validation_response(self, locale: Locale) -> Custom<Json<ValidationResponse>> {
         self.log_and_record("API Validation Error");
         let status = self.status_code();
 
-        let (field, message) = match &self {
-            AppError::Database(db_err) => ("database", format!("Database error: {}", db_err)),
+        let (field, code, english, detail) = match &self {
+            AppError::Database(db_err) => {
+                let detail = db_err.to_string();
+                let english = format!("Database error: {}", detail);
+                ("database", "error.database", english, Some(detail))
+            }
             AppError::Authentication(msg) => {
-                ("authentication", format!("Authentication error: {}", msg))
+                let english = format!("Authentication error: {}", msg);
+                ("authentication", "error.authentication", english, Some(msg.clone()))
             }
             AppError::Authorization(msg) => {
-                ("authorization", format!("Permission denied: {}", msg))
+                let english = format!("Permission denied: {}", msg);
+                ("authorization", "error.authorization", english, Some(msg.clone()))
+            }
+            AppError::NotFound(msg) => {
+                let english = format!("Not found: {}", msg);
+                ("resource", "error.resource_not_found", english, Some(msg.clone()))
+            }
+            AppError::ExternalService(msg) => {
+                let english = format!("Service error: {}", msg);
+                ("service", "error.service", english, Some(msg.clone()))
+            }
+            AppError::Internal(_) => {
+                let english = "Internal server error".to_string();
+                ("server", "error.internal", english, None)
             }
-            AppError::NotFound(msg) => ("resource", format!("Not found: {}", msg)),
-            AppError::ExternalService(msg) => ("service", format!("Service error: {}", msg)),
-            AppError::Internal(_) => ("server", "Internal server error".to_string()),
         };
 
+        let message = localized(locale, code, english, detail.as_deref());
         Custom(
             status,
-            Json(ValidationResponse::with_error(field, &message)),
+            Json(ValidationResponse::with_error(field, code, &message)),
         )
     }
 }
 
 impl ToValidationResponse for Status {
     #[instrument]
-    fn to_validation_response(self) -> Custom<Json<ValidationResponse>> {
-        let (field, message) = match self {
+    fn to_localized_validation_response(self, locale: Locale) -> Custom<Json<ValidationResponse>> {
+        let (field, key, english) = match self {
             Status::Forbidden => (
                 "permission",
+                "error.permission",
                 "You don't have permission to perform this action",
             ),
-            Status::Unauthorized => ("authentication", "Authentication required"),
-            Status::NotFound => ("resource", "Resource not found"),
-            Status::Conflict => ("resource", "Resource already exists"),
-            Status::BadRequest => ("request", "Bad request"),
-            Status::UnprocessableEntity => ("validation", "Validation failed"),
-            Status::InternalServerError => ("server", "Internal server error"),
-            Status::ServiceUnavailable => ("service", "Service unavailable"),
-            _ => ("error", "An error occurred"),
+            Status::Unauthorized => (
+                "authentication",
+                "error.authentication_required",
+                "Authentication required",
+            ),
+            Status::NotFound => ("resource", "error.not_found", "Resource not found"),
+            Status::Conflict => ("resource", "error.conflict", "Resource already exists"),
+            Status::BadRequest => ("request", "error.bad_request", "Bad request"),
+            Status::PayloadTooLarge => (
+                "request",
+                "error.payload_too_large",
+                "Request body exceeded the configured limit",
+            ),
+            Status::UnprocessableEntity => {
+                ("validation", "error.validation_failed", "Validation failed")
+            }
+            Status::InternalServerError => ("server", "error.internal", "Internal server error"),
+            Status::ServiceUnavailable => ("service", "error.unavailable", "Service unavailable"),
+            _ => ("error", "error.generic", "An error occurred"),
         };
 
         // Surface bare-status returns at log level so they don't vanish silently.
@@ -84,7 +205,8 @@ impl ToValidationResponse for Status {
             warn!(status = %self, field, "API returned bare error status");
         }
 
-        Custom(self, Json(ValidationResponse::with_error(field, message)))
+        let message = localized(locale, key, english.to_string(), None);
+        Custom(self, Json(ValidationResponse::with_error(field, key, &message)))
     }
 }
 
@@ -95,33 +217,455 @@ pub struct AppErrorWrapper(pub AppError);
 impl From<ValidationErrorWrapper> for Custom<Json<ValidationResponse>> {
     #[instrument]
     fn from(wrapper: ValidationErrorWrapper) -> Self {
-        let errors = wrapper.0;
-        let mut error_map = HashMap::new();
+        validation_errors_response(&wrapper.0, Locale::default())
+    }
+}
 
-        for (field, field_errors) in errors.field_errors() {
-            let error_messages: Vec<String> = field_errors
-                .iter()
-                .map(|error| {
-                    error
-                        .message
-                        .clone()
-                        .unwrap_or_else(|| "Invalid value".into())
-                        .to_string()
-                })
-                .collect();
+impl From<AppErrorWrapper> for Custom<Json<ValidationResponse>> {
+    fn from(wrapper: AppErrorWrapper) -> Self {
+        wrapper.0.to_validation_response()
+    }
+}
 
-            error_map.insert(field.to_string(), error_messages);
+// ---- Field limits ----
+
+/// Field length policy for request validation. One instance is managed as
+/// Rocket state and passed to `validate_with_args`, so changing a limit here
+/// (or via its `VALIDATION_*` env var, read into `AppConfig::validation`)
+/// changes it for every request that carries that field. Lengths count
+/// characters; usernames count graphemes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    pub username_min: usize,
+    pub username_max: usize,
+    pub password_min: usize,
+    pub display_name_max: usize,
+    pub person_name_max: usize,
+    pub technique_name_max: usize,
+    pub tag_name_max: usize,
+    pub note_max: usize,
+    pub description_max: usize,
+    pub technique_notes_max: usize,
+    /// Serialized size of a user's UI preferences object, in bytes.
+    pub preferences_max_bytes: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            username_min: 3,
+            username_max: 50,
+            password_min: 5,
+            display_name_max: 100,
+            person_name_max: 50,
+            technique_name_max: 100,
+            tag_name_max: 50,
+            note_max: 2000,
+            description_max: 5000,
+            technique_notes_max: 10_000,
+            preferences_max_bytes: 16_384,
         }
+    }
+}
 
-        Custom(
-            Status::UnprocessableEntity,
-            Json(ValidationResponse::new(error_map)),
-        )
+impl ValidationConfig {
+    /// Defaults, with any `VALIDATION_<FIELD>` env var (for example
+    /// `VALIDATION_PASSWORD_MIN`) taking precedence. The server gets these
+    /// through `AppConfig`; this is for the command-line tools.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let config: Self = Figment::from(Env::prefixed("VALIDATION_"))
+            .extract()
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        config.check().map_err(ConfigError::Invalid)?;
+        Ok(config)
+    }
+
+    /// Each limit by field name, e.g. `("password_min", 5)`.
+    pub fn limits(&self) -> [(&'static str, usize); 11] {
+        [
+            ("username_min", self.username_min),
+            ("username_max", self.username_max),
+            ("password_min", self.password_min),
+            ("display_name_max", self.display_name_max),
+            ("person_name_max", self.person_name_max),
+            ("technique_name_max", self.technique_name_max),
+            ("tag_name_max", self.tag_name_max),
+            ("note_max", self.note_max),
+            ("description_max", self.description_max),
+            ("technique_notes_max", self.technique_notes_max),
+            ("preferences_max_bytes", self.preferences_max_bytes),
+        ]
+    }
+
+    /// Every maximum must allow at least one character, and the username
+    /// range must not be empty.
+    pub fn check(&self) -> Result<(), String> {
+        if let Some((field, _)) =
+            self.limits().into_iter().find(|(field, limit)| field.ends_with("_max") && *limit < 1)
+        {
+            return Err(format!("VALIDATION_{} must be at least 1", field.to_ascii_uppercase()));
+        }
+        if self.username_min > self.username_max {
+            return Err(format!(
+                "VALIDATION_USERNAME_MIN ({}) is above VALIDATION_USERNAME_MAX ({})",
+                self.username_min, self.username_max
+            ));
+        }
+        Ok(())
     }
 }
 
-impl From<AppErrorWrapper> for Custom<Json<ValidationResponse>> {
-    fn from(wrapper: AppErrorWrapper) -> Self {
-        wrapper.0.to_validation_response()
+/// Error carrying a catalog code (see `crate::i18n`), the English message,
+/// and the params its translations interpolate.
+fn coded_error(
+    code: &'static str,
+    message: String,
+    params: &[(&'static str, usize)],
+) -> ValidationError {
+    let mut error = ValidationError::new(code).with_message(message.into());
+    for (name, value) in params {
+        error.add_param(Cow::Borrowed(*name), value);
+    }
+    error
+}
+
+pub fn validate_password(password: &str, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if password.chars().count() < config.password_min {
+        return Err(coded_error(
+            "password.too_short",
+            format!("Password must be at least {} characters long", config.password_min),
+            &[("min", config.password_min)],
+        ));
     }
+    Ok(())
+}
+
+/// Blank names are refused rather than stored: a client that wants the
+/// username shown instead sends `null` (see `User::effective_display_name`).
+pub fn validate_display_name(name: &str, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(coded_error(
+            "display_name.required",
+            "Display name cannot be blank; send null to show the username instead".to_string(),
+            &[],
+        ));
+    }
+    if name.chars().count() > config.display_name_max {
+        return Err(coded_error(
+            "display_name.too_long",
+            format!("Display name must be under {} characters", config.display_name_max),
+            &[("max", config.display_name_max)],
+        ));
+    }
+    Ok(())
+}
+
+/// First and last names.
+pub fn validate_person_name(name: &str, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if name.chars().count() > config.person_name_max {
+        return Err(coded_error(
+            "name.too_long",
+            format!("Name must be under {} characters", config.person_name_max),
+            &[("max", config.person_name_max)],
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_technique_name(
+    name: &str,
+    config: &ValidationConfig,
+) -> Result<(), ValidationError> {
+    let length = name.chars().count();
+    if length == 0 || length > config.technique_name_max {
+        return Err(coded_error(
+            "technique_name.length",
+            format!(
+                "Technique name must be between 1 and {} characters",
+                config.technique_name_max
+            ),
+            &[("max", config.technique_name_max)],
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_tag_name(name: &str, config: &ValidationConfig) -> Result<(), ValidationError> {
+    let length = name.chars().count();
+    if length == 0 || length > config.tag_name_max {
+        return Err(coded_error(
+            "tag_name.length",
+            format!("Tag name must be between 1 and {} characters", config.tag_name_max),
+            &[("max", config.tag_name_max)],
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_note(note: &str, config: &ValidationConfig) -> Result<(), ValidationError> {
+    if note.chars().count() > config.note_max {
+        return Err(coded_error(
+            "note.too_long",
+            format!("Note must be under {} characters", config.note_max),
+            &[("max", config.note_max)],
+        ));
+    }
+    Ok(())
+}
+
+/// IANA zone names only (`Europe/Lisbon`, `UTC`); abbreviations like `EST`
+/// are ambiguous and fixed offsets don't follow daylight saving.
+pub fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    if timezone.parse::<chrono_tz::Tz>().is_err() {
+        return Err(coded_error(
+            "timezone.invalid",
+            format!("Unknown timezone '{}'", timezone),
+            &[],
+        ));
+    }
+    Ok(())
+}
+
+/// `#rrggbb`, the one form every client can render without parsing CSS.
+pub fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let valid = color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(coded_error(
+            "color.invalid",
+            format!("'{}' is not a #rrggbb colour", color),
+            &[],
+        ));
+    }
+    Ok(())
+}
+
+/// Class sessions run from a minute up to a full day.
+pub fn validate_class_duration(minutes: i64) -> Result<(), ValidationError> {
+    const MAX_MINUTES: usize = 24 * 60;
+    if !(1..=MAX_MINUTES as i64).contains(&minutes) {
+        return Err(coded_error(
+            "duration.invalid",
+            format!("Duration must be between 1 and {} minutes", MAX_MINUTES),
+            &[("max", MAX_MINUTES)],
+        ));
+    }
+    Ok(())
+}
+
+/// Links to technique media. Only http(s), so a stored link can't run
+/// script when the SPA renders it as an `href`.
+pub fn validate_media_url(url: &str) -> Result<(), ValidationError> {
+    let valid = ["https://", "http://"].iter().any(|scheme| {
+        url.get(..scheme.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+            && url.len() > scheme.len()
+    });
+    if !valid || url.chars().any(char::is_whitespace) {
+        return Err(coded_error(
+            "url.invalid",
+            format!("'{}' is not an http(s) link", url),
+            &[],
+        ));
+    }
+    Ok(())
+}
+
+/// Technique and collection descriptions.
+pub fn validate_description(
+    description: &str,
+    config: &ValidationConfig,
+) -> Result<(), ValidationError> {
+    if description.chars().count() > config.description_max {
+        return Err(coded_error(
+            "description.too_long",
+            format!("Description must be under {} characters", config.description_max),
+            &[("max", config.description_max)],
+        ));
+    }
+    Ok(())
+}
+
+/// Student and coach notes on an assigned technique. Longer than attempt
+/// notes (`validate_note`) because they accumulate over months.
+pub fn validate_technique_notes(
+    notes: &str,
+    config: &ValidationConfig,
+) -> Result<(), ValidationError> {
+    if notes.chars().count() > config.technique_notes_max {
+        return Err(coded_error(
+            "notes.too_long",
+            format!("Notes must be under {} characters", config.technique_notes_max),
+            &[("max", config.technique_notes_max)],
+        ));
+    }
+    Ok(())
+}
+
+/// UI preferences are opaque to the server, so the only limit is size,
+/// measured on the JSON as it will be stored.
+pub fn validate_preferences(
+    preferences: &serde_json::Map<String, serde_json::Value>,
+    config: &ValidationConfig,
+) -> Result<(), ValidationError> {
+    let size = serde_json::to_string(preferences).map_or(usize::MAX, |json| json.len());
+    if size > config.preferences_max_bytes {
+        return Err(coded_error(
+            "preferences.too_large",
+            format!("Preferences must be under {} bytes", config.preferences_max_bytes),
+            &[("max", config.preferences_max_bytes)],
+        ));
+    }
+    Ok(())
+}
+
+// ---- Usernames ----
+
+/// Canonical form of a username: trimmed and NFC-normalised. Without this,
+/// "José" typed with a precomposed "é" and with "e" plus a combining accent
+/// are two different accounts that render identically.
+pub fn normalize_username(raw: &str) -> String {
+    raw.trim().nfc().collect()
+}
+
+/// Letters and digits from any script, combining marks (for scripts that
+/// don't compose under NFC), and `.`, `_`, `-`. No whitespace, symbols or
+/// invisible format characters.
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c) || matches!(c, '.' | '_' | '-')
+}
+
+/// Validator for already-normalised usernames. Used by every request that
+/// sets a username so registration, admin edit, profile update and invite
+/// claim all accept the same set. Length is counted in graphemes (what the
+/// user sees as one character) rather than bytes or code points.
+pub fn validate_username(
+    username: &str,
+    config: &ValidationConfig,
+) -> Result<(), ValidationError> {
+    let length = username.graphemes(true).count();
+    if !(config.username_min..=config.username_max).contains(&length) {
+        return Err(coded_error(
+            "username.length",
+            format!(
+                "Username must be between {} and {} characters",
+                config.username_min, config.username_max
+            ),
+            &[("min", config.username_min), ("max", config.username_max)],
+        ));
+    }
+
+    if !username.chars().all(is_username_char) {
+        return Err(coded_error(
+            "username.invalid_chars",
+            "Username can only contain letters, numbers, '.', '_' and '-'".to_string(),
+            &[],
+        ));
+    }
+
+    Ok(())
+}
+
+/// `deserialize_with` hook that normalises a username as it is read, so
+/// validation, the uniqueness lookup and the stored value all see the same
+/// string.
+pub fn deserialize_username<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|raw| normalize_username(&raw))
+}
+
+/// As [`deserialize_username`], for optional fields. Pair with
+/// `#[serde(default)]` so a missing field still reads as `None`.
+pub fn deserialize_optional_username<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(|raw| raw.as_deref().map(normalize_username))
+}
+
+// ---- Tags ----
+
+/// Canonical form of a tag name: trimmed, with runs of whitespace collapsed
+/// to a single space, so "  half   guard " is stored as "half guard". Case
+/// is kept as typed ("BJJ" stays "BJJ"); the `COLLATE NOCASE` unique index
+/// on `tags.name` is what stops "Guard" and "guard" both existing.
+pub fn normalize_tag_name(raw: &str) -> String {
+    raw.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `deserialize_with` hook that normalises a tag name before validation.
+pub fn deserialize_tag_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|raw| normalize_tag_name(&sanitize_plain_text(&raw)))
+}
+
+// ---- Free text ----
+
+/// Removes HTML from user-entered text so nothing stored can become markup
+/// if a client ever renders it as HTML. Tags are dropped (`<script>` and
+/// `<style>` along with their contents) and the text between them kept;
+/// entities are decoded back so the result is plain text, which the SPA
+/// escapes on render. Text with no `<` is returned unchanged.
+///
+/// Repeats until stable, so `&lt;b&gt;` typed literally cannot decode into
+/// a tag that a later pass (see `sanitize_stored_text`) would strip.
+pub fn sanitize_plain_text(raw: &str) -> String {
+    let mut text = raw.to_string();
+    while text.contains('<') {
+        let cleaned = ammonia::Builder::empty()
+            .clean(&text)
+            .to_string()
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&nbsp;", "\u{a0}")
+            .replace("&amp;", "&");
+        if cleaned == text {
+            break;
+        }
+        text = cleaned;
+    }
+    text
+}
+
+/// `deserialize_with` hook for notes, names and descriptions.
+pub fn deserialize_plain_text<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|raw| sanitize_plain_text(&raw))
+}
+
+/// Optional variant of `deserialize_plain_text`. Pair with `#[serde(default)]`
+/// so a missing field still deserializes to `None`.
+pub fn deserialize_optional_plain_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(|raw| raw.map(|s| sanitize_plain_text(&s)))
+}
+
+/// For fields where `null` means "clear" and a missing field means "leave
+/// alone": missing is `None` (via `#[serde(default)]`), `null` is
+/// `Some(None)`, and a string is `Some(Some(..))`, sanitized.
+pub fn deserialize_nullable_plain_text<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_optional_plain_text(deserializer).map(Some)
+}
+
+/// `deserialize_nullable_plain_text` for values that need no sanitizing.
+pub fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
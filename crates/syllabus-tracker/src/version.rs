@@ -0,0 +1,44 @@
+//! `GET /api/version`: which build is serving. Public, so the SPA can poll it
+//! and offer a reload once a deploy has replaced the backend it loaded with.
+
+use chrono::DateTime;
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::models::to_rfc3339_utc;
+use crate::versioning::API_VERSIONS;
+
+/// Commit the binary was built from (see `build.rs`). `None` when built
+/// outside a git checkout without `GIT_SHA` set.
+pub const GIT_SHA: Option<&str> = option_env!("GIT_SHA");
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VersionInfo {
+    pub version: String,
+    pub commit: Option<String>,
+    /// RFC 3339, UTC.
+    pub built_at: Option<String>,
+    /// Which `/api/v<n>` this build serves (see `crate::versioning`).
+    pub api_versions: Vec<u32>,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        let built_at = env!("BUILD_UNIX_TIME")
+            .parse()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(to_rfc3339_utc);
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: GIT_SHA.map(str::to_string),
+            built_at,
+            api_versions: API_VERSIONS.to_vec(),
+        }
+    }
+}
+
+#[get("/version")]
+pub fn api_version() -> Json<VersionInfo> {
+    Json(VersionInfo::current())
+}
@@ -0,0 +1,131 @@
+//! API versions. Every route is mounted under `/api/v1`, and again under
+//! bare `/api` as a compatibility shim for the deployed SPA, which calls
+//! unversioned paths. Bare `/api` keeps serving version 1 after a version 2
+//! exists, so a breaking change to a response shape goes into a new version
+//! instead of breaking clients that haven't been updated.
+//!
+//! A client that can't change its paths sends `Api-Version: <n>` on a bare
+//! path instead. Every `/api` response names the version that served it in
+//! the same header, and asking for a version this build doesn't serve is a
+//! 406 that lists the ones it does.
+
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{ContentType, Header, Status};
+use rocket::{Build, Data, Request, Response, Rocket, Route};
+use serde_json::json;
+use tracing::{error, warn};
+
+/// Versions this build serves, oldest first.
+pub const API_VERSIONS: &[u32] = &[1];
+
+/// What bare `/api` paths serve when the request doesn't say.
+pub const UNVERSIONED_API_VERSION: u32 = 1;
+
+pub const API_VERSION_HEADER: &str = "Api-Version";
+
+pub trait MountApi {
+    /// Mounts version 1 routes under `/api/v1` and bare `/api`. A later
+    /// version's routes get a mount of their own under `/api/v<n>` only.
+    fn mount_api(self, routes: Vec<Route>) -> Self;
+}
+
+impl MountApi for Rocket<Build> {
+    fn mount_api(self, routes: Vec<Route>) -> Self {
+        self.mount("/api/v1", routes.clone()).mount("/api", routes)
+    }
+}
+
+/// What `ApiVersionFairing` settled on for a request.
+#[derive(Debug, Clone, Copy)]
+enum Negotiated {
+    NotApi,
+    Serve(u32),
+    Unsupported,
+}
+
+/// The `n` of a path that starts `v<n>/` (or is just `v<n>`).
+fn path_version(rest: &str) -> Option<u32> {
+    rest.split('/').next()?.strip_prefix('v')?.parse().ok()
+}
+
+#[derive(Debug)]
+pub struct ApiVersionFairing;
+
+#[rocket::async_trait]
+impl Fairing for ApiVersionFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "API version negotiation",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let path = request.uri().path().to_string();
+        let Some(rest) = path.strip_prefix("/api/") else {
+            return;
+        };
+
+        let (negotiated, rewrite_to) = if let Some(version) = path_version(rest) {
+            let negotiated = if API_VERSIONS.contains(&version) {
+                Negotiated::Serve(version)
+            } else {
+                Negotiated::Unsupported
+            };
+            (negotiated, None)
+        } else {
+            match request.headers().get_one(API_VERSION_HEADER) {
+                None => (Negotiated::Serve(UNVERSIONED_API_VERSION), None),
+                Some(asked) => match asked.trim().parse::<u32>() {
+                    Ok(version) if API_VERSIONS.contains(&version) => {
+                        (Negotiated::Serve(version), Some(version))
+                    }
+                    // Nothing is mounted under v0, so no handler runs.
+                    _ => (Negotiated::Unsupported, Some(0)),
+                },
+            }
+        };
+
+        if let Some(version) = rewrite_to {
+            let mut uri = format!("/api/v{}/{}", version, rest);
+            if let Some(query) = request.uri().query() {
+                uri.push('?');
+                uri.push_str(query.as_str());
+            }
+            match Origin::parse_owned(uri) {
+                Ok(origin) => request.set_uri(origin),
+                Err(e) => error!(error = %e, "Failed to route request to its API version"),
+            }
+        }
+        request.local_cache(|| negotiated);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        match *request.local_cache(|| Negotiated::NotApi) {
+            Negotiated::NotApi => {}
+            Negotiated::Serve(version) => {
+                response.set_header(Header::new(API_VERSION_HEADER, version.to_string()));
+            }
+            Negotiated::Unsupported => {
+                warn!(
+                    asked = request.headers().get_one(API_VERSION_HEADER).unwrap_or("-"),
+                    uri = %request.uri(),
+                    "Unsupported API version"
+                );
+                let body = json!({
+                    "error": "Not Acceptable",
+                    "status": 406,
+                    "hint": "This API version isn't served here.",
+                    "supported": API_VERSIONS,
+                })
+                .to_string();
+                response.set_status(Status::NotAcceptable);
+                response.set_header(ContentType::JSON);
+                response.set_sized_body(body.len(), Cursor::new(body));
+            }
+        }
+    }
+}
@@ -1,9 +1,19 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
 use once_cell::sync::Lazy;
 use opentelemetry::{
     global,
     metrics::{Counter, Gauge, Histogram, Meter},
     KeyValue,
 };
+use sqlx::SqlitePool;
+use tracing::error;
+
+use super::ProcessingJobs;
+use crate::db;
+use crate::error::AppError;
+use crate::scheduler::Job;
 
 pub struct VideoMetrics {
     pub uploads_total: Counter<u64>,
@@ -114,3 +124,40 @@ pub fn video_metrics() -> &'static VideoMetrics {
 pub fn kv(key: &'static str, value: impl Into<opentelemetry::Value>) -> KeyValue {
     KeyValue::new(key, value.into())
 }
+
+/// Records the storage and processing gauges, which are sampled rather than
+/// updated as they change.
+pub struct VideoGauges {
+    jobs: Arc<ProcessingJobs>,
+}
+
+impl VideoGauges {
+    pub fn new(jobs: Arc<ProcessingJobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl Job for VideoGauges {
+    fn name(&self) -> &'static str {
+        "video_gauges"
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        "every 5m"
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String, AppError> {
+        let metrics = video_metrics();
+        metrics.processing_jobs_active.record(self.jobs.snapshot(), &[]);
+        match db::total_video_storage_bytes(pool).await {
+            Ok(bytes) => metrics.storage_bytes_total.record(bytes.max(0) as u64, &[]),
+            Err(e) => error!("failed to sample storage bytes: {}", e),
+        }
+        match db::total_video_objects(pool).await {
+            Ok(count) => metrics.storage_objects_total.record(count.max(0) as u64, &[]),
+            Err(e) => error!("failed to sample storage objects: {}", e),
+        }
+        Ok("Sampled video gauges".to_string())
+    }
+}
@@ -14,7 +14,9 @@ use uuid::Uuid;
 
 use crate::auth::{Permission, User};
 use crate::db;
+use crate::ids::UserId;
 use crate::models::{ProcessingStatus, Video};
+use crate::validation::{deserialize_optional_plain_text, deserialize_plain_text};
 use crate::videos::embeds;
 use crate::videos::metrics::{kv, video_metrics};
 use crate::videos::pipeline::{
@@ -54,14 +56,18 @@ pub struct UploadForm<'r> {
 
 #[derive(Deserialize)]
 pub struct LinkVideoRequest {
+    #[serde(deserialize_with = "deserialize_plain_text")]
     pub title: String,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
     pub description: Option<String>,
     pub url: String,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateVideoRequest {
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
     pub title: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_plain_text")]
     pub description: Option<String>,
     pub position: Option<i64>,
 }
@@ -148,7 +154,7 @@ pub async fn api_video_upload(
         tid,
         form.title.trim(),
         form.description.as_deref(),
-        user.id,
+        user.id.0,
     )
     .await
     .map_err(Status::from)?;
@@ -204,7 +210,7 @@ pub async fn api_video_link(
             technique_id: tid,
             title: trimmed_title,
             description: req.description.as_deref(),
-            uploaded_by_id: user.id,
+            uploaded_by_id: user.id.0,
             kind: parsed.kind,
             external_url: &parsed.canonical_url,
             external_host: Some(parsed.host.as_str()),
@@ -251,7 +257,7 @@ pub async fn api_list_technique_videos(
     let videos = if !is_coach {
         // Students always see only what's effectively visible to them,
         // regardless of any for_student query param a client tries to pass.
-        db::list_videos_for_technique_visible_to(pool.inner(), tid, user.id)
+        db::list_videos_for_technique_visible_to(pool.inner(), tid, user.id.0)
             .await
             .map_err(Status::from)?
     } else {
@@ -328,7 +334,7 @@ pub async fn api_set_video_student_visibility(
     pool: &State<Pool<Sqlite>>,
 ) -> Result<Status, Status> {
     user.require_permission(crate::auth::Permission::ManageVideoVisibility)?;
-    db::set_video_student_visibility(pool.inner(), vid, student_id, body.visible, user.id)
+    db::set_video_student_visibility(pool.inner(), vid, student_id, body.visible, user.id.0)
         .await
         .map_err(Status::from)?;
     Ok(Status::NoContent)
@@ -508,7 +514,7 @@ pub async fn api_video_playback_url(
     // visible to them. Coaches bypass the check (library / preview flow).
     let is_coach = user.has_permission(crate::auth::Permission::ViewAllStudents);
     if !is_coach {
-        let visible = db::video_visible_to_student(pool.inner(), vid, user.id)
+        let visible = db::video_visible_to_student(pool.inner(), vid, user.id.0)
             .await
             .map_err(Status::from)?;
         if !visible {
@@ -556,7 +562,7 @@ pub async fn api_video_download_url(
         .ok_or(Status::NotFound)?;
     let is_coach = user.has_permission(crate::auth::Permission::ViewAllStudents);
     if !is_coach {
-        let visible = db::video_visible_to_student(pool.inner(), vid, user.id)
+        let visible = db::video_visible_to_student(pool.inner(), vid, user.id.0)
             .await
             .map_err(Status::from)?;
         if !visible {
@@ -640,7 +646,7 @@ pub async fn api_video_watch_events(
             seconds_watched: seconds,
         });
     }
-    db::ingest_watch_events(pool.inner(), vid, user.id, play_id, &inputs)
+    db::ingest_watch_events(pool.inner(), vid, user.id.0, play_id, &inputs)
         .await
         .map_err(Status::from)?;
     let metrics = video_metrics();
@@ -661,7 +667,7 @@ pub async fn api_video_privacy_ack(
     user: User,
     pool: &State<Pool<Sqlite>>,
 ) -> Result<Status, Status> {
-    db::record_privacy_ack(pool.inner(), user.id)
+    db::record_privacy_ack(pool.inner(), user.id.0)
         .await
         .map_err(Status::from)?;
     Ok(Status::NoContent)
@@ -677,7 +683,7 @@ pub async fn api_video_privacy_ack_status(
     user: User,
     pool: &State<Pool<Sqlite>>,
 ) -> Result<Json<PrivacyAckStatus>, Status> {
-    let acked = db::has_privacy_ack(pool.inner(), user.id)
+    let acked = db::has_privacy_ack(pool.inner(), user.id.0)
         .await
         .map_err(Status::from)?;
     Ok(Json(PrivacyAckStatus { acked }))
@@ -705,7 +711,7 @@ pub struct StudentWatchActivityResponse {
 #[instrument(skip(pool))]
 #[get("/students/<sid>/watch-activity")]
 pub async fn api_student_watch_activity(
-    sid: i64,
+    sid: UserId,
     user: User,
     pool: &State<Pool<Sqlite>>,
 ) -> Result<Json<StudentWatchActivityResponse>, Status> {
@@ -729,7 +735,7 @@ pub async fn api_my_watch_state(
     user: User,
     pool: &State<Pool<Sqlite>>,
 ) -> Result<Json<WatchStateResponse>, Status> {
-    let videos = db::get_my_watch_state(pool.inner(), user.id, &video_ids)
+    let videos = db::get_my_watch_state(pool.inner(), user.id.0, &video_ids)
         .await
         .map_err(Status::from)?;
     Ok(Json(WatchStateResponse { videos }))
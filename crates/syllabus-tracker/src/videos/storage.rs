@@ -108,7 +108,7 @@ impl S3VideoStorage {
     }
 }
 
-fn build_client(config: &S3Config, endpoint: &str) -> S3Client {
+pub(crate) fn build_client(config: &S3Config, endpoint: &str) -> S3Client {
     let credentials = Credentials::new(
         config.access_key.clone(),
         config.secret_key.clone(),
@@ -0,0 +1,125 @@
+//! Repo automation that needs more than a shell one-liner. Run through the
+//! cargo alias in `.cargo/config.toml`:
+//!
+//! - `cargo xtask prepare`: regenerate the `.sqlx/` offline query cache.
+//! - `cargo xtask prepare --check`: report whether `.sqlx/` is stale.
+//!
+//! `query!` macros type-check against a live database at compile time unless
+//! `SQLX_OFFLINE=true`, in which case they read `.sqlx/` instead. Every build
+//! in this repo (dev shell, Docker, CI) runs offline, so the cache is checked
+//! in and must be refreshed whenever a `query!` string or `config/schema.sql`
+//! changes.
+//!
+//! `prepare` builds its own throwaway database instead of using the dev one:
+//! an EMPTY, freshly migrated DB is the deterministic prepare state. With no
+//! rows, SQLite type inference falls back to each column's declared affinity
+//! instead of the storage class of whatever row happened to be present. A
+//! seeded DB makes expression columns (MAX/COALESCE/CASE) data-dependent,
+//! e.g. an all-NULL aggregate infers `Null` instead of the schema's `Text`.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use anyhow::{Context, Result};
+
+fn print_help() {
+    println!("Usage: cargo xtask <task>");
+    println!();
+    println!("Tasks:");
+    println!("  prepare            Regenerate .sqlx/ against a fresh, empty database.");
+    println!("  prepare --check    Fail if .sqlx/ does not match a fresh prepare.");
+}
+
+fn main() -> ExitCode {
+    if let Err(e) = run() {
+        eprintln!("Error: {:#}", e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+fn run() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["prepare"] => prepare(false),
+        ["prepare", "--check"] => prepare(true),
+        [] | ["--help"] | ["-h"] | ["help"] => {
+            print_help();
+            Ok(())
+        }
+        other => anyhow::bail!("Unknown task: {}", other.join(" ")),
+    }
+}
+
+/// Workspace root, two levels above this crate's manifest.
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
+        .nth(2)
+        .expect("xtask lives at crates/xtask")
+        .to_path_buf()
+}
+
+fn cargo() -> Command {
+    let mut cmd = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()));
+    cmd.current_dir(workspace_root());
+    cmd
+}
+
+fn status(cmd: &mut Command, what: &str) -> Result<()> {
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to start {}", what))?;
+    anyhow::ensure!(status.success(), "{} failed ({})", what, status);
+    Ok(())
+}
+
+/// Removes the scratch directory however `prepare` exits.
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn prepare(check: bool) -> Result<()> {
+    let sqlx_cli = cargo()
+        .args(["sqlx", "--version"])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    anyhow::ensure!(
+        sqlx_cli,
+        "`cargo sqlx` not found; use `nix develop` or \
+         `cargo install sqlx-cli --no-default-features --features sqlite`"
+    );
+
+    let tmp = TempDir(std::env::temp_dir().join(format!("xtask-prepare-{}", std::process::id())));
+    std::fs::create_dir_all(&tmp.0).context("Failed to create scratch directory")?;
+    let url = format!("sqlite://{}", tmp.0.join("prepare.db").display());
+
+    println!("Migrating scratch database");
+    status(
+        cargo()
+            .args(["run", "-q", "-p", "migration-engine", "--bin", "migrate"])
+            .env("SQLX_OFFLINE", "true")
+            .env("DATABASE_URL", &url)
+            .env("SCHEMA_PATH", "./config/schema.sql"),
+        "migrate",
+    )?;
+
+    // `--workspace` puts the cache at the workspace root; `-p` limits the
+    // cargo-check to the macro-bearing crate, and `--tests` pulls in queries
+    // from `#[cfg(test)]` modules.
+    println!("Running sqlx prepare{}", if check { " --check" } else { "" });
+    let mut cmd = cargo();
+    cmd.args(["sqlx", "prepare"]);
+    if check {
+        cmd.arg("--check");
+    }
+    cmd.args(["--workspace", "--", "-p", "syllabus-tracker", "--tests", "--all-features"])
+        .env("DATABASE_URL", &url)
+        .env_remove("SQLX_OFFLINE");
+    status(&mut cmd, "sqlx prepare")
+}
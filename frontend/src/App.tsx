@@ -9,6 +9,7 @@ import {
 import { ReactQueryDevtools } from '@tanstack/react-query-devtools';
 import { Layout } from './components/layout';
 import { SwUpdateToast } from './components/sw-update-toast';
+import { VersionSkewToast } from './components/version-skew-toast';
 import { RequireAdmin, RequireAuth, RequireCoach } from './components/route-guards';
 import { TelemetryProvider } from './context/telemetry';
 import { CapabilitiesProvider } from './context/capabilities';
@@ -227,6 +228,7 @@ function AppShell() {
         </Layout>
         </CapabilitiesProvider>
         <SwUpdateToast />
+        <VersionSkewToast />
         <Toaster
           position="top-center"
           closeButton
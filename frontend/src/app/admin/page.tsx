@@ -156,7 +156,7 @@ function UserStatusBadges({ user }: { user: User }) {
 
 export default function AdminPage() {
   const usersQuery = useAllUsers();
-  const users = usersQuery.data ?? [];
+  const users = usersQuery.data?.items ?? [];
   const loading = usersQuery.isLoading;
   const error = usersQuery.error ? 'Failed to load users. Please try again.' : null;
   const updateUserMutation = useUpdateUser();
@@ -289,7 +289,7 @@ export default function AdminPage() {
         userId: selectedUser.id,
         data: {
           username: data.username,
-          display_name: data.display_name,
+          display_name: data.display_name.trim() || null,
           role: data.role,
         },
       });
@@ -40,6 +40,8 @@ import { cn } from '@/lib/utils';
 import { formatRelative } from '@/lib/dates';
 import { statusToDotClass } from '@/lib/status';
 import { Button } from '@/components/ui/button';
+import { Label } from '@/components/ui/label';
+import { Switch } from '@/components/ui/switch';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { AttemptHeatmap } from '@/components/attempt-heatmap';
 import { EmptyState } from '@/components/empty-state';
@@ -190,19 +192,27 @@ function CoachDashboard({ user }: { user: User }) {
   }, [activeStudents]);
 
   const [rosterTab, setRosterTab] = useState<RosterTab>('initiative');
+  // Only offered once the billing system has reported on someone.
+  const tracksMembership = activeStudents.some((s) => s.membership_status);
+  const [membersOnly, setMembersOnly] = useState(false);
+  const inRoster = (list: User[]) =>
+    membersOnly && tracksMembership
+      ? list.filter((s) => s.membership_status === 'active')
+      : list;
 
   const rosterCounts = {
-    initiative: initiativeStudents.length,
-    recent: recentStudents.length,
-    quiet: quietStudents.length,
+    initiative: inRoster(initiativeStudents).length,
+    recent: inRoster(recentStudents).length,
+    quiet: inRoster(quietStudents).length,
   };
 
-  const rosterForTab =
+  const rosterForTab = inRoster(
     rosterTab === 'initiative'
       ? initiativeStudents
       : rosterTab === 'quiet'
         ? quietStudents
-        : recentStudents;
+        : recentStudents,
+  );
 
   if (loading) {
     return (
@@ -296,9 +306,23 @@ function CoachDashboard({ user }: { user: User }) {
           </TabsTrigger>
         </TabsList>
 
-        <p className="px-1 text-xs text-muted-foreground">
-          {rosterDescription(rosterTab)}
-        </p>
+        <div className="flex items-center justify-between gap-3 px-1">
+          <p className="text-xs text-muted-foreground">
+            {rosterDescription(rosterTab)}
+          </p>
+          {tracksMembership && (
+            <div className="flex shrink-0 items-center gap-2">
+              <Switch
+                id="members-only"
+                checked={membersOnly}
+                onCheckedChange={setMembersOnly}
+              />
+              <Label htmlFor="members-only" className="text-xs">
+                Active members
+              </Label>
+            </div>
+          )}
+        </div>
 
         <TabsContent value={rosterTab}>
           <Roster
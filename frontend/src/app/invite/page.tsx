@@ -4,7 +4,7 @@ import { AlertCircle, GraduationCap } from 'lucide-react';
 import { toast } from 'sonner';
 import { z } from 'zod';
 import { zodResolver } from '@hookform/resolvers/zod';
-import { claimInvite, getInvite, type InviteInfo } from '@/lib/api';
+import { getInvite, registerWithInvite, type InviteInfo } from '@/lib/api';
 import { Button } from '@/components/ui/button';
 import {
   Form,
@@ -25,7 +25,10 @@ const claimSchema = z
       .string()
       .min(3, 'Username must be at least 3 characters')
       .max(50, 'Username is too long')
-      .regex(/^\S+$/, 'Username cannot contain spaces'),
+      .regex(
+        /^[\p{L}\p{N}\p{M}._-]+$/u,
+        "Username can only contain letters, numbers, '.', '_' and '-'",
+      ),
     password: z.string().min(5, 'Password must be at least 5 characters'),
     confirm_password: z.string().min(1, 'Please confirm your password'),
   })
@@ -82,7 +85,7 @@ export default function InvitePage({ onClaimSuccess }: InvitePageProps) {
   async function handleSubmit(data: ClaimValues) {
     if (!token) return;
     try {
-      const response = await claimInvite(token, {
+      const response = await registerWithInvite(token, {
         username: data.username,
         password: data.password,
       });
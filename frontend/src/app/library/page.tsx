@@ -20,6 +20,7 @@ import type {
   LibraryTechniqueStats,
   Tag,
 } from '@/lib/api';
+import { matchesAlias } from '@/lib/api';
 import {
   useAllTags,
   useCollections,
@@ -119,6 +120,7 @@ export default function LibraryPage() {
       const matchesText =
         !needle ||
         t.name.toLowerCase().includes(needle) ||
+        matchesAlias(t, needle) ||
         t.description.toLowerCase().includes(needle) ||
         t.tags.some((tag) => tag.name.toLowerCase().includes(needle));
       const matchesTags =
@@ -2,8 +2,16 @@ import { useEffect } from 'react';
 import { toast } from 'sonner';
 import { z } from 'zod';
 import { zodResolver } from '@hookform/resolvers/zod';
-import { useCurrentUser } from '@/lib/queries';
-import { useUpdatePassword, useUpdateUserProfile } from '@/lib/mutations';
+import { useCurrentUser, useNotificationPreferences } from '@/lib/queries';
+import {
+  useUpdateNotificationPreferences,
+  useUpdatePassword,
+  useUpdateUserProfile,
+} from '@/lib/mutations';
+import { badgeLabel } from '@/lib/api';
+import type { DigestFrequency, NotificationPreferences, UserBadge } from '@/lib/api';
+import { formatAbsolute } from '@/lib/dates';
+import { Badge } from '@/components/ui/badge';
 import { Button } from '@/components/ui/button';
 import {
   Form,
@@ -14,8 +22,16 @@ import {
   FormMessage,
 } from '@/components/ui/form';
 import { Input } from '@/components/ui/input';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from '@/components/ui/select';
 import { Separator } from '@/components/ui/separator';
 import { Skeleton } from '@/components/ui/skeleton';
+import { Switch } from '@/components/ui/switch';
 import { TracedForm } from '@/components/traced-form';
 import { handleApiFormError, useFormWithValidation } from '@/components/hooks/useFormErrors';
 
@@ -56,6 +72,12 @@ const passwordSchema = z
     message: 'Passwords do not match',
   });
 
+const DIGEST_OPTIONS: { value: DigestFrequency; label: string }[] = [
+  { value: 'off', label: 'Never' },
+  { value: 'daily', label: 'Daily' },
+  { value: 'weekly', label: 'Weekly' },
+];
+
 type ProfileValues = z.infer<typeof profileSchema>;
 type PasswordValues = z.infer<typeof passwordSchema>;
 
@@ -94,7 +116,7 @@ export default function ProfilePage() {
   async function handleProfileSubmit(data: ProfileValues) {
     try {
       await profileMutation.mutateAsync({
-        display_name: data.display_name,
+        display_name: data.display_name.trim() || null,
         username: data.username.trim(),
       });
       toast.success('Profile updated');
@@ -268,6 +290,143 @@ export default function ProfilePage() {
         </Form>
       </section>
 
+      <Separator className="my-8" />
+
+      <NotificationSettings />
+
+      {user?.role === 'student' && (
+        <>
+          <Separator className="my-8" />
+          <BadgeList badges={user.badges ?? []} />
+        </>
+      )}
+    </div>
+  );
+}
+
+function BadgeList({ badges }: { badges: UserBadge[] }) {
+  return (
+    <section className="space-y-5">
+      <h2 className="text-base font-semibold">Badges</h2>
+      {badges.length === 0 ? (
+        <p className="text-sm text-muted-foreground">
+          Nothing yet. Your first green technique earns one.
+        </p>
+      ) : (
+        <div className="flex flex-wrap gap-2">
+          {badges.map((badge) => (
+            <Badge
+              key={`${badge.badge}-${badge.subject_id ?? 0}`}
+              variant="secondary"
+              title={`Earned ${formatAbsolute(badge.awarded_at)}`}
+            >
+              {badgeLabel(badge)}
+            </Badge>
+          ))}
+        </div>
+      )}
+    </section>
+  );
+}
+
+// Each control saves as soon as it changes.
+function NotificationSettings() {
+  const preferencesQuery = useNotificationPreferences();
+  const preferences = preferencesQuery.data ?? null;
+  const updateMutation = useUpdateNotificationPreferences();
+
+  async function save(patch: Partial<NotificationPreferences>) {
+    if (!preferences) return;
+    try {
+      await updateMutation.mutateAsync({ ...preferences, ...patch });
+    } catch {
+      toast.error('Failed to save notification settings');
+    }
+  }
+
+  return (
+    <section className="space-y-5">
+      <h2 className="text-base font-semibold">Email notifications</h2>
+
+      {!preferences ? (
+        <div className="space-y-3">
+          <Skeleton className="h-9 w-full" />
+          <Skeleton className="h-9 w-full" />
+        </div>
+      ) : (
+        <div className="space-y-4">
+          <NotificationToggle
+            label="New assignments"
+            description="When a coach assigns you techniques or a curriculum."
+            checked={preferences.email_on_assignment}
+            disabled={updateMutation.isPending}
+            onChange={(checked) => save({ email_on_assignment: checked })}
+          />
+          <NotificationToggle
+            label="Coach notes"
+            description="When a coach writes notes on one of your techniques."
+            checked={preferences.email_on_coach_note}
+            disabled={updateMutation.isPending}
+            onChange={(checked) => save({ email_on_coach_note: checked })}
+          />
+          <div className="flex items-center justify-between gap-3">
+            <div className="min-w-0">
+              <p className="text-sm font-medium">Summary email</p>
+              <p className="text-xs text-muted-foreground">
+                A digest of recent activity.
+              </p>
+            </div>
+            <Select
+              value={preferences.digest}
+              disabled={updateMutation.isPending}
+              onValueChange={(value) => {
+                const digest = DIGEST_OPTIONS.find((o) => o.value === value)?.value;
+                if (digest) save({ digest });
+              }}
+            >
+              <SelectTrigger className="w-[120px]" aria-label="Summary email frequency">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {DIGEST_OPTIONS.map((option) => (
+                  <SelectItem key={option.value} value={option.value}>
+                    {option.label}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+          </div>
+        </div>
+      )}
+    </section>
+  );
+}
+
+function NotificationToggle({
+  label,
+  description,
+  checked,
+  disabled,
+  onChange,
+}: {
+  label: string;
+  description: string;
+  checked: boolean;
+  disabled: boolean;
+  onChange: (checked: boolean) => void;
+}) {
+  return (
+    <div className="flex items-center justify-between gap-3">
+      <div className="min-w-0">
+        <p className="text-sm font-medium">{label}</p>
+        <p className="text-xs text-muted-foreground">{description}</p>
+      </div>
+      <Switch
+        checked={checked}
+        disabled={disabled}
+        onCheckedChange={onChange}
+        aria-label={label}
+      />
     </div>
   );
 }
@@ -25,7 +25,10 @@ const registerSchema = z
       .string()
       .min(3, 'Username must be at least 3 characters')
       .max(50)
-      .regex(/^\S+$/, 'Username cannot contain spaces'),
+      .regex(
+        /^[\p{L}\p{N}\p{M}._-]+$/u,
+        "Username can only contain letters, numbers, '.', '_' and '-'",
+      ),
     password: z.string().min(5, 'Password must be at least 5 characters'),
     confirm_password: z.string().min(1, 'Please confirm your password'),
   })
@@ -1,4 +1,4 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { Link, useParams, useSearchParams } from "react-router-dom";
 import { useQueryClient } from "@tanstack/react-query";
 import {
@@ -9,6 +9,7 @@ import {
 } from "lucide-react";
 import { toast } from "sonner";
 import {
+  coachUpdateReadLabel,
   type Attempt,
   type SingleStudentTechnique,
   type Tag,
@@ -23,6 +24,7 @@ import {
 } from "@/lib/queries";
 import {
   useDeleteAttempt,
+  useMarkStudentTechniqueSeen,
   useRemoveTagFromTechnique,
   useUpdateAttempt,
   useUpdateTechnique,
@@ -88,6 +90,14 @@ export default function StudentTechniqueDetail({
   const removeTagMutation = useRemoveTagFromTechnique();
   const updateAttemptMutation = useUpdateAttempt(stId, studentId);
   const deleteAttemptMutation = useDeleteAttempt(stId, studentId);
+  const markSeen = useMarkStudentTechniqueSeen().mutate;
+
+  // Opening the page counts as looking, same as expanding the row; for the
+  // student this is also what marks coach feedback as read.
+  const hasUnseenActivity = detailQuery.data?.technique.has_unseen_activity ?? false;
+  useEffect(() => {
+    if (hasUnseenActivity) markSeen(stId);
+  }, [hasUnseenActivity, markSeen, stId]);
 
   const [tagToRemove, setTagToRemove] = useState<{
     technique: Technique;
@@ -423,6 +433,7 @@ export default function StudentTechniqueDetail({
                   {formatRelative(technique.last_coach_update_at)}
                   {technique.last_coach_update_by_name &&
                     ` · ${technique.last_coach_update_by_name}`}
+                  {!isOwnTechnique && coachUpdateReadLabel(technique)}
                 </span>
               </div>
             )}
@@ -0,0 +1,45 @@
+import { Progress } from '@/components/ui/progress';
+import type { GroupProgress, StudentAnalytics } from '@/lib/api';
+
+interface GroupProgressListProps {
+  analytics: StudentAnalytics;
+}
+
+// Progress bars per curriculum and per tag, over the techniques assigned to
+// the student. Sections with nothing assigned are left out.
+export function GroupProgressList({ analytics }: GroupProgressListProps) {
+  const sections: { title: string; groups: GroupProgress[] }[] = [
+    { title: 'Curricula', groups: analytics.curricula },
+    { title: 'Tags', groups: analytics.tags },
+  ].filter((section) => section.groups.length > 0);
+  if (sections.length === 0) return null;
+
+  return (
+    <div className="grid gap-4 sm:grid-cols-2">
+      {sections.map((section) => (
+        <section key={section.title} className="space-y-2">
+          <h2 className="text-xs font-medium uppercase tracking-wide text-muted-foreground">
+            {section.title}
+          </h2>
+          <ul className="space-y-1.5">
+            {section.groups.map((group) => (
+              <li key={group.id} className="flex items-center gap-3 text-xs">
+                <span className="w-28 shrink-0 truncate" title={group.name}>
+                  {group.name}
+                </span>
+                <Progress
+                  value={group.percent}
+                  className="h-1.5"
+                  aria-label={`${group.name}: ${group.percent}% done`}
+                />
+                <span className="w-16 shrink-0 text-right text-muted-foreground">
+                  {group.green}/{group.assigned} done
+                </span>
+              </li>
+            ))}
+          </ul>
+        </section>
+      ))}
+    </div>
+  );
+}
@@ -4,12 +4,14 @@ import { useQueryClient } from "@tanstack/react-query";
 import {
   ChevronDownIcon,
   ChevronUpIcon,
+  HandIcon,
   XIcon,
 } from "lucide-react";
-import type { Attempt, Tag, Technique } from "@/lib/api";
+import { coachUpdateReadLabel, type Attempt, type Tag, type Technique } from "@/lib/api";
 import { useAttempts } from "@/lib/queries";
 import {
   useMarkStudentTechniqueSeen,
+  useRequestReview,
   useUpdateTechnique,
 } from "@/lib/mutations";
 import { qk } from "@/lib/query-keys";
@@ -74,6 +76,7 @@ export function TechniqueRow({
   const attemptsError = attemptsQuery.error ? "Could not load attempts" : null;
   const markSeenMutation = useMarkStudentTechniqueSeen();
   const updateTechniqueMutation = useUpdateTechnique();
+  const requestReviewMutation = useRequestReview();
   const status = technique.status as Status;
   const canEditStudentNotes = isOwnTechnique;
   const canManageTagsOnRow = canEditAll || canManageTags;
@@ -120,12 +123,25 @@ export function TechniqueRow({
         studentTechniqueId: technique.id,
         updates: { status: next },
       });
-      onTechniqueUpdate({ ...technique, status: next });
+      // A status change from a coach answers any pending review request.
+      onTechniqueUpdate({ ...technique, status: next, review_requested_at: null });
     } catch {
       // Surface failure silently; mutation rollback restores the prior state.
     }
   }
 
+  async function handleRequestReview() {
+    try {
+      await requestReviewMutation.mutateAsync(technique.id);
+      onTechniqueUpdate({
+        ...technique,
+        review_requested_at: new Date().toISOString(),
+      });
+    } catch {
+      // Leave the button in place so the student can try again.
+    }
+  }
+
   function handleTagAdded(tag: Tag) {
     const updated = [...technique.tags, tag].sort((a, b) =>
       a.name.localeCompare(b.name),
@@ -207,7 +223,39 @@ export function TechniqueRow({
       {expanded && (
         <div className="space-y-5 px-4 pb-5">
           {canEditAll && (
-            <StatusToggle value={status} onChange={handleStatusChange} size="sm" />
+            <div className="flex flex-wrap items-center gap-2">
+              <StatusToggle value={status} onChange={handleStatusChange} size="sm" />
+              {technique.review_requested_at && (
+                <Badge
+                  variant="outline"
+                  className="gap-1 border-primary/40 text-primary"
+                  title={`Requested ${formatRelative(technique.review_requested_at)}`}
+                >
+                  <HandIcon className="h-3 w-3" aria-hidden />
+                  Review requested
+                </Badge>
+              )}
+            </div>
+          )}
+
+          {viewerIsOwner && status !== "green" && (
+            technique.review_requested_at ? (
+              <p className="flex items-center gap-1.5 text-xs text-muted-foreground">
+                <HandIcon className="h-3.5 w-3.5" aria-hidden />
+                Review requested {formatRelative(technique.review_requested_at)}
+              </p>
+            ) : (
+              <Button
+                type="button"
+                variant="outline"
+                size="sm"
+                disabled={requestReviewMutation.isPending}
+                onClick={handleRequestReview}
+              >
+                <HandIcon className="mr-1.5 h-3.5 w-3.5" aria-hidden />
+                Ready for review
+              </Button>
+            )
           )}
 
           {technique.technique_description && (
@@ -346,6 +394,7 @@ export function TechniqueRow({
           <FooterMeta
             technique={technique}
             studentId={studentId}
+            showReadReceipt={!viewerIsOwner}
             onViewDetails={() => {
               const next = new URLSearchParams();
               const tab = searchParams.get('tab');
@@ -380,6 +429,7 @@ function buildMetaParts({
   showCollectionChip,
 }: MetaArgs): string[] {
   const parts: string[] = [];
+  if (technique.review_requested_at) parts.push("Review requested");
   if (technique.attempt_count > 0) {
     parts.push(
       `${technique.attempt_count} ${technique.attempt_count === 1 ? "attempt" : "attempts"}`,
@@ -410,12 +460,14 @@ function statusBorderClass(status: Status): string {
 interface FooterMetaProps {
   technique: Technique;
   studentId: number;
+  showReadReceipt: boolean;
   onViewDetails: () => void;
   onEditDefinition?: () => void;
 }
 
 function FooterMeta({
   technique,
+  showReadReceipt,
   onViewDetails,
   onEditDefinition,
 }: FooterMetaProps) {
@@ -434,6 +486,7 @@ function FooterMeta({
                 Coach: {formatRelative(lastCoach)}
                 {technique.last_coach_update_by_name &&
                   ` · ${technique.last_coach_update_by_name}`}
+                {showReadReceipt && coachUpdateReadLabel(technique)}
               </p>
             )}
             {lastStudent && (
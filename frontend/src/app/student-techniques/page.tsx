@@ -23,6 +23,7 @@ import type {
 import {
   useAllTags,
   useAttemptSummary,
+  useStudentAnalytics,
   useStudentTechniques,
 } from '@/lib/queries';
 import {
@@ -71,6 +72,7 @@ import {
   type FilterTab,
 } from './components/technique-filters';
 import { TagRemoveDialog } from './components/tag-remove-dialog';
+import { GroupProgressList } from './components/group-progress';
 import type { Status } from '@/lib/status';
 
 const FILTER_TAB_VALUES = new Set<FilterTab>([
@@ -96,6 +98,7 @@ export default function StudentTechniques({ user }: StudentTechniquesProps) {
   const studentTechniquesQuery = useStudentTechniques(studentId);
   const tagsQuery = useAllTags();
   const attemptSummaryQuery = useAttemptSummary(studentId);
+  const analyticsQuery = useStudentAnalytics(studentId);
   const updateTechniqueMutation = useUpdateTechnique();
   const removeTagMutation = useRemoveTagFromTechnique();
   const resetClaimMutation = useResetUserClaim();
@@ -145,6 +148,7 @@ export default function StudentTechniques({ user }: StudentTechniquesProps) {
   const data = studentTechniquesQuery.data ?? null;
   const allTags = tagsQuery.data ?? [];
   const attemptSummary = attemptSummaryQuery.data ?? null;
+  const analytics = analyticsQuery.data ?? null;
   const loading = studentTechniquesQuery.isLoading;
   const error = studentTechniquesQuery.error
     ? 'Failed to load techniques. Please try again.'
@@ -543,6 +547,7 @@ export default function StudentTechniques({ user }: StudentTechniquesProps) {
               </div>
             )}
           </div>
+          {analytics && <GroupProgressList analytics={analytics} />}
           {(data.can_assign_techniques || (data.can_edit_all_techniques && !isOwnView)) && (
             <div className="flex items-center gap-2">
               {data.can_edit_all_techniques && !isOwnView && (
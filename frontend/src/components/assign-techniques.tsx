@@ -13,7 +13,7 @@ import {
   SelectValue,
 } from '@/components/ui/select';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
-import { type Collection } from '@/lib/api';
+import { type Collection, matchesAlias } from '@/lib/api';
 import {
   useCollections,
   useStudentUnassignedTechniques,
@@ -101,6 +101,7 @@ export default function AssignTechniques({
       const matchesText =
         !needle ||
         technique.name.toLowerCase().includes(needle) ||
+        matchesAlias(technique, needle) ||
         technique.description.toLowerCase().includes(needle) ||
         technique.tags.some((tag) => tag.name.toLowerCase().includes(needle));
       const matchesTags =
@@ -168,7 +169,7 @@ export default function AssignTechniques({
     }
   }
 
-  async function handleCreateTechnique(data: CreateTechniqueFormValues) {
+  async function handleCreateTechnique(data: CreateTechniqueFormValues, force = false) {
     if (!data.name.trim() || !data.description.trim()) return;
     try {
       await createMutation.mutateAsync({
@@ -176,10 +177,23 @@ export default function AssignTechniques({
         name: data.name,
         description: data.description,
         collectionId: chosenCollectionId(),
+        force,
       });
       createTechniqueForm.reset();
       onAssignComplete();
     } catch (err) {
+      // 409: the library already has techniques with similar names. The
+      // name field shows which; the toast lets the coach create it anyway.
+      if (err instanceof Response && err.status === 409) {
+        await handleApiFormError(err, createTechniqueForm.setError, ['name']);
+        toast.warning('A similar technique already exists', {
+          action: {
+            label: 'Create anyway',
+            onClick: () => void handleCreateTechnique(data, true),
+          },
+        });
+        return;
+      }
       const handled = await handleApiFormError(
         err,
         createTechniqueForm.setError,
@@ -378,7 +392,7 @@ export default function AssignTechniques({
           <TabsContent value="create" className="mt-4 min-h-0 overflow-y-auto">
             <TracedForm
               id="create_technique"
-              onSubmit={createTechniqueForm.handleSubmit(handleCreateTechnique)}
+              onSubmit={createTechniqueForm.handleSubmit((data) => handleCreateTechnique(data))}
               className="space-y-4"
             >
               {fileUnderField}
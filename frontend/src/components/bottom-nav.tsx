@@ -13,7 +13,7 @@ import {
   type LucideIcon,
 } from 'lucide-react';
 import type { User } from '@/lib/api';
-import { isCoachOrAdmin, isAdmin } from '@/lib/api';
+import { hasPermission } from '@/lib/api';
 import { useInstallTrigger } from '@/lib/install';
 import { cn } from '@/lib/utils';
 import {
@@ -40,7 +40,7 @@ function buildTabs(user: User): Tab[] {
   const tabs: Tab[] = [
     { to: '/dashboard', label: 'Dashboard', icon: LayoutDashboard },
   ];
-  if (isCoachOrAdmin(user)) {
+  if (hasPermission(user, 'view_all_students')) {
     tabs.push({ to: '/students', label: 'Students', icon: Users });
     tabs.push({
       to: '/library',
@@ -113,8 +113,8 @@ function MoreTab({ user, onLogout }: { user: User; onLogout: () => void }) {
   const [open, setOpen] = useState(false);
   const navigate = useNavigate();
   const install = useInstallTrigger();
-  const coachOrAdmin = isCoachOrAdmin(user);
-  const admin = isAdmin(user);
+  const coachOrAdmin = hasPermission(user, 'view_all_students');
+  const admin = hasPermission(user, 'edit_user_roles');
 
   const close = () => setOpen(false);
 
@@ -1,5 +1,5 @@
 import { useState } from "react";
-import { Link, useNavigate } from "react-router-dom";
+import { Link, useNavigate, useSearchParams } from "react-router-dom";
 import { z } from "zod";
 import { zodResolver } from "@hookform/resolvers/zod";
 import { login } from "@/lib/api";
@@ -14,6 +14,7 @@ import {
   FormMessage,
 } from "@/components/ui/form";
 import { Input } from "@/components/ui/input";
+import { useCapabilities } from "@/context/capabilities-context";
 import { TracedForm } from "./traced-form";
 import { useFormWithValidation } from "./hooks/useFormErrors";
 
@@ -30,6 +31,10 @@ interface LoginFormProps extends React.ComponentProps<"div"> {
 
 export function LoginForm({ onSuccess, className, ...props }: LoginFormProps) {
   const navigate = useNavigate();
+  const { flags, oidc } = useCapabilities();
+  const [searchParams] = useSearchParams();
+  // Set by the server when a sign-in through the identity provider fails.
+  const oidcFailed = searchParams.get("error") === "oidc";
   const [isLoading, setIsLoading] = useState(false);
 
   const form = useFormWithValidation<LoginFormValues>({
@@ -127,12 +132,27 @@ export function LoginForm({ onSuccess, className, ...props }: LoginFormProps) {
               {isLoading ? "Signing in..." : "Sign in"}
             </Button>
 
-            <p className="text-center text-sm text-muted-foreground">
-              No account yet?{" "}
-              <Link to="/register" className="font-medium text-primary hover:underline">
-                Sign up
-              </Link>
-            </p>
+            {oidc && (
+              <Button asChild variant="outline" className="w-full">
+                {/* A full page load, so the server can redirect to the provider. */}
+                <a href="/api/auth/oidc/login">Sign in with your gym account</a>
+              </Button>
+            )}
+            {oidcFailed && (
+              <p className="text-center text-sm text-destructive">
+                Couldn't sign you in with that account. Use your username and
+                password, or ask a coach to check the email on your profile.
+              </p>
+            )}
+
+            {flags.self_registration && (
+              <p className="text-center text-sm text-muted-foreground">
+                No account yet?{" "}
+                <Link to="/register" className="font-medium text-primary hover:underline">
+                  Sign up
+                </Link>
+              </p>
+            )}
           </TracedForm>
         </Form>
       </div>
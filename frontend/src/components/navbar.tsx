@@ -1,7 +1,7 @@
 import { useNavigate, useLocation, Link } from "react-router-dom";
 import { Download, LogOut, UserRound } from "lucide-react";
 import type { User } from "@/lib/api";
-import { isCoachOrAdmin, isAdmin } from "@/lib/api";
+import { hasPermission } from "@/lib/api";
 import { useInstallTrigger } from "@/lib/install";
 import { Button } from "@/components/ui/button";
 import {
@@ -34,15 +34,20 @@ function initials(user: Pick<User, "display_name" | "username">): string {
 }
 
 function buildNavLinks(user: User): NavLink[] {
-  const coachOrAdmin = isCoachOrAdmin(user);
   const isStudent = user.role === "student";
 
   const links: NavLink[] = [{ to: "/dashboard", label: "Dashboard" }];
   if (isStudent) links.push({ to: `/student/${user.id}`, label: "My techniques" });
-  if (coachOrAdmin) links.push({ to: "/students", label: "Students" });
-  if (coachOrAdmin) links.push({ to: "/library", label: "Techniques" });
-  if (coachOrAdmin) links.push({ to: "/register-user", label: "New user" });
-  if (isAdmin(user)) links.push({ to: "/admin", label: "Admin" });
+  if (hasPermission(user, "view_all_students")) {
+    links.push({ to: "/students", label: "Students" });
+  }
+  if (hasPermission(user, "create_techniques")) {
+    links.push({ to: "/library", label: "Techniques" });
+  }
+  if (hasPermission(user, "register_users")) {
+    links.push({ to: "/register-user", label: "New user" });
+  }
+  if (hasPermission(user, "edit_user_roles")) links.push({ to: "/admin", label: "Admin" });
   return links;
 }
 
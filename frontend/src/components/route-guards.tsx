@@ -1,6 +1,6 @@
 import type { ReactNode } from "react";
 import { Navigate } from "react-router-dom";
-import { isAdmin, isCoachOrAdmin } from "@/lib/api";
+import { hasPermission } from "@/lib/api";
 import { useCurrentUser } from "@/lib/queries";
 
 interface GuardProps {
@@ -18,7 +18,7 @@ export function RequireCoach({ children }: GuardProps) {
   const { data: user, isLoading } = useCurrentUser();
   if (isLoading) return null;
   if (!user) return <Navigate to="/login" replace />;
-  if (!isCoachOrAdmin(user)) return <Navigate to="/dashboard" replace />;
+  if (!hasPermission(user, "view_all_students")) return <Navigate to="/dashboard" replace />;
   return <>{children}</>;
 }
 
@@ -26,6 +26,6 @@ export function RequireAdmin({ children }: GuardProps) {
   const { data: user, isLoading } = useCurrentUser();
   if (isLoading) return null;
   if (!user) return <Navigate to="/login" replace />;
-  if (!isAdmin(user)) return <Navigate to="/dashboard" replace />;
+  if (!hasPermission(user, "edit_user_roles")) return <Navigate to="/dashboard" replace />;
   return <>{children}</>;
 }
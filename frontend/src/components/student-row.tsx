@@ -1,6 +1,13 @@
 import { type ReactNode } from "react";
 import { Link } from "react-router-dom";
-import { Archive, ChevronRight, Clock, GraduationCap, PlayCircle } from "lucide-react";
+import {
+  Archive,
+  ChevronRight,
+  Clock,
+  GraduationCap,
+  Hand,
+  PlayCircle,
+} from "lucide-react";
 import { Avatar, AvatarFallback } from "@/components/ui/avatar";
 import { Badge } from "@/components/ui/badge";
 import { Progress } from "@/components/ui/progress";
@@ -89,6 +96,16 @@ export function StudentRow({
                 title="New student activity since you last looked"
               />
             )}
+            {!!student.review_requests && (
+              <Badge
+                variant="outline"
+                className="shrink-0 gap-1 border-primary/40 px-1.5 py-0 text-[10px] font-medium text-primary"
+                title="Techniques this student has asked you to review"
+              >
+                <Hand className="h-3 w-3" aria-hidden />
+                {student.review_requests} to review
+              </Badge>
+            )}
             {watchedRecently && !student.graduated_at && !showWatchTitle && (
               <Badge
                 variant="outline"
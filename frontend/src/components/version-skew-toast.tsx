@@ -0,0 +1,43 @@
+import { useEffect, useRef, useState } from "react";
+import { toast } from "sonner";
+import { Button } from "@/components/ui/button";
+import { useServerVersion } from "@/lib/queries";
+
+// The service worker toast (sw-update-toast.tsx) only fires once the new
+// bundle is fetched. This one compares the backend commit against the one
+// seen when the tab loaded, so a tab held open across a deploy is told
+// before it sends requests the new API no longer understands.
+export function VersionSkewToast() {
+  const { data } = useServerVersion();
+  const loadedCommitRef = useRef<string | null>(null);
+  const [dismissed, setDismissed] = useState(false);
+  const toastIdRef = useRef<string | number | null>(null);
+
+  const commit = data?.commit ?? null;
+
+  useEffect(() => {
+    if (commit == null) return;
+    if (loadedCommitRef.current == null) {
+      loadedCommitRef.current = commit;
+      return;
+    }
+    if (commit === loadedCommitRef.current) return;
+    if (dismissed || toastIdRef.current != null) return;
+
+    toastIdRef.current = toast("A new version is available", {
+      description: "Reload to get the latest changes.",
+      duration: Infinity,
+      action: (
+        <Button size="sm" onClick={() => window.location.reload()}>
+          Reload
+        </Button>
+      ),
+      onDismiss: () => {
+        toastIdRef.current = null;
+        setDismissed(true);
+      },
+    });
+  }, [commit, dismissed]);
+
+  return null;
+}
@@ -3,6 +3,12 @@ import type { Capabilities } from '@/lib/api';
 
 export const DEFAULT_CAPABILITIES: Capabilities = {
   videos: false,
+  oidc: false,
+  flags: {
+    self_registration: true,
+    public_sharing: false,
+    webhooks: false,
+  },
 };
 
 export const CapabilitiesContext = createContext<Capabilities>(DEFAULT_CAPABILITIES);
@@ -1,3 +1,5 @@
+import type { JsonValue, ValidationErrorResponse } from "./types";
+
 export interface LoginCredentials {
   username: string;
   password: string;
@@ -13,6 +15,26 @@ export interface LoginResponse {
 export interface Tag {
   id: number;
   name: string;
+  archived: boolean;
+  category: string | null;
+  /** `#rrggbb`. */
+  color: string | null;
+  parent_id: number | null;
+}
+
+/** A tag with the tags nested under it, by name. */
+export interface TagNode extends Tag {
+  children: TagNode[];
+}
+
+/**
+ * On update, a missing field is left alone and `null` clears it; a `null`
+ * parent makes the tag top-level.
+ */
+export interface TagDetails {
+  category?: string | null;
+  color?: string | null;
+  parent_id?: number | null;
 }
 
 export async function login(
@@ -72,6 +94,49 @@ export function isAdmin(user: User | null): user is User & { role: "admin" } {
   return !!user && user.role === "admin";
 }
 
+// Mirrors `auth::Permission` on the server.
+export type Permission =
+  | "view_own_profile"
+  | "edit_own_profile"
+  | "view_own_techniques"
+  | "edit_own_notes"
+  | "view_all_students"
+  | "view_all_gym_students"
+  | "edit_all_techniques"
+  | "assign_techniques"
+  | "create_techniques"
+  | "delete_techniques"
+  | "register_users"
+  | "manage_tags"
+  | "track_attendance"
+  | "manage_schedule"
+  | "conduct_gradings"
+  | "view_student_journals"
+  | "edit_user_roles"
+  | "delete_users"
+  | "edit_user_credentials"
+  | "upload_videos"
+  | "delete_videos"
+  | "manage_video_visibility"
+  | "view_watch_stats"
+  | "view_storage_stats"
+  | "view_coach_report"
+  | "archive_tags"
+  | "manage_status_transitions"
+  | "manage_statuses"
+  | "manage_ranks"
+  | "manage_feature_flags"
+  | "import_syllabus"
+  | "manage_memberships"
+  | "manage_note_templates"
+  | "manage_config"
+  | "view_system_status";
+
+// Only the user from /api/me carries permissions; anyone else has none here.
+export function hasPermission(user: User | null, permission: Permission): boolean {
+  return !!user?.permissions?.includes(permission);
+}
+
 export interface User {
   id: number;
   username: string;
@@ -92,9 +157,43 @@ export interface User {
   amber_count?: number | null;
   green_count?: number | null;
   has_unseen_activity?: boolean | null;
+  // Techniques the student has asked a coach to review. Dashboard list only.
+  review_requests?: number | null;
   last_student_initiative_at?: string | null;
   last_watch_at?: string | null;
   last_watch_video_title?: string | null;
+  // IANA zone name chosen in profile settings; null means use the browser's.
+  timezone?: string | null;
+  // Set by the gym's billing system; null when membership isn't tracked.
+  membership_status?: MembershipStatus | null;
+  // Image URL for the profile picture; null shows initials.
+  avatar_url?: string | null;
+  // Only returned by /api/me.
+  badges?: UserBadge[];
+  permissions?: Permission[];
+}
+
+export type MembershipStatus = "active" | "lapsed";
+
+export type BadgeKind = "first_green" | "training_days" | "curriculum_complete";
+
+export interface UserBadge {
+  badge: BadgeKind;
+  // The curriculum, for curriculum_complete.
+  subject_id: number | null;
+  subject_name: string | null;
+  awarded_at: string;
+}
+
+export function badgeLabel(badge: UserBadge): string {
+  switch (badge.badge) {
+    case "first_green":
+      return "First green";
+    case "training_days":
+      return "10 days trained";
+    case "curriculum_complete":
+      return `Completed ${badge.subject_name ?? "a curriculum"}`;
+  }
 }
 
 export async function getCurrentUser(): Promise<User | null> {
@@ -114,8 +213,17 @@ export async function getCurrentUser(): Promise<User | null> {
   }
 }
 
+export interface FeatureFlags {
+  self_registration: boolean;
+  public_sharing: boolean;
+  webhooks: boolean;
+}
+
 export interface Capabilities {
   videos: boolean;
+  // Sign-in through the gym's identity provider at /api/auth/oidc/login.
+  oidc: boolean;
+  flags: FeatureFlags;
 }
 
 export async function getCapabilities(): Promise<Capabilities | null> {
@@ -132,6 +240,27 @@ export async function getCapabilities(): Promise<Capabilities | null> {
   }
 }
 
+export interface VersionInfo {
+  version: string;
+  commit: string | null;
+  built_at: string | null;
+  /** The `/api/v<n>` versions served. Bare `/api` paths stay on version 1. */
+  api_versions: number[];
+}
+
+// Public. Polled to notice when a deploy has replaced the backend.
+export async function getVersion(): Promise<VersionInfo | null> {
+  try {
+    const response = await fetch("/api/version");
+    if (!response.ok) {
+      return null;
+    }
+    return await response.json();
+  } catch {
+    return null;
+  }
+}
+
 // Get unassigned techniques for a student
 export async function getTechniquesForAssignment(
   studentId: number,
@@ -149,13 +278,15 @@ export async function getTechniquesForAssignment(
     );
   }
 
-  return await response.json();
+  const page: Paginated<AssignableTechnique> = await response.json();
+  return page.items;
 }
 
 export async function assignTechniquesToStudent(
   studentId: number,
   techniqueIds: number[],
   collectionId?: number | null,
+  force = false,
 ): Promise<Response> {
   return await fetch(`/api/student/${studentId}/add_techniques`, {
     method: "POST",
@@ -163,16 +294,58 @@ export async function assignTechniquesToStudent(
     body: JSON.stringify({
       technique_ids: techniqueIds,
       collection_id: collectionId ?? null,
+      force,
     }),
     credentials: "include",
   });
 }
 
+export interface GroupAssignment {
+  created: number;
+  /** Techniques the student already had, left as they were. */
+  skipped: number;
+}
+
+// Assigns everything carrying a tag (or one nested under it), or in a
+// curriculum. Pass exactly one of the two.
+export async function assignTechniquesByGroup(
+  studentId: number,
+  group: { tag_id: number } | { collection_id: number },
+): Promise<GroupAssignment> {
+  const response = await fetch(`/api/student/${studentId}/assign_by_tag`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(group),
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to assign techniques: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+// Unassigns student techniques (row ids, not library ids). All or nothing:
+// a 404 means none were removed.
+export async function removeTechniquesFromStudent(
+  studentId: number,
+  studentTechniqueIds: number[],
+): Promise<Response> {
+  return await fetch(`/api/student/${studentId}/techniques`, {
+    method: "DELETE",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ student_technique_ids: studentTechniqueIds }),
+    credentials: "include",
+  });
+}
+
 export async function createAndAssignTechnique(
   studentId: number,
   name: string,
   description: string,
   collectionId?: number | null,
+  force = false,
 ): Promise<Response> {
   return await fetch(`/api/student/${studentId}/create_technique`, {
     method: "POST",
@@ -181,6 +354,7 @@ export async function createAndAssignTechnique(
       name,
       description,
       collection_id: collectionId ?? null,
+      force,
     }),
     credentials: "include",
   });
@@ -194,6 +368,8 @@ export interface Technique {
   status: "red" | "amber" | "green";
   student_notes: string;
   coach_notes: string;
+  /** Hidden from the student; they get empty `coach_notes`. */
+  coach_notes_private: boolean;
   created_at: string;
   updated_at: string;
   last_coach_update_at: string | null;
@@ -201,13 +377,28 @@ export interface Technique {
   last_student_update_at: string | null;
   last_student_update_by_name: string | null;
   has_unseen_activity: boolean;
+  // When the student last opened this row, whoever is viewing.
+  student_last_viewed_at: string | null;
+  // Has the student opened it since the last coach edit? Null when no coach
+  // has edited it.
+  coach_update_read: boolean | null;
   collection_id: number | null;
   collection_name: string | null;
+  // Set while the student is waiting on a coach review; cleared when a coach
+  // changes the status.
+  review_requested_at: string | null;
   tags: Tag[];
+  media: TechniqueMedia[];
   attempt_count: number;
   last_attempt_at: string | null;
 }
 
+// Suffix for "last coach update" lines in the coach's view.
+export function coachUpdateReadLabel(technique: Technique): string {
+  if (technique.coach_update_read === null) return "";
+  return technique.coach_update_read ? " · Read" : " · Not read yet";
+}
+
 export interface LibraryTechnique {
   id: number;
   name: string;
@@ -219,7 +410,210 @@ export interface LibraryTechnique {
 /// Shape returned by `/api/student/<id>/unassigned_techniques`. Includes the
 /// library tags so assignment dialogs can show them as chips.
 export interface AssignableTechnique extends LibraryTechnique {
+  /** The base technique this is a variant of, if any. */
+  parent_technique_id: number | null;
   tags: Tag[];
+  aliases: TechniqueAlias[];
+}
+
+/** Another name for a technique, e.g. "Juji-gatame" for "Armbar". */
+export interface TechniqueAlias {
+  id: number;
+  alias: string;
+  /** Language code such as "pt" or "ja", for translations. */
+  language: string | null;
+}
+
+export function matchesAlias(
+  technique: { aliases: TechniqueAlias[] },
+  needle: string,
+): boolean {
+  return technique.aliases.some((a) => a.alias.toLowerCase().includes(needle));
+}
+
+// Library techniques matching every word of `query` by name, description,
+// tag or alias, best match first.
+export async function searchTechniques(
+  query: string,
+): Promise<AssignableTechnique[]> {
+  const params = new URLSearchParams({ q: query });
+  const response = await fetch(`/api/techniques/search?${params}`, {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to search techniques: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+export async function addTechniqueAlias(
+  techniqueId: number,
+  data: { alias: string; language?: string },
+): Promise<Response> {
+  return await fetch(`/api/techniques/${techniqueId}/aliases`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function removeTechniqueAlias(
+  techniqueId: number,
+  aliasId: number,
+): Promise<Response> {
+  return await fetch(`/api/techniques/${techniqueId}/aliases/${aliasId}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+/** A technique a student should reach green on before being assigned
+ * another. */
+export interface Prerequisite {
+  technique_id: number;
+  technique_name: string;
+}
+
+export async function getTechniquePrerequisites(
+  techniqueId: number,
+): Promise<Prerequisite[]> {
+  const response = await fetch(
+    `/api/techniques/${techniqueId}/prerequisites`,
+    { credentials: "include" },
+  );
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch prerequisites: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+// A 422 means the link would make a technique its own prerequisite.
+export async function addTechniquePrerequisite(
+  techniqueId: number,
+  prerequisiteId: number,
+): Promise<Response> {
+  return await fetch(`/api/techniques/${techniqueId}/prerequisites`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ prerequisite_id: prerequisiteId }),
+    credentials: "include",
+  });
+}
+
+export async function removeTechniquePrerequisite(
+  techniqueId: number,
+  prerequisiteId: number,
+): Promise<Response> {
+  return await fetch(
+    `/api/techniques/${techniqueId}/prerequisites/${prerequisiteId}`,
+    { method: "DELETE", credentials: "include" },
+  );
+}
+
+/** A demonstration linked to a library technique. Exactly one of `url` and
+ * `attachment_id` is set; fetch an attachment through
+ * `/api/attachments/<id>/download-url`. */
+export interface TechniqueMedia {
+  id: number;
+  kind: "video" | "image";
+  title: string;
+  url: string | null;
+  attachment_id: number | null;
+  created_at: string;
+}
+
+export async function getTechniqueMedia(
+  techniqueId: number,
+): Promise<TechniqueMedia[]> {
+  const response = await fetch(`/api/techniques/${techniqueId}/media`, {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch technique media");
+  return await response.json();
+}
+
+export async function addTechniqueMedia(
+  techniqueId: number,
+  data: {
+    kind: TechniqueMedia["kind"];
+    title?: string;
+    url?: string;
+    attachment_id?: number;
+  },
+): Promise<Response> {
+  return await fetch(`/api/techniques/${techniqueId}/media`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function removeTechniqueMedia(
+  techniqueId: number,
+  mediaId: number,
+): Promise<Response> {
+  return await fetch(`/api/techniques/${techniqueId}/media/${mediaId}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+export interface Attachment {
+  id: number;
+  filename: string;
+  content_type: string;
+  bytes: number;
+  uploaded_by_id: number | null;
+  created_at: string;
+}
+
+export interface UploadedFile {
+  attachment: Attachment;
+  url: string;
+  expires_at: string;
+}
+
+/** Stores an image, clip or PDF; link the returned attachment id to a
+ * technique with `addTechniqueMedia`. 415 for other types, 413 when too
+ * large. */
+export async function uploadFile(file: File): Promise<UploadedFile> {
+  const body = new FormData();
+  body.append("file", file);
+  const response = await fetch("/api/uploads", {
+    method: "POST",
+    body,
+    credentials: "include",
+  });
+  if (!response.ok) throw response;
+  return await response.json();
+}
+
+/** Replaces the signed-in user's profile picture (PNG, JPEG, WebP or GIF). */
+export async function uploadAvatar(
+  file: File,
+): Promise<{ avatar_url: string | null }> {
+  const body = new FormData();
+  body.append("file", file);
+  const response = await fetch("/api/profile/avatar", {
+    method: "POST",
+    body,
+    credentials: "include",
+  });
+  if (!response.ok) throw response;
+  return await response.json();
+}
+
+export async function deleteAvatar(): Promise<Response> {
+  return await fetch("/api/profile/avatar", {
+    method: "DELETE",
+    credentials: "include",
+  });
 }
 
 export interface Collection {
@@ -228,8 +622,11 @@ export interface Collection {
   description: string;
   coach_id: number | null;
   created_at: string;
+  // Rank name the curriculum is for, e.g. "Blue".
+  belt_level: string | null;
   technique_count: number;
   student_count: number;
+  // In teaching order.
   techniques: LibraryTechnique[];
   can_create_techniques: boolean;
   can_edit_all_techniques: boolean;
@@ -252,6 +649,7 @@ export async function getCollection(id: number): Promise<Collection> {
 export async function createCollection(data: {
   name: string;
   description?: string;
+  belt_level?: string;
 }): Promise<Response> {
   return await fetch("/api/collections", {
     method: "POST",
@@ -263,7 +661,7 @@ export async function createCollection(data: {
 
 export async function updateCollection(
   id: number,
-  data: { name: string; description?: string },
+  data: { name: string; description?: string; belt_level?: string },
 ): Promise<Response> {
   return await fetch(`/api/collections/${id}`, {
     method: "PUT",
@@ -273,6 +671,19 @@ export async function updateCollection(
   });
 }
 
+// `techniqueIds` must list every technique in the collection once.
+export async function reorderCollectionTechniques(
+  id: number,
+  techniqueIds: number[],
+): Promise<Response> {
+  return await fetch(`/api/collections/${id}/techniques/order`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ technique_ids: techniqueIds }),
+    credentials: "include",
+  });
+}
+
 export async function deleteCollection(id: number): Promise<Response> {
   return await fetch(`/api/collections/${id}`, {
     method: "DELETE",
@@ -292,22 +703,30 @@ export async function addTechniquesToCollection(
   });
 }
 
+// A 409 lists likely duplicates; resend with `force` to create anyway.
 export async function createTechniqueInCollection(
   collectionId: number,
   name: string,
   description: string,
+  force = false,
 ): Promise<Response> {
   return await fetch(`/api/collections/${collectionId}/create_technique`, {
     method: "POST",
     headers: { "Content-Type": "application/json" },
-    body: JSON.stringify({ name, description }),
+    body: JSON.stringify({ name, description, force }),
     credentials: "include",
   });
 }
 
+// `parent_technique_id` groups the technique under a base as a variant;
+// null makes it a base again. A 422 means variants would nest.
 export async function updateLibraryTechnique(
   techniqueId: number,
-  data: { name: string; description: string },
+  data: {
+    name: string;
+    description: string;
+    parent_technique_id?: number | null;
+  },
 ): Promise<Response> {
   return await fetch(`/api/techniques/${techniqueId}`, {
     method: "PUT",
@@ -317,6 +736,39 @@ export async function updateLibraryTechnique(
   });
 }
 
+// Soft delete: assigned students keep it, and restoreTechnique undoes it.
+export async function deleteTechnique(techniqueId: number): Promise<Response> {
+  return await fetch(`/api/technique/${techniqueId}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+export async function restoreTechnique(techniqueId: number): Promise<Response> {
+  return await fetch(`/api/technique/${techniqueId}/restore`, {
+    method: "POST",
+    credentials: "include",
+  });
+}
+
+export interface BulkTechniqueRow {
+  name: string;
+  description: string;
+  tags?: string[];
+}
+
+// All rows or none: a 422 keys each problem `<row>.<field>`, rows from 0.
+export async function createTechniquesBulk(
+  rows: BulkTechniqueRow[],
+): Promise<Response> {
+  return await fetch("/api/techniques/bulk", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(rows),
+    credentials: "include",
+  });
+}
+
 export async function removeTechniqueFromCollection(
   collectionId: number,
   techniqueId: number,
@@ -362,106 +814,910 @@ export interface StudentTechniques {
   can_manage_tags: boolean;
 }
 
-export interface SingleStudentTechnique {
-  technique: Technique;
-  student: User;
-  can_edit_all_techniques: boolean;
-  can_manage_tags: boolean;
+/** `StudentTechniques` as sent, with the techniques as a page. */
+interface StudentTechniquesPage extends Omit<StudentTechniques, "techniques"> {
+  techniques: Paginated<Technique>;
+}
+
+export interface SingleStudentTechnique {
+  technique: Technique;
+  student: User;
+  can_edit_all_techniques: boolean;
+  can_manage_tags: boolean;
+}
+
+export async function getStudentTechniqueDetail(
+  studentTechniqueId: number,
+): Promise<SingleStudentTechnique> {
+  const response = await fetch(`/api/student_technique/${studentTechniqueId}`, {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch technique");
+  return await response.json();
+}
+
+// For an <a download> link: the browser fetches it with the session cookie
+// and saves the CSV the server streams back.
+export function studentTechniquesCsvUrl(studentId: number): string {
+  return `/api/student/${studentId}/techniques/export.csv`;
+}
+
+/** Unset fields don't narrow the list; `tagId` takes in nested tags. */
+export interface StudentTechniqueFilter {
+  tagId?: number;
+  status?: string;
+  /** RFC 3339, e.g. an `updated_at` from an earlier response. */
+  updatedSince?: string;
+}
+
+export async function getStudentTechniques(
+  studentId: number,
+  filter: StudentTechniqueFilter = {},
+): Promise<StudentTechniques> {
+  const params = new URLSearchParams();
+  if (filter.tagId !== undefined) params.append("tag", String(filter.tagId));
+  if (filter.status) params.append("status", filter.status);
+  if (filter.updatedSince) params.append("updated_since", filter.updatedSince);
+  const response = await fetch(
+    `/api/student/${studentId}/techniques?${params.toString()}`,
+    { credentials: "include" },
+  );
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch techniques: ${response.statusText}`);
+  }
+
+  const body: StudentTechniquesPage = await response.json();
+  return { ...body, techniques: body.techniques.items };
+}
+
+export interface TechniqueUpdate {
+  status?: "red" | "amber" | "green";
+  student_notes?: string;
+  coach_notes?: string;
+  /** Sets coach notes to a template's text; don't send with coach_notes. */
+  coach_notes_template_id?: number;
+  /** Coaches only: hides the coach notes from the student. */
+  coach_notes_private?: boolean;
+  technique_name?: string;
+  technique_description?: string;
+}
+
+export type TechniqueField =
+  | "status"
+  | "student_notes"
+  | "coach_notes"
+  | "technique_name"
+  | "technique_description";
+
+/** Body of a successful `updateTechnique`. */
+export interface TechniqueUpdateResult {
+  technique: Technique;
+  /** What the save changed; empty when it matched what was stored. */
+  changed: TechniqueField[];
+}
+
+export interface NoteTemplate {
+  id: number;
+  name: string;
+  body: string;
+  /** null for a template shared with every coach. */
+  owner_id: number | null;
+  updated_at: string;
+}
+
+export async function getNoteTemplates(): Promise<NoteTemplate[]> {
+  const response = await fetch("/api/note_templates", {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch note templates");
+  return await response.json();
+}
+
+export async function createNoteTemplate(data: {
+  name: string;
+  body: string;
+  shared?: boolean;
+}): Promise<Response> {
+  return await fetch("/api/note_templates", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function updateNoteTemplate(
+  id: number,
+  data: { name: string; body: string },
+): Promise<Response> {
+  return await fetch(`/api/note_templates/${id}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function deleteNoteTemplate(id: number): Promise<Response> {
+  return await fetch(`/api/note_templates/${id}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+export async function updateTechnique(
+  techniqueId: number,
+  updates: TechniqueUpdate,
+): Promise<Response> {
+  const response = await fetch(`/api/student_technique/${techniqueId}`, {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(updates),
+    credentials: "include",
+  });
+
+  return response; // Return raw response instead of throwing
+}
+
+/** One item's outcome in `batchUpdateTechniques`, in the order sent. */
+export interface BatchTechniqueUpdateResult {
+  id: number;
+  /** What `updateTechnique` would have answered for this item alone. */
+  status: number;
+  technique: Technique | null;
+  changed: TechniqueField[];
+  error: ValidationErrorResponse | null;
+}
+
+/**
+ * Saves several techniques in one request, such as a night's gradings.
+ * Items that fail are reported and the rest are still saved.
+ */
+export async function batchUpdateTechniques(
+  updates: (TechniqueUpdate & { id: number })[],
+): Promise<BatchTechniqueUpdateResult[]> {
+  const response = await fetch("/api/student_techniques/batch", {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(updates),
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to update techniques: ${response.statusText}`);
+  }
+  const body: { results: BatchTechniqueUpdateResult[] } = await response.json();
+  return body.results;
+}
+
+export interface RankRequirement {
+  tag_id: number;
+  tag_name: string;
+  min_count: number;
+  status: string;
+}
+
+export interface Rank {
+  name: string;
+  requirements: RankRequirement[];
+}
+
+export interface RequirementProgress extends RankRequirement {
+  count: number;
+  met: boolean;
+}
+
+export interface RankEligibility {
+  student_id: number;
+  student_name: string;
+  current_rank: string | null;
+  next_rank: string | null;
+  requirements: RequirementProgress[];
+  eligible: boolean;
+}
+
+export async function getRanks(): Promise<Rank[]> {
+  const response = await fetch("/api/ranks", { credentials: "include" });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch ranks: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function replaceRanks(
+  ranks: {
+    name: string;
+    requirements: { tag_id: number; min_count: number; status?: string }[];
+  }[],
+): Promise<Response> {
+  return await fetch("/api/ranks", {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ ranks }),
+    credentials: "include",
+  });
+}
+
+export async function getRankEligibility(): Promise<RankEligibility[]> {
+  const response = await fetch("/api/ranks/eligibility", {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch rank eligibility: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function setStudentRank(
+  studentId: number,
+  rank: string | null,
+): Promise<Response> {
+  return await fetch(`/api/student/${studentId}/rank`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ rank }),
+    credentials: "include",
+  });
+}
+
+export interface JournalEntry {
+  id: number;
+  technique_id: number | null;
+  technique_name: string | null;
+  entry_date: string;
+  body: string;
+  /** Readable by coaches as well as the author. */
+  shared: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface JournalEntryData {
+  body: string;
+  technique_id?: number | null;
+  /** YYYY-MM-DD; the server uses today when omitted. */
+  entry_date?: string;
+  /** Private to the author unless true. */
+  shared?: boolean;
+}
+
+export async function getJournal(techniqueId?: number): Promise<JournalEntry[]> {
+  const query = techniqueId === undefined ? "" : `?technique_id=${techniqueId}`;
+  const response = await fetch(`/api/journal${query}`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch journal: ${response.status}`);
+  }
+  return await response.json();
+}
+
+// Only the entries the student has shared with their coaches.
+export async function getStudentJournal(
+  studentId: number,
+): Promise<JournalEntry[]> {
+  const response = await fetch(`/api/student/${studentId}/journal`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch student journal: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function createJournalEntry(
+  data: JournalEntryData,
+): Promise<Response> {
+  return await fetch("/api/journal", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function updateJournalEntry(
+  entryId: number,
+  data: JournalEntryData,
+): Promise<Response> {
+  return await fetch(`/api/journal/${entryId}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function deleteJournalEntry(entryId: number): Promise<Response> {
+  return await fetch(`/api/journal/${entryId}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+export interface ClassSession {
+  id: number;
+  title: string;
+  starts_at: string;
+  duration_minutes: number;
+  coach_id: number | null;
+  student_ids: number[];
+}
+
+export interface ClassSessionData {
+  title?: string;
+  starts_at: string;
+  /** Minutes; the server uses 60 when omitted. */
+  duration_minutes?: number;
+}
+
+export interface AttendanceRecord {
+  class_id: number;
+  title: string;
+  starts_at: string;
+  duration_minutes: number;
+}
+
+export async function createClass(data: ClassSessionData): Promise<Response> {
+  return await fetch("/api/classes", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+// Dates are YYYY-MM-DD, both inclusive; the server defaults to the last
+// four weeks.
+export async function getClasses(
+  from?: string,
+  to?: string,
+): Promise<ClassSession[]> {
+  const params = new URLSearchParams();
+  if (from) params.append("from", from);
+  if (to) params.append("to", to);
+  const response = await fetch(`/api/classes?${params.toString()}`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch classes: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function setClassAttendance(
+  classId: number,
+  studentIds: number[],
+): Promise<Response> {
+  return await fetch(`/api/classes/${classId}/attendance`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ student_ids: studentIds }),
+    credentials: "include",
+  });
+}
+
+export async function getStudentAttendance(
+  studentId: number,
+): Promise<AttendanceRecord[]> {
+  const response = await fetch(`/api/student/${studentId}/attendance`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch attendance: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export interface ScheduleSlot {
+  id: number;
+  title: string;
+  /** ISO weekday: 1 is Monday, 7 is Sunday. */
+  weekday: number;
+  /** HH:MM:SS, wall-clock in `timezone`. */
+  start_time: string;
+  duration_minutes: number;
+  timezone: string;
+  coach_id: number | null;
+  starts_on: string;
+  ends_on: string | null;
+}
+
+export interface ScheduleSlotData {
+  title: string;
+  weekday: number;
+  start_time: string;
+  duration_minutes?: number;
+  /** Defaults to the caller's timezone, else UTC. */
+  timezone?: string;
+  starts_on?: string;
+  ends_on?: string | null;
+}
+
+export async function getSchedule(): Promise<ScheduleSlot[]> {
+  const response = await fetch("/api/schedule", { credentials: "include" });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch schedule: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function createScheduleSlot(
+  data: ScheduleSlotData,
+): Promise<Response> {
+  return await fetch("/api/schedule", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function updateScheduleSlot(
+  slotId: number,
+  data: ScheduleSlotData,
+): Promise<Response> {
+  return await fetch(`/api/schedule/${slotId}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function deleteScheduleSlot(slotId: number): Promise<Response> {
+  return await fetch(`/api/schedule/${slotId}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+// Calendar apps subscribe to this without signing in; it 404s unless the
+// public_sharing flag is on.
+export const SCHEDULE_FEED_PATH = "/api/schedule.ics";
+
+export type GradingOutcome = "passed" | "failed" | "deferred";
+
+export interface GradingResult {
+  technique_id: number;
+  technique_name: string;
+  /** null until the coach has graded it. */
+  passed: boolean | null;
+  note: string;
+}
+
+export interface Grading {
+  id: number;
+  student_id: number;
+  collection_id: number | null;
+  collection_name: string;
+  coach_id: number | null;
+  opened_at: string;
+  closed_at: string | null;
+  outcome: GradingOutcome | null;
+  notes: string;
+  results: GradingResult[];
+}
+
+export interface GradingSummary {
+  id: number;
+  student_id: number;
+  collection_id: number | null;
+  collection_name: string;
+  coach_id: number | null;
+  opened_at: string;
+  closed_at: string | null;
+  outcome: GradingOutcome | null;
+  passed: number;
+  failed: number;
+  pending: number;
+}
+
+export async function openGrading(
+  studentId: number,
+  collectionId: number,
+): Promise<Response> {
+  return await fetch("/api/gradings", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ student_id: studentId, collection_id: collectionId }),
+    credentials: "include",
+  });
+}
+
+export async function getGradings(studentId?: number): Promise<GradingSummary[]> {
+  const query = studentId === undefined ? "" : `?student_id=${studentId}`;
+  const response = await fetch(`/api/gradings${query}`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch gradings: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function getGrading(gradingId: number): Promise<Grading> {
+  const response = await fetch(`/api/gradings/${gradingId}`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch grading: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function recordGradingResult(
+  gradingId: number,
+  techniqueId: number,
+  passed: boolean | null,
+  note: string = "",
+): Promise<Response> {
+  return await fetch(`/api/gradings/${gradingId}/results/${techniqueId}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ passed, note }),
+    credentials: "include",
+  });
+}
+
+export async function closeGrading(
+  gradingId: number,
+  outcome: GradingOutcome,
+  notes: string = "",
+): Promise<Response> {
+  return await fetch(`/api/gradings/${gradingId}/close`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ outcome, notes }),
+    credentials: "include",
+  });
+}
+
+export async function bulkUpdateStatus(
+  studentId: number,
+  data: {
+    status: "red" | "amber" | "green";
+    tag_id?: number;
+    from_status?: "red" | "amber" | "green";
+  },
+): Promise<Response> {
+  return await fetch(`/api/student/${studentId}/techniques/bulk_status`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function getStudents(
+  sortBy?: string,
+  includeArchived: boolean = false,
+): Promise<User[]> {
+  let url = "/api/students?";
+
+  const params = new URLSearchParams();
+  if (sortBy) {
+    params.append("sort_by", sortBy);
+  }
+  if (includeArchived) {
+    params.append("include_archived", "true");
+  }
+
+  url += params.toString();
+
+  const response = await fetch(url, {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error("Failed to fetch students");
+  }
+
+  const page: Paginated<User> = await response.json();
+  return page.items;
+}
+
+export interface ProfileUpdateData {
+  // null clears the name so the username is shown instead.
+  display_name: string | null;
+  username?: string;
+}
+
+export async function updateUserProfile(
+  data: ProfileUpdateData,
+): Promise<Response> {
+  const response = await fetch("/api/profile", {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+
+  return response; // Return raw response
+}
+
+export async function updateUserTimezone(
+  timezone: string | null,
+): Promise<Response> {
+  const response = await fetch("/api/profile/timezone", {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ timezone }),
+    credentials: "include",
+  });
+
+  return response; // Return raw response
+}
+
+// Free-form UI settings (theme, default sort, collapsed sections) that follow
+// the user across devices. The server stores whatever object it is given.
+export type Preferences = Record<string, JsonValue>;
+
+export async function getPreferences(): Promise<Preferences> {
+  const response = await fetch("/api/me/preferences", {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch preferences: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+// Replaces the stored object, so callers merge with what they loaded first.
+export async function updatePreferences(
+  preferences: Preferences,
+): Promise<Response> {
+  const response = await fetch("/api/me/preferences", {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(preferences),
+    credentials: "include",
+  });
+
+  return response;
+}
+
+export type DigestFrequency = "off" | "daily" | "weekly";
+
+// Which emails the user wants. Users who never saved get the server defaults.
+export interface NotificationPreferences {
+  email_on_assignment: boolean;
+  email_on_coach_note: boolean;
+  digest: DigestFrequency;
+}
+
+export async function getNotificationPreferences(): Promise<NotificationPreferences> {
+  const response = await fetch("/api/me/notification_preferences", {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch notification preferences: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+export async function updateNotificationPreferences(
+  preferences: NotificationPreferences,
+): Promise<Response> {
+  return await fetch("/api/me/notification_preferences", {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(preferences),
+    credentials: "include",
+  });
+}
+
+export type TokenScope = "read" | "write";
+
+// A personal access token, for scripts sending `Authorization: Bearer`.
+export interface ApiToken {
+  id: number;
+  name: string;
+  token_prefix: string;
+  scopes: TokenScope[];
+  created_at: string;
+  expires_at: string | null;
+  last_used_at: string | null;
+}
+
+// Only returned when the token is created; it can't be shown again.
+export interface CreatedApiToken extends ApiToken {
+  secret: string;
+}
+
+export interface ApiTokenData {
+  name: string;
+  scopes: TokenScope[];
+  expires_in_days?: number;
+}
+
+export async function listApiTokens(): Promise<ApiToken[]> {
+  const response = await fetch("/api/me/api_tokens", {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch API tokens: ${response.statusText}`);
+  }
+
+  return await response.json();
+}
+
+export async function createApiToken(data: ApiTokenData): Promise<Response> {
+  return await fetch("/api/me/api_tokens", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
+export async function revokeApiToken(id: number): Promise<Response> {
+  return await fetch(`/api/me/api_tokens/${id}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
+}
+
+export type NotificationKind = "assignment" | "coach_note";
+
+// In-app notifications are kept whatever the email preferences say.
+export interface Notification {
+  id: number;
+  kind: NotificationKind;
+  student_technique_id: number | null;
+  technique_name: string | null;
+  actor_name: string | null;
+  created_at: string;
+  read_at: string | null;
+}
+
+export interface NotificationsResponse {
+  /** Across all pages. */
+  unread: number;
+  notifications: Paginated<Notification>;
+}
+
+export async function getNotifications(
+  page?: number,
+  perPage?: number,
+): Promise<NotificationsResponse> {
+  const params = new URLSearchParams();
+  if (page) params.set("page", String(page));
+  if (perPage) params.set("per_page", String(perPage));
+  const search = params.toString();
+  const response = await fetch(
+    `/api/notifications${search ? `?${search}` : ""}`,
+    { credentials: "include" },
+  );
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch notifications: ${response.statusText}`);
+  }
+
+  return await response.json();
 }
 
-export async function getStudentTechniqueDetail(
-  studentTechniqueId: number,
-): Promise<SingleStudentTechnique> {
-  const response = await fetch(`/api/student_technique/${studentTechniqueId}`, {
+export async function markNotificationRead(id: number): Promise<Response> {
+  return await fetch(`/api/notifications/${id}/read`, {
+    method: "POST",
     credentials: "include",
   });
-  if (!response.ok) throw new Error("Failed to fetch technique");
-  return await response.json();
 }
 
-export async function getStudentTechniques(
-  studentId: number,
-): Promise<StudentTechniques> {
-  const response = await fetch(`/api/student/${studentId}/techniques`, {
+export interface StatusTransition {
+  from_status: string;
+  to_status: string;
+  min_role: "coach" | "admin";
+}
+
+export async function getStatusTransitions(): Promise<StatusTransition[]> {
+  const response = await fetch("/api/status_transitions", {
     credentials: "include",
   });
 
   if (!response.ok) {
-    throw new Error(`Failed to fetch techniques: ${response.statusText}`);
+    throw new Error(
+      `Failed to fetch status transitions: ${response.statusText}`,
+    );
   }
 
-  return await response.json();
-}
-
-export interface TechniqueUpdate {
-  status?: "red" | "amber" | "green";
-  student_notes?: string;
-  coach_notes?: string;
-  technique_name?: string;
-  technique_description?: string;
+  const data: { transitions: StatusTransition[] } = await response.json();
+  return data.transitions;
 }
 
-export async function updateTechnique(
-  techniqueId: number,
-  updates: TechniqueUpdate,
+export async function replaceStatusTransitions(
+  transitions: StatusTransition[],
 ): Promise<Response> {
-  const response = await fetch(`/api/student_technique/${techniqueId}`, {
+  const response = await fetch("/api/status_transitions", {
     method: "PUT",
     headers: {
       "Content-Type": "application/json",
     },
-    body: JSON.stringify(updates),
+    body: JSON.stringify({ transitions }),
     credentials: "include",
   });
 
-  return response; // Return raw response instead of throwing
+  return response;
 }
 
-export async function getStudents(
-  sortBy?: string,
-  includeArchived: boolean = false,
-): Promise<User[]> {
-  let url = "/api/students?";
-
-  const params = new URLSearchParams();
-  if (sortBy) {
-    params.append("sort_by", sortBy);
-  }
-  if (includeArchived) {
-    params.append("include_archived", "true");
-  }
-
-  url += params.toString();
+export interface StatusLevel {
+  name: string;
+  color: string;
+  position: number;
+}
 
-  const response = await fetch(url, {
+export async function getStatuses(): Promise<StatusLevel[]> {
+  const response = await fetch("/api/statuses", {
     credentials: "include",
   });
 
   if (!response.ok) {
-    throw new Error("Failed to fetch students");
+    throw new Error(`Failed to fetch statuses: ${response.statusText}`);
   }
 
-  return await response.json();
+  const data: StatusLevel[] = await response.json();
+  return data;
 }
 
-export interface ProfileUpdateData {
-  display_name: string;
-  username?: string;
+export async function createStatus(
+  name: string,
+  color: string,
+): Promise<Response> {
+  return fetch("/api/statuses", {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ name, color }),
+    credentials: "include",
+  });
 }
 
-export async function updateUserProfile(
-  data: ProfileUpdateData,
+export async function updateStatusColor(
+  name: string,
+  color: string,
 ): Promise<Response> {
-  const response = await fetch("/api/profile", {
+  return fetch(`/api/statuses/${encodeURIComponent(name)}`, {
     method: "PUT",
     headers: {
       "Content-Type": "application/json",
     },
-    body: JSON.stringify(data),
+    body: JSON.stringify({ color }),
     credentials: "include",
   });
+}
 
-  return response; // Return raw response
+export async function reorderStatuses(names: string[]): Promise<Response> {
+  return fetch("/api/statuses/order", {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ names }),
+    credentials: "include",
+  });
+}
+
+export async function deleteStatus(name: string): Promise<Response> {
+  return fetch(`/api/statuses/${encodeURIComponent(name)}`, {
+    method: "DELETE",
+    credentials: "include",
+  });
 }
 
 export interface PasswordUpdateData {
@@ -486,7 +1742,7 @@ export async function updatePassword(
 
 export interface UserRegistrationData {
   username: string;
-  display_name: string;
+  display_name: string | null;
   password: string;
   confirm_password: string;
   role: string;
@@ -507,7 +1763,7 @@ export async function registerUser(
 
 export interface UserUpdateData {
   username?: string;
-  display_name?: string;
+  display_name?: string | null;
   password?: string;
   archived?: boolean;
   graduated?: boolean;
@@ -528,8 +1784,120 @@ export async function updateUser(
   });
 }
 
-export async function getAllTags(): Promise<Tag[]> {
-  const response = await fetch("/api/tags", {
+// The students a coach looks after. An empty list means the coach sees
+// every student.
+export async function getCoachStudents(coachId: number): Promise<number[]> {
+  const response = await fetch(`/api/admin/coaches/${coachId}/students`, {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch coach students");
+  const body: { student_ids: number[] } = await response.json();
+  return body.student_ids;
+}
+
+export async function setCoachStudents(
+  coachId: number,
+  studentIds: number[],
+): Promise<Response> {
+  return await fetch(`/api/admin/coaches/${coachId}/students`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ student_ids: studentIds }),
+    credentials: "include",
+  });
+}
+
+export interface CoachActivity {
+  user_id: number;
+  name: string;
+  techniques_created: number;
+  assignments: number;
+  notes_written: number;
+}
+
+export interface CoachReport {
+  from: string;
+  to: string;
+  coaches: CoachActivity[];
+}
+
+/** `from` and `to` are YYYY-MM-DD; the server defaults to the last 30 days. */
+export async function getCoachReport(
+  from?: string,
+  to?: string,
+): Promise<CoachReport> {
+  const params = new URLSearchParams();
+  if (from) params.set("from", from);
+  if (to) params.set("to", to);
+  const query = params.toString();
+  const response = await fetch(
+    `/api/admin/coach_report${query ? `?${query}` : ""}`,
+    { credentials: "include" },
+  );
+  if (!response.ok) {
+    throw new Error(`Failed to fetch coach report: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export type ActivityKind =
+  | "technique_updated"
+  | "review_requested"
+  | "attempt_logged";
+
+export interface ActivityEvent {
+  kind: ActivityKind;
+  student_id: number;
+  student_technique_id: number;
+  at: string;
+}
+
+/**
+ * Opens the coach dashboard's live feed of student activity. Events are
+ * hints to refetch; the socket closes on sign-out or a restart, so callers
+ * reconnect from `onclose`.
+ */
+export function openCoachLiveFeed(
+  onEvent: (event: ActivityEvent) => void,
+): WebSocket {
+  const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
+  const socket = new WebSocket(
+    `${protocol}//${window.location.host}/api/coach/live`,
+  );
+  socket.onmessage = (message) => {
+    onEvent(JSON.parse(message.data) as ActivityEvent);
+  };
+  return socket;
+}
+
+export type JobStatus = "queued" | "running" | "succeeded" | "failed";
+
+export interface BackgroundJob {
+  id: number;
+  kind: string;
+  requested_by_id: number | null;
+  status: JobStatus;
+  /** The operation's usual response body once it has succeeded. */
+  result: JsonValue | null;
+  error: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+export async function getJob(jobId: number): Promise<BackgroundJob> {
+  const response = await fetch(`/api/jobs/${jobId}`, {
+    credentials: "include",
+  });
+  if (!response.ok) {
+    throw new Error(`Failed to fetch job: ${response.status}`);
+  }
+  return await response.json();
+}
+
+export async function getAllTags(includeArchived = false): Promise<Tag[]> {
+  const query = includeArchived ? "?include_archived=true" : "";
+  const response = await fetch(`/api/tags${query}`, {
     credentials: "include",
   });
 
@@ -541,19 +1909,51 @@ export async function getAllTags(): Promise<Tag[]> {
   return data.tags;
 }
 
-export async function createTag(name: string): Promise<Response> {
+export async function getTagTree(
+  includeArchived = false,
+): Promise<TagNode[]> {
+  const query = includeArchived ? "?include_archived=true" : "";
+  const response = await fetch(`/api/tags/tree${query}`, {
+    credentials: "include",
+  });
+
+  if (!response.ok) {
+    throw new Error(`Failed to fetch tag tree: ${response.statusText}`);
+  }
+
+  const data = await response.json();
+  return data.tags;
+}
+
+export async function createTag(
+  name: string,
+  details: TagDetails = {},
+): Promise<Response> {
   const response = await fetch("/api/tags", {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
     },
-    body: JSON.stringify({ name }),
+    body: JSON.stringify({ name, ...details }),
     credentials: "include",
   });
 
   return response;
 }
 
+export async function updateTag(
+  tagId: number,
+  name: string,
+  details: TagDetails = {},
+): Promise<Response> {
+  return await fetch(`/api/tags/${tagId}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ name, ...details }),
+    credentials: "include",
+  });
+}
+
 export async function deleteTag(tagId: number): Promise<Response> {
   const response = await fetch(`/api/tags/${tagId}`, {
     method: "DELETE",
@@ -563,6 +1963,18 @@ export async function deleteTag(tagId: number): Promise<Response> {
   return response;
 }
 
+export async function setTagArchived(
+  tagId: number,
+  archived: boolean,
+): Promise<Response> {
+  return await fetch(`/api/tags/${tagId}/archived`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ archived }),
+    credentials: "include",
+  });
+}
+
 export async function addTagToTechnique(
   techniqueId: number,
   tagId: number,
@@ -594,13 +2006,55 @@ export async function removeTagFromTechnique(
   return response;
 }
 
-export async function getAllUsers(): Promise<User[]> {
-  const response = await fetch("/api/admin/users", {
-    credentials: "include",
-  });
+/**
+ * One page of a longer list; `page` counts from 1. `next` and `prev` are
+ * ready-to-fetch URLs, null at either end. The student lists only page when
+ * asked to with `page` or `per_page`; otherwise everything is one page.
+ */
+export interface Paginated<T> {
+  items: T[];
+  total: number;
+  page: number;
+  per_page: number;
+  next: string | null;
+  prev: string | null;
+}
+
+export interface UserListQuery {
+  role?: Role;
+  archived?: boolean;
+  search?: string;
+  sort?: "name" | "username" | "role";
+  desc?: boolean;
+  page?: number;
+  /** At most 200; the server defaults to 50. */
+  perPage?: number;
+}
+
+export async function getAllUsers(
+  query: UserListQuery = {},
+): Promise<Paginated<User>> {
+  const params = new URLSearchParams();
+  if (query.role) params.set("role", query.role);
+  if (query.archived !== undefined) {
+    params.set("archived", String(query.archived));
+  }
+  if (query.search) params.set("search", query.search);
+  if (query.sort) params.set("sort", query.sort);
+  if (query.desc) params.set("desc", "true");
+  if (query.page) params.set("page", String(query.page));
+  if (query.perPage) params.set("per_page", String(query.perPage));
+  const search = params.toString();
+  const response = await fetch(
+    `/api/admin/users${search ? `?${search}` : ""}`,
+    { credentials: "include" },
+  );
 
-  const users: User[] = await response.json();
-  return users.map((u) => ({ ...u, role: normaliseRole(u.role) }));
+  const page: Paginated<User> = await response.json();
+  return {
+    ...page,
+    items: page.items.map((u) => ({ ...u, role: normaliseRole(u.role) })),
+  };
 }
 
 export async function markStudentTechniqueSeen(id: number): Promise<void> {
@@ -614,6 +2068,13 @@ export async function markStudentTechniqueSeen(id: number): Promise<void> {
   }
 }
 
+export async function requestReview(id: number): Promise<Response> {
+  return await fetch(`/api/student_technique/${id}/request_review`, {
+    method: "POST",
+    credentials: "include",
+  });
+}
+
 export interface InviteUserData {
   display_name: string;
   role: string;
@@ -636,6 +2097,22 @@ export async function inviteUser(
   });
 }
 
+export interface InvitationData {
+  role: string;
+  display_name?: string;
+}
+
+export async function createInvitation(
+  data: InvitationData,
+): Promise<Response> {
+  return await fetch("/api/admin/invitations", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
 export interface InviteInfo {
   display_name: string;
   email: string | null;
@@ -665,6 +2142,23 @@ export async function claimInvite(
   });
 }
 
+export interface InviteRegistrationData extends ClaimInviteData {
+  display_name?: string;
+}
+
+// 410 once the invitation is used or expired.
+export async function registerWithInvite(
+  token: string,
+  data: InviteRegistrationData,
+): Promise<Response> {
+  return await fetch(`/api/register/invite/${encodeURIComponent(token)}`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(data),
+    credentials: "include",
+  });
+}
+
 export interface SelfRegisterData {
   username: string;
   password: string;
@@ -736,7 +2230,10 @@ export interface LibraryTechniqueRow {
   id: number;
   name: string;
   description: string;
+  /** The base technique this is a variant of, if any. */
+  parent_technique_id: number | null;
   tags: Tag[];
+  aliases: TechniqueAlias[];
   /** IDs of every collection this technique belongs to. */
   collection_ids: number[];
   collection_count: number;
@@ -813,6 +2310,47 @@ export interface AttemptSummary {
   total: number;
 }
 
+// Completion for one tag or curriculum: green out of the techniques in it
+// that are assigned to the student. `techniques` counts the whole library.
+export interface GroupProgress {
+  id: number;
+  name: string;
+  techniques: number;
+  assigned: number;
+  green: number;
+  percent: number;
+}
+
+export interface StudentAnalytics {
+  tags: GroupProgress[];
+  curricula: GroupProgress[];
+}
+
+export interface StatusCount {
+  status: string;
+  count: number;
+}
+
+export interface TagStatusBreakdown {
+  id: number;
+  name: string;
+  total: number;
+  statuses: StatusCount[];
+  last_updated_at: string | null;
+}
+
+// Counts only; statuses with no techniques are left out.
+export interface StudentProgress {
+  total: number;
+  statuses: StatusCount[];
+  tags: TagStatusBreakdown[];
+  last_updated_at: string | null;
+  last_coach_update_at: string | null;
+  last_student_update_at: string | null;
+  classes_attended: number;
+  last_attended_at: string | null;
+}
+
 export interface AttemptBucket {
   date: string;
   count: number;
@@ -906,6 +2444,48 @@ export async function getAttemptSummary(
   return await response.json();
 }
 
+export async function getStudentAnalytics(
+  studentId: number,
+): Promise<StudentAnalytics> {
+  const response = await fetch(`/api/student/${studentId}/analytics`, {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch student analytics");
+  return await response.json();
+}
+
+export async function getStudentProgress(
+  studentId: number,
+): Promise<StudentProgress> {
+  const response = await fetch(`/api/student/${studentId}/progress`, {
+    credentials: "include",
+  });
+  if (!response.ok) throw new Error("Failed to fetch student progress");
+  return await response.json();
+}
+
+export interface StudentTechniqueRevision {
+  id: number;
+  field: "status" | "student_notes" | "coach_notes";
+  old_value: string;
+  new_value: string;
+  changed_at: string;
+  changed_by_id: number | null;
+  changed_by_name: string | null;
+}
+
+export async function getStudentTechniqueHistory(
+  studentTechniqueId: number,
+): Promise<StudentTechniqueRevision[]> {
+  const response = await fetch(
+    `/api/student_technique/${studentTechniqueId}/history`,
+    { credentials: "include" },
+  );
+  if (!response.ok) throw new Error("Failed to fetch technique history");
+  const data: { revisions: StudentTechniqueRevision[] } = await response.json();
+  return data.revisions;
+}
+
 export async function getAttemptHeatmap(
   studentId: number,
   from?: string,
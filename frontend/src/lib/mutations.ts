@@ -20,6 +20,7 @@ import {
   removeTagFromTechnique,
   removeTechniqueFromCollection,
   reorderVideos,
+  requestReview,
   resetUserClaim,
   setStudentGraduated,
   setVideoGlobalHidden,
@@ -27,6 +28,7 @@ import {
   updateAttempt,
   updateCollection,
   updateLibraryTechnique,
+  updateNotificationPreferences,
   updatePassword,
   updateTechnique,
   updateUser,
@@ -34,6 +36,8 @@ import {
   updateVideo,
 } from "./api";
 import type {
+  NotificationPreferences,
+  Paginated,
   SingleStudentTechnique,
   StudentTechniques,
   Technique,
@@ -73,6 +77,17 @@ export function useUpdatePassword() {
   });
 }
 
+export function useUpdateNotificationPreferences() {
+  const qc = useQueryClient();
+  return useMutation({
+    mutationFn: async (preferences: NotificationPreferences) =>
+      unwrap(await updateNotificationPreferences(preferences)),
+    onSuccess: (_res, preferences) => {
+      qc.setQueryData(qk.notificationPreferences(), preferences);
+    },
+  });
+}
+
 // ============================================================
 // Users / admin
 // ============================================================
@@ -107,9 +122,14 @@ export function useToggleUserArchived() {
       unwrap(await updateUser(vars.userId, { archived: vars.archived })),
     onMutate: async ({ userId, archived }) => {
       await qc.cancelQueries({ queryKey: qk.users() });
-      const previous = qc.getQueryData<User[]>(qk.users());
-      qc.setQueryData<User[]>(qk.users(), (prev) =>
-        prev?.map((u) => (u.id === userId ? { ...u, archived } : u)),
+      const previous = qc.getQueryData<Paginated<User>>(qk.users());
+      qc.setQueryData<Paginated<User>>(qk.users(), (prev) =>
+        prev && {
+          ...prev,
+          items: prev.items.map((u) =>
+            u.id === userId ? { ...u, archived } : u,
+          ),
+        },
       );
       return { previous };
     },
@@ -279,6 +299,7 @@ export function useUpdateTechnique() {
       Promise.all([
         qc.invalidateQueries({ queryKey: qk.studentTechnique(studentTechniqueId) }),
         qc.invalidateQueries({ predicate: qk.matches.anyStudentTechniques }),
+        qc.invalidateQueries({ predicate: qk.matches.anyStudentAnalytics }),
         qc.invalidateQueries({ queryKey: ["students"] }),
       ]),
   });
@@ -354,6 +375,19 @@ export function useMarkStudentTechniqueSeen() {
   });
 }
 
+export function useRequestReview() {
+  const qc = useQueryClient();
+  return useMutation({
+    mutationFn: async (studentTechniqueId: number) =>
+      unwrap(await requestReview(studentTechniqueId)),
+    onSuccess: (_res, studentTechniqueId) =>
+      Promise.all([
+        qc.invalidateQueries({ queryKey: qk.studentTechnique(studentTechniqueId) }),
+        qc.invalidateQueries({ predicate: qk.matches.anyStudentTechniques }),
+      ]),
+  });
+}
+
 // ============================================================
 // Assignment
 // ============================================================
@@ -377,6 +411,7 @@ export function useAssignTechniquesToStudent() {
       Promise.all([
         qc.invalidateQueries({ queryKey: qk.studentTechniques(studentId) }),
         qc.invalidateQueries({ queryKey: qk.studentUnassigned(studentId) }),
+        qc.invalidateQueries({ queryKey: qk.studentAnalytics(studentId) }),
         qc.invalidateQueries({ queryKey: ["students"] }),
       ]),
   });
@@ -390,6 +425,7 @@ export function useCreateAndAssignTechnique() {
       name: string;
       description: string;
       collectionId?: number | null;
+      force?: boolean;
     }) =>
       unwrap(
         await createAndAssignTechnique(
@@ -397,12 +433,14 @@ export function useCreateAndAssignTechnique() {
           vars.name,
           vars.description,
           vars.collectionId,
+          vars.force,
         ),
       ),
     onSuccess: (_res, { studentId }) =>
       Promise.all([
         qc.invalidateQueries({ queryKey: qk.studentTechniques(studentId) }),
         qc.invalidateQueries({ queryKey: qk.studentUnassigned(studentId) }),
+        qc.invalidateQueries({ queryKey: qk.studentAnalytics(studentId) }),
         qc.invalidateQueries({ queryKey: qk.libraryStats() }),
         qc.invalidateQueries({ queryKey: qk.collections() }),
         qc.invalidateQueries({ queryKey: ["students"] }),
@@ -419,6 +457,7 @@ export function useAssignCollectionToStudent() {
       Promise.all([
         qc.invalidateQueries({ queryKey: qk.studentTechniques(studentId) }),
         qc.invalidateQueries({ queryKey: qk.studentUnassigned(studentId) }),
+        qc.invalidateQueries({ queryKey: qk.studentAnalytics(studentId) }),
         qc.invalidateQueries({ queryKey: qk.collection(collectionId) }),
         qc.invalidateQueries({ queryKey: qk.collectionStudents(collectionId) }),
         qc.invalidateQueries({ queryKey: ["students"] }),
@@ -15,11 +15,14 @@ import {
   getLibraryStats,
   getLibraryTechniques,
   getLibraryTechniqueStats,
+  getNotificationPreferences,
   getRecentAttemptsForStudent,
+  getStudentAnalytics,
   getStudentTechniqueDetail,
   getStudentTechniques,
   getStudents,
   getTechniquesForAssignment,
+  getVersion,
   getVideoStats,
   getVideoStatus,
   listAttempts,
@@ -40,6 +43,13 @@ export function useCurrentUser() {
   });
 }
 
+export function useNotificationPreferences() {
+  return useQuery({
+    queryKey: qk.notificationPreferences(),
+    queryFn: getNotificationPreferences,
+  });
+}
+
 export function useCapabilities() {
   return useQuery({
     queryKey: qk.capabilities(),
@@ -48,12 +58,23 @@ export function useCapabilities() {
   });
 }
 
+// Polled (and refetched on focus) so a long-open tab notices a deploy.
+export function useServerVersion() {
+  return useQuery({
+    queryKey: qk.version(),
+    queryFn: getVersion,
+    refetchInterval: 10 * 60 * 1000,
+  });
+}
+
 // ---- Users / students ----
 
+// The admin page filters and sorts client-side, so it asks for the largest
+// page the server allows.
 export function useAllUsers() {
   return useQuery({
     queryKey: qk.users(),
-    queryFn: getAllUsers,
+    queryFn: () => getAllUsers({ perPage: 200 }),
   });
 }
 
@@ -114,6 +135,13 @@ export function useAttemptSummary(studentId: number | undefined) {
   });
 }
 
+export function useStudentAnalytics(studentId: number | undefined) {
+  return useQuery({
+    queryKey: qk.studentAnalytics(studentId ?? 0),
+    queryFn: whenId(studentId, getStudentAnalytics),
+  });
+}
+
 export function useAttemptHeatmap(studentId: number | undefined) {
   return useQuery({
     queryKey: qk.attemptHeatmap(studentId ?? 0),
@@ -5,6 +5,8 @@ import type { Query } from "@tanstack/react-query";
 export const qk = {
   currentUser: () => ["currentUser"] as const,
   capabilities: () => ["capabilities"] as const,
+  version: () => ["version"] as const,
+  notificationPreferences: () => ["notificationPreferences"] as const,
 
   users: () => ["users"] as const,
 
@@ -16,6 +18,8 @@ export const qk = {
   studentUnassigned: (id: number) => ["student", id, "unassignedTechniques"] as const,
   attemptSummary: (id: number) => ["student", id, "attemptSummary"] as const,
   attemptHeatmap: (id: number) => ["student", id, "attemptHeatmap"] as const,
+  studentAnalytics: (id: number) => ["student", id, "analytics"] as const,
+  studentProgress: (id: number) => ["student", id, "progress"] as const,
   recentAttempts: (id: number, limit: number) =>
     ["student", id, "recentAttempts", limit] as const,
 
@@ -54,6 +58,8 @@ export const qk = {
   matches: {
     anyStudentTechniques: (q: Query) =>
       q.queryKey[0] === "student" && q.queryKey[2] === "techniques",
+    anyStudentAnalytics: (q: Query) =>
+      q.queryKey[0] === "student" && q.queryKey[2] === "analytics",
     anyStudentTechniqueDetail: (q: Query) => q.queryKey[0] === "studentTechnique",
     anyStudentTechniqueScope: (q: Query) =>
       (q.queryKey[0] === "student" && q.queryKey[2] === "techniques") ||
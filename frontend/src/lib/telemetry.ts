@@ -15,8 +15,22 @@ import { BatchSpanProcessor } from "@opentelemetry/sdk-trace-web";
 import { resourceFromAttributes } from "@opentelemetry/resources";
 import { ATTR_SERVICE_NAME } from "@opentelemetry/semantic-conventions";
 
+/**
+ * The backend issues `otel_session_id` as a script-readable cookie and tags
+ * its request spans with it, so using the same id here joins the two. The
+ * localStorage copy only covers a browser that somehow has no cookie yet.
+ */
 function getOrCreateSessionId(): string {
   const key = "otel_session_id";
+  const fromCookie = document.cookie
+    .split("; ")
+    .find((part) => part.startsWith(`${key}=`))
+    ?.slice(key.length + 1);
+  if (fromCookie) {
+    localStorage.setItem(key, fromCookie);
+    return fromCookie;
+  }
+
   let sessionId = localStorage.getItem(key);
 
   if (!sessionId) {
@@ -1,6 +1,26 @@
+// Any JSON value, for payloads whose shape the server does not fix.
+export type JsonValue =
+  | string
+  | number
+  | boolean
+  | null
+  | JsonValue[]
+  | { [key: string]: JsonValue };
+
+// One field error in machine-readable form. `code` is a stable key such as
+// `password.too_short`; `params` carries the values the message was built
+// from (e.g. `min`, `max`).
+export interface FieldErrorDetail {
+  code: string;
+  message: string;
+  params?: Record<string, JsonValue>;
+}
+
 export interface ValidationErrorResponse {
   status: string;
   errors: Record<string, string[]>;
+  // Same errors as `errors`, in the same order, with codes and params.
+  details?: Record<string, FieldErrorDetail[]>;
 }
 
 // Type guard to check if an object is a ValidationErrorResponse
@@ -116,6 +116,8 @@ export default defineConfig({
       "/api": {
         target: "http://localhost:8000",
         changeOrigin: true,
+        // For the coach dashboard's live feed (/api/coach/live).
+        ws: true,
       },
     },
   },